@@ -0,0 +1,25 @@
+-- Track which transaction last changed each execution's status
+--
+-- change_xid is the 64-bit id of the transaction that created the execution or
+-- last changed its status. Transaction ids only grow, so they work as a change
+-- cursor: every transaction older than the current snapshot's xmin has either
+-- committed or aborted, so rows below that fence can no longer appear late.
+
+ALTER TABLE executions
+    ADD COLUMN change_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+CREATE FUNCTION executions_set_change_xid() RETURNS trigger AS $$
+BEGIN
+    IF NEW.status IS DISTINCT FROM OLD.status THEN
+        NEW.change_xid := pg_current_xact_id();
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER executions_change_xid
+    BEFORE UPDATE ON executions
+    FOR EACH ROW
+    EXECUTE FUNCTION executions_set_change_xid();
+
+CREATE INDEX executions_change_xid ON executions (change_xid, id);
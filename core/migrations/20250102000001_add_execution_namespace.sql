@@ -0,0 +1,11 @@
+-- Namespaces group executions per tenant so quotas can be enforced on them
+--
+-- Child executions inherit their parent workflow's namespace; everything
+-- created before namespaces existed lives in 'default'.
+
+ALTER TABLE executions
+    ADD COLUMN namespace TEXT NOT NULL DEFAULT 'default';
+
+-- Quota checks: active executions and recent enqueues per namespace
+CREATE INDEX executions_namespace_status ON executions (namespace, status);
+CREATE INDEX executions_namespace_created_at ON executions (namespace, created_at);
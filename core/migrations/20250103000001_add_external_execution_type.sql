@@ -0,0 +1,4 @@
+-- External executions are completed by a token instead of a worker
+ALTER TABLE executions DROP CONSTRAINT executions_type_check;
+ALTER TABLE executions ADD CONSTRAINT executions_type_check
+    CHECK (type IN ('task', 'workflow', 'external'));
@@ -0,0 +1,11 @@
+-- When an execution was first claimed, for claim latency SLOs
+--
+-- Stays NULL until the first claim and is not moved by later claims, such
+-- as a workflow resuming. Executions that started before this column existed
+-- keep NULL and are left out of latency measurements.
+
+ALTER TABLE executions
+    ADD COLUMN started_at TIMESTAMP WITH TIME ZONE;
+
+-- SLO evaluation scans recent executions per queue
+CREATE INDEX executions_queue_created_at ON executions (queue, created_at);
@@ -0,0 +1,12 @@
+-- Cluster-wide maintenance window
+--
+-- While the single row exists and has not expired, no worker claims work.
+-- Expiry makes the window time-boxed: a forgotten `maintenance exit` cannot
+-- stall the cluster forever.
+
+CREATE TABLE maintenance_window (
+    singleton BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (singleton),
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    reason TEXT
+);
@@ -0,0 +1,17 @@
+-- Optional TTL for unclaimed work
+--
+-- An execution with `expires_at` that is still pending at that time is never
+-- claimed; the internal worker moves it to the 'expired' status instead of
+-- 'failed', so "nobody got to it in time" stays distinct from "it ran and
+-- failed".
+
+ALTER TABLE executions
+    ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE executions DROP CONSTRAINT executions_status_check;
+ALTER TABLE executions ADD CONSTRAINT executions_status_check
+    CHECK (status IN ('pending', 'running', 'suspended', 'completed', 'failed', 'expired'));
+
+-- The expiry sweep only looks at pending executions that have a TTL
+CREATE INDEX executions_pending_expires_at ON executions (expires_at)
+    WHERE status = 'pending' AND expires_at IS NOT NULL;
@@ -0,0 +1,8 @@
+-- Consecutive transient errors while running a workflow
+--
+-- Counts runs that failed for infrastructure reasons (lost connection,
+-- serialization conflict) since the workflow last suspended normally, so
+-- retries with backoff can stop after a capped number of attempts.
+
+ALTER TABLE executions
+    ADD COLUMN runner_errors INTEGER NOT NULL DEFAULT 0;
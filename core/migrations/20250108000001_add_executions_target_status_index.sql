@@ -0,0 +1,6 @@
+-- Executions in flight per target
+--
+-- Concurrency groups count the running and suspended executions of their
+-- member targets on every claim.
+
+CREATE INDEX executions_target_name_status ON executions (target_name, status);
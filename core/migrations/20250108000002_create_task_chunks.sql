@@ -0,0 +1,13 @@
+-- Create task_chunks table for incremental task results
+--
+-- A running task can emit partial results before it finishes. They are
+-- numbered in emit order per task, and workflows read them with
+-- Task.stream(task, from).
+
+CREATE TABLE task_chunks (
+    execution_id TEXT NOT NULL REFERENCES executions(id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    chunk JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (execution_id, seq)
+);
@@ -0,0 +1,6 @@
+-- Effective settings per workflow definition
+--
+-- The workflow's front matter merged over `[workflow_defaults]` from
+-- rhythm.toml, resolved when the definition is registered.
+
+ALTER TABLE workflow_definitions ADD COLUMN settings JSONB NOT NULL DEFAULT '{}';
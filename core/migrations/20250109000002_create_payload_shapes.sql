@@ -0,0 +1,18 @@
+-- Payload shapes per target, for schema drift detection
+--
+-- `baseline` is the first shape seen for a target's inputs or output and
+-- `latest` the most recent one; drift is any difference between them.
+
+CREATE TABLE payload_shapes (
+    target_name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    baseline JSONB NOT NULL,
+    latest JSONB NOT NULL,
+    samples BIGINT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_seen_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (target_name, kind)
+);
+
+-- The analyzer walks completed executions in completion order
+CREATE INDEX executions_completed_at ON executions (completed_at) WHERE status = 'completed';
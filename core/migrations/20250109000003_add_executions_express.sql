@@ -0,0 +1,7 @@
+-- Express lane for synchronous runs
+--
+-- An express workflow and every execution it starts are queued ahead of
+-- normal work, so a caller blocking on the result waits as little as
+-- possible.
+
+ALTER TABLE executions ADD COLUMN express BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,12 @@
+-- VM step traces of executions that opted in to tracing
+--
+-- A row enables tracing; `steps` holds the most recent steps of the last
+-- run that failed.
+
+CREATE TABLE vm_traces (
+    execution_id TEXT PRIMARY KEY REFERENCES executions(id) ON DELETE CASCADE,
+    enabled_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    recorded_at TIMESTAMP WITH TIME ZONE,
+    total_steps BIGINT,
+    steps JSONB
+);
@@ -0,0 +1,7 @@
+-- Per-execution retry policy
+--
+-- Set when the execution is created; a failed task is retried up to the
+-- policy's max_retries, redelivered after its backoff delay. NULL falls
+-- back to [[task_configs]] with immediate redelivery.
+
+ALTER TABLE executions ADD COLUMN retry_policy JSONB;
@@ -0,0 +1,9 @@
+-- Cancelled executions
+--
+-- A workflow stopped through cancel_workflow moves to 'cancelled', and so
+-- does every unfinished execution it started, unless in-flight tasks were
+-- allowed to finish.
+
+ALTER TABLE executions DROP CONSTRAINT executions_status_check;
+ALTER TABLE executions ADD CONSTRAINT executions_status_check
+    CHECK (status IN ('pending', 'running', 'suspended', 'completed', 'failed', 'expired', 'cancelled'));
@@ -0,0 +1,12 @@
+-- Task heartbeats
+--
+-- A running task's worker reports in periodically; heartbeat_at is when it
+-- last did (or when the task started) and worker_id who it was. The reaper
+-- reclaims or fails running tasks whose heartbeat is too old.
+
+ALTER TABLE executions
+    ADD COLUMN heartbeat_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN worker_id TEXT;
+
+CREATE INDEX executions_running_heartbeat_at ON executions (heartbeat_at)
+    WHERE status = 'running' AND type = 'task';
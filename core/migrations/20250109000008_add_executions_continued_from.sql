@@ -0,0 +1,11 @@
+-- Continue-as-new
+--
+-- A workflow that continues as new finishes and hands over to a fresh
+-- execution of itself; continued_from links the fresh execution back to the
+-- one it continues, so a workflow's history can be followed across them.
+
+ALTER TABLE executions
+    ADD COLUMN continued_from TEXT;
+
+CREATE UNIQUE INDEX executions_continued_from ON executions (continued_from)
+    WHERE continued_from IS NOT NULL;
@@ -0,0 +1,41 @@
+-- Execution history
+--
+-- An append-only log of what happened to each execution, for debugging and
+-- audit. Creation and every status change are logged by trigger; the runner
+-- adds the events only it sees (children scheduled and finished, resumes).
+
+CREATE TABLE execution_events (
+    id BIGSERIAL PRIMARY KEY,
+    execution_id TEXT NOT NULL REFERENCES executions(id) ON DELETE CASCADE,
+    event_type TEXT NOT NULL,
+    details JSONB,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX execution_events_execution_id ON execution_events (execution_id, id);
+
+CREATE FUNCTION executions_log_event() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO execution_events (execution_id, event_type)
+        VALUES (NEW.id, 'created');
+    ELSIF NEW.status IS DISTINCT FROM OLD.status THEN
+        INSERT INTO execution_events (execution_id, event_type, details)
+        VALUES (
+            NEW.id,
+            CASE NEW.status
+                WHEN 'running' THEN 'claimed'
+                WHEN 'pending' THEN 'retried'
+                ELSE NEW.status
+            END,
+            jsonb_build_object('from', OLD.status)
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER executions_log_event
+    AFTER INSERT OR UPDATE OF status ON executions
+    FOR EACH ROW
+    EXECUTE FUNCTION executions_log_event();
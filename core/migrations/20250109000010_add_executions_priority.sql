@@ -0,0 +1,7 @@
+-- Execution priority
+--
+-- Work for an execution is queued at its priority, so urgent executions are
+-- claimed ahead of older ones on the same queue. Children inherit their
+-- parent workflow's priority unless given one.
+
+ALTER TABLE executions ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
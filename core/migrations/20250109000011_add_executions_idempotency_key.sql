@@ -0,0 +1,10 @@
+-- Idempotency keys
+--
+-- An execution created with a key is returned again, instead of a new one
+-- being created, when a caller retries with the same key for the same queue
+-- and target within the configured window.
+
+ALTER TABLE executions ADD COLUMN idempotency_key TEXT;
+
+CREATE INDEX executions_idempotency_key ON executions (queue, target_name, idempotency_key, created_at)
+    WHERE idempotency_key IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Trace context
+--
+-- The W3C traceparent an execution was created under, so the spans of its
+-- claim, run and child executions join the caller's distributed trace.
+-- Children without one of their own inherit their workflow's.
+
+ALTER TABLE executions ADD COLUMN traceparent TEXT;
@@ -0,0 +1,15 @@
+-- Execution logs
+--
+-- Messages written by workflows (Log.info/warn/error) and by task workers
+-- while running an execution, kept in the order they were written.
+
+CREATE TABLE execution_logs (
+    id BIGSERIAL PRIMARY KEY,
+    execution_id TEXT NOT NULL REFERENCES executions(id) ON DELETE CASCADE,
+    level TEXT NOT NULL,
+    message TEXT NOT NULL,
+    data JSONB,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX execution_logs_execution_id ON execution_logs (execution_id, id);
@@ -0,0 +1,29 @@
+-- Sticky workflows
+--
+-- A worker running with [worker.sticky] records itself on the state it
+-- saves. When the workflow is queued again, the entry prefers that worker
+-- until `preferred_until`; after that any worker may claim it.
+--
+-- `version` counts writes to a saved state, so a worker can tell whether
+-- the VM it kept in memory is still current.
+
+ALTER TABLE workflow_execution_context
+    ADD COLUMN version BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN sticky_worker_id TEXT,
+    ADD COLUMN sticky_timeout_ms INTEGER;
+
+ALTER TABLE work_queue
+    ADD COLUMN preferred_worker_id TEXT,
+    ADD COLUMN preferred_until TIMESTAMP;
+
+CREATE FUNCTION workflow_execution_context_bump_version() RETURNS trigger AS $$
+BEGIN
+    NEW.version := OLD.version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER workflow_execution_context_bump_version
+    BEFORE UPDATE ON workflow_execution_context
+    FOR EACH ROW
+    EXECUTE FUNCTION workflow_execution_context_bump_version();
@@ -0,0 +1,79 @@
+-- Webhooks
+--
+-- A webhook is a URL notified when executions matching its filter finish.
+-- The status change queues one delivery per matching webhook by trigger, in
+-- the transaction that made it, so no notification is lost to a crash; the
+-- internal worker then POSTs each delivery, retrying with backoff until the
+-- endpoint accepts it or attempts run out.
+
+CREATE TABLE webhooks (
+    id TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    -- Key the payloads are signed with
+    secret TEXT NOT NULL,
+    -- Statuses notified: completed, failed, cancelled
+    events TEXT[] NOT NULL,
+    -- Only executions on this queue / of this target, when set
+    queue TEXT,
+    target_name TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id TEXT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    execution_id TEXT NOT NULL,
+    event TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    -- pending, delivered, or failed once attempts ran out
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    delivered_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhook_deliveries_due ON webhook_deliveries (next_attempt_at)
+    WHERE status = 'pending';
+CREATE INDEX webhook_deliveries_webhook_id ON webhook_deliveries (webhook_id, id);
+
+CREATE FUNCTION executions_queue_webhooks() RETURNS trigger AS $$
+BEGIN
+    IF NEW.status IN ('completed', 'failed', 'cancelled')
+        AND NEW.status IS DISTINCT FROM OLD.status THEN
+        INSERT INTO webhook_deliveries (webhook_id, execution_id, event, payload)
+        SELECT
+            w.id,
+            NEW.id,
+            NEW.status,
+            jsonb_build_object(
+                'event', NEW.status,
+                'execution', jsonb_build_object(
+                    'id', NEW.id,
+                    'type', NEW.type,
+                    'target_name', NEW.target_name,
+                    'queue', NEW.queue,
+                    'namespace', NEW.namespace,
+                    'status', NEW.status,
+                    'inputs', NEW.inputs,
+                    'output', NEW.output,
+                    'attempt', NEW.attempt,
+                    'parent_workflow_id', NEW.parent_workflow_id,
+                    'created_at', NEW.created_at,
+                    'completed_at', NEW.completed_at
+                )
+            )
+        FROM webhooks w
+        WHERE NEW.status = ANY(w.events)
+          AND (w.queue IS NULL OR w.queue = NEW.queue)
+          AND (w.target_name IS NULL OR w.target_name = NEW.target_name);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER executions_queue_webhooks
+    AFTER UPDATE OF status ON executions
+    FOR EACH ROW
+    EXECUTE FUNCTION executions_queue_webhooks();
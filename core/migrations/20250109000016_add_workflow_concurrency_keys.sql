@@ -0,0 +1,57 @@
+-- Workflow concurrency keys
+--
+-- At most one unfinished workflow holds a concurrency key. A workflow
+-- started with a key that is taken is rejected, replaces the holder, or
+-- waits: it is created pending with `waiting_for_key` set and no work
+-- queued, and when the holder finishes the oldest waiter takes the key and
+-- is queued.
+--
+-- Starts and the hand-over take the same transaction-scoped advisory lock
+-- on the key, so a waiter is never added after the holder has finished.
+
+ALTER TABLE executions
+    ADD COLUMN concurrency_key TEXT,
+    ADD COLUMN waiting_for_key TEXT;
+
+CREATE UNIQUE INDEX executions_concurrency_key ON executions (concurrency_key)
+    WHERE concurrency_key IS NOT NULL AND status IN ('pending', 'running', 'suspended');
+
+CREATE INDEX executions_waiting_for_key ON executions (waiting_for_key, created_at)
+    WHERE waiting_for_key IS NOT NULL;
+
+CREATE FUNCTION executions_pass_concurrency_key() RETURNS trigger AS $$
+DECLARE
+    next_id TEXT;
+    next_queue TEXT;
+    next_priority INTEGER;
+BEGIN
+    IF NEW.concurrency_key IS NOT NULL
+        AND NEW.status IN ('completed', 'failed', 'expired', 'cancelled')
+        AND OLD.status IN ('pending', 'running', 'suspended') THEN
+        PERFORM pg_advisory_xact_lock(hashtext('concurrency_key:' || NEW.concurrency_key));
+
+        UPDATE executions
+        SET concurrency_key = waiting_for_key, waiting_for_key = NULL
+        WHERE id = (
+            SELECT id FROM executions
+            WHERE waiting_for_key = NEW.concurrency_key AND status = 'pending'
+            ORDER BY created_at, id
+            LIMIT 1
+            FOR UPDATE
+        )
+        RETURNING id, queue, priority INTO next_id, next_queue, next_priority;
+
+        IF next_id IS NOT NULL THEN
+            INSERT INTO work_queue (execution_id, queue, priority)
+            VALUES (next_id, next_queue, next_priority)
+            ON CONFLICT (execution_id, (claimed_until IS NULL)) DO NOTHING;
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER executions_pass_concurrency_key
+    AFTER UPDATE OF status ON executions
+    FOR EACH ROW
+    EXECUTE FUNCTION executions_pass_concurrency_key();
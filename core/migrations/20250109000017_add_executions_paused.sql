@@ -0,0 +1,7 @@
+-- Paused workflows
+--
+-- An operator can pause a workflow, e.g. while a downstream it calls is
+-- broken. Its queued work, resumptions from timers and signals included,
+-- stays on the queue but isn't claimed until the workflow is resumed.
+
+ALTER TABLE executions ADD COLUMN paused BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,12 @@
+-- Binary saved states
+--
+-- With `[payloads] state_encoding = "msgpack"` a saved VM state is written
+-- to `state` as tagged MessagePack bytes (see `state_encoding`) instead of
+-- to `locals` as JSON. Each row has exactly one of the two.
+
+ALTER TABLE workflow_execution_context
+    ADD COLUMN state BYTEA,
+    ALTER COLUMN locals DROP NOT NULL,
+    ALTER COLUMN locals DROP DEFAULT,
+    ADD CONSTRAINT workflow_execution_context_one_state
+        CHECK ((locals IS NULL) <> (state IS NULL));
@@ -0,0 +1,11 @@
+-- Execution tags
+--
+-- String key/value pairs set when an execution is created or added later,
+-- e.g. `customer_id`, so operators can find every execution for a customer.
+-- Children start with their workflow's tags. Listings filter with
+-- containment (`tags @> '{"customer_id": "123"}'`), which the GIN index
+-- serves.
+
+ALTER TABLE executions ADD COLUMN tags JSONB NOT NULL DEFAULT '{}';
+
+CREATE INDEX executions_tags ON executions USING GIN (tags jsonb_path_ops);
@@ -0,0 +1,16 @@
+-- API keys
+--
+-- Keys callers present to the adapters, each with the scopes it grants
+-- (read_only, enqueue, worker, admin). Only a SHA-256 of the key is kept;
+-- the id is the part of the key that finds its row.
+
+CREATE TABLE api_keys (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL,
+    scopes TEXT[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    -- Set once the key is no longer accepted; the row stays for the record
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
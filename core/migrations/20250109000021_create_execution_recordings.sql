@@ -0,0 +1,15 @@
+-- Recordings of workflow runs, for replaying them locally
+--
+-- A row enables recording. Each run recorded saves the definition it ran,
+-- every value the workflow read from outside it (`VM::recorded`) and
+-- appends the values it was resumed with, so the workflow can be re-run
+-- from the beginning to the same end after its saved state is gone.
+
+CREATE TABLE execution_recordings (
+    execution_id TEXT PRIMARY KEY REFERENCES executions(id) ON DELETE CASCADE,
+    enabled_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    recorded_at TIMESTAMP WITH TIME ZONE,
+    workflow_definition_id INTEGER,
+    reads JSONB NOT NULL DEFAULT '[]',
+    resumes JSONB NOT NULL DEFAULT '[]'
+);
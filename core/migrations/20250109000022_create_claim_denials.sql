@@ -0,0 +1,15 @@
+-- Denied claims
+--
+-- When a worker's claim policy refuses work it claimed, the work goes back
+-- to the queue and a row here keeps that worker from claiming it again
+-- until `until`, so work it may run behind the refused item isn't starved.
+-- Other workers are unaffected.
+
+CREATE TABLE claim_denials (
+    execution_id TEXT NOT NULL REFERENCES executions(id) ON DELETE CASCADE,
+    worker_id TEXT NOT NULL,
+    until TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (execution_id, worker_id)
+);
+
+CREATE INDEX idx_claim_denials_worker ON claim_denials (worker_id, until);
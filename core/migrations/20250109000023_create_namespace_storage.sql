@@ -0,0 +1,67 @@
+-- Stored bytes per namespace
+--
+-- The storage quota is checked on every creation in a namespace, under its
+-- quota lock, so summing the size of every execution it ever stored there
+-- would slow creations down as its history grows. Instead a trigger keeps
+-- a running total here, in the transaction that creates, completes or
+-- deletes the execution.
+--
+-- Each namespace's total is spread over 16 shards by execution id, so
+-- concurrent completions in one namespace rarely wait on the same row; the
+-- total is the sum of its shards.
+
+CREATE TABLE namespace_storage (
+    namespace TEXT NOT NULL,
+    shard SMALLINT NOT NULL,
+    bytes BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (namespace, shard)
+);
+
+CREATE FUNCTION execution_storage_bytes(inputs JSONB, output JSONB) RETURNS BIGINT AS $$
+    SELECT pg_column_size(inputs)::BIGINT + COALESCE(pg_column_size(output), 0)
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE FUNCTION add_namespace_storage(ns TEXT, execution_id TEXT, delta BIGINT)
+RETURNS void AS $$
+    INSERT INTO namespace_storage (namespace, shard, bytes)
+    VALUES (ns, (hashtext(execution_id) & 15)::SMALLINT, delta)
+    ON CONFLICT (namespace, shard)
+    DO UPDATE SET bytes = namespace_storage.bytes + EXCLUDED.bytes
+$$ LANGUAGE sql;
+
+CREATE FUNCTION executions_track_storage() RETURNS trigger AS $$
+DECLARE
+    delta BIGINT;
+BEGIN
+    -- Most updates, such as a completion, change the size in place
+    IF TG_OP = 'UPDATE' AND NEW.namespace = OLD.namespace THEN
+        delta := execution_storage_bytes(NEW.inputs, NEW.output)
+            - execution_storage_bytes(OLD.inputs, OLD.output);
+        IF delta <> 0 THEN
+            PERFORM add_namespace_storage(NEW.namespace, NEW.id, delta);
+        END IF;
+        RETURN NULL;
+    END IF;
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        PERFORM add_namespace_storage(
+            OLD.namespace, OLD.id, -execution_storage_bytes(OLD.inputs, OLD.output)
+        );
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        PERFORM add_namespace_storage(
+            NEW.namespace, NEW.id, execution_storage_bytes(NEW.inputs, NEW.output)
+        );
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER executions_track_storage
+    AFTER INSERT OR DELETE OR UPDATE OF inputs, output, namespace ON executions
+    FOR EACH ROW
+    EXECUTE FUNCTION executions_track_storage();
+
+INSERT INTO namespace_storage (namespace, shard, bytes)
+SELECT namespace, (hashtext(id) & 15)::SMALLINT, SUM(execution_storage_bytes(inputs, output))
+FROM executions
+GROUP BY 1, 2;
@@ -0,0 +1,7 @@
+-- Child-first cancellation
+--
+-- A workflow cancelled child-first keeps running until its child workflows
+-- have been cancelled and it has run its compensations. The reason it was
+-- asked to cancel is kept here until then.
+
+ALTER TABLE executions ADD COLUMN cancel_requested TEXT;
@@ -4,16 +4,51 @@
 //! with all configured services. The client module is responsible for
 //! storing the singleton.
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use sqlx::PgPool;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 
-use crate::config::Config;
+use crate::auth::{ApiKey, Scope, Unauthenticated};
+use crate::blob_store::{BlobStore, PayloadStore};
+use crate::config::{Config, TaskConfig};
+use crate::diagnostics::DiagnosticsSampler;
+use crate::encryption::Keyring;
+use crate::queue_backend::QueueBackend;
+use crate::queue_limits::QueueLimits;
+use crate::quotas::{QuotaEnforcer, QuotaMetrics};
 use crate::services::{
-    ExecutionService, InitializationService, SchedulerService, SignalService, WorkerService,
+    ApiKeyService, ExecutionService, InitializationService, MaintenanceService, SchedulerService,
+    SchemaService, SignalService, SloService, TransferService, WebhookService, WorkerService,
     WorkflowService,
 };
+use crate::worker::{
+    ClaimAuthorizer, ClaimPolicy, OutputSchemas, ReaperPolicy, RetryRules, RunnerRetryPolicy,
+    WorkerMiddleware,
+};
+
+/// Error returned by operations that change state on a read-only Application
+///
+/// Adapters can find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOnly {
+    /// The rejected operation, e.g. `create_execution`
+    pub operation: &'static str,
+}
+
+impl std::fmt::Display for ReadOnly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Rhythm is in read-only mode: {} is not allowed",
+            self.operation
+        )
+    }
+}
+
+impl std::error::Error for ReadOnly {}
 
 /// The Rhythm application instance with all services
 pub struct Application {
@@ -25,7 +60,17 @@ pub struct Application {
     pub worker_service: WorkerService,
     pub scheduler_service: SchedulerService,
     pub signal_service: SignalService,
+    pub slo_service: SloService,
+    pub maintenance_service: MaintenanceService,
+    pub schema_service: SchemaService,
+    pub transfer_service: TransferService,
+    pub webhook_service: WebhookService,
+    pub api_key_service: ApiKeyService,
     pub initialization_service: InitializationService,
+    quotas: Arc<QuotaEnforcer>,
+    read_only: bool,
+    /// The key `Client` calls are made with
+    api_key: Option<ApiKey>,
     internal_worker_started: AtomicBool,
 }
 
@@ -35,22 +80,85 @@ impl Application {
         // Create pool from config
         let pool = crate::db::pool::create_pool_from_config(&config).await?;
 
+        Ok(Self::with_pool(config, pool))
+    }
+
+    /// Create an Application on an existing pool
+    ///
+    /// Nothing is shared between Applications, so several can run in one
+    /// process against different databases. Panics if `[encryption]`, `[queue]`
+    /// or an `output_schema` is invalid; `Config::load` checks them.
+    pub fn with_pool(config: Config, pool: PgPool) -> Self {
         let shutdown_token = CancellationToken::new();
 
-        let scheduler_service = SchedulerService::new(pool.clone());
+        let quotas = Arc::new(QuotaEnforcer::new(config.quotas.clone()));
+        let queue_limits = QueueLimits::from_config(&config.queue);
+        let payloads = payload_store(&config, PayloadStore::from_config(&config.payloads));
+        let scheduler_service = SchedulerService::new(pool.clone())
+            .with_quotas(quotas.clone())
+            .with_payloads(payloads.clone());
+        let authorizer = ClaimAuthorizer::from_config(&config.worker, &config.claim_policy);
+        let mut worker_service =
+            WorkerService::new(pool.clone(), shutdown_token.clone(), authorizer)
+                .with_retry_rules(RetryRules::new(config.task_configs.clone()))
+                .with_output_schemas(output_schemas(&config))
+                .with_runner_retry(RunnerRetryPolicy::from(&config.worker.runner_retry))
+                .with_step_budget(config.worker.step_budget.clone())
+                .with_reaper(ReaperPolicy::from(&config.worker.reaper))
+                .with_diagnostics(DiagnosticsSampler::new(config.diagnostics.clone()))
+                .with_concurrency_groups(config.concurrency_groups.clone())
+                .with_queue_backend(queue_backend(&config, &pool))
+                .with_queue_limits(queue_limits.clone())
+                .with_payloads(payloads.clone());
+        if config.worker.defer_work_cleanup {
+            worker_service = worker_service.with_deferred_cleanup();
+        }
+        if config.worker.replay_check {
+            worker_service = worker_service.with_replay_check();
+        }
+        if config.worker.record_runs {
+            worker_service = worker_service.with_recorded_runs();
+        }
+        if config.worker.sticky.enabled {
+            worker_service = worker_service.with_sticky_workflows(&config.worker.sticky);
+        }
+        let slo_service = SloService::new(pool.clone(), config.slos.clone());
+        let webhook_service = WebhookService::new(pool.clone())
+            .with_config(config.webhooks.clone())
+            .with_payloads(payloads.clone());
+        let workflow_defaults = config.workflow_defaults.clone();
+        let idempotency_window = Duration::from_secs(config.idempotency.window_secs);
 
-        Ok(Self {
+        Self {
             config,
             pool: pool.clone(),
             shutdown_token: shutdown_token.clone(),
-            execution_service: ExecutionService::new(pool.clone()),
-            workflow_service: WorkflowService::new(pool.clone()),
-            worker_service: WorkerService::new(pool.clone(), shutdown_token),
+            execution_service: ExecutionService::new(pool.clone())
+                .with_quotas(quotas.clone())
+                .with_queue_limits(queue_limits.clone())
+                .with_idempotency_window(idempotency_window)
+                .with_payloads(payloads.clone()),
+            workflow_service: WorkflowService::new(pool.clone())
+                .with_quotas(quotas.clone())
+                .with_queue_limits(queue_limits)
+                .with_workflow_defaults(workflow_defaults.clone())
+                .with_payloads(payloads),
+            worker_service,
             scheduler_service,
             signal_service: SignalService::new(pool.clone()),
-            initialization_service: InitializationService::new(pool),
+            slo_service,
+            maintenance_service: MaintenanceService::new(pool.clone()),
+            schema_service: SchemaService::new(pool.clone()),
+            transfer_service: TransferService::new(pool.clone()),
+            webhook_service,
+            api_key_service: ApiKeyService::new(pool.clone()),
+            initialization_service: InitializationService::new(pool)
+                .with_workflow_defaults(workflow_defaults),
+            quotas,
+            read_only: false,
+            api_key: None,
             internal_worker_started: AtomicBool::new(false),
-        })
+        }
     }
 
     /// Get the database pool
@@ -63,6 +171,90 @@ impl Application {
         &self.config
     }
 
+    /// Whether operations that change state are refused
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail with `ReadOnly` if `operation` may not run on this application
+    pub fn ensure_writable(&self, operation: &'static str) -> Result<()> {
+        if self.read_only {
+            return Err(ReadOnly { operation }.into());
+        }
+        Ok(())
+    }
+
+    /// Make calls with `api_key`, so `authorize` checks them against its
+    /// scopes
+    pub fn set_api_key(&mut self, api_key: ApiKey) {
+        self.api_key = Some(api_key);
+    }
+
+    pub fn api_key(&self) -> Option<&ApiKey> {
+        self.api_key.as_ref()
+    }
+
+    /// Fail unless this application's API key allows `scope`
+    ///
+    /// Without a key, fails with `Unauthenticated` when `[auth] required` is
+    /// set and allows everything otherwise.
+    pub fn authorize(&self, scope: Scope, operation: &'static str) -> Result<()> {
+        match &self.api_key {
+            Some(api_key) => api_key.require(scope, operation),
+            None if self.config.auth.required => Err(Unauthenticated {
+                reason: format!("{} needs an API key", operation),
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Quota rejections counted by this application
+    pub fn quota_metrics(&self) -> QuotaMetrics {
+        self.quotas.metrics()
+    }
+
+    /// Replace the claim authorization policy built from config
+    pub fn set_claim_policy(&mut self, policy: Arc<dyn ClaimPolicy>) {
+        self.worker_service.set_claim_policy(policy);
+    }
+
+    /// Offload payloads over `[payloads] max_inline_bytes` to `store`
+    ///
+    /// Replaces the `[payloads.store]` from config, e.g. with an S3 store.
+    /// Set it before starting workers.
+    pub fn set_blob_store(&mut self, store: Arc<dyn BlobStore>) {
+        let payloads = payload_store(
+            &self.config,
+            PayloadStore::from_config(&self.config.payloads).with_blob_store(store),
+        );
+        self.execution_service = self
+            .execution_service
+            .clone()
+            .with_payloads(payloads.clone());
+        self.workflow_service = self
+            .workflow_service
+            .clone()
+            .with_payloads(payloads.clone());
+        self.scheduler_service = self
+            .scheduler_service
+            .clone()
+            .with_payloads(payloads.clone());
+        self.worker_service = self.worker_service.clone().with_payloads(payloads);
+    }
+
+    /// Claim work through `backend` instead of the `[queue]` config's
+    ///
+    /// E.g. an SQS backend. Set it before starting workers.
+    pub fn set_queue_backend(&mut self, backend: Arc<dyn QueueBackend>) {
+        self.worker_service = self.worker_service.clone().with_queue_backend(backend);
+    }
+
+    /// Add a middleware around this application's claims and completions
+    pub fn add_middleware(&mut self, middleware: Arc<dyn WorkerMiddleware>) {
+        self.worker_service.add_middleware(middleware);
+    }
+
     /// Request graceful shutdown
     pub fn request_shutdown(&self) {
         self.shutdown_token.cancel();
@@ -73,21 +265,54 @@ impl Application {
     /// This should be called when starting a worker process. The internal worker
     /// handles background tasks like processing the scheduled queue.
     ///
-    /// Returns an error if the internal worker has already been started.
+    /// Returns an error if the internal worker has already been started or
+    /// the application is read-only.
     pub fn start_internal_worker(&self) -> Result<()> {
+        self.ensure_writable("start_internal_worker")?;
         if self.internal_worker_started.swap(true, Ordering::SeqCst) {
             bail!("Internal worker has already been started");
         }
 
-        let internal_worker = crate::internal_worker::InternalWorker::new(
+        let mut internal_worker = crate::internal_worker::InternalWorker::new(
             self.scheduler_service.clone(),
             self.shutdown_token.clone(),
-        );
+        )
+        .with_slo_monitor(self.slo_service.clone());
+        if self.config.schema_drift.enabled {
+            internal_worker = internal_worker.with_schema_analyzer(self.schema_service.clone());
+        }
+        if self.config.worker.reaper.enabled {
+            internal_worker = internal_worker.with_reaper(self.worker_service.clone());
+        }
+        if self.config.webhooks.enabled {
+            internal_worker = internal_worker.with_webhooks(self.webhook_service.clone());
+        }
         tokio::spawn(internal_worker.run());
         Ok(())
     }
 }
 
+/// The `output_schema`s of `[[task_configs]]` and tasks declared in code
+fn output_schemas(config: &Config) -> OutputSchemas {
+    OutputSchemas::from_task_configs(&config.task_configs)
+        .unwrap_or_else(|e| panic!("Invalid [[task_configs]]: {:#}", e))
+}
+
+/// The `[queue]` backend on `pool`
+fn queue_backend(config: &Config, pool: &PgPool) -> Arc<dyn QueueBackend> {
+    crate::queue_backend::from_config(&config.queue, pool.clone())
+        .unwrap_or_else(|e| panic!("Invalid [queue] config: {:#}", e))
+}
+
+/// `payloads` encrypting with the `[encryption]` keys, if configured
+fn payload_store(config: &Config, payloads: PayloadStore) -> PayloadStore {
+    match Keyring::from_config(&config.encryption) {
+        Ok(Some(keyring)) => payloads.with_encryption(keyring),
+        Ok(None) => payloads,
+        Err(e) => panic!("Invalid [encryption] config: {:#}", e),
+    }
+}
+
 /// Workflow file for registration
 #[derive(Debug, Clone)]
 pub struct WorkflowFile {
@@ -99,6 +324,12 @@ pub struct WorkflowFile {
 /// Options for initializing Rhythm
 #[derive(Debug, Clone)]
 pub struct InitOptions {
+    /// Config to use as-is instead of loading it from files and env vars
+    pub config: Option<Config>,
+
+    /// Pool to use instead of connecting with the configured database URL
+    pub pool: Option<PgPool>,
+
     /// Database URL (overrides config file and env vars)
     pub database_url: Option<String>,
 
@@ -110,15 +341,38 @@ pub struct InitOptions {
 
     /// Workflow files to register during initialization
     pub workflows: Vec<WorkflowFile>,
+
+    /// Task settings declared in code, e.g. by SDK decorators
+    ///
+    /// A `[[task_configs]]` entry for the same task takes precedence.
+    pub tasks: Vec<TaskConfig>,
+
+    /// Claim policy overriding the one built from `[claim_policy]` config
+    pub claim_policy: Option<Arc<dyn ClaimPolicy>>,
+
+    /// Worker middlewares, outermost first
+    pub middlewares: Vec<Arc<dyn WorkerMiddleware>>,
+
+    /// Refuse operations that change state, e.g. against a replica or backup
+    ///
+    /// Skips migrations and workflow registration. A pool created by
+    /// initialization is also opened with read-only transactions.
+    pub read_only: bool,
 }
 
 impl Default for InitOptions {
     fn default() -> Self {
         Self {
+            config: None,
+            pool: None,
             database_url: None,
             config_path: None,
             auto_migrate: true,
             workflows: Vec::new(),
+            tasks: Vec::new(),
+            claim_policy: None,
+            middlewares: Vec::new(),
+            read_only: false,
         }
     }
 }
@@ -136,6 +390,18 @@ impl InitBuilder {
         }
     }
 
+    /// Use this config instead of loading one
+    pub fn config(mut self, config: Config) -> Self {
+        self.options.config = Some(config);
+        self
+    }
+
+    /// Use an existing connection pool
+    pub fn pool(mut self, pool: PgPool) -> Self {
+        self.options.pool = Some(pool);
+        self
+    }
+
     /// Set the database URL
     pub fn database_url(mut self, url: impl Into<String>) -> Self {
         self.options.database_url = Some(url.into());
@@ -160,6 +426,30 @@ impl InitBuilder {
         self
     }
 
+    /// Add task settings declared in code
+    pub fn tasks(mut self, tasks: Vec<TaskConfig>) -> Self {
+        self.options.tasks = tasks;
+        self
+    }
+
+    /// Set a custom claim authorization policy
+    pub fn claim_policy(mut self, policy: Arc<dyn ClaimPolicy>) -> Self {
+        self.options.claim_policy = Some(policy);
+        self
+    }
+
+    /// Add a worker middleware; earlier ones wrap later ones
+    pub fn middleware(mut self, middleware: Arc<dyn WorkerMiddleware>) -> Self {
+        self.options.middlewares.push(middleware);
+        self
+    }
+
+    /// Open in read-only mode
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.options.read_only = read_only;
+        self
+    }
+
     /// Initialize Rhythm with the configured options
     pub async fn init(self) -> Result<Application> {
         initialize(self.options).await
@@ -177,19 +467,51 @@ impl Default for InitBuilder {
 /// Thin wrapper for direct usage (without Client singleton).
 /// Most users should use Client::initialize() instead.
 pub async fn initialize(options: InitOptions) -> Result<Application> {
-    // Bootstrap: Load config
-    let config = crate::config::Config::builder()
-        .database_url(options.database_url)
-        .config_path(options.config_path.map(std::path::PathBuf::from))
-        .build()?;
-
-    // Instantiate (creates pool internally)
-    let app = Application::new(config).await?;
+    // Bootstrap: Load config unless one was given
+    let mut config = match options.config {
+        Some(config) => config,
+        None => crate::config::Config::builder()
+            .database_url(options.database_url)
+            .config_path(options.config_path.map(std::path::PathBuf::from))
+            .build()?,
+    };
+    crate::telemetry::init(&config.telemetry);
+    for task in options.tasks {
+        if !config
+            .task_configs
+            .iter()
+            .any(|configured| configured.target_name == task.target_name)
+        {
+            config.task_configs.push(task);
+        }
+    }
+    OutputSchemas::from_task_configs(&config.task_configs).context("Invalid task settings")?;
+
+    // Instantiate (creates a pool unless one was given)
+    let mut app = match options.pool {
+        Some(pool) => Application::with_pool(config, pool),
+        None if options.read_only => {
+            let pool = crate::db::pool::create_read_only_pool_from_config(&config).await?;
+            Application::with_pool(config, pool)
+        }
+        None => Application::new(config).await?,
+    };
+    app.read_only = options.read_only;
+    if let Some(policy) = options.claim_policy {
+        app.set_claim_policy(policy);
+    }
+    for middleware in options.middlewares {
+        app.add_middleware(middleware);
+    }
 
-    // Initialize
-    app.initialization_service
-        .initialize(options.auto_migrate, options.workflows)
-        .await?;
+    // Initialize (a read-only application must not write)
+    if app.read_only {
+        tracing::info!("Read-only mode: skipping migrations and workflow registration");
+    } else {
+        app.initialization_service
+            .initialize(options.auto_migrate, options.workflows)
+            .await?;
+    }
 
     Ok(app)
 }
@@ -197,6 +519,179 @@ pub async fn initialize(options: InitOptions) -> Result<Application> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{NamespaceQuota, QuotasConfig};
+    use crate::errors::ErrorCode;
+    use crate::test_helpers::task_params;
+    use crate::types::CreateExecutionParams;
+    use sqlx::postgres::PgPoolOptions;
+    use std::collections::HashMap;
+
+    fn config_with_max_running(max_running: Option<i64>) -> Config {
+        Config {
+            database: Default::default(),
+            worker: Default::default(),
+            claim_policy: Default::default(),
+            quotas: QuotasConfig {
+                default: NamespaceQuota {
+                    max_running,
+                    ..Default::default()
+                },
+                namespaces: HashMap::new(),
+            },
+            slos: Vec::new(),
+            task_configs: Vec::new(),
+            diagnostics: Default::default(),
+            concurrency_groups: Vec::new(),
+            workflow_defaults: Default::default(),
+            schema_drift: Default::default(),
+            idempotency: Default::default(),
+            webhooks: Default::default(),
+            telemetry: Default::default(),
+            payloads: Default::default(),
+            encryption: Default::default(),
+            queue: Default::default(),
+            auth: Default::default(),
+        }
+    }
+
+    fn task() -> CreateExecutionParams {
+        task_params("work")
+    }
+
+    #[sqlx::test]
+    async fn test_applications_run_side_by_side(pool: PgPool) -> Result<()> {
+        // A second database, so each Application has its own
+        let other_db = format!("rhythm_side_{}", uuid::Uuid::new_v4().simple());
+        sqlx::query(&format!("CREATE DATABASE {}", other_db))
+            .execute(&pool)
+            .await?;
+        let other_pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_with(pool.connect_options().as_ref().clone().database(&other_db))
+            .await?;
+        crate::db::migration::migrate(&other_pool).await?;
+
+        let limited = InitBuilder::new()
+            .config(config_with_max_running(Some(1)))
+            .pool(pool.clone())
+            .init()
+            .await?;
+        let unlimited = InitBuilder::new()
+            .config(config_with_max_running(None))
+            .pool(other_pool.clone())
+            .init()
+            .await?;
+
+        let id = limited.execution_service.create_execution(task()).await?;
+        assert!(limited
+            .execution_service
+            .create_execution(task())
+            .await
+            .is_err());
+        for _ in 0..2 {
+            unlimited.execution_service.create_execution(task()).await?;
+        }
+
+        // Each sees only its own database and counts only its own rejections
+        assert!(unlimited
+            .execution_service
+            .get_execution(&id)
+            .await?
+            .is_none());
+        assert_eq!(
+            limited.quota_metrics().rejections["default"]["max_running"],
+            1
+        );
+        assert!(unlimited.quota_metrics().rejections.is_empty());
+        assert_eq!(
+            unlimited
+                .execution_service
+                .get_namespace_usage("default")
+                .await?
+                .running,
+            2
+        );
+
+        other_pool.close().await;
+        sqlx::query(&format!("DROP DATABASE {}", other_db))
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_read_only_refuses_changes_but_serves_queries(pool: PgPool) -> Result<()> {
+        let id = ExecutionService::new(pool.clone())
+            .create_execution(task())
+            .await?;
+
+        let app = InitBuilder::new()
+            .config(config_with_max_running(None))
+            .pool(pool)
+            .read_only(true)
+            .init()
+            .await?;
+        assert!(app.is_read_only());
+
+        let err = app.ensure_writable("create_execution").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ReadOnly>(),
+            Some(&ReadOnly {
+                operation: "create_execution"
+            })
+        );
+        assert!(app
+            .start_internal_worker()
+            .unwrap_err()
+            .downcast_ref::<ReadOnly>()
+            .is_some());
+
+        assert!(app.execution_service.get_execution(&id).await?.is_some());
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_authorize_checks_api_key_scopes(pool: PgPool) -> Result<()> {
+        let mut config = config_with_max_running(None);
+        config.auth.required = true;
+        let mut app = Application::with_pool(config, pool);
+
+        let err = app.authorize(Scope::ReadOnly, "get_execution").unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::Unauthenticated);
+
+        let created = app
+            .api_key_service
+            .create("enqueuer", &[Scope::Enqueue])
+            .await?;
+        app.set_api_key(created.api_key);
+        app.authorize(Scope::ReadOnly, "get_execution")?;
+        app.authorize(Scope::Enqueue, "start_workflow")?;
+        let err = app.authorize(Scope::Admin, "cancel_workflow").unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::PermissionDenied);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_read_only_pool_rejects_writes() -> Result<()> {
+        let mut config = config_with_max_running(None);
+        config.database.url = Some(std::env::var("RHYTHM_DATABASE_URL")?);
+
+        let app = InitBuilder::new()
+            .config(config)
+            .read_only(true)
+            .init()
+            .await?;
+
+        // Services used directly bypass the Client guard, but Postgres refuses
+        let err = app
+            .execution_service
+            .create_execution(task())
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("read-only transaction"));
+        app.execution_service.get_namespace_usage("default").await?;
+        Ok(())
+    }
 
     #[tokio::test(flavor = "multi_thread")]
     #[ignore]
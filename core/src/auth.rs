@@ -0,0 +1,249 @@
+//! API keys and the scopes they grant
+//!
+//! A key is `rhythm_<id>_<secret>`. Only the SHA-256 of the whole key is
+//! stored, in `api_keys`, so a key is shown once when it is created and
+//! can't be read back; the `id` part finds the row to check it against and
+//! names the key in listings and errors.
+//!
+//! Each key holds one or more scopes:
+//!
+//! - `read_only` - queries; every key may make them
+//! - `enqueue` - creating executions, starting workflows, sending signals
+//! - `worker` - claiming, running and completing work
+//! - `admin` - everything, including cancelling, pausing and registering
+//!
+//! With `[auth] required = true`, `Client` calls are refused unless the
+//! process presents a key (`[auth] api_key` or `RHYTHM_API_KEY`) whose
+//! scopes allow them, and `rhythm serve` wants one as a bearer token. Keys
+//! are managed with `rhythm api-keys` (see `ApiKeyService`).
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const KEY_PREFIX: &str = "rhythm_";
+
+/// What an API key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    ReadOnly,
+    Enqueue,
+    Worker,
+    Admin,
+}
+
+impl Scope {
+    /// Stable snake_case label, matching the database and serde representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadOnly => "read_only",
+            Scope::Enqueue => "enqueue",
+            Scope::Worker => "worker",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read_only" => Ok(Scope::ReadOnly),
+            "enqueue" => Ok(Scope::Enqueue),
+            "worker" => Ok(Scope::Worker),
+            "admin" => Ok(Scope::Admin),
+            _ => anyhow::bail!(
+                "Unknown scope '{}' (expected read_only, enqueue, worker or admin)",
+                s
+            ),
+        }
+    }
+}
+
+/// An API key, without its secret
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiKey {
+    pub id: String,
+    /// Who or what the key is for
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// When it stopped being accepted
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Whether the key may do what `scope` covers
+    ///
+    /// `admin` allows everything and every key may read.
+    pub fn allows(&self, scope: Scope) -> bool {
+        scope == Scope::ReadOnly
+            || self
+                .scopes
+                .iter()
+                .any(|s| *s == scope || *s == Scope::Admin)
+    }
+
+    /// Fail with `PermissionDenied` unless the key allows `scope`
+    pub fn require(&self, scope: Scope, operation: &'static str) -> Result<()> {
+        if !self.allows(scope) {
+            return Err(PermissionDenied {
+                key_id: self.id.clone(),
+                operation,
+                scope,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// A key just created; `key` is the only copy of its secret
+#[derive(Debug, Clone, Serialize)]
+pub struct NewApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// Error for a caller that presented no key, or one that isn't accepted
+///
+/// Adapters can find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unauthenticated {
+    pub reason: String,
+}
+
+impl std::fmt::Display for Unauthenticated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Not authenticated: {}", self.reason)
+    }
+}
+
+impl std::error::Error for Unauthenticated {}
+
+/// Error for an operation the caller's key has no scope for
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionDenied {
+    pub key_id: String,
+    /// The refused operation, e.g. `cancel_workflow`
+    pub operation: &'static str,
+    /// The scope it needs
+    pub scope: Scope,
+}
+
+impl std::fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "API key {} may not {}: it needs the {} scope",
+            self.key_id,
+            self.operation,
+            self.scope.as_str()
+        )
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+/// A new key and its id
+pub(crate) fn generate_key() -> (String, String) {
+    let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    let secret = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let key = format!("{}{}_{}", KEY_PREFIX, id, secret);
+    (id, key)
+}
+
+/// The id part of `key`, if it is shaped like a key
+pub(crate) fn key_id(key: &str) -> Option<&str> {
+    let (id, secret) = key.strip_prefix(KEY_PREFIX)?.split_once('_')?;
+    Some(id).filter(|id| !id.is_empty() && !secret.is_empty())
+}
+
+/// Hex SHA-256 of `key`, as stored
+pub(crate) fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Compare hashes in time independent of where they differ
+pub(crate) fn hashes_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    fn key(scopes: &[Scope]) -> ApiKey {
+        ApiKey {
+            id: "abc".to_string(),
+            name: "billing".to_string(),
+            scopes: scopes.to_vec(),
+            created_at: Utc::now(),
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn test_generated_keys_parse_and_hash() {
+        let (id, secret) = generate_key();
+        assert_eq!(key_id(&secret), Some(id.as_str()));
+        assert!(hashes_match(&hash_key(&secret), &hash_key(&secret)));
+
+        let (_, other) = generate_key();
+        assert_ne!(secret, other);
+        assert!(!hashes_match(&hash_key(&secret), &hash_key(&other)));
+
+        assert_eq!(key_id("rhythm_abc"), None);
+        assert_eq!(key_id("rhythm__secret"), None);
+        assert_eq!(key_id("other_abc_secret"), None);
+    }
+
+    #[test]
+    fn test_scopes_allow_operations() {
+        let enqueue = key(&[Scope::Enqueue]);
+        assert!(enqueue.allows(Scope::ReadOnly));
+        assert!(enqueue.allows(Scope::Enqueue));
+        assert!(!enqueue.allows(Scope::Worker));
+        assert!(!enqueue.allows(Scope::Admin));
+
+        let admin = key(&[Scope::Admin]);
+        assert!(admin.allows(Scope::Worker));
+        assert!(admin.allows(Scope::Admin));
+
+        let err = key(&[Scope::ReadOnly])
+            .require(Scope::Admin, "cancel_workflow")
+            .unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::PermissionDenied);
+        assert_eq!(
+            err.to_string(),
+            "API key abc may not cancel_workflow: it needs the admin scope"
+        );
+    }
+
+    #[test]
+    fn test_scope_labels_round_trip() {
+        for scope in [Scope::ReadOnly, Scope::Enqueue, Scope::Worker, Scope::Admin] {
+            assert_eq!(scope.as_str().parse::<Scope>().unwrap(), scope);
+            assert_eq!(serde_json::to_value(scope).unwrap(), scope.as_str());
+        }
+        assert!("root".parse::<Scope>().is_err());
+    }
+}
@@ -1,11 +1,22 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
-use sqlx::postgres::PgPoolOptions;
+use rhythm_core::cli::{
+    self, api_keys::ApiKeysCommands, executions::ExecutionsCommands,
+    maintenance::MaintenanceCommands, parse_duration, parse_task_output, schema::SchemaCommands,
+    webhooks::WebhooksCommands, workflows::WorkflowCommands,
+};
+use serde_json::Value as JsonValue;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "rhythm")]
 #[command(about = "Rhythm workflow engine CLI", long_about = None)]
 struct Cli {
+    /// Config file path (overrides default search)
+    #[arg(long, global = true)]
+    config: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -14,36 +25,354 @@ struct Cli {
 enum Commands {
     /// Run database migrations
     Migrate,
-}
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    /// Rewrite saved workflow states in the current format
+    ///
+    /// States are written in the configured `[payloads] state_encoding`.
+    /// Dry run unless --apply is given.
+    MigrateState {
+        /// Write the rewritten states instead of only reporting
+        #[arg(long)]
+        apply: bool,
 
-    match cli.command {
-        Commands::Migrate => {
-            migrate().await?;
-        }
-    }
+        /// States read per query
+        #[arg(long, default_value_t = 500)]
+        batch_size: i64,
 
-    Ok(())
-}
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Encrypt stored inputs, outputs and workflow states with the active key
+    ///
+    /// Run after changing `[encryption] active_key`, before removing the
+    /// old key. Dry run unless --apply is given.
+    Reencrypt {
+        /// Write the re-encrypted payloads instead of only reporting
+        #[arg(long)]
+        apply: bool,
+
+        /// Rows read per query
+        #[arg(long, default_value_t = 500)]
+        batch_size: i64,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show compliance with the configured claim latency SLOs
+    Slo {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show how full each concurrency group is
+    Groups {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Inspect workflow files and migrate running workflows
+    Workflow {
+        #[command(subcommand)]
+        command: WorkflowCommands,
+    },
+
+    /// List, inspect, cancel and pause executions
+    Executions {
+        #[command(subcommand)]
+        command: ExecutionsCommands,
+    },
+
+    /// Show an execution (same as `executions show`)
+    Show {
+        id: String,
+
+        /// Also show the VM steps of its last failed run, if it opted in to tracing
+        #[arg(long)]
+        vm_trace: bool,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare two executions and the children each started
+    DiffExecutions {
+        left: String,
+        right: String,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show a workflow's saved VM state: where it is, its frames and
+    /// variables, and what it is waiting on
+    InspectVm {
+        id: String,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Export executions, their saved states and workflow definitions as NDJSON
+    Export {
+        /// Only executions created at or after this time (RFC 3339)
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+
+        /// File to write; defaults to stdout
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+    },
+
+    /// Import a file written by `export`, skipping records already present
+    Import {
+        /// Path to the NDJSON file
+        file: std::path::PathBuf,
+    },
+
+    /// Run a workflow file to completion with an embedded worker
+    ///
+    /// Registers the workflow, starts it on a queue of its own and prints
+    /// its result. Tasks have no handlers here: give their output with
+    /// --task, or they fail.
+    Run {
+        /// Path to the .flow file; the file name is the workflow name
+        file: std::path::PathBuf,
+
+        /// Workflow inputs as a JSON object
+        #[arg(long, default_value = "{}")]
+        inputs: String,
+
+        /// Output for a task, as NAME=JSON; repeat for more tasks
+        #[arg(long = "task", value_name = "NAME=JSON", value_parser = parse_task_output)]
+        tasks: Vec<(String, JsonValue)>,
+
+        /// Give up after this long, e.g. 90s, 10m
+        #[arg(long, default_value = "5m", value_parser = parse_duration)]
+        timeout: Duration,
+    },
 
-async fn migrate() -> Result<()> {
-    let database_url = std::env::var("RHYTHM_DATABASE_URL")
-        .or_else(|_| std::env::var("DATABASE_URL"))
-        .expect("RHYTHM_DATABASE_URL or DATABASE_URL must be set");
+    /// Run workflow unit tests (`*.flow.test` files) without a database
+    Test {
+        /// Test files, or directories to search for them
+        #[arg(default_value = "workflows")]
+        paths: Vec<std::path::PathBuf>,
+    },
 
-    println!("Running migrations against: {}", database_url);
+    /// Print a workflow file's task graph, without a database
+    ///
+    /// The tasks, child workflows and signals it may use, its awaits and
+    /// loops, and the calls that may run in parallel, as JSON.
+    Graph {
+        /// Path to the .flow file; the file name is the workflow name
+        file: std::path::PathBuf,
 
-    let pool = PgPoolOptions::new()
-        .max_connections(1)
-        .connect(&database_url)
-        .await?;
+        /// Print Graphviz DOT instead, e.g. for `dot -Tsvg`
+        #[arg(long)]
+        dot: bool,
+    },
 
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    /// Step through a workflow file interactively, without a database
+    ///
+    /// Stops before the first statement; `help` lists the commands. An
+    /// await stops the program until its result is given with `resume`.
+    Debug {
+        /// Path to the .flow file
+        file: std::path::PathBuf,
 
-    println!("Migrations completed successfully");
+        /// Workflow inputs as a JSON object
+        #[arg(long, default_value = "{}")]
+        inputs: String,
+
+        /// Stop before statements on this line; repeat for more lines
+        #[arg(long = "break", value_name = "LINE")]
+        breakpoints: Vec<usize>,
+
+        /// Start from a VM state written by `save` instead of the beginning
+        #[arg(long)]
+        state: Option<std::path::PathBuf>,
+    },
+
+    /// Re-run a recorded workflow locally in the debugger
+    ///
+    /// The workflow starts from the beginning of the definition its run
+    /// ran, reads the values the run read and gets the results its awaits
+    /// got, so it ends the way the run did. The execution must have been
+    /// recorded (`[worker] record_runs` or `Client::enable_recording`).
+    Replay {
+        id: String,
+
+        /// Stop before statements on this line; repeat for more lines
+        #[arg(long = "break", value_name = "LINE")]
+        breakpoints: Vec<usize>,
+    },
+
+    /// Evaluate Flow statements and expressions interactively, without a
+    /// database
+    ///
+    /// Variables carry over between inputs. Tasks and child workflows
+    /// return `null` unless stubbed; `.help` lists the commands.
+    Repl {
+        /// Value of `Inputs`, as a JSON object
+        #[arg(long, default_value = "{}")]
+        inputs: String,
+    },
+
+    /// Soak test: run a randomized, faulty workload in a temporary database
+    Simulate {
+        /// Seed for the workload; defaults to the current time
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Workflows to start
+        #[arg(long, default_value_t = 50)]
+        workflows: usize,
+
+        /// Most tasks a fan-out starts
+        #[arg(long, default_value_t = 5)]
+        max_fan_out: usize,
+
+        /// Chance a task run fails
+        #[arg(long, default_value_t = 0.1)]
+        failure_rate: f64,
+
+        /// Chance the worker crashes on a claim
+        #[arg(long, default_value_t = 0.05)]
+        crash_rate: f64,
+
+        /// Chance a started workflow is cancelled at each step
+        #[arg(long, default_value_t = 0.02)]
+        cancel_rate: f64,
+
+        /// Give up on the workload draining after this long, e.g. 90s, 10m
+        #[arg(long, default_value = "2m", value_parser = parse_duration)]
+        timeout: Duration,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Serve the read-only web dashboard
+    #[cfg(feature = "dashboard")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: std::net::SocketAddr,
+    },
+
+    /// Pause or resume claims cluster-wide
+    Maintenance {
+        #[command(subcommand)]
+        command: MaintenanceCommands,
+    },
+
+    /// Inspect the payload shapes recorded per target
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommands,
+    },
+
+    /// Register and inspect webhooks notified when executions finish
+    Webhooks {
+        #[command(subcommand)]
+        command: WebhooksCommands,
+    },
+
+    /// Create, list and revoke the API keys adapters present
+    ApiKeys {
+        #[command(subcommand)]
+        command: ApiKeysCommands,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Cli::parse();
+    let config = args.config;
+
+    match args.command {
+        Commands::Migrate => cli::migrate().await?,
+        Commands::MigrateState {
+            apply,
+            batch_size,
+            json,
+        } => cli::rewrite::migrate_state(config, apply, batch_size, json).await?,
+        Commands::Reencrypt {
+            apply,
+            batch_size,
+            json,
+        } => cli::rewrite::reencrypt(config, apply, batch_size, json).await?,
+        Commands::Slo { json } => cli::status::slo(config, json).await?,
+        Commands::Groups { json } => cli::status::groups(config, json).await?,
+        Commands::Maintenance { command } => cli::maintenance::run(config, command).await?,
+        Commands::Schema { command } => cli::schema::run(config, command).await?,
+        Commands::Webhooks { command } => cli::webhooks::run(config, command).await?,
+        Commands::ApiKeys { command } => cli::api_keys::run(config, command).await?,
+        Commands::Workflow { command } => cli::workflows::run(config, command).await?,
+        Commands::Executions { command } => cli::executions::run(config, command).await?,
+        Commands::Show { id, vm_trace, json } => {
+            cli::executions::show(config, &id, vm_trace, json).await?
+        }
+        Commands::DiffExecutions { left, right, json } => {
+            cli::executions::diff(config, &left, &right, json).await?
+        }
+        Commands::InspectVm { id, json } => cli::executions::inspect_vm(config, &id, json).await?,
+        Commands::Export { since, out } => {
+            cli::transfer::export(config, since, out.as_deref()).await?
+        }
+        Commands::Import { file } => cli::transfer::import(config, &file).await?,
+        Commands::Run {
+            file,
+            inputs,
+            tasks,
+            timeout,
+        } => cli::run_file::run(config, &file, &inputs, tasks, timeout).await?,
+        Commands::Test { paths } => cli::flow_tests::run(&paths)?,
+        Commands::Graph { file, dot } => cli::workflows::graph(&file, dot)?,
+        Commands::Debug {
+            file,
+            inputs,
+            breakpoints,
+            state,
+        } => cli::debug::debug_file(&file, &inputs, breakpoints, state.as_deref())?,
+        Commands::Replay { id, breakpoints } => {
+            cli::debug::replay(config, &id, breakpoints).await?
+        }
+        Commands::Repl { inputs } => cli::repl::run(&inputs)?,
+        Commands::Simulate {
+            seed,
+            workflows,
+            max_fan_out,
+            failure_rate,
+            crash_rate,
+            cancel_rate,
+            timeout,
+            json,
+        } => {
+            let seed = seed.unwrap_or_else(|| chrono::Utc::now().timestamp_micros() as u64);
+            let simulation = rhythm_core::simulation::SimulationConfig {
+                seed,
+                workflows,
+                max_fan_out,
+                failure_rate,
+                crash_rate,
+                cancel_rate,
+                drain_timeout: timeout,
+            };
+            cli::simulate::run(config, &simulation, json).await?
+        }
+        #[cfg(feature = "dashboard")]
+        Commands::Serve { addr } => cli::serve::run(addr, config).await?,
+    }
 
     Ok(())
 }
@@ -0,0 +1,816 @@
+//! External storage for large payloads
+//!
+//! Limits from `[payloads]` config are checked wherever execution inputs and
+//! outputs are written. A payload over `max_inline_bytes` is written to a
+//! `BlobStore` and replaced in its row by a reference:
+//!
+//! ```json
+//! { "$rhythm_blob": { "key": "<sha256 of the payload>", "size": 1048576 } }
+//! ```
+//!
+//! References are resolved again where payloads are read: by workers before
+//! running a task or workflow, when a workflow resumes with a child's result,
+//! and by `get_execution`. Listings return them as stored.
+//!
+//! Blobs are named by their content's hash, so writing one again (a retried
+//! completion, the same inputs twice) is harmless. They are not deleted with
+//! their executions.
+//!
+//! Without a blob store, payloads are only checked against the limits and
+//! `$rhythm_blob` is plain data.
+//!
+//! With encryption on (see `encryption`), payloads are encrypted after they
+//! are checked against the limits, so sizes are of the plain payload, and an
+//! offloaded payload's blob holds the encrypted one. Either way they are
+//! encrypted for the execution and column they are written to, which
+//! readers name to decrypt them. `$rhythm_enc` is reserved whether or not
+//! encryption is on, so a payload can't be mistaken for an encrypted one.
+//!
+//! Saved workflow states are never offloaded. They are written as JSON or
+//! MessagePack per `state_encoding` (see `state_encoding`), checked against
+//! `max_state_bytes` and compressed above `compress_state_above_bytes` (see
+//! `compression`) before they are encrypted.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::compression;
+use crate::config::PayloadsConfig;
+use crate::db::workflow_execution_context::StoredState;
+use crate::encryption::{self, Binding, Keyring, BINDING_KEY, ENVELOPE_KEY};
+use crate::errors::RhythmError;
+use crate::executor::{Val, VM};
+use crate::payload::PayloadEncoding;
+use crate::state_encoding;
+use crate::types::ExecutionOutcome;
+
+/// Key of the object that stands in for an offloaded payload
+pub const BLOB_REFERENCE_KEY: &str = "$rhythm_blob";
+
+/// Error code of an execution whose result was over the payload limits
+pub const PAYLOAD_TOO_LARGE_CODE: &str = "PAYLOAD_TOO_LARGE";
+
+/// Error code of a workflow whose state was over `max_state_bytes`
+pub const STATE_TOO_LARGE_CODE: &str = "STATE_TOO_LARGE";
+
+/// Storage for offloaded payloads, e.g. a shared filesystem or S3
+///
+/// Calls run on a blocking thread, so implementations may block, and can
+/// reach async clients through `tokio::runtime::Handle::current()`.
+pub trait BlobStore: Send + Sync + Debug {
+    /// Store `bytes` under `key`, replacing what is there
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<()>;
+
+    /// The bytes stored under `key`
+    fn get(&self, key: &str) -> Result<Vec<u8>>;
+}
+
+/// Blob store on a directory every worker can reach
+#[derive(Debug, Clone)]
+pub struct FilesystemBlobStore {
+    root: PathBuf,
+}
+
+impl FilesystemBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Blobs are spread over subdirectories by their first two characters
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        if key.len() < 3 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(anyhow!("Invalid blob key: {}", key));
+        }
+        Ok(self.root.join(&key[..2]).join(key))
+    }
+}
+
+impl BlobStore for FilesystemBlobStore {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path(key)?;
+        let dir = path.parent().expect("blob paths have a parent");
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create blob directory {}", dir.display()))?;
+
+        // Written aside and renamed, so readers never see a partial blob
+        let partial = dir.join(format!(".{}.{}", key, uuid::Uuid::new_v4()));
+        std::fs::write(&partial, bytes)
+            .with_context(|| format!("Failed to write blob {}", partial.display()))?;
+        std::fs::rename(&partial, &path)
+            .with_context(|| format!("Failed to write blob {}", path.display()))
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.path(key)?;
+        std::fs::read(&path).with_context(|| format!("Failed to read blob {}", path.display()))
+    }
+}
+
+/// Payload limits, the blob store oversized payloads go to, and the keys
+/// payloads are encrypted with
+#[derive(Debug, Clone, Default)]
+pub struct PayloadStore {
+    max_inline_bytes: Option<usize>,
+    max_bytes: Option<usize>,
+    max_state_bytes: Option<usize>,
+    compress_state_above_bytes: Option<usize>,
+    state_encoding: PayloadEncoding,
+    blobs: Option<Arc<dyn BlobStore>>,
+    keyring: Option<Arc<Keyring>>,
+}
+
+impl PayloadStore {
+    /// Limits from config, with the filesystem store if one is configured
+    pub fn from_config(config: &PayloadsConfig) -> Self {
+        Self {
+            max_inline_bytes: config.max_inline_bytes,
+            max_bytes: config.max_bytes,
+            max_state_bytes: config.max_state_bytes,
+            compress_state_above_bytes: Some(config.compress_state_above_bytes),
+            state_encoding: config.state_encoding,
+            blobs: config
+                .store
+                .as_ref()
+                .map(|store| Arc::new(FilesystemBlobStore::new(&store.path)) as Arc<dyn BlobStore>),
+            keyring: None,
+        }
+    }
+
+    /// Offload oversized payloads to `blobs`
+    pub fn with_blob_store(mut self, blobs: Arc<dyn BlobStore>) -> Self {
+        self.blobs = Some(blobs);
+        self
+    }
+
+    /// Encrypt payloads and saved states with `keyring`
+    pub fn with_encryption(mut self, keyring: Keyring) -> Self {
+        self.keyring = Some(Arc::new(keyring));
+        self
+    }
+
+    /// The payload to store in the row and column `binding` names for
+    /// `value`
+    ///
+    /// `what` names the payload in errors, e.g. "Task result". Fails with
+    /// `RhythmError::Validation` if `value` is over the limits, or uses the
+    /// reserved `$rhythm_enc` key, or `$rhythm_blob` with a blob store.
+    pub async fn offload(
+        &self,
+        value: JsonValue,
+        what: &str,
+        binding: Binding<'_>,
+    ) -> Result<JsonValue> {
+        let reserved = [
+            (self.blobs.is_some(), BLOB_REFERENCE_KEY),
+            (true, ENVELOPE_KEY),
+        ];
+        for (_, key) in reserved.iter().filter(|(on, _)| *on) {
+            if contains_key(&value, key) {
+                return Err(reserved_key(what, key));
+            }
+        }
+        if self.max_inline_bytes.is_none()
+            && self.max_bytes.is_none()
+            && self.blobs.is_none()
+            && self.keyring.is_none()
+        {
+            return Ok(value);
+        }
+
+        let bytes = serde_json::to_vec(&value).context("Failed to serialize payload")?;
+        let size = bytes.len();
+        if let Some(max_bytes) = self.max_bytes.filter(|max| size > *max) {
+            return Err(too_large(what, size, max_bytes));
+        }
+        let Some(max_inline_bytes) = self.max_inline_bytes.filter(|max| size > *max) else {
+            return self.seal(value, binding);
+        };
+        let Some(blobs) = self.blobs.clone() else {
+            return Err(too_large(what, size, max_inline_bytes));
+        };
+
+        let bytes = match &self.keyring {
+            Some(keyring) => serde_json::to_vec(&keyring.encrypt(&value, binding)?)
+                .context("Failed to serialize payload")?,
+            None => bytes,
+        };
+        let key: String = Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let blob_key = key.clone();
+        tokio::task::spawn_blocking(move || blobs.put(&blob_key, &bytes))
+            .await
+            .context("Blob store write panicked")??;
+        Ok(json!({ BLOB_REFERENCE_KEY: { "key": key, "size": size } }))
+    }
+
+    /// How an execution that returned `result` finishes
+    ///
+    /// A result over the limits fails the execution with
+    /// `PAYLOAD_TOO_LARGE` instead, so whoever awaits it finds out.
+    pub async fn offload_result(
+        &self,
+        result: JsonValue,
+        what: &str,
+        execution_id: &str,
+    ) -> Result<ExecutionOutcome> {
+        match self
+            .offload(result, what, Binding::output(execution_id))
+            .await
+        {
+            Ok(result) => Ok(ExecutionOutcome::Success(result)),
+            Err(e) => match e.downcast_ref::<RhythmError>() {
+                Some(RhythmError::Validation(message)) => Ok(ExecutionOutcome::Failure(json!({
+                    "code": PAYLOAD_TOO_LARGE_CODE,
+                    "message": message,
+                }))),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// `value` encrypted for where `binding` names, when encryption is on
+    ///
+    /// For what is stored without the payload limits: errors and saved
+    /// workflow states.
+    pub fn seal(&self, value: JsonValue, binding: Binding<'_>) -> Result<JsonValue> {
+        encryption::seal(self.keyring.as_deref(), value, binding)
+    }
+
+    /// `value`, read from where `binding` names, decrypted if `seal`
+    /// encrypted it
+    pub fn open(&self, value: JsonValue, binding: Binding<'_>) -> Result<JsonValue> {
+        encryption::open(self.keyring.as_deref(), value, Some(binding))
+    }
+
+    /// What to store for the saved state of workflow `execution_id`, in
+    /// `state_encoding`
+    ///
+    /// Fails with `RhythmError::Validation` if the state is over
+    /// `max_state_bytes`, naming its largest variable.
+    pub fn seal_state(&self, execution_id: &str, vm: &VM) -> Result<StoredState> {
+        let binding = Binding::state(execution_id);
+        if self.state_encoding == PayloadEncoding::MsgPack {
+            let bytes = state_encoding::encode(vm)?;
+            let size = bytes.len();
+            self.check_state_size(vm, size)?;
+            let compress = self.compress_state_above_bytes.is_some_and(|t| size > t);
+            tracing::debug!(state_bytes = size, compressed = compress, "Saving VM state");
+            return state_encoding::seal(bytes, compress, self.keyring.as_deref(), binding)
+                .map(StoredState::Binary);
+        }
+
+        let state = serde_json::to_value(vm).context("Failed to serialize VM state")?;
+        if self.max_state_bytes.is_none() && self.compress_state_above_bytes.is_none() {
+            return self
+                .seal(state, binding)
+                .context("Failed to encrypt VM state")
+                .map(StoredState::Json);
+        }
+
+        let bytes = serde_json::to_vec(&state).context("Failed to serialize VM state")?;
+        let size = bytes.len();
+        self.check_state_size(vm, size)?;
+        let state = match self.compress_state_above_bytes {
+            Some(threshold) if size > threshold => compression::compress(&bytes)?,
+            _ => state,
+        };
+        tracing::debug!(
+            state_bytes = size,
+            compressed = compression::is_compressed(&state),
+            "Saving VM state"
+        );
+        self.seal(state, binding)
+            .context("Failed to encrypt VM state")
+            .map(StoredState::Json)
+    }
+
+    /// The VM in workflow `execution_id`'s saved state, whichever encoding
+    /// it was written in
+    pub fn open_state(&self, execution_id: &str, stored: StoredState) -> Result<VM> {
+        let binding = Binding::state(execution_id);
+        match stored {
+            StoredState::Json(value) => {
+                serde_json::from_value(self.open_json_state(value, binding)?)
+                    .context("Failed to deserialize VM state")
+            }
+            StoredState::Binary(bytes) => state_encoding::decode(&state_encoding::open(
+                &bytes,
+                self.keyring.as_deref(),
+                binding,
+            )?),
+        }
+    }
+
+    /// Whether `stored`, which loads as `vm`, is written the way
+    /// `seal_state` would write it now: in the current layout and encoding,
+    /// under the active key
+    pub fn is_current_state(
+        &self,
+        execution_id: &str,
+        stored: &StoredState,
+        vm: &VM,
+    ) -> Result<bool> {
+        let binding = Binding::state(execution_id);
+        let current = serde_json::to_value(vm).context("Failed to serialize VM state")?;
+        match stored {
+            StoredState::Json(value) => Ok(self.state_encoding == PayloadEncoding::Json
+                && !self.needs_reencryption(value)
+                && self.open_json_state(value.clone(), binding)? == current),
+            StoredState::Binary(bytes) => {
+                let keyring = self.keyring.as_deref();
+                if self.state_encoding != PayloadEncoding::MsgPack
+                    || state_encoding::key_id(bytes) != keyring.map(Keyring::active_key)
+                    || (keyring.is_some() && !state_encoding::is_bound(bytes))
+                {
+                    return Ok(false);
+                }
+                let plain = state_encoding::open(bytes, keyring, binding)?;
+                let stored: JsonValue =
+                    rmp_serde::from_slice(&plain).context("Failed to deserialize VM state")?;
+                Ok(stored == current)
+            }
+        }
+    }
+
+    fn open_json_state(&self, stored: JsonValue, binding: Binding<'_>) -> Result<JsonValue> {
+        compression::decompress(
+            self.open(stored, binding)
+                .context("Failed to decrypt VM state")?,
+        )
+    }
+
+    fn check_state_size(&self, vm: &VM, size: usize) -> Result<()> {
+        match self.max_state_bytes.filter(|max| size > *max) {
+            Some(max_state_bytes) => Err(state_too_large(vm, size, max_state_bytes)),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `stored` would be encrypted differently if written now
+    ///
+    /// False when it is already under the active key and bound to its row,
+    /// encryption is off, or it is offloaded: blobs keep the key they were
+    /// written with.
+    pub fn needs_reencryption(&self, stored: &JsonValue) -> bool {
+        match &self.keyring {
+            Some(keyring) => {
+                reference_key(stored).is_none()
+                    && (encryption::envelope_key(stored) != Some(keyring.active_key())
+                        || !encryption::is_bound(stored))
+            }
+            None => false,
+        }
+    }
+
+    /// What to store instead of `stored`, read from where `binding` names,
+    /// to have it under the active key, or `None` if it
+    /// `needs_reencryption` not
+    pub fn reencrypt(&self, stored: &JsonValue, binding: Binding<'_>) -> Result<Option<JsonValue>> {
+        if !self.needs_reencryption(stored) {
+            return Ok(None);
+        }
+        self.seal(self.open(stored.clone(), binding)?, binding)
+            .map(Some)
+    }
+
+    /// `value`, read from where `binding` names, with the payloads it
+    /// references read back in, decrypted
+    pub async fn resolve(&self, value: JsonValue, binding: Binding<'_>) -> Result<JsonValue> {
+        if !contains_key(&value, BLOB_REFERENCE_KEY) && !contains_key(&value, ENVELOPE_KEY) {
+            return Ok(value);
+        }
+        let store = self.clone();
+        let binding = binding.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut value = value;
+            store.resolve_json(&mut value, Binding::parse(&binding))?;
+            Ok(value)
+        })
+        .await
+        .context("Blob store read panicked")?
+    }
+
+    /// As `resolve`, for a value a workflow is resumed with
+    ///
+    /// Results in it are decrypted for the rows `bind_stored` marked them
+    /// as read from.
+    pub async fn resolve_val(&self, val: Val) -> Result<Val> {
+        if !val_contains_key(&val, BLOB_REFERENCE_KEY) && !val_contains_key(&val, ENVELOPE_KEY) {
+            return Ok(val);
+        }
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut val = val;
+            store.resolve_val_in_place(&mut val)?;
+            Ok(val)
+        })
+        .await
+        .context("Blob store read panicked")?
+    }
+
+    /// The payload a reference or envelope read from where `binding` names
+    /// stands for, or where `bind_stored` marked it as read from
+    ///
+    /// References are left alone without a blob store, as they could be
+    /// plain data.
+    fn read_stored<'a>(
+        &self,
+        value: &'a JsonValue,
+        binding: Option<Binding<'a>>,
+    ) -> Result<Option<JsonValue>> {
+        let keyring = self.keyring.as_deref();
+        if encryption::envelope_key(value).is_some() {
+            let binding = binding.or_else(|| encryption::stated_binding(value));
+            return encryption::open(keyring, value.clone(), binding).map(Some);
+        }
+        match (reference_key(value), &self.blobs) {
+            (Some(key), Some(blobs)) => {
+                let binding = binding
+                    .or_else(|| Binding::parse(value[BLOB_REFERENCE_KEY][BINDING_KEY].as_str()?));
+                encryption::open(keyring, read_blob(blobs.as_ref(), key)?, binding).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn resolve_json(&self, value: &mut JsonValue, binding: Option<Binding<'_>>) -> Result<()> {
+        if let Some(payload) = self.read_stored(value, binding)? {
+            *value = payload;
+            return Ok(());
+        }
+        match value {
+            JsonValue::Object(map) => map
+                .values_mut()
+                .try_for_each(|v| self.resolve_json(v, binding)),
+            JsonValue::Array(items) => items
+                .iter_mut()
+                .try_for_each(|v| self.resolve_json(v, binding)),
+            _ => Ok(()),
+        }
+    }
+
+    fn resolve_val_in_place(&self, val: &mut Val) -> Result<()> {
+        if is_stored_val(val) {
+            let value = crate::executor::val_to_json(val)?;
+            if let Some(payload) = self.read_stored(&value, None)? {
+                *val = crate::executor::json_to_val(&payload)?;
+                return Ok(());
+            }
+        }
+        match val {
+            Val::Obj(map) => map
+                .values_mut()
+                .try_for_each(|v| self.resolve_val_in_place(v)),
+            Val::List(items) => items
+                .iter_mut()
+                .try_for_each(|v| self.resolve_val_in_place(v)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Marks a reference or envelope read from the row and column `binding`
+/// names as read from there
+///
+/// For results carried on before they are resolved, such as a child's
+/// result a workflow resumes with, which `resolve_val` then decrypts for
+/// that row only. Envelopes from before values were bound are left as
+/// they are.
+pub fn bind_stored(value: &mut JsonValue, binding: Binding<'_>) {
+    if reference_key(value).is_some() {
+        value[BLOB_REFERENCE_KEY][BINDING_KEY] = json!(binding.to_string());
+    } else if encryption::envelope_key(value).is_some() && encryption::is_bound(value) {
+        value[ENVELOPE_KEY][BINDING_KEY] = json!(binding.to_string());
+    }
+}
+
+/// Fails with `RhythmError::Validation` if `value` uses a key reserved for
+/// stored payloads
+///
+/// For values stored as they are, such as signal payloads, which are
+/// resumed with like results.
+pub fn check_reserved_keys(value: &JsonValue, what: &str) -> Result<()> {
+    for key in [BLOB_REFERENCE_KEY, ENVELOPE_KEY] {
+        if contains_key(value, key) {
+            return Err(reserved_key(what, key));
+        }
+    }
+    Ok(())
+}
+
+fn reserved_key(what: &str, key: &str) -> anyhow::Error {
+    RhythmError::Validation(format!("{}: `{}` is a reserved key", what, key)).into()
+}
+
+fn too_large(what: &str, size: usize, limit: usize) -> anyhow::Error {
+    RhythmError::Validation(format!(
+        "{}: {} bytes is over the {} byte payload limit",
+        what, size, limit
+    ))
+    .into()
+}
+
+fn state_too_large(vm: &VM, size: usize, limit: usize) -> anyhow::Error {
+    let largest = vm
+        .env
+        .iter()
+        .map(|(name, val)| (serde_json::to_vec(val).map_or(0, |b| b.len()), name))
+        .max();
+    let mut message = format!(
+        "Workflow state is {} bytes, over the {} byte limit",
+        size, limit
+    );
+    if let Some((bytes, name)) = largest {
+        message.push_str(&format!(
+            "; its largest variable is `{}` ({} bytes)",
+            name, bytes
+        ));
+    }
+    RhythmError::Validation(message).into()
+}
+
+/// The blob key if `value` is a reference
+fn reference_key(value: &JsonValue) -> Option<&str> {
+    match value {
+        JsonValue::Object(map) if map.len() == 1 => map.get(BLOB_REFERENCE_KEY)?["key"].as_str(),
+        _ => None,
+    }
+}
+
+fn contains_key(value: &JsonValue, key: &str) -> bool {
+    match value {
+        JsonValue::Object(map) => {
+            map.contains_key(key) || map.values().any(|v| contains_key(v, key))
+        }
+        JsonValue::Array(items) => items.iter().any(|v| contains_key(v, key)),
+        _ => false,
+    }
+}
+
+fn read_blob(blobs: &dyn BlobStore, key: &str) -> Result<JsonValue> {
+    let bytes = blobs.get(key)?;
+    serde_json::from_slice(&bytes).with_context(|| format!("Invalid payload in blob {}", key))
+}
+
+/// Whether `val` could be a reference or an envelope
+fn is_stored_val(val: &Val) -> bool {
+    let Val::Obj(map) = val else {
+        return false;
+    };
+    map.len() == 1 && (map.contains_key(BLOB_REFERENCE_KEY) || map.contains_key(ENVELOPE_KEY))
+}
+
+fn val_contains_key(val: &Val, key: &str) -> bool {
+    match val {
+        Val::Obj(map) => map.contains_key(key) || map.values().any(|v| val_contains_key(v, key)),
+        Val::List(items) => items.iter().any(|v| val_contains_key(v, key)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BlobStoreConfig;
+    use base64::Engine;
+
+    fn store(max_inline_bytes: usize, dir: &std::path::Path) -> PayloadStore {
+        PayloadStore::from_config(&PayloadsConfig {
+            max_inline_bytes: Some(max_inline_bytes),
+            max_bytes: Some(10_000),
+            store: Some(BlobStoreConfig {
+                path: dir.to_path_buf(),
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rhythm-blobs-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_oversized_payloads_round_trip_through_the_store() {
+        let dir = temp_dir("round-trip");
+        let payloads = store(64, &dir);
+        let row = Binding::inputs("e1");
+
+        let small = json!({ "id": 1 });
+        assert_eq!(
+            payloads
+                .offload(small.clone(), "Inputs", row)
+                .await
+                .unwrap(),
+            small
+        );
+
+        let large = json!({ "rows": vec!["row"; 50] });
+        let stored = payloads
+            .offload(large.clone(), "Inputs", row)
+            .await
+            .unwrap();
+        assert!(reference_key(&stored).is_some());
+        assert_eq!(payloads.resolve(stored.clone(), row).await.unwrap(), large);
+
+        // Nested in a workflow's view of several results
+        let results = Val::List(vec![
+            Val::Null,
+            crate::executor::json_to_val(&stored).unwrap(),
+        ]);
+        let Val::List(resolved) = payloads.resolve_val(results).await.unwrap() else {
+            panic!("expected a list");
+        };
+        assert_eq!(crate::executor::val_to_json(&resolved[1]).unwrap(), large);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_limits_reject_payloads() {
+        let dir = temp_dir("limits");
+        let payloads = store(64, &dir);
+        let row = Binding::inputs("e1");
+        let err = payloads
+            .offload(json!({ "blob": "x".repeat(20_000) }), "Inputs", row)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("over the 10000 byte payload limit"));
+
+        let err = payloads
+            .offload(
+                json!({ BLOB_REFERENCE_KEY: { "key": "../secrets" } }),
+                "Inputs",
+                row,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is a reserved key"));
+
+        // Without a store, the inline limit is the limit
+        let inline_only = PayloadStore::from_config(&PayloadsConfig {
+            max_inline_bytes: Some(8),
+            ..Default::default()
+        });
+        let err = inline_only
+            .offload(json!("0123456789"), "Output", row)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RhythmError>(),
+            Some(RhythmError::Validation(_))
+        ));
+
+        // An envelope is reserved even without encryption, so it can't be
+        // stored as plain data that reads back as an encrypted value
+        let err = PayloadStore::default()
+            .offload(json!({ "note": { ENVELOPE_KEY: "x" } }), "Inputs", row)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("`$rhythm_enc` is a reserved key"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_states_are_capped_and_compressed() {
+        let mut vm = crate::executor::VM::new(
+            crate::parser::parse_workflow("return 1").unwrap().body,
+            Default::default(),
+            crate::executor::WorkflowContext {
+                execution_id: "wf".to_string(),
+            },
+        );
+        vm.env
+            .insert("rows".to_string(), Val::Str("row ".repeat(5_000)));
+        let payloads = PayloadStore::from_config(&PayloadsConfig {
+            max_state_bytes: Some(100_000),
+            compress_state_above_bytes: 10_000,
+            ..Default::default()
+        });
+
+        let stored = payloads.seal_state("wf", &vm).unwrap();
+        assert!(compression::is_compressed(stored.as_json().unwrap()));
+        assert!(payloads.is_current_state("wf", &stored, &vm).unwrap());
+        assert_eq!(
+            serde_json::to_value(payloads.open_state("wf", stored).unwrap()).unwrap(),
+            serde_json::to_value(&vm).unwrap()
+        );
+
+        vm.env
+            .insert("rows".to_string(), Val::Str("row ".repeat(50_000)));
+        let err = payloads.seal_state("wf", &vm).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RhythmError>(),
+            Some(RhythmError::Validation(_))
+        ));
+        assert!(err
+            .to_string()
+            .contains("over the 100000 byte limit; its largest variable is `rows`"));
+    }
+
+    #[test]
+    fn test_binary_states() {
+        let mut vm = crate::executor::VM::new(
+            crate::parser::parse_workflow("return 1").unwrap().body,
+            Default::default(),
+            crate::executor::WorkflowContext {
+                execution_id: "wf".to_string(),
+            },
+        );
+        vm.env
+            .insert("rows".to_string(), Val::Str("row ".repeat(5_000)));
+        let json = PayloadStore::default();
+        let binary = PayloadStore::from_config(&PayloadsConfig {
+            compress_state_above_bytes: 10_000,
+            state_encoding: PayloadEncoding::MsgPack,
+            ..Default::default()
+        });
+
+        let stored = binary.seal_state("wf", &vm).unwrap();
+        let StoredState::Binary(bytes) = &stored else {
+            panic!("Expected a binary state");
+        };
+        assert_eq!(bytes[0], state_encoding::FORMAT_MSGPACK_V1);
+        assert!(bytes.len() < 1_000);
+        assert!(binary.is_current_state("wf", &stored, &vm).unwrap());
+        assert!(!json.is_current_state("wf", &stored, &vm).unwrap());
+
+        // Either store reads both encodings
+        let from_json = json.seal_state("wf", &vm).unwrap();
+        assert!(!binary.is_current_state("wf", &from_json, &vm).unwrap());
+        for (store, state) in [(&json, stored), (&binary, from_json)] {
+            assert_eq!(
+                serde_json::to_value(store.open_state("wf", state).unwrap()).unwrap(),
+                serde_json::to_value(&vm).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_results_decrypt_only_for_the_row_they_were_read_from() {
+        let dir = temp_dir("bound");
+        let key = base64::engine::general_purpose::STANDARD.encode([1u8; 32]);
+        let keyring = Keyring::from_config(&crate::config::EncryptionConfig {
+            active_key: Some("k1".to_string()),
+            keys: [("k1".to_string(), key)].into(),
+        })
+        .unwrap()
+        .unwrap();
+        let payloads = store(64, &dir).with_encryption(keyring);
+
+        let small = json!({ "id": "a" });
+        let large = json!({ "rows": vec!["row"; 50] });
+        let mut inline = payloads
+            .offload(small.clone(), "Output", Binding::output("a"))
+            .await
+            .unwrap();
+        let mut offloaded = payloads
+            .offload(large.clone(), "Output", Binding::output("b"))
+            .await
+            .unwrap();
+        assert!(payloads
+            .resolve(inline.clone(), Binding::output("b"))
+            .await
+            .is_err());
+        assert!(payloads
+            .resolve(offloaded.clone(), Binding::output("a"))
+            .await
+            .is_err());
+
+        // Results a workflow resumes with are marked with the row they came from
+        bind_stored(&mut inline, Binding::output("a"));
+        bind_stored(&mut offloaded, Binding::output("b"));
+        let results = crate::executor::json_to_val(&json!([inline, offloaded])).unwrap();
+        let resolved = payloads.resolve_val(results).await.unwrap();
+        assert_eq!(
+            crate::executor::val_to_json(&resolved).unwrap(),
+            json!([small, large])
+        );
+
+        // One copied into another child's row doesn't decrypt there
+        let mut swapped = payloads
+            .offload(small, "Output", Binding::output("a"))
+            .await
+            .unwrap();
+        bind_stored(&mut swapped, Binding::output("b"));
+        let swapped = crate::executor::json_to_val(&swapped).unwrap();
+        assert!(payloads.resolve_val(swapped).await.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_filesystem_store_rejects_paths_as_keys() {
+        let store = FilesystemBlobStore::new(std::env::temp_dir());
+        assert!(store.get("../../etc/passwd").is_err());
+        assert!(store.put("ab/cd", b"{}").is_err());
+    }
+}
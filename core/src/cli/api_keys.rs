@@ -0,0 +1,84 @@
+//! `rhythm api-keys`
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use super::open_app;
+use crate::auth::Scope;
+
+/// `rhythm api-keys` subcommands
+#[derive(Subcommand)]
+pub enum ApiKeysCommands {
+    /// Create a key; it is printed once and can't be shown again
+    Create {
+        /// Who or what the key is for
+        name: String,
+
+        /// Scope to grant: read_only, enqueue, worker or admin; repeat for more
+        #[arg(long = "scope", required = true)]
+        scopes: Vec<Scope>,
+    },
+
+    /// List keys, revoked ones included
+    List {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Stop accepting a key
+    Revoke { id: String },
+}
+
+/// Run a `rhythm api-keys` subcommand
+pub async fn run(config_path: Option<String>, command: ApiKeysCommands) -> Result<()> {
+    let read_only = matches!(command, ApiKeysCommands::List { .. });
+    let service = open_app(config_path, read_only).await?.api_key_service;
+
+    match command {
+        ApiKeysCommands::Create { name, scopes } => {
+            let created = service.create(&name, &scopes).await?;
+            println!("Created API key {} for {}", created.api_key.id, name);
+            println!("{}", created.key);
+            println!("Store it now; it can't be shown again");
+        }
+        ApiKeysCommands::List { json } => {
+            let keys = service.list().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&keys)?);
+                return Ok(());
+            }
+            if keys.is_empty() {
+                println!("No API keys");
+                return Ok(());
+            }
+            println!(
+                "{:<12}  {:<24} {:<28} {:<20} STATUS",
+                "ID", "NAME", "SCOPES", "LAST USED"
+            );
+            for key in keys {
+                let scopes: Vec<&str> = key.scopes.iter().map(|s| s.as_str()).collect();
+                let last_used = key.last_used_at.map_or("never".to_string(), |at| {
+                    at.format("%Y-%m-%d %H:%M").to_string()
+                });
+                let status = match key.revoked_at {
+                    Some(at) => format!("revoked {}", at.format("%Y-%m-%d %H:%M")),
+                    None => "active".to_string(),
+                };
+                println!(
+                    "{:<12}  {:<24} {:<28} {:<20} {}",
+                    key.id,
+                    key.name,
+                    scopes.join(","),
+                    last_used,
+                    status
+                );
+            }
+        }
+        ApiKeysCommands::Revoke { id } => {
+            service.revoke(&id).await?;
+            println!("Revoked API key {}", id);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,295 @@
+//! `rhythm debug` and `rhythm replay`
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+
+use super::open_app;
+use crate::ExecutionStatus;
+
+const DEBUG_HELP: &str = "\
+  s, step            run to the next statement
+  c, continue        run to a breakpoint, an await or the end
+  b, break [LINE]    stop before statements on LINE, or list breakpoints
+  d, delete LINE     remove the breakpoint on LINE
+  l, locals          show the variables in scope
+  p, print NAME      show a variable, or Inputs or Context
+  bt, frames         show the frame stack, innermost last
+  r, resume JSON     settle the await the program is stopped on
+  save PATH          write the VM state, to restart from with --state
+  q, quit            stop debugging";
+
+/// Step through a workflow file interactively
+pub fn debug_file(
+    file: &std::path::Path,
+    inputs: &str,
+    breakpoints: Vec<usize>,
+    state: Option<&std::path::Path>,
+) -> Result<()> {
+    use crate::executor::{json_to_val_map, Debugger, WorkflowContext, VM};
+
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let mut debugger = match state {
+        Some(path) => {
+            let state = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Debugger::restore(serde_json::from_str(&state).context("State is not valid JSON")?)?
+        }
+        None => {
+            let workflow = crate::parser::parse_workflow(&source)
+                .map_err(|e| anyhow::anyhow!("{}: {}", file.display(), e))?;
+            let inputs: JsonValue =
+                serde_json::from_str(inputs).context("--inputs is not valid JSON")?;
+            Debugger::new(VM::new(
+                workflow.body,
+                json_to_val_map(&inputs)?,
+                WorkflowContext {
+                    execution_id: "debug".to_string(),
+                },
+            ))
+        }
+    };
+    for line in breakpoints {
+        debugger.set_breakpoint(line);
+    }
+
+    debug_session(
+        debugger,
+        &source,
+        &format!("Debugging {}", file.display()),
+        state.is_none(),
+        None,
+    )
+}
+
+/// Re-run a recorded workflow in the debugger
+pub async fn replay(config_path: Option<String>, id: &str, breakpoints: Vec<usize>) -> Result<()> {
+    let app = open_app(config_path, true).await?;
+    let recorded = app
+        .workflow_service
+        .get_recording(id)
+        .await?
+        .with_context(|| format!("Execution {} was not recorded", id))?;
+    let mut debugger = recorded.debugger()?;
+    for line in breakpoints {
+        debugger.set_breakpoint(line);
+    }
+    println!(
+        "{} awaited result(s) and {} outside value(s) recorded",
+        recorded.resumes.len(),
+        recorded.reads.len()
+    );
+    debug_session(
+        debugger,
+        &recorded.source,
+        &format!("Replaying {} {}", recorded.workflow_name, id),
+        true,
+        Some(&recorded.execution),
+    )
+}
+
+/// Drive `debugger` from commands read on stdin until `quit` or EOF
+///
+/// With `recorded`, the debugger is replaying that execution's run: awaits
+/// are settled with the values it was resumed with, and how the replay
+/// ends is compared with how the run did.
+fn debug_session(
+    mut debugger: crate::executor::Debugger,
+    source: &str,
+    title: &str,
+    from_start: bool,
+    recorded: Option<&crate::types::Execution>,
+) -> Result<()> {
+    use crate::executor::{json_to_val, val_to_json, Awaitable, Control, Debugger, Stop, Val};
+    use std::io::{BufRead, Write};
+
+    let lines: Vec<&str> = source.lines().collect();
+    let show = |val: &Val| match val_to_json(val) {
+        Ok(json) => json.to_string(),
+        Err(_) => format!("{:?}", val),
+    };
+    let awaiting = |debugger: &Debugger, awaitable: &Awaitable| match awaitable {
+        Awaitable::Execution(id) => debugger
+            .vm()
+            .outbox
+            .executions
+            .iter()
+            .find(|creation| creation.id == *id)
+            .map_or_else(|| id.clone(), |creation| creation.target_name.clone()),
+        other => format!("{:?}", other),
+    };
+    let report = |debugger: &Debugger, stop: Stop| match stop {
+        Stop::Statement { line } | Stop::Breakpoint { line } => {
+            let at = if matches!(stop, Stop::Breakpoint { .. }) {
+                "Breakpoint, line"
+            } else {
+                "Line"
+            };
+            println!("{} {}", at, line);
+            if let Some(text) = lines.get(line - 1) {
+                println!("{:>5} | {}", line, text.trim_end());
+            }
+        }
+        Stop::Suspended(awaitable) => {
+            println!(
+                "Awaiting {} at line {}; give its result with `resume <json>`",
+                awaiting(debugger, &awaitable),
+                debugger.current_line().unwrap_or_default()
+            );
+        }
+        Stop::Finished => {
+            let ended = match debugger.control() {
+                Control::Return(val) => {
+                    println!("Returned {}", show(val));
+                    Some((ExecutionStatus::Completed, val))
+                }
+                Control::Throw(val) => {
+                    println!("Threw {}", show(val));
+                    Some((ExecutionStatus::Failed, val))
+                }
+                Control::None => {
+                    println!("Finished");
+                    Some((ExecutionStatus::Completed, &Val::Null))
+                }
+                _ => {
+                    println!("Finished");
+                    None
+                }
+            };
+            if let Some(execution) = recorded {
+                // Compared as values, as numbers may come back from the
+                // database written differently
+                let recorded_output = execution.output.as_ref().and_then(|o| json_to_val(o).ok());
+                let same = match ended {
+                    Some((status, val)) => {
+                        status == execution.status && recorded_output.as_ref() == Some(val)
+                    }
+                    None => false,
+                };
+                if !execution.status.is_terminal() {
+                    println!("The recorded run is still {}", execution.status.as_str());
+                } else if same {
+                    println!("Same as the recorded run");
+                } else {
+                    println!(
+                        "The recorded run ended {} with {}",
+                        execution.status.as_str(),
+                        execution.output.as_ref().unwrap_or(&JsonValue::Null)
+                    );
+                }
+            }
+        }
+    };
+    // Awaits the recorded run got past are settled as it was
+    let advance = |debugger: &mut Debugger, to_breakpoint: bool| loop {
+        let stop = if to_breakpoint {
+            debugger.continue_running()
+        } else {
+            debugger.step()
+        };
+        if let Stop::Suspended(awaitable) = &stop {
+            let line = debugger.current_line().unwrap_or_default();
+            if let Some(value) = debugger.resume_recorded() {
+                println!(
+                    "Awaiting {} at line {}; resumed with {} as recorded",
+                    awaiting(debugger, awaitable),
+                    line,
+                    show(&value)
+                );
+                continue;
+            }
+        }
+        return stop;
+    };
+
+    println!("{}; `help` lists commands", title);
+    if from_start {
+        let stop = advance(&mut debugger, false);
+        report(&debugger, stop);
+    } else {
+        report(&debugger, debugger.stop());
+    }
+
+    let stdin = std::io::stdin();
+    loop {
+        print!("(rhythm) ");
+        std::io::stdout().flush()?;
+        let mut input = String::new();
+        if stdin.lock().read_line(&mut input)? == 0 {
+            break;
+        }
+        let input = input.trim();
+        let (command, arg) = input.split_once(' ').unwrap_or((input, ""));
+        let arg = arg.trim();
+        if matches!(command, "q" | "quit") {
+            break;
+        }
+        let line_arg = || {
+            arg.parse::<usize>()
+                .with_context(|| format!("Not a line: {}", arg))
+        };
+
+        let result: Result<()> = (|| {
+            match command {
+                "" => {}
+                "s" | "step" => {
+                    let stop = advance(&mut debugger, false);
+                    report(&debugger, stop);
+                }
+                "c" | "continue" => {
+                    let stop = advance(&mut debugger, true);
+                    report(&debugger, stop);
+                }
+                "b" | "break" if arg.is_empty() => {
+                    let lines: Vec<String> =
+                        debugger.breakpoints().map(|l| l.to_string()).collect();
+                    println!("Breakpoints: {}", lines.join(", "));
+                }
+                "b" | "break" => debugger.set_breakpoint(line_arg()?),
+                "d" | "delete" => {
+                    if !debugger.clear_breakpoint(line_arg()?) {
+                        println!("No breakpoint on line {}", arg);
+                    }
+                }
+                "l" | "locals" => {
+                    for (name, val) in debugger.locals() {
+                        println!("{} = {}", name, show(val));
+                    }
+                }
+                "p" | "print" => match debugger.locals().get(arg).copied().or(debugger.global(arg))
+                {
+                    Some(val) => println!("{}", show(val)),
+                    None => println!("{} is not defined", arg),
+                },
+                "bt" | "frames" => {
+                    for frame in debugger.frames() {
+                        println!("{:>5} {} {}", frame.line, frame.kind, frame.pc);
+                    }
+                }
+                "r" | "resume" => {
+                    let value: JsonValue =
+                        serde_json::from_str(arg).context("Result is not valid JSON")?;
+                    if !debugger.resume(json_to_val(&value)?) {
+                        println!("Not stopped on an await");
+                        return Ok(());
+                    }
+                    let stop = advance(&mut debugger, false);
+                    report(&debugger, stop);
+                }
+                "save" => {
+                    let state = serde_json::to_string_pretty(&debugger.save()?)?;
+                    std::fs::write(arg, state)
+                        .with_context(|| format!("Failed to write {}", arg))?;
+                    println!("Saved to {}", arg);
+                }
+                "h" | "help" => println!("{}", DEBUG_HELP),
+                _ => println!("Unknown command {}; `help` lists commands", command),
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            println!("{:#}", e);
+        }
+    }
+    Ok(())
+}
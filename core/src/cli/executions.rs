@@ -0,0 +1,517 @@
+//! `rhythm executions`, `rhythm show`, `rhythm diff-executions` and
+//! `rhythm inspect-vm`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+
+use super::{open_app, parse_exec_type, parse_status, parse_tag, print_json_field};
+use crate::{ExecutionFilters, ExecutionPage, ExecutionStatus, ExecutionType, SortOrder};
+
+/// `rhythm executions` subcommands
+#[derive(Subcommand)]
+pub enum ExecutionsCommands {
+    /// List executions, newest first
+    ///
+    /// Prints the cursor for the next page when there are more.
+    List {
+        /// Only executions with this status, e.g. running, failed
+        #[arg(long, value_parser = parse_status)]
+        status: Option<ExecutionStatus>,
+
+        /// Only executions of this type: task, workflow or external
+        #[arg(long = "type", value_parser = parse_exec_type)]
+        exec_type: Option<ExecutionType>,
+
+        /// Only executions on this queue
+        #[arg(long)]
+        queue: Option<String>,
+
+        /// Only executions of this task or workflow
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Only children of this workflow
+        #[arg(long)]
+        parent: Option<String>,
+
+        /// Only executions with this tag, as KEY=VALUE; repeat to require more
+        #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+
+        /// Only executions created at or after this time (RFC 3339)
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+
+        /// Only executions created before this time (RFC 3339)
+        #[arg(long)]
+        until: Option<DateTime<Utc>>,
+
+        /// List oldest first
+        #[arg(long)]
+        oldest_first: bool,
+
+        /// Continue from a previous page's cursor
+        #[arg(long)]
+        cursor: Option<String>,
+
+        /// Most executions to list
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show an execution, the children it started and what it is waiting on
+    Show {
+        id: String,
+
+        /// Also show the VM steps of its last failed run, if it opted in to tracing
+        #[arg(long)]
+        vm_trace: bool,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Cancel a workflow and the unfinished executions it started
+    Cancel {
+        id: String,
+
+        /// Reason recorded in the cancellation error
+        #[arg(long, default_value = "Cancelled from the CLI")]
+        reason: String,
+
+        /// Let tasks a worker is running finish instead of cancelling them
+        #[arg(long)]
+        finish_running_tasks: bool,
+
+        /// Cancel the workflows it started first, level by level, letting
+        /// each run its compensations; running tasks are left to finish
+        #[arg(long, conflicts_with = "finish_running_tasks")]
+        child_first: bool,
+
+        /// With --child-first, seconds each level may take before it is
+        /// cancelled outright
+        #[arg(
+            long,
+            default_value_t = crate::worker::cancel::DEFAULT_LEVEL_TIMEOUT.as_secs(),
+            requires = "child_first"
+        )]
+        level_timeout_secs: u64,
+    },
+
+    /// Pause a workflow: its queued work isn't claimed until it is resumed
+    Pause { id: String },
+
+    /// Resume a paused workflow
+    Resume { id: String },
+
+    /// Add tags to an execution, replacing any with the same keys
+    Tag {
+        id: String,
+
+        /// Tag as KEY=VALUE; repeat for more
+        #[arg(value_name = "KEY=VALUE", value_parser = parse_tag, required = true)]
+        tags: Vec<(String, String)>,
+    },
+}
+
+/// Run a `rhythm executions` subcommand
+pub async fn run(config_path: Option<String>, command: ExecutionsCommands) -> Result<()> {
+    match command {
+        ExecutionsCommands::List {
+            status,
+            exec_type,
+            queue,
+            target,
+            parent,
+            tags,
+            since,
+            until,
+            oldest_first,
+            cursor,
+            limit,
+            json,
+        } => {
+            let filters = ExecutionFilters {
+                status,
+                exec_type,
+                queue,
+                target_name: target,
+                parent_workflow_id: parent,
+                created_after: since,
+                created_before: until,
+                order: if oldest_first {
+                    SortOrder::OldestFirst
+                } else {
+                    SortOrder::NewestFirst
+                },
+                after: cursor.as_deref().map(str::parse).transpose()?,
+                limit: Some(limit),
+                offset: None,
+                namespace: None,
+                tags: tags.into_iter().collect(),
+            };
+            list_executions(config_path, filters, json).await
+        }
+        ExecutionsCommands::Show { id, vm_trace, json } => {
+            show(config_path, &id, vm_trace, json).await
+        }
+        ExecutionsCommands::Cancel {
+            id,
+            reason,
+            finish_running_tasks,
+            child_first,
+            level_timeout_secs,
+        } => {
+            let app = open_app(config_path, false).await?;
+            let cancelled = if child_first {
+                app.execution_service
+                    .cancel_workflow_child_first(
+                        &id,
+                        &reason,
+                        std::time::Duration::from_secs(level_timeout_secs),
+                    )
+                    .await?
+            } else {
+                app.execution_service
+                    .cancel_workflow(&id, &reason, finish_running_tasks)
+                    .await?
+            };
+            if cancelled {
+                println!("Cancelled {}", id);
+            } else {
+                println!("{} had already finished", id);
+            }
+            Ok(())
+        }
+        ExecutionsCommands::Pause { id } => {
+            let app = open_app(config_path, false).await?;
+            if app.execution_service.pause_execution(&id).await? {
+                println!("Paused {}", id);
+            } else {
+                println!("{} was already paused", id);
+            }
+            Ok(())
+        }
+        ExecutionsCommands::Resume { id } => {
+            let app = open_app(config_path, false).await?;
+            if app.execution_service.resume_execution(&id).await? {
+                println!("Resumed {}", id);
+            } else {
+                println!("{} wasn't paused", id);
+            }
+            Ok(())
+        }
+        ExecutionsCommands::Tag { id, tags } => {
+            let app = open_app(config_path, false).await?;
+            app.execution_service
+                .tag_execution(&id, tags.into_iter().collect())
+                .await?;
+            println!("Tagged {}", id);
+            Ok(())
+        }
+    }
+}
+
+async fn list_executions(
+    config_path: Option<String>,
+    filters: ExecutionFilters,
+    json: bool,
+) -> Result<()> {
+    let app = open_app(config_path, true).await?;
+    let page = app.execution_service.list_executions(filters).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&page)?);
+        return Ok(());
+    }
+    let ExecutionPage { executions, cursor } = page;
+    if executions.is_empty() {
+        println!("No executions found");
+        return Ok(());
+    }
+
+    println!(
+        "{:<36} {:<8} {:<24} {:<12} {:<10} CREATED",
+        "ID", "TYPE", "TARGET", "QUEUE", "STATUS"
+    );
+    for execution in executions {
+        println!(
+            "{:<36} {:<8} {:<24} {:<12} {:<10} {}",
+            execution.id,
+            execution.exec_type.as_str(),
+            execution.target_name,
+            execution.queue,
+            execution.status.as_str(),
+            execution.created_at.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    if let Some(cursor) = cursor {
+        println!("More: --cursor {}", cursor);
+    }
+    Ok(())
+}
+
+/// Show an execution, the children it started and what it is waiting on
+pub async fn show(config_path: Option<String>, id: &str, vm_trace: bool, json: bool) -> Result<()> {
+    let app = open_app(config_path, true).await?;
+    let execution = app
+        .execution_service
+        .get_execution(id)
+        .await?
+        .with_context(|| format!("Execution not found: {}", id))?;
+    let trace = if vm_trace {
+        app.execution_service.get_vm_trace(id).await?
+    } else {
+        None
+    };
+    let tags = app.execution_service.get_execution_tags(id).await?;
+    let (children, suspended_on) = if execution.exec_type == ExecutionType::Workflow {
+        (
+            app.workflow_service.get_workflow_tasks(id).await?,
+            app.workflow_service.get_suspension_point(id).await?,
+        )
+    } else {
+        (Vec::new(), None)
+    };
+
+    if json {
+        let mut value = serde_json::to_value(&execution)?;
+        value["tags"] = serde_json::to_value(&tags)?;
+        if execution.exec_type == ExecutionType::Workflow {
+            value["children"] = serde_json::to_value(&children)?;
+            value["suspended_on"] = serde_json::to_value(&suspended_on)?;
+        }
+        if vm_trace {
+            value["vm_trace"] = serde_json::to_value(&trace)?;
+        }
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    println!("{} {}", execution.target_name, execution.id);
+    println!("  status:  {}", execution.status.as_str());
+    println!("  queue:   {}", execution.queue);
+    println!("  created: {}", execution.created_at);
+    let mut tags: Vec<_> = tags.into_iter().collect();
+    tags.sort();
+    for (key, value) in &tags {
+        println!("  tag:     {}={}", key, value);
+    }
+    if let Some(completed_at) = execution.completed_at {
+        println!("  done:    {}", completed_at);
+    }
+    print_json_field("inputs", &execution.inputs);
+    if let Some(output) = &execution.output {
+        let label = match execution.status {
+            ExecutionStatus::Failed | ExecutionStatus::Expired | ExecutionStatus::Cancelled => {
+                "error"
+            }
+            _ => "output",
+        };
+        print_json_field(label, output);
+    }
+    if let Some(suspended_on) = &suspended_on {
+        print_json_field("waiting", suspended_on);
+    }
+    if !children.is_empty() {
+        println!();
+        println!("Children ({}):", children.len());
+        for child in &children {
+            println!(
+                "  {:<36} {:<8} {:<24} {}",
+                child.id,
+                child.exec_type.as_str(),
+                child.target_name,
+                child.status.as_str()
+            );
+        }
+    }
+    if !vm_trace {
+        return Ok(());
+    }
+
+    println!();
+    let Some(trace) = trace else {
+        println!("VM tracing is not enabled for this execution");
+        return Ok(());
+    };
+    let (Some(recorded_at), Some(total)) = (trace.recorded_at, trace.total_steps) else {
+        println!("VM tracing enabled {}; no failed run yet", trace.enabled_at);
+        return Ok(());
+    };
+    println!(
+        "VM trace of the failed run at {} (last {} of {} steps):",
+        recorded_at,
+        trace.steps.len(),
+        total
+    );
+    for step in &trace.steps {
+        println!(
+            "  {:>6} {:indent$}{} {} @ {}:{}{}",
+            step.step,
+            "",
+            step.frame,
+            step.pc,
+            step.span.start_line + 1,
+            step.span.start_col + 1,
+            step.control
+                .as_ref()
+                .map_or(String::new(), |control| format!("  [{}]", control)),
+            indent = step.depth.saturating_sub(1) * 2
+        );
+    }
+    Ok(())
+}
+
+/// Compare two executions and the children each started
+pub async fn diff(config_path: Option<String>, left: &str, right: &str, json: bool) -> Result<()> {
+    use crate::execution_diff::{ChildDiff, FieldDiff};
+
+    let app = open_app(config_path, true).await?;
+    let diff = app.execution_service.diff_executions(left, right).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    let changes = |fields: &[FieldDiff]| -> Vec<String> {
+        let show = |value: &Option<serde_json::Value>| {
+            value
+                .as_ref()
+                .map_or("(absent)".to_string(), |v| v.to_string())
+        };
+        fields
+            .iter()
+            .flat_map(|field| {
+                field.changes.iter().map(move |change| {
+                    format!(
+                        "{}{}: {} -> {}",
+                        field.field,
+                        change.path,
+                        show(&change.left),
+                        show(&change.right)
+                    )
+                })
+            })
+            .collect()
+    };
+
+    println!(
+        "{} {} ({}) vs {} ({})",
+        diff.left.target_name,
+        diff.left.id,
+        diff.left.status.as_str(),
+        diff.right.id,
+        diff.right.status.as_str()
+    );
+    for change in changes(&diff.fields) {
+        println!("  {}", change);
+    }
+
+    match diff.first_divergence {
+        Some(index) => println!("\nChildren (first divergence at #{}):", index + 1),
+        None => println!("\nChildren (no divergence):"),
+    }
+    for (index, child) in diff.children.iter().enumerate() {
+        let (mark, name, notes) = match child {
+            ChildDiff::Matched { left, fields, .. } => (
+                if fields.is_empty() { "=" } else { "~" },
+                &left.target_name,
+                changes(fields),
+            ),
+            ChildDiff::OnlyLeft { execution } => (
+                "-",
+                &execution.target_name,
+                vec![format!("only in {}", diff.left.id)],
+            ),
+            ChildDiff::OnlyRight { execution } => (
+                "+",
+                &execution.target_name,
+                vec![format!("only in {}", diff.right.id)],
+            ),
+        };
+        println!("  {:>3} {} {}", index + 1, mark, name);
+        for note in notes {
+            println!("          {}", note);
+        }
+    }
+
+    if diff.is_same() {
+        println!("\nNo differences");
+    }
+    Ok(())
+}
+
+/// Show a workflow's saved VM state
+pub async fn inspect_vm(config_path: Option<String>, id: &str, json: bool) -> Result<()> {
+    use crate::executor::Awaitable;
+
+    let app = open_app(config_path, true).await?;
+    let inspection = app
+        .workflow_service
+        .inspect_vm(id)
+        .await?
+        .with_context(|| format!("No saved VM state for execution {}", id))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&inspection)?);
+        return Ok(());
+    }
+
+    println!("{} {}", inspection.workflow_name, inspection.execution_id);
+    match (inspection.line, &inspection.source_line) {
+        (Some(line), Some(text)) => {
+            println!("  at line {}", line);
+            println!("  {:>5} | {}", line, text);
+        }
+        (Some(line), None) => println!("  at line {}", line),
+        _ => println!("  finished"),
+    }
+    if let Some(awaiting) = &inspection.awaiting {
+        let name = |id: &str| {
+            inspection
+                .pending
+                .iter()
+                .find(|child| child.id == id)
+                .map_or_else(|| id.to_string(), |child| child.target_name.clone())
+        };
+        match serde_json::from_value::<Awaitable>(awaiting.clone()) {
+            Ok(Awaitable::Execution(id)) => println!("  waiting: {} ({})", name(&id), id),
+            Ok(Awaitable::Timer { fire_at }) => println!("  waiting: timer until {}", fire_at),
+            Ok(Awaitable::Signal { name, .. }) => println!("  waiting: signal {}", name),
+            _ => print_json_field("waiting", awaiting),
+        }
+    }
+
+    println!();
+    println!("Frames (innermost last):");
+    for frame in &inspection.frames {
+        println!("  {:>5} {} {}", frame.line, frame.kind, frame.pc);
+    }
+    if !inspection.locals.is_empty() {
+        println!();
+        println!("Locals:");
+        for (name, value) in &inspection.locals {
+            println!("  {} = {}", name, value);
+        }
+    }
+    if !inspection.pending.is_empty() {
+        println!();
+        println!("Pending ({}):", inspection.pending.len());
+        for child in &inspection.pending {
+            println!(
+                "  {:<36} {:<8} {:<24} {}",
+                child.id,
+                child.exec_type.as_str(),
+                child.target_name,
+                child.status.as_str()
+            );
+        }
+    }
+    Ok(())
+}
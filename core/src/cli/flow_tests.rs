@@ -0,0 +1,55 @@
+//! `rhythm test`
+
+use anyhow::Result;
+
+/// Run workflow unit tests (`*.flow.test` files) found under `paths`
+pub fn run(paths: &[std::path::PathBuf]) -> Result<()> {
+    use crate::flow_test;
+
+    let mut files = Vec::new();
+    for path in paths {
+        files.extend(flow_test::discover(path)?);
+    }
+    if files.is_empty() {
+        println!("No {} files found", flow_test::TEST_FILE_SUFFIX);
+        return Ok(());
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for file in files {
+        let report = flow_test::run_file(&file)?;
+        println!("{}", report.path.display());
+
+        let location = |span: Option<crate::executor::types::ast::Span>| match span {
+            Some(span) => format!(
+                "{}:{}:{}",
+                report.workflow.display(),
+                span.start_line + 1,
+                span.start_col + 1
+            ),
+            None => report.workflow.display().to_string(),
+        };
+        if let Some(error) = &report.error {
+            println!("  error {}: {}", location(error.span), error.message);
+            failed += 1;
+            continue;
+        }
+        for case in &report.cases {
+            match &case.failure {
+                None => println!("  ok    {}", case.name),
+                Some(failure) => {
+                    println!("  FAIL  {}", case.name);
+                    println!("        {}: {}", location(failure.span), failure.message);
+                }
+            }
+        }
+        passed += report.passed();
+        failed += report.failed();
+    }
+
+    println!("\n{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        anyhow::bail!("{} workflow test(s) failed", failed);
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! `rhythm maintenance`
+
+use anyhow::Result;
+use clap::Subcommand;
+use std::time::Duration;
+
+use super::{open_app, parse_duration};
+
+/// `rhythm maintenance` subcommands
+#[derive(Subcommand)]
+pub enum MaintenanceCommands {
+    /// Pause claims, then wait for in-flight work to finish
+    Enter {
+        /// How long claims stay paused unless exited earlier, e.g. 90s, 10m, 1h
+        #[arg(long, value_parser = parse_duration)]
+        duration: Duration,
+
+        /// Longest to wait for in-flight work (defaults to the duration)
+        #[arg(long, value_parser = parse_duration)]
+        wait: Option<Duration>,
+
+        /// Note shown by `maintenance status`
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// Resume claims
+    Exit,
+
+    /// Show the maintenance window and in-flight work
+    Status,
+}
+
+/// Run a `rhythm maintenance` subcommand
+pub async fn run(config_path: Option<String>, command: MaintenanceCommands) -> Result<()> {
+    let read_only = matches!(command, MaintenanceCommands::Status);
+    let service = open_app(config_path, read_only).await?.maintenance_service;
+
+    match command {
+        MaintenanceCommands::Enter {
+            duration,
+            wait,
+            reason,
+        } => {
+            let window = service.enter(duration, reason.as_deref()).await?;
+            println!("Claims paused until {}", window.expires_at);
+
+            let status = service.wait_for_drain(wait.unwrap_or(duration)).await?;
+            if status.window.is_none() {
+                anyhow::bail!("Maintenance window closed while waiting for in-flight work");
+            }
+            if status.in_flight > 0 {
+                anyhow::bail!(
+                    "Timed out with {} claims still in flight; claims stay paused until {}",
+                    status.in_flight,
+                    window.expires_at
+                );
+            }
+            println!("No work in flight; safe to proceed");
+        }
+        MaintenanceCommands::Exit => {
+            if service.exit().await? {
+                println!("Claims resumed");
+            } else {
+                println!("Not in maintenance");
+            }
+        }
+        MaintenanceCommands::Status => {
+            let status = service.status().await?;
+            match &status.window {
+                Some(window) => {
+                    println!("Claims paused until {}", window.expires_at);
+                    if let Some(reason) = &window.reason {
+                        println!("Reason: {}", reason);
+                    }
+                }
+                None => println!("Not in maintenance"),
+            }
+            println!("In-flight claims: {}", status.in_flight);
+        }
+    }
+    Ok(())
+}
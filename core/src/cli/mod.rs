@@ -0,0 +1,114 @@
+//! Commands of the `rhythm` binary
+//!
+//! The binary parses the command line and hands each command to the
+//! function here that runs it, one file per command group.
+
+use anyhow::Result;
+use serde_json::Value as JsonValue;
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
+
+use crate::{Application, ExecutionStatus, ExecutionType, InitBuilder};
+
+pub mod api_keys;
+pub mod debug;
+pub mod executions;
+pub mod flow_tests;
+pub mod maintenance;
+pub mod repl;
+pub mod rewrite;
+pub mod run_file;
+pub mod schema;
+#[cfg(feature = "dashboard")]
+pub mod serve;
+pub mod simulate;
+pub mod status;
+pub mod transfer;
+pub mod webhooks;
+pub mod workflows;
+
+/// Run database migrations
+pub async fn migrate() -> Result<()> {
+    let database_url = std::env::var("RHYTHM_DATABASE_URL")
+        .or_else(|_| std::env::var("DATABASE_URL"))
+        .expect("RHYTHM_DATABASE_URL or DATABASE_URL must be set");
+
+    println!("Running migrations against: {}", database_url);
+
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await?;
+
+    sqlx::migrate!("./migrations").run(&pool).await?;
+
+    println!("Migrations completed successfully");
+
+    Ok(())
+}
+
+/// Connect to the configured database without migrating or registering workflows
+pub(crate) async fn open_app(config_path: Option<String>, read_only: bool) -> Result<Application> {
+    let mut builder = InitBuilder::new().auto_migrate(false).read_only(read_only);
+    if let Some(path) = config_path {
+        builder = builder.config_path(path);
+    }
+    builder.init().await
+}
+
+/// Parse durations like `90s`, `10m`, `1h`, or bare seconds
+pub fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let (number, unit) = s
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((s, ""), |i| s.split_at(i));
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", s))?;
+    let secs = match unit {
+        "" | "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        _ => return Err(format!("invalid duration unit in {}; use s, m or h", s)),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+/// Parse an execution status, e.g. `running`
+pub fn parse_status(s: &str) -> std::result::Result<ExecutionStatus, String> {
+    serde_json::from_value(JsonValue::String(s.to_string()))
+        .map_err(|_| format!("unknown status: {}", s))
+}
+
+/// Parse an execution type: `task`, `workflow` or `external`
+pub fn parse_exec_type(s: &str) -> std::result::Result<ExecutionType, String> {
+    serde_json::from_value(JsonValue::String(s.to_string()))
+        .map_err(|_| format!("unknown execution type: {}", s))
+}
+
+/// Parse a task's output given as `NAME=JSON`
+pub fn parse_task_output(s: &str) -> std::result::Result<(String, JsonValue), String> {
+    let (name, output) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=JSON, got {}", s))?;
+    let output = serde_json::from_str(output)
+        .map_err(|e| format!("invalid JSON output for task {}: {}", name, e))?;
+    Ok((name.to_string(), output))
+}
+
+/// Parse a tag given as `KEY=VALUE`
+pub fn parse_tag(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got {}", s)),
+    }
+}
+
+/// Print a labelled JSON value, pretty-printed and indented under the label
+pub(crate) fn print_json_field(label: &str, value: &JsonValue) {
+    let pretty = serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string());
+    println!(
+        "  {:<9}{}",
+        format!("{}:", label),
+        pretty.replace('\n', "\n           ")
+    );
+}
@@ -0,0 +1,115 @@
+//! `rhythm repl`
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+
+const REPL_HELP: &str = "\
+  .stub NAME JSON     return JSON from task or workflow NAME; repeat to queue more
+  .fail NAME JSON     fail task or workflow NAME with the error JSON
+  .signal NAME JSON   deliver JSON to the next wait for signal NAME
+  .vars               show the variables declared so far
+  .exit               leave (or Ctrl-D)
+Anything else is Flow. An input ending in an expression prints its value;
+open brackets and strings continue on the next line.";
+
+/// Evaluate Flow statements and expressions interactively
+pub fn run(inputs: &str) -> Result<()> {
+    use crate::executor::Val;
+    use crate::repl::{needs_more, to_json, whole_numbers, Evaluation, Repl};
+    use std::io::{BufRead, Write};
+
+    let show = |val: &Val| match to_json(val) {
+        Ok(json) => json.to_string(),
+        Err(_) => format!("{:?}", val),
+    };
+
+    let inputs: JsonValue = serde_json::from_str(inputs).context("--inputs is not valid JSON")?;
+    let mut repl = Repl::new(&inputs)?;
+
+    println!("Flow REPL; `.help` lists commands");
+    let stdin = std::io::stdin();
+    let mut source = String::new();
+    loop {
+        print!(
+            "{}",
+            if source.is_empty() {
+                "flow> "
+            } else {
+                "  ... "
+            }
+        );
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+
+        if source.is_empty() && line.trim_start().starts_with('.') {
+            let line = line.trim();
+            let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let (name, json) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+            let value = || -> Result<JsonValue> {
+                serde_json::from_str(json).with_context(|| format!("Not valid JSON: {}", json))
+            };
+            if matches!(command, ".exit" | ".quit") {
+                break;
+            }
+            let result: Result<()> = (|| {
+                match command {
+                    ".stub" => repl.stub(name, value()?),
+                    ".fail" => repl.fail(name, value()?),
+                    ".signal" => repl.signal(name, value()?),
+                    ".vars" => {
+                        for (name, val) in repl.variables() {
+                            println!("{} = {}", name, show(val));
+                        }
+                    }
+                    ".help" => println!("{}", REPL_HELP),
+                    _ => println!("Unknown command {}; `.help` lists commands", command),
+                }
+                Ok(())
+            })();
+            if let Err(e) = result {
+                println!("{:#}", e);
+            }
+            continue;
+        }
+
+        source.push_str(&line);
+        if needs_more(&source) {
+            continue;
+        }
+        let input = std::mem::take(&mut source);
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        let started = repl.calls().len();
+        let evaluation = repl.eval(&input);
+        for call in &repl.calls()[started..] {
+            let settled = if call.stub.is_some() {
+                "stubbed"
+            } else {
+                "no stub, null"
+            };
+            let inputs = whole_numbers(call.inputs.clone());
+            println!("started {} {} ({})", call.target_name, inputs, settled);
+        }
+        for log in repl.take_logs() {
+            let level = format!("{:?}", log.level).to_lowercase();
+            match &log.data {
+                Some(data) => println!("[{}] {} {}", level, log.message, show(data)),
+                None => println!("[{}] {}", level, log.message),
+            }
+        }
+        match evaluation {
+            Ok(Evaluation::Value(value)) => println!("{}", value),
+            Ok(Evaluation::Done) => {}
+            Ok(Evaluation::Threw(error)) => println!("Uncaught {}", error),
+            Ok(Evaluation::Stopped(reason)) => println!("Stopped: {}", reason),
+            Err(e) => println!("{:#}", e),
+        }
+    }
+    Ok(())
+}
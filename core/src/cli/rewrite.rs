@@ -0,0 +1,113 @@
+//! `rhythm migrate-state` and `rhythm reencrypt`, which rewrite stored
+//! rows in place
+
+use anyhow::Result;
+
+use super::open_app;
+
+/// Rewrite saved workflow states in the current format
+pub async fn migrate_state(
+    config_path: Option<String>,
+    apply: bool,
+    batch_size: i64,
+    json: bool,
+) -> Result<()> {
+    if batch_size < 1 {
+        anyhow::bail!("--batch-size must be at least 1");
+    }
+    let app = open_app(config_path, !apply).await?;
+    let summary = app
+        .workflow_service
+        .rewrite_saved_states(apply, batch_size)
+        .await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        for failure in &summary.failed {
+            println!("FAIL  {}: {}", failure.execution_id, failure.error);
+        }
+        println!(
+            "{} {} of {} saved states ({} already current, {} saved again meanwhile){}",
+            if apply { "Rewrote" } else { "Would rewrite" },
+            summary.rewritten,
+            summary.scanned,
+            summary.current,
+            summary.skipped,
+            if apply {
+                ""
+            } else {
+                " (dry run; pass --apply to write)"
+            }
+        );
+    }
+
+    if !summary.failed.is_empty() {
+        anyhow::bail!("{} saved states could not be read", summary.failed.len());
+    }
+    Ok(())
+}
+
+/// Encrypt stored inputs, outputs and workflow states with the active key
+pub async fn reencrypt(
+    config_path: Option<String>,
+    apply: bool,
+    batch_size: i64,
+    json: bool,
+) -> Result<()> {
+    if batch_size < 1 {
+        anyhow::bail!("--batch-size must be at least 1");
+    }
+    let app = open_app(config_path, !apply).await?;
+    let Some(active_key) = app.config().encryption.active_key.clone() else {
+        anyhow::bail!("Encryption is not configured; set [encryption] active_key");
+    };
+    let payloads = app
+        .execution_service
+        .reencrypt_payloads(apply, batch_size)
+        .await?;
+    let states = app
+        .workflow_service
+        .rewrite_saved_states(apply, batch_size)
+        .await?;
+
+    if json {
+        let summary = serde_json::json!({ "executions": payloads, "states": states });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        for failure in &payloads.failed {
+            println!("FAIL  {}: {}", failure.execution_id, failure.error);
+        }
+        for failure in &states.failed {
+            println!("FAIL  {} (state): {}", failure.execution_id, failure.error);
+        }
+        let verb = if apply {
+            "Re-encrypted"
+        } else {
+            "Would re-encrypt"
+        };
+        println!(
+            "{} {} of {} executions and {} of {} saved states with key '{}'{}",
+            verb,
+            payloads.reencrypted,
+            payloads.scanned,
+            states.rewritten,
+            states.scanned,
+            active_key,
+            if apply {
+                ""
+            } else {
+                " (dry run; pass --apply to write)"
+            }
+        );
+    }
+
+    let failed = payloads.failed.len() + states.failed.len();
+    if failed > 0 {
+        anyhow::bail!(
+            "{} executions or saved states could not be decrypted",
+            failed
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! `rhythm run`
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+use std::time::Duration;
+
+use crate::{ExecutionStatus, InitBuilder};
+
+/// Run a workflow file to completion with an embedded worker
+pub async fn run(
+    config_path: Option<String>,
+    file: &std::path::Path,
+    inputs: &str,
+    tasks: Vec<(String, JsonValue)>,
+    timeout: Duration,
+) -> Result<()> {
+    use crate::worker::{DelegatedAction, TaskOutcome};
+    use crate::WorkflowFile;
+
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let name = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .with_context(|| format!("No workflow name in {}", file.display()))?
+        .to_string();
+    let inputs: JsonValue = serde_json::from_str(inputs).context("--inputs is not valid JSON")?;
+    let tasks: std::collections::HashMap<String, JsonValue> = tasks.into_iter().collect();
+
+    let mut builder = InitBuilder::new()
+        .auto_migrate(true)
+        .workflows(vec![WorkflowFile {
+            name: name.clone(),
+            source,
+            file_path: file.display().to_string(),
+        }]);
+    if let Some(path) = config_path {
+        builder = builder.config_path(path);
+    }
+    let app = builder.init().await?;
+
+    // A queue of its own keeps the embedded worker off everyone else's work
+    let queue = format!("rhythm-run-{}", uuid::Uuid::new_v4());
+    let id = app
+        .workflow_service
+        .start_workflow(&name, inputs, &queue, None)
+        .await?;
+    println!("Started {} {}", name, id);
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let execution = app
+            .execution_service
+            .get_execution(&id)
+            .await?
+            .with_context(|| format!("Execution {} disappeared", id))?;
+        let output = execution.output.unwrap_or(JsonValue::Null);
+        match execution.status {
+            ExecutionStatus::Completed => {
+                println!("{}", serde_json::to_string_pretty(&output)?);
+                return Ok(());
+            }
+            ExecutionStatus::Failed | ExecutionStatus::Expired | ExecutionStatus::Cancelled => {
+                println!("{}", serde_json::to_string_pretty(&output)?);
+                anyhow::bail!("Workflow {} {}", id, execution.status.as_str());
+            }
+            _ => {}
+        }
+        if tokio::time::Instant::now() >= deadline {
+            anyhow::bail!("Workflow {} still running after {:?}", id, timeout);
+        }
+
+        match app
+            .worker_service
+            .run_cooperative_worker_loop_on(&queue)
+            .await?
+        {
+            DelegatedAction::ExecuteTask {
+                execution_id,
+                target_name,
+                ..
+            } => {
+                let outcome = match tasks.get(&target_name) {
+                    Some(output) => TaskOutcome::Complete(output.clone()),
+                    None => TaskOutcome::Fail {
+                        error: serde_json::json!({
+                            "code": "NoTaskOutput",
+                            "message": format!("No output given for task {}; pass --task {}=JSON", target_name, target_name),
+                        }),
+                        retry: false,
+                    },
+                };
+                app.worker_service
+                    .record_outcome(&execution_id, outcome)
+                    .await?;
+            }
+            DelegatedAction::Continue => {}
+            DelegatedAction::Wait { duration_ms } => {
+                // Fire due timers now rather than on the internal worker's next poll
+                app.scheduler_service.process_ready_items(100).await?;
+                tokio::time::sleep(Duration::from_millis(duration_ms.min(100))).await;
+            }
+            DelegatedAction::Shutdown => anyhow::bail!("Worker shut down before {} finished", id),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! `rhythm schema`
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use super::open_app;
+
+/// `rhythm schema` subcommands
+#[derive(Subcommand)]
+pub enum SchemaCommands {
+    /// Show payloads whose latest shape differs from their baseline
+    Drift {
+        /// Only this target
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Accept a target's current payload shapes as its baseline
+    Accept {
+        /// Target name
+        target: String,
+    },
+}
+
+/// Run a `rhythm schema` subcommand
+pub async fn run(config_path: Option<String>, command: SchemaCommands) -> Result<()> {
+    use crate::payload_schema::ShapeChange;
+
+    let read_only = matches!(command, SchemaCommands::Drift { .. });
+    let service = open_app(config_path, read_only).await?.schema_service;
+
+    match command {
+        SchemaCommands::Drift { target, json } => {
+            let drift = service.drift(target.as_deref()).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&drift)?);
+                return Ok(());
+            }
+            if drift.is_empty() {
+                println!("No drift");
+                return Ok(());
+            }
+            for payload in drift {
+                println!(
+                    "{} {} (baseline {}, last seen {}, {} samples)",
+                    payload.shapes.target_name,
+                    payload.shapes.kind.as_str(),
+                    payload.shapes.first_seen_at,
+                    payload.shapes.last_seen_at,
+                    payload.shapes.samples
+                );
+                for change in payload.changes {
+                    match change {
+                        ShapeChange::Added { path, shape } => println!("  + {}: {}", path, shape),
+                        ShapeChange::Missing { path, shape } => {
+                            println!("  - {}: {}", path, shape)
+                        }
+                        ShapeChange::TypeChanged { path, from, to } => {
+                            println!("  ~ {}: {} -> {}", path, from, to)
+                        }
+                    }
+                }
+            }
+        }
+        SchemaCommands::Accept { target } => {
+            let accepted = service.accept(&target).await?;
+            println!("Accepted {} shape(s) for {}", accepted, target);
+        }
+    }
+    Ok(())
+}
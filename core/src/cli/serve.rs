@@ -0,0 +1,22 @@
+//! `rhythm serve`
+
+use anyhow::Result;
+
+use super::open_app;
+
+/// Serve the read-only web dashboard until Ctrl-C
+pub async fn run(addr: std::net::SocketAddr, config_path: Option<String>) -> Result<()> {
+    let app = std::sync::Arc::new(open_app(config_path, true).await?);
+
+    let shutdown = app.shutdown_token.clone();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            let _ = tokio::signal::ctrl_c().await;
+            shutdown.cancel();
+        }
+    });
+
+    println!("Serving dashboard on http://{}", addr);
+    crate::dashboard::serve(app, addr, shutdown).await
+}
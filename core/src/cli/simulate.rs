@@ -0,0 +1,64 @@
+//! `rhythm simulate`
+
+use anyhow::{Context, Result};
+
+use crate::InitBuilder;
+
+/// Run a randomized, faulty workload in a temporary database
+pub async fn run(
+    config_path: Option<String>,
+    simulation: &crate::simulation::SimulationConfig,
+    json: bool,
+) -> Result<()> {
+    let config = crate::config::Config::builder()
+        .config_path(config_path.map(std::path::PathBuf::from))
+        .build()?;
+    let url = config
+        .database
+        .url
+        .clone()
+        .context("No database URL configured")?;
+
+    let database = crate::simulation::TempDatabase::create(&url).await?;
+    let result = async {
+        let app = InitBuilder::new()
+            .config(config)
+            .pool(database.pool.clone())
+            .init()
+            .await?;
+        crate::simulation::run(&app, simulation).await
+    }
+    .await;
+    database.drop_database().await?;
+    let report = result?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("seed {}", report.seed);
+        println!("  workflows:     {}", report.workflows_started);
+        println!(
+            "  tasks run:     {} ({} failed)",
+            report.tasks_run, report.tasks_failed
+        );
+        println!("  crashes:       {}", report.crashes);
+        println!(
+            "  stale reports: {} ({} accepted)",
+            report.stale_reports, report.stale_reports_accepted
+        );
+        println!("  signals:       {}", report.signals_sent);
+        println!("  cancelled:     {}", report.workflows_cancelled);
+        for violation in &report.violations {
+            println!("  VIOLATION {}", violation);
+        }
+    }
+
+    if !report.is_ok() {
+        anyhow::bail!(
+            "{} invariant violation(s); replay with --seed {}",
+            report.violations.len(),
+            report.seed
+        );
+    }
+    Ok(())
+}
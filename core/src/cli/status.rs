@@ -0,0 +1,66 @@
+//! `rhythm slo` and `rhythm groups`
+
+use anyhow::Result;
+
+use super::open_app;
+
+/// Show compliance with the configured claim latency SLOs
+pub async fn slo(config_path: Option<String>, json: bool) -> Result<()> {
+    let app = open_app(config_path, true).await?;
+    let statuses = app.slo_service.evaluate().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&statuses)?);
+        return Ok(());
+    }
+    if statuses.is_empty() {
+        println!("No SLOs configured; add [[slos]] entries to rhythm.toml");
+        return Ok(());
+    }
+
+    println!(
+        "{:<32} {:>10} {:>10} {:>8} {:>10}  STATUS",
+        "SLO", "COMPLIANCE", "OBJECTIVE", "BUDGET", "MEASURED"
+    );
+    for status in statuses {
+        println!(
+            "{:<32} {:>9.2}% {:>9.2}% {:>7.0}% {:>10}  {}",
+            status.slo.name,
+            status.compliance * 100.0,
+            status.slo.objective * 100.0,
+            status.error_budget_remaining * 100.0,
+            status.counts.total,
+            if status.met { "ok" } else { "MISSED" }
+        );
+    }
+    Ok(())
+}
+
+/// Show how full each concurrency group is
+pub async fn groups(config_path: Option<String>, json: bool) -> Result<()> {
+    let app = open_app(config_path, true).await?;
+    let groups = app.worker_service.group_occupancy().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+    if groups.is_empty() {
+        println!(
+            "No concurrency groups configured; add [[concurrency_groups]] entries to rhythm.toml"
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{:<32} {:>8} {:>8} {:>8}",
+        "GROUP", "RUNNING", "MAX", "WAITING"
+    );
+    for group in groups {
+        println!(
+            "{:<32} {:>8} {:>8} {:>8}",
+            group.name, group.running, group.max_running, group.waiting
+        );
+    }
+    Ok(())
+}
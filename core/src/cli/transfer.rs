@@ -0,0 +1,51 @@
+//! `rhythm export` and `rhythm import`
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::open_app;
+
+/// Export executions, their saved states and workflow definitions as NDJSON
+pub async fn export(
+    config_path: Option<String>,
+    since: Option<DateTime<Utc>>,
+    out: Option<&std::path::Path>,
+) -> Result<()> {
+    let app = open_app(config_path, true).await?;
+    let summary = match out {
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let mut writer = std::io::BufWriter::new(file);
+            app.transfer_service.export(since, &mut writer).await?
+        }
+        None => {
+            let mut writer = std::io::BufWriter::new(std::io::stdout().lock());
+            app.transfer_service.export(since, &mut writer).await?
+        }
+    };
+
+    // Keep stdout for the export itself
+    eprintln!(
+        "Exported {} executions, {} saved states and {} workflow definitions",
+        summary.executions, summary.contexts, summary.workflow_definitions
+    );
+    Ok(())
+}
+
+/// Import a file written by `export`, skipping records already present
+pub async fn import(config_path: Option<String>, file: &std::path::Path) -> Result<()> {
+    let app = open_app(config_path, false).await?;
+    let input =
+        std::fs::File::open(file).with_context(|| format!("Failed to open {}", file.display()))?;
+    let summary = app
+        .transfer_service
+        .import(std::io::BufReader::new(input))
+        .await?;
+
+    println!(
+        "Imported {} executions, {} saved states and {} workflow definitions ({} already present)",
+        summary.executions, summary.contexts, summary.workflow_definitions, summary.skipped
+    );
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! `rhythm webhooks`
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use super::open_app;
+use crate::{CreateWebhookParams, WebhookEvent};
+
+/// `rhythm webhooks` subcommands
+#[derive(Subcommand)]
+pub enum WebhooksCommands {
+    /// Register a webhook
+    Add {
+        /// Endpoint to POST notifications to
+        url: String,
+
+        /// Key the payloads are signed with
+        #[arg(long)]
+        secret: String,
+
+        /// Status to notify: completed, failed or cancelled; repeat for more
+        #[arg(long = "event", required = true)]
+        events: Vec<WebhookEvent>,
+
+        /// Only executions on this queue
+        #[arg(long)]
+        queue: Option<String>,
+
+        /// Only executions of this task or workflow
+        #[arg(long)]
+        target: Option<String>,
+    },
+
+    /// List registered webhooks
+    List {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Remove a webhook and its undelivered notifications
+    Remove { id: String },
+
+    /// Show a webhook's recent deliveries, newest first
+    Deliveries {
+        id: String,
+
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Send a delivery that ran out of attempts again
+    Redeliver { delivery_id: i64 },
+}
+
+/// Run a `rhythm webhooks` subcommand
+pub async fn run(config_path: Option<String>, command: WebhooksCommands) -> Result<()> {
+    let read_only = matches!(
+        command,
+        WebhooksCommands::List { .. } | WebhooksCommands::Deliveries { .. }
+    );
+    let service = open_app(config_path, read_only).await?.webhook_service;
+
+    match command {
+        WebhooksCommands::Add {
+            url,
+            secret,
+            events,
+            queue,
+            target,
+        } => {
+            let webhook = service
+                .register(CreateWebhookParams {
+                    url,
+                    secret,
+                    events,
+                    queue,
+                    target_name: target,
+                })
+                .await?;
+            println!("Registered webhook {}", webhook.id);
+        }
+        WebhooksCommands::List { json } => {
+            let webhooks = service.list().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&webhooks)?);
+                return Ok(());
+            }
+            if webhooks.is_empty() {
+                println!("No webhooks registered");
+                return Ok(());
+            }
+            println!(
+                "{:<36}  {:<28} {:<16} {:<16} URL",
+                "ID", "EVENTS", "QUEUE", "TARGET"
+            );
+            for webhook in webhooks {
+                let events: Vec<&str> = webhook.events.iter().map(|e| e.as_str()).collect();
+                println!(
+                    "{:<36}  {:<28} {:<16} {:<16} {}",
+                    webhook.id,
+                    events.join(","),
+                    webhook.queue.as_deref().unwrap_or("*"),
+                    webhook.target_name.as_deref().unwrap_or("*"),
+                    webhook.url
+                );
+            }
+        }
+        WebhooksCommands::Remove { id } => {
+            service.remove(&id).await?;
+            println!("Removed webhook {}", id);
+        }
+        WebhooksCommands::Deliveries { id, limit, json } => {
+            let deliveries = service.deliveries(&id, limit).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&deliveries)?);
+                return Ok(());
+            }
+            if deliveries.is_empty() {
+                println!("No deliveries");
+                return Ok(());
+            }
+            println!(
+                "{:>8}  {:<36}  {:<10} {:<10} {:>8}  LAST ERROR",
+                "ID", "EXECUTION", "EVENT", "STATUS", "ATTEMPTS"
+            );
+            for delivery in deliveries {
+                println!(
+                    "{:>8}  {:<36}  {:<10} {:<10} {:>8}  {}",
+                    delivery.id,
+                    delivery.execution_id,
+                    delivery.event.as_str(),
+                    delivery.status.as_str(),
+                    delivery.attempts,
+                    delivery.last_error.as_deref().unwrap_or("")
+                );
+            }
+        }
+        WebhooksCommands::Redeliver { delivery_id } => {
+            service.redeliver(delivery_id).await?;
+            println!("Queued delivery {} to be sent again", delivery_id);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,181 @@
+//! `rhythm workflow` and `rhythm graph`
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+
+use super::open_app;
+
+/// `rhythm workflow` subcommands
+#[derive(Subcommand)]
+pub enum WorkflowCommands {
+    /// Print a workflow's AST as canonical JSON
+    DumpAst {
+        /// Path to the .flow file
+        file: std::path::PathBuf,
+    },
+
+    /// Move suspended executions to the workflow's latest registered definition
+    ///
+    /// Dry run unless --apply is given.
+    Migrate {
+        /// Workflow name
+        name: String,
+
+        /// TOML file mapping old suspension points to new ones
+        #[arg(long)]
+        plan: std::path::PathBuf,
+
+        /// Write the migrated state instead of only reporting
+        #[arg(long)]
+        apply: bool,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show a workflow's settings: its front matter over `[workflow_defaults]`
+    EffectiveConfig {
+        /// Workflow name
+        name: String,
+
+        /// Print JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Run a `rhythm workflow` subcommand
+pub async fn run(config_path: Option<String>, command: WorkflowCommands) -> Result<()> {
+    match command {
+        WorkflowCommands::DumpAst { file } => dump_ast(&file),
+        WorkflowCommands::Migrate {
+            name,
+            plan,
+            apply,
+            json,
+        } => migrate(config_path, &name, &plan, apply, json).await,
+        WorkflowCommands::EffectiveConfig { name, json } => {
+            effective_config(config_path, &name, json).await
+        }
+    }
+}
+
+fn dump_ast(file: &std::path::Path) -> Result<()> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let workflow = crate::parser::parse_workflow(&source)
+        .map_err(|e| anyhow::anyhow!("{}: {}", file.display(), e))?;
+    print!("{}", crate::parser::to_canonical_json(&workflow));
+    Ok(())
+}
+
+/// Print a workflow file's task graph, as JSON or Graphviz DOT
+pub fn graph(file: &std::path::Path, dot: bool) -> Result<()> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let workflow = crate::parser::parse_workflow(&source)
+        .map_err(|e| anyhow::anyhow!("{}: {}", file.display(), e))?;
+    let graph = crate::parser::task_graph::task_graph(&workflow);
+    if dot {
+        let name = file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("workflow");
+        print!("{}", graph.to_dot(name));
+    } else {
+        println!("{}", serde_json::to_string_pretty(&graph)?);
+    }
+    Ok(())
+}
+
+async fn migrate(
+    config_path: Option<String>,
+    name: &str,
+    plan_path: &std::path::Path,
+    apply: bool,
+    json: bool,
+) -> Result<()> {
+    let text = std::fs::read_to_string(plan_path)
+        .with_context(|| format!("Failed to read {}", plan_path.display()))?;
+    let plan: crate::executor::migrate::MigrationPlan = toml::from_str(&text)
+        .with_context(|| format!("Failed to parse {}", plan_path.display()))?;
+
+    let app = open_app(config_path, !apply).await?;
+    let reports = app
+        .workflow_service
+        .migrate_executions(name, &plan, apply)
+        .await?;
+    let failed = reports.iter().filter(|r| !r.is_ok()).count();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        let line = |l: Option<usize>| l.map_or("?".to_string(), |l| l.to_string());
+        for report in &reports {
+            match &report.error {
+                None => println!(
+                    "ok    {}  line {} -> {}",
+                    report.execution_id,
+                    line(report.from_line),
+                    line(report.to_line)
+                ),
+                Some(error) => println!(
+                    "FAIL  {}  line {}: {}",
+                    report.execution_id,
+                    line(report.from_line),
+                    error
+                ),
+            }
+        }
+        println!(
+            "{} {} of {} suspended executions{}",
+            if apply { "Migrated" } else { "Would migrate" },
+            reports.len() - failed,
+            reports.len(),
+            if apply {
+                ""
+            } else {
+                " (dry run; pass --apply to write)"
+            }
+        );
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} executions could not be migrated", failed);
+    }
+    Ok(())
+}
+
+async fn effective_config(config_path: Option<String>, name: &str, json: bool) -> Result<()> {
+    let app = open_app(config_path, true).await?;
+    let Some(config) = app.workflow_service.effective_config(name).await? else {
+        anyhow::bail!("Workflow '{}' is not registered", name);
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+
+    let settings = crate::parser::front_matter::flatten(&config.settings);
+    if settings.is_empty() {
+        println!("{} has no settings", config.name);
+        return Ok(());
+    }
+    let width = settings.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, value) in &settings {
+        let origin = if config.from_front_matter.contains(key) {
+            "front matter"
+        } else {
+            "workflow_defaults"
+        };
+        println!(
+            "{:<width$}  {:<20}  ({})",
+            key,
+            value.to_string(),
+            origin,
+            width = width
+        );
+    }
+    Ok(())
+}
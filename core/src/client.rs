@@ -4,14 +4,26 @@
 //! Application singleton and provides static methods that delegate to services.
 //!
 //! Language adapters (Python, Node.js, etc.) should ONLY call Client methods.
+//! Rust embedders that need several instances in one process build
+//! `Application`s directly with `InitBuilder` instead.
 
 use anyhow::{anyhow, Context, Result};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::sync::OnceLock;
 use tokio::sync::Mutex;
 
 use crate::application::{Application, WorkflowFile};
-use crate::types::{CreateExecutionParams, ScheduleExecutionParams};
+use crate::auth::Scope;
+use crate::config::TaskConfig;
+use crate::parser::input_schema::InputSchema;
+use crate::parser::task_graph::TaskGraph;
+use crate::types::{
+    ConcurrencyKey, CreateExecutionParams, CreateWebhookParams, Execution, ExecutionChanges,
+    ExecutionEvent, ExecutionFilters, ExecutionLog, ExecutionPage, LogLevel, NamespaceUsage,
+    QueueStats, ScheduleExecutionParams, SloStatus, Webhook,
+};
+use crate::worker::{DrainReport, TaskCompletion, TaskDispatcher};
 
 /// Global application instance (ONLY place with static state)
 static APP: OnceLock<Application> = OnceLock::new();
@@ -29,11 +41,19 @@ impl Client {
     ///
     /// Handles bootstrap → instantiation → initialization → storage
     /// Thread-safe: uses mutex to prevent concurrent initialization
+    ///
+    /// With `read_only`, queries work but every operation that changes state
+    /// fails with `ReadOnly`; migrations and workflow registration are skipped.
+    /// The `[auth] api_key` configured, if any, is checked here, and later
+    /// calls are refused unless its scopes allow them (see `auth`).
+    /// `tasks` are task settings declared in the host's code.
     pub async fn initialize(
         database_url: Option<String>,
         config_path: Option<String>,
         auto_migrate: bool,
         workflows: Vec<WorkflowFile>,
+        tasks: Vec<TaskConfig>,
+        read_only: bool,
     ) -> Result<()> {
         // Acquire lock to prevent concurrent initialization
         let _guard = INIT_LOCK.lock().await;
@@ -44,15 +64,24 @@ impl Client {
         }
 
         // Delegate to application::initialize for all the complex work
-        let app = crate::application::initialize(crate::application::InitOptions {
+        let mut app = crate::application::initialize(crate::application::InitOptions {
             database_url,
             config_path,
             auto_migrate,
             workflows,
+            tasks,
+            read_only,
+            ..Default::default()
         })
         .await
         .context("Failed to initialize application")?;
 
+        // Every call is checked against the key presented now
+        if let Some(key) = app.config.auth.api_key.clone() {
+            let api_key = app.api_key_service.authenticate(&key).await?;
+            app.set_api_key(api_key);
+        }
+
         // Store the singleton
         APP.set(app)
             .map_err(|_| anyhow!("Application already initialized"))?;
@@ -69,30 +98,206 @@ impl Client {
 
     /// Create a new execution and enqueue it for processing
     pub async fn create_execution(params: CreateExecutionParams) -> Result<String> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Enqueue, "create_execution")?;
         app.execution_service.create_execution(params).await
     }
 
     /// Get execution by ID
-    pub async fn get_execution(execution_id: String) -> Result<Option<JsonValue>> {
-        let app = Self::get_app()?;
-        let execution = app.execution_service.get_execution(&execution_id).await?;
-        Ok(execution.map(|e| serde_json::to_value(e).unwrap()))
+    ///
+    /// Returns the typed `Execution` so adapters can map fields directly
+    /// instead of re-parsing a JSON document.
+    pub async fn get_execution(execution_id: String) -> Result<Option<Execution>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_execution")?;
+        app.execution_service.get_execution(&execution_id).await
+    }
+
+    /// Get an execution's history (created, claimed, suspended, ...), oldest first
+    pub async fn get_execution_history(execution_id: String) -> Result<Vec<ExecutionEvent>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_execution_history")?;
+        app.execution_service
+            .get_execution_history(&execution_id)
+            .await
+    }
+
+    /// Get an execution's logs, in the order they were written
+    pub async fn get_execution_logs(execution_id: String) -> Result<Vec<ExecutionLog>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_execution_logs")?;
+        app.execution_service
+            .get_execution_logs(&execution_id)
+            .await
+    }
+
+    /// Trace the VM steps of a workflow's runs, saving them if a run fails
+    ///
+    /// For debugging interpreter-level issues; see `rhythm show --vm-trace`.
+    pub async fn enable_vm_trace(execution_id: String) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Admin, "enable_vm_trace")?;
+        app.execution_service.enable_vm_trace(&execution_id).await
+    }
+
+    /// Record what a workflow's runs read, so it can be re-run locally
+    ///
+    /// For reproducing production failures; see `rhythm replay`.
+    pub async fn enable_recording(execution_id: String) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Admin, "enable_recording")?;
+        app.execution_service.enable_recording(&execution_id).await
+    }
+
+    /// Get executions whose status changed since a cursor
+    ///
+    /// Cheap polling for dashboards: pass `None` first, then the `cursor` from
+    /// the previous result.
+    pub async fn get_changes(
+        since_cursor: Option<String>,
+        limit: Option<i64>,
+    ) -> Result<ExecutionChanges> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_changes")?;
+        app.execution_service
+            .get_changes(since_cursor.as_deref(), limit)
+            .await
+    }
+
+    /// One page of executions matching `filters`, with the cursor for the next
+    pub async fn list_executions(filters: ExecutionFilters) -> Result<ExecutionPage> {
+        let app = Self::get_app_for(Scope::ReadOnly, "list_executions")?;
+        app.execution_service.list_executions(filters).await
+    }
+
+    /// Current usage counted against a namespace's quotas
+    pub async fn get_namespace_usage(namespace: String) -> Result<NamespaceUsage> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_namespace_usage")?;
+        app.execution_service.get_namespace_usage(&namespace).await
+    }
+
+    /// Load on each queue, with throughput over the last `window_minutes`
+    /// (default 5), for scaling workers
+    pub async fn get_queue_stats(window_minutes: Option<u32>) -> Result<Vec<QueueStats>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_queue_stats")?;
+        app.execution_service.queue_stats(window_minutes).await
+    }
+
+    /// Current compliance of the configured claim latency SLOs
+    pub async fn get_slo_status() -> Result<Vec<SloStatus>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_slo_status")?;
+        app.slo_service.evaluate().await
     }
 
     /// Complete an execution with a result
     pub async fn complete_execution(execution_id: String, result: JsonValue) -> Result<()> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Worker, "complete_execution")?;
         app.worker_service
             .complete_work(&execution_id, Some(result), None)
             .await
     }
 
     /// Fail an execution with an error
-    pub async fn fail_execution(execution_id: String, error: JsonValue) -> Result<()> {
-        let app = Self::get_app()?;
+    ///
+    /// With `retry`, the task is re-queued while it has retries left, unless
+    /// its `[[task_configs]]` rules give up on the error's code.
+    pub async fn fail_execution(execution_id: String, error: JsonValue, retry: bool) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Worker, "fail_execution")?;
         app.worker_service
-            .complete_work(&execution_id, None, Some(error))
+            .fail_work(&execution_id, error, retry)
+            .await?;
+        Ok(())
+    }
+
+    /// Record that a task is still running and extend its claim
+    ///
+    /// Hosts running a task outside `run_host_loop` call this periodically
+    /// (well inside the one-minute claim lease); otherwise the reaper takes
+    /// the task back once its heartbeat is older than the configured
+    /// timeout. `worker_id` defaults to the configured worker id. Returns
+    /// `false` once the task is no longer this worker's to run.
+    pub async fn heartbeat(execution_id: String, worker_id: Option<String>) -> Result<bool> {
+        let app = Self::get_writable_app(Scope::Worker, "heartbeat")?;
+        app.worker_service
+            .heartbeat(&execution_id, worker_id.as_deref())
+            .await
+    }
+
+    /// Cancel a workflow and every unfinished execution it started
+    ///
+    /// With `finish_running_tasks`, tasks a worker is running are left to
+    /// finish. Returns `false` if the workflow had already finished.
+    pub async fn cancel_workflow(
+        execution_id: String,
+        reason: String,
+        finish_running_tasks: bool,
+    ) -> Result<bool> {
+        let app = Self::get_writable_app(Scope::Admin, "cancel_workflow")?;
+        app.execution_service
+            .cancel_workflow(&execution_id, &reason, finish_running_tasks)
+            .await
+    }
+
+    /// Pause a workflow, holding its queued work until it is resumed
+    ///
+    /// Returns `false` if it was already paused.
+    pub async fn pause_execution(execution_id: String) -> Result<bool> {
+        let app = Self::get_writable_app(Scope::Admin, "pause_execution")?;
+        app.execution_service.pause_execution(&execution_id).await
+    }
+
+    /// Resume a paused workflow
+    ///
+    /// Returns `false` if it wasn't paused.
+    pub async fn resume_execution(execution_id: String) -> Result<bool> {
+        let app = Self::get_writable_app(Scope::Admin, "resume_execution")?;
+        app.execution_service.resume_execution(&execution_id).await
+    }
+
+    /// Add tags to an execution, replacing any with the same keys
+    pub async fn tag_execution(execution_id: String, tags: HashMap<String, String>) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Enqueue, "tag_execution")?;
+        app.execution_service
+            .tag_execution(&execution_id, tags)
+            .await
+    }
+
+    /// An execution's tags
+    pub async fn get_execution_tags(execution_id: String) -> Result<HashMap<String, String>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_execution_tags")?;
+        app.execution_service
+            .get_execution_tags(&execution_id)
+            .await
+    }
+
+    /// Record a partial result of a running task, for `Task.stream`
+    pub async fn emit_partial_result(execution_id: String, chunk: JsonValue) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Worker, "emit_partial_result")?;
+        app.worker_service
+            .emit_partial_result(&execution_id, chunk)
+            .await?;
+        Ok(())
+    }
+
+    /// Write a message to an execution's logs, e.g. from a task handler
+    pub async fn append_log(
+        execution_id: String,
+        level: LogLevel,
+        message: String,
+        data: Option<JsonValue>,
+    ) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Worker, "append_log")?;
+        app.worker_service
+            .append_log(&execution_id, level, &message, data)
+            .await
+    }
+
+    /// Complete an external task using the token from `ExternalTask.create`
+    pub async fn complete_external_task(token: String, result: JsonValue) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Worker, "complete_external_task")?;
+        app.execution_service
+            .complete_external_task(&token, result)
+            .await
+    }
+
+    /// Fail an external task using the token from `ExternalTask.create`
+    pub async fn fail_external_task(token: String, error: JsonValue) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Worker, "fail_external_task")?;
+        app.execution_service
+            .fail_external_task(&token, error)
             .await
     }
 
@@ -107,33 +312,155 @@ impl Client {
     /// Only returns when it has a task that needs to be executed by the host.
     /// Queue is hardcoded to "default".
     pub async fn run_cooperative_worker_loop() -> Result<JsonValue> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Worker, "run_cooperative_worker_loop")?;
         let action = app.worker_service.run_cooperative_worker_loop().await?;
         Ok(serde_json::to_value(action)?)
     }
 
+    /// Claim up to `max_count` tasks from `queues` at once
+    ///
+    /// For hosts that run tasks on a pool of threads: one claim fills the
+    /// pool, and `complete_executions` reports the results back. Workflows
+    /// claimed along the way are run in core. Returns a JSON list of
+    /// `{execution_id, target_name, inputs}`, empty when there is no work.
+    pub async fn claim_executions(
+        worker_id: Option<String>,
+        queues: Vec<String>,
+        max_count: usize,
+    ) -> Result<JsonValue> {
+        let app = Self::get_writable_app(Scope::Worker, "claim_executions")?;
+        let tasks = app
+            .worker_service
+            .claim_executions(worker_id.as_deref(), &queues, max_count)
+            .await?;
+        Ok(serde_json::to_value(tasks)?)
+    }
+
+    /// Record the outcomes of several tasks at once
+    ///
+    /// For hosts that run tasks on a pool of threads and report them back in
+    /// batches instead of one `complete_execution` call each. The final
+    /// outcomes are recorded, and their workflows woken, in one transaction.
+    pub async fn complete_executions(completions: Vec<TaskCompletion>) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Worker, "complete_executions")?;
+        app.worker_service.record_outcomes(completions).await
+    }
+
+    /// Run a worker on `queues` until shutdown, calling back into the host
+    /// only to run tasks
+    ///
+    /// Claiming, workflows, heartbeats for running tasks and recording task
+    /// outcomes all happen in core. An empty `queues` means "default".
+    pub async fn run_worker(
+        queues: Vec<String>,
+        dispatcher: &mut dyn TaskDispatcher,
+    ) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Worker, "run_worker")?;
+        app.worker_service.run_host_loop(&queues, dispatcher).await
+    }
+
     /// Request graceful shutdown of worker loops
     ///
     /// Triggers the shutdown token, causing all active worker loops to
     /// exit gracefully on their next iteration (~100ms latency).
     pub fn request_shutdown() -> Result<()> {
-        let app = Self::get_app()?;
+        let app = Self::get_app_for(Scope::Worker, "request_shutdown")?;
         app.request_shutdown();
         Ok(())
     }
 
+    /// Stop claiming new work, leaving tasks already handed out running
+    ///
+    /// The first step of a drain; follow with `wait_idle`, or use
+    /// `drain_worker` to also release tasks still running at a deadline.
+    pub fn begin_shutdown() -> Result<()> {
+        let app = Self::get_app_for(Scope::Worker, "begin_shutdown")?;
+        app.worker_service.worker().begin_shutdown();
+        Ok(())
+    }
+
+    /// Wait up to `timeout` until no task handed to the host is unreported
+    ///
+    /// Returns whether the worker went idle.
+    pub async fn wait_idle(timeout: std::time::Duration) -> Result<bool> {
+        let app = Self::get_app_for(Scope::Worker, "wait_idle")?;
+        Ok(app.worker_service.worker().wait_idle(timeout).await)
+    }
+
+    /// Stop claiming, wait up to `timeout` for in-flight tasks, then put the
+    /// rest back on their queues for another worker
+    pub async fn drain_worker(timeout: std::time::Duration) -> Result<DrainReport> {
+        let app = Self::get_writable_app(Scope::Worker, "drain_worker")?;
+        app.worker_service.worker().shutdown(timeout).await
+    }
+
     /* ===================== Workflow Operations ===================== */
 
     /// Start a workflow execution
+    ///
+    /// With `concurrency`, the workflow holds its key while unfinished (see
+    /// `WorkflowService::start_workflow_with_concurrency_key`).
     pub async fn start_workflow(
         workflow_name: String,
         inputs: JsonValue,
         queue: Option<String>,
+        namespace: Option<String>,
+        concurrency: Option<ConcurrencyKey>,
     ) -> Result<String> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Enqueue, "start_workflow")?;
+        let queue = queue.as_deref().unwrap_or("default");
+        match &concurrency {
+            Some(concurrency) => {
+                app.workflow_service
+                    .start_workflow_with_concurrency_key(
+                        &workflow_name,
+                        inputs,
+                        queue,
+                        namespace.as_deref(),
+                        concurrency,
+                    )
+                    .await
+            }
+            None => {
+                app.workflow_service
+                    .start_workflow(&workflow_name, inputs, queue, namespace.as_deref())
+                    .await
+            }
+        }
+    }
+
+    /// Run a workflow in the express lane and wait up to `timeout` for its output
+    ///
+    /// Fails with `SyncRunFailed` or `SyncRunTimeout` (see
+    /// `WorkflowService::run_workflow_sync`).
+    pub async fn run_workflow_sync(
+        workflow_name: String,
+        inputs: JsonValue,
+        queue: Option<String>,
+        namespace: Option<String>,
+        timeout: std::time::Duration,
+    ) -> Result<JsonValue> {
+        let app = Self::get_writable_app(Scope::Enqueue, "run_workflow_sync")?;
         let queue = queue.as_deref().unwrap_or("default");
         app.workflow_service
-            .start_workflow(&workflow_name, inputs, queue)
+            .run_workflow_sync(&workflow_name, inputs, queue, namespace.as_deref(), timeout)
+            .await
+    }
+
+    /// Output of a finished workflow, waiting up to `timeout` for it if
+    /// `wait` is set
+    ///
+    /// `None` if it hasn't finished and `wait` isn't set. Fails with
+    /// `SyncRunFailed` or `SyncRunTimeout` (see
+    /// `WorkflowService::get_workflow_result`).
+    pub async fn get_workflow_result(
+        execution_id: String,
+        wait: bool,
+        timeout: std::time::Duration,
+    ) -> Result<Option<JsonValue>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_workflow_result")?;
+        app.workflow_service
+            .get_workflow_result(&execution_id, wait, timeout)
             .await
     }
 
@@ -142,19 +469,19 @@ impl Client {
     /// Creates the execution immediately in Pending status, then schedules
     /// it to be enqueued at the specified time.
     pub async fn schedule_execution(params: ScheduleExecutionParams) -> Result<String> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Enqueue, "schedule_execution")?;
         app.scheduler_service.schedule_execution(params).await
     }
 
     /// Register a workflow definition
     pub async fn register_workflow(name: String, source: String) -> Result<i32> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Admin, "register_workflow")?;
         app.workflow_service.register_workflow(&name, &source).await
     }
 
     /// Get all child task executions for a workflow
     pub async fn get_workflow_tasks(workflow_id: String) -> Result<Vec<JsonValue>> {
-        let app = Self::get_app()?;
+        let app = Self::get_app_for(Scope::ReadOnly, "get_workflow_tasks")?;
         let tasks = app
             .workflow_service
             .get_workflow_tasks(&workflow_id)
@@ -165,6 +492,19 @@ impl Client {
             .collect())
     }
 
+    /// The inputs schema a registered workflow declares in its front matter
+    pub async fn get_workflow_schema(name: String) -> Result<Option<InputSchema>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "get_workflow_schema")?;
+        app.workflow_service.get_workflow_schema(&name).await
+    }
+
+    /// The task graph of a registered workflow: what it may start and wait
+    /// on, in order, and which calls may run in parallel
+    pub async fn analyze_workflow(name: String) -> Result<TaskGraph> {
+        let app = Self::get_app_for(Scope::ReadOnly, "analyze_workflow")?;
+        app.workflow_service.analyze_workflow(&name).await
+    }
+
     /* ===================== Signal Operations ===================== */
 
     /// Send a signal to a workflow
@@ -177,13 +517,33 @@ impl Client {
         payload: JsonValue,
         queue: Option<String>,
     ) -> Result<()> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Enqueue, "send_signal")?;
         let queue = queue.as_deref().unwrap_or("default");
         app.signal_service
             .send_signal(&workflow_id, &signal_name, payload, queue)
             .await
     }
 
+    /* ===================== Webhook Operations ===================== */
+
+    /// Register a URL to be notified when matching executions finish
+    pub async fn register_webhook(params: CreateWebhookParams) -> Result<Webhook> {
+        let app = Self::get_writable_app(Scope::Admin, "register_webhook")?;
+        app.webhook_service.register(params).await
+    }
+
+    /// List registered webhooks
+    pub async fn list_webhooks() -> Result<Vec<Webhook>> {
+        let app = Self::get_app_for(Scope::ReadOnly, "list_webhooks")?;
+        app.webhook_service.list().await
+    }
+
+    /// Remove a webhook and its undelivered notifications
+    pub async fn remove_webhook(webhook_id: String) -> Result<()> {
+        let app = Self::get_writable_app(Scope::Admin, "remove_webhook")?;
+        app.webhook_service.remove(&webhook_id).await
+    }
+
     /* ===================== Internal Operations ===================== */
 
     /// Start the internal worker (scheduler queue processor)
@@ -193,7 +553,7 @@ impl Client {
     ///
     /// Returns an error if the internal worker has already been started.
     pub fn start_internal_worker() -> Result<()> {
-        let app = Self::get_app()?;
+        let app = Self::get_writable_app(Scope::Worker, "start_internal_worker")?;
         app.start_internal_worker()
     }
 
@@ -204,4 +564,23 @@ impl Client {
         APP.get()
             .ok_or_else(|| anyhow!("Application not initialized - call Client::initialize() first"))
     }
+
+    /// Get the application instance for an operation `scope` covers
+    ///
+    /// Fails with `Unauthenticated` or `PermissionDenied` when the API key
+    /// doesn't allow it (see `auth`).
+    fn get_app_for(scope: Scope, operation: &'static str) -> Result<&'static Application> {
+        let app = Self::get_app()?;
+        app.authorize(scope, operation)?;
+        Ok(app)
+    }
+
+    /// Get the application instance for an operation that changes state
+    ///
+    /// Fails with `ReadOnly` when initialized in read-only mode.
+    fn get_writable_app(scope: Scope, operation: &'static str) -> Result<&'static Application> {
+        let app = Self::get_app_for(scope, operation)?;
+        app.ensure_writable(operation)?;
+        Ok(app)
+    }
 }
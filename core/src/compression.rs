@@ -0,0 +1,88 @@
+//! Compression of saved workflow states
+//!
+//! A saved VM state whose JSON is over `[payloads]
+//! compress_state_above_bytes` is compressed with zstd before it is
+//! encrypted and written, and stored as an envelope (binary states are
+//! compressed in place, see `state_encoding`):
+//!
+//! ```json
+//! { "$rhythm_zstd": { "size": 1048576, "data": "<base64>" } }
+//! ```
+//!
+//! `size` is the length of the uncompressed JSON. Envelopes are expanded
+//! wherever saved states are read (see `PayloadStore::open_state`), so states
+//! written before compression was turned on, or below the threshold, are
+//! read as they are.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value as JsonValue};
+
+/// Key of the object that stands in for a compressed value
+pub const COMPRESSED_KEY: &str = "$rhythm_zstd";
+
+const LEVEL: i32 = 3;
+
+/// Envelope holding `bytes`, the serialized JSON of a value, compressed
+pub fn compress(bytes: &[u8]) -> Result<JsonValue> {
+    let data = compress_bytes(bytes)?;
+    Ok(json!({
+        COMPRESSED_KEY: { "size": bytes.len(), "data": BASE64.encode(data) }
+    }))
+}
+
+/// `bytes` compressed with zstd
+pub fn compress_bytes(bytes: &[u8]) -> Result<Vec<u8>> {
+    zstd::encode_all(bytes, LEVEL).context("Failed to compress value")
+}
+
+/// The bytes `compress_bytes` compressed into `data`
+pub fn decompress_bytes(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::decode_all(data).context("Failed to decompress value")
+}
+
+/// Whether `value` is an envelope `compress` made
+pub fn is_compressed(value: &JsonValue) -> bool {
+    matches!(value, JsonValue::Object(map) if map.len() == 1 && map.contains_key(COMPRESSED_KEY))
+}
+
+/// `value` expanded if it is an envelope, or as it is
+pub fn decompress(value: JsonValue) -> Result<JsonValue> {
+    if !is_compressed(&value) {
+        return Ok(value);
+    }
+    let data = value[COMPRESSED_KEY]["data"]
+        .as_str()
+        .context("Compressed value has no data")?;
+    let data = BASE64
+        .decode(data)
+        .context("Compressed value is not valid base64")?;
+    let bytes = decompress_bytes(&data)?;
+    serde_json::from_slice(&bytes).context("Decompressed value is not valid JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let value = json!({ "locals": { "rows": vec!["same row"; 1000] } });
+        let bytes = serde_json::to_vec(&value).unwrap();
+
+        let compressed = compress(&bytes).unwrap();
+        assert!(is_compressed(&compressed));
+        assert_eq!(compressed[COMPRESSED_KEY]["size"], bytes.len());
+        assert!(serde_json::to_vec(&compressed).unwrap().len() < bytes.len() / 10);
+        assert_eq!(decompress(compressed).unwrap(), value);
+    }
+
+    #[test]
+    fn test_plain_values_pass_through() {
+        let value = json!({ COMPRESSED_KEY: "data", "other": 1 });
+        assert!(!is_compressed(&value));
+        assert_eq!(decompress(value.clone()).unwrap(), value);
+        assert!(decompress(json!({ COMPRESSED_KEY: { "data": "not base64!" } })).is_err());
+    }
+}
@@ -16,6 +16,124 @@
 //! acquire_timeout_secs = 10
 //! idle_timeout_secs = 600
 //! max_lifetime_secs = 1800
+//!
+//! [worker]
+//! id = "payments-worker-1"
+//! labels = { pci = "true" }
+//! defer_work_cleanup = true
+//! replay_check = true
+//! record_runs = true
+//!
+//! [worker.runner_retry]
+//! max_attempts = 5
+//! base_delay_ms = 1000
+//! max_delay_ms = 300000
+//!
+//! [worker.reaper]
+//! heartbeat_timeout_secs = 300
+//! max_reclaims = 3
+//!
+//! [worker.sticky]
+//! enabled = true
+//! timeout_ms = 5000
+//! cache_size = 1000
+//!
+//! [worker.step_budget]
+//! max_steps = 100000
+//! on_exceeded = "fail"
+//!
+//! [[claim_policy.rules]]
+//! queue = "payments"
+//! require_labels = { pci = "true" }
+//!
+//! [quotas.default]
+//! max_running = 10000
+//!
+//! [quotas.namespaces.acme]
+//! max_running = 500
+//! max_enqueues_per_minute = 1200
+//! max_storage_bytes = 1073741824
+//!
+//! [[slos]]
+//! name = "payments-claimed-fast"
+//! queue = "payments"
+//! threshold_ms = 5000
+//! objective = 0.95
+//!
+//! [[task_configs]]
+//! target_name = "charge_card"
+//! max_retries = 5
+//! retry_on = ["RATE_LIMIT", "HTTP_5*"]
+//! give_up_on = ["VALIDATION"]
+//! output_schema = { charge_id = "string", amount = "number" }
+//!
+//! [diagnostics]
+//! sample_rate = 0.01
+//! sample_failures = true
+//!
+//! [[diagnostics.rules]]
+//! target_name = "checkout"
+//! sample_rate = 0.5
+//!
+//! [[concurrency_groups]]
+//! name = "exports"
+//! max_running = 5
+//! targets = ["export_csv", "export_pdf"]
+//!
+//! [workflow_defaults]
+//! queue = "default"
+//! retries = 3
+//! timeout = "10m"
+//! validator = { unused_variable = "warn" }
+//!
+//! [schema_drift]
+//! enabled = true
+//!
+//! [idempotency]
+//! window_secs = 86400
+//!
+//! [webhooks]
+//! enabled = true
+//! max_attempts = 8
+//! base_delay_ms = 10000
+//! max_delay_ms = 3600000
+//! timeout_ms = 10000
+//!
+//! [telemetry]
+//! otlp_endpoint = "http://localhost:4318/v1/traces"
+//! service_name = "billing-worker"
+//!
+//! [payloads]
+//! max_inline_bytes = 262144
+//! max_bytes = 104857600
+//! max_state_bytes = 16777216
+//! compress_state_above_bytes = 65536
+//! state_encoding = "msgpack"
+//!
+//! [payloads.store]
+//! path = "/var/lib/rhythm/blobs"
+//!
+//! [queue]
+//! backend = "redis"
+//! redis_url = "redis://localhost:6379"
+//! key_prefix = "rhythm"
+//! refill_batch_size = 100
+//! default_max_depth = 100000
+//! full_wait_ms = 5000
+//!
+//! [queue.max_depth]
+//! emails = 10000
+//!
+//! [encryption]
+//! active_key = "2026-10"
+//!
+//! [encryption.keys]
+//! "2026-10" = "<32 bytes, base64>"
+//! "2026-01" = "<32 bytes, base64>"
+//!
+//! [auth]
+//! required = true
+//! api_key = "rhythm_<id>_<secret>"
 //! ```
 //!
 //! # Environment Variables
@@ -24,10 +142,22 @@
 //! - RHYTHM_DATABASE_URL
 //! - RHYTHM_DATABASE_MAX_CONNECTIONS
 //! - RHYTHM_DATABASE_MIN_CONNECTIONS
+//! - RHYTHM_WORKER_ID
+//! - RHYTHM_WORKER_LABELS (comma-separated `key=value` pairs)
+//! - RHYTHM_WORKER_DEFER_WORK_CLEANUP (`true` or `false`)
+//! - RHYTHM_TELEMETRY_OTLP_ENDPOINT
+//! - RHYTHM_ENCRYPTION_ACTIVE_KEY
+//! - RHYTHM_ENCRYPTION_KEYS (comma-separated `id=base64` pairs)
+//! - RHYTHM_QUEUE_REDIS_URL
+//! - RHYTHM_AUTH_REQUIRED (`true` or `false`)
+//! - RHYTHM_API_KEY
 //! - etc.
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::payload::PayloadEncoding;
 use std::env;
 use std::path::{Path, PathBuf};
 
@@ -36,6 +166,55 @@ use std::path::{Path, PathBuf};
 pub struct Config {
     #[serde(default)]
     pub database: DatabaseConfig,
+
+    #[serde(default)]
+    pub worker: WorkerConfig,
+
+    #[serde(default)]
+    pub claim_policy: ClaimPolicyConfig,
+
+    #[serde(default)]
+    pub quotas: QuotasConfig,
+
+    #[serde(default)]
+    pub slos: Vec<SloConfig>,
+
+    #[serde(default)]
+    pub task_configs: Vec<TaskConfig>,
+
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+
+    #[serde(default)]
+    pub concurrency_groups: Vec<ConcurrencyGroupConfig>,
+
+    /// Settings for workflows that their front matter doesn't set
+    #[serde(default)]
+    pub workflow_defaults: serde_json::Map<String, serde_json::Value>,
+
+    #[serde(default)]
+    pub schema_drift: SchemaDriftConfig,
+
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+
+    #[serde(default)]
+    pub payloads: PayloadsConfig,
+
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+
+    #[serde(default)]
+    pub queue: QueueConfig,
+
+    #[serde(default)]
+    pub auth: AuthConfig,
 }
 
 /// Database connection configuration
@@ -82,6 +261,610 @@ fn default_max_lifetime_secs() -> u64 {
     1800
 }
 
+/// Identity this process presents when claiming work
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkerConfig {
+    /// Stable worker id (generated per process if unset)
+    pub id: Option<String>,
+
+    /// Free-form labels matched against claim policy rules
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+
+    /// Batch deletes of finished tasks' work queue entries off the
+    /// completion path (see `worker::cleanup`)
+    #[serde(default)]
+    pub defer_work_cleanup: bool,
+
+    /// Replay each workflow from the beginning against its history before
+    /// resuming it, failing it if the code no longer does what it did (see
+    /// `executor::replay`)
+    #[serde(default)]
+    pub replay_check: bool,
+
+    /// Record what every workflow run reads from outside it and is resumed
+    /// with, so `rhythm replay` can re-run any of them (see
+    /// `db::execution_recordings`)
+    #[serde(default)]
+    pub record_runs: bool,
+
+    /// Backoff for workflow runs that fail for transient reasons
+    #[serde(default)]
+    pub runner_retry: RunnerRetryConfig,
+
+    /// Reclaiming or failing tasks whose worker stopped heartbeating
+    #[serde(default)]
+    pub reaper: ReaperConfig,
+
+    /// Resuming workflows on the worker that last ran them
+    #[serde(default)]
+    pub sticky: StickyConfig,
+
+    /// VM steps a workflow run may take before it yields or fails
+    #[serde(default)]
+    pub step_budget: StepBudgetConfig,
+}
+
+/// Sweep for running tasks whose worker went silent
+///
+/// A task with no heartbeat for `heartbeat_timeout_secs` is put back on its
+/// queue, up to `max_reclaims` times, then fails with `HEARTBEAT_TIMEOUT`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReaperConfig {
+    #[serde(default = "default_reaper_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_heartbeat_timeout_secs")]
+    pub heartbeat_timeout_secs: u64,
+    #[serde(default = "default_max_reclaims")]
+    pub max_reclaims: u32,
+}
+
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            heartbeat_timeout_secs: default_heartbeat_timeout_secs(),
+            max_reclaims: default_max_reclaims(),
+        }
+    }
+}
+
+fn default_reaper_enabled() -> bool {
+    true
+}
+fn default_heartbeat_timeout_secs() -> u64 {
+    300
+}
+fn default_max_reclaims() -> u32 {
+    3
+}
+
+/// Affinity of suspended workflows for the worker that saved them
+///
+/// When enabled, a worker keeps the VMs of up to `cache_size` workflows it
+/// saved, and when one is queued again other workers leave it for
+/// `timeout_ms` so this worker can resume it without reloading its state.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StickyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_sticky_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default = "default_sticky_cache_size")]
+    pub cache_size: usize,
+}
+
+impl Default for StickyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: default_sticky_timeout_ms(),
+            cache_size: default_sticky_cache_size(),
+        }
+    }
+}
+
+fn default_sticky_timeout_ms() -> u64 {
+    5000
+}
+fn default_sticky_cache_size() -> usize {
+    1000
+}
+
+/// Limit on VM steps per workflow run, guarding workers against loops that
+/// never await
+///
+/// A run that takes `max_steps` steps either yields, saving its state and
+/// queueing itself again so it continues later, or fails with
+/// `LOOP_BUDGET_EXCEEDED`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepBudgetConfig {
+    #[serde(default = "default_step_budget_max_steps")]
+    pub max_steps: usize,
+    #[serde(default)]
+    pub on_exceeded: StepBudgetAction,
+}
+
+impl Default for StepBudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_steps: default_step_budget_max_steps(),
+            on_exceeded: StepBudgetAction::default(),
+        }
+    }
+}
+
+fn default_step_budget_max_steps() -> usize {
+    crate::worker::runner::STEPS_PER_CLAIM
+}
+
+/// What happens to a workflow run that uses up its step budget
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepBudgetAction {
+    /// Save the workflow and queue it to continue
+    #[default]
+    Yield,
+    /// Fail the workflow with `LOOP_BUDGET_EXCEEDED`
+    Fail,
+}
+
+/// Retries of workflow runs that hit a transient error, such as a lost
+/// database connection or a serialization conflict
+///
+/// The delay doubles from `base_delay_ms` up to `max_delay_ms`. After
+/// `max_attempts` consecutive errors the execution fails.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunnerRetryConfig {
+    #[serde(default = "default_runner_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_runner_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_runner_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+impl Default for RunnerRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_runner_max_attempts(),
+            base_delay_ms: default_runner_base_delay_ms(),
+            max_delay_ms: default_runner_max_delay_ms(),
+        }
+    }
+}
+
+fn default_runner_max_attempts() -> u32 {
+    5
+}
+fn default_runner_base_delay_ms() -> u64 {
+    1000
+}
+fn default_runner_max_delay_ms() -> u64 {
+    300_000
+}
+
+/// Rules restricting which workers may claim which executions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClaimPolicyConfig {
+    #[serde(default)]
+    pub rules: Vec<ClaimRule>,
+}
+
+/// A claim rule applies to executions matching `queue` and `target_name`
+/// (unset means any) and requires the worker to carry every listed label
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClaimRule {
+    pub queue: Option<String>,
+    pub target_name: Option<String>,
+    #[serde(default)]
+    pub require_labels: HashMap<String, String>,
+}
+
+/// Per-namespace execution quotas
+///
+/// Namespaces without their own entry use `default`; unset limits are unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuotasConfig {
+    #[serde(default)]
+    pub default: NamespaceQuota,
+    #[serde(default)]
+    pub namespaces: HashMap<String, NamespaceQuota>,
+}
+
+impl QuotasConfig {
+    /// Limits that apply to a namespace
+    pub fn for_namespace(&self, namespace: &str) -> &NamespaceQuota {
+        self.namespaces.get(namespace).unwrap_or(&self.default)
+    }
+}
+
+/// Limits checked whenever an execution is created in a namespace
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NamespaceQuota {
+    /// Executions that have not finished yet (pending, running or suspended)
+    pub max_running: Option<i64>,
+    /// Executions created in the trailing 60 seconds
+    pub max_enqueues_per_minute: Option<i64>,
+    /// Bytes of inputs and outputs stored across the namespace's executions
+    pub max_storage_bytes: Option<i64>,
+}
+
+impl NamespaceQuota {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_running.is_none()
+            && self.max_enqueues_per_minute.is_none()
+            && self.max_storage_bytes.is_none()
+    }
+}
+
+/// Claim latency objective, e.g. 95% of payment tasks claimed within 5s
+///
+/// Latency is measured from an execution's creation to its first claim.
+/// Unset `queue` or `target_name` matches any.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SloConfig {
+    pub name: String,
+    pub queue: Option<String>,
+    pub target_name: Option<String>,
+    /// Longest acceptable wait before the first claim
+    pub threshold_ms: u64,
+    /// Fraction of executions that must meet the threshold, e.g. 0.95
+    pub objective: f64,
+    /// Trailing window the objective is evaluated over
+    #[serde(default = "default_slo_window_secs")]
+    pub window_secs: u64,
+}
+
+fn default_slo_window_secs() -> u64 {
+    3600
+}
+
+/// Retry rules for failed executions of one task
+///
+/// Patterns match the failure's error code exactly, or by prefix when they
+/// end in `*`. `give_up_on` wins over `retry_on`; codes matching neither
+/// follow the retry flag the worker reported the failure with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TaskConfig {
+    pub target_name: String,
+    /// Retries before the failure is final (default 3)
+    pub max_retries: Option<u32>,
+    /// Error codes that are always retried
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+    /// Error codes that are never retried
+    #[serde(default)]
+    pub give_up_on: Vec<String>,
+    /// Fields the task's result must have, as `name: type` entries like a
+    /// workflow's `inputs:` (see `worker::output_schema`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+}
+
+/// Which executions get verbose diagnostics recorded (see `diagnostics`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DiagnosticsConfig {
+    /// Fraction of executions sampled, from 0.0 (default) to 1.0
+    #[serde(default)]
+    pub sample_rate: f64,
+    /// Also record failed executions that weren't sampled
+    #[serde(default)]
+    pub sample_failures: bool,
+    /// Rates for particular workflows or queues; the first match wins
+    #[serde(default)]
+    pub rules: Vec<SamplingRule>,
+}
+
+/// Sample rate for executions matching a workflow and/or queue
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SamplingRule {
+    pub target_name: Option<String>,
+    pub queue: Option<String>,
+    pub sample_rate: f64,
+}
+
+/// Limit on executions of a set of targets in flight at once, across workers
+///
+/// Executions over the limit wait in their queues. As slots free up, the
+/// member target with the fewest executions in flight goes next, so one busy
+/// workflow can't starve the rest of the group. All workers must be
+/// configured with the same groups.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ConcurrencyGroupConfig {
+    pub name: String,
+    /// Running or suspended executions allowed at once
+    pub max_running: u32,
+    /// Workflows and tasks in the group; a target listed in several groups
+    /// belongs to the first
+    pub targets: Vec<String>,
+}
+
+/// Payload shape inference and drift detection (see `payload_schema`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SchemaDriftConfig {
+    /// Have the internal worker record the shapes of completed executions
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Deduplication of executions created with an idempotency key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdempotencyConfig {
+    /// How long a key returns the execution first created with it
+    #[serde(default = "default_idempotency_window_secs")]
+    pub window_secs: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: default_idempotency_window_secs(),
+        }
+    }
+}
+
+fn default_idempotency_window_secs() -> u64 {
+    24 * 60 * 60
+}
+
+/// Delivery of webhook notifications (see `WebhookService`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhooksConfig {
+    /// Have the internal worker send queued deliveries
+    #[serde(default = "default_webhooks_enabled")]
+    pub enabled: bool,
+
+    /// Attempts at a delivery before it is marked failed
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+
+    /// Delay before the first retry, doubled for each one after
+    #[serde(default = "default_webhook_base_delay_ms")]
+    pub base_delay_ms: u64,
+
+    #[serde(default = "default_webhook_max_delay_ms")]
+    pub max_delay_ms: u64,
+
+    /// How long an endpoint has to respond
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_webhooks_enabled(),
+            max_attempts: default_webhook_max_attempts(),
+            base_delay_ms: default_webhook_base_delay_ms(),
+            max_delay_ms: default_webhook_max_delay_ms(),
+            timeout_ms: default_webhook_timeout_ms(),
+        }
+    }
+}
+
+fn default_webhooks_enabled() -> bool {
+    true
+}
+fn default_webhook_max_attempts() -> u32 {
+    8
+}
+fn default_webhook_base_delay_ms() -> u64 {
+    10_000
+}
+fn default_webhook_max_delay_ms() -> u64 {
+    60 * 60 * 1000
+}
+fn default_webhook_timeout_ms() -> u64 {
+    10_000
+}
+
+/// Export of execution spans to an OpenTelemetry collector
+///
+/// Exporting needs the `otel` feature; spans are still created, and trace
+/// context still stored and propagated, without it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP traces endpoint; nothing is exported without one
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+
+    /// `service.name` of the exported spans
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_service_name(),
+        }
+    }
+}
+
+fn default_service_name() -> String {
+    "rhythm".to_string()
+}
+
+/// Size limits on execution inputs and outputs, and on saved workflow states
+///
+/// A payload larger than `max_inline_bytes` is written to the blob store and
+/// only a reference kept in its row; without a store it is rejected. One
+/// larger than `max_bytes` is rejected either way. A workflow whose state
+/// is larger than `max_state_bytes` when it is saved fails with
+/// `STATE_TOO_LARGE`; states larger than `compress_state_above_bytes` are
+/// stored compressed (see `compression`), and states are written as
+/// `state_encoding` (see `state_encoding`). Unset limits are unlimited.
+/// Sizes are of the payload serialized as JSON, or the state serialized in
+/// its encoding.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PayloadsConfig {
+    #[serde(default)]
+    pub max_inline_bytes: Option<usize>,
+
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+
+    /// Where offloaded payloads are written
+    #[serde(default)]
+    pub store: Option<BlobStoreConfig>,
+
+    #[serde(default)]
+    pub max_state_bytes: Option<usize>,
+
+    #[serde(default = "default_compress_state_above_bytes")]
+    pub compress_state_above_bytes: usize,
+
+    #[serde(default)]
+    pub state_encoding: PayloadEncoding,
+}
+
+impl Default for PayloadsConfig {
+    fn default() -> Self {
+        Self {
+            max_inline_bytes: None,
+            max_bytes: None,
+            store: None,
+            max_state_bytes: None,
+            compress_state_above_bytes: default_compress_state_above_bytes(),
+            state_encoding: PayloadEncoding::default(),
+        }
+    }
+}
+
+fn default_compress_state_above_bytes() -> usize {
+    65_536
+}
+
+/// Built-in filesystem blob store
+///
+/// Other stores, e.g. S3, are set with `Application::set_blob_store`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlobStoreConfig {
+    /// Directory blobs are written under; shared by every worker
+    pub path: PathBuf,
+}
+
+/// Encryption at rest of payloads and saved workflow states
+///
+/// Keys are 32 bytes, base64-encoded, by ID. New values are encrypted with
+/// `active_key`; any listed key decrypts, so old keys stay listed until
+/// what they encrypted has been re-encrypted. Without `active_key`, nothing
+/// is encrypted. See `encryption`.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EncryptionConfig {
+    #[serde(default)]
+    pub active_key: Option<String>,
+
+    #[serde(default)]
+    pub keys: HashMap<String, String>,
+}
+
+impl std::fmt::Debug for EncryptionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut key_ids: Vec<_> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("EncryptionConfig")
+            .field("active_key", &self.active_key)
+            .field("keys", &key_ids)
+            .finish()
+    }
+}
+
+/// API keys (see `auth`)
+///
+/// With `required`, `Client` calls and the dashboard are refused unless the
+/// caller presents an accepted key. `api_key` is the key this process
+/// presents; it is checked once, when `Client` initializes.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub required: bool,
+
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("required", &self.required)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// Where workers claim work from (see `queue_backend`)
+///
+/// Executions and the record of queued work are in Postgres whatever the
+/// backend. With `backend = "redis"`, which needs the `redis` feature,
+/// workers claim from Redis at `redis_url`, under keys starting with
+/// `key_prefix`, and a worker that finds a queue short moves up to
+/// `refill_batch_size` pieces of work there from Postgres.
+///
+/// `max_depth` caps the work waiting in each queue it names, and
+/// `default_max_depth` in the others; creating an execution on a full queue
+/// waits up to `full_wait_ms` for room, then fails (see `queue_limits`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueueConfig {
+    #[serde(default)]
+    pub backend: QueueBackendKind,
+
+    #[serde(default)]
+    pub redis_url: Option<String>,
+
+    #[serde(default = "default_queue_key_prefix")]
+    pub key_prefix: String,
+
+    #[serde(default = "default_refill_batch_size")]
+    pub refill_batch_size: usize,
+
+    /// Most unclaimed executions a queue may hold, per queue name
+    #[serde(default)]
+    pub max_depth: HashMap<String, i64>,
+
+    /// Most unclaimed executions for queues not in `max_depth`; unlimited
+    /// if unset
+    #[serde(default)]
+    pub default_max_depth: Option<i64>,
+
+    /// How long creating an execution waits for room in a full queue; 0
+    /// fails at once
+    #[serde(default)]
+    pub full_wait_ms: u64,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            backend: QueueBackendKind::default(),
+            redis_url: None,
+            key_prefix: default_queue_key_prefix(),
+            refill_batch_size: default_refill_batch_size(),
+            max_depth: HashMap::new(),
+            default_max_depth: None,
+            full_wait_ms: 0,
+        }
+    }
+}
+
+fn default_queue_key_prefix() -> String {
+    "rhythm".to_string()
+}
+fn default_refill_batch_size() -> usize {
+    100
+}
+
+/// Which `QueueBackend` workers claim through
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueBackendKind {
+    #[default]
+    Postgres,
+    Redis,
+}
+
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
@@ -182,6 +965,22 @@ impl ConfigBuilder {
         // Step 1: Start with defaults
         let mut config = Config {
             database: DatabaseConfig::default(),
+            worker: WorkerConfig::default(),
+            claim_policy: ClaimPolicyConfig::default(),
+            quotas: QuotasConfig::default(),
+            slos: Vec::new(),
+            task_configs: Vec::new(),
+            diagnostics: DiagnosticsConfig::default(),
+            concurrency_groups: Vec::new(),
+            workflow_defaults: serde_json::Map::new(),
+            schema_drift: SchemaDriftConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            payloads: PayloadsConfig::default(),
+            encryption: EncryptionConfig::default(),
+            queue: QueueConfig::default(),
+            auth: AuthConfig::default(),
         };
 
         // Step 2: Try to load from config file
@@ -205,6 +1004,11 @@ impl ConfigBuilder {
                   3. CLI flag: --database-url postgresql://..."
             );
         }
+        crate::encryption::Keyring::from_config(&config.encryption)
+            .context("Invalid [encryption] config")?;
+        crate::worker::OutputSchemas::from_task_configs(&config.task_configs)
+            .context("Invalid [[task_configs]]")?;
+        crate::queue_backend::check_config(&config.queue).context("Invalid [queue] config")?;
 
         Ok(config)
     }
@@ -296,6 +1100,77 @@ impl ConfigBuilder {
                 config.database.max_lifetime_secs = lifetime;
             }
         }
+
+        // Worker identity
+        if let Ok(id) = env::var("RHYTHM_WORKER_ID") {
+            config.worker.id = Some(id);
+        }
+
+        if let Ok(labels) = env::var("RHYTHM_WORKER_LABELS") {
+            config.worker.labels.extend(parse_labels(&labels));
+        }
+
+        if let Ok(defer) = env::var("RHYTHM_WORKER_DEFER_WORK_CLEANUP") {
+            if let Ok(defer) = defer.parse() {
+                config.worker.defer_work_cleanup = defer;
+            }
+        }
+
+        if let Ok(replay_check) = env::var("RHYTHM_WORKER_REPLAY_CHECK") {
+            if let Ok(replay_check) = replay_check.parse() {
+                config.worker.replay_check = replay_check;
+            }
+        }
+
+        if let Ok(record_runs) = env::var("RHYTHM_WORKER_RECORD_RUNS") {
+            if let Ok(record_runs) = record_runs.parse() {
+                config.worker.record_runs = record_runs;
+            }
+        }
+
+        if let Ok(sticky) = env::var("RHYTHM_WORKER_STICKY") {
+            if let Ok(sticky) = sticky.parse() {
+                config.worker.sticky.enabled = sticky;
+            }
+        }
+
+        if let Ok(max_steps) = env::var("RHYTHM_WORKER_STEP_BUDGET") {
+            if let Ok(max_steps) = max_steps.parse() {
+                config.worker.step_budget.max_steps = max_steps;
+            }
+        }
+
+        if let Ok(enabled) = env::var("RHYTHM_WEBHOOKS_ENABLED") {
+            if let Ok(enabled) = enabled.parse() {
+                config.webhooks.enabled = enabled;
+            }
+        }
+
+        if let Ok(endpoint) = env::var("RHYTHM_TELEMETRY_OTLP_ENDPOINT") {
+            config.telemetry.otlp_endpoint = Some(endpoint);
+        }
+
+        if let Ok(active_key) = env::var("RHYTHM_ENCRYPTION_ACTIVE_KEY") {
+            config.encryption.active_key = Some(active_key);
+        }
+
+        if let Ok(keys) = env::var("RHYTHM_ENCRYPTION_KEYS") {
+            config.encryption.keys.extend(parse_labels(&keys));
+        }
+
+        if let Ok(url) = env::var("RHYTHM_QUEUE_REDIS_URL") {
+            config.queue.redis_url = Some(url);
+        }
+
+        if let Ok(required) = env::var("RHYTHM_AUTH_REQUIRED") {
+            if let Ok(required) = required.parse() {
+                config.auth.required = required;
+            }
+        }
+
+        if let Ok(key) = env::var("RHYTHM_API_KEY") {
+            config.auth.api_key = Some(key);
+        }
     }
 
     /// Apply CLI overrides (highest priority)
@@ -326,6 +1201,15 @@ impl ConfigBuilder {
     }
 }
 
+/// Parse `key=value,key2=value2` label lists, skipping malformed entries
+fn parse_labels(s: &str) -> HashMap<String, String> {
+    s.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .filter(|(k, _)| !k.is_empty())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -334,6 +1218,22 @@ mod tests {
     fn test_default_config() {
         let config = Config {
             database: DatabaseConfig::default(),
+            worker: WorkerConfig::default(),
+            claim_policy: ClaimPolicyConfig::default(),
+            quotas: QuotasConfig::default(),
+            slos: Vec::new(),
+            task_configs: Vec::new(),
+            diagnostics: DiagnosticsConfig::default(),
+            concurrency_groups: Vec::new(),
+            workflow_defaults: serde_json::Map::new(),
+            schema_drift: SchemaDriftConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            payloads: PayloadsConfig::default(),
+            encryption: EncryptionConfig::default(),
+            queue: QueueConfig::default(),
+            auth: AuthConfig::default(),
         };
 
         assert_eq!(config.database.url, None);
@@ -358,6 +1258,45 @@ mod tests {
         assert_eq!(config.database.min_connections, 5); // Default
     }
 
+    #[test]
+    fn test_parse_worker_and_claim_policy() {
+        let toml_str = r#"
+            [worker]
+            id = "pci-1"
+            labels = { pci = "true" }
+            replay_check = true
+            record_runs = true
+
+            [[claim_policy.rules]]
+            queue = "payments"
+            require_labels = { pci = "true" }
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.worker.id, Some("pci-1".to_string()));
+        assert_eq!(config.worker.labels.get("pci"), Some(&"true".to_string()));
+        assert!(config.worker.replay_check);
+        assert!(config.worker.record_runs);
+        assert_eq!(config.worker.runner_retry, RunnerRetryConfig::default());
+        assert_eq!(config.worker.reaper, ReaperConfig::default());
+        assert_eq!(config.worker.sticky, StickyConfig::default());
+        assert_eq!(config.worker.step_budget, StepBudgetConfig::default());
+        assert_eq!(config.claim_policy.rules.len(), 1);
+        assert_eq!(
+            config.claim_policy.rules[0].queue,
+            Some("payments".to_string())
+        );
+        assert_eq!(config.claim_policy.rules[0].target_name, None);
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        let labels = parse_labels("pci=true, region = eu,bogus,=x");
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels.get("pci"), Some(&"true".to_string()));
+        assert_eq!(labels.get("region"), Some(&"eu".to_string()));
+    }
+
     #[test]
     fn test_builder_with_overrides() {
         let config = Config::builder()
@@ -389,4 +1328,288 @@ mod tests {
             std::env::set_var("RHYTHM_DATABASE_URL", url);
         }
     }
+
+    #[test]
+    fn test_parse_quotas() {
+        let toml_str = r#"
+            [quotas.default]
+            max_running = 100
+
+            [quotas.namespaces.acme]
+            max_enqueues_per_minute = 10
+            max_storage_bytes = 2048
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.quotas.for_namespace("other").max_running, Some(100));
+
+        let acme = config.quotas.for_namespace("acme");
+        assert_eq!(acme.max_running, None);
+        assert_eq!(acme.max_enqueues_per_minute, Some(10));
+        assert_eq!(acme.max_storage_bytes, Some(2048));
+        assert!(!acme.is_unlimited());
+        assert!(Config {
+            database: DatabaseConfig::default(),
+            worker: WorkerConfig::default(),
+            claim_policy: ClaimPolicyConfig::default(),
+            quotas: QuotasConfig::default(),
+            slos: Vec::new(),
+            task_configs: Vec::new(),
+            diagnostics: DiagnosticsConfig::default(),
+            concurrency_groups: Vec::new(),
+            workflow_defaults: serde_json::Map::new(),
+            schema_drift: SchemaDriftConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            payloads: PayloadsConfig::default(),
+            encryption: EncryptionConfig::default(),
+            queue: QueueConfig::default(),
+            auth: AuthConfig::default(),
+        }
+        .quotas
+        .for_namespace("acme")
+        .is_unlimited());
+    }
+
+    #[test]
+    fn test_parse_slos() {
+        let config: Config = toml::from_str(
+            r#"
+            [[slos]]
+            name = "payments-claimed-fast"
+            queue = "payments"
+            threshold_ms = 5000
+            objective = 0.95
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.slos,
+            vec![SloConfig {
+                name: "payments-claimed-fast".to_string(),
+                queue: Some("payments".to_string()),
+                target_name: None,
+                threshold_ms: 5000,
+                objective: 0.95,
+                window_secs: 3600,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_idempotency() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.idempotency.window_secs, 86400);
+
+        let config: Config = toml::from_str(
+            r#"
+            [idempotency]
+            window_secs = 600
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.idempotency.window_secs, 600);
+    }
+
+    #[test]
+    fn test_parse_webhooks() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.webhooks, WebhooksConfig::default());
+        assert!(config.webhooks.enabled);
+
+        let config: Config = toml::from_str(
+            r#"
+            [webhooks]
+            enabled = false
+            max_attempts = 3
+            "#,
+        )
+        .unwrap();
+        assert!(!config.webhooks.enabled);
+        assert_eq!(config.webhooks.max_attempts, 3);
+        assert_eq!(config.webhooks.base_delay_ms, 10_000);
+    }
+
+    #[test]
+    fn test_parse_telemetry() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.telemetry, TelemetryConfig::default());
+        assert_eq!(config.telemetry.service_name, "rhythm");
+
+        let config: Config = toml::from_str(
+            r#"
+            [telemetry]
+            otlp_endpoint = "http://collector:4318/v1/traces"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.telemetry.otlp_endpoint.as_deref(),
+            Some("http://collector:4318/v1/traces")
+        );
+        assert_eq!(config.telemetry.service_name, "rhythm");
+    }
+
+    #[test]
+    fn test_parse_sticky() {
+        let config: Config = toml::from_str(
+            r#"
+            [worker.sticky]
+            enabled = true
+            timeout_ms = 250
+            "#,
+        )
+        .unwrap();
+        assert!(config.worker.sticky.enabled);
+        assert_eq!(config.worker.sticky.timeout_ms, 250);
+        assert_eq!(config.worker.sticky.cache_size, 1000);
+    }
+
+    #[test]
+    fn test_parse_step_budget() {
+        let config: Config = toml::from_str(
+            r#"
+            [worker.step_budget]
+            on_exceeded = "fail"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.worker.step_budget.max_steps, 100_000);
+        assert_eq!(
+            config.worker.step_budget.on_exceeded,
+            StepBudgetAction::Fail
+        );
+
+        assert!(toml::from_str::<Config>(
+            r#"
+            [worker.step_budget]
+            on_exceeded = "abort"
+            "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_task_configs() {
+        let config: Config = toml::from_str(
+            r#"
+            [[task_configs]]
+            target_name = "charge_card"
+            retry_on = ["RATE_LIMIT"]
+            give_up_on = ["VALIDATION"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.task_configs,
+            vec![TaskConfig {
+                target_name: "charge_card".to_string(),
+                max_retries: None,
+                retry_on: vec!["RATE_LIMIT".to_string()],
+                give_up_on: vec!["VALIDATION".to_string()],
+                output_schema: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_payloads() {
+        let config: Config = toml::from_str(
+            r#"
+            [payloads]
+            max_inline_bytes = 1024
+
+            [payloads.store]
+            path = "/var/lib/rhythm/blobs"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.payloads,
+            PayloadsConfig {
+                max_inline_bytes: Some(1024),
+                max_bytes: None,
+                store: Some(BlobStoreConfig {
+                    path: PathBuf::from("/var/lib/rhythm/blobs"),
+                }),
+                max_state_bytes: None,
+                compress_state_above_bytes: 65_536,
+                state_encoding: PayloadEncoding::Json,
+            }
+        );
+
+        let config: Config = toml::from_str(
+            r#"
+            [payloads]
+            state_encoding = "msgpack"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.payloads.state_encoding, PayloadEncoding::MsgPack);
+    }
+
+    #[test]
+    fn test_parse_encryption() {
+        let config: Config = toml::from_str(
+            r#"
+            [encryption]
+            active_key = "2026-10"
+
+            [encryption.keys]
+            "2026-10" = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE="
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.encryption.active_key.as_deref(), Some("2026-10"));
+        assert_eq!(config.encryption.keys.len(), 1);
+        // Keys stay out of debug output
+        assert!(!format!("{:?}", config).contains("AQEB"));
+    }
+
+    #[test]
+    fn test_parse_queue() {
+        let config: Config = toml::from_str(
+            r#"
+            [queue]
+            backend = "redis"
+            redis_url = "redis://cache:6379"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.queue.backend, QueueBackendKind::Redis);
+        assert_eq!(
+            config.queue.redis_url.as_deref(),
+            Some("redis://cache:6379")
+        );
+        assert_eq!(config.queue.key_prefix, "rhythm");
+        assert_eq!(config.queue.refill_batch_size, 100);
+        assert!(config.queue.max_depth.is_empty());
+        assert_eq!(config.queue.default_max_depth, None);
+
+        let config: Config = toml::from_str(
+            r#"
+            [queue]
+            default_max_depth = 1000
+            full_wait_ms = 250
+
+            [queue.max_depth]
+            emails = 50
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.queue.backend, QueueBackendKind::Postgres);
+        assert_eq!(config.queue.max_depth["emails"], 50);
+        assert_eq!(config.queue.default_max_depth, Some(1000));
+        assert_eq!(config.queue.full_wait_ms, 250);
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.queue, QueueConfig::default());
+        assert_eq!(config.queue.backend, QueueBackendKind::Postgres);
+    }
 }
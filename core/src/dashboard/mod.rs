@@ -0,0 +1,797 @@
+//! Embedded read-only dashboard
+//!
+//! Enabled with the `dashboard` feature, serves a static page plus a few JSON
+//! endpoints built on the existing service queries:
+//!
+//! - `GET /` - the dashboard page
+//! - `GET /api/queues` - per-queue work depths, and status counts over recent
+//!   executions
+//! - `GET /api/executions` - a page of executions, with the cursor of the
+//!   next; filtered by `status`, `type`, `queue`, `target_name`,
+//!   `parent_workflow_id`, `namespace`, `created_after` and `created_before`,
+//!   and paged with `order`, `cursor` and `limit`
+//! - `GET /api/executions/{id}` - one execution, the tree of executions under
+//!   it, and what it is suspended on
+//! - `GET /api/executions/{id}/logs` - what the execution logged, in order
+//! - `GET /api/workflows` - the latest definition of each workflow
+//! - `GET /api/workflows/{name}` - a workflow's source and effective settings
+//! - `GET /api/failures` - failed executions, taking the same parameters as
+//!   `/api/executions`
+//!
+//! Times are RFC 3339; `order` is `newest_first` (default) or `oldest_first`.
+//!
+//! With `[auth] required = true`, the `/api` endpoints want an accepted API
+//! key of any scope as `Authorization: Bearer <key>`; the page asks for one
+//! when they refuse it.
+//!
+//! Requests are routed by axum and served over HTTP/1.1 by hyper, one
+//! request per connection, which is all the bundled page needs. A client
+//! gets [`REQUEST_HEAD_TIMEOUT`] to send its request and
+//! [`CONNECTION_TIMEOUT`] for the whole exchange, and at most
+//! [`MAX_CONNECTIONS`] are served at once; the rest wait to be accepted.
+
+use anyhow::{Context, Result};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::service::TowerToHyperService;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+use crate::application::Application;
+use crate::auth::Scope;
+use crate::errors::ErrorCode;
+use crate::types::{Execution, ExecutionFilters, ExecutionStatus};
+
+const INDEX_HTML: &str = include_str!("index.html");
+
+/// Largest request head accepted before the connection is dropped
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// Longest a client may take to send its request head
+pub const REQUEST_HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest a connection may stay open, response included
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Most connections served at once
+pub const MAX_CONNECTIONS: usize = 256;
+
+/// Executions scanned to build the queue summary
+const QUEUE_SUMMARY_WINDOW: i64 = 1000;
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+
+/// Most executions shown under one execution's detail
+const MAX_TREE_SIZE: i64 = 1000;
+
+/// Serve the dashboard on `addr` until `shutdown` is cancelled
+pub async fn serve(
+    app: Arc<Application>,
+    addr: SocketAddr,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind dashboard to {}", addr))?;
+    tracing::info!("Dashboard listening on http://{}", listener.local_addr()?);
+
+    serve_router(listener, router(app), Limits::default(), shutdown).await
+}
+
+/// How long clients get and how many are served at once
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    head_timeout: Duration,
+    connection_timeout: Duration,
+    max_connections: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            head_timeout: REQUEST_HEAD_TIMEOUT,
+            connection_timeout: CONNECTION_TIMEOUT,
+            max_connections: MAX_CONNECTIONS,
+        }
+    }
+}
+
+async fn serve_router(
+    listener: TcpListener,
+    router: Router,
+    limits: Limits,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let permits = Arc::new(Semaphore::new(limits.max_connections));
+    loop {
+        // At the limit, new connections wait in the listen backlog
+        let permit = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            permit = permits.clone().acquire_owned() => permit?,
+        };
+        let (stream, peer) = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+
+        let service = TowerToHyperService::new(router.clone());
+        tokio::spawn(async move {
+            let connection = http1::Builder::new()
+                .timer(TokioTimer::new())
+                .header_read_timeout(limits.head_timeout)
+                .max_buf_size(MAX_REQUEST_HEAD)
+                .keep_alive(false)
+                .serve_connection(TokioIo::new(stream), service);
+            match tokio::time::timeout(limits.connection_timeout, connection).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::debug!("Dashboard connection from {} failed: {}", peer, e),
+                Err(_) => tracing::debug!("Dashboard connection from {} timed out", peer),
+            }
+            drop(permit);
+        });
+    }
+}
+
+fn router(app: Arc<Application>) -> Router {
+    let api = Router::new()
+        .route("/queues", get(queues))
+        .route("/executions", get(executions))
+        .route("/executions/{id}", get(execution_detail))
+        .route("/executions/{id}/logs", get(execution_logs))
+        .route("/workflows", get(workflows))
+        .route("/workflows/{name}", get(workflow_detail))
+        .route("/failures", get(failures))
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(middleware::from_fn_with_state(app.clone(), authenticate));
+
+    Router::new()
+        .route("/", get(|| async { Html(INDEX_HTML) }))
+        .nest("/api", api)
+        .fallback(|| async { error(StatusCode::NOT_FOUND, "Not found") })
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(middleware::map_response(no_store))
+        .with_state(app)
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+async fn method_not_allowed() -> Response {
+    error(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported")
+}
+
+async fn no_store(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+/// Refuse requests without an accepted API key when `[auth] required` is set
+async fn authenticate(
+    State(app): State<Arc<Application>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !app.config.auth.required {
+        return next.run(request).await;
+    }
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(bearer_key);
+    let Some(key) = bearer else {
+        return error(StatusCode::UNAUTHORIZED, "An API key is required");
+    };
+    let checked = match app.api_key_service.authenticate(&key).await {
+        Ok(api_key) => api_key.require(Scope::ReadOnly, "view the dashboard"),
+        Err(e) => Err(e),
+    };
+    match checked {
+        Ok(()) => next.run(request).await,
+        Err(e) => match ErrorCode::of(&e) {
+            ErrorCode::Unauthenticated => error(StatusCode::UNAUTHORIZED, &e.to_string()),
+            ErrorCode::PermissionDenied => error(StatusCode::FORBIDDEN, &e.to_string()),
+            _ => {
+                tracing::error!("Dashboard authentication failed: {:#}", e);
+                error(StatusCode::INTERNAL_SERVER_ERROR, "Internal error")
+            }
+        },
+    }
+}
+
+/// The key of an `Authorization: Bearer` header value
+fn bearer_key(value: &str) -> Option<String> {
+    let (scheme, key) = value.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| key.trim().to_string())
+}
+
+type ApiResult = Result<Json<JsonValue>, ApiError>;
+
+/// Why an endpoint has no body to return
+#[derive(Debug)]
+enum ApiError {
+    NotFound,
+    Failed(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Failed(e)
+    }
+}
+
+impl From<BadRequest> for ApiError {
+    fn from(e: BadRequest) -> Self {
+        Self::Failed(e.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::NotFound => error(StatusCode::NOT_FOUND, "Not found"),
+            Self::Failed(e) if e.is::<BadRequest>() => {
+                error(StatusCode::BAD_REQUEST, &e.to_string())
+            }
+            Self::Failed(e) => {
+                tracing::error!("Dashboard request failed: {:#}", e);
+                error(StatusCode::INTERNAL_SERVER_ERROR, "Internal error")
+            }
+        }
+    }
+}
+
+/// A query parameter the endpoint could not use
+#[derive(Debug)]
+struct BadRequest(String);
+
+impl std::fmt::Display for BadRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BadRequest {}
+
+async fn queues(State(app): State<Arc<Application>>) -> ApiResult {
+    let recent = app
+        .execution_service
+        .query_executions(ExecutionFilters {
+            limit: Some(QUEUE_SUMMARY_WINDOW),
+            ..Default::default()
+        })
+        .await?;
+
+    let mut queues: BTreeMap<String, (Option<JsonValue>, BTreeMap<&'static str, u64>)> =
+        BTreeMap::new();
+    for depth in app.execution_service.queue_depths().await? {
+        queues.entry(depth.queue.clone()).or_default().0 =
+            Some(json!({ "pending": depth.pending, "claimed": depth.claimed }));
+    }
+    for execution in &recent {
+        *queues
+            .entry(execution.queue.clone())
+            .or_default()
+            .1
+            .entry(execution.status.as_str())
+            .or_default() += 1;
+    }
+
+    let queues: Vec<JsonValue> = queues
+        .into_iter()
+        .map(|(name, (depth, counts))| {
+            let depth = depth.unwrap_or_else(|| json!({ "pending": 0, "claimed": 0 }));
+            json!({ "name": name, "depth": depth, "counts": counts })
+        })
+        .collect();
+    Ok(Json(json!({ "window": recent.len(), "queues": queues })))
+}
+
+async fn executions(
+    State(app): State<Arc<Application>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> ApiResult {
+    let page = app
+        .execution_service
+        .list_executions(execution_filters(&query)?)
+        .await?;
+    Ok(Json(
+        json!({ "executions": page.executions, "cursor": page.cursor }),
+    ))
+}
+
+async fn failures(
+    State(app): State<Arc<Application>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> ApiResult {
+    let filters = ExecutionFilters {
+        status: Some(ExecutionStatus::Failed),
+        ..execution_filters(&query)?
+    };
+    let page = app.execution_service.list_executions(filters).await?;
+    Ok(Json(
+        json!({ "failures": page.executions, "cursor": page.cursor }),
+    ))
+}
+
+/// Filters from the query string of an execution listing
+fn execution_filters(query: &HashMap<String, String>) -> Result<ExecutionFilters, BadRequest> {
+    let text = |key: &str| query.get(key).filter(|s| !s.is_empty()).cloned();
+    let time = |key: &str| {
+        text(key)
+            .map(|s| {
+                DateTime::parse_from_rfc3339(&s)
+                    .map(|t| t.with_timezone(&Utc))
+                    .map_err(|_| BadRequest(format!("Invalid {}: {}", key, s)))
+            })
+            .transpose()
+    };
+    let limit = match text("limit") {
+        Some(s) => s
+            .parse::<i64>()
+            .map_err(|_| BadRequest(format!("Invalid limit: {}", s)))?
+            .clamp(1, MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+    let after = match text("cursor") {
+        Some(s) => Some(
+            s.parse()
+                .map_err(|_| BadRequest(format!("Invalid cursor: {}", s)))?,
+        ),
+        None => None,
+    };
+
+    Ok(ExecutionFilters {
+        status: label(query, "status", "status")?,
+        exec_type: label(query, "type", "type")?,
+        queue: text("queue"),
+        target_name: text("target_name"),
+        parent_workflow_id: text("parent_workflow_id"),
+        namespace: text("namespace"),
+        // `tags.customer_id=123`
+        tags: query
+            .iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix("tags.")?, value)))
+            .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+        created_after: time("created_after")?,
+        created_before: time("created_before")?,
+        order: label(query, "order", "order")?.unwrap_or_default(),
+        after,
+        limit: Some(limit),
+        offset: None,
+    })
+}
+
+/// A lowercase label like "failed" from the query string
+fn label<T: DeserializeOwned>(
+    query: &HashMap<String, String>,
+    key: &str,
+    what: &str,
+) -> Result<Option<T>, BadRequest> {
+    match query.get(key).filter(|s| !s.is_empty()) {
+        Some(s) => serde_json::from_value(json!(s))
+            .map(Some)
+            .map_err(|_| BadRequest(format!("Unknown {}: {}", what, s))),
+        None => Ok(None),
+    }
+}
+
+async fn execution_detail(
+    State(app): State<Arc<Application>>,
+    Path(id): Path<String>,
+) -> ApiResult {
+    let Some(execution) = app.execution_service.get_execution(&id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    let descendants = app
+        .workflow_service
+        .get_workflow_descendants(&id, MAX_TREE_SIZE)
+        .await?;
+    let truncated = descendants.len() as i64 >= MAX_TREE_SIZE;
+    let suspended_on = if execution.status == ExecutionStatus::Suspended {
+        app.workflow_service.get_suspension_point(&id).await?
+    } else {
+        None
+    };
+
+    Ok(Json(json!({
+        "execution": execution,
+        "children": child_tree(&id, descendants),
+        "truncated": truncated,
+        "suspended_on": suspended_on,
+    })))
+}
+
+async fn execution_logs(State(app): State<Arc<Application>>, Path(id): Path<String>) -> ApiResult {
+    if app.execution_service.get_execution(&id).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+    let logs = app.execution_service.get_execution_logs(&id).await?;
+    Ok(Json(json!({ "logs": logs })))
+}
+
+/// Nest `descendants` under their parents, each with a `children` array
+fn child_tree(root_id: &str, descendants: Vec<Execution>) -> Vec<JsonValue> {
+    let mut by_parent: HashMap<String, Vec<Execution>> = HashMap::new();
+    for execution in descendants {
+        if let Some(parent) = execution.parent_workflow_id.clone() {
+            by_parent.entry(parent).or_default().push(execution);
+        }
+    }
+
+    fn nest(parent_id: &str, by_parent: &mut HashMap<String, Vec<Execution>>) -> Vec<JsonValue> {
+        let children = by_parent.remove(parent_id).unwrap_or_default();
+        children
+            .into_iter()
+            .map(|child| {
+                let grandchildren = nest(&child.id, by_parent);
+                let mut node = serde_json::to_value(&child).unwrap_or_default();
+                node["children"] = JsonValue::Array(grandchildren);
+                node
+            })
+            .collect()
+    }
+    nest(root_id, &mut by_parent)
+}
+
+async fn workflows(State(app): State<Arc<Application>>) -> ApiResult {
+    let workflows = app.workflow_service.list_workflows().await?;
+    Ok(Json(json!({ "workflows": workflows })))
+}
+
+async fn workflow_detail(
+    State(app): State<Arc<Application>>,
+    Path(name): Path<String>,
+) -> ApiResult {
+    let Some(source) = app.workflow_service.get_workflow_definition(&name).await? else {
+        return Err(ApiError::NotFound);
+    };
+    let config = app.workflow_service.effective_config(&name).await?;
+    Ok(Json(json!({
+        "name": name,
+        "source": source,
+        "config": config,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use sqlx::PgPool;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    fn app(pool: PgPool) -> Application {
+        let config = Config {
+            database: Default::default(),
+            worker: Default::default(),
+            claim_policy: Default::default(),
+            quotas: Default::default(),
+            slos: Vec::new(),
+            task_configs: Vec::new(),
+            diagnostics: Default::default(),
+            concurrency_groups: Vec::new(),
+            workflow_defaults: Default::default(),
+            schema_drift: Default::default(),
+            idempotency: Default::default(),
+            webhooks: Default::default(),
+            telemetry: Default::default(),
+            payloads: Default::default(),
+            encryption: Default::default(),
+            queue: Default::default(),
+            auth: Default::default(),
+        };
+        Application::with_pool(config, pool)
+    }
+
+    /// Serve `router` on a free local port, returning its address
+    async fn start_router(router: Router, limits: Limits) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_router(
+            listener,
+            router,
+            limits,
+            CancellationToken::new(),
+        ));
+        addr
+    }
+
+    /// Serve the dashboard for `app`, returning its base URL
+    async fn start(app: Arc<Application>) -> String {
+        let addr = start_router(router(app), Limits::default()).await;
+        format!("http://{}", addr)
+    }
+
+    async fn body(base: &str, target: &str) -> (u16, JsonValue) {
+        let response = reqwest::get(format!("{}{}", base, target)).await.unwrap();
+        let status = response.status().as_u16();
+        let text = response.text().await.unwrap();
+        (
+            status,
+            serde_json::from_str(&text).unwrap_or(JsonValue::Null),
+        )
+    }
+
+    #[test]
+    fn test_bearer_key() {
+        assert_eq!(
+            bearer_key("Bearer  rhythm_a_b").as_deref(),
+            Some("rhythm_a_b")
+        );
+        assert_eq!(
+            bearer_key("bearer rhythm_a_b").as_deref(),
+            Some("rhythm_a_b")
+        );
+        assert_eq!(bearer_key("Basic dXNlcg=="), None);
+        assert_eq!(bearer_key("rhythm_a_b"), None);
+    }
+
+    #[test]
+    fn test_execution_filters_reads_tags() {
+        let query = HashMap::from(
+            [
+                ("tags.customer_id", "123"),
+                ("tags.plan", ""),
+                ("tags.", "x"),
+                ("queue", "default"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        let filters = execution_filters(&query).unwrap();
+        assert_eq!(
+            filters.tags,
+            HashMap::from([("customer_id".to_string(), "123".to_string())])
+        );
+        assert_eq!(filters.queue.as_deref(), Some("default"));
+    }
+
+    fn ok_router() -> Router {
+        Router::new().route("/", get(|| async { "ok" }))
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_head_is_dropped() {
+        let limits = Limits {
+            head_timeout: Duration::from_millis(100),
+            connection_timeout: Duration::from_secs(30),
+            max_connections: 1,
+        };
+        let addr = start_router(ok_router(), limits).await;
+
+        // Takes the only connection and never finishes its request head
+        let mut slow = TcpStream::connect(addr).await.unwrap();
+        slow.write_all(b"GET / HTTP/1.1\r\nHost: x\r\n")
+            .await
+            .unwrap();
+
+        let mut rest = Vec::new();
+        let closed = tokio::time::timeout(Duration::from_secs(5), slow.read_to_end(&mut rest));
+        assert!(closed.await.is_ok(), "slow connection was kept open");
+
+        let response = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_connections_past_the_limit_wait() {
+        let limits = Limits {
+            max_connections: 1,
+            ..Limits::default()
+        };
+        let addr = start_router(ok_router(), limits).await;
+
+        let mut slow = TcpStream::connect(addr).await.unwrap();
+        slow.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let waiting = tokio::spawn(reqwest::get(format!("http://{}/", addr)));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+
+        drop(slow);
+        let response = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .expect("waiting connection was not served")
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[test]
+    fn test_child_tree_nests_descendants() {
+        let execution = |id: &str, parent: &str| Execution {
+            id: id.to_string(),
+            exec_type: crate::types::ExecutionType::Workflow,
+            target_name: "step".to_string(),
+            queue: "default".to_string(),
+            namespace: "default".to_string(),
+            status: ExecutionStatus::Pending,
+            inputs: json!({}),
+            output: None,
+            attempt: 0,
+            parent_workflow_id: Some(parent.to_string()),
+            created_at: Utc::now(),
+            completed_at: None,
+        };
+        let tree = child_tree(
+            "root",
+            vec![
+                execution("a", "root"),
+                execution("b", "root"),
+                execution("a1", "a"),
+            ],
+        );
+
+        let ids: Vec<&str> = tree.iter().map(|n| n["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(tree[0]["children"][0]["id"], "a1");
+        assert_eq!(tree[0]["children"][0]["children"], json!([]));
+        assert_eq!(tree[1]["children"], json!([]));
+    }
+
+    #[sqlx::test]
+    async fn test_routes_serve_page_lists_and_detail(pool: PgPool) -> Result<()> {
+        let app = Arc::new(app(pool.clone()));
+        app.workflow_service
+            .register_workflow(
+                "wait",
+                "result = await Task.run(\"work\", {})\nreturn result",
+            )
+            .await?;
+        let workflow_id = app
+            .workflow_service
+            .start_workflow("wait", json!({}), "default", None)
+            .await?;
+
+        let base = start(app.clone()).await;
+        let page = reqwest::get(format!("{}/", base)).await?;
+        assert_eq!(page.status(), 200);
+        assert!(page.headers()["content-type"]
+            .to_str()?
+            .starts_with("text/html"));
+        assert_eq!(page.headers()["cache-control"], "no-store");
+
+        let (status, list) = body(&base, "/api/executions?target_name=wait").await;
+        assert_eq!(status, 200);
+        assert_eq!(list["executions"][0]["id"], workflow_id.as_str());
+
+        let (status, queues) = body(&base, "/api/queues").await;
+        assert_eq!(status, 200);
+        assert_eq!(queues["queues"][0]["name"], "default");
+        assert_eq!(queues["queues"][0]["counts"]["pending"], 1);
+        assert_eq!(
+            queues["queues"][0]["depth"],
+            json!({ "pending": 1, "claimed": 0 })
+        );
+
+        let (status, workflows) = body(&base, "/api/workflows").await;
+        assert_eq!(status, 200);
+        assert_eq!(workflows["workflows"][0]["name"], "wait");
+        assert_eq!(workflows["workflows"][0]["versions"], 1);
+        let (status, workflow) = body(&base, "/api/workflows/wait").await;
+        assert_eq!(status, 200);
+        assert!(workflow["source"].as_str().unwrap().contains("Task.run"));
+        assert_eq!(body(&base, "/api/workflows/missing").await.0, 404);
+
+        let (status, failures) = body(&base, "/api/failures").await;
+        assert_eq!(status, 200);
+        assert_eq!(failures["failures"], json!([]));
+
+        let (status, detail) = body(&base, &format!("/api/executions/{}", workflow_id)).await;
+        assert_eq!(status, 200);
+        assert_eq!(detail["execution"]["target_name"], "wait");
+        assert_eq!(detail["children"], json!([]));
+        assert_eq!(detail["suspended_on"], JsonValue::Null);
+
+        let (status, logs) = body(&base, &format!("/api/executions/{}/logs", workflow_id)).await;
+        assert_eq!(status, 200);
+        assert_eq!(logs["logs"], json!([]));
+
+        assert_eq!(body(&base, "/api/executions/missing").await.0, 404);
+        assert_eq!(body(&base, "/api/executions/missing/logs").await.0, 404);
+        assert_eq!(body(&base, "/api/executions?status=bogus").await.0, 400);
+        assert_eq!(
+            body(&base, "/api/executions?created_after=today").await.0,
+            400
+        );
+        assert_eq!(body(&base, "/api/executions?cursor=nope").await.0, 400);
+        assert_eq!(body(&base, "/nope").await.0, 404);
+
+        let (status, none) = body(&base, "/api/executions?target_name=send%20email").await;
+        assert_eq!(status, 200);
+        assert_eq!(none["executions"], json!([]));
+
+        let post = reqwest::Client::new()
+            .post(format!("{}/api/queues", base))
+            .send()
+            .await?;
+        assert_eq!(post.status(), 405);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_api_wants_a_key_when_auth_is_required(pool: PgPool) -> Result<()> {
+        let mut app = app(pool);
+        app.config.auth.required = true;
+        let app = Arc::new(app);
+        let created = app
+            .api_key_service
+            .create("dashboard", &[Scope::ReadOnly])
+            .await?;
+        let base = start(app.clone()).await;
+        let with_key = |key: &str| {
+            let request = reqwest::Client::new()
+                .get(format!("{}/api/queues", base))
+                .bearer_auth(key)
+                .send();
+            async { request.await.unwrap().status().as_u16() }
+        };
+
+        // The page itself loads, to ask for a key
+        assert_eq!(body(&base, "/").await.0, 200);
+        assert_eq!(body(&base, "/api/queues").await.0, 401);
+        assert_eq!(with_key("rhythm_nope_nope").await, 401);
+        assert_eq!(with_key(&created.key).await, 200);
+
+        app.api_key_service.revoke(&created.api_key.id).await?;
+        assert_eq!(with_key(&created.key).await, 401);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_detail_reports_suspension_point(pool: PgPool) -> Result<()> {
+        let app = Arc::new(app(pool.clone()));
+        app.workflow_service
+            .register_workflow(
+                "wait",
+                "result = await Task.run(\"work\", {})\nreturn result",
+            )
+            .await?;
+        let workflow_id = app
+            .workflow_service
+            .start_workflow("wait", json!({}), "default", None)
+            .await?;
+
+        let execution = app
+            .execution_service
+            .get_execution(&workflow_id)
+            .await?
+            .unwrap();
+        crate::worker::run_workflow(&pool, execution).await?;
+
+        let base = start(app).await;
+        let (_, detail) = body(&base, &format!("/api/executions/{}", workflow_id)).await;
+        assert_eq!(detail["execution"]["status"], "suspended");
+        let children = detail["children"].as_array().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0]["target_name"], "work");
+        assert_eq!(children[0]["children"], json!([]));
+        assert_eq!(
+            detail["suspended_on"],
+            json!({ "t": "Execution", "v": children[0]["id"] })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! API Key Database Operations
+
+use anyhow::{Context, Result};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+
+use crate::auth::{ApiKey, Scope};
+
+const COLUMNS: &str = "id, name, scopes, created_at, last_used_at, revoked_at";
+
+fn api_key_from_row(row: &PgRow) -> ApiKey {
+    let scopes: Vec<String> = row.get("scopes");
+    ApiKey {
+        id: row.get("id"),
+        name: row.get("name"),
+        scopes: scopes.iter().filter_map(|s| s.parse().ok()).collect(),
+        created_at: row.get("created_at"),
+        last_used_at: row.get("last_used_at"),
+        revoked_at: row.get("revoked_at"),
+    }
+}
+
+/// Store a key under `id` by its hash
+pub async fn create_api_key<'e, E>(
+    executor: E,
+    id: &str,
+    name: &str,
+    key_hash: &str,
+    scopes: &[Scope],
+) -> Result<ApiKey>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let scopes: Vec<&str> = scopes.iter().map(|s| s.as_str()).collect();
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO api_keys (id, name, key_hash, scopes)
+        VALUES ($1, $2, $3, $4)
+        RETURNING {}
+        "#,
+        COLUMNS
+    ))
+    .bind(id)
+    .bind(name)
+    .bind(key_hash)
+    .bind(&scopes)
+    .fetch_one(executor)
+    .await
+    .context("Failed to create API key")?;
+    Ok(api_key_from_row(&row))
+}
+
+/// A key and the hash to check a presented key against
+pub async fn get_api_key<'e, E>(executor: E, id: &str) -> Result<Option<(ApiKey, String)>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let row = sqlx::query(&format!(
+        "SELECT {}, key_hash FROM api_keys WHERE id = $1",
+        COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to fetch API key")?;
+    Ok(row.map(|row| (api_key_from_row(&row), row.get("key_hash"))))
+}
+
+/// Every key, revoked ones included, oldest first
+pub async fn list_api_keys<'e, E>(executor: E) -> Result<Vec<ApiKey>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM api_keys ORDER BY created_at, id",
+        COLUMNS
+    ))
+    .fetch_all(executor)
+    .await
+    .context("Failed to list API keys")?;
+    Ok(rows.iter().map(api_key_from_row).collect())
+}
+
+/// Stop accepting a key; returns whether it existed and wasn't revoked yet
+pub async fn revoke_api_key<'e, E>(executor: E, id: &str) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result =
+        sqlx::query("UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+            .bind(id)
+            .execute(executor)
+            .await
+            .context("Failed to revoke API key")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Note that a key was just used
+pub async fn touch_api_key<'e, E>(executor: E, id: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(executor)
+        .await
+        .context("Failed to record API key use")?;
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Concurrency Group Operations
+//!
+//! Claims that respect `[[concurrency_groups]]` limits, and group occupancy.
+//!
+//! An execution holds a slot in its target's group from the claim that
+//! starts it until it finishes: while claimed but not yet started, running,
+//! or suspended. Only pending executions are held back, so suspended
+//! workflows always resume.
+
+use anyhow::{Context, Result};
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+
+use crate::config::ConcurrencyGroupConfig;
+use crate::types::GroupOccupancy;
+
+/// Member targets of `groups` as parallel arrays for `UNNEST`
+///
+/// A target listed in several groups belongs to the first.
+fn members(groups: &[ConcurrencyGroupConfig]) -> (Vec<String>, Vec<String>, Vec<i32>) {
+    let mut seen = HashSet::new();
+    let mut targets = Vec::new();
+    let mut names = Vec::new();
+    let mut limits = Vec::new();
+    for group in groups {
+        for target in &group.targets {
+            if seen.insert(target.as_str()) {
+                targets.push(target.clone());
+                names.push(group.name.clone());
+                limits.push(i32::try_from(group.max_running).unwrap_or(i32::MAX));
+            }
+        }
+    }
+    (targets, names, limits)
+}
+
+/// Claim the next piece of work from `queue` that fits its group's limit
+///
+/// Like `claim_work` with a limit of one, except that a pending execution
+/// of a group member is skipped while its group is full. Among waiting
+/// members, the target with the fewest executions in flight goes first, then
+/// the oldest entry. Grouped claims are serialized with an advisory lock so
+/// two workers can't both take the last slot. Work preferring another
+/// worker than `worker_id` is skipped as in `claim_work`.
+pub async fn claim_grouped_work(
+    pool: &PgPool,
+    queue: &str,
+    groups: &[ConcurrencyGroupConfig],
+    worker_id: Option<&str>,
+) -> Result<Option<String>> {
+    let (targets, names, limits) = members(groups);
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('rhythm.concurrency_groups', 0))")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to lock concurrency groups")?;
+
+    let claimed: Option<String> = sqlx::query_scalar(
+        r#"
+        WITH members AS (
+            SELECT * FROM UNNEST($2::TEXT[], $3::TEXT[], $4::INT[])
+                AS m(target_name, group_name, max_running)
+        ),
+        in_flight AS (
+            SELECT m.target_name, m.group_name, COUNT(*) AS running
+            FROM executions e
+            JOIN members m ON m.target_name = e.target_name
+            WHERE e.status IN ('running', 'suspended')
+               OR (e.status = 'pending' AND EXISTS (
+                   SELECT 1 FROM work_queue w
+                   WHERE w.execution_id = e.id AND w.claimed_until > NOW()
+               ))
+            GROUP BY m.target_name, m.group_name
+        ),
+        group_in_flight AS (
+            SELECT group_name, SUM(running) AS running
+            FROM in_flight
+            GROUP BY group_name
+        ),
+        to_claim AS (
+            SELECT wq.id
+            FROM work_queue wq
+            JOIN executions e ON e.id = wq.execution_id
+            LEFT JOIN members m ON m.target_name = e.target_name
+            LEFT JOIN in_flight t ON t.target_name = e.target_name
+            LEFT JOIN group_in_flight g ON g.group_name = m.group_name
+            WHERE wq.queue = $1
+              AND (wq.claimed_until IS NULL OR wq.claimed_until < NOW())
+              AND NOT EXISTS (
+                  SELECT 1 FROM work_queue wq2
+                  WHERE wq2.execution_id = wq.execution_id
+                    AND wq2.claimed_until IS NOT NULL
+                    AND wq2.claimed_until > NOW()
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM maintenance_window WHERE expires_at > NOW()
+              )
+              AND (e.status <> 'pending' OR e.expires_at IS NULL OR e.expires_at > NOW())
+              AND NOT e.paused
+              AND (
+                  m.group_name IS NULL
+                  OR e.status <> 'pending'
+                  OR COALESCE(g.running, 0) < m.max_running
+              )
+              AND (
+                  wq.preferred_worker_id IS NULL
+                  OR wq.preferred_worker_id = $5
+                  OR wq.preferred_until <= NOW()
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM claim_denials d
+                  WHERE d.execution_id = wq.execution_id
+                    AND d.worker_id = $5
+                    AND d.until > NOW()
+              )
+            ORDER BY
+                wq.priority DESC,
+                CASE WHEN e.status = 'pending' THEN COALESCE(t.running, 0) ELSE 0 END,
+                wq.created_at ASC
+            LIMIT 1
+            FOR UPDATE OF wq SKIP LOCKED
+        )
+        UPDATE work_queue
+        SET claimed_until = NOW() + INTERVAL '1 minute'
+        WHERE id IN (SELECT id FROM to_claim)
+        RETURNING execution_id
+        "#,
+    )
+    .bind(queue)
+    .bind(&targets)
+    .bind(&names)
+    .bind(&limits)
+    .bind(worker_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to claim grouped work")?;
+
+    tx.commit().await?;
+    Ok(claimed)
+}
+
+/// Slots in use and executions waiting in each of `groups`
+pub async fn get_group_occupancy<'e, E>(
+    executor: E,
+    groups: &[ConcurrencyGroupConfig],
+) -> Result<Vec<GroupOccupancy>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let (targets, names, _) = members(groups);
+
+    let rows = sqlx::query(
+        r#"
+        WITH members AS (
+            SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[]) AS m(target_name, group_name)
+        )
+        SELECT
+            m.group_name,
+            COUNT(*) FILTER (
+                WHERE e.status IN ('running', 'suspended') OR claimed.execution_id IS NOT NULL
+            ) AS running,
+            COUNT(*) FILTER (
+                WHERE e.status = 'pending' AND claimed.execution_id IS NULL
+            ) AS waiting
+        FROM members m
+        JOIN executions e ON e.target_name = m.target_name
+        LEFT JOIN LATERAL (
+            SELECT w.execution_id FROM work_queue w
+            WHERE w.execution_id = e.id AND w.claimed_until > NOW()
+            LIMIT 1
+        ) claimed ON e.status = 'pending'
+        WHERE e.status IN ('pending', 'running', 'suspended')
+        GROUP BY m.group_name
+        "#,
+    )
+    .bind(&targets)
+    .bind(&names)
+    .fetch_all(executor)
+    .await
+    .context("Failed to get concurrency group occupancy")?;
+
+    Ok(groups
+        .iter()
+        .map(|group| {
+            let row = rows
+                .iter()
+                .find(|row| row.get::<String, _>("group_name") == group.name);
+            GroupOccupancy {
+                name: group.name.clone(),
+                max_running: i64::from(group.max_running),
+                running: row.map_or(0, |row| row.get("running")),
+                waiting: row.map_or(0, |row| row.get("waiting")),
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,94 @@
+//! Execution History Database Operations
+//!
+//! Creation and status changes are logged by a trigger on `executions`; the
+//! events below are the ones only the runner sees.
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+
+use crate::types::{ExecutionEvent, ExecutionEventType};
+
+/// Append an event to an execution's history
+pub async fn record_event<'e, E>(
+    executor: E,
+    execution_id: &str,
+    event_type: ExecutionEventType,
+    details: Option<JsonValue>,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO execution_events (execution_id, event_type, details)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(execution_id)
+    .bind(event_type)
+    .bind(details)
+    .execute(executor)
+    .await
+    .context("Failed to record execution event")?;
+    Ok(())
+}
+
+/// Append an event of `event_type` to each of several executions' history
+///
+/// `details` line up with `execution_ids`.
+pub async fn record_events<'e, E>(
+    executor: E,
+    execution_ids: &[String],
+    event_type: ExecutionEventType,
+    details: &[JsonValue],
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO execution_events (execution_id, event_type, details)
+        SELECT event.execution_id, $2, event.details
+        FROM UNNEST($1::text[], $3::jsonb[]) WITH ORDINALITY AS event(execution_id, details, n)
+        ORDER BY event.n
+        "#,
+    )
+    .bind(execution_ids)
+    .bind(event_type)
+    .bind(details)
+    .execute(executor)
+    .await
+    .context("Failed to record execution events")?;
+    Ok(())
+}
+
+/// An execution's history, oldest first
+pub async fn get_history<'e, E>(executor: E, execution_id: &str) -> Result<Vec<ExecutionEvent>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT id, execution_id, event_type, details, created_at
+        FROM execution_events
+        WHERE execution_id = $1
+        ORDER BY id
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_all(executor)
+    .await
+    .context("Failed to fetch execution history")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ExecutionEvent {
+            id: row.get("id"),
+            execution_id: row.get("execution_id"),
+            event_type: row.get("event_type"),
+            details: row.get("details"),
+            created_at: row.get("created_at"),
+        })
+        .collect())
+}
@@ -0,0 +1,65 @@
+//! Execution Log Database Operations
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+
+use crate::types::{ExecutionLog, LogLevel};
+
+/// Append a message to an execution's logs
+pub async fn append_log<'e, E>(
+    executor: E,
+    execution_id: &str,
+    level: LogLevel,
+    message: &str,
+    data: Option<&JsonValue>,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO execution_logs (execution_id, level, message, data)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(execution_id)
+    .bind(level)
+    .bind(message)
+    .bind(data)
+    .execute(executor)
+    .await
+    .context("Failed to append execution log")?;
+    Ok(())
+}
+
+/// An execution's logs, in the order they were written
+pub async fn get_logs<'e, E>(executor: E, execution_id: &str) -> Result<Vec<ExecutionLog>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT id, execution_id, level, message, data, created_at
+        FROM execution_logs
+        WHERE execution_id = $1
+        ORDER BY id
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_all(executor)
+    .await
+    .context("Failed to fetch execution logs")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ExecutionLog {
+            id: row.get("id"),
+            execution_id: row.get("execution_id"),
+            level: row.get("level"),
+            message: row.get("message"),
+            data: row.get("data"),
+            created_at: row.get("created_at"),
+        })
+        .collect())
+}
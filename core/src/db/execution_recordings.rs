@@ -0,0 +1,147 @@
+//! Execution Recording Database Operations
+//!
+//! Executions opt in to recording, or every workflow is recorded with
+//! `[worker] record_runs`; what a recorded run read is saved with it (see
+//! `WorkflowService::get_recording`).
+
+use anyhow::{Context, Result};
+use sqlx::Row;
+
+use crate::executor::Val;
+use crate::types::ExecutionRecording;
+
+/// Values the execution was resumed with before it was recorded, from its
+/// history
+const EARLIER_RESUMES: &str = r#"
+    COALESCE(
+        (
+            SELECT jsonb_agg(details->'value' ORDER BY id)
+            FROM execution_events
+            WHERE execution_id = $1 AND event_type = 'resumed' AND details ? 'value'
+        ),
+        '[]'::jsonb
+    )
+"#;
+
+/// Values the execution read in runs before it was recorded, from its
+/// history
+const EARLIER_READS: &str = r#"
+    COALESCE(
+        (
+            SELECT jsonb_agg(r.value ORDER BY e.id, r.position)
+            FROM execution_events e,
+                jsonb_array_elements(e.details->'values') WITH ORDINALITY AS r(value, position)
+            WHERE e.execution_id = $1 AND e.event_type = 'read'
+        ),
+        '[]'::jsonb
+    )
+"#;
+
+/// Record the execution's runs from now on
+///
+/// The values it already read and was resumed with are taken from its
+/// history.
+pub async fn enable<'e, E>(executor: E, execution_id: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO execution_recordings (execution_id, reads, resumes)
+        VALUES ($1, {}, {})
+        ON CONFLICT (execution_id) DO NOTHING
+        "#,
+        EARLIER_READS, EARLIER_RESUMES
+    ))
+    .bind(execution_id)
+    .execute(executor)
+    .await
+    .context("Failed to enable execution recording")?;
+    Ok(())
+}
+
+/// Whether the execution's runs are recorded
+pub async fn is_enabled<'e, E>(executor: E, execution_id: &str) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM execution_recordings WHERE execution_id = $1)")
+        .bind(execution_id)
+        .fetch_one(executor)
+        .await
+        .context("Failed to check execution recording")
+}
+
+/// Save a run: the values it read and the values it resumed the workflow
+/// with
+///
+/// Enables recording if it wasn't. Called before the run's own reads and
+/// resumes are added to the history.
+pub async fn save_run<'e, E>(
+    executor: E,
+    execution_id: &str,
+    workflow_definition_id: i32,
+    reads: &[Val],
+    resumes: &[Val],
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let reads = serde_json::to_value(reads).context("Failed to serialize recorded reads")?;
+    let resumes = serde_json::to_value(resumes).context("Failed to serialize recorded resumes")?;
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO execution_recordings
+            (execution_id, recorded_at, workflow_definition_id, reads, resumes)
+        VALUES ($1, NOW(), $2, {} || $3, {} || $4)
+        ON CONFLICT (execution_id) DO UPDATE
+        SET recorded_at = NOW(),
+            workflow_definition_id = $2,
+            reads = execution_recordings.reads || $3,
+            resumes = execution_recordings.resumes || $4
+        "#,
+        EARLIER_READS, EARLIER_RESUMES
+    ))
+    .bind(execution_id)
+    .bind(workflow_definition_id)
+    .bind(reads)
+    .bind(resumes)
+    .execute(executor)
+    .await
+    .context("Failed to save execution recording")?;
+    Ok(())
+}
+
+/// The execution's recording, if it opted in
+pub async fn get_recording<'e, E>(
+    executor: E,
+    execution_id: &str,
+) -> Result<Option<ExecutionRecording>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let row = sqlx::query(
+        r#"
+        SELECT execution_id, enabled_at, recorded_at, workflow_definition_id, reads, resumes
+        FROM execution_recordings
+        WHERE execution_id = $1
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to fetch execution recording")?;
+
+    row.map(|row| {
+        Ok(ExecutionRecording {
+            execution_id: row.get("execution_id"),
+            enabled_at: row.get("enabled_at"),
+            recorded_at: row.get("recorded_at"),
+            workflow_definition_id: row.get("workflow_definition_id"),
+            reads: serde_json::from_value(row.get("reads")).context("Malformed recorded reads")?,
+            resumes: serde_json::from_value(row.get("resumes"))
+                .context("Malformed recorded resumes")?,
+        })
+    })
+    .transpose()
+}
@@ -2,10 +2,17 @@
 
 use anyhow::{Context, Result};
 use serde_json::Value as JsonValue;
+use sqlx::types::Json;
 use sqlx::{PgPool, Row};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::types::{CreateExecutionParams, Execution, ExecutionFilters, ExecutionStatus};
+use crate::invariants;
+use crate::telemetry::TraceContext;
+use crate::types::{
+    ChangeCursor, CreateExecutionParams, Execution, ExecutionFilters, ExecutionStatus,
+    ExecutionType, RetryPolicy, SortOrder,
+};
 
 pub async fn get_execution(pool: &PgPool, execution_id: &str) -> Result<Option<Execution>> {
     let result = sqlx::query(
@@ -24,6 +31,7 @@ pub async fn get_execution(pool: &PgPool, execution_id: &str) -> Result<Option<E
             exec_type: row.get("type"),
             target_name: row.get("target_name"),
             queue: row.get("queue"),
+            namespace: row.get("namespace"),
             status: row.get("status"),
             inputs: row.get("inputs"),
             output: row.get("output"),
@@ -38,6 +46,47 @@ pub async fn get_execution(pool: &PgPool, execution_id: &str) -> Result<Option<E
     Ok(None)
 }
 
+/// Lock an execution's row until `tx` ends and return its type and status
+pub async fn lock_execution(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+) -> Result<Option<(ExecutionType, ExecutionStatus)>> {
+    sqlx::query_as(
+        r#"
+        SELECT type, status FROM executions WHERE id = $1 FOR UPDATE
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .context("Failed to lock execution")
+}
+
+/// `lock_execution` for several executions at once, in ID order
+///
+/// Returns the type and status of those that exist.
+pub async fn lock_executions(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_ids: &[String],
+) -> Result<Vec<(String, ExecutionType, ExecutionStatus)>> {
+    sqlx::query_as(
+        r#"
+        SELECT id, type, status FROM executions
+        WHERE id = ANY($1)
+        ORDER BY id
+        FOR UPDATE
+        "#,
+    )
+    .bind(execution_ids)
+    .fetch_all(&mut **tx)
+    .await
+    .context("Failed to lock executions")
+}
+
+/// Create an execution
+///
+/// Children inherit their parent workflow's namespace and priority (unless
+/// given) and express lane.
 pub async fn create_execution(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     params: CreateExecutionParams,
@@ -54,8 +103,23 @@ pub async fn create_execution(
             r#"
             INSERT INTO executions (
                 id, type, target_name, queue, status,
-                inputs, parent_workflow_id
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                inputs, parent_workflow_id, namespace, expires_at, express,
+                retry_policy, priority, idempotency_key, traceparent, tags
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7,
+                COALESCE(
+                    $8,
+                    (SELECT namespace FROM executions WHERE id = $7),
+                    'default'
+                ),
+                NOW() + $9 * INTERVAL '1 second',
+                COALESCE((SELECT express FROM executions WHERE id = $7), false),
+                $10,
+                COALESCE($11, (SELECT priority FROM executions WHERE id = $7), 0),
+                $12,
+                COALESCE($13, (SELECT traceparent FROM executions WHERE id = $7), $14),
+                COALESCE((SELECT tags FROM executions WHERE id = $7), '{}') || $15
+            )
             ON CONFLICT (id) DO NOTHING
             RETURNING id, (xmax = 0) AS inserted
             "#,
@@ -67,6 +131,25 @@ pub async fn create_execution(
         .bind(ExecutionStatus::Pending)
         .bind(&current_params.inputs)
         .bind(&current_params.parent_workflow_id)
+        .bind(&current_params.namespace)
+        .bind(
+            current_params
+                .ttl_seconds
+                .map(|secs| i64::try_from(secs).unwrap_or(i64::MAX)),
+        )
+        .bind(
+            current_params
+                .retry_policy
+                .as_ref()
+                .map(serde_json::to_value)
+                .transpose()
+                .context("Failed to serialize retry policy")?,
+        )
+        .bind(current_params.priority)
+        .bind(&current_params.idempotency_key)
+        .bind(&current_params.traceparent)
+        .bind(TraceContext::new_root().to_string())
+        .bind(Json(&current_params.tags))
         .fetch_optional(&mut **tx)
         .await
         .context("Failed to create execution")?;
@@ -82,7 +165,11 @@ pub async fn create_execution(
                         .context("Failed to check existing execution status")?;
 
                 match existing {
-                    Some((ExecutionStatus::Failed,)) => {
+                    Some((
+                        ExecutionStatus::Failed
+                        | ExecutionStatus::Expired
+                        | ExecutionStatus::Cancelled,
+                    )) => {
                         sqlx::query("DELETE FROM executions WHERE id = $1")
                             .bind(&id)
                             .execute(&mut **tx)
@@ -109,6 +196,111 @@ pub async fn create_execution(
     }
 }
 
+/// Put an execution, and every execution it starts from now on, in the
+/// express lane (see `work_queue::EXPRESS_PRIORITY`)
+pub async fn mark_express<'e, E>(executor: E, execution_id: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query("UPDATE executions SET express = true WHERE id = $1")
+        .bind(execution_id)
+        .execute(executor)
+        .await
+        .context("Failed to mark execution express")?;
+    Ok(())
+}
+
+/// Pause or resume an execution; its work isn't claimed while paused
+///
+/// Returns `false` if it already was.
+pub async fn set_paused<'e, E>(executor: E, execution_id: &str, paused: bool) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query("UPDATE executions SET paused = $2 WHERE id = $1 AND paused <> $2")
+        .bind(execution_id)
+        .bind(paused)
+        .execute(executor)
+        .await
+        .context("Failed to pause execution")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Hold the lock on a concurrency key until `tx` ends
+///
+/// Also taken when a holder finishes and hands the key over, so what
+/// `concurrency_key_holder` returns stays true until `tx` ends.
+pub async fn lock_concurrency_key(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    key: &str,
+) -> Result<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('concurrency_key:' || $1))")
+        .bind(key)
+        .execute(&mut **tx)
+        .await
+        .context("Failed to lock concurrency key")?;
+    Ok(())
+}
+
+/// The unfinished workflow holding a concurrency key, if any
+pub async fn concurrency_key_holder<'e, E>(executor: E, key: &str) -> Result<Option<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT id FROM executions
+        WHERE concurrency_key = $1 AND status IN ('pending', 'running', 'suspended')
+        "#,
+    )
+    .bind(key)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to find concurrency key holder")
+}
+
+/// Workflows waiting for a concurrency key, oldest first
+pub async fn concurrency_key_waiters<'e, E>(executor: E, key: &str) -> Result<Vec<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT id FROM executions
+        WHERE waiting_for_key = $1 AND status = 'pending'
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(key)
+    .fetch_all(executor)
+    .await
+    .context("Failed to find concurrency key waiters")
+}
+
+/// Give an execution a concurrency key, or make it wait for the key
+pub async fn set_concurrency_key<'e, E>(
+    executor: E,
+    execution_id: &str,
+    key: &str,
+    waiting: bool,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let query = if waiting {
+        "UPDATE executions SET waiting_for_key = $2 WHERE id = $1"
+    } else {
+        "UPDATE executions SET concurrency_key = $2 WHERE id = $1"
+    };
+    sqlx::query(query)
+        .bind(execution_id)
+        .bind(key)
+        .execute(executor)
+        .await
+        .context("Failed to set concurrency key")?;
+    Ok(())
+}
+
 pub async fn start_execution_unless_finished<'e, E>(
     executor: E,
     execution_id: &str,
@@ -120,9 +312,11 @@ where
         r#"
         WITH updated AS (
             UPDATE executions
-            SET status = 'running'
+            SET status = 'running',
+                started_at = COALESCE(started_at, NOW()),
+                heartbeat_at = NOW()
             WHERE id = $1
-              AND status NOT IN ('completed', 'failed')
+              AND status NOT IN ('completed', 'failed', 'expired', 'cancelled')
             RETURNING *
         )
         SELECT * FROM updated
@@ -141,6 +335,7 @@ where
             exec_type: row.get("type"),
             target_name: row.get("target_name"),
             queue: row.get("queue"),
+            namespace: row.get("namespace"),
             status: row.get("status"),
             inputs: row.get("inputs"),
             output: row.get("output"),
@@ -165,12 +360,16 @@ where
 {
     let result = sqlx::query(
         r#"
+        WITH prev AS (
+            SELECT id, status FROM executions WHERE id = $2 FOR UPDATE
+        )
         UPDATE executions
         SET status = 'completed',
             output = $1,
             completed_at = NOW()
-        WHERE id = $2
-        RETURNING *
+        FROM prev
+        WHERE executions.id = prev.id
+        RETURNING executions.*, prev.status AS prev_status
         "#,
     )
     .bind(output)
@@ -185,6 +384,7 @@ where
             exec_type: row.get("type"),
             target_name: row.get("target_name"),
             queue: row.get("queue"),
+            namespace: row.get("namespace"),
             status: row.get("status"),
             inputs: row.get("inputs"),
             output: row.get("output"),
@@ -193,6 +393,12 @@ where
             created_at: row.get("created_at"),
             completed_at: row.get("completed_at"),
         };
+        invariants::check_transition(
+            &exec.id,
+            &exec.exec_type,
+            &row.get("prev_status"),
+            &exec.status,
+        )?;
         return Ok(Some(exec));
     }
 
@@ -209,12 +415,16 @@ where
 {
     let result = sqlx::query(
         r#"
+        WITH prev AS (
+            SELECT id, status FROM executions WHERE id = $2 FOR UPDATE
+        )
         UPDATE executions
         SET status = 'failed',
             output = $1,
             completed_at = NOW()
-        WHERE id = $2
-        RETURNING *
+        FROM prev
+        WHERE executions.id = prev.id
+        RETURNING executions.*, prev.status AS prev_status
         "#,
     )
     .bind(&output)
@@ -229,6 +439,7 @@ where
             exec_type: row.get("type"),
             target_name: row.get("target_name"),
             queue: row.get("queue"),
+            namespace: row.get("namespace"),
             status: row.get("status"),
             inputs: row.get("inputs"),
             output: row.get("output"),
@@ -237,36 +448,59 @@ where
             created_at: row.get("created_at"),
             completed_at: row.get("completed_at"),
         };
+        invariants::check_transition(
+            &exec.id,
+            &exec.exec_type,
+            &row.get("prev_status"),
+            &exec.status,
+        )?;
         return Ok(Some(exec));
     }
 
     Ok(None)
 }
 
-pub async fn suspend_execution<'e, E>(executor: E, execution_id: &str) -> Result<Option<Execution>>
+/// Complete or fail several executions at once
+///
+/// `statuses` (`Completed` or `Failed`) and `outputs` line up with
+/// `execution_ids`. The caller checks the transitions under
+/// `lock_executions`. Returns the finished executions.
+pub async fn finish_executions<'e, E>(
+    executor: E,
+    execution_ids: &[String],
+    statuses: &[ExecutionStatus],
+    outputs: &[JsonValue],
+) -> Result<Vec<Execution>>
 where
     E: sqlx::Executor<'e, Database = sqlx::Postgres>,
 {
-    let result = sqlx::query(
+    let statuses: Vec<&str> = statuses.iter().map(ExecutionStatus::as_str).collect();
+    let rows = sqlx::query(
         r#"
         UPDATE executions
-        SET status = 'suspended',
+        SET status = outcome.status,
+            output = outcome.output,
             completed_at = NOW()
-        WHERE id = $1
-        RETURNING *
+        FROM UNNEST($1::text[], $2::text[], $3::jsonb[]) AS outcome(id, status, output)
+        WHERE executions.id = outcome.id
+        RETURNING executions.*
         "#,
     )
-    .bind(execution_id)
-    .fetch_optional(executor)
+    .bind(execution_ids)
+    .bind(&statuses)
+    .bind(outputs)
+    .fetch_all(executor)
     .await
-    .context("Failed to suspend execution")?;
+    .context("Failed to finish executions")?;
 
-    if let Some(row) = result {
-        let exec = Execution {
+    Ok(rows
+        .into_iter()
+        .map(|row| Execution {
             id: row.get("id"),
             exec_type: row.get("type"),
             target_name: row.get("target_name"),
             queue: row.get("queue"),
+            namespace: row.get("namespace"),
             status: row.get("status"),
             inputs: row.get("inputs"),
             output: row.get("output"),
@@ -274,83 +508,241 @@ where
             parent_workflow_id: row.get("parent_workflow_id"),
             created_at: row.get("created_at"),
             completed_at: row.get("completed_at"),
-        };
-        return Ok(Some(exec));
-    }
-
-    Ok(None)
+        })
+        .collect())
 }
 
-/// Query executions with filters
+/// Expire pending executions whose TTL has run out
 ///
-/// Returns a list of executions matching the provided filters.
-pub async fn query_executions(pool: &PgPool, filters: ExecutionFilters) -> Result<Vec<Execution>> {
-    let mut query = String::from("SELECT * FROM executions WHERE 1=1");
-    let mut bind_count = 0;
-
-    // Build WHERE clause based on filters
-    if filters.parent_workflow_id.is_some() {
-        bind_count += 1;
-        query.push_str(&format!(" AND parent_workflow_id = ${}", bind_count));
-    }
-
-    if filters.status.is_some() {
-        bind_count += 1;
-        query.push_str(&format!(" AND status = ${}", bind_count));
-    }
-
-    if filters.target_name.is_some() {
-        bind_count += 1;
-        query.push_str(&format!(" AND target_name = ${}", bind_count));
-    }
-
-    query.push_str(" ORDER BY created_at DESC");
+/// Moves up to `limit` of them to `expired` with `output` as their error and
+/// returns them. Rows locked by another sweep are skipped.
+pub async fn expire_due_executions<'e, E>(
+    executor: E,
+    output: &JsonValue,
+    limit: i64,
+) -> Result<Vec<Execution>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        WITH due AS (
+            SELECT id
+            FROM executions
+            WHERE status = 'pending'
+              AND expires_at <= NOW()
+            ORDER BY expires_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        UPDATE executions
+        SET status = 'expired',
+            output = $1,
+            completed_at = NOW()
+        WHERE id IN (SELECT id FROM due)
+        RETURNING *
+        "#,
+    )
+    .bind(output)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context("Failed to expire executions")?;
 
-    if filters.limit.is_some() {
-        bind_count += 1;
-        query.push_str(&format!(" LIMIT ${}", bind_count));
-    }
+    Ok(rows
+        .into_iter()
+        .map(|row| Execution {
+            id: row.get("id"),
+            exec_type: row.get("type"),
+            target_name: row.get("target_name"),
+            queue: row.get("queue"),
+            namespace: row.get("namespace"),
+            status: row.get("status"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+            attempt: row.get("attempt"),
+            parent_workflow_id: row.get("parent_workflow_id"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+        })
+        .collect())
+}
 
-    if filters.offset.is_some() {
-        bind_count += 1;
-        query.push_str(&format!(" OFFSET ${}", bind_count));
-    }
+/// Record that `worker_id` is still running an execution
+///
+/// Returns `false` unless the execution is running.
+pub async fn record_heartbeat<'e, E>(
+    executor: E,
+    execution_id: &str,
+    worker_id: &str,
+) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE executions
+        SET heartbeat_at = NOW(),
+            worker_id = $2
+        WHERE id = $1
+          AND status = 'running'
+        "#,
+    )
+    .bind(execution_id)
+    .bind(worker_id)
+    .execute(executor)
+    .await
+    .context("Failed to record heartbeat")?;
 
-    // Build query with bindings
-    let mut sql_query = sqlx::query(&query);
+    Ok(result.rows_affected() > 0)
+}
 
-    if let Some(ref parent_id) = filters.parent_workflow_id {
-        sql_query = sql_query.bind(parent_id);
-    }
+/// `record_heartbeat` for several executions at once
+pub async fn record_heartbeats<'e, E>(
+    executor: E,
+    execution_ids: &[String],
+    worker_id: &str,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        UPDATE executions
+        SET heartbeat_at = NOW(),
+            worker_id = $2
+        WHERE id = ANY($1)
+          AND status = 'running'
+        "#,
+    )
+    .bind(execution_ids)
+    .bind(worker_id)
+    .execute(executor)
+    .await
+    .context("Failed to record heartbeats")?;
 
-    if let Some(ref status) = filters.status {
-        sql_query = sql_query.bind(status);
-    }
+    Ok(())
+}
 
-    if let Some(ref target_name) = filters.target_name {
-        sql_query = sql_query.bind(target_name);
-    }
+/// Lock running tasks whose last heartbeat is older than `timeout_secs`
+///
+/// Returns their IDs and attempt numbers, oldest heartbeat first.
+pub async fn lock_stale_tasks(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    timeout_secs: i64,
+    limit: i64,
+) -> Result<Vec<(String, i32)>> {
+    sqlx::query_as(
+        r#"
+        SELECT id, attempt
+        FROM executions
+        WHERE status = 'running'
+          AND type = 'task'
+          AND heartbeat_at < NOW() - $1 * INTERVAL '1 second'
+        ORDER BY heartbeat_at
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(timeout_secs)
+    .bind(limit)
+    .fetch_all(&mut **tx)
+    .await
+    .context("Failed to find stale tasks")
+}
 
-    if let Some(limit) = filters.limit {
-        sql_query = sql_query.bind(limit);
-    }
+/// Cancel an unfinished execution, storing `output` as its error
+///
+/// Returns `None` if the execution doesn't exist or already finished.
+pub async fn cancel_execution<'e, E>(
+    executor: E,
+    execution_id: &str,
+    output: &JsonValue,
+) -> Result<Option<Execution>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE executions
+        SET status = 'cancelled',
+            output = $2,
+            completed_at = NOW()
+        WHERE id = $1
+          AND status IN ('pending', 'running', 'suspended')
+        RETURNING *
+        "#,
+    )
+    .bind(execution_id)
+    .bind(output)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to cancel execution")?;
 
-    if let Some(offset) = filters.offset {
-        sql_query = sql_query.bind(offset);
-    }
+    Ok(result.map(|row| Execution {
+        id: row.get("id"),
+        exec_type: row.get("type"),
+        target_name: row.get("target_name"),
+        queue: row.get("queue"),
+        namespace: row.get("namespace"),
+        status: row.get("status"),
+        inputs: row.get("inputs"),
+        output: row.get("output"),
+        attempt: row.get("attempt"),
+        parent_workflow_id: row.get("parent_workflow_id"),
+        created_at: row.get("created_at"),
+        completed_at: row.get("completed_at"),
+    }))
+}
 
-    let rows = sql_query
-        .fetch_all(pool)
-        .await
-        .context("Failed to query executions")?;
+/// Cancel the unfinished executions a workflow started, at any depth
+///
+/// With `finish_running_tasks`, tasks a worker is running are left to
+/// finish; child workflows are cancelled either way. Returns the cancelled
+/// executions.
+pub async fn cancel_descendants<'e, E>(
+    executor: E,
+    workflow_id: &str,
+    output: &JsonValue,
+    finish_running_tasks: bool,
+) -> Result<Vec<Execution>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT id FROM executions WHERE parent_workflow_id = $1
+            UNION ALL
+            SELECT e.id FROM executions e JOIN tree ON e.parent_workflow_id = tree.id
+        )
+        UPDATE executions
+        SET status = 'cancelled',
+            output = $2,
+            completed_at = NOW()
+        WHERE id IN (SELECT id FROM tree)
+          AND (
+              status IN ('pending', 'suspended')
+              OR (status = 'running' AND (type <> 'task' OR NOT $3))
+          )
+        RETURNING *
+        "#,
+    )
+    .bind(workflow_id)
+    .bind(output)
+    .bind(finish_running_tasks)
+    .fetch_all(executor)
+    .await
+    .context("Failed to cancel child executions")?;
 
-    let executions = rows
+    Ok(rows
         .into_iter()
         .map(|row| Execution {
             id: row.get("id"),
             exec_type: row.get("type"),
             target_name: row.get("target_name"),
             queue: row.get("queue"),
+            namespace: row.get("namespace"),
             status: row.get("status"),
             inputs: row.get("inputs"),
             output: row.get("output"),
@@ -359,7 +751,798 @@ pub async fn query_executions(pool: &PgPool, filters: ExecutionFilters) -> Resul
             created_at: row.get("created_at"),
             completed_at: row.get("completed_at"),
         })
-        .collect();
+        .collect())
+}
 
-    Ok(executions)
+/// Executions started, directly or not, by `workflow_id`
+///
+/// Returned level by level, oldest first within a level, up to `limit`.
+pub async fn get_descendants<'e, E>(
+    executor: E,
+    workflow_id: &str,
+    limit: i64,
+) -> Result<Vec<Execution>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT id, 1 AS depth FROM executions WHERE parent_workflow_id = $1
+            UNION ALL
+            SELECT e.id, tree.depth + 1
+            FROM executions e JOIN tree ON e.parent_workflow_id = tree.id
+        )
+        SELECT executions.*
+        FROM executions JOIN tree ON executions.id = tree.id
+        ORDER BY tree.depth, executions.created_at, executions.id
+        LIMIT $2
+        "#,
+    )
+    .bind(workflow_id)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context("Failed to get descendant executions")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Execution {
+            id: row.get("id"),
+            exec_type: row.get("type"),
+            target_name: row.get("target_name"),
+            queue: row.get("queue"),
+            namespace: row.get("namespace"),
+            status: row.get("status"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+            attempt: row.get("attempt"),
+            parent_workflow_id: row.get("parent_workflow_id"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+        })
+        .collect())
+}
+
+/// The unfinished workflows under `workflow_id`, itself included
+///
+/// Each comes with its queue and depth (0 for `workflow_id`), deepest
+/// first and oldest first within a level.
+pub async fn get_unfinished_workflow_tree<'e, E>(
+    executor: E,
+    workflow_id: &str,
+) -> Result<Vec<(String, String, i32)>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_as(
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT id, 0 AS depth FROM executions WHERE id = $1
+            UNION ALL
+            SELECT e.id, tree.depth + 1
+            FROM executions e JOIN tree ON e.parent_workflow_id = tree.id
+            WHERE e.type = 'workflow'
+        )
+        SELECT executions.id, executions.queue, tree.depth
+        FROM executions JOIN tree ON executions.id = tree.id
+        WHERE executions.status IN ('pending', 'running', 'suspended')
+        ORDER BY tree.depth DESC, executions.created_at, executions.id
+        "#,
+    )
+    .bind(workflow_id)
+    .fetch_all(executor)
+    .await
+    .context("Failed to get unfinished workflow tree")
+}
+
+/// Ask unfinished executions to cancel once they have wound down
+///
+/// An execution already asked keeps its first reason.
+pub async fn request_cancel<'e, E>(
+    executor: E,
+    execution_ids: &[String],
+    reason: &str,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        UPDATE executions
+        SET cancel_requested = COALESCE(cancel_requested, $2)
+        WHERE id = ANY($1)
+          AND status IN ('pending', 'running', 'suspended')
+        "#,
+    )
+    .bind(execution_ids)
+    .bind(reason)
+    .execute(executor)
+    .await
+    .context("Failed to request cancellation")?;
+    Ok(())
+}
+
+/// Why an unfinished execution was asked to cancel, if it was
+pub async fn get_cancel_request<'e, E>(executor: E, execution_id: &str) -> Result<Option<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let reason: Option<Option<String>> = sqlx::query_scalar(
+        r#"
+        SELECT cancel_requested FROM executions
+        WHERE id = $1
+          AND status IN ('pending', 'running', 'suspended')
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to get cancellation request")?;
+    Ok(reason.flatten())
+}
+
+/// Whether a workflow `workflow_id` started is unfinished
+pub async fn has_unfinished_child_workflows<'e, E>(executor: E, workflow_id: &str) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM executions
+            WHERE parent_workflow_id = $1
+              AND type = 'workflow'
+              AND status IN ('pending', 'running', 'suspended')
+        )
+        "#,
+    )
+    .bind(workflow_id)
+    .fetch_one(executor)
+    .await
+    .context("Failed to check for unfinished child workflows")
+}
+
+/// Those of `execution_ids` that haven't finished
+pub async fn get_unfinished<'e, E>(executor: E, execution_ids: &[String]) -> Result<Vec<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT id FROM executions
+        WHERE id = ANY($1)
+          AND status IN ('pending', 'running', 'suspended')
+        ORDER BY id
+        "#,
+    )
+    .bind(execution_ids)
+    .fetch_all(executor)
+    .await
+    .context("Failed to get unfinished executions")
+}
+
+/// Cancel the tasks `workflow_ids` started that no worker has claimed
+///
+/// Returns the IDs of the cancelled tasks.
+pub async fn cancel_pending_child_tasks<'e, E>(
+    executor: E,
+    workflow_ids: &[String],
+    output: &JsonValue,
+) -> Result<Vec<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        UPDATE executions
+        SET status = 'cancelled',
+            output = $2,
+            completed_at = NOW()
+        WHERE parent_workflow_id = ANY($1)
+          AND type <> 'workflow'
+          AND status = 'pending'
+        RETURNING id
+        "#,
+    )
+    .bind(workflow_ids)
+    .bind(output)
+    .fetch_all(executor)
+    .await
+    .context("Failed to cancel pending child tasks")
+}
+
+pub async fn suspend_execution<'e, E>(executor: E, execution_id: &str) -> Result<Option<Execution>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        WITH prev AS (
+            SELECT id, status FROM executions WHERE id = $1 FOR UPDATE
+        )
+        UPDATE executions
+        SET status = 'suspended',
+            completed_at = NOW(),
+            runner_errors = 0
+        FROM prev
+        WHERE executions.id = prev.id
+        RETURNING executions.*, prev.status AS prev_status,
+            EXISTS (
+                SELECT 1 FROM workflow_execution_context c
+                WHERE c.execution_id = executions.id
+            ) AS has_context
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to suspend execution")?;
+
+    if let Some(row) = result {
+        let exec = Execution {
+            id: row.get("id"),
+            exec_type: row.get("type"),
+            target_name: row.get("target_name"),
+            queue: row.get("queue"),
+            namespace: row.get("namespace"),
+            status: row.get("status"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+            attempt: row.get("attempt"),
+            parent_workflow_id: row.get("parent_workflow_id"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+        };
+        invariants::check_transition(
+            &exec.id,
+            &exec.exec_type,
+            &row.get("prev_status"),
+            &exec.status,
+        )?;
+        invariants::check_suspended_context(&exec.id, &exec.exec_type, row.get("has_context"))?;
+        return Ok(Some(exec));
+    }
+
+    Ok(None)
+}
+
+/// Suspend a running workflow after a transient runner error
+///
+/// Returns its count of consecutive runner errors including this one, or
+/// `None` if it is no longer running.
+pub async fn suspend_after_runner_error<'e, E>(
+    executor: E,
+    execution_id: &str,
+) -> Result<Option<i32>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        UPDATE executions
+        SET status = 'suspended',
+            runner_errors = runner_errors + 1
+        WHERE id = $1
+          AND status = 'running'
+        RETURNING runner_errors
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to record runner error")
+}
+
+/// The retry policy an execution was created with, if any
+pub async fn get_retry_policy<'e, E>(executor: E, execution_id: &str) -> Result<Option<RetryPolicy>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let policy: Option<Option<JsonValue>> =
+        sqlx::query_scalar("SELECT retry_policy FROM executions WHERE id = $1")
+            .bind(execution_id)
+            .fetch_optional(executor)
+            .await
+            .context("Failed to get retry policy")?;
+    policy
+        .flatten()
+        .map(serde_json::from_value)
+        .transpose()
+        .context("Invalid stored retry policy")
+}
+
+/// The trace context an execution was created under, if any
+pub async fn get_traceparent<'e, E>(executor: E, execution_id: &str) -> Result<Option<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let traceparent: Option<Option<String>> =
+        sqlx::query_scalar("SELECT traceparent FROM executions WHERE id = $1")
+            .bind(execution_id)
+            .fetch_optional(executor)
+            .await
+            .context("Failed to get trace context")?;
+    Ok(traceparent.flatten())
+}
+
+/// An execution's tags, or `None` if there is no such execution
+pub async fn get_tags<'e, E>(
+    executor: E,
+    execution_id: &str,
+) -> Result<Option<HashMap<String, String>>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let tags: Option<Json<HashMap<String, String>>> =
+        sqlx::query_scalar("SELECT tags FROM executions WHERE id = $1")
+            .bind(execution_id)
+            .fetch_optional(executor)
+            .await
+            .context("Failed to get execution tags")?;
+    Ok(tags.map(|Json(tags)| tags))
+}
+
+/// Add `tags` to an execution's, replacing those with the same keys
+///
+/// Returns `false` if there is no such execution.
+pub async fn add_tags<'e, E>(
+    executor: E,
+    execution_id: &str,
+    tags: &HashMap<String, String>,
+) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query("UPDATE executions SET tags = tags || $2 WHERE id = $1")
+        .bind(execution_id)
+        .bind(Json(tags))
+        .execute(executor)
+        .await
+        .context("Failed to tag execution")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Inputs of the executions `workflow_id` started, by execution id
+pub async fn get_child_inputs<'e, E>(
+    executor: E,
+    workflow_id: &str,
+) -> Result<HashMap<String, JsonValue>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows: Vec<(String, JsonValue)> =
+        sqlx::query_as("SELECT id, inputs FROM executions WHERE parent_workflow_id = $1")
+            .bind(workflow_id)
+            .fetch_all(executor)
+            .await
+            .context("Failed to get child execution inputs")?;
+    Ok(rows.into_iter().collect())
+}
+
+/// The execution created with an idempotency key within the last `window`
+///
+/// Locks the key for the rest of the transaction first, so concurrent
+/// creations with the same key see each other's execution.
+pub async fn find_by_idempotency_key(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    queue: &str,
+    target_name: &str,
+    idempotency_key: &str,
+    window: std::time::Duration,
+) -> Result<Option<String>> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1 || '/' || $2 || '/' || $3, 0))")
+        .bind(queue)
+        .bind(target_name)
+        .bind(idempotency_key)
+        .execute(&mut **tx)
+        .await
+        .context("Failed to lock idempotency key")?;
+
+    sqlx::query_scalar(
+        r#"
+        SELECT id FROM executions
+        WHERE queue = $1 AND target_name = $2 AND idempotency_key = $3
+          AND created_at > NOW() - $4 * INTERVAL '1 second'
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(queue)
+    .bind(target_name)
+    .bind(idempotency_key)
+    .bind(window.as_secs_f64())
+    .fetch_optional(&mut **tx)
+    .await
+    .context("Failed to find execution by idempotency key")
+}
+
+/// Record that `execution_id` continues `continued_from` as a new execution
+pub async fn set_continued_from<'e, E>(
+    executor: E,
+    execution_id: &str,
+    continued_from: &str,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query("UPDATE executions SET continued_from = $2 WHERE id = $1")
+        .bind(execution_id)
+        .bind(continued_from)
+        .execute(executor)
+        .await
+        .context("Failed to link continued execution")?;
+    Ok(())
+}
+
+/// The execution `execution_id` continued as new, if it did
+pub async fn get_continuation<'e, E>(executor: E, execution_id: &str) -> Result<Option<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar("SELECT id FROM executions WHERE continued_from = $1")
+        .bind(execution_id)
+        .fetch_optional(executor)
+        .await
+        .context("Failed to get continued execution")
+}
+
+/// The execution `execution_id` was continued from, if any
+pub async fn get_continued_from<'e, E>(executor: E, execution_id: &str) -> Result<Option<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let continued_from: Option<Option<String>> =
+        sqlx::query_scalar("SELECT continued_from FROM executions WHERE id = $1")
+            .bind(execution_id)
+            .fetch_optional(executor)
+            .await
+            .context("Failed to get continued_from")?;
+    Ok(continued_from.flatten())
+}
+
+/// Put a failed attempt back to pending and count the next attempt
+///
+/// Only running executions are retried, so a failure reported for an
+/// execution that already finished leaves it alone.
+pub async fn retry_execution<'e, E>(executor: E, execution_id: &str) -> Result<Option<Execution>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE executions
+        SET status = 'pending',
+            attempt = attempt + 1
+        WHERE id = $1
+          AND status = 'running'
+        RETURNING *
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to retry execution")?;
+
+    if let Some(row) = result {
+        let exec = Execution {
+            id: row.get("id"),
+            exec_type: row.get("type"),
+            target_name: row.get("target_name"),
+            queue: row.get("queue"),
+            namespace: row.get("namespace"),
+            status: row.get("status"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+            attempt: row.get("attempt"),
+            parent_workflow_id: row.get("parent_workflow_id"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+        };
+        return Ok(Some(exec));
+    }
+
+    Ok(None)
+}
+
+/// An execution's stored inputs and output, as `list_payloads_page` reads them
+#[derive(Debug)]
+pub struct StoredPayloads {
+    pub execution_id: String,
+    pub inputs: JsonValue,
+    pub output: Option<JsonValue>,
+}
+
+/// Get the stored inputs and outputs of up to `limit` executions, by ID
+///
+/// Pages start after `after_execution_id`, so callers can walk the table.
+pub async fn list_payloads_page(
+    pool: &PgPool,
+    after_execution_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<StoredPayloads>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, inputs, output
+        FROM executions
+        WHERE $1::TEXT IS NULL OR id > $1
+        ORDER BY id
+        LIMIT $2
+        "#,
+    )
+    .bind(after_execution_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to list execution payloads")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| StoredPayloads {
+            execution_id: row.get("id"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+        })
+        .collect())
+}
+
+/// Replace an execution's stored inputs and output, unless they changed
+/// since `seen` was read
+///
+/// Returns whether they were replaced.
+pub async fn replace_payloads(
+    pool: &PgPool,
+    seen: &StoredPayloads,
+    inputs: &JsonValue,
+    output: Option<&JsonValue>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE executions
+        SET inputs = $4,
+            output = $5
+        WHERE id = $1
+          AND inputs = $2
+          AND output IS NOT DISTINCT FROM $3
+        "#,
+    )
+    .bind(&seen.execution_id)
+    .bind(&seen.inputs)
+    .bind(&seen.output)
+    .bind(inputs)
+    .bind(output)
+    .execute(pool)
+    .await
+    .context("Failed to replace execution payloads")?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Query executions with filters
+///
+/// Returns a list of executions matching the provided filters, ordered by
+/// creation time and then ID so that pages from `filters.after` are stable.
+pub async fn query_executions(pool: &PgPool, filters: ExecutionFilters) -> Result<Vec<Execution>> {
+    let mut query = String::from("SELECT * FROM executions WHERE 1=1");
+    let mut bind_count = 0;
+
+    // Build WHERE clause based on filters
+    if filters.parent_workflow_id.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND parent_workflow_id = ${}", bind_count));
+    }
+
+    if filters.status.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND status = ${}", bind_count));
+    }
+
+    if filters.exec_type.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND type = ${}", bind_count));
+    }
+
+    if filters.target_name.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND target_name = ${}", bind_count));
+    }
+
+    if filters.queue.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND queue = ${}", bind_count));
+    }
+
+    if filters.namespace.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND namespace = ${}", bind_count));
+    }
+
+    if !filters.tags.is_empty() {
+        bind_count += 1;
+        query.push_str(&format!(" AND tags @> ${}", bind_count));
+    }
+
+    if filters.created_after.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND created_at >= ${}", bind_count));
+    }
+
+    if filters.created_before.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND created_at < ${}", bind_count));
+    }
+
+    let (comparison, direction) = match filters.order {
+        SortOrder::NewestFirst => ("<", "DESC"),
+        SortOrder::OldestFirst => (">", "ASC"),
+    };
+
+    if filters.after.is_some() {
+        query.push_str(&format!(
+            " AND (created_at, id) {} (${}, ${})",
+            comparison,
+            bind_count + 1,
+            bind_count + 2
+        ));
+        bind_count += 2;
+    }
+
+    query.push_str(&format!(
+        " ORDER BY created_at {}, id {}",
+        direction, direction
+    ));
+
+    if filters.limit.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" LIMIT ${}", bind_count));
+    }
+
+    if filters.offset.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" OFFSET ${}", bind_count));
+    }
+
+    // Build query with bindings
+    let mut sql_query = sqlx::query(&query);
+
+    if let Some(ref parent_id) = filters.parent_workflow_id {
+        sql_query = sql_query.bind(parent_id);
+    }
+
+    if let Some(ref status) = filters.status {
+        sql_query = sql_query.bind(status);
+    }
+
+    if let Some(ref exec_type) = filters.exec_type {
+        sql_query = sql_query.bind(exec_type);
+    }
+
+    if let Some(ref target_name) = filters.target_name {
+        sql_query = sql_query.bind(target_name);
+    }
+
+    if let Some(ref queue) = filters.queue {
+        sql_query = sql_query.bind(queue);
+    }
+
+    if let Some(ref namespace) = filters.namespace {
+        sql_query = sql_query.bind(namespace);
+    }
+
+    if !filters.tags.is_empty() {
+        sql_query = sql_query.bind(Json(&filters.tags));
+    }
+
+    if let Some(created_after) = filters.created_after {
+        sql_query = sql_query.bind(created_after);
+    }
+
+    if let Some(created_before) = filters.created_before {
+        sql_query = sql_query.bind(created_before);
+    }
+
+    if let Some(ref after) = filters.after {
+        sql_query = sql_query.bind(after.created_at).bind(&after.id);
+    }
+
+    if let Some(limit) = filters.limit {
+        sql_query = sql_query.bind(limit);
+    }
+
+    if let Some(offset) = filters.offset {
+        sql_query = sql_query.bind(offset);
+    }
+
+    let rows = sql_query
+        .fetch_all(pool)
+        .await
+        .context("Failed to query executions")?;
+
+    let executions = rows
+        .into_iter()
+        .map(|row| Execution {
+            id: row.get("id"),
+            exec_type: row.get("type"),
+            target_name: row.get("target_name"),
+            queue: row.get("queue"),
+            namespace: row.get("namespace"),
+            status: row.get("status"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+            attempt: row.get("attempt"),
+            parent_workflow_id: row.get("parent_workflow_id"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+        })
+        .collect();
+
+    Ok(executions)
+}
+
+/// Fetch executions whose status changed after `since`
+///
+/// Only changes from transactions older than the current snapshot's xmin are
+/// returned, so a change committed late can never land behind a cursor that
+/// was already handed out. Returns the page, the next cursor, and whether the
+/// page was cut off by `limit`.
+pub async fn get_changes(
+    pool: &PgPool,
+    since: &ChangeCursor,
+    limit: i64,
+) -> Result<(Vec<Execution>, ChangeCursor, bool)> {
+    let fence: i64 =
+        sqlx::query_scalar("SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint")
+            .fetch_one(pool)
+            .await
+            .context("Failed to read snapshot xmin")?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT *, change_xid::text::bigint AS change_cursor
+        FROM executions
+        WHERE (change_xid, id) > ($1::bigint::text::xid8, $2)
+          AND change_xid < $3::bigint::text::xid8
+        ORDER BY change_xid, id
+        LIMIT $4
+        "#,
+    )
+    .bind(since.xid)
+    .bind(&since.after_id)
+    .bind(fence)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get execution changes")?;
+
+    let has_more = rows.len() as i64 == limit;
+    let cursor = match rows.last() {
+        Some(row) if has_more => ChangeCursor {
+            xid: row.get("change_cursor"),
+            after_id: row.get("id"),
+        },
+        _ => ChangeCursor {
+            xid: fence.max(since.xid),
+            after_id: String::new(),
+        },
+    };
+
+    let executions = rows
+        .into_iter()
+        .map(|row| Execution {
+            id: row.get("id"),
+            exec_type: row.get("type"),
+            target_name: row.get("target_name"),
+            queue: row.get("queue"),
+            namespace: row.get("namespace"),
+            status: row.get("status"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+            attempt: row.get("attempt"),
+            parent_workflow_id: row.get("parent_workflow_id"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+        })
+        .collect();
+
+    Ok((executions, cursor, has_more))
 }
@@ -0,0 +1,88 @@
+//! Maintenance Window Operations
+//!
+//! Backs `rhythm maintenance`: an open window pauses claims cluster-wide.
+
+use anyhow::{Context, Result};
+use sqlx::Row;
+
+use crate::types::MaintenanceWindow;
+
+/// Open a maintenance window for `duration_secs`, replacing any current one
+pub async fn enter_maintenance<'e, E>(
+    executor: E,
+    duration_secs: u64,
+    reason: Option<&str>,
+) -> Result<MaintenanceWindow>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let row = sqlx::query(
+        r#"
+        INSERT INTO maintenance_window (singleton, started_at, expires_at, reason)
+        VALUES (TRUE, NOW(), NOW() + $1 * INTERVAL '1 second', $2)
+        ON CONFLICT (singleton) DO UPDATE
+        SET started_at = EXCLUDED.started_at,
+            expires_at = EXCLUDED.expires_at,
+            reason = EXCLUDED.reason
+        RETURNING started_at, expires_at, reason
+        "#,
+    )
+    .bind(duration_secs as f64)
+    .bind(reason)
+    .fetch_one(executor)
+    .await
+    .context("Failed to enter maintenance")?;
+
+    Ok(window_from_row(&row))
+}
+
+/// Close the maintenance window; returns whether one was open
+pub async fn exit_maintenance<'e, E>(executor: E) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query("DELETE FROM maintenance_window WHERE expires_at > NOW()")
+        .execute(executor)
+        .await
+        .context("Failed to exit maintenance")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The open maintenance window, if any
+pub async fn get_maintenance_window<'e, E>(executor: E) -> Result<Option<MaintenanceWindow>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let row = sqlx::query(
+        r#"
+        SELECT started_at, expires_at, reason
+        FROM maintenance_window
+        WHERE expires_at > NOW()
+        "#,
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to get maintenance window")?;
+
+    Ok(row.as_ref().map(window_from_row))
+}
+
+/// Work claimed and still within its lease
+pub async fn count_in_flight_work<'e, E>(executor: E) -> Result<i64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar("SELECT COUNT(*) FROM work_queue WHERE claimed_until > NOW()")
+        .fetch_one(executor)
+        .await
+        .context("Failed to count in-flight work")
+}
+
+fn window_from_row(row: &sqlx::postgres::PgRow) -> MaintenanceWindow {
+    MaintenanceWindow {
+        started_at: row.get("started_at"),
+        expires_at: row.get("expires_at"),
+        reason: row.get("reason"),
+    }
+}
@@ -7,11 +7,24 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
+pub mod api_keys;
+pub mod concurrency_groups;
+pub mod execution_events;
+pub mod execution_logs;
+pub mod execution_recordings;
 pub mod executions;
+pub mod maintenance;
 pub mod migration;
+pub mod payload_shapes;
 pub mod pool;
+pub mod quotas;
 pub mod scheduled_queue;
 pub mod signals;
+pub mod slos;
+pub mod task_chunks;
+pub mod transfer;
+pub mod vm_traces;
+pub mod webhooks;
 pub mod work_queue;
 pub mod workflow_definitions;
 pub mod workflow_execution_context;
@@ -20,11 +33,16 @@ pub mod workflow_execution_context;
 mod tests;
 
 // Re-export commonly used items
+pub use concurrency_groups::*;
 pub use executions::*;
+pub use maintenance::*;
 pub use migration::*;
 pub use pool::*;
+pub use quotas::*;
 pub use scheduled_queue::*;
 pub use signals::*;
+pub use slos::*;
+pub use task_chunks::*;
 pub use work_queue::*;
 pub use workflow_definitions::*;
 pub use workflow_execution_context::*;
@@ -0,0 +1,160 @@
+//! Payload Shapes Database Operations
+//!
+//! Backs schema drift detection (see `payload_schema`).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+
+use crate::payload_schema::{PayloadKind, PayloadShapes};
+
+/// A completed execution's payloads
+pub struct CompletedPayloads {
+    pub target_name: String,
+    pub inputs: JsonValue,
+    pub output: Option<JsonValue>,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Completion time of the newest execution observed so far
+pub async fn get_watermark<'e, E>(executor: E) -> Result<Option<DateTime<Utc>>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar("SELECT MAX(last_seen_at) FROM payload_shapes")
+        .fetch_one(executor)
+        .await
+        .context("Failed to get payload shape watermark")
+}
+
+/// Executions completed after `after`, oldest first
+pub async fn get_completed_since<'e, E>(
+    executor: E,
+    after: Option<DateTime<Utc>>,
+    limit: i32,
+) -> Result<Vec<CompletedPayloads>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT target_name, inputs, output, completed_at
+        FROM executions
+        WHERE status = 'completed'
+          AND ($1::timestamptz IS NULL OR completed_at > $1)
+        ORDER BY completed_at
+        LIMIT $2
+        "#,
+    )
+    .bind(after)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context("Failed to fetch completed executions")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| CompletedPayloads {
+            target_name: row.get("target_name"),
+            inputs: row.get("inputs"),
+            output: row.get("output"),
+            completed_at: row.get("completed_at"),
+        })
+        .collect())
+}
+
+/// Record a shape seen at `seen_at`, returning the previous latest shape
+///
+/// The first shape recorded for a target and kind becomes its baseline.
+pub async fn observe_shape<'e, E>(
+    executor: E,
+    target_name: &str,
+    kind: PayloadKind,
+    shape: &JsonValue,
+    seen_at: DateTime<Utc>,
+) -> Result<Option<JsonValue>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        WITH prev AS (
+            SELECT latest FROM payload_shapes WHERE target_name = $1 AND kind = $2
+        )
+        INSERT INTO payload_shapes
+            (target_name, kind, baseline, latest, first_seen_at, last_seen_at)
+        VALUES ($1, $2, $3, $3, $4, $4)
+        ON CONFLICT (target_name, kind) DO UPDATE
+        SET latest = EXCLUDED.latest,
+            samples = payload_shapes.samples + 1,
+            last_seen_at = GREATEST(payload_shapes.last_seen_at, EXCLUDED.last_seen_at)
+        RETURNING (SELECT latest FROM prev)
+        "#,
+    )
+    .bind(target_name)
+    .bind(kind.as_str())
+    .bind(shape)
+    .bind(seen_at)
+    .fetch_one(executor)
+    .await
+    .context("Failed to record payload shape")
+}
+
+/// Recorded shapes, optionally for one target, by target and kind
+pub async fn list_shapes<'e, E>(
+    executor: E,
+    target_name: Option<&str>,
+) -> Result<Vec<PayloadShapes>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT target_name, kind, baseline, latest, samples, first_seen_at, last_seen_at
+        FROM payload_shapes
+        WHERE ($1::text IS NULL OR target_name = $1)
+        ORDER BY target_name, kind
+        "#,
+    )
+    .bind(target_name)
+    .fetch_all(executor)
+    .await
+    .context("Failed to list payload shapes")?;
+
+    rows.into_iter()
+        .map(|row| {
+            let kind: String = row.get("kind");
+            Ok(PayloadShapes {
+                target_name: row.get("target_name"),
+                kind: PayloadKind::parse(&kind)
+                    .with_context(|| format!("Unknown payload kind '{}'", kind))?,
+                baseline: row.get("baseline"),
+                latest: row.get("latest"),
+                samples: row.get("samples"),
+                first_seen_at: row.get("first_seen_at"),
+                last_seen_at: row.get("last_seen_at"),
+            })
+        })
+        .collect()
+}
+
+/// Make the latest shapes of a target its baseline, returning how many changed
+pub async fn accept_latest<'e, E>(executor: E, target_name: &str) -> Result<u64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE payload_shapes
+        SET baseline = latest
+        WHERE target_name = $1 AND baseline <> latest
+        "#,
+    )
+    .bind(target_name)
+    .execute(executor)
+    .await
+    .context("Failed to accept payload shapes")?;
+
+    Ok(result.rows_affected())
+}
@@ -4,9 +4,10 @@
 //! No caching, no static storage - just a factory function.
 
 use anyhow::{Context, Result};
-use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::PgPool;
 use std::env;
+use std::str::FromStr;
 
 use crate::config::Config;
 
@@ -44,7 +45,33 @@ pub async fn create_pool_with_max_connections(max_connections: u32) -> Result<Pg
 /// This is the recommended way to create a pool as it uses all configuration
 /// settings from the Config (max_connections, timeouts, etc.)
 pub async fn create_pool_from_config(config: &Config) -> Result<PgPool> {
-    let pool = PgPoolOptions::new()
+    let pool = pool_options(config)
+        .connect(database_url(config))
+        .await
+        .context("Failed to connect to database")?;
+
+    Ok(pool)
+}
+
+/// Create a pool whose sessions default to read-only transactions
+///
+/// Postgres rejects any write made through it, which also makes it safe to
+/// point at a hot standby or a restored backup.
+pub async fn create_read_only_pool_from_config(config: &Config) -> Result<PgPool> {
+    let options = PgConnectOptions::from_str(database_url(config))
+        .context("Invalid database URL")?
+        .options([("default_transaction_read_only", "on")]);
+
+    let pool = pool_options(config)
+        .connect_with(options)
+        .await
+        .context("Failed to connect to database")?;
+
+    Ok(pool)
+}
+
+fn pool_options(config: &Config) -> PgPoolOptions {
+    PgPoolOptions::new()
         .max_connections(config.database.max_connections)
         .min_connections(config.database.min_connections)
         .acquire_timeout(std::time::Duration::from_secs(
@@ -56,15 +83,12 @@ pub async fn create_pool_from_config(config: &Config) -> Result<PgPool> {
         .max_lifetime(std::time::Duration::from_secs(
             config.database.max_lifetime_secs,
         ))
-        .connect(
-            &config
-                .database
-                .url
-                .clone()
-                .expect("Database URL validated by config loading"),
-        )
-        .await
-        .context("Failed to connect to database")?;
+}
 
-    Ok(pool)
+fn database_url(config: &Config) -> &str {
+    config
+        .database
+        .url
+        .as_deref()
+        .expect("Database URL validated by config loading")
 }
@@ -0,0 +1,111 @@
+//! Namespace Usage Operations
+//!
+//! Backs the per-namespace execution quotas.
+
+use anyhow::{Context, Result};
+
+use crate::types::NamespaceUsage;
+
+/// Serialize quota checks for a namespace until the transaction ends
+///
+/// Without this, two concurrent creations could both see room for one more
+/// execution and both insert.
+pub async fn lock_namespace(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    namespace: &str,
+) -> Result<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('rhythm.quota:' || $1, 0))")
+        .bind(namespace)
+        .execute(&mut **tx)
+        .await
+        .context("Failed to lock namespace for quota check")?;
+
+    Ok(())
+}
+
+/// Namespace an execution will be created in
+///
+/// An explicit namespace wins, then the parent workflow's, then `default`.
+pub async fn resolve_namespace(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    namespace: Option<&str>,
+    parent_workflow_id: Option<&str>,
+) -> Result<String> {
+    if let Some(namespace) = namespace {
+        return Ok(namespace.to_string());
+    }
+
+    let inherited: Option<String> = match parent_workflow_id {
+        Some(parent_id) => sqlx::query_scalar("SELECT namespace FROM executions WHERE id = $1")
+            .bind(parent_id)
+            .fetch_optional(&mut **tx)
+            .await
+            .context("Failed to look up parent namespace")?,
+        None => None,
+    };
+
+    Ok(inherited.unwrap_or_else(|| crate::types::DEFAULT_NAMESPACE.to_string()))
+}
+
+/// Current usage counted against a namespace's quotas
+///
+/// Storage is the on-disk (possibly compressed) size of inputs and outputs.
+pub async fn get_namespace_usage(pool: &sqlx::PgPool, namespace: &str) -> Result<NamespaceUsage> {
+    Ok(NamespaceUsage {
+        namespace: namespace.to_string(),
+        running: count_running(pool, namespace).await?,
+        enqueued_last_minute: count_enqueued_last_minute(pool, namespace).await?,
+        storage_bytes: get_storage_bytes(pool, namespace).await?,
+    })
+}
+
+/// Executions in a namespace that have not finished
+pub async fn count_running<'e, E>(executor: E, namespace: &str) -> Result<i64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM executions
+        WHERE namespace = $1 AND status IN ('pending', 'running', 'suspended')
+        "#,
+    )
+    .bind(namespace)
+    .fetch_one(executor)
+    .await
+    .context("Failed to count running executions")
+}
+
+/// Executions created in a namespace in the trailing 60 seconds
+pub async fn count_enqueued_last_minute<'e, E>(executor: E, namespace: &str) -> Result<i64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM executions
+        WHERE namespace = $1 AND created_at > NOW() - INTERVAL '1 minute'
+        "#,
+    )
+    .bind(namespace)
+    .fetch_one(executor)
+    .await
+    .context("Failed to count recent executions")
+}
+
+/// Bytes of inputs and outputs stored in a namespace, as the
+/// `namespace_storage` trigger keeps count
+pub async fn get_storage_bytes<'e, E>(executor: E, namespace: &str) -> Result<i64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        "SELECT COALESCE(SUM(bytes), 0)::BIGINT FROM namespace_storage WHERE namespace = $1",
+    )
+    .bind(namespace)
+    .fetch_one(executor)
+    .await
+    .context("Failed to get namespace storage")
+}
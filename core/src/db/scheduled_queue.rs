@@ -96,3 +96,85 @@ pub async fn delete_items(
 
     Ok(result.rows_affected())
 }
+
+/// A scheduled item coming due
+#[derive(Debug)]
+pub struct UpcomingItem {
+    pub id: Uuid,
+    pub run_at: NaiveDateTime,
+    /// Milliseconds until it is due by the database's clock, rounded up;
+    /// zero or less if it is due already
+    pub due_in_ms: i64,
+}
+
+/// Items due within `within_ms`, in due order, continuing after the
+/// `(run_at, id)` of `after` if given
+pub async fn upcoming_items<'e, E>(
+    executor: E,
+    within_ms: i64,
+    after: Option<(NaiveDateTime, Uuid)>,
+    limit: i64,
+) -> Result<Vec<UpcomingItem>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let (after_run_at, after_id) = after.unzip();
+    let rows = sqlx::query(
+        r#"
+        SELECT id, run_at, CEIL(EXTRACT(EPOCH FROM run_at - NOW()) * 1000)::BIGINT AS due_in_ms
+        FROM scheduled_queue
+        WHERE run_at <= NOW() + $1 * INTERVAL '1 millisecond'
+          AND ($2::TIMESTAMP IS NULL OR (run_at, id) > ($2, $3))
+        ORDER BY run_at ASC, id ASC
+        LIMIT $4
+        "#,
+    )
+    .bind(within_ms)
+    .bind(after_run_at)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context("Failed to load upcoming items")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| UpcomingItem {
+            id: row.get("id"),
+            run_at: row.get("run_at"),
+            due_in_ms: row.get("due_in_ms"),
+        })
+        .collect())
+}
+
+/// Claim the items of `ids` that are due, locked for update
+///
+/// Skips items not due yet and items another transaction holds. Must be
+/// called within a transaction.
+pub async fn claim_due_items(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ids: &[Uuid],
+) -> Result<Vec<ScheduledItem>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, run_at, params
+        FROM scheduled_queue
+        WHERE id = ANY($1) AND run_at <= NOW()
+        ORDER BY run_at ASC
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(ids)
+    .fetch_all(&mut **tx)
+    .await
+    .context("Failed to claim due items")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ScheduledItem {
+            id: row.get("id"),
+            run_at: row.get("run_at"),
+            params: row.get("params"),
+        })
+        .collect())
+}
@@ -0,0 +1,56 @@
+//! Claim Latency Operations
+//!
+//! Backs the claim latency SLOs.
+
+use anyhow::{Context, Result};
+use sqlx::Row;
+
+use crate::types::ClaimLatencyCounts;
+
+/// Count recent executions and how many were claimed within `threshold_ms`
+///
+/// Covers executions created in the trailing `window_secs`, optionally
+/// limited to a queue and target. An unclaimed execution only counts once it
+/// has waited longer than the threshold, as a miss. External executions are
+/// never claimed and are left out.
+pub async fn get_claim_latency_counts<'e, E>(
+    executor: E,
+    queue: Option<&str>,
+    target_name: Option<&str>,
+    threshold_ms: u64,
+    window_secs: u64,
+) -> Result<ClaimLatencyCounts>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let row = sqlx::query(
+        r#"
+        SELECT
+            COUNT(*) FILTER (
+                WHERE started_at IS NOT NULL
+                   OR (status = 'pending' AND created_at <= NOW() - $3 * INTERVAL '1 millisecond')
+            ) AS total,
+            COUNT(*) FILTER (
+                WHERE started_at IS NOT NULL
+                  AND started_at - created_at <= $3 * INTERVAL '1 millisecond'
+            ) AS within
+        FROM executions
+        WHERE created_at >= NOW() - $4 * INTERVAL '1 second'
+          AND type <> 'external'
+          AND ($1::text IS NULL OR queue = $1)
+          AND ($2::text IS NULL OR target_name = $2)
+        "#,
+    )
+    .bind(queue)
+    .bind(target_name)
+    .bind(threshold_ms as f64)
+    .bind(window_secs as f64)
+    .fetch_one(executor)
+    .await
+    .context("Failed to count claim latencies")?;
+
+    Ok(ClaimLatencyCounts {
+        total: row.get("total"),
+        within: row.get("within"),
+    })
+}
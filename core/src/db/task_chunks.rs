@@ -0,0 +1,52 @@
+//! Task Chunks Database Operations
+//!
+//! Stores the partial results tasks emit while running, numbered from 0 in
+//! emit order.
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+
+/// Append a chunk to a task's partial results, returning its number
+///
+/// Callers lock the task's row first (`lock_execution`) so concurrent emits
+/// are numbered one after the other.
+pub async fn append_chunk(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+    chunk: &JsonValue,
+) -> Result<i32> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO task_chunks (execution_id, seq, chunk)
+        SELECT $1, COALESCE(MAX(seq) + 1, 0), $2
+        FROM task_chunks
+        WHERE execution_id = $1
+        RETURNING seq
+        "#,
+    )
+    .bind(execution_id)
+    .bind(chunk)
+    .fetch_one(&mut **tx)
+    .await
+    .context("Failed to append task chunk")
+}
+
+/// Chunks of a task numbered `from` onwards, in order
+pub async fn get_chunks<'e, E>(executor: E, execution_id: &str, from: i32) -> Result<Vec<JsonValue>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT chunk
+        FROM task_chunks
+        WHERE execution_id = $1 AND seq >= $2
+        ORDER BY seq
+        "#,
+    )
+    .bind(execution_id)
+    .bind(from)
+    .fetch_all(executor)
+    .await
+    .context("Failed to get task chunks")
+}
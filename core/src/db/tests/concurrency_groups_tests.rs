@@ -0,0 +1,115 @@
+//! Tests for concurrency group claims and occupancy
+
+use crate::config::ConcurrencyGroupConfig;
+use crate::db::{
+    claim_grouped_work, complete_execution, enqueue_work, get_group_occupancy,
+    start_execution_unless_finished,
+};
+use crate::types::{CreateExecutionParams, ExecutionType};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// Helper to create and enqueue a test execution
+async fn create_queued_execution(pool: &PgPool, id: &str, target_name: &str) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    let params = CreateExecutionParams {
+        id: Some(id.to_string()),
+        exec_type: ExecutionType::Task,
+        target_name: target_name.to_string(),
+        queue: "default".to_string(),
+        inputs: serde_json::json!({}),
+        parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
+    };
+    crate::db::executions::create_execution(&mut tx, params).await?;
+    enqueue_work(&mut *tx, id, "default", 0).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+fn exports_group(max_running: u32) -> Vec<ConcurrencyGroupConfig> {
+    vec![ConcurrencyGroupConfig {
+        name: "exports".to_string(),
+        max_running,
+        targets: vec!["export_csv".to_string(), "export_pdf".to_string()],
+    }]
+}
+
+#[sqlx::test]
+async fn test_full_group_holds_back_members_only(pool: PgPool) -> anyhow::Result<()> {
+    let groups = exports_group(1);
+    create_queued_execution(&pool, "csv1", "export_csv").await?;
+    create_queued_execution(&pool, "pdf1", "export_pdf").await?;
+    create_queued_execution(&pool, "other", "send_email").await?;
+
+    assert_eq!(
+        claim_grouped_work(&pool, "default", &groups, None).await?,
+        Some("csv1".to_string())
+    );
+    // The claimed member holds the only slot even before it starts
+    assert_eq!(
+        claim_grouped_work(&pool, "default", &groups, None).await?,
+        Some("other".to_string())
+    );
+    assert_eq!(
+        claim_grouped_work(&pool, "default", &groups, None).await?,
+        None
+    );
+
+    // Finishing frees the slot
+    start_execution_unless_finished(&pool, "csv1").await?;
+    complete_execution(&pool, "csv1", serde_json::json!(null)).await?;
+    assert_eq!(
+        claim_grouped_work(&pool, "default", &groups, None).await?,
+        Some("pdf1".to_string())
+    );
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_least_busy_member_goes_first(pool: PgPool) -> anyhow::Result<()> {
+    let groups = exports_group(3);
+    create_queued_execution(&pool, "csv1", "export_csv").await?;
+    create_queued_execution(&pool, "csv2", "export_csv").await?;
+    create_queued_execution(&pool, "csv3", "export_csv").await?;
+    create_queued_execution(&pool, "pdf1", "export_pdf").await?;
+
+    let mut claimed = Vec::new();
+    while let Some(id) = claim_grouped_work(&pool, "default", &groups, None).await? {
+        claimed.push(id);
+    }
+
+    // pdf1 is newest but its target has nothing in flight after csv1 starts
+    assert_eq!(claimed, vec!["csv1", "pdf1", "csv2"]);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_group_occupancy(pool: PgPool) -> anyhow::Result<()> {
+    let groups = exports_group(2);
+    create_queued_execution(&pool, "csv1", "export_csv").await?;
+    create_queued_execution(&pool, "csv2", "export_csv").await?;
+    create_queued_execution(&pool, "pdf1", "export_pdf").await?;
+
+    let occupancy = get_group_occupancy(&pool, &groups).await?;
+    assert_eq!(occupancy.len(), 1);
+    assert_eq!((occupancy[0].running, occupancy[0].waiting), (0, 3));
+
+    claim_grouped_work(&pool, "default", &groups, None).await?;
+    let claimed = claim_grouped_work(&pool, "default", &groups, None)
+        .await?
+        .expect("second slot should be free");
+    start_execution_unless_finished(&pool, &claimed).await?;
+
+    let occupancy = get_group_occupancy(&pool, &groups).await?;
+    assert_eq!(occupancy[0].name, "exports");
+    assert_eq!(occupancy[0].max_running, 2);
+    assert_eq!((occupancy[0].running, occupancy[0].waiting), (2, 1));
+    Ok(())
+}
@@ -3,6 +3,7 @@
 use crate::db::executions::{complete_execution, fail_execution, start_execution_unless_finished};
 use crate::types::{CreateExecutionParams, ExecutionStatus, ExecutionType};
 use sqlx::PgPool;
+use std::collections::HashMap;
 
 /// Helper to create test executions
 async fn create_test_execution(pool: &PgPool, id: &str) -> anyhow::Result<()> {
@@ -14,6 +15,13 @@ async fn create_test_execution(pool: &PgPool, id: &str) -> anyhow::Result<()> {
         queue: "default".to_string(),
         inputs: serde_json::json!({}),
         parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
     };
     crate::db::executions::create_execution(&mut tx, params).await?;
     tx.commit().await?;
@@ -103,3 +111,252 @@ async fn test_start_execution_unless_finished_starts_suspended(pool: PgPool) ->
     );
     Ok(())
 }
+
+#[sqlx::test]
+async fn test_finishing_twice_is_an_invariant_violation(pool: PgPool) -> anyhow::Result<()> {
+    use crate::invariants::InvariantViolation;
+
+    create_test_execution(&pool, "exec1").await?;
+    start_execution_unless_finished(&pool, "exec1").await?;
+    complete_execution(&pool, "exec1", serde_json::json!("done")).await?;
+
+    // Callers propagate the error out of their transaction, rolling it back
+    let mut tx = pool.begin().await?;
+    let err = fail_execution(&mut *tx, "exec1", serde_json::json!({"error": "late"}))
+        .await
+        .unwrap_err();
+    drop(tx);
+    let violation = err
+        .downcast_ref::<InvariantViolation>()
+        .expect("should be an InvariantViolation");
+    assert_eq!(violation.execution_id, "exec1");
+
+    // The first outcome stands
+    let execution = crate::db::executions::get_execution(&pool, "exec1")
+        .await?
+        .expect("execution should exist");
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+    assert_eq!(execution.output, Some(serde_json::json!("done")));
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_get_changes_returns_created_then_only_changed(pool: PgPool) -> anyhow::Result<()> {
+    use crate::db::executions::get_changes;
+    use crate::types::ChangeCursor;
+
+    create_test_execution(&pool, "exec1").await?;
+    create_test_execution(&pool, "exec2").await?;
+
+    let (changes, cursor, has_more) = get_changes(&pool, &ChangeCursor::default(), 100).await?;
+    let ids: Vec<&str> = changes.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, vec!["exec1", "exec2"]);
+    assert!(!has_more);
+
+    // Nothing new since the cursor
+    let (changes, cursor, _) = get_changes(&pool, &cursor, 100).await?;
+    assert!(changes.is_empty());
+
+    // A status change shows up once
+    start_execution_unless_finished(&pool, "exec2").await?;
+    let (changes, cursor, _) = get_changes(&pool, &cursor, 100).await?;
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].id, "exec2");
+    assert_eq!(changes[0].status, ExecutionStatus::Running);
+
+    let (changes, _, _) = get_changes(&pool, &cursor, 100).await?;
+    assert!(changes.is_empty());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_get_changes_pages_within_one_transaction(pool: PgPool) -> anyhow::Result<()> {
+    use crate::db::executions::get_changes;
+    use crate::types::ChangeCursor;
+
+    // All three rows share one transaction id, so paging must split on id
+    let mut tx = pool.begin().await?;
+    for id in ["a", "b", "c"] {
+        let params = CreateExecutionParams {
+            id: Some(id.to_string()),
+            exec_type: ExecutionType::Task,
+            target_name: "test_task".to_string(),
+            queue: "default".to_string(),
+            inputs: serde_json::json!({}),
+            parent_workflow_id: None,
+            namespace: None,
+            ttl_seconds: None,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            traceparent: None,
+            tags: HashMap::new(),
+        };
+        crate::db::executions::create_execution(&mut tx, params).await?;
+    }
+    tx.commit().await?;
+
+    let (page1, cursor, has_more) = get_changes(&pool, &ChangeCursor::default(), 2).await?;
+    assert!(has_more);
+    assert_eq!(page1.len(), 2);
+    assert_eq!(cursor.after_id, "b");
+
+    // Cursor survives a string round-trip
+    let cursor: ChangeCursor = cursor.to_string().parse()?;
+    let (page2, _, has_more) = get_changes(&pool, &cursor, 2).await?;
+    assert!(!has_more);
+    assert_eq!(page2.len(), 1);
+    assert_eq!(page2[0].id, "c");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_get_changes_holds_back_behind_open_transaction(pool: PgPool) -> anyhow::Result<()> {
+    use crate::db::executions::get_changes;
+    use crate::types::ChangeCursor;
+
+    create_test_execution(&pool, "exec1").await?;
+    let (_, cursor, _) = get_changes(&pool, &ChangeCursor::default(), 100).await?;
+
+    // An older transaction is still open while a newer one commits
+    let mut open_tx = pool.begin().await?;
+    sqlx::query("UPDATE executions SET status = 'running' WHERE id = 'exec1'")
+        .execute(&mut *open_tx)
+        .await?;
+    create_test_execution(&pool, "exec2").await?;
+
+    let (changes, held_cursor, _) = get_changes(&pool, &cursor, 100).await?;
+    assert!(
+        changes.is_empty(),
+        "Newer commits must wait until older transactions finish"
+    );
+
+    open_tx.commit().await?;
+
+    let (changes, _, _) = get_changes(&pool, &held_cursor, 100).await?;
+    let ids: Vec<&str> = changes.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, vec!["exec1", "exec2"]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_query_executions_filters_by_queue(pool: PgPool) -> anyhow::Result<()> {
+    create_test_execution(&pool, "exec1").await?;
+    create_test_execution(&pool, "exec2").await?;
+    sqlx::query("UPDATE executions SET queue = 'billing' WHERE id = 'exec2'")
+        .execute(&pool)
+        .await?;
+
+    let executions = crate::db::executions::query_executions(
+        &pool,
+        crate::types::ExecutionFilters {
+            queue: Some("billing".to_string()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let ids: Vec<&str> = executions.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, vec!["exec2"]);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_query_executions_keyset_pages(pool: PgPool) -> anyhow::Result<()> {
+    use crate::types::{ExecutionFilters, ExecutionPageCursor, SortOrder};
+
+    // exec2 and exec3 share a creation time, so the ID breaks the tie
+    for (id, minutes_ago) in [("exec1", 3), ("exec2", 2), ("exec3", 2), ("exec4", 1)] {
+        create_test_execution(&pool, id).await?;
+        sqlx::query(
+            "UPDATE executions SET created_at = date_trunc('minute', NOW()) - $2 * INTERVAL '1 minute' WHERE id = $1",
+        )
+        .bind(id)
+        .bind(minutes_ago)
+        .execute(&pool)
+        .await?;
+    }
+    sqlx::query("UPDATE executions SET type = 'workflow' WHERE id = 'exec4'")
+        .execute(&pool)
+        .await?;
+
+    let page = |order, after: Option<ExecutionPageCursor>| {
+        let pool = pool.clone();
+        async move {
+            let executions = crate::db::executions::query_executions(
+                &pool,
+                ExecutionFilters {
+                    exec_type: Some(ExecutionType::Task),
+                    order,
+                    after,
+                    limit: Some(2),
+                    ..Default::default()
+                },
+            )
+            .await?;
+            anyhow::Ok(executions)
+        }
+    };
+    let ids = |executions: &[crate::types::Execution]| {
+        executions.iter().map(|e| e.id.clone()).collect::<Vec<_>>()
+    };
+
+    let first = page(SortOrder::NewestFirst, None).await?;
+    assert_eq!(ids(&first), vec!["exec3", "exec2"]);
+    let cursor: ExecutionPageCursor = ExecutionPageCursor::after(&first[1]).to_string().parse()?;
+    let second = page(SortOrder::NewestFirst, Some(cursor)).await?;
+    assert_eq!(ids(&second), vec!["exec1"]);
+
+    let first = page(SortOrder::OldestFirst, None).await?;
+    assert_eq!(ids(&first), vec!["exec1", "exec2"]);
+    let second = page(
+        SortOrder::OldestFirst,
+        Some(ExecutionPageCursor::after(&first[1])),
+    )
+    .await?;
+    assert_eq!(ids(&second), vec!["exec3"]);
+
+    // Created time range: [from, to)
+    let executions = crate::db::executions::query_executions(
+        &pool,
+        ExecutionFilters {
+            created_after: Some(first[1].created_at),
+            created_before: Some(first[1].created_at + chrono::Duration::minutes(1)),
+            ..Default::default()
+        },
+    )
+    .await?;
+    assert_eq!(ids(&executions), vec!["exec3", "exec2"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_execution_page_cursor_parse() {
+    use crate::types::ExecutionPageCursor;
+
+    let cursor: ExecutionPageCursor = "1700000000123456:id:with:colons".parse().unwrap();
+    assert_eq!(cursor.created_at.timestamp_micros(), 1700000000123456);
+    assert_eq!(cursor.id, "id:with:colons");
+    assert_eq!(cursor.to_string(), "1700000000123456:id:with:colons");
+
+    assert!("abc".parse::<ExecutionPageCursor>().is_err());
+}
+
+#[test]
+fn test_change_cursor_parse() {
+    use crate::types::ChangeCursor;
+
+    let cursor: ChangeCursor = "42".parse().unwrap();
+    assert_eq!(cursor.xid, 42);
+    assert_eq!(cursor.after_id, "");
+
+    let cursor: ChangeCursor = "42:id:with:colons".parse().unwrap();
+    assert_eq!(cursor.after_id, "id:with:colons");
+    assert_eq!(cursor.to_string(), "42:id:with:colons");
+
+    assert!("abc".parse::<ChangeCursor>().is_err());
+}
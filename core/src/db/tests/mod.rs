@@ -2,6 +2,7 @@
 //!
 //! Integration tests for database operations
 
+mod concurrency_groups_tests;
 mod executions_tests;
 mod scheduled_queue_tests;
 mod signals_tests;
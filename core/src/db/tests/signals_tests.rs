@@ -8,6 +8,7 @@ use crate::types::{CreateExecutionParams, ExecutionType};
 use crate::worker::signals::resolve_signal_claims;
 use serde_json::json;
 use sqlx::PgPool;
+use std::collections::HashMap;
 
 /// Helper to create a test execution (required for foreign key constraint)
 async fn create_test_execution(pool: &PgPool, id: &str) -> anyhow::Result<()> {
@@ -19,6 +20,13 @@ async fn create_test_execution(pool: &PgPool, id: &str) -> anyhow::Result<()> {
         queue: "default".to_string(),
         inputs: json!({}),
         parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
     };
     crate::db::executions::create_execution(&mut tx, params).await?;
     tx.commit().await?;
@@ -3,9 +3,10 @@
 //! These tests verify critical work queue behavior, especially around claim_work
 //! which had a bug where it would claim multiple items despite LIMIT=1.
 
-use crate::db::{claim_work, complete_work, enqueue_work};
+use crate::db::{claim_work, complete_work, enqueue_work, extend_claims, release_work};
 use crate::types::{CreateExecutionParams, ExecutionType};
 use sqlx::PgPool;
+use std::collections::HashMap;
 
 /// Helper to create test executions
 async fn create_test_execution(pool: &PgPool, id: &str, queue: &str) -> anyhow::Result<()> {
@@ -17,6 +18,13 @@ async fn create_test_execution(pool: &PgPool, id: &str, queue: &str) -> anyhow::
         queue: queue.to_string(),
         inputs: serde_json::json!({}),
         parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
     };
     crate::db::executions::create_execution(&mut tx, params).await?;
     tx.commit().await?;
@@ -63,7 +71,7 @@ async fn test_claim_work_respects_limit(pool: PgPool) -> anyhow::Result<()> {
     assert_eq!(count_unclaimed(&pool, "default").await?, 3);
 
     // Claim with LIMIT 1
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
 
     // CRITICAL: Should only claim 1 item
     assert_eq!(claimed.len(), 1, "claim_work should respect LIMIT=1");
@@ -83,7 +91,7 @@ async fn test_claim_work_with_multiple_limit(pool: PgPool) -> anyhow::Result<()>
     }
 
     // Claim with LIMIT 3
-    let claimed = claim_work(&pool, "default", 3).await?;
+    let claimed = claim_work(&pool, "default", 3, None).await?;
 
     assert_eq!(claimed.len(), 3);
     assert_eq!(count_claimed(&pool, "default").await?, 3);
@@ -104,23 +112,75 @@ async fn test_claim_work_respects_priority(pool: PgPool) -> anyhow::Result<()> {
     enqueue_work(&pool, "medium", "default", 50).await?; // priority 50
 
     // Claim 1 - should get highest priority
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed.len(), 1);
     assert_eq!(claimed[0], "high");
 
     // Claim 1 more - should get medium priority
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed.len(), 1);
     assert_eq!(claimed[0], "medium");
 
     // Claim 1 more - should get low priority
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed.len(), 1);
     assert_eq!(claimed[0], "low");
 
     Ok(())
 }
 
+#[sqlx::test]
+async fn test_enqueue_work_uses_execution_priority(pool: PgPool) -> anyhow::Result<()> {
+    create_test_execution(&pool, "normal", "default").await?;
+
+    let mut tx = pool.begin().await?;
+    let urgent = CreateExecutionParams {
+        id: Some("urgent".to_string()),
+        exec_type: ExecutionType::Workflow,
+        target_name: "test_workflow".to_string(),
+        queue: "default".to_string(),
+        inputs: serde_json::json!({}),
+        parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: Some(10),
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
+    };
+    crate::db::executions::create_execution(&mut tx, urgent).await?;
+    // A child inherits its parent's priority
+    let child = CreateExecutionParams {
+        id: Some("child".to_string()),
+        exec_type: ExecutionType::Task,
+        target_name: "test_task".to_string(),
+        queue: "default".to_string(),
+        inputs: serde_json::json!({}),
+        parent_workflow_id: Some("urgent".to_string()),
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
+    };
+    crate::db::executions::create_execution(&mut tx, child).await?;
+    tx.commit().await?;
+
+    // Queued later, and by callers that pass no priority
+    enqueue_work(&pool, "normal", "default", 0).await?;
+    enqueue_work(&pool, "urgent", "default", 0).await?;
+    enqueue_work(&pool, "child", "default", 0).await?;
+
+    assert_eq!(claim_work(&pool, "default", 1, None).await?, vec!["urgent"]);
+    assert_eq!(claim_work(&pool, "default", 1, None).await?, vec!["child"]);
+    assert_eq!(claim_work(&pool, "default", 1, None).await?, vec!["normal"]);
+
+    Ok(())
+}
+
 #[sqlx::test]
 async fn test_claim_work_skips_already_claimed(pool: PgPool) -> anyhow::Result<()> {
     create_test_execution(&pool, "exec1", "default").await?;
@@ -130,16 +190,16 @@ async fn test_claim_work_skips_already_claimed(pool: PgPool) -> anyhow::Result<(
     enqueue_work(&pool, "exec2", "default", 0).await?;
 
     // First claim
-    let claimed1 = claim_work(&pool, "default", 1).await?;
+    let claimed1 = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed1.len(), 1);
 
     // Second claim should get the other execution
-    let claimed2 = claim_work(&pool, "default", 1).await?;
+    let claimed2 = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed2.len(), 1);
     assert_ne!(claimed1[0], claimed2[0]);
 
     // Third claim should get nothing
-    let claimed3 = claim_work(&pool, "default", 1).await?;
+    let claimed3 = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed3.len(), 0);
 
     Ok(())
@@ -151,7 +211,7 @@ async fn test_claim_work_reclaims_expired(pool: PgPool) -> anyhow::Result<()> {
     enqueue_work(&pool, "exec1", "default", 0).await?;
 
     // Claim the work
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed.len(), 1);
 
     // Manually expire the claim by setting claimed_until to the past
@@ -163,7 +223,7 @@ async fn test_claim_work_reclaims_expired(pool: PgPool) -> anyhow::Result<()> {
     .await?;
 
     // Should be able to claim again
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed.len(), 1);
     assert_eq!(claimed[0], "exec1");
 
@@ -176,7 +236,7 @@ async fn test_complete_work(pool: PgPool) -> anyhow::Result<()> {
     enqueue_work(&pool, "exec1", "default", 0).await?;
 
     // Claim work
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed.len(), 1);
     assert_eq!(count_claimed(&pool, "default").await?, 1);
 
@@ -190,6 +250,65 @@ async fn test_complete_work(pool: PgPool) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[sqlx::test]
+async fn test_release_work(pool: PgPool) -> anyhow::Result<()> {
+    create_test_execution(&pool, "exec1", "default").await?;
+    enqueue_work(&pool, "exec1", "default", 5).await?;
+
+    let claimed = claim_work(&pool, "default", 1, None).await?;
+    assert_eq!(claimed.len(), 1);
+
+    // Releasing puts the work back as unclaimed with the same priority
+    release_work(&pool, "exec1").await?;
+    assert_eq!(count_claimed(&pool, "default").await?, 0);
+    assert_eq!(count_unclaimed(&pool, "default").await?, 1);
+
+    let priority: i32 =
+        sqlx::query_scalar("SELECT priority FROM work_queue WHERE execution_id = $1")
+            .bind("exec1")
+            .fetch_one(&pool)
+            .await?;
+    assert_eq!(priority, 5);
+
+    // It can be claimed again
+    let claimed = claim_work(&pool, "default", 1, None).await?;
+    assert_eq!(claimed, vec!["exec1".to_string()]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_extend_claims_skips_work_without_a_claim(pool: PgPool) -> anyhow::Result<()> {
+    for id in ["exec1", "exec2", "exec3"] {
+        create_test_execution(&pool, id, "default").await?;
+        enqueue_work(&pool, id, "default", 0).await?;
+    }
+    claim_work(&pool, "default", 3, None).await?;
+    complete_work(&pool, "exec2").await?;
+    release_work(&pool, "exec3").await?;
+
+    let ids = ["exec1", "exec2", "exec3"].map(String::from);
+    assert_eq!(extend_claims(&pool, &ids).await?, vec!["exec1".to_string()]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_release_work_keeps_pending_reenqueue(pool: PgPool) -> anyhow::Result<()> {
+    create_test_execution(&pool, "exec1", "default").await?;
+    enqueue_work(&pool, "exec1", "default", 0).await?;
+    claim_work(&pool, "default", 1, None).await?;
+
+    // Re-enqueued while claimed, then released: only one unclaimed entry remains
+    enqueue_work(&pool, "exec1", "default", 0).await?;
+    release_work(&pool, "exec1").await?;
+
+    assert_eq!(count_claimed(&pool, "default").await?, 0);
+    assert_eq!(count_unclaimed(&pool, "default").await?, 1);
+
+    Ok(())
+}
+
 #[sqlx::test]
 async fn test_enqueue_work_is_idempotent(pool: PgPool) -> anyhow::Result<()> {
     create_test_execution(&pool, "exec1", "default").await?;
@@ -214,7 +333,7 @@ async fn test_claim_work_respects_queue(pool: PgPool) -> anyhow::Result<()> {
     enqueue_work(&pool, "exec2", "queue2", 0).await?;
 
     // Claim from queue1
-    let claimed = claim_work(&pool, "queue1", 1).await?;
+    let claimed = claim_work(&pool, "queue1", 1, None).await?;
     assert_eq!(claimed.len(), 1);
     assert_eq!(claimed[0], "exec1");
 
@@ -235,7 +354,7 @@ async fn test_claim_work_prevents_claiming_execution_with_active_claim(
 
     // Enqueue and claim the work
     enqueue_work(&pool, "exec1", "default", 0).await?;
-    let claimed = claim_work(&pool, "default", 1).await?;
+    let claimed = claim_work(&pool, "default", 1, None).await?;
     assert_eq!(claimed.len(), 1);
 
     // Now enqueue the same execution again (this can happen if work is re-queued
@@ -251,7 +370,7 @@ async fn test_claim_work_prevents_claiming_execution_with_active_claim(
 
     // Trying to claim more work should NOT claim the unclaimed entry
     // because the execution already has an active claim
-    let claimed2 = claim_work(&pool, "default", 10).await?;
+    let claimed2 = claim_work(&pool, "default", 10, None).await?;
     assert_eq!(
         claimed2.len(),
         0,
@@ -0,0 +1,212 @@
+//! Export and import of executions between databases
+//!
+//! Executions are exported as whole rows (`to_jsonb`), so a file carries
+//! every column; import restores the columns that mean the same thing in
+//! another database and leaves the rest (change cursors, heartbeats, runner
+//! error counts) to their defaults. Workflow definitions and saved states
+//! refer to each other by definition name and version hash rather than by
+//! the local serial id.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+
+use crate::types::ExecutionStatus;
+
+/// Definitions the executions created since `since` may run: the one each
+/// saved state is on, and the latest of each workflow started
+pub async fn export_definitions<'e, E>(
+    executor: E,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<JsonValue>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        WITH exported AS (
+            SELECT id, target_name, type
+            FROM executions
+            WHERE $1::timestamptz IS NULL OR created_at >= $1
+        )
+        SELECT jsonb_build_object(
+            'name', d.name,
+            'version_hash', d.version_hash,
+            'source', d.source,
+            'settings', d.settings,
+            'created_at', d.created_at
+        )
+        FROM workflow_definitions d
+        WHERE d.id IN (
+            SELECT c.workflow_definition_id
+            FROM workflow_execution_context c JOIN exported e ON e.id = c.execution_id
+        )
+        OR d.id IN (
+            SELECT DISTINCT ON (name) id
+            FROM workflow_definitions
+            WHERE name IN (SELECT target_name FROM exported WHERE type = 'workflow')
+            ORDER BY name, created_at DESC
+        )
+        ORDER BY d.created_at, d.id
+        "#,
+    )
+    .bind(since)
+    .fetch_all(executor)
+    .await
+    .context("Failed to export workflow definitions")
+}
+
+/// Up to `limit` executions created since `since`, oldest first, after the
+/// `(created_at, id)` of the last one of the previous page
+pub async fn export_executions<'e, E>(
+    executor: E,
+    since: Option<DateTime<Utc>>,
+    after: Option<&(DateTime<Utc>, String)>,
+    limit: i64,
+) -> Result<Vec<(DateTime<Utc>, String, JsonValue)>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let (after_created_at, after_id) = after.map(|(at, id)| (at, id.as_str())).unzip();
+    let rows = sqlx::query(
+        r#"
+        SELECT created_at, id, to_jsonb(e) AS row
+        FROM executions e
+        WHERE ($1::timestamptz IS NULL OR created_at >= $1)
+          AND ($2::timestamptz IS NULL OR (created_at, id) > ($2, $3))
+        ORDER BY created_at, id
+        LIMIT $4
+        "#,
+    )
+    .bind(since)
+    .bind(after_created_at)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context("Failed to export executions")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.get("created_at"), row.get("id"), row.get("row")))
+        .collect())
+}
+
+/// Saved states of the given executions, binary ones in base64
+pub async fn export_contexts<'e, E>(executor: E, execution_ids: &[String]) -> Result<Vec<JsonValue>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT jsonb_build_object(
+            'execution_id', c.execution_id,
+            'workflow_name', d.name,
+            'version_hash', d.version_hash,
+            'vm_state', c.locals,
+            'state', encode(c.state, 'base64')
+        )
+        FROM workflow_execution_context c
+        JOIN workflow_definitions d ON d.id = c.workflow_definition_id
+        WHERE c.execution_id = ANY($1)
+        ORDER BY c.execution_id
+        "#,
+    )
+    .bind(execution_ids)
+    .fetch_all(executor)
+    .await
+    .context("Failed to export saved states")
+}
+
+/// Insert an exported workflow definition, unless it is already registered
+///
+/// Returns whether it was inserted.
+pub async fn import_definition<'e, E>(executor: E, definition: &JsonValue) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        INSERT INTO workflow_definitions
+            (name, version_hash, source, parsed_steps, file_path, settings, created_at)
+        SELECT
+            $1->>'name', $1->>'version_hash', $1->>'source', '{}', '',
+            COALESCE($1->'settings', '{}'),
+            COALESCE(($1->>'created_at')::timestamptz, NOW())
+        ON CONFLICT (name, version_hash) DO NOTHING
+        "#,
+    )
+    .bind(definition)
+    .execute(executor)
+    .await
+    .context("Failed to import workflow definition")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Insert an exported execution, unless one with its id exists
+///
+/// A running execution comes back pending and not yet started, as nothing
+/// is running it here.
+/// Its parent is dropped if it isn't in this database. Returns the id,
+/// status, queue and priority it was inserted with.
+pub async fn import_execution<'e, E>(
+    executor: E,
+    execution: &JsonValue,
+) -> Result<Option<(String, ExecutionStatus, String, i32)>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_as(
+        r#"
+        INSERT INTO executions (
+            id, type, target_name, queue, status, inputs, output, attempt,
+            parent_workflow_id, namespace, created_at, started_at, completed_at,
+            expires_at, express, retry_policy, priority, idempotency_key,
+            traceparent, continued_from
+        )
+        SELECT
+            r.id, r.type, r.target_name, r.queue,
+            CASE WHEN r.status = 'running' THEN 'pending' ELSE r.status END,
+            r.inputs, r.output, COALESCE(r.attempt, 0),
+            (SELECT p.id FROM executions p WHERE p.id = r.parent_workflow_id),
+            COALESCE(r.namespace, 'default'), COALESCE(r.created_at, NOW()),
+            CASE WHEN r.status = 'running' THEN NULL ELSE r.started_at END,
+            r.completed_at, r.expires_at, COALESCE(r.express, false),
+            r.retry_policy, COALESCE(r.priority, 0), r.idempotency_key,
+            r.traceparent, r.continued_from
+        FROM jsonb_populate_record(NULL::executions, $1) r
+        ON CONFLICT (id) DO NOTHING
+        RETURNING id, status, queue, priority
+        "#,
+    )
+    .bind(execution)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to import execution")
+}
+
+/// Insert an exported saved state
+///
+/// Returns whether it was inserted: it isn't if its execution already has
+/// a saved state, or the definition it is on isn't registered.
+pub async fn import_context<'e, E>(executor: E, context: &JsonValue) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        INSERT INTO workflow_execution_context (execution_id, workflow_definition_id, locals, state)
+        SELECT $1->>'execution_id', d.id, NULLIF($1->'vm_state', 'null'::jsonb),
+            decode($1->>'state', 'base64')
+        FROM workflow_definitions d
+        WHERE d.name = $1->>'workflow_name' AND d.version_hash = $1->>'version_hash'
+        ON CONFLICT (execution_id) DO NOTHING
+        "#,
+    )
+    .bind(context)
+    .execute(executor)
+    .await
+    .context("Failed to import saved state")?;
+    Ok(result.rows_affected() > 0)
+}
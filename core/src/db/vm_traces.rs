@@ -0,0 +1,101 @@
+//! VM Trace Database Operations
+//!
+//! Executions opt in to step tracing (see `executor::trace`); the steps of a
+//! traced run are saved when the run fails.
+
+use anyhow::{Context, Result};
+use sqlx::Row;
+
+use crate::executor::TraceStep;
+use crate::types::ExecutionVmTrace;
+
+/// Trace the execution's runs from now on
+pub async fn enable<'e, E>(executor: E, execution_id: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO vm_traces (execution_id)
+        VALUES ($1)
+        ON CONFLICT (execution_id) DO NOTHING
+        "#,
+    )
+    .bind(execution_id)
+    .execute(executor)
+    .await
+    .context("Failed to enable VM trace")?;
+    Ok(())
+}
+
+/// Whether the execution's runs are traced
+pub async fn is_enabled<'e, E>(executor: E, execution_id: &str) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM vm_traces WHERE execution_id = $1)")
+        .bind(execution_id)
+        .fetch_one(executor)
+        .await
+        .context("Failed to check VM trace")
+}
+
+/// Save the steps of a failed run, replacing any saved before
+pub async fn save_steps<'e, E>(
+    executor: E,
+    execution_id: &str,
+    total_steps: u64,
+    steps: &[TraceStep],
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let steps = serde_json::to_value(steps).context("Failed to serialize VM trace")?;
+    sqlx::query(
+        r#"
+        UPDATE vm_traces
+        SET recorded_at = NOW(), total_steps = $2, steps = $3
+        WHERE execution_id = $1
+        "#,
+    )
+    .bind(execution_id)
+    .bind(i64::try_from(total_steps).unwrap_or(i64::MAX))
+    .bind(steps)
+    .execute(executor)
+    .await
+    .context("Failed to save VM trace")?;
+    Ok(())
+}
+
+/// The execution's trace, if it opted in
+pub async fn get_trace<'e, E>(executor: E, execution_id: &str) -> Result<Option<ExecutionVmTrace>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let row = sqlx::query(
+        r#"
+        SELECT execution_id, enabled_at, recorded_at, total_steps, steps
+        FROM vm_traces
+        WHERE execution_id = $1
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(executor)
+    .await
+    .context("Failed to fetch VM trace")?;
+
+    row.map(|row| {
+        let steps: Option<serde_json::Value> = row.get("steps");
+        Ok(ExecutionVmTrace {
+            execution_id: row.get("execution_id"),
+            enabled_at: row.get("enabled_at"),
+            recorded_at: row.get("recorded_at"),
+            total_steps: row.get("total_steps"),
+            steps: match steps {
+                Some(steps) => serde_json::from_value(steps).context("Malformed VM trace")?,
+                None => Vec::new(),
+            },
+        })
+    })
+    .transpose()
+}
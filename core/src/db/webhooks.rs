@@ -0,0 +1,268 @@
+//! Webhook Database Operations
+//!
+//! Deliveries are queued by trigger when an execution's status changes (see
+//! the `create_webhooks` migration); this module registers webhooks and
+//! moves deliveries through their attempts.
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use std::time::Duration;
+
+use crate::types::{CreateWebhookParams, Webhook, WebhookDelivery, WebhookEvent};
+
+/// A delivery claimed for an attempt, with where to send it
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub webhook_id: String,
+    pub execution_id: String,
+    pub event: WebhookEvent,
+    pub payload: JsonValue,
+    /// Attempts made before this one
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+fn webhook_from_row(row: &PgRow) -> Webhook {
+    let events: Vec<String> = row.get("events");
+    Webhook {
+        id: row.get("id"),
+        url: row.get("url"),
+        events: events.iter().filter_map(|e| e.parse().ok()).collect(),
+        queue: row.get("queue"),
+        target_name: row.get("target_name"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Register a webhook under `id`
+pub async fn create_webhook<'e, E>(
+    executor: E,
+    id: &str,
+    params: &CreateWebhookParams,
+) -> Result<Webhook>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let events: Vec<&str> = params.events.iter().map(|e| e.as_str()).collect();
+    let row = sqlx::query(
+        r#"
+        INSERT INTO webhooks (id, url, secret, events, queue, target_name)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, url, events, queue, target_name, created_at
+        "#,
+    )
+    .bind(id)
+    .bind(&params.url)
+    .bind(&params.secret)
+    .bind(&events)
+    .bind(&params.queue)
+    .bind(&params.target_name)
+    .fetch_one(executor)
+    .await
+    .context("Failed to create webhook")?;
+    Ok(webhook_from_row(&row))
+}
+
+/// Every registered webhook, oldest first
+pub async fn list_webhooks<'e, E>(executor: E) -> Result<Vec<Webhook>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT id, url, events, queue, target_name, created_at
+        FROM webhooks
+        ORDER BY created_at, id
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to list webhooks")?;
+    Ok(rows.iter().map(webhook_from_row).collect())
+}
+
+/// Remove a webhook and its deliveries; returns whether it existed
+pub async fn delete_webhook<'e, E>(executor: E, id: &str) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query("DELETE FROM webhooks WHERE id = $1")
+        .bind(id)
+        .execute(executor)
+        .await
+        .context("Failed to delete webhook")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A webhook's most recent deliveries, newest first
+pub async fn list_deliveries<'e, E>(
+    executor: E,
+    webhook_id: &str,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT id, webhook_id, execution_id, event, payload, status, attempts,
+               next_attempt_at, last_error, delivered_at, created_at
+        FROM webhook_deliveries
+        WHERE webhook_id = $1
+        ORDER BY id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(webhook_id)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context("Failed to list webhook deliveries")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| WebhookDelivery {
+            id: row.get("id"),
+            webhook_id: row.get("webhook_id"),
+            execution_id: row.get("execution_id"),
+            event: row.get("event"),
+            payload: row.get("payload"),
+            status: row.get("status"),
+            attempts: row.get("attempts"),
+            next_attempt_at: row.get("next_attempt_at"),
+            last_error: row.get("last_error"),
+            delivered_at: row.get("delivered_at"),
+            created_at: row.get("created_at"),
+        })
+        .collect())
+}
+
+/// Claim up to `limit` pending deliveries that are due
+///
+/// A claimed delivery isn't due again for `lease_secs`, so another worker
+/// won't send it while this one is, but it is retried if this one dies.
+pub async fn claim_due_deliveries<'e, E>(
+    executor: E,
+    limit: i64,
+    lease_secs: f64,
+) -> Result<Vec<DueDelivery>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        WITH due AS (
+            SELECT id
+            FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= NOW()
+            ORDER BY next_attempt_at, id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        ),
+        claimed AS (
+            UPDATE webhook_deliveries d
+            SET next_attempt_at = NOW() + make_interval(secs => $2)
+            FROM due
+            WHERE d.id = due.id
+            RETURNING d.id, d.webhook_id, d.execution_id, d.event, d.payload, d.attempts
+        )
+        SELECT c.*, w.url, w.secret
+        FROM claimed c
+        JOIN webhooks w ON w.id = c.webhook_id
+        ORDER BY c.id
+        "#,
+    )
+    .bind(limit)
+    .bind(lease_secs)
+    .fetch_all(executor)
+    .await
+    .context("Failed to claim webhook deliveries")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| DueDelivery {
+            id: row.get("id"),
+            webhook_id: row.get("webhook_id"),
+            execution_id: row.get("execution_id"),
+            event: row.get("event"),
+            payload: row.get("payload"),
+            attempts: row.get("attempts"),
+            url: row.get("url"),
+            secret: row.get("secret"),
+        })
+        .collect())
+}
+
+/// Record a delivery the endpoint accepted
+pub async fn mark_delivered<'e, E>(executor: E, id: i64) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = 'delivered', attempts = attempts + 1, delivered_at = NOW(),
+            last_error = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(executor)
+    .await
+    .context("Failed to mark webhook delivered")?;
+    Ok(())
+}
+
+/// Record a failed attempt: retried after `retry_in`, or failed for good
+/// without one
+pub async fn record_failed_attempt<'e, E>(
+    executor: E,
+    id: i64,
+    error: &str,
+    retry_in: Option<Duration>,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET attempts = attempts + 1,
+            last_error = $2,
+            status = CASE WHEN $3::float8 IS NULL THEN 'failed' ELSE 'pending' END,
+            next_attempt_at = COALESCE(NOW() + make_interval(secs => $3), next_attempt_at)
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(retry_in.map(|delay| delay.as_secs_f64()))
+    .execute(executor)
+    .await
+    .context("Failed to record webhook attempt")?;
+    Ok(())
+}
+
+/// Queue a failed delivery to be sent again now; returns whether it was
+/// found
+pub async fn redeliver<'e, E>(executor: E, id: i64) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = 'pending', attempts = 0, next_attempt_at = NOW()
+        WHERE id = $1 AND status = 'failed'
+        "#,
+    )
+    .bind(id)
+    .execute(executor)
+    .await
+    .context("Failed to redeliver webhook")?;
+    Ok(result.rows_affected() > 0)
+}
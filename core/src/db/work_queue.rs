@@ -5,10 +5,20 @@
 use anyhow::{Context, Result};
 use sqlx::Row;
 
+use crate::types::{QueueDepth, QueueStats};
+
+/// Priority of work for express executions (see `run_workflow_sync`)
+///
+/// Above any priority callers pass, so the express lane always claims first.
+pub const EXPRESS_PRIORITY: i32 = 1_000_000;
+
 /// Enqueue work for an execution
 ///
 /// Creates an unclaimed work queue entry. If an unclaimed entry already exists,
-/// this operation does nothing (idempotent).
+/// this operation does nothing (idempotent). Work is queued at the higher of
+/// `priority` and the execution's own priority, and at `EXPRESS_PRIORITY`
+/// for an express execution. A workflow whose state was saved by a sticky
+/// worker prefers that worker for the timeout it saved.
 pub async fn enqueue_work<'e, E>(
     executor: E,
     execution_id: &str,
@@ -20,8 +30,17 @@ where
 {
     sqlx::query(
         r#"
-        INSERT INTO work_queue (execution_id, queue, priority)
-        VALUES ($1, $2, $3)
+        INSERT INTO work_queue (execution_id, queue, priority, preferred_worker_id, preferred_until)
+        SELECT
+            $1, $2,
+            CASE
+                WHEN (SELECT express FROM executions WHERE id = $1) THEN GREATEST($3, $4)
+                ELSE GREATEST($3, (SELECT priority FROM executions WHERE id = $1))
+            END,
+            c.sticky_worker_id,
+            NOW() + c.sticky_timeout_ms * INTERVAL '1 millisecond'
+        FROM (SELECT 1) AS one
+        LEFT JOIN workflow_execution_context c ON c.execution_id = $1
         ON CONFLICT (execution_id, (claimed_until IS NULL))
         DO NOTHING
         "#,
@@ -29,6 +48,7 @@ where
     .bind(execution_id)
     .bind(queue)
     .bind(priority)
+    .bind(EXPRESS_PRIORITY)
     .execute(executor)
     .await
     .context("Failed to enqueue work")?;
@@ -36,11 +56,66 @@ where
     Ok(())
 }
 
+/// `enqueue_work` for several executions at once
+///
+/// `queues` line up with `execution_ids`; an execution listed more than
+/// once is queued on the first of its queues.
+pub async fn enqueue_work_batch<'e, E>(
+    executor: E,
+    execution_ids: &[String],
+    queues: &[String],
+    priority: i32,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO work_queue (execution_id, queue, priority, preferred_worker_id, preferred_until)
+        SELECT
+            work.id, work.queue,
+            CASE
+                WHEN e.express THEN GREATEST($3, $4)
+                ELSE GREATEST($3, e.priority)
+            END,
+            c.sticky_worker_id,
+            NOW() + c.sticky_timeout_ms * INTERVAL '1 millisecond'
+        FROM (
+            SELECT DISTINCT ON (id) id, queue
+            FROM UNNEST($1::text[], $2::text[]) WITH ORDINALITY AS work(id, queue, n)
+            ORDER BY id, n
+        ) AS work
+        LEFT JOIN executions e ON e.id = work.id
+        LEFT JOIN workflow_execution_context c ON c.execution_id = work.id
+        ON CONFLICT (execution_id, (claimed_until IS NULL))
+        DO NOTHING
+        "#,
+    )
+    .bind(execution_ids)
+    .bind(queues)
+    .bind(priority)
+    .bind(EXPRESS_PRIORITY)
+    .execute(executor)
+    .await
+    .context("Failed to enqueue work batch")?;
+
+    Ok(())
+}
+
 /// Claim work from the queue
 ///
 /// Returns a list of execution IDs that were successfully claimed.
-/// Uses lease-based claiming with a 1-minute timeout.
-pub async fn claim_work<'e, E>(executor: E, queue: &str, limit: i32) -> Result<Vec<String>>
+/// Uses lease-based claiming with a 1-minute timeout. Claims nothing while
+/// a maintenance window is open, and skips paused workflows and executions
+/// whose TTL has run out (the expiry sweep removes those). Work preferring a worker other than
+/// `worker_id` is skipped until its preference runs out, and work denied to
+/// `worker_id` (see `deny_work`) until the denial lapses.
+pub async fn claim_work<'e, E>(
+    executor: E,
+    queue: &str,
+    limit: i32,
+    worker_id: Option<&str>,
+) -> Result<Vec<String>>
 where
     E: sqlx::Executor<'e, Database = sqlx::Postgres>,
 {
@@ -57,6 +132,25 @@ where
                     AND wq2.claimed_until IS NOT NULL
                     AND wq2.claimed_until > NOW()
               )
+              AND NOT EXISTS (
+                  SELECT 1 FROM maintenance_window WHERE expires_at > NOW()
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM executions e
+                  WHERE e.id = work_queue.execution_id
+                    AND (e.paused OR (e.status = 'pending' AND e.expires_at <= NOW()))
+              )
+              AND (
+                  preferred_worker_id IS NULL
+                  OR preferred_worker_id = $3
+                  OR preferred_until <= NOW()
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM claim_denials d
+                  WHERE d.execution_id = work_queue.execution_id
+                    AND d.worker_id = $3
+                    AND d.until > NOW()
+              )
             ORDER BY priority DESC, created_at ASC
             LIMIT $2
             FOR UPDATE SKIP LOCKED
@@ -69,6 +163,7 @@ where
     )
     .bind(queue)
     .bind(limit)
+    .bind(worker_id)
     .fetch_all(executor)
     .await
     .context("Failed to claim work")?;
@@ -79,6 +174,88 @@ where
         .collect())
 }
 
+/// Claim up to `limit` pieces of work from any of `queues` at once
+///
+/// Same rules as `claim_work`, in one statement. Returns the claimed
+/// execution IDs with the queue each was claimed from, highest priority
+/// first.
+pub async fn claim_work_batch<'e, E>(
+    executor: E,
+    queues: &[String],
+    limit: i32,
+    worker_id: Option<&str>,
+) -> Result<Vec<(String, String)>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        WITH to_claim AS (
+            SELECT id
+            FROM work_queue
+            WHERE queue = ANY($1)
+              AND (claimed_until IS NULL OR claimed_until < NOW())
+              AND NOT EXISTS (
+                  SELECT 1 FROM work_queue wq2
+                  WHERE wq2.execution_id = work_queue.execution_id
+                    AND wq2.claimed_until IS NOT NULL
+                    AND wq2.claimed_until > NOW()
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM maintenance_window WHERE expires_at > NOW()
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM executions e
+                  WHERE e.id = work_queue.execution_id
+                    AND (e.paused OR (e.status = 'pending' AND e.expires_at <= NOW()))
+              )
+              AND (
+                  preferred_worker_id IS NULL
+                  OR preferred_worker_id = $3
+                  OR preferred_until <= NOW()
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM claim_denials d
+                  WHERE d.execution_id = work_queue.execution_id
+                    AND d.worker_id = $3
+                    AND d.until > NOW()
+              )
+            ORDER BY priority DESC, created_at ASC
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        UPDATE work_queue
+        SET claimed_until = NOW() + INTERVAL '1 minute'
+        WHERE id IN (SELECT id FROM to_claim)
+        RETURNING execution_id, queue, priority, created_at
+        "#,
+    )
+    .bind(queues)
+    .bind(limit)
+    .bind(worker_id)
+    .fetch_all(executor)
+    .await
+    .context("Failed to claim work")?;
+
+    let mut claimed: Vec<(i32, chrono::NaiveDateTime, String, String)> = rows
+        .into_iter()
+        .map(|row| {
+            (
+                row.get("priority"),
+                row.get("created_at"),
+                row.get("execution_id"),
+                row.get("queue"),
+            )
+        })
+        .collect();
+    // RETURNING doesn't keep the claim order
+    claimed.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    Ok(claimed
+        .into_iter()
+        .map(|(_, _, execution_id, queue)| (execution_id, queue))
+        .collect())
+}
+
 /// Claim work for a specific execution
 ///
 /// Claims the unclaimed work queue entry for a specific execution.
@@ -100,6 +277,78 @@ pub async fn claim_specific_execution(pool: &sqlx::PgPool, execution_id: &str) -
     Ok(())
 }
 
+/// Extend the lease on claimed work
+///
+/// Called periodically while a host runs a long task so the claim doesn't
+/// lapse and the task get claimed again. Returns `false` once there is no
+/// claim left to extend.
+pub async fn extend_claim<'e, E>(executor: E, execution_id: &str) -> Result<bool>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE work_queue
+        SET claimed_until = NOW() + INTERVAL '1 minute'
+        WHERE execution_id = $1
+          AND claimed_until IS NOT NULL
+        "#,
+    )
+    .bind(execution_id)
+    .execute(executor)
+    .await
+    .context("Failed to extend claim")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Extend the leases on several executions' claimed work
+///
+/// Same as `extend_claim` for each ID, in one statement. Returns the IDs
+/// that still had a claim to extend.
+pub async fn extend_claims<'e, E>(executor: E, execution_ids: &[String]) -> Result<Vec<String>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        UPDATE work_queue
+        SET claimed_until = NOW() + INTERVAL '1 minute'
+        WHERE execution_id = ANY($1)
+          AND claimed_until IS NOT NULL
+        RETURNING execution_id
+        "#,
+    )
+    .bind(execution_ids)
+    .fetch_all(executor)
+    .await
+    .context("Failed to extend claims")
+}
+
+/// End the lease on claimed work now, as if its worker had died
+///
+/// The work can be claimed again at once. Used by the simulation harness
+/// to inject worker crashes without waiting out the lease.
+pub async fn expire_claim<'e, E>(executor: E, execution_id: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        UPDATE work_queue
+        SET claimed_until = NOW() - INTERVAL '1 second'
+        WHERE execution_id = $1
+          AND claimed_until IS NOT NULL
+        "#,
+    )
+    .bind(execution_id)
+    .execute(executor)
+    .await
+    .context("Failed to expire claim")?;
+
+    Ok(())
+}
+
 /// Complete work for an execution
 ///
 /// Deletes the claimed work queue entry. Preserves any unclaimed entry that
@@ -122,3 +371,264 @@ where
 
     Ok(())
 }
+
+/// Remove the unclaimed entries of several executions
+///
+/// Used for executions that finished without being claimed, such as
+/// expired ones.
+pub async fn remove_unclaimed_work<'e, E>(executor: E, execution_ids: &[String]) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        DELETE FROM work_queue
+        WHERE execution_id = ANY($1)
+          AND claimed_until IS NULL
+        "#,
+    )
+    .bind(execution_ids)
+    .execute(executor)
+    .await
+    .context("Failed to remove unclaimed work")?;
+
+    Ok(())
+}
+
+/// Remove every work queue entry of the given executions, claimed or not
+///
+/// Used for cancelled executions: a worker's claim ends with them.
+pub async fn remove_work<'e, E>(executor: E, execution_ids: &[String]) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        DELETE FROM work_queue
+        WHERE execution_id = ANY($1)
+        "#,
+    )
+    .bind(execution_ids)
+    .execute(executor)
+    .await
+    .context("Failed to remove work")?;
+
+    Ok(())
+}
+
+/// Complete work for several executions at once
+///
+/// Same as `complete_work` for each ID, in one statement.
+pub async fn complete_work_batch<'e, E>(executor: E, execution_ids: &[String]) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        DELETE FROM work_queue
+        WHERE execution_id = ANY($1)
+          AND claimed_until IS NOT NULL
+        "#,
+    )
+    .bind(execution_ids)
+    .execute(executor)
+    .await
+    .context("Failed to complete work batch")?;
+
+    Ok(())
+}
+
+/// Release a claim without processing the work
+///
+/// Deletes the claimed entry and puts it back as unclaimed with its original
+/// priority and position. If an unclaimed entry was queued in the meantime,
+/// that entry is kept and this one is dropped.
+pub async fn release_work<'e, E>(executor: E, execution_id: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        WITH released AS (
+            DELETE FROM work_queue
+            WHERE execution_id = $1
+              AND claimed_until IS NOT NULL
+            RETURNING execution_id, queue, priority, created_at
+        )
+        INSERT INTO work_queue (execution_id, queue, priority, created_at)
+        SELECT execution_id, queue, priority, created_at FROM released
+        ON CONFLICT (execution_id, (claimed_until IS NULL))
+        DO NOTHING
+        "#,
+    )
+    .bind(execution_id)
+    .execute(executor)
+    .await
+    .context("Failed to release work")?;
+
+    Ok(())
+}
+
+/// Keep `worker_id` from claiming `execution_id` again for `backoff`
+///
+/// For work the worker's claim policy refused; the claim is released
+/// separately. Claims made for the worker skip it until then. Lapsed
+/// denials of the worker are cleared on the way.
+pub async fn deny_work(
+    pool: &sqlx::PgPool,
+    execution_id: &str,
+    worker_id: &str,
+    backoff: std::time::Duration,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM claim_denials WHERE worker_id = $1 AND until <= NOW()")
+        .bind(worker_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear lapsed claim denials")?;
+    sqlx::query(
+        r#"
+        INSERT INTO claim_denials (execution_id, worker_id, until)
+        VALUES ($1, $2, NOW() + make_interval(secs => $3))
+        ON CONFLICT (execution_id, worker_id) DO UPDATE SET until = EXCLUDED.until
+        "#,
+    )
+    .bind(execution_id)
+    .bind(worker_id)
+    .bind(backoff.as_secs_f64())
+    .execute(&mut *tx)
+    .await
+    .context("Failed to record claim denial")?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Serialize depth checks for a queue until the transaction ends
+///
+/// Without this, two concurrent creations could both see room for one more
+/// execution and both enqueue.
+pub async fn lock_queue_depth(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    queue: &str,
+) -> Result<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('rhythm.queue_depth:' || $1, 0))")
+        .bind(queue)
+        .execute(&mut **tx)
+        .await
+        .context("Failed to lock queue for depth check")?;
+
+    Ok(())
+}
+
+/// Work waiting in a queue: unclaimed, or claimed with the lease run out,
+/// as `queue_depths` counts `pending`
+pub async fn pending_depth<'e, E>(executor: E, queue: &str) -> Result<i64>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM work_queue
+        WHERE queue = $1
+          AND (claimed_until IS NULL OR claimed_until < NOW())
+        "#,
+    )
+    .bind(queue)
+    .fetch_one(executor)
+    .await
+    .context("Failed to count queued work")
+}
+
+/// Pending and claimed work per queue, for queues with any work
+pub async fn queue_depths<'e, E>(executor: E) -> Result<Vec<QueueDepth>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT queue,
+               COUNT(*) FILTER (WHERE claimed_until IS NULL OR claimed_until < NOW()) AS pending,
+               COUNT(*) FILTER (WHERE claimed_until >= NOW()) AS claimed
+        FROM work_queue
+        GROUP BY queue
+        ORDER BY queue
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to count queued work")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| QueueDepth {
+            queue: row.get("queue"),
+            pending: row.get("pending"),
+            claimed: row.get("claimed"),
+        })
+        .collect())
+}
+
+/// Work, suspended workflows and executions finished in the last
+/// `window_minutes` per queue, for queues with any of them
+pub async fn queue_stats<'e, E>(executor: E, window_minutes: u32) -> Result<Vec<QueueStats>>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    let rows = sqlx::query(
+        r#"
+        WITH work AS (
+            SELECT queue,
+                   COUNT(*) FILTER (WHERE claimed_until IS NULL OR claimed_until < NOW()) AS pending,
+                   COUNT(*) FILTER (WHERE claimed_until >= NOW()) AS claimed,
+                   EXTRACT(EPOCH FROM LOCALTIMESTAMP - MIN(created_at) FILTER (
+                       WHERE claimed_until IS NULL OR claimed_until < NOW()
+                   ))::float8 AS oldest_pending_secs
+            FROM work_queue
+            GROUP BY queue
+        ),
+        recent AS (
+            SELECT queue,
+                   COUNT(*) FILTER (WHERE status = 'suspended') AS suspended,
+                   COUNT(*) FILTER (WHERE status = 'completed' AND completed_at >= NOW() - $1 * INTERVAL '1 minute') AS completed,
+                   COUNT(*) FILTER (WHERE status = 'failed' AND completed_at >= NOW() - $1 * INTERVAL '1 minute') AS failed
+            FROM executions
+            WHERE status = 'suspended' OR completed_at >= NOW() - $1 * INTERVAL '1 minute'
+            GROUP BY queue
+        )
+        SELECT COALESCE(w.queue, r.queue) AS queue,
+               COALESCE(w.pending, 0) AS pending,
+               COALESCE(w.claimed, 0) AS claimed,
+               COALESCE(r.suspended, 0) AS suspended,
+               w.oldest_pending_secs,
+               COALESCE(r.completed, 0) AS completed,
+               COALESCE(r.failed, 0) AS failed
+        FROM work w
+        FULL OUTER JOIN recent r ON r.queue = w.queue
+        ORDER BY 1
+        "#,
+    )
+    .bind(window_minutes as i32)
+    .fetch_all(executor)
+    .await
+    .context("Failed to compute queue stats")?;
+
+    let minutes = window_minutes.max(1) as f64;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let completed: i64 = row.get("completed");
+            let failed: i64 = row.get("failed");
+            QueueStats {
+                queue: row.get("queue"),
+                pending: row.get("pending"),
+                claimed: row.get("claimed"),
+                suspended: row.get("suspended"),
+                oldest_pending_secs: row.get("oldest_pending_secs"),
+                completed,
+                failed,
+                throughput_per_minute: (completed + failed) as f64 / minutes,
+            }
+        })
+        .collect())
+}
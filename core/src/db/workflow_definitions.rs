@@ -1,8 +1,11 @@
 //! Workflow Definitions Database Operations
 
 use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
 use sqlx::{PgPool, Row};
 
+use crate::types::WorkflowSummary;
+
 /// Get existing workflow definition by name and version hash
 ///
 /// Returns the workflow ID if it exists, None otherwise.
@@ -37,17 +40,36 @@ pub async fn create_workflow_definition(
     name: &str,
     version_hash: &str,
     source: &str,
+) -> Result<i32> {
+    create_workflow_definition_with_settings(
+        pool,
+        name,
+        version_hash,
+        source,
+        &JsonValue::Object(Default::default()),
+    )
+    .await
+}
+
+/// Create a new workflow definition with its effective settings
+pub async fn create_workflow_definition_with_settings(
+    pool: &PgPool,
+    name: &str,
+    version_hash: &str,
+    source: &str,
+    settings: &JsonValue,
 ) -> Result<i32> {
     let row = sqlx::query(
         r#"
-        INSERT INTO workflow_definitions (name, version_hash, source, parsed_steps, file_path)
-        VALUES ($1, $2, $3, '{}', '')
+        INSERT INTO workflow_definitions (name, version_hash, source, parsed_steps, file_path, settings)
+        VALUES ($1, $2, $3, '{}', '', $4)
         RETURNING id
         "#,
     )
     .bind(name)
     .bind(version_hash)
     .bind(source)
+    .bind(settings)
     .fetch_one(pool)
     .await
     .context("Failed to create workflow definition")?;
@@ -55,6 +77,28 @@ pub async fn create_workflow_definition(
     Ok(row.get("id"))
 }
 
+/// Get the source and effective settings of the latest definition of a workflow
+pub async fn get_workflow_settings(
+    pool: &PgPool,
+    workflow_name: &str,
+) -> Result<Option<(String, JsonValue)>> {
+    let row = sqlx::query(
+        r#"
+        SELECT source, settings
+        FROM workflow_definitions
+        WHERE name = $1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(workflow_name)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch workflow settings")?;
+
+    Ok(row.map(|r| (r.get("source"), r.get("settings"))))
+}
+
 /// Get workflow source from workflow_definitions by name
 ///
 /// Returns the workflow definition ID and source code for the most recently
@@ -76,3 +120,47 @@ pub async fn get_workflow_by_name(pool: &PgPool, workflow_name: &str) -> Result<
 
     Ok((row.get("id"), row.get("source")))
 }
+
+/// Get a workflow definition's name and source by its ID
+pub async fn get_workflow_definition(pool: &PgPool, id: i32) -> Result<(String, String)> {
+    let row = sqlx::query(
+        r#"
+        SELECT name, source
+        FROM workflow_definitions
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_one(pool)
+    .await
+    .context("Failed to fetch workflow definition")?;
+
+    Ok((row.get("name"), row.get("source")))
+}
+
+/// The latest definition of every registered workflow, by name
+pub async fn list_workflows(pool: &PgPool) -> Result<Vec<WorkflowSummary>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT DISTINCT ON (name)
+            name, version_hash, settings, created_at,
+            COUNT(*) OVER (PARTITION BY name) AS versions
+        FROM workflow_definitions
+        ORDER BY name, created_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to list workflow definitions")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| WorkflowSummary {
+            name: row.get("name"),
+            version_hash: row.get("version_hash"),
+            versions: row.get("versions"),
+            settings: row.get("settings"),
+            registered_at: row.get("created_at"),
+        })
+        .collect())
+}
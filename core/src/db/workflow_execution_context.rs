@@ -1,14 +1,51 @@
 //! Workflow Execution Context Database Operations
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
+use sqlx::postgres::PgRow;
 use sqlx::{PgPool, Row};
 
+/// A saved VM state as stored
+///
+/// JSON states are in the `locals` column and binary ones (see
+/// `state_encoding`) in `state`; a row has one or the other.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoredState {
+    Json(JsonValue),
+    Binary(Vec<u8>),
+}
+
+impl StoredState {
+    /// The state if it is stored as JSON
+    pub fn as_json(&self) -> Option<&JsonValue> {
+        match self {
+            StoredState::Json(value) => Some(value),
+            StoredState::Binary(_) => None,
+        }
+    }
+
+    /// Values for the `locals` and `state` columns
+    fn columns(&self) -> (Option<&JsonValue>, Option<&[u8]>) {
+        match self {
+            StoredState::Json(value) => (Some(value), None),
+            StoredState::Binary(bytes) => (None, Some(bytes)),
+        }
+    }
+
+    fn from_row(row: &PgRow) -> Self {
+        match row.get::<Option<Vec<u8>>, _>("state") {
+            Some(bytes) => StoredState::Binary(bytes),
+            None => StoredState::Json(row.get("vm_state")),
+        }
+    }
+}
+
 /// Workflow execution context from database
 #[derive(Debug)]
 pub struct WorkflowExecutionContext {
     pub workflow_definition_id: i32,
-    pub vm_state: JsonValue,
+    pub vm_state: StoredState,
 }
 
 /// Get workflow execution context for a given execution ID
@@ -20,7 +57,7 @@ pub async fn get_context(
 ) -> Result<Option<WorkflowExecutionContext>> {
     let maybe_row = sqlx::query(
         r#"
-        SELECT workflow_definition_id, locals as vm_state
+        SELECT workflow_definition_id, locals as vm_state, state
         FROM workflow_execution_context
         WHERE execution_id = $1
         "#,
@@ -32,37 +69,67 @@ pub async fn get_context(
 
     Ok(maybe_row.map(|row| WorkflowExecutionContext {
         workflow_definition_id: row.get("workflow_definition_id"),
-        vm_state: row.get("vm_state"),
+        vm_state: StoredState::from_row(&row),
     }))
 }
 
+/// Version of an execution's saved state, if it has one
+///
+/// Cheaper than `get_context` for checking whether a copy of the state kept
+/// in memory is still current.
+pub async fn get_context_version(pool: &PgPool, execution_id: &str) -> Result<Option<i64>> {
+    sqlx::query_scalar(
+        r#"
+        SELECT version
+        FROM workflow_execution_context
+        WHERE execution_id = $1
+        "#,
+    )
+    .bind(execution_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch workflow execution context version")
+}
+
 /// Upsert workflow execution context
 ///
-/// Creates a new record if it doesn't exist, updates if it does.
+/// Creates a new record if it doesn't exist, updates if it does. With
+/// `sticky`, a `(worker_id, timeout_ms)` pair, the workflow prefers that
+/// worker the next time it is queued (see `work_queue::enqueue_work`).
+/// Returns the version of the saved state.
 pub async fn upsert_context(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     execution_id: &str,
     workflow_definition_id: i32,
-    vm_state: &JsonValue,
-) -> Result<()> {
-    sqlx::query(
+    vm_state: &StoredState,
+    sticky: Option<(&str, i32)>,
+) -> Result<i64> {
+    let (sticky_worker_id, sticky_timeout_ms) = sticky.unzip();
+    let (locals, state) = vm_state.columns();
+    sqlx::query_scalar(
         r#"
-        INSERT INTO workflow_execution_context (execution_id, workflow_definition_id, locals)
-        VALUES ($1, $2, $3)
+        INSERT INTO workflow_execution_context
+            (execution_id, workflow_definition_id, locals, state, sticky_worker_id, sticky_timeout_ms)
+        VALUES ($1, $2, $3, $4, $5, $6)
         ON CONFLICT (execution_id)
         DO UPDATE SET
             locals = EXCLUDED.locals,
+            state = EXCLUDED.state,
+            sticky_worker_id = EXCLUDED.sticky_worker_id,
+            sticky_timeout_ms = EXCLUDED.sticky_timeout_ms,
             updated_at = NOW()
+        RETURNING version
         "#,
     )
     .bind(execution_id)
     .bind(workflow_definition_id)
-    .bind(vm_state)
-    .execute(&mut **tx)
+    .bind(locals)
+    .bind(state)
+    .bind(sticky_worker_id)
+    .bind(sticky_timeout_ms)
+    .fetch_one(&mut **tx)
     .await
-    .context("Failed to upsert workflow execution context")?;
-
-    Ok(())
+    .context("Failed to upsert workflow execution context")
 }
 
 /// Delete workflow execution context
@@ -85,3 +152,168 @@ where
 
     Ok(())
 }
+
+/// Delete the contexts of several executions, e.g. cancelled workflows
+pub async fn delete_contexts<'e, E>(executor: E, execution_ids: &[String]) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        DELETE FROM workflow_execution_context
+        WHERE execution_id = ANY($1)
+        "#,
+    )
+    .bind(execution_ids)
+    .execute(executor)
+    .await
+    .context("Failed to delete workflow execution contexts")?;
+
+    Ok(())
+}
+
+/// Saved state of a workflow execution, with when it was saved
+#[derive(Debug)]
+pub struct SuspendedContext {
+    pub execution_id: String,
+    pub workflow_definition_id: i32,
+    pub vm_state: StoredState,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Get the saved state of every suspended execution of a workflow
+pub async fn list_suspended_contexts(
+    pool: &PgPool,
+    workflow_name: &str,
+) -> Result<Vec<SuspendedContext>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT c.execution_id, c.workflow_definition_id, c.locals as vm_state, c.state,
+            c.updated_at
+        FROM workflow_execution_context c
+        JOIN executions e ON e.id = c.execution_id
+        WHERE e.type = 'workflow'
+          AND e.target_name = $1
+          AND e.status = 'suspended'
+        ORDER BY e.created_at, e.id
+        "#,
+    )
+    .bind(workflow_name)
+    .fetch_all(pool)
+    .await
+    .context("Failed to list suspended workflow contexts")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SuspendedContext {
+            execution_id: row.get("execution_id"),
+            workflow_definition_id: row.get("workflow_definition_id"),
+            vm_state: StoredState::from_row(&row),
+            updated_at: row.get("updated_at"),
+        })
+        .collect())
+}
+
+/// Replace a suspended execution's state and definition
+///
+/// Only applies while the execution is still suspended and its state is
+/// unchanged since `seen_at`, so a worker resuming it in the meantime wins.
+/// Returns whether the state was replaced.
+pub async fn replace_suspended_context(
+    pool: &PgPool,
+    execution_id: &str,
+    seen_at: DateTime<Utc>,
+    workflow_definition_id: i32,
+    vm_state: &StoredState,
+) -> Result<bool> {
+    let (locals, state) = vm_state.columns();
+    let result = sqlx::query(
+        r#"
+        UPDATE workflow_execution_context c
+        SET workflow_definition_id = $3,
+            locals = $4,
+            state = $5,
+            updated_at = NOW()
+        FROM executions e
+        WHERE c.execution_id = $1
+          AND c.updated_at = $2
+          AND e.id = c.execution_id
+          AND e.status = 'suspended'
+        "#,
+    )
+    .bind(execution_id)
+    .bind(seen_at)
+    .bind(workflow_definition_id)
+    .bind(locals)
+    .bind(state)
+    .execute(pool)
+    .await
+    .context("Failed to replace workflow execution context")?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Get up to `limit` saved states, any status, ordered by execution ID
+///
+/// Pages start after `after_execution_id`, so callers can walk the table.
+pub async fn list_contexts_page(
+    pool: &PgPool,
+    after_execution_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<SuspendedContext>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT execution_id, workflow_definition_id, locals as vm_state, state, updated_at
+        FROM workflow_execution_context
+        WHERE $1::TEXT IS NULL OR execution_id > $1
+        ORDER BY execution_id
+        LIMIT $2
+        "#,
+    )
+    .bind(after_execution_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to list workflow execution contexts")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SuspendedContext {
+            execution_id: row.get("execution_id"),
+            workflow_definition_id: row.get("workflow_definition_id"),
+            vm_state: StoredState::from_row(&row),
+            updated_at: row.get("updated_at"),
+        })
+        .collect())
+}
+
+/// Replace a saved state, unless a worker saved a new one since `seen_at`
+///
+/// Returns whether the state was replaced.
+pub async fn replace_context_state(
+    pool: &PgPool,
+    execution_id: &str,
+    seen_at: DateTime<Utc>,
+    vm_state: &StoredState,
+) -> Result<bool> {
+    let (locals, state) = vm_state.columns();
+    let result = sqlx::query(
+        r#"
+        UPDATE workflow_execution_context
+        SET locals = $3,
+            state = $4,
+            updated_at = NOW()
+        WHERE execution_id = $1
+          AND updated_at = $2
+        "#,
+    )
+    .bind(execution_id)
+    .bind(seen_at)
+    .bind(locals)
+    .bind(state)
+    .execute(pool)
+    .await
+    .context("Failed to replace workflow execution context state")?;
+
+    Ok(result.rows_affected() == 1)
+}
@@ -0,0 +1,207 @@
+//! Sampling of executions for verbose diagnostics
+//!
+//! Recording every workflow run in detail (locals, step counts, outcomes) is
+//! too expensive at scale, so it is done for a sample of executions chosen
+//! by `[diagnostics]` config. Anything that records detail about an
+//! execution asks the same `DiagnosticsSampler`, so a sampled execution is
+//! sampled everywhere and an unsampled one nowhere.
+//!
+//! Sampling is decided from a hash of the execution id, not a random draw:
+//! every worker and every resumption of a workflow comes to the same answer
+//! without storing it. Failed executions can be recorded regardless
+//! (`sample_failures`), since those are the ones worth looking into.
+//!
+//! Detail is emitted as tracing events with the `rhythm::diagnostics` target,
+//! so it can be routed separately from regular logs.
+
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+
+use crate::config::{DiagnosticsConfig, SamplingRule};
+use crate::executor::stdlib::inject_stdlib;
+use crate::executor::{Control, VM};
+use crate::types::Execution;
+
+/// Tracing target of detailed diagnostics events
+pub const DIAGNOSTICS_TARGET: &str = "rhythm::diagnostics";
+
+/// Decides which executions get verbose diagnostics
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticsSampler {
+    config: DiagnosticsConfig,
+}
+
+impl DiagnosticsSampler {
+    pub fn new(config: DiagnosticsConfig) -> Self {
+        Self { config }
+    }
+
+    /// Fraction of executions of `target_name` on `queue` that are sampled
+    pub fn sample_rate(&self, target_name: &str, queue: &str) -> f64 {
+        self.config
+            .rules
+            .iter()
+            .find(|rule| rule_matches(rule, target_name, queue))
+            .map_or(self.config.sample_rate, |rule| rule.sample_rate)
+            .clamp(0.0, 1.0)
+    }
+
+    /// Whether `execution` is in the sample, regardless of how it ends
+    pub fn is_sampled(&self, execution: &Execution) -> bool {
+        sample_point(&execution.id) < self.sample_rate(&execution.target_name, &execution.queue)
+    }
+
+    /// Whether to record detail for `execution`, given whether it failed
+    pub fn records(&self, execution: &Execution, failed: bool) -> bool {
+        (failed && self.config.sample_failures) || self.is_sampled(execution)
+    }
+
+    /// Emit the detail of a workflow run, if `execution` is recorded
+    pub fn record_run(&self, execution: &Execution, vm: &VM, steps: usize, yielded: bool) {
+        let failed = matches!(vm.control, Control::Throw(_));
+        if !self.records(execution, failed) {
+            return;
+        }
+        let outcome = match &vm.control {
+            _ if yielded => "yielded",
+            Control::Suspend(_) => "suspended",
+            Control::Throw(_) => "failed",
+            _ => "completed",
+        };
+        tracing::info!(
+            target: DIAGNOSTICS_TARGET,
+            execution_id = %execution.id,
+            target_name = %execution.target_name,
+            queue = %execution.queue,
+            outcome,
+            steps,
+            locals = %workflow_locals(vm),
+            "Workflow run"
+        );
+    }
+}
+
+fn rule_matches(rule: &SamplingRule, target_name: &str, queue: &str) -> bool {
+    rule.target_name.as_deref().is_none_or(|t| t == target_name)
+        && rule.queue.as_deref().is_none_or(|q| q == queue)
+}
+
+/// Position of an execution id in [0, 1), stable across processes
+fn sample_point(execution_id: &str) -> f64 {
+    // FNV-1a; std's hashers aren't guaranteed stable between releases
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in execution_id.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    // Finish with murmur3's fmix64, as FNV's high bits barely move for ids
+    // differing only in their last characters
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^= hash >> 33;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Variables the workflow's own code has set, leaving out builtins
+fn workflow_locals(vm: &VM) -> JsonValue {
+    let mut builtins = std::collections::HashMap::new();
+    inject_stdlib(&mut builtins);
+    let builtins: HashSet<_> = builtins
+        .into_keys()
+        .chain(["Context".to_string()])
+        .collect();
+
+    let mut locals: Vec<_> = vm
+        .env
+        .iter()
+        .filter(|(name, _)| !builtins.contains(*name))
+        .collect();
+    locals.sort_by(|a, b| a.0.cmp(b.0));
+    locals
+        .into_iter()
+        .map(|(name, val)| {
+            let val = serde_json::to_value(val).unwrap_or(JsonValue::Null);
+            (name.clone(), val)
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ExecutionStatus, ExecutionType};
+    use serde_json::json;
+
+    fn execution(id: &str, target_name: &str, queue: &str) -> Execution {
+        Execution {
+            id: id.to_string(),
+            exec_type: ExecutionType::Workflow,
+            target_name: target_name.to_string(),
+            queue: queue.to_string(),
+            namespace: "default".to_string(),
+            status: ExecutionStatus::Running,
+            inputs: json!({}),
+            output: None,
+            attempt: 0,
+            parent_workflow_id: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    fn sampler(sample_rate: f64, sample_failures: bool) -> DiagnosticsSampler {
+        DiagnosticsSampler::new(DiagnosticsConfig {
+            sample_rate,
+            sample_failures,
+            rules: vec![
+                SamplingRule {
+                    target_name: Some("checkout".to_string()),
+                    queue: None,
+                    sample_rate: 1.0,
+                },
+                SamplingRule {
+                    target_name: None,
+                    queue: Some("bulk".to_string()),
+                    sample_rate: 0.0,
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn test_rules_override_default_rate() {
+        let sampler = sampler(0.25, false);
+        assert_eq!(sampler.sample_rate("checkout", "bulk"), 1.0);
+        assert_eq!(sampler.sample_rate("import", "bulk"), 0.0);
+        assert_eq!(sampler.sample_rate("import", "default"), 0.25);
+    }
+
+    #[test]
+    fn test_sampling_is_stable_and_near_rate() {
+        let sampler = sampler(0.1, false);
+        let ids: Vec<_> = (0..10_000).map(|i| format!("exec-{}", i)).collect();
+        let sampled: Vec<_> = ids
+            .iter()
+            .map(|id| sampler.is_sampled(&execution(id, "import", "default")))
+            .collect();
+        let again: Vec<_> = ids
+            .iter()
+            .map(|id| sampler.is_sampled(&execution(id, "import", "default")))
+            .collect();
+        assert_eq!(sampled, again);
+
+        let count = sampled.iter().filter(|s| **s).count();
+        assert!((800..1200).contains(&count), "{} sampled", count);
+    }
+
+    #[test]
+    fn test_failures_recorded_when_configured() {
+        let exec = execution("exec-1", "import", "bulk");
+        assert!(!sampler(1.0, false).records(&exec, true));
+        assert!(sampler(1.0, true).records(&exec, true));
+        assert!(!sampler(1.0, true).records(&exec, false));
+    }
+}
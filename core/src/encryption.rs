@@ -0,0 +1,510 @@
+//! Encryption at rest for payloads and saved workflow states
+//!
+//! With an `[encryption] active_key` configured, execution inputs, outputs
+//! and errors and saved VM states are encrypted with AES-256-GCM before they
+//! are written, and stored as an envelope naming the key they were
+//! encrypted with:
+//!
+//! ```json
+//! { "$rhythm_enc": { "key": "2026-10", "nonce": "<base64>", "data": "<base64>", "for": "inputs:<id>" } }
+//! ```
+//!
+//! `for` names the execution and column the value was written to, which is
+//! authenticated with the ciphertext, so an envelope copied into another row
+//! fails to decrypt. Envelopes written before values were bound have no
+//! `for` and decrypt anywhere until they are re-encrypted.
+//!
+//! Envelopes are decrypted where blob references are resolved (see
+//! `blob_store`) and where saved states are loaded. Any listed key can
+//! decrypt, so keys are rotated by adding a new one, making it active, and
+//! re-encrypting what is stored with `rhythm reencrypt --apply` before the
+//! old key is removed. Rows written before encryption was turned on are read
+//! as they are.
+//!
+//! Errors the engine writes itself (cancellations, expiries, timeouts) are
+//! stored in plain text, as are signal payloads, logs, events and VM
+//! traces. `rhythm export` writes values as they are stored, so an import
+//! needs the keys they were encrypted with.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::EncryptionConfig;
+use crate::errors::RhythmError;
+
+/// Key of the object that stands in for an encrypted value
+pub const ENVELOPE_KEY: &str = "$rhythm_enc";
+
+/// Field of an envelope naming the row and column it belongs to
+pub const BINDING_KEY: &str = "for";
+
+const KEY_BYTES: usize = 32;
+const NONCE_BYTES: usize = 12;
+
+/// Column an encrypted value is stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Inputs,
+    Output,
+    State,
+}
+
+impl Column {
+    fn as_str(self) -> &'static str {
+        match self {
+            Column::Inputs => "inputs",
+            Column::Output => "output",
+            Column::State => "state",
+        }
+    }
+}
+
+/// The execution and column a value is encrypted for
+///
+/// Written as `<column>:<execution id>` and authenticated with the
+/// ciphertext, so a value only decrypts where it was stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding<'a> {
+    pub execution_id: &'a str,
+    pub column: Column,
+}
+
+impl<'a> Binding<'a> {
+    pub fn inputs(execution_id: &'a str) -> Self {
+        Self {
+            execution_id,
+            column: Column::Inputs,
+        }
+    }
+
+    pub fn output(execution_id: &'a str) -> Self {
+        Self {
+            execution_id,
+            column: Column::Output,
+        }
+    }
+
+    pub fn state(execution_id: &'a str) -> Self {
+        Self {
+            execution_id,
+            column: Column::State,
+        }
+    }
+
+    /// The binding `text` was written as by `to_string`
+    pub fn parse(text: &'a str) -> Option<Self> {
+        let (column, execution_id) = text.split_once(':')?;
+        let column = match column {
+            "inputs" => Column::Inputs,
+            "output" => Column::Output,
+            "state" => Column::State,
+            _ => return None,
+        };
+        Some(Self {
+            execution_id,
+            column,
+        })
+    }
+}
+
+impl fmt::Display for Binding<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.column.as_str(), self.execution_id)
+    }
+}
+
+/// The keys values are encrypted and decrypted with
+#[derive(Clone)]
+pub struct Keyring {
+    active: String,
+    ciphers: HashMap<String, Aes256Gcm>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut key_ids: Vec<_> = self.ciphers.keys().collect();
+        key_ids.sort();
+        f.debug_struct("Keyring")
+            .field("active", &self.active)
+            .field("keys", &key_ids)
+            .finish()
+    }
+}
+
+impl Keyring {
+    /// The keyring `config` describes, or `None` if encryption is off
+    ///
+    /// Fails if a key isn't 32 bytes of base64, or the active key isn't
+    /// one of the keys.
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>> {
+        let mut ciphers = HashMap::new();
+        for (id, key) in &config.keys {
+            let bytes = BASE64
+                .decode(key.trim())
+                .with_context(|| format!("Encryption key '{}' is not valid base64", id))?;
+            if bytes.len() != KEY_BYTES {
+                anyhow::bail!(
+                    "Encryption key '{}' is {} bytes; AES-256 keys are {}",
+                    id,
+                    bytes.len(),
+                    KEY_BYTES
+                );
+            }
+            let cipher = Aes256Gcm::new_from_slice(&bytes)
+                .map_err(|_| anyhow!("Encryption key '{}' is not a valid key", id))?;
+            ciphers.insert(id.clone(), cipher);
+        }
+
+        let Some(active) = config.active_key.clone() else {
+            return Ok(None);
+        };
+        if !ciphers.contains_key(&active) {
+            anyhow::bail!("Active encryption key '{}' is not among the keys", active);
+        }
+        Ok(Some(Self { active, ciphers }))
+    }
+
+    /// ID of the key new values are encrypted with
+    pub fn active_key(&self) -> &str {
+        &self.active
+    }
+
+    /// `value` encrypted with the active key for `binding`, as an envelope
+    pub fn encrypt(&self, value: &JsonValue, binding: Binding<'_>) -> Result<JsonValue> {
+        let plaintext = serde_json::to_vec(value).context("Failed to serialize value")?;
+        let aad = binding.to_string();
+        let (nonce, data) = self.encrypt_with_active(&plaintext, &aad)?;
+        Ok(json!({
+            ENVELOPE_KEY: {
+                "key": self.active,
+                "nonce": BASE64.encode(nonce),
+                "data": BASE64.encode(data),
+                BINDING_KEY: aad,
+            }
+        }))
+    }
+
+    /// The value in `envelope`, read from where `binding` names
+    ///
+    /// Fails if it was encrypted with a key that isn't listed, was tampered
+    /// with, or was encrypted for another row or column. An envelope
+    /// written before values were bound decrypts without `binding`.
+    pub fn decrypt(&self, envelope: &JsonValue, binding: Option<Binding<'_>>) -> Result<JsonValue> {
+        let key_id = envelope_key(envelope).ok_or_else(|| anyhow!("Not an encrypted value"))?;
+        let cipher = self.ciphers.get(key_id).ok_or_else(|| {
+            anyhow!(
+                "Value is encrypted with key '{}', which is not configured",
+                key_id
+            )
+        })?;
+
+        let sealed = &envelope[ENVELOPE_KEY];
+        let field = |name: &str| -> Result<Vec<u8>> {
+            let encoded = sealed[name]
+                .as_str()
+                .ok_or_else(|| anyhow!("Encrypted value has no {}", name))?;
+            BASE64
+                .decode(encoded)
+                .with_context(|| format!("Encrypted value has an invalid {}", name))
+        };
+        let nonce = field("nonce")?;
+        if nonce.len() != NONCE_BYTES {
+            anyhow::bail!("Encrypted value has an invalid nonce");
+        }
+        let aad = match (sealed.get(BINDING_KEY), binding) {
+            (None, _) => String::new(),
+            (Some(_), None) => anyhow::bail!("Encrypted value was read without its row"),
+            (Some(bound), Some(binding)) => {
+                let aad = binding.to_string();
+                let bound = bound.as_str().unwrap_or_default();
+                if bound != aad {
+                    anyhow::bail!("Encrypted value belongs to {}, not {}", bound, aad);
+                }
+                aad
+            }
+        };
+        let msg = field("data")?;
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &msg,
+                    aad: aad.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("Failed to decrypt value with key '{}'", key_id))?;
+        serde_json::from_slice(&plaintext).context("Decrypted value is not valid JSON")
+    }
+
+    /// `plaintext` encrypted with the active key for `binding`
+    ///
+    /// The bytes name the key, as a length byte and the key ID, followed by
+    /// the nonce and the ciphertext. The binding isn't stored, so whoever
+    /// stores them must note that they are bound.
+    pub fn encrypt_bytes(&self, plaintext: &[u8], binding: Binding<'_>) -> Result<Vec<u8>> {
+        let key_id = self.active.as_bytes();
+        let key_len = u8::try_from(key_id.len())
+            .map_err(|_| anyhow!("Encryption key ID '{}' is too long", self.active))?;
+        let (nonce, data) = self.encrypt_with_active(plaintext, &binding.to_string())?;
+
+        let mut sealed = Vec::with_capacity(1 + key_id.len() + nonce.len() + data.len());
+        sealed.push(key_len);
+        sealed.extend_from_slice(key_id);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&data);
+        Ok(sealed)
+    }
+
+    /// The plaintext `encrypt_bytes` encrypted into `sealed` for
+    /// `binding`, or `None` for bytes written before values were bound
+    pub fn decrypt_bytes(&self, sealed: &[u8], binding: Option<Binding<'_>>) -> Result<Vec<u8>> {
+        let key_id = bytes_key(sealed).ok_or_else(|| anyhow!("Not encrypted bytes"))?;
+        let cipher = self.ciphers.get(key_id).ok_or_else(|| {
+            anyhow!(
+                "Value is encrypted with key '{}', which is not configured",
+                key_id
+            )
+        })?;
+        let rest = &sealed[1 + key_id.len()..];
+        if rest.len() < NONCE_BYTES {
+            anyhow::bail!("Encrypted value has an invalid nonce");
+        }
+        let (nonce, msg) = rest.split_at(NONCE_BYTES);
+        let aad = binding.map(|b| b.to_string()).unwrap_or_default();
+        cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg,
+                    aad: aad.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("Failed to decrypt value with key '{}'", key_id))
+    }
+
+    fn encrypt_with_active(&self, plaintext: &[u8], aad: &str) -> Result<(Vec<u8>, Vec<u8>)> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: plaintext,
+            aad: aad.as_bytes(),
+        };
+        let data = self.ciphers[&self.active]
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow!("Failed to encrypt value"))?;
+        Ok((nonce.to_vec(), data))
+    }
+}
+
+/// The ID of the key `sealed` was encrypted with by `encrypt_bytes`
+pub fn bytes_key(sealed: &[u8]) -> Option<&str> {
+    let (&key_len, rest) = sealed.split_first()?;
+    std::str::from_utf8(rest.get(..key_len as usize)?).ok()
+}
+
+/// The ID of the key `value` was encrypted with, if it is an envelope
+pub fn envelope_key(value: &JsonValue) -> Option<&str> {
+    match value {
+        JsonValue::Object(map) if map.len() == 1 => map.get(ENVELOPE_KEY)?["key"].as_str(),
+        _ => None,
+    }
+}
+
+/// Whether `envelope` was encrypted for the row it is stored in
+pub fn is_bound(envelope: &JsonValue) -> bool {
+    envelope[ENVELOPE_KEY].get(BINDING_KEY).is_some()
+}
+
+/// The binding `envelope` says it was encrypted for
+pub fn stated_binding(envelope: &JsonValue) -> Option<Binding<'_>> {
+    Binding::parse(envelope[ENVELOPE_KEY][BINDING_KEY].as_str()?)
+}
+
+/// `value` decrypted if it is an envelope, or as it is
+///
+/// Fails for an envelope when encryption is off.
+pub fn open(
+    keyring: Option<&Keyring>,
+    value: JsonValue,
+    binding: Option<Binding<'_>>,
+) -> Result<JsonValue> {
+    match (envelope_key(&value), keyring) {
+        (None, _) => Ok(value),
+        (Some(_), Some(keyring)) => keyring.decrypt(&value, binding),
+        (Some(key_id), None) => Err(anyhow!(
+            "Value is encrypted with key '{}', but encryption is not configured",
+            key_id
+        )),
+    }
+}
+
+/// `value` encrypted with the active key for `binding`, or as it is when
+/// encryption is off
+///
+/// Fails with `RhythmError::Validation` for a value shaped like an envelope
+/// when encryption is off, as it would be read back as one.
+pub fn seal(
+    keyring: Option<&Keyring>,
+    value: JsonValue,
+    binding: Binding<'_>,
+) -> Result<JsonValue> {
+    match keyring {
+        Some(keyring) => keyring.encrypt(&value, binding),
+        None if envelope_key(&value).is_some() => {
+            Err(RhythmError::Validation(format!("`{}` is a reserved key", ENVELOPE_KEY)).into())
+        }
+        None => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(active: &str, keys: &[(&str, u8)]) -> EncryptionConfig {
+        EncryptionConfig {
+            active_key: Some(active.to_string()),
+            keys: keys
+                .iter()
+                .map(|(id, byte)| (id.to_string(), BASE64.encode([*byte; KEY_BYTES])))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_and_rotation() {
+        let old = Keyring::from_config(&config("old", &[("old", 1)]))
+            .unwrap()
+            .unwrap();
+        let value = json!({ "email": "ada@example.com", "amount": 42 });
+        let row = Some(Binding::inputs("e1"));
+        let sealed = old.encrypt(&value, Binding::inputs("e1")).unwrap();
+        assert_eq!(envelope_key(&sealed), Some("old"));
+        assert!(!sealed.to_string().contains("ada@example.com"));
+        assert_eq!(old.decrypt(&sealed, row).unwrap(), value);
+
+        // After rotation, old values still decrypt and new ones use the new key
+        let rotated = Keyring::from_config(&config("new", &[("old", 1), ("new", 2)]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(rotated.decrypt(&sealed, row).unwrap(), value);
+        let resealed = rotated.encrypt(&value, Binding::inputs("e1")).unwrap();
+        assert_eq!(envelope_key(&resealed), Some("new"));
+
+        // Once the old key is gone, its values can't be read
+        let retired = Keyring::from_config(&config("new", &[("new", 2)]))
+            .unwrap()
+            .unwrap();
+        let err = retired.decrypt(&sealed, row).unwrap_err();
+        assert!(err.to_string().contains("'old', which is not configured"));
+    }
+
+    #[test]
+    fn test_tampered_and_plain_values() {
+        let keyring = Keyring::from_config(&config("k1", &[("k1", 7)]))
+            .unwrap()
+            .unwrap();
+        let row = Binding::output("e1");
+        let mut sealed = keyring.encrypt(&json!("secret"), row).unwrap();
+        sealed[ENVELOPE_KEY]["nonce"] = json!(BASE64.encode([0u8; 12]));
+        assert!(keyring.decrypt(&sealed, Some(row)).is_err());
+
+        // Values written before encryption was on are read as they are
+        let plain = json!({ "id": 1 });
+        assert_eq!(
+            open(Some(&keyring), plain.clone(), Some(row)).unwrap(),
+            plain
+        );
+        let sealed = keyring.encrypt(&plain, row).unwrap();
+        assert!(open(None, sealed.clone(), Some(row)).is_err());
+
+        // Nor can plain values pass for envelopes when encryption is off
+        let err = seal(None, sealed, row).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RhythmError>(),
+            Some(RhythmError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_values_only_decrypt_where_they_were_stored() {
+        let keyring = Keyring::from_config(&config("k1", &[("k1", 7)]))
+            .unwrap()
+            .unwrap();
+        let sealed = keyring
+            .encrypt(&json!("secret"), Binding::output("e1"))
+            .unwrap();
+        assert_eq!(stated_binding(&sealed), Some(Binding::output("e1")));
+
+        for other in [Binding::output("e2"), Binding::inputs("e1")] {
+            let err = keyring.decrypt(&sealed, Some(other)).unwrap_err();
+            assert!(err.to_string().contains("belongs to output:e1"));
+        }
+        assert!(keyring.decrypt(&sealed, None).is_err());
+
+        // Restating the binding doesn't get past the ciphertext's own
+        let mut moved = sealed.clone();
+        moved[ENVELOPE_KEY][BINDING_KEY] = json!("output:e2");
+        assert!(keyring
+            .decrypt(&moved, Some(Binding::output("e2")))
+            .is_err());
+
+        // Nor does dropping it
+        let mut unbound = sealed;
+        unbound[ENVELOPE_KEY]
+            .as_object_mut()
+            .unwrap()
+            .remove(BINDING_KEY);
+        assert!(!is_bound(&unbound));
+        assert!(keyring.decrypt(&unbound, None).is_err());
+
+        let state = keyring
+            .encrypt_bytes(b"state", Binding::state("e1"))
+            .unwrap();
+        assert!(keyring
+            .decrypt_bytes(&state, Some(Binding::state("e2")))
+            .is_err());
+        assert!(keyring.decrypt_bytes(&state, None).is_err());
+        assert_eq!(Binding::parse("state:e:1"), Some(Binding::state("e:1")));
+        assert_eq!(Binding::parse("vm:e1"), None);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let keyring = Keyring::from_config(&config("k1", &[("k1", 7)]))
+            .unwrap()
+            .unwrap();
+        let row = Some(Binding::state("e1"));
+        let sealed = keyring
+            .encrypt_bytes(b"saved state", Binding::state("e1"))
+            .unwrap();
+        assert_eq!(bytes_key(&sealed), Some("k1"));
+        assert_eq!(keyring.decrypt_bytes(&sealed, row).unwrap(), b"saved state");
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(keyring.decrypt_bytes(&tampered, row).is_err());
+        assert!(keyring.decrypt_bytes(&sealed[..4], row).is_err());
+        assert_eq!(bytes_key(&[]), None);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Keyring::from_config(&EncryptionConfig::default())
+            .unwrap()
+            .is_none());
+        assert!(Keyring::from_config(&config("missing", &[("k1", 1)])).is_err());
+
+        let short = EncryptionConfig {
+            active_key: Some("k1".to_string()),
+            keys: HashMap::from([("k1".to_string(), BASE64.encode([1u8; 16]))]),
+        };
+        let err = Keyring::from_config(&short).unwrap_err();
+        assert!(err.to_string().contains("16 bytes"));
+    }
+}
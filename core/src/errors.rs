@@ -0,0 +1,178 @@
+//! Error kinds adapters can tell apart
+//!
+//! Core returns `anyhow::Error` throughout. Where the caller did something
+//! wrong, the error carries a `RhythmError` (or one of the typed errors of
+//! quotas, queue limits, read-only mode, API keys and synchronous runs);
+//! database and serialization failures carry their library's error.
+//! `ErrorCode::of` finds the kind in an error's chain so bindings can raise
+//! a distinct exception for each.
+
+use serde::Serialize;
+
+use crate::application::ReadOnly;
+use crate::auth::{PermissionDenied, Unauthenticated};
+use crate::queue_limits::QueueFull;
+use crate::quotas::QuotaExceeded;
+use crate::services::workflow_service::{SyncRunFailed, SyncRunTimeout};
+
+/// Error for a request core refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum RhythmError {
+    /// No such record, e.g. `kind` "Execution"
+    NotFound { kind: &'static str, id: String },
+    /// The record is not in a state that allows the request
+    Conflict(String),
+    /// The request itself is invalid
+    Validation(String),
+}
+
+impl RhythmError {
+    pub fn not_found(kind: &'static str, id: impl Into<String>) -> Self {
+        RhythmError::NotFound {
+            kind,
+            id: id.into(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RhythmError::NotFound { .. } => ErrorCode::NotFound,
+            RhythmError::Conflict(_) => ErrorCode::Conflict,
+            RhythmError::Validation(_) => ErrorCode::Validation,
+        }
+    }
+}
+
+impl std::fmt::Display for RhythmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RhythmError::NotFound { kind, id } => write!(f, "{} not found: {}", kind, id),
+            RhythmError::Conflict(message) | RhythmError::Validation(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RhythmError {}
+
+/// Kind of an error, as sent across the FFI boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    Conflict,
+    Validation,
+    Serialization,
+    Database,
+    QuotaExceeded,
+    QueueFull,
+    ReadOnly,
+    Unauthenticated,
+    PermissionDenied,
+    WorkflowTimeout,
+    WorkflowFailed,
+    /// Anything else, e.g. core not being initialized
+    Internal,
+}
+
+impl ErrorCode {
+    /// The kind of the outermost recognized error in `error`'s chain
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(Self::of_cause)
+            .unwrap_or(ErrorCode::Internal)
+    }
+
+    fn of_cause(cause: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        if let Some(e) = cause.downcast_ref::<RhythmError>() {
+            Some(e.code())
+        } else if cause.is::<QuotaExceeded>() {
+            Some(ErrorCode::QuotaExceeded)
+        } else if cause.is::<QueueFull>() {
+            Some(ErrorCode::QueueFull)
+        } else if cause.is::<ReadOnly>() {
+            Some(ErrorCode::ReadOnly)
+        } else if cause.is::<Unauthenticated>() {
+            Some(ErrorCode::Unauthenticated)
+        } else if cause.is::<PermissionDenied>() {
+            Some(ErrorCode::PermissionDenied)
+        } else if cause.is::<SyncRunTimeout>() {
+            Some(ErrorCode::WorkflowTimeout)
+        } else if cause.is::<SyncRunFailed>() {
+            Some(ErrorCode::WorkflowFailed)
+        } else if cause.is::<serde_json::Error>() {
+            Some(ErrorCode::Serialization)
+        } else {
+            cause.downcast_ref::<sqlx::Error>().map(|e| match e {
+                sqlx::Error::RowNotFound => ErrorCode::NotFound,
+                sqlx::Error::Database(db) if db.is_unique_violation() => ErrorCode::Conflict,
+                _ => ErrorCode::Database,
+            })
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::Validation => "validation",
+            ErrorCode::Serialization => "serialization",
+            ErrorCode::Database => "database",
+            ErrorCode::QuotaExceeded => "quota_exceeded",
+            ErrorCode::QueueFull => "queue_full",
+            ErrorCode::ReadOnly => "read_only",
+            ErrorCode::Unauthenticated => "unauthenticated",
+            ErrorCode::PermissionDenied => "permission_denied",
+            ErrorCode::WorkflowTimeout => "workflow_timeout",
+            ErrorCode::WorkflowFailed => "workflow_failed",
+            ErrorCode::Internal => "internal",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_code_of_context_wrapped_errors() {
+        let error = anyhow::Error::new(RhythmError::not_found("Execution", "abc"))
+            .context("Failed to cancel execution");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::NotFound);
+        assert_eq!(error.root_cause().to_string(), "Execution not found: abc");
+
+        let error: anyhow::Error = serde_json::from_str::<u32>("{")
+            .context("Failed to decode inputs")
+            .unwrap_err();
+        assert_eq!(ErrorCode::of(&error), ErrorCode::Serialization);
+
+        let error = anyhow::Error::new(sqlx::Error::PoolTimedOut).context("Failed to claim");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::Database);
+
+        let error = anyhow::Error::new(ReadOnly {
+            operation: "create_execution",
+        });
+        assert_eq!(ErrorCode::of(&error).as_str(), "read_only");
+
+        let error = anyhow::Error::new(QueueFull {
+            queue: "emails".to_string(),
+            limit: 10,
+            depth: 10,
+        })
+        .context("Failed to create execution");
+        assert_eq!(ErrorCode::of(&error).as_str(), "queue_full");
+    }
+
+    #[test]
+    fn test_unrecognized_errors_are_internal() {
+        let error = anyhow::anyhow!("Application not initialized");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::Internal);
+        assert_eq!(
+            serde_json::to_value(ErrorCode::of(&error)).unwrap(),
+            serde_json::json!("internal")
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//! Side-by-side comparison of two executions
+//!
+//! Answers "why did the same workflow behave differently this time": the
+//! two executions are compared field by field, and their child executions
+//! are aligned by target name (longest common subsequence) so an extra,
+//! missing or reordered task shows up as such rather than as every later
+//! child differing. A different branch taken shows as the point where the
+//! child sequences part.
+//!
+//! Children are ordered by creation time. Children started in the same
+//! workflow run share a timestamp and are ordered by target name instead.
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::types::Execution;
+
+/// One difference between two JSON values, at a JSON-pointer-like path
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    /// `/`-separated path into the value; empty for the value itself
+    pub path: String,
+    pub left: Option<JsonValue>,
+    pub right: Option<JsonValue>,
+}
+
+/// A field that differs between two executions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldDiff {
+    /// `target_name`, `status`, `inputs` or `output`
+    pub field: &'static str,
+    pub changes: Vec<ValueChange>,
+}
+
+/// A child in one or both executions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChildDiff {
+    /// Same target at this point in both sequences
+    Matched {
+        left: Box<Execution>,
+        right: Box<Execution>,
+        fields: Vec<FieldDiff>,
+    },
+    /// Only the left execution started this child here
+    OnlyLeft { execution: Execution },
+    /// Only the right execution started this child here
+    OnlyRight { execution: Execution },
+}
+
+impl ChildDiff {
+    pub fn is_same(&self) -> bool {
+        matches!(self, ChildDiff::Matched { fields, .. } if fields.is_empty())
+    }
+}
+
+/// Comparison of two executions and their children
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExecutionDiff {
+    pub left: Execution,
+    pub right: Execution,
+    /// Differences between the executions themselves
+    pub fields: Vec<FieldDiff>,
+    /// Aligned children, in order
+    pub children: Vec<ChildDiff>,
+    /// Index into `children` where the two first differ
+    pub first_divergence: Option<usize>,
+}
+
+impl ExecutionDiff {
+    pub fn is_same(&self) -> bool {
+        self.fields.is_empty() && self.first_divergence.is_none()
+    }
+}
+
+/// Compare two executions given their children
+pub fn diff_executions(
+    left: Execution,
+    mut left_children: Vec<Execution>,
+    right: Execution,
+    mut right_children: Vec<Execution>,
+) -> ExecutionDiff {
+    sort_children(&mut left_children);
+    sort_children(&mut right_children);
+
+    let children = align(left_children, right_children);
+    let first_divergence = children.iter().position(|child| !child.is_same());
+    ExecutionDiff {
+        fields: diff_fields(&left, &right),
+        left,
+        right,
+        children,
+        first_divergence,
+    }
+}
+
+fn sort_children(children: &mut [Execution]) {
+    children.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.target_name.cmp(&b.target_name))
+    });
+}
+
+/// Fields that differ; ids, attempts and timestamps always differ and are skipped
+fn diff_fields(left: &Execution, right: &Execution) -> Vec<FieldDiff> {
+    let fields = [
+        (
+            "target_name",
+            JsonValue::from(left.target_name.as_str()),
+            JsonValue::from(right.target_name.as_str()),
+        ),
+        (
+            "status",
+            serde_json::to_value(&left.status).unwrap_or_default(),
+            serde_json::to_value(&right.status).unwrap_or_default(),
+        ),
+        ("inputs", left.inputs.clone(), right.inputs.clone()),
+        (
+            "output",
+            left.output.clone().unwrap_or_default(),
+            right.output.clone().unwrap_or_default(),
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter_map(|(field, l, r)| {
+            let changes = diff_values(&l, &r);
+            (!changes.is_empty()).then_some(FieldDiff { field, changes })
+        })
+        .collect()
+}
+
+/// Leaf-level differences between two JSON values
+pub fn diff_values(left: &JsonValue, right: &JsonValue) -> Vec<ValueChange> {
+    let mut changes = Vec::new();
+    collect_changes(String::new(), Some(left), Some(right), &mut changes);
+    changes
+}
+
+fn collect_changes(
+    path: String,
+    left: Option<&JsonValue>,
+    right: Option<&JsonValue>,
+    changes: &mut Vec<ValueChange>,
+) {
+    match (left, right) {
+        (Some(JsonValue::Object(l)), Some(JsonValue::Object(r))) => {
+            let mut keys: Vec<&String> = l.keys().chain(r.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                collect_changes(format!("{}/{}", path, key), l.get(key), r.get(key), changes);
+            }
+        }
+        (Some(JsonValue::Array(l)), Some(JsonValue::Array(r))) => {
+            for i in 0..l.len().max(r.len()) {
+                collect_changes(format!("{}/{}", path, i), l.get(i), r.get(i), changes);
+            }
+        }
+        (l, r) if l != r => changes.push(ValueChange {
+            path,
+            left: l.cloned(),
+            right: r.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+/// Align two child sequences on their longest common subsequence of targets
+fn align(left: Vec<Execution>, right: Vec<Execution>) -> Vec<ChildDiff> {
+    let (n, m) = (left.len(), right.len());
+    // lcs[i][j] = LCS length of left[i..] and right[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if left[i].target_name == right[j].target_name {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    let (mut i, mut j) = (0, 0);
+    let mut aligned = Vec::with_capacity(n.max(m));
+    loop {
+        match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) if l.target_name == r.target_name => {
+                let (l, r) = (left.next().unwrap(), right.next().unwrap());
+                aligned.push(ChildDiff::Matched {
+                    fields: diff_fields(&l, &r),
+                    left: Box::new(l),
+                    right: Box::new(r),
+                });
+                i += 1;
+                j += 1;
+            }
+            (Some(_), Some(_)) if lcs[i + 1][j] >= lcs[i][j + 1] => {
+                aligned.push(ChildDiff::OnlyLeft {
+                    execution: left.next().unwrap(),
+                });
+                i += 1;
+            }
+            (_, Some(_)) => {
+                aligned.push(ChildDiff::OnlyRight {
+                    execution: right.next().unwrap(),
+                });
+                j += 1;
+            }
+            (Some(_), None) => {
+                aligned.push(ChildDiff::OnlyLeft {
+                    execution: left.next().unwrap(),
+                });
+                i += 1;
+            }
+            (None, None) => break,
+        }
+    }
+    aligned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ExecutionStatus, ExecutionType};
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    fn execution(id: &str, target_name: &str, inputs: JsonValue, offset_secs: i64) -> Execution {
+        Execution {
+            id: id.to_string(),
+            exec_type: ExecutionType::Task,
+            target_name: target_name.to_string(),
+            queue: "default".to_string(),
+            namespace: "default".to_string(),
+            status: ExecutionStatus::Completed,
+            inputs,
+            output: None,
+            attempt: 0,
+            parent_workflow_id: None,
+            created_at: Utc::now() + Duration::seconds(offset_secs),
+            completed_at: None,
+        }
+    }
+
+    fn kinds(diff: &ExecutionDiff) -> Vec<String> {
+        diff.children
+            .iter()
+            .map(|child| match child {
+                ChildDiff::Matched { left, fields, .. } if fields.is_empty() => {
+                    format!("={}", left.target_name)
+                }
+                ChildDiff::Matched { left, .. } => format!("~{}", left.target_name),
+                ChildDiff::OnlyLeft { execution } => format!("-{}", execution.target_name),
+                ChildDiff::OnlyRight { execution } => format!("+{}", execution.target_name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_executions_have_no_divergence() {
+        let children = || vec![execution("c1", "lookup", json!({"id": 1}), 0)];
+        let diff = diff_executions(
+            execution("a", "refund", json!({}), 0),
+            children(),
+            execution("b", "refund", json!({}), 60),
+            children(),
+        );
+
+        assert!(diff.is_same());
+        assert_eq!(kinds(&diff), vec!["=lookup"]);
+    }
+
+    #[test]
+    fn test_children_align_around_extra_and_missing_tasks() {
+        let diff = diff_executions(
+            execution("a", "refund", json!({"order": 1}), 0),
+            vec![
+                execution("l1", "lookup", json!({"id": 1}), 1),
+                execution("l2", "notify", json!({}), 2),
+                execution("l3", "refund_card", json!({}), 3),
+            ],
+            execution("b", "refund", json!({"order": 2}), 0),
+            vec![
+                execution("r1", "lookup", json!({"id": 2}), 1),
+                execution("r2", "refund_card", json!({}), 2),
+                execution("r3", "audit", json!({}), 3),
+            ],
+        );
+
+        assert_eq!(
+            kinds(&diff),
+            vec!["~lookup", "-notify", "=refund_card", "+audit"]
+        );
+        assert_eq!(diff.first_divergence, Some(0));
+        assert_eq!(
+            diff.fields,
+            vec![FieldDiff {
+                field: "inputs",
+                changes: vec![ValueChange {
+                    path: "/order".to_string(),
+                    left: Some(json!(1)),
+                    right: Some(json!(2)),
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_values_reports_leaf_paths() {
+        assert_eq!(
+            diff_values(
+                &json!({"a": [1, 2], "b": {"c": true}}),
+                &json!({"a": [1], "b": {"c": true, "d": null}})
+            ),
+            vec![
+                ValueChange {
+                    path: "/a/1".to_string(),
+                    left: Some(json!(2)),
+                    right: None,
+                },
+                ValueChange {
+                    path: "/b/d".to_string(),
+                    left: None,
+                    right: Some(json!(null)),
+                },
+            ]
+        );
+        assert_eq!(
+            diff_values(&json!(1), &json!("1")),
+            vec![ValueChange {
+                path: String::new(),
+                left: Some(json!(1)),
+                right: Some(json!("1")),
+            }]
+        );
+    }
+}
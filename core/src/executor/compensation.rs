@@ -0,0 +1,69 @@
+//! Compensation of failed workflows (sagas)
+//!
+//! `Compensate.register(task_name, inputs)` records a task that undoes a
+//! step the workflow has taken. If the workflow then fails with an error it
+//! doesn't catch, the VM unwinds before it finishes: it runs the registered
+//! tasks one at a time, the last registered first, and then throws the
+//! error the workflow failed with.
+//!
+//! Each compensation is awaited like any other task. One that fails
+//! doesn't stop the rest, and its error is dropped. A workflow that
+//! returns, or is cancelled outright, runs none of its compensations; one
+//! cancelled child-first (see `worker::cancel`) unwinds as if it had
+//! failed with the `CANCELLED` error.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::outbox::ExecutionCreation;
+use super::types::{Awaitable, Control, Val};
+use super::vm::VM;
+use crate::types::ExecutionType;
+
+/// A task that undoes a step of the workflow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Compensation {
+    pub task_name: String,
+    pub inputs: HashMap<String, Val>,
+}
+
+/// Whether the workflow has failed and has compensations left to run
+pub(crate) fn needs_unwind(vm: &VM) -> bool {
+    vm.frames.is_empty()
+        && (vm.unwinding.is_some()
+            || (matches!(vm.control, Control::Throw(_)) && !vm.compensations.is_empty()))
+}
+
+/// Take the next step of unwinding a failed workflow
+///
+/// Starts the most recently registered compensation and suspends on it,
+/// or throws the workflow's error again once none are left.
+pub(crate) fn unwind(vm: &mut VM) {
+    if !needs_unwind(vm) {
+        return;
+    }
+    if let (None, Control::Throw(error)) = (&vm.unwinding, &vm.control) {
+        vm.unwinding = Some(error.clone());
+    }
+    // What the last compensation resumed with, failed or not
+    vm.resume_value = None;
+
+    match vm.compensations.pop() {
+        Some(compensation) => {
+            let execution_id = Uuid::new_v4().to_string();
+            vm.outbox.push_execution(ExecutionCreation::new(
+                execution_id.clone(),
+                compensation.task_name,
+                compensation.inputs,
+                ExecutionType::Task,
+            ));
+            vm.control = Control::Suspend(Awaitable::Execution(execution_id));
+        }
+        None => {
+            if let Some(error) = vm.unwinding.take() {
+                vm.control = Control::Throw(error);
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Step debugger for the VM
+//!
+//! `Debugger` drives a `VM` one statement at a time, stopping before each
+//! statement runs, at breakpoints set by line, and wherever the VM suspends
+//! on an await. While stopped, the frames, the variables in scope and the
+//! control state can be inspected, and the VM can be saved as the JSON state
+//! a worker would persist, to be restored later.
+//!
+//! The VM is deterministic, so the same inputs and await results stop at
+//! the same places every time. A recorded run is replayed by handing the VM
+//! the outside values the run read and settling its awaits with the values
+//! it was resumed with (see `replaying`). Lines are 1-based, as editors
+//! show them.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+use super::exec_loop::{is_running, step};
+use super::trace::frame_pc;
+use super::types::{Awaitable, Control, FrameKind, Val};
+use super::vm::VM;
+
+/// Why the debugger stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stop {
+    /// About to run the statement at `line`
+    Statement { line: usize },
+    /// About to run the statement at `line`, which has a breakpoint
+    Breakpoint { line: usize },
+    /// Waiting on an await; `Debugger::resume` settles it
+    Suspended(Awaitable),
+    /// The program returned, threw, or continued as new
+    Finished,
+}
+
+/// A frame on the VM's stack, innermost last
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameInfo {
+    /// Kind of statement, e.g. `Block`
+    pub kind: &'static str,
+    /// Phase the frame is in, with the statement or item index for blocks
+    /// and loops, e.g. `Execute@2`
+    pub pc: String,
+    pub line: usize,
+}
+
+/// Single-step debugger over a `VM`
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    vm: VM,
+    breakpoints: BTreeSet<usize>,
+    /// Names the VM starts with (stdlib, `Inputs`, `Context`), left out of
+    /// `locals`
+    globals: HashSet<String>,
+    steps: u64,
+    /// Values a replayed run was resumed with, not handed back yet
+    resumes: VecDeque<Val>,
+}
+
+impl Debugger {
+    pub fn new(vm: VM) -> Self {
+        let mut globals = std::collections::HashMap::new();
+        super::stdlib::inject_stdlib(&mut globals);
+        let mut globals: HashSet<String> = globals.into_keys().collect();
+        globals.extend(["Inputs".to_string(), "Context".to_string()]);
+        Self {
+            vm,
+            breakpoints: BTreeSet::new(),
+            globals,
+            steps: 0,
+            resumes: VecDeque::new(),
+        }
+    }
+
+    /// Replay a recorded run: the VM reads `reads` instead of outside values
+    /// like the clock, and `resume_recorded` settles its awaits with
+    /// `resumes`, in order
+    pub fn replaying(mut self, reads: Vec<Val>, resumes: Vec<Val>) -> Self {
+        self.vm.outbox.replayed = reads.into();
+        self.resumes = resumes.into();
+        self
+    }
+
+    /// Restore a VM saved by `save`
+    pub fn restore(state: JsonValue) -> Result<Self> {
+        let vm: VM = serde_json::from_value(state).context("Invalid VM state")?;
+        Ok(Self::new(vm))
+    }
+
+    /// The VM as the JSON state a worker persists
+    ///
+    /// Breakpoints are not part of it. A VM resumed but not stepped since
+    /// loses its resume value, as it would between worker runs.
+    pub fn save(&self) -> Result<JsonValue> {
+        serde_json::to_value(&self.vm).context("Failed to serialize VM state")
+    }
+
+    pub fn vm(&self) -> &VM {
+        &self.vm
+    }
+
+    /// VM steps taken so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Stop before any statement that starts on `line`
+    pub fn set_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    pub fn clear_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.remove(&line)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Run until the next statement is about to start
+    pub fn step(&mut self) -> Stop {
+        self.run(false)
+    }
+
+    /// Run until a breakpoint, a suspension or the end
+    pub fn continue_running(&mut self) -> Stop {
+        self.run(true)
+    }
+
+    /// Settle the await the VM is suspended on with `value`
+    ///
+    /// Returns false if the VM is not suspended.
+    pub fn resume(&mut self, value: Val) -> bool {
+        self.vm.resume(value)
+    }
+
+    /// Settle the await the VM is suspended on with the next value the
+    /// replayed run was resumed with, returning it
+    ///
+    /// Returns `None` if the VM is not suspended or no recorded value is
+    /// left, as where the recorded run is still waiting.
+    pub fn resume_recorded(&mut self) -> Option<Val> {
+        if !matches!(self.vm.control, Control::Suspend(_)) {
+            return None;
+        }
+        let value = self.resumes.pop_front()?;
+        self.vm.resume(value.clone());
+        Some(value)
+    }
+
+    /// Recorded resume values not handed back yet
+    pub fn recorded_resumes_left(&self) -> usize {
+        self.resumes.len()
+    }
+
+    /// Where the VM is stopped now, without stepping
+    pub fn stop(&self) -> Stop {
+        if let Control::Suspend(awaitable) = &self.vm.control {
+            return Stop::Suspended(awaitable.clone());
+        }
+        if !is_running(&self.vm) {
+            return Stop::Finished;
+        }
+        let line = self.current_line().unwrap_or_default();
+        if self.breakpoints.contains(&line) {
+            Stop::Breakpoint { line }
+        } else {
+            Stop::Statement { line }
+        }
+    }
+
+    /// Line of the innermost frame's statement
+    pub fn current_line(&self) -> Option<usize> {
+        self.vm
+            .frames
+            .last()
+            .map(|frame| frame.node.span().start_line + 1)
+    }
+
+    /// The frames on the stack, innermost last
+    pub fn frames(&self) -> Vec<FrameInfo> {
+        self.vm
+            .frames
+            .iter()
+            .map(|frame| {
+                let (kind, pc) = frame_pc(&frame.kind);
+                FrameInfo {
+                    kind,
+                    pc,
+                    line: frame.node.span().start_line + 1,
+                }
+            })
+            .collect()
+    }
+
+    /// Variables the workflow has declared that are in scope
+    pub fn locals(&self) -> BTreeMap<&str, &Val> {
+        self.vm
+            .env
+            .iter()
+            .filter(|(name, _)| !self.globals.contains(*name))
+            .map(|(name, val)| (name.as_str(), val))
+            .collect()
+    }
+
+    /// `Inputs`, `Context` and the standard library are globals
+    pub fn global(&self, name: &str) -> Option<&Val> {
+        self.vm.env.get(name)
+    }
+
+    pub fn control(&self) -> &Control {
+        &self.vm.control
+    }
+
+    fn run(&mut self, to_breakpoint: bool) -> Stop {
+        loop {
+            if !is_running(&self.vm) {
+                return self.stop();
+            }
+            let depth = self.vm.frames.len();
+            step(&mut self.vm);
+            self.steps += 1;
+
+            // A statement starts when its frame is pushed; blocks only
+            // hold statements
+            let started = self.vm.frames.len() > depth
+                && !matches!(
+                    self.vm.frames.last().map(|frame| &frame.kind),
+                    Some(FrameKind::Block { .. })
+                );
+            if !started || self.vm.control != Control::None {
+                continue;
+            }
+            match self.stop() {
+                Stop::Statement { .. } if to_breakpoint => continue,
+                stop => return stop,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{json_to_val_map, WorkflowContext};
+    use serde_json::json;
+
+    const PROGRAM: &str = r#"let total = 0
+for (let n of [1, 2]) {
+    total = total + n
+}
+let charge = await Task.run("charge", { total: total })
+return charge.id"#;
+
+    fn debugger() -> Debugger {
+        let workflow = crate::parser::parse_workflow(PROGRAM).unwrap();
+        let inputs = json_to_val_map(&json!({ "order": "o1" })).unwrap();
+        Debugger::new(VM::new(
+            workflow.body,
+            inputs,
+            WorkflowContext {
+                execution_id: "debug".to_string(),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_step_stops_before_each_statement() {
+        let mut debugger = debugger();
+        let mut lines = Vec::new();
+        while let Stop::Statement { line } = debugger.step() {
+            lines.push(line);
+        }
+        assert_eq!(lines, vec![1, 2, 3, 3, 5]);
+        assert!(matches!(
+            debugger.stop(),
+            Stop::Suspended(Awaitable::Execution(_))
+        ));
+        assert_eq!(debugger.locals()["total"], &Val::Num(3.0));
+        assert!(!debugger.locals().contains_key("Math"));
+        assert!(debugger.global("Inputs").is_some());
+    }
+
+    #[test]
+    fn test_continue_stops_at_breakpoints() {
+        let mut debugger = debugger();
+        debugger.set_breakpoint(3);
+        assert_eq!(debugger.continue_running(), Stop::Breakpoint { line: 3 });
+        assert_eq!(debugger.locals()["n"], &Val::Num(1.0));
+        let frames: Vec<_> = debugger.frames().iter().map(|f| f.kind).collect();
+        assert_eq!(frames, vec!["Block", "ForLoop", "Block", "Assign"]);
+
+        assert_eq!(debugger.continue_running(), Stop::Breakpoint { line: 3 });
+        assert_eq!(debugger.locals()["n"], &Val::Num(2.0));
+        assert!(debugger.clear_breakpoint(3));
+        assert!(matches!(debugger.continue_running(), Stop::Suspended(_)));
+    }
+
+    #[test]
+    fn test_saved_vm_resumes_to_the_end() {
+        let mut debugger = debugger();
+        debugger.continue_running();
+        let state = debugger.save().unwrap();
+
+        let mut restored = Debugger::restore(state).unwrap();
+        assert!(matches!(restored.stop(), Stop::Suspended(_)));
+        assert!(restored.resume(Val::Obj(
+            [("id".to_string(), Val::Str("ch_1".to_string()))].into()
+        )));
+        assert_eq!(restored.step(), Stop::Statement { line: 6 });
+        assert_eq!(restored.continue_running(), Stop::Finished);
+        assert_eq!(
+            *restored.control(),
+            Control::Return(Val::Str("ch_1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_replaying_reproduces_a_recorded_failure() {
+        let program = r#"let token = Math.random()
+let charge = await Task.run("charge", { token: token })
+if (charge.declined) {
+    throw Error("Declined at " + Date.now(), "DECLINED")
+}
+return charge.id"#;
+        let workflow = crate::parser::parse_workflow(program).unwrap();
+        let new_vm = || {
+            VM::new(
+                workflow.body.clone(),
+                json_to_val_map(&json!({})).unwrap(),
+                WorkflowContext {
+                    execution_id: "recorded".to_string(),
+                },
+            )
+        };
+        let declined = Val::Obj([("declined".to_string(), Val::Bool(true))].into());
+
+        // The run as it happened
+        let mut recorded = Debugger::new(new_vm());
+        assert!(matches!(recorded.continue_running(), Stop::Suspended(_)));
+        assert!(recorded.resume(declined.clone()));
+        assert_eq!(recorded.continue_running(), Stop::Finished);
+        let reads = recorded.vm().recorded.clone();
+        assert_eq!(reads.len(), 2);
+
+        let mut replayed = Debugger::new(new_vm()).replaying(reads.clone(), vec![declined.clone()]);
+        assert_eq!(replayed.resume_recorded(), None);
+        replayed.set_breakpoint(4);
+        assert!(matches!(replayed.continue_running(), Stop::Suspended(_)));
+        assert_eq!(replayed.locals()["token"], &reads[0]);
+        assert_eq!(replayed.resume_recorded(), Some(declined));
+        assert_eq!(replayed.recorded_resumes_left(), 0);
+        assert_eq!(replayed.continue_running(), Stop::Breakpoint { line: 4 });
+        assert_eq!(replayed.continue_running(), Stop::Finished);
+        assert!(matches!(replayed.control(), Control::Throw(_)));
+        assert_eq!(replayed.control(), recorded.control());
+        assert_eq!(replayed.resume_recorded(), None);
+    }
+}
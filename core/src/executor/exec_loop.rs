@@ -6,13 +6,16 @@
 //! ## Function Organization
 //! Functions are ordered by importance/call hierarchy:
 //! 1. run_until_done() - Top-level driver (calls step repeatedly)
-//! 2. step() - Main execution loop (dispatches to statement handlers)
+//! 2. run_for_steps() - Same, but stops after a step budget
+//! 3. step() - Main execution loop (dispatches to statement handlers)
 
+use super::compensation::{needs_unwind, unwind};
 use super::statements::{
     execute_assign, execute_block, execute_break, execute_continue, execute_declare, execute_expr,
-    execute_for_loop, execute_if, execute_return, execute_try, execute_while,
+    execute_for_loop, execute_if, execute_return, execute_throw, execute_try, execute_while,
 };
-use super::types::{Control, FrameKind, Stmt};
+use super::trace::StepStart;
+use super::types::{Awaitable, Control, FrameKind, Stmt};
 use super::vm::VM;
 
 /* ===================== Public API ===================== */
@@ -22,21 +25,51 @@ use super::vm::VM;
 /// This is the top-level driver that repeatedly calls step() until execution finishes.
 /// After completion, inspect `vm.control` for the final state and `vm.outbox` for side effects.
 pub fn run_until_done(vm: &mut VM) {
-    while !vm.frames.is_empty() && !matches!(vm.control, Control::Suspend(_)) {
+    while is_running(vm) {
         step(vm);
     }
 }
 
+/// Run the VM until it completes or suspends, taking at most `max_steps` steps
+///
+/// Returns the number of steps taken, or `None` if the budget ran out first.
+/// The VM is then left mid-program with `Control::None` and can be
+/// serialized and resumed later. The budget only stops the VM in normal
+/// flow: never while unwinding, or while a resume value (which is not
+/// serialized) is waiting to be consumed.
+pub fn run_for_steps(vm: &mut VM, max_steps: usize) -> Option<usize> {
+    let mut steps = 0;
+    while is_running(vm) {
+        if steps >= max_steps && vm.control == Control::None && vm.resume_value.is_none() {
+            return None;
+        }
+        step(vm);
+        steps += 1;
+    }
+    Some(steps)
+}
+
+/// Whether the VM has more to do before it completes, suspends or continues
+/// as new
+pub(crate) fn is_running(vm: &VM) -> bool {
+    (!vm.frames.is_empty() || needs_unwind(vm))
+        && !matches!(vm.control, Control::Suspend(_))
+        && vm.outbox.continue_as_new.is_none()
+}
+
 /// Execute one step of the VM
 ///
 /// This is the core interpreter loop. It:
 /// 1. Gets the top frame
 /// 2. Dispatches to the appropriate statement handler
 /// 3. Each handler manages its own control flow propagation
+///
+/// The step is recorded if the VM is tracing.
 pub fn step(vm: &mut VM) {
     // Get top frame (if any)
     let Some(frame_idx) = vm.frames.len().checked_sub(1) else {
-        // No frames left - nothing to do
+        // No frames left - a failed workflow runs its compensations
+        unwind(vm);
         return;
     };
 
@@ -46,12 +79,20 @@ pub fn step(vm: &mut VM) {
         (f.kind.clone(), f.node.clone())
     };
 
+    // Remember what the step started from, for the trace
+    let traced = vm
+        .trace
+        .is_some()
+        .then(|| StepStart::new(frame_idx + 1, &kind, &node, &vm.control));
+
     // Dispatch to statement handler
     match (kind, node) {
         (FrameKind::Return { phase }, Stmt::Return { value, .. }) => {
             execute_return(vm, phase, value)
         }
 
+        (FrameKind::Throw { phase }, Stmt::Throw { value, .. }) => execute_throw(vm, phase, value),
+
         (
             FrameKind::Block {
                 phase,
@@ -126,4 +167,19 @@ pub fn step(vm: &mut VM) {
         // Shouldn't happen - frame kind doesn't match node
         _ => panic!("Frame kind does not match statement node"),
     }
+
+    // Keep values read from outside the workflow with its state
+    vm.recorded.append(&mut vm.outbox.recorded);
+    vm.compensations.append(&mut vm.outbox.compensations);
+
+    // A handle awaited before resumes with its result without suspending
+    if let Control::Suspend(Awaitable::Execution(execution_id)) = &vm.control {
+        if let Some(result) = vm.settled.get(execution_id).cloned() {
+            vm.resume(result);
+        }
+    }
+
+    if let (Some(start), Some(trace)) = (traced, vm.trace.as_mut()) {
+        trace.record(start, &vm.control);
+    }
 }
@@ -63,6 +63,31 @@ pub fn eval_expr(
 
         Expr::LitNull { .. } => EvalResult::Value { v: Val::Null },
 
+        Expr::Template { quasis, exprs, .. } => {
+            // Interleave the text with each interpolated value (left to right)
+            let mut s = quasis[0].clone();
+            for (expr, quasi) in exprs.iter().zip(&quasis[1..]) {
+                match eval_expr(expr, env, resume_value, outbox) {
+                    EvalResult::Value { v } => s.push_str(&super::stdlib::to_string(&v)),
+                    EvalResult::Suspend { .. } => {
+                        // This should never happen - validator ensures no await in templates
+                        return EvalResult::Throw {
+                            error: Val::Error(ErrorInfo::new(
+                                errors::INTERNAL_ERROR,
+                                "Suspension during template literal evaluation (should be prevented by semantic validator)",
+                            )),
+                        };
+                    }
+                    EvalResult::Throw { error } => {
+                        // Propagate error from interpolation
+                        return EvalResult::Throw { error };
+                    }
+                }
+                s.push_str(quasi);
+            }
+            EvalResult::Value { v: Val::Str(s) }
+        }
+
         Expr::LitList { elements, .. } => {
             // Evaluate all elements (left to right)
             let mut vals = Vec::new();
@@ -183,6 +208,42 @@ pub fn eval_expr(
                                         bindings: vec![Val::List(items)],
                                     },
                                 },
+                                "push" => EvalResult::Value {
+                                    v: Val::Func {
+                                        func: super::stdlib::StdlibFunc::ArrayPush,
+                                        bindings: vec![Val::List(items)],
+                                    },
+                                },
+                                "slice" => EvalResult::Value {
+                                    v: Val::Func {
+                                        func: super::stdlib::StdlibFunc::ArraySlice,
+                                        bindings: vec![Val::List(items)],
+                                    },
+                                },
+                                "indexOf" => EvalResult::Value {
+                                    v: Val::Func {
+                                        func: super::stdlib::StdlibFunc::ArrayIndexOf,
+                                        bindings: vec![Val::List(items)],
+                                    },
+                                },
+                                "reverse" => EvalResult::Value {
+                                    v: Val::Func {
+                                        func: super::stdlib::StdlibFunc::ArrayReverse,
+                                        bindings: vec![Val::List(items)],
+                                    },
+                                },
+                                "sort" => EvalResult::Value {
+                                    v: Val::Func {
+                                        func: super::stdlib::StdlibFunc::ArraySort,
+                                        bindings: vec![Val::List(items)],
+                                    },
+                                },
+                                "flat" => EvalResult::Value {
+                                    v: Val::Func {
+                                        func: super::stdlib::StdlibFunc::ArrayFlat,
+                                        bindings: vec![Val::List(items)],
+                                    },
+                                },
                                 _ => EvalResult::Throw {
                                     error: Val::Error(ErrorInfo::new(
                                         errors::PROPERTY_NOT_FOUND,
@@ -191,6 +252,20 @@ pub fn eval_expr(
                                 },
                             }
                         }
+                        Val::Error(err) => match property.as_str() {
+                            "code" => EvalResult::Value {
+                                v: Val::Str(err.code),
+                            },
+                            "message" => EvalResult::Value {
+                                v: Val::Str(err.message),
+                            },
+                            _ => EvalResult::Throw {
+                                error: Val::Error(ErrorInfo::new(
+                                    errors::PROPERTY_NOT_FOUND,
+                                    format!("Property '{}' not found on error", property),
+                                )),
+                            },
+                        },
                         Val::Str(s) => {
                             // Handle string properties and methods
                             match property.as_str() {
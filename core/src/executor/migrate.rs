@@ -0,0 +1,360 @@
+//! Moving a suspended VM onto a new workflow definition
+//!
+//! A suspended VM carries the AST it was started with, so it keeps running
+//! the old definition until it finishes. When a breaking change can't wait
+//! for that, `migrate_vm` rebuilds the VM at an await in the new definition:
+//! the frame stack is recreated along the path to that statement, in-scope
+//! variables are carried over (optionally renamed or set), and the VM stays
+//! suspended on the same awaitable, resuming there as if it had always run
+//! the new code.
+//!
+//! Suspension points are named by the 1-based line of the statement holding
+//! the `await`. `for` loops on the way keep the iteration state of the old
+//! VM's loops, outermost first.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use super::json::json_to_val;
+use super::stdlib::inject_stdlib;
+use super::types::{
+    BlockPhase, Control, DeclareTarget, Expr, ForLoopPhase, Frame, FrameKind, Stmt, TryPhase,
+    WhilePhase,
+};
+use super::vm::{push_stmt, VM};
+use super::Outbox;
+
+/// Globals every VM gets from its execution rather than its code
+const RUNTIME_GLOBALS: [&str; 2] = ["Context", "Inputs"];
+
+/// Where each old suspension point goes in the new definition
+///
+/// ```toml
+/// [[points]]
+/// from_line = 12
+/// to_line = 15
+/// rename = { total = "amount" }
+/// set = { currency = "USD" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MigrationPlan {
+    #[serde(default)]
+    pub points: Vec<PointMapping>,
+}
+
+impl MigrationPlan {
+    /// The mapping for a VM suspended at `line` of the old definition
+    pub fn point(&self, line: usize) -> Option<&PointMapping> {
+        self.points.iter().find(|point| point.from_line == line)
+    }
+}
+
+/// How one old suspension point maps onto the new definition
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PointMapping {
+    /// Line of the awaiting statement in the old definition
+    pub from_line: usize,
+    /// Line of the awaiting statement in the new definition
+    pub to_line: usize,
+    /// New variable name -> old variable it takes its value from
+    #[serde(default)]
+    pub rename: HashMap<String, String>,
+    /// New variable name -> value, for variables the old code didn't have
+    #[serde(default)]
+    pub set: HashMap<String, JsonValue>,
+}
+
+/// Line of the statement a suspended VM is waiting in
+pub fn suspension_line(vm: &VM) -> Option<usize> {
+    if !matches!(vm.control, Control::Suspend(_)) {
+        return None;
+    }
+    vm.frames
+        .last()
+        .map(|frame| frame.node.span().start_line + 1)
+}
+
+/// Rebuild a suspended VM at `mapping.to_line` of `program`
+pub fn migrate_vm(old: &VM, program: &Stmt, mapping: &PointMapping) -> Result<VM> {
+    let Control::Suspend(awaitable) = &old.control else {
+        bail!("VM is not suspended");
+    };
+    let line = suspension_line(old);
+    if line != Some(mapping.from_line) {
+        bail!(
+            "VM is suspended at line {}, not line {}",
+            line.map_or("?".to_string(), |l| l.to_string()),
+            mapping.from_line
+        );
+    }
+
+    let mut path = Vec::new();
+    if !find_path(program, mapping.to_line, &mut path) {
+        bail!(
+            "No statement awaiting at line {} of the new definition",
+            mapping.to_line
+        );
+    }
+
+    let mut old_loops = old.frames.iter().filter_map(|frame| match &frame.kind {
+        FrameKind::ForLoop { items, idx, .. } => Some((items.clone(), *idx)),
+        _ => None,
+    });
+
+    let mut frames = Vec::with_capacity(path.len());
+    let mut in_scope = Vec::new();
+    for step in &path {
+        let kind = match step {
+            Step::Block { stmt, child } => {
+                let Stmt::Block { body, .. } = stmt else {
+                    unreachable!("block step on a non-block statement");
+                };
+                let mut declared_vars = Vec::new();
+                for (i, s) in body[..=*child].iter().enumerate() {
+                    let names = declared_names(s);
+                    // Names the awaiting statement declares get their value
+                    // when the await resumes
+                    if i < *child {
+                        in_scope.extend(names.iter().cloned());
+                    }
+                    declared_vars.extend(names);
+                }
+                FrameKind::Block {
+                    phase: BlockPhase::Execute,
+                    idx: child + 1,
+                    declared_vars,
+                }
+            }
+            Step::While(_) => FrameKind::While {
+                phase: WhilePhase::Eval,
+                label: None,
+            },
+            Step::ForLoop(stmt) => {
+                let Stmt::ForLoop { binding, .. } = stmt else {
+                    unreachable!("loop step on a non-loop statement");
+                };
+                let (items, idx) = old_loops.next().ok_or_else(|| {
+                    anyhow!(
+                        "Line {} is inside more for loops than line {}",
+                        mapping.to_line,
+                        mapping.from_line
+                    )
+                })?;
+                in_scope.push(binding.clone());
+                FrameKind::ForLoop {
+                    phase: ForLoopPhase::Iterate,
+                    items,
+                    idx,
+                }
+            }
+            Step::Try { stmt, in_catch } => {
+                let Stmt::Try { catch_var, .. } = stmt else {
+                    unreachable!("try step on a non-try statement");
+                };
+                if *in_catch {
+                    in_scope.push(catch_var.clone());
+                }
+                FrameKind::Try {
+                    phase: if *in_catch {
+                        TryPhase::CatchStarted
+                    } else {
+                        TryPhase::TryStarted
+                    },
+                    catch_var: catch_var.clone(),
+                }
+            }
+            Step::Leaf(_) => continue,
+        };
+        frames.push(Frame {
+            kind,
+            node: step.stmt().clone(),
+        });
+    }
+    if old_loops.next().is_some() {
+        bail!(
+            "Line {} is inside fewer for loops than line {}",
+            mapping.to_line,
+            mapping.from_line
+        );
+    }
+
+    let mut env = HashMap::new();
+    for name in RUNTIME_GLOBALS {
+        if let Some(value) = old.env.get(name) {
+            env.insert(name.to_string(), value.clone());
+        }
+    }
+    inject_stdlib(&mut env);
+    for name in in_scope {
+        let value = match mapping.set.get(&name) {
+            Some(value) => json_to_val(value)?,
+            None => {
+                let source = mapping.rename.get(&name).unwrap_or(&name);
+                old.env.get(source).cloned().ok_or_else(|| {
+                    anyhow!(
+                        "No value for variable '{}' at line {}; add it to rename or set",
+                        name,
+                        mapping.to_line
+                    )
+                })?
+            }
+        };
+        env.insert(name, value);
+    }
+
+    let mut vm = VM {
+        frames,
+        control: Control::Suspend(awaitable.clone()),
+        env,
+        recorded: old.recorded.clone(),
+        settled: old.settled.clone(),
+        compensations: old.compensations.clone(),
+        unwinding: None,
+        resume_value: None,
+        outbox: Outbox::new(),
+        trace: None,
+    };
+    if let Some(Step::Leaf(stmt)) = path.last() {
+        push_stmt(&mut vm, stmt);
+    }
+    Ok(vm)
+}
+
+/// One statement on the way from the program root to the awaiting statement
+enum Step<'a> {
+    /// Running `body[child]` of a block
+    Block {
+        stmt: &'a Stmt,
+        child: usize,
+    },
+    While(&'a Stmt),
+    ForLoop(&'a Stmt),
+    Try {
+        stmt: &'a Stmt,
+        in_catch: bool,
+    },
+    /// The awaiting statement
+    Leaf(&'a Stmt),
+}
+
+impl Step<'_> {
+    fn stmt(&self) -> &Stmt {
+        match self {
+            Step::Block { stmt, .. }
+            | Step::While(stmt)
+            | Step::ForLoop(stmt)
+            | Step::Try { stmt, .. }
+            | Step::Leaf(stmt) => stmt,
+        }
+    }
+}
+
+/// Collect the steps down to the awaiting statement starting on `line`
+fn find_path<'a>(stmt: &'a Stmt, line: usize, path: &mut Vec<Step<'a>>) -> bool {
+    match stmt {
+        Stmt::Block { body, .. } => {
+            for (child, s) in body.iter().enumerate() {
+                path.push(Step::Block { stmt, child });
+                if find_path(s, line, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+        // The If frame is replaced by its branch once the test is evaluated
+        Stmt::If { then_s, else_s, .. } => {
+            find_path(then_s, line, path)
+                || else_s.as_deref().is_some_and(|e| find_path(e, line, path))
+        }
+        Stmt::While { body, .. } => {
+            path.push(Step::While(stmt));
+            find_path(body, line, path) || {
+                path.pop();
+                false
+            }
+        }
+        Stmt::ForLoop { body, .. } => {
+            path.push(Step::ForLoop(stmt));
+            find_path(body, line, path) || {
+                path.pop();
+                false
+            }
+        }
+        Stmt::Try {
+            body, catch_body, ..
+        } => {
+            for (inner, in_catch) in [(body, false), (catch_body, true)] {
+                path.push(Step::Try { stmt, in_catch });
+                if find_path(inner, line, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+        Stmt::Declare { init: Some(e), .. } | Stmt::Expr { expr: e, .. }
+            if stmt.span().start_line + 1 == line && contains_await(e) =>
+        {
+            path.push(Step::Leaf(stmt));
+            true
+        }
+        Stmt::Return { value: Some(e), .. } | Stmt::Throw { value: e, .. }
+            if stmt.span().start_line + 1 == line && contains_await(e) =>
+        {
+            path.push(Step::Leaf(stmt));
+            true
+        }
+        // Attribute assignment can't hold an await (see `execute_assign`)
+        Stmt::Assign {
+            path: access,
+            value,
+            ..
+        } if stmt.span().start_line + 1 == line && access.is_empty() && contains_await(value) => {
+            path.push(Step::Leaf(stmt));
+            true
+        }
+        _ => false,
+    }
+}
+
+fn declared_names(stmt: &Stmt) -> Vec<String> {
+    match stmt {
+        Stmt::Declare { target, .. } => match target {
+            DeclareTarget::Simple { name, .. } => vec![name.clone()],
+            DeclareTarget::Destructure { names, .. } => names.clone(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn contains_await(expr: &Expr) -> bool {
+    match expr {
+        Expr::Await { .. } => true,
+        Expr::LitBool { .. }
+        | Expr::LitNum { .. }
+        | Expr::LitStr { .. }
+        | Expr::LitNull { .. }
+        | Expr::Ident { .. } => false,
+        Expr::Template { exprs, .. }
+        | Expr::LitList {
+            elements: exprs, ..
+        } => exprs.iter().any(contains_await),
+        Expr::LitObj { properties, .. } => properties.iter().any(|(_, _, e)| contains_await(e)),
+        Expr::Member { object, .. } => contains_await(object),
+        Expr::Call { callee, args, .. } => {
+            contains_await(callee) || args.iter().any(contains_await)
+        }
+        Expr::BinaryOp { left, right, .. } => contains_await(left) || contains_await(right),
+        Expr::Ternary {
+            condition,
+            consequent,
+            alternate,
+            ..
+        } => contains_await(condition) || contains_await(consequent) || contains_await(alternate),
+    }
+}
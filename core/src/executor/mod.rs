@@ -32,13 +32,18 @@
 //! - Suspend/Resume for async task execution
 //! - Standard library (Math, Task modules, arithmetic and comparison operators)
 
+pub mod compensation;
+pub mod debugger;
 pub mod errors;
 pub mod exec_loop;
 pub mod expressions;
 pub mod json;
+pub mod migrate;
 pub mod outbox;
+pub mod replay;
 pub mod statements;
 pub mod stdlib;
+pub mod trace;
 pub mod types;
 pub mod vm;
 
@@ -46,9 +51,12 @@ pub mod vm;
 mod tests;
 
 // Re-export commonly used items
-pub use exec_loop::{run_until_done, step};
+pub use compensation::Compensation;
+pub use debugger::{Debugger, FrameInfo, Stop};
+pub use exec_loop::{run_for_steps, run_until_done, step};
 pub use expressions::EvalResult;
 pub use json::{json_to_val, json_to_val_map, val_map_to_json, val_to_json};
-pub use outbox::{ExecutionCreation, Outbox, TimerSchedule};
+pub use outbox::{ExecutionCreation, ExecutionOptions, LogEntry, Outbox, TimerSchedule};
+pub use trace::{TraceStep, VmTrace};
 pub use types::{Awaitable, Control, ErrorInfo, Expr, Stmt, Val};
 pub use vm::{WorkflowContext, VM};
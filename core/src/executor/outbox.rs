@@ -4,10 +4,11 @@
 //! or timer scheduling) without actually performing them. The external orchestrator
 //! is responsible for processing the outbox after execution.
 
+use super::compensation::Compensation;
 use super::types::Val;
-use crate::types::ExecutionType;
+use crate::types::{ExecutionType, LogLevel};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 /// An execution creation side effect
 ///
@@ -27,6 +28,9 @@ pub struct ExecutionCreation {
 
     /// The type of execution (Task or Workflow)
     pub target_type: ExecutionType,
+
+    /// Execution policy passed to Task.run()
+    pub options: ExecutionOptions,
 }
 
 impl ExecutionCreation {
@@ -42,8 +46,36 @@ impl ExecutionCreation {
             target_name,
             inputs,
             target_type,
+            options: ExecutionOptions::default(),
         }
     }
+
+    /// Set the execution policy the orchestrator creates the execution with
+    pub fn with_options(mut self, options: ExecutionOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Execution policy from the options argument of Task.run()
+///
+/// Unset fields fall back to what the orchestrator would otherwise use: the
+/// workflow's queue and priority, and the `[[task_configs]]` retry limit and
+/// codes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionOptions {
+    /// Expire the execution if it is not claimed within this many seconds
+    pub timeout_seconds: Option<u64>,
+    /// Retries before a failure is final
+    pub max_retries: Option<u32>,
+    /// Error codes retried even when the host gave up
+    pub retry_on: Vec<String>,
+    /// Error codes that fail at once
+    pub give_up_on: Vec<String>,
+    /// Queue to run on instead of the workflow's
+    pub queue: Option<String>,
+    /// Higher is claimed first
+    pub priority: Option<i32>,
 }
 
 /// A timer scheduling side effect
@@ -89,6 +121,18 @@ impl SignalRequest {
     }
 }
 
+/// A log message written by the workflow
+///
+/// Added to the outbox by Log.info/warn/error(); the orchestrator stores it
+/// with the state it was written in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    pub message: String,
+    /// Structured data passed alongside the message
+    pub data: Option<Val>,
+}
+
 /// Outbox - collection of side effects
 #[derive(Debug, Clone, Default)]
 pub struct Outbox {
@@ -98,6 +142,20 @@ pub struct Outbox {
     pub timers: Vec<TimerSchedule>,
     /// Signal request side effects
     pub signals: Vec<SignalRequest>,
+    /// Log messages, in the order they were written
+    pub logs: Vec<LogEntry>,
+    /// Compensations registered by Compensate.register(). The VM moves
+    /// these into its state.
+    pub compensations: Vec<Compensation>,
+    /// Values read from outside the workflow (like the clock), in the order
+    /// they were handed to it. The VM moves these into its state.
+    pub recorded: Vec<Val>,
+    /// Values to hand back instead of reading outside the workflow again,
+    /// when replaying a run (see `replay`)
+    pub replayed: VecDeque<Val>,
+    /// Inputs for the fresh execution this workflow continues as, set by
+    /// Workflow.continueAsNew(). The VM stops once it is set.
+    pub continue_as_new: Option<HashMap<String, Val>>,
 }
 
 impl Outbox {
@@ -107,6 +165,11 @@ impl Outbox {
             executions: Vec::new(),
             timers: Vec::new(),
             signals: Vec::new(),
+            logs: Vec::new(),
+            compensations: Vec::new(),
+            recorded: Vec::new(),
+            replayed: VecDeque::new(),
+            continue_as_new: None,
         }
     }
 
@@ -130,6 +193,26 @@ impl Outbox {
         self.signals.push(signal);
     }
 
+    /// Add a log message
+    pub fn push_log(&mut self, log: LogEntry) {
+        self.logs.push(log);
+    }
+
+    /// Add a compensation
+    pub fn push_compensation(&mut self, compensation: Compensation) {
+        self.compensations.push(compensation);
+    }
+
+    /// Record a value read from outside the workflow
+    pub fn record(&mut self, value: Val) {
+        self.recorded.push(value);
+    }
+
+    /// The value the run being replayed read next, if any is left
+    pub fn next_replayed(&mut self) -> Option<Val> {
+        self.replayed.pop_front()
+    }
+
     /// Find a signal request by claim_id
     pub fn get_signal(&self, claim_id: &str) -> Option<&SignalRequest> {
         self.signals.iter().find(|s| s.claim_id == claim_id)
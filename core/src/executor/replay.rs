@@ -0,0 +1,231 @@
+//! Checking that a workflow replays the way it ran
+//!
+//! A workflow resumes from its saved VM, so nothing notices if the code it
+//! runs would no longer do what it did: the state just carries on from a
+//! point the code can't reach. `replay` re-executes a workflow from the
+//! beginning against what its run recorded - the children it started, the
+//! values it was resumed with and the values it read from outside - and
+//! reports the first place the new run does something else.
+//!
+//! The replay stops where the saved run did: at the await after its last
+//! resume. Timers, signals and logs are not compared; a change in them shows
+//! up as soon as it changes which children are started.
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use super::compensation::needs_unwind;
+use super::exec_loop::step;
+use super::json::{json_to_val, val_map_to_json};
+use super::types::ast::Span;
+use super::types::{Control, Stmt, Val};
+use super::vm::{WorkflowContext, VM};
+use crate::types::ExecutionType;
+
+/// What a workflow's run recorded, in order
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    /// Children the workflow started
+    pub calls: Vec<RecordedCall>,
+    /// Values the workflow was resumed with after each await
+    pub resumes: Vec<Val>,
+    /// Values the workflow read from outside it (see `VM::recorded`)
+    pub recorded: Vec<Val>,
+}
+
+/// A child execution a workflow started
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecordedCall {
+    #[serde(rename = "type")]
+    pub target_type: ExecutionType,
+    pub target_name: String,
+    /// Not known for a child that has since been deleted
+    pub inputs: Option<JsonValue>,
+}
+
+/// Where a replay stopped matching the recorded run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Divergence {
+    /// The child started at `position` (0-based) differs from the recorded
+    /// one, or one of them is missing
+    Call {
+        position: usize,
+        expected: Option<RecordedCall>,
+        actual: Option<RecordedCall>,
+        span: Option<Span>,
+    },
+    /// Read outside values (like `Date.now()`) a different number of times
+    Reads { expected: usize, actual: usize },
+    /// Returned or threw before reaching the await the run is saved at
+    Finished,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Divergence::Call {
+                position,
+                expected,
+                actual,
+                ..
+            } => {
+                let describe = |call: &RecordedCall| {
+                    format!("{} '{}'", call.target_type.as_str(), call.target_name)
+                };
+                let n = position + 1;
+                match (expected, actual) {
+                    (Some(expected), Some(actual)) if describe(expected) == describe(actual) => {
+                        write!(
+                            f,
+                            "Call #{} to {} has other inputs on replay",
+                            n,
+                            describe(actual)
+                        )
+                    }
+                    (Some(expected), Some(actual)) => write!(
+                        f,
+                        "Call #{} was to {} when recorded, but to {} on replay",
+                        n,
+                        describe(expected),
+                        describe(actual)
+                    ),
+                    (Some(expected), None) => {
+                        write!(
+                            f,
+                            "Call #{} to {} is not made on replay",
+                            n,
+                            describe(expected)
+                        )
+                    }
+                    (None, Some(actual)) => {
+                        write!(f, "Call #{} to {} was never recorded", n, describe(actual))
+                    }
+                    (None, None) => write!(f, "Call #{} differs on replay", n),
+                }
+            }
+            Divergence::Reads { expected, actual } => write!(
+                f,
+                "Read {} outside value(s) when recorded, but {} on replay",
+                expected, actual
+            ),
+            Divergence::Finished => {
+                f.write_str("Finished on replay before reaching the point it was saved at")
+            }
+        }
+    }
+}
+
+impl Divergence {
+    /// The error a workflow found to diverge fails with
+    pub fn to_error_json(&self) -> JsonValue {
+        serde_json::json!({
+            "type": "NonDeterministic",
+            "message": format!("Workflow is not deterministic: {}", self),
+            "divergence": self,
+        })
+    }
+}
+
+/// Replay `program` from the beginning against `history`
+///
+/// `at_await` says the run was saved suspended on an await, rather than
+/// yielded part way through a step budget. Only then must the replay end
+/// with exactly the recorded children and reads; a yielded run may have
+/// been saved before making some of the replay's. Fails if the replay takes
+/// more than `max_steps` steps, as it can't be checked then.
+pub fn replay(
+    program: &Stmt,
+    inputs: HashMap<String, Val>,
+    context: WorkflowContext,
+    history: &History,
+    at_await: bool,
+    max_steps: usize,
+) -> Result<Option<Divergence>> {
+    let mut vm = VM::new(program.clone(), inputs, context);
+    vm.outbox.replayed = history.recorded.iter().cloned().collect();
+    let mut resumes = history.resumes.iter();
+    let mut position = 0;
+    let mut steps = 0;
+
+    loop {
+        while (!vm.frames.is_empty() || needs_unwind(&vm))
+            && !matches!(vm.control, Control::Suspend(_))
+        {
+            if steps >= max_steps {
+                bail!("Replay ran out of steps after {}", steps);
+            }
+            let span = vm.frames.last().map(|frame| frame.node.span());
+            step(&mut vm);
+            steps += 1;
+
+            for creation in std::mem::take(&mut vm.outbox.executions) {
+                let expected = history.calls.get(position);
+                let matches = match expected {
+                    Some(expected) => {
+                        expected.target_type == creation.target_type
+                            && expected.target_name == creation.target_name
+                            && match &expected.inputs {
+                                // Compared as values, as numbers may come back
+                                // from the database written differently
+                                Some(inputs) => {
+                                    json_to_val(inputs)? == Val::Obj(creation.inputs.clone())
+                                }
+                                None => true,
+                            }
+                    }
+                    None => false,
+                };
+                let actual = RecordedCall {
+                    target_type: creation.target_type,
+                    target_name: creation.target_name,
+                    inputs: Some(val_map_to_json(&creation.inputs)?),
+                };
+                if !matches && (expected.is_some() || at_await) {
+                    return Ok(Some(Divergence::Call {
+                        position,
+                        expected: expected.cloned(),
+                        actual: Some(actual),
+                        span,
+                    }));
+                }
+                position += 1;
+            }
+        }
+        // The rest of the outbox was acted on when the run was recorded
+        vm.outbox.timers.clear();
+        vm.outbox.signals.clear();
+        vm.outbox.logs.clear();
+
+        if !matches!(vm.control, Control::Suspend(_)) {
+            if resumes.len() > 0 || at_await {
+                return Ok(Some(Divergence::Finished));
+            }
+            break;
+        }
+        match resumes.next() {
+            Some(value) => {
+                vm.resume(value.clone());
+            }
+            None => break,
+        }
+    }
+
+    if let Some(expected) = history.calls.get(position) {
+        return Ok(Some(Divergence::Call {
+            position,
+            expected: Some(expected.clone()),
+            actual: None,
+            span: None,
+        }));
+    }
+    if at_await && vm.recorded.len() != history.recorded.len() {
+        return Ok(Some(Divergence::Reads {
+            expected: history.recorded.len(),
+            actual: vm.recorded.len(),
+        }));
+    }
+    Ok(None)
+}
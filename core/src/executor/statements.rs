@@ -9,7 +9,7 @@ use super::stdlib::to_string;
 use super::types::{
     AssignPhase, BlockPhase, BreakPhase, ContinuePhase, Control, DeclarePhase, DeclareTarget, Expr,
     ExprPhase, ForLoopKind, ForLoopPhase, FrameKind, IfPhase, MemberAccess, ReturnPhase, Stmt,
-    TryPhase, Val, VarKind, WhilePhase,
+    ThrowPhase, TryPhase, Val, VarKind, WhilePhase,
 };
 use super::vm::{push_stmt, VM};
 
@@ -111,6 +111,31 @@ pub fn execute_return(vm: &mut VM, phase: ReturnPhase, value: Option<Expr>) {
     }
 }
 
+/// Execute Throw statement
+pub fn execute_throw(vm: &mut VM, phase: ThrowPhase, value: Expr) {
+    match phase {
+        ThrowPhase::Eval => {
+            // Evaluate the thrown value; an error while doing so is thrown instead
+            let val = match eval_expr(&value, &vm.env, &mut vm.resume_value, &mut vm.outbox) {
+                EvalResult::Value { v } => v,
+                EvalResult::Suspend { awaitable } => {
+                    // Expression suspended (await encountered)
+                    // DO NOT pop the frame - we need to preserve state for resumption
+                    vm.control = Control::Suspend(awaitable);
+                    return;
+                }
+                EvalResult::Throw { error } => error,
+            };
+
+            // Set control to Throw; enclosing try statements catch it
+            vm.control = Control::Throw(val);
+
+            // Pop this frame
+            vm.frames.pop();
+        }
+    }
+}
+
 /// Execute Try statement
 pub fn execute_try(
     vm: &mut VM,
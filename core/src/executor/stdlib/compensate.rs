@@ -0,0 +1,50 @@
+//! Compensate stdlib functions
+
+use crate::executor::compensation::Compensation;
+use crate::executor::errors::{self, ErrorInfo};
+use crate::executor::expressions::EvalResult;
+use crate::executor::outbox::Outbox;
+use crate::executor::types::Val;
+
+/// Compensate.register(task_name, inputs) - Undo a step if the workflow fails
+///
+/// Records a task to run if the workflow fails with an error it doesn't
+/// catch (see `compensation`). Nothing runs now. Returns null.
+pub fn register(args: &[Val], outbox: &mut Outbox) -> EvalResult {
+    if args.len() != 2 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 2 arguments, got {}", args.len()),
+            )),
+        };
+    }
+
+    let task_name = match &args[0] {
+        Val::Str(s) => s.clone(),
+        _ => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "First argument (task_name) must be a string",
+                )),
+            };
+        }
+    };
+
+    let inputs = match &args[1] {
+        Val::Obj(map) => map.clone(),
+        _ => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Second argument (inputs) must be an object",
+                )),
+            };
+        }
+    };
+
+    outbox.push_compensation(Compensation { task_name, inputs });
+
+    EvalResult::Value { v: Val::Null }
+}
@@ -0,0 +1,78 @@
+//! Date stdlib functions
+//!
+//! Reading the clock is not deterministic, so every read goes through the
+//! outbox and is recorded in the VM state along with the rest of the run.
+
+use crate::executor::errors::{self, ErrorInfo};
+use crate::executor::expressions::EvalResult;
+use crate::executor::outbox::Outbox;
+use crate::executor::types::Val;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Read the clock, as milliseconds since the Unix epoch
+fn read_clock(outbox: &mut Outbox) -> f64 {
+    let ms = match outbox.next_replayed() {
+        Some(Val::Num(ms)) => ms,
+        _ => Utc::now().timestamp_millis() as f64,
+    };
+    outbox.record(Val::Num(ms));
+    ms
+}
+
+/// Date.now() - Milliseconds since the Unix epoch
+pub fn now(args: &[Val], outbox: &mut Outbox) -> EvalResult {
+    // Validate argument count
+    if !args.is_empty() {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 0 arguments, got {}", args.len()),
+            )),
+        };
+    }
+
+    EvalResult::Value {
+        v: Val::Num(read_clock(outbox)),
+    }
+}
+
+/// Date.iso(ms?) - An ISO 8601 UTC timestamp, e.g. `2025-01-09T12:00:00.000Z`
+///
+/// Formats `ms` (milliseconds since the Unix epoch) if given, otherwise
+/// reads the clock like `Date.now()`.
+pub fn iso(args: &[Val], outbox: &mut Outbox) -> EvalResult {
+    // Validate argument count
+    if args.len() > 1 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected at most 1 argument, got {}", args.len()),
+            )),
+        };
+    }
+
+    let ms = match args.first() {
+        None => read_clock(outbox),
+        Some(Val::Num(ms)) => *ms,
+        Some(_) => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Argument (ms) must be a number",
+                )),
+            };
+        }
+    };
+
+    match DateTime::<Utc>::from_timestamp_millis(ms as i64) {
+        Some(time) => EvalResult::Value {
+            v: Val::Str(time.to_rfc3339_opts(SecondsFormat::Millis, true)),
+        },
+        None => EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_TYPE,
+                "Argument (ms) is out of range for a date",
+            )),
+        },
+    }
+}
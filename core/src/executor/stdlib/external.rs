@@ -0,0 +1,71 @@
+//! ExternalTask stdlib functions
+
+use crate::executor::errors::{self, ErrorInfo};
+use crate::executor::expressions::EvalResult;
+use crate::executor::outbox::{ExecutionCreation, Outbox};
+use crate::executor::types::{Awaitable, Val};
+use crate::types::ExecutionType;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// ExternalTask.create(name, inputs?) - Create a task completed from outside
+///
+/// Records an external execution in the outbox and returns
+/// `{ token, promise }`. Nothing is queued for workers; the promise resolves
+/// once an outside system passes the token to `complete_external_task`.
+pub fn create(args: &[Val], outbox: &mut Outbox) -> EvalResult {
+    if args.is_empty() || args.len() > 2 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 1 or 2 arguments, got {}", args.len()),
+            )),
+        };
+    }
+
+    let name = match &args[0] {
+        Val::Str(s) => s.clone(),
+        _ => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "First argument (name) must be a string",
+                )),
+            };
+        }
+    };
+
+    let inputs = match args.get(1) {
+        None => HashMap::new(),
+        Some(Val::Obj(map)) => map.clone(),
+        Some(_) => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Second argument (inputs) must be an object",
+                )),
+            };
+        }
+    };
+
+    // The execution ID doubles as the completion token
+    let token = Uuid::new_v4().to_string();
+
+    outbox.push_execution(ExecutionCreation::new(
+        token.clone(),
+        name,
+        inputs,
+        ExecutionType::External,
+    ));
+
+    let mut handle = HashMap::new();
+    handle.insert("token".to_string(), Val::Str(token.clone()));
+    handle.insert(
+        "promise".to_string(),
+        Val::Promise(Awaitable::Execution(token)),
+    );
+
+    EvalResult::Value {
+        v: Val::Obj(handle),
+    }
+}
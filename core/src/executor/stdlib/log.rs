@@ -0,0 +1,42 @@
+//! Log stdlib functions
+
+use crate::executor::errors::{self, ErrorInfo};
+use crate::executor::expressions::EvalResult;
+use crate::executor::outbox::{LogEntry, Outbox};
+use crate::executor::types::Val;
+use crate::types::LogLevel;
+
+/// Log.info/warn/error(message, data?) - Write to the execution's logs
+///
+/// The message is stored along with the state it was written in, so a
+/// resumed workflow never logs it twice. Returns null.
+pub fn write(level: LogLevel, args: &[Val], outbox: &mut Outbox) -> EvalResult {
+    if args.is_empty() || args.len() > 2 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 1 or 2 arguments, got {}", args.len()),
+            )),
+        };
+    }
+
+    let message = match &args[0] {
+        Val::Str(s) => s.clone(),
+        _ => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Argument (message) must be a string",
+                )),
+            };
+        }
+    };
+
+    outbox.push_log(LogEntry {
+        level,
+        message,
+        data: args.get(1).cloned(),
+    });
+
+    EvalResult::Value { v: Val::Null }
+}
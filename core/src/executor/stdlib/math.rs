@@ -2,7 +2,9 @@
 
 use crate::executor::errors::{self, ErrorInfo};
 use crate::executor::expressions::EvalResult;
+use crate::executor::outbox::Outbox;
 use crate::executor::types::Val;
+use uuid::Uuid;
 
 /// Math.floor(x) - Returns the largest integer less than or equal to x
 pub fn floor(args: &[Val]) -> EvalResult {
@@ -118,3 +120,141 @@ pub fn round(args: &[Val]) -> EvalResult {
         },
     }
 }
+
+/// Numeric arguments of a variadic Math function, at least one of them
+fn numbers(args: &[Val]) -> Result<Vec<f64>, EvalResult> {
+    if args.is_empty() {
+        return Err(EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                "Expected at least 1 argument, got 0",
+            )),
+        });
+    }
+    args.iter()
+        .map(|arg| match arg {
+            Val::Num(n) => Ok(*n),
+            _ => Err(EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Arguments must be numbers",
+                )),
+            }),
+        })
+        .collect()
+}
+
+/// Math.min(x, ...) - Returns the smallest of the arguments
+///
+/// Like JavaScript, the result is NaN if any argument is NaN.
+pub fn min(args: &[Val]) -> EvalResult {
+    match numbers(args) {
+        Ok(ns) => EvalResult::Value {
+            v: Val::Num(ns.into_iter().fold(f64::INFINITY, |a, b| {
+                if a.is_nan() || b.is_nan() {
+                    f64::NAN
+                } else {
+                    a.min(b)
+                }
+            })),
+        },
+        Err(thrown) => thrown,
+    }
+}
+
+/// Math.max(x, ...) - Returns the largest of the arguments
+///
+/// Like JavaScript, the result is NaN if any argument is NaN.
+pub fn max(args: &[Val]) -> EvalResult {
+    match numbers(args) {
+        Ok(ns) => EvalResult::Value {
+            v: Val::Num(ns.into_iter().fold(f64::NEG_INFINITY, |a, b| {
+                if a.is_nan() || b.is_nan() {
+                    f64::NAN
+                } else {
+                    a.max(b)
+                }
+            })),
+        },
+        Err(thrown) => thrown,
+    }
+}
+
+/// Math.pow(base, exponent) - Returns base raised to the power of exponent
+pub fn pow(args: &[Val]) -> EvalResult {
+    // Validate argument count
+    if args.len() != 2 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 2 arguments, got {}", args.len()),
+            )),
+        };
+    }
+
+    // Validate argument types
+    match (&args[0], &args[1]) {
+        (Val::Num(base), Val::Num(exponent)) => EvalResult::Value {
+            v: Val::Num(base.powf(*exponent)),
+        },
+        _ => EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_TYPE,
+                "Arguments must be numbers",
+            )),
+        },
+    }
+}
+
+/// Math.sqrt(x) - Returns the square root of x, or NaN if x is negative
+pub fn sqrt(args: &[Val]) -> EvalResult {
+    // Validate argument count
+    if args.len() != 1 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 1 argument, got {}", args.len()),
+            )),
+        };
+    }
+
+    // Validate argument type
+    match &args[0] {
+        Val::Num(n) => EvalResult::Value {
+            v: Val::Num(n.sqrt()),
+        },
+        _ => EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_TYPE,
+                "Argument must be a number",
+            )),
+        },
+    }
+}
+
+/// Math.random() - Returns a number in [0, 1)
+///
+/// Draws are not deterministic, so each one is recorded through the outbox
+/// and kept in the VM state like a clock read.
+pub fn random(args: &[Val], outbox: &mut Outbox) -> EvalResult {
+    // Validate argument count
+    if !args.is_empty() {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 0 arguments, got {}", args.len()),
+            )),
+        };
+    }
+
+    let n = match outbox.next_replayed() {
+        Some(Val::Num(n)) => n,
+        _ => {
+            // The low 53 bits of a v4 UUID are random, and fill an f64's mantissa
+            let bits = Uuid::new_v4().as_u64_pair().1 & ((1 << 53) - 1);
+            bits as f64 / (1u64 << 53) as f64
+        }
+    };
+    outbox.record(Val::Num(n));
+    EvalResult::Value { v: Val::Num(n) }
+}
@@ -2,6 +2,10 @@
 //!
 //! This module contains all stdlib function implementations organized by category.
 
+pub mod compensate;
+pub mod date;
+pub mod external;
+pub mod log;
 pub mod math;
 pub mod signal;
 pub mod task;
@@ -11,6 +15,7 @@ pub mod workflow;
 use super::expressions::EvalResult;
 use super::outbox::Outbox;
 use super::types::Val;
+use crate::types::LogLevel;
 use serde::{Deserialize, Serialize};
 
 /* ===================== Standard Library Function Types ===================== */
@@ -26,10 +31,17 @@ pub enum StdlibFunc {
     MathCeil,
     MathAbs,
     MathRound,
+    MathMin,
+    MathMax,
+    MathPow,
+    MathSqrt,
+    MathRandom,
     // Task functions
     TaskRun,
+    TaskStream,
     // Workflow functions
     WorkflowRun,
+    WorkflowContinueAsNew,
     // Promise functions
     PromiseAll,
     PromiseAny,
@@ -38,8 +50,19 @@ pub enum StdlibFunc {
     PromiseRaceKv,
     // Time functions
     TimeDelay,
+    // Date functions
+    DateNow,
+    DateIso,
     // Signal functions
     SignalNext,
+    // External task functions
+    ExternalTaskCreate,
+    // Compensation functions
+    CompensateRegister,
+    // Log functions
+    LogInfo,
+    LogWarn,
+    LogError,
     // Arithmetic operators
     Add,
     Sub,
@@ -56,9 +79,17 @@ pub enum StdlibFunc {
     And,
     Or,
     Not,
+    // Error constructor
+    ErrorNew,
     // Array methods
     ArrayConcat,
     ArrayIncludes,
+    ArrayPush,
+    ArraySlice,
+    ArrayIndexOf,
+    ArrayReverse,
+    ArraySort,
+    ArrayFlat,
     // String methods
     StringIncludes,
 }
@@ -76,10 +107,19 @@ pub fn call_stdlib_func(func: &StdlibFunc, args: &[Val], outbox: &mut Outbox) ->
         StdlibFunc::MathCeil => math::ceil(args),
         StdlibFunc::MathAbs => math::abs(args),
         StdlibFunc::MathRound => math::round(args),
+        StdlibFunc::MathMin => math::min(args),
+        StdlibFunc::MathMax => math::max(args),
+        StdlibFunc::MathPow => math::pow(args),
+        StdlibFunc::MathSqrt => math::sqrt(args),
+        // Random draws are recorded - outbox required
+        StdlibFunc::MathRandom => math::random(args, outbox),
         // Task functions have side effects - outbox required
         StdlibFunc::TaskRun => task::run(args, outbox),
+        // Reading partial results is pure
+        StdlibFunc::TaskStream => task::stream(args),
         // Workflow functions have side effects - outbox required
         StdlibFunc::WorkflowRun => workflow::run(args, outbox),
+        StdlibFunc::WorkflowContinueAsNew => workflow::continue_as_new(args, outbox),
         // Promise functions (pure - no outbox needed)
         StdlibFunc::PromiseAll => task::all(args),
         StdlibFunc::PromiseAny => task::any(args),
@@ -88,8 +128,19 @@ pub fn call_stdlib_func(func: &StdlibFunc, args: &[Val], outbox: &mut Outbox) ->
         StdlibFunc::PromiseRaceKv => task::race_kv(args),
         // Time functions have side effects - outbox required
         StdlibFunc::TimeDelay => timer::delay(args, outbox),
+        // Clock reads are recorded - outbox required
+        StdlibFunc::DateNow => date::now(args, outbox),
+        StdlibFunc::DateIso => date::iso(args, outbox),
         // Signal functions have side effects - outbox required
         StdlibFunc::SignalNext => signal::next(args, outbox),
+        // External task functions have side effects - outbox required
+        StdlibFunc::ExternalTaskCreate => external::create(args, outbox),
+        // Compensations are kept with the state - outbox required
+        StdlibFunc::CompensateRegister => compensate::register(args, outbox),
+        // Log messages are stored with the state - outbox required
+        StdlibFunc::LogInfo => log::write(LogLevel::Info, args, outbox),
+        StdlibFunc::LogWarn => log::write(LogLevel::Warn, args, outbox),
+        StdlibFunc::LogError => log::write(LogLevel::Error, args, outbox),
         // Arithmetic operators
         StdlibFunc::Add => add(args),
         StdlibFunc::Sub => sub(args),
@@ -106,9 +157,17 @@ pub fn call_stdlib_func(func: &StdlibFunc, args: &[Val], outbox: &mut Outbox) ->
         StdlibFunc::And => and(args),
         StdlibFunc::Or => or(args),
         StdlibFunc::Not => not(args),
+        // Error constructor
+        StdlibFunc::ErrorNew => error_new(args),
         // Array methods
         StdlibFunc::ArrayConcat => array_concat(args),
         StdlibFunc::ArrayIncludes => array_includes(args),
+        StdlibFunc::ArrayPush => array_push(args),
+        StdlibFunc::ArraySlice => array_slice(args),
+        StdlibFunc::ArrayIndexOf => array_index_of(args),
+        StdlibFunc::ArrayReverse => array_reverse(args),
+        StdlibFunc::ArraySort => array_sort(args),
+        StdlibFunc::ArrayFlat => array_flat(args),
         // String methods
         StdlibFunc::StringIncludes => string_includes(args),
     }
@@ -343,6 +402,49 @@ fn not(args: &[Val]) -> EvalResult {
     EvalResult::Value { v: Val::Bool(!val) }
 }
 
+/* ===================== Error Constructor ===================== */
+
+/// Code of errors created by `Error(message)` without an explicit code
+const DEFAULT_ERROR_CODE: &str = "Error";
+
+/// Error(message, code?) - create an error value to `throw`
+///
+/// Thrown out of a workflow, the error fails the execution with
+/// `{ code, message }` as its error payload. `code` defaults to `"Error"`.
+///
+/// Args: [message, code?]
+fn error_new(args: &[Val]) -> EvalResult {
+    if args.is_empty() || args.len() > 2 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                "TypeError",
+                "Error expects a message and an optional code",
+            )),
+        };
+    }
+    let Val::Str(message) = &args[0] else {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                "TypeError",
+                "Error message must be a string",
+            )),
+        };
+    };
+    let code = match args.get(1) {
+        None => DEFAULT_ERROR_CODE,
+        Some(Val::Str(code)) => code.as_str(),
+        Some(_) => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new("TypeError", "Error code must be a string")),
+            };
+        }
+    };
+
+    EvalResult::Value {
+        v: Val::Error(ErrorInfo::new(code, message.as_str())),
+    }
+}
+
 /* ===================== Array Methods ===================== */
 
 /// Array.concat - returns a new array with elements from both arrays
@@ -435,6 +537,191 @@ fn array_includes(args: &[Val]) -> EvalResult {
     }
 }
 
+/// Helper: The receiver array of an array method
+fn list_receiver<'a>(args: &'a [Val], method: &str) -> Result<&'a Vec<Val>, EvalResult> {
+    match args.first() {
+        Some(Val::List(items)) => Ok(items),
+        _ => Err(EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                "TypeError",
+                format!("{} can only be called on arrays", method),
+            )),
+        }),
+    }
+}
+
+/// Helper: Resolve a possibly negative index argument against a length
+///
+/// Negative indexes count back from the end; the result is clamped to
+/// `0..=len`.
+fn relative_index(arg: Option<&Val>, len: usize, default: usize) -> Result<usize, EvalResult> {
+    match arg {
+        None | Some(Val::Null) => Ok(default),
+        Some(Val::Num(n)) => {
+            let n = n.trunc();
+            let index = if n < 0.0 { len as f64 + n } else { n };
+            Ok(index.clamp(0.0, len as f64) as usize)
+        }
+        Some(_) => Err(EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new("TypeError", "index must be a number")),
+        }),
+    }
+}
+
+/// Array.push - returns a new array with the values appended
+///
+/// Unlike JavaScript, values are immutable, so the receiver is left as is
+/// and the extended array is returned: `items = items.push(4)`. A `push`
+/// whose result is dropped is rejected by the `discarded-push` rule.
+///
+/// Args: [receiver_array, ...values_to_push]
+fn array_push(args: &[Val]) -> EvalResult {
+    let items = match list_receiver(args, "push") {
+        Ok(items) => items,
+        Err(thrown) => return thrown,
+    };
+    let mut result = items.clone();
+    result.extend(args[1..].iter().cloned());
+    EvalResult::Value {
+        v: Val::List(result),
+    }
+}
+
+/// Array.slice - returns the elements from `start` up to, not including, `end`
+///
+/// JavaScript behavior:
+/// - Both indexes are optional and may be negative to count from the end
+/// - Out-of-range indexes are clamped, so an empty range gives `[]`
+///
+/// Args: [receiver_array, start?, end?]
+fn array_slice(args: &[Val]) -> EvalResult {
+    let items = match list_receiver(args, "slice") {
+        Ok(items) => items,
+        Err(thrown) => return thrown,
+    };
+    let bounds = relative_index(args.get(1), items.len(), 0).and_then(|start| {
+        relative_index(args.get(2), items.len(), items.len()).map(|end| (start, end))
+    });
+    match bounds {
+        Ok((start, end)) if start < end => EvalResult::Value {
+            v: Val::List(items[start..end].to_vec()),
+        },
+        Ok(_) => EvalResult::Value {
+            v: Val::List(vec![]),
+        },
+        Err(thrown) => thrown,
+    }
+}
+
+/// Array.indexOf - index of the first matching element, or -1
+///
+/// Uses the same value equality as `includes`.
+///
+/// Args: [receiver_array, search_element]
+fn array_index_of(args: &[Val]) -> EvalResult {
+    let items = match list_receiver(args, "indexOf") {
+        Ok(items) => items,
+        Err(thrown) => return thrown,
+    };
+    let Some(search_element) = args.get(1) else {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new("TypeError", "indexOf expects 1 argument")),
+        };
+    };
+    let index = items
+        .iter()
+        .position(|item| values_equal(item, search_element))
+        .map_or(-1.0, |i| i as f64);
+    EvalResult::Value { v: Val::Num(index) }
+}
+
+/// Array.reverse - returns a new array with the elements in reverse order
+///
+/// Args: [receiver_array]
+fn array_reverse(args: &[Val]) -> EvalResult {
+    let items = match list_receiver(args, "reverse") {
+        Ok(items) => items,
+        Err(thrown) => return thrown,
+    };
+    EvalResult::Value {
+        v: Val::List(items.iter().rev().cloned().collect()),
+    }
+}
+
+/// Array.sort - returns a new array sorted in ascending order
+///
+/// Numbers sort numerically and strings by code point. Comparator
+/// functions are not supported, and arrays mixing other types throw,
+/// rather than following JavaScript's sort-as-strings default.
+///
+/// Args: [receiver_array]
+fn array_sort(args: &[Val]) -> EvalResult {
+    let items = match list_receiver(args, "sort") {
+        Ok(items) => items,
+        Err(thrown) => return thrown,
+    };
+    let mut result = items.clone();
+    if result.iter().all(|item| matches!(item, Val::Num(_))) {
+        result.sort_by(|a, b| match (a, b) {
+            (Val::Num(a), Val::Num(b)) => a.total_cmp(b),
+            _ => std::cmp::Ordering::Equal,
+        });
+    } else if result.iter().all(|item| matches!(item, Val::Str(_))) {
+        result.sort_by(|a, b| match (a, b) {
+            (Val::Str(a), Val::Str(b)) => a.cmp(b),
+            _ => std::cmp::Ordering::Equal,
+        });
+    } else {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                "TypeError",
+                "sort expects an array of only numbers or only strings",
+            )),
+        };
+    }
+    EvalResult::Value {
+        v: Val::List(result),
+    }
+}
+
+/// Array.flat - returns a new array with nested arrays flattened
+///
+/// JavaScript behavior:
+/// - Flattens one level by default: [1, [2, [3]]].flat() => [1, 2, [3]]
+/// - `depth` sets how many levels to flatten
+///
+/// Args: [receiver_array, depth?]
+fn array_flat(args: &[Val]) -> EvalResult {
+    let items = match list_receiver(args, "flat") {
+        Ok(items) => items,
+        Err(thrown) => return thrown,
+    };
+    let depth = match args.get(1) {
+        None | Some(Val::Null) => 1.0,
+        Some(Val::Num(n)) => n.trunc(),
+        Some(_) => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new("TypeError", "flat depth must be a number")),
+            };
+        }
+    };
+
+    fn flatten_into(result: &mut Vec<Val>, items: &[Val], depth: f64) {
+        for item in items {
+            match item {
+                Val::List(nested) if depth >= 1.0 => flatten_into(result, nested, depth - 1.0),
+                other => result.push(other.clone()),
+            }
+        }
+    }
+
+    let mut result = Vec::with_capacity(items.len());
+    flatten_into(&mut result, items, depth);
+    EvalResult::Value {
+        v: Val::List(result),
+    }
+}
+
 /// Helper: Check if two values are equal (for includes)
 fn values_equal(a: &Val, b: &Val) -> bool {
     match (a, b) {
@@ -553,6 +840,9 @@ pub fn to_string(val: &Val) -> String {
             super::types::Awaitable::Signal { name, .. } => {
                 format!("[Promise Signal({})]", name)
             }
+            super::types::Awaitable::Stream { execution_id, from } => {
+                format!("[Promise Stream({}, {})]", execution_id, from)
+            }
         },
         Val::Error(err) => format!("[Error: {}]", err.message),
         Val::Func { .. } => "[Function]".to_string(),
@@ -580,14 +870,26 @@ pub fn inject_stdlib(env: &mut std::collections::HashMap<String, Val>) {
     math_obj.insert("ceil".to_string(), func(StdlibFunc::MathCeil));
     math_obj.insert("abs".to_string(), func(StdlibFunc::MathAbs));
     math_obj.insert("round".to_string(), func(StdlibFunc::MathRound));
+    math_obj.insert("min".to_string(), func(StdlibFunc::MathMin));
+    math_obj.insert("max".to_string(), func(StdlibFunc::MathMax));
+    math_obj.insert("pow".to_string(), func(StdlibFunc::MathPow));
+    math_obj.insert("sqrt".to_string(), func(StdlibFunc::MathSqrt));
+    math_obj.insert("random".to_string(), func(StdlibFunc::MathRandom));
 
     // Create Task object with methods
     let mut task_obj = std::collections::HashMap::new();
     task_obj.insert("run".to_string(), func(StdlibFunc::TaskRun));
+    task_obj.insert("stream".to_string(), func(StdlibFunc::TaskStream));
+    // Task.all is Promise.all under the name task-centric workflows reach for
+    task_obj.insert("all".to_string(), func(StdlibFunc::PromiseAll));
 
     // Create Workflow object with methods
     let mut workflow_obj = std::collections::HashMap::new();
     workflow_obj.insert("run".to_string(), func(StdlibFunc::WorkflowRun));
+    workflow_obj.insert(
+        "continueAsNew".to_string(),
+        func(StdlibFunc::WorkflowContinueAsNew),
+    );
 
     // Create Promise object with methods
     let mut promise_obj = std::collections::HashMap::new();
@@ -601,17 +903,43 @@ pub fn inject_stdlib(env: &mut std::collections::HashMap<String, Val>) {
     let mut timer_obj = std::collections::HashMap::new();
     timer_obj.insert("delay".to_string(), func(StdlibFunc::TimeDelay));
 
+    // Create Date object with methods
+    let mut date_obj = std::collections::HashMap::new();
+    date_obj.insert("now".to_string(), func(StdlibFunc::DateNow));
+    date_obj.insert("iso".to_string(), func(StdlibFunc::DateIso));
+
     // Create Signal object with methods
     let mut signal_obj = std::collections::HashMap::new();
     signal_obj.insert("next".to_string(), func(StdlibFunc::SignalNext));
 
+    // Create ExternalTask object with methods
+    let mut external_task_obj = std::collections::HashMap::new();
+    external_task_obj.insert("create".to_string(), func(StdlibFunc::ExternalTaskCreate));
+
+    // Create Compensate object with methods
+    let mut compensate_obj = std::collections::HashMap::new();
+    compensate_obj.insert("register".to_string(), func(StdlibFunc::CompensateRegister));
+
+    // Create Log object with methods
+    let mut log_obj = std::collections::HashMap::new();
+    log_obj.insert("info".to_string(), func(StdlibFunc::LogInfo));
+    log_obj.insert("warn".to_string(), func(StdlibFunc::LogWarn));
+    log_obj.insert("error".to_string(), func(StdlibFunc::LogError));
+
     // Add stdlib objects to environment
     env.insert("Math".to_string(), Val::Obj(math_obj));
     env.insert("Task".to_string(), Val::Obj(task_obj));
     env.insert("Workflow".to_string(), Val::Obj(workflow_obj));
     env.insert("Promise".to_string(), Val::Obj(promise_obj));
     env.insert("Timer".to_string(), Val::Obj(timer_obj));
+    env.insert("Date".to_string(), Val::Obj(date_obj));
     env.insert("Signal".to_string(), Val::Obj(signal_obj));
+    env.insert("ExternalTask".to_string(), Val::Obj(external_task_obj));
+    env.insert("Compensate".to_string(), Val::Obj(compensate_obj));
+    env.insert("Log".to_string(), Val::Obj(log_obj));
+
+    // Add the Error constructor
+    env.insert("Error".to_string(), func(StdlibFunc::ErrorNew));
 
     // Add global operator functions
     env.insert("add".to_string(), func(StdlibFunc::Add));
@@ -2,23 +2,25 @@
 
 use crate::executor::errors::{self, ErrorInfo};
 use crate::executor::expressions::EvalResult;
-use crate::executor::outbox::{ExecutionCreation, Outbox};
+use crate::executor::outbox::{ExecutionCreation, ExecutionOptions, Outbox};
 use crate::executor::types::{Awaitable, Val};
 use crate::types::ExecutionType;
 use std::collections::HashMap;
 use uuid::Uuid;
 
-/// Task.run(task_name, inputs) - Create a new task
+/// Task.run(task_name, inputs, options?) - Create a new task
 ///
 /// Generates a UUID for the task, records a side effect in the outbox,
-/// and returns a Promise value wrapping the task.
+/// and returns a Promise value wrapping the task. `options` may set
+/// `timeout_seconds`, `max_retries`, `retry_on`, `give_up_on`, `queue` and
+/// `priority` for the task.
 pub fn run(args: &[Val], outbox: &mut Outbox) -> EvalResult {
     // Validate argument count
-    if args.len() != 2 {
+    if args.len() != 2 && args.len() != 3 {
         return EvalResult::Throw {
             error: Val::Error(ErrorInfo::new(
                 errors::WRONG_ARG_COUNT,
-                format!("Expected 2 arguments, got {}", args.len()),
+                format!("Expected 2 or 3 arguments, got {}", args.len()),
             )),
         };
     }
@@ -49,16 +51,35 @@ pub fn run(args: &[Val], outbox: &mut Outbox) -> EvalResult {
         }
     };
 
+    // Extract options (optional third argument, must be object)
+    let options = match args.get(2) {
+        None | Some(Val::Null) => ExecutionOptions::default(),
+        Some(Val::Obj(map)) => match parse_options(map) {
+            Ok(options) => options,
+            Err(message) => {
+                return EvalResult::Throw {
+                    error: Val::Error(ErrorInfo::new(errors::WRONG_ARG_TYPE, message)),
+                };
+            }
+        },
+        Some(_) => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Third argument (options) must be an object",
+                )),
+            };
+        }
+    };
+
     // Generate UUID for the task
     let execution_id = Uuid::new_v4().to_string();
 
     // Record side effect in outbox
-    outbox.push_execution(ExecutionCreation::new(
-        execution_id.clone(),
-        task_name,
-        inputs,
-        ExecutionType::Task,
-    ));
+    outbox.push_execution(
+        ExecutionCreation::new(execution_id.clone(), task_name, inputs, ExecutionType::Task)
+            .with_options(options),
+    );
 
     // Return Promise value wrapping the task
     EvalResult::Value {
@@ -66,6 +87,114 @@ pub fn run(args: &[Val], outbox: &mut Outbox) -> EvalResult {
     }
 }
 
+/// Parse the options object of Task.run, ignoring fields set to null
+fn parse_options(map: &HashMap<String, Val>) -> Result<ExecutionOptions, String> {
+    let mut options = ExecutionOptions::default();
+    for (key, value) in map {
+        if matches!(value, Val::Null) {
+            continue;
+        }
+        match key.as_str() {
+            "timeout_seconds" => {
+                options.timeout_seconds = Some(non_negative_integer(key, value)?);
+            }
+            "max_retries" => {
+                let max_retries = non_negative_integer(key, value)?;
+                options.max_retries =
+                    Some(u32::try_from(max_retries).map_err(|_| format!("{} is too large", key))?);
+            }
+            "retry_on" => options.retry_on = error_codes(key, value)?,
+            "give_up_on" => options.give_up_on = error_codes(key, value)?,
+            "queue" => match value {
+                Val::Str(queue) if !queue.is_empty() => options.queue = Some(queue.clone()),
+                _ => return Err(format!("{} must be a non-empty string", key)),
+            },
+            "priority" => match value {
+                Val::Num(n)
+                    if n.fract() == 0.0 && *n >= i32::MIN as f64 && *n <= i32::MAX as f64 =>
+                {
+                    options.priority = Some(*n as i32);
+                }
+                _ => return Err(format!("{} must be an integer", key)),
+            },
+            _ => {
+                return Err(format!(
+                    "Unknown option '{}'; expected timeout_seconds, max_retries, retry_on, give_up_on, queue or priority",
+                    key
+                ))
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn non_negative_integer(key: &str, value: &Val) -> Result<u64, String> {
+    match value {
+        Val::Num(n) if *n >= 0.0 && n.fract() == 0.0 && *n <= u64::MAX as f64 => Ok(*n as u64),
+        _ => Err(format!("{} must be a non-negative integer", key)),
+    }
+}
+
+fn error_codes(key: &str, value: &Val) -> Result<Vec<String>, String> {
+    match value {
+        Val::List(items) => items
+            .iter()
+            .map(|item| match item {
+                Val::Str(code) if !code.is_empty() => Ok(code.clone()),
+                _ => Err(format!("{} must be a list of error codes", key)),
+            })
+            .collect(),
+        _ => Err(format!("{} must be a list of error codes", key)),
+    }
+}
+
+/// Task.stream(task, from?) - Wait for a task's partial results
+///
+/// `task` is the Promise from `Task.run`, or its execution ID. Returns a
+/// Promise that resolves once the task has emitted chunks numbered `from`
+/// (default 0) or later, or has finished, to `{ chunks, next, done }`: pass
+/// `next` as `from` to read on. Only a workflow's own tasks wake it up when
+/// they emit.
+pub fn stream(args: &[Val]) -> EvalResult {
+    if args.is_empty() || args.len() > 2 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 1 or 2 arguments, got {}", args.len()),
+            )),
+        };
+    }
+
+    let execution_id = match &args[0] {
+        Val::Promise(Awaitable::Execution(id)) | Val::Str(id) => id.clone(),
+        _ => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "First argument (task) must be a task Promise or execution ID",
+                )),
+            };
+        }
+    };
+
+    let from = match args.get(1) {
+        None | Some(Val::Null) => 0,
+        Some(Val::Num(n)) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+        Some(_) => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Second argument (from) must be a non-negative integer",
+                )),
+            };
+        }
+    };
+
+    EvalResult::Value {
+        v: Val::Promise(Awaitable::Stream { execution_id, from }),
+    }
+}
+
 /// Extract awaitables from array or object of promises.
 /// Returns (items, is_object) or an error.
 fn extract_awaitables(arg: &Val) -> Result<(Vec<(String, Awaitable)>, bool), EvalResult> {
@@ -64,3 +64,35 @@ pub fn run(args: &[Val], outbox: &mut Outbox) -> EvalResult {
         v: Val::Promise(Awaitable::Execution(execution_id)),
     }
 }
+
+/// Workflow.continueAsNew(inputs) - Restart this workflow with fresh state
+///
+/// Records the new inputs in the outbox, which stops the VM. The runner then
+/// completes this execution and starts a new one of the same workflow in the
+/// same transaction.
+pub fn continue_as_new(args: &[Val], outbox: &mut Outbox) -> EvalResult {
+    if args.len() != 1 {
+        return EvalResult::Throw {
+            error: Val::Error(ErrorInfo::new(
+                errors::WRONG_ARG_COUNT,
+                format!("Expected 1 argument, got {}", args.len()),
+            )),
+        };
+    }
+
+    let inputs = match &args[0] {
+        Val::Obj(map) => map.clone(),
+        _ => {
+            return EvalResult::Throw {
+                error: Val::Error(ErrorInfo::new(
+                    errors::WRONG_ARG_TYPE,
+                    "Argument (inputs) must be an object",
+                )),
+            };
+        }
+    };
+
+    outbox.continue_as_new = Some(inputs);
+
+    EvalResult::Value { v: Val::Null }
+}
@@ -322,3 +322,54 @@ fn test_await_task_run_with_multiline_object() {
         Control::Return(Val::Str("order_processed".to_string()))
     );
 }
+
+#[test]
+fn test_await_handle_again_resumes_without_suspending() {
+    let source = r#"
+            let charge = Task.run("charge", {})
+            let first = await charge
+            if (first > 0) {
+                let again = await charge
+                return [first, again]
+            }
+            return null
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+    let Control::Suspend(Awaitable::Execution(task_id)) = vm.control.clone() else {
+        panic!("Expected to suspend on the task, got {:?}", vm.control);
+    };
+
+    // The result is kept with the state, so a reloaded VM has it too
+    vm.resume(Val::Num(7.0));
+    assert_eq!(vm.settled.get(&task_id), Some(&Val::Num(7.0)));
+    let reloaded: VM = serde_json::from_str(&serde_json::to_string(&vm).unwrap()).unwrap();
+    assert_eq!(reloaded.settled, vm.settled);
+
+    // The second await doesn't suspend
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![Val::Num(7.0), Val::Num(7.0)]))
+    );
+}
+
+#[test]
+fn test_results_without_a_handle_in_scope_are_dropped() {
+    let source = r#"
+            let first = await Task.run("a", {})
+            let second = await Task.run("b", {})
+            return first + second
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+    vm.resume(Val::Num(1.0));
+    run_until_done(&mut vm);
+    vm.resume(Val::Num(2.0));
+    run_until_done(&mut vm);
+
+    assert_eq!(vm.control, Control::Return(Val::Num(3.0)));
+    assert!(vm.settled.is_empty());
+}
@@ -740,6 +740,140 @@ fn test_array_includes_null() {
     assert_eq!(vm.control, Control::Return(Val::Bool(true)));
 }
 
+/* ===================== Array Method Tests ===================== */
+
+fn nums(ns: &[f64]) -> Val {
+    Val::List(ns.iter().map(|n| Val::Num(*n)).collect())
+}
+
+#[test]
+fn test_array_push_returns_extended_copy() {
+    let source = r#"
+            let a = [1, 2]
+            let b = a.push(3, 4)
+            return [a, b]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![
+            nums(&[1.0, 2.0]),
+            nums(&[1.0, 2.0, 3.0, 4.0])
+        ]))
+    );
+}
+
+#[test]
+fn test_array_slice() {
+    let source = r#"
+            let a = [1, 2, 3, 4, 5]
+            return [a.slice(1, 3), a.slice(-2), a.slice(), a.slice(4, 1), a.slice(2, 99)]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![
+            nums(&[2.0, 3.0]),
+            nums(&[4.0, 5.0]),
+            nums(&[1.0, 2.0, 3.0, 4.0, 5.0]),
+            nums(&[]),
+            nums(&[3.0, 4.0, 5.0]),
+        ]))
+    );
+}
+
+#[test]
+fn test_array_index_of() {
+    let source = r#"
+            let a = ["x", "y", "x"]
+            return [a.indexOf("x"), a.indexOf("y"), a.indexOf("z")]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+    assert_eq!(vm.control, Control::Return(nums(&[0.0, 1.0, -1.0])));
+}
+
+#[test]
+fn test_array_reverse_leaves_original() {
+    let source = r#"
+            let a = [1, 2, 3]
+            return [a.reverse(), a]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![
+            nums(&[3.0, 2.0, 1.0]),
+            nums(&[1.0, 2.0, 3.0])
+        ]))
+    );
+}
+
+#[test]
+fn test_array_sort_numbers_and_strings() {
+    let source = r#"
+            return [[10, 9, 1, -2].sort(), ["b", "c", "a"].sort()]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![
+            nums(&[-2.0, 1.0, 9.0, 10.0]),
+            Val::List(vec![
+                Val::Str("a".to_string()),
+                Val::Str("b".to_string()),
+                Val::Str("c".to_string()),
+            ]),
+        ]))
+    );
+}
+
+#[test]
+fn test_array_sort_mixed_types_throws() {
+    let source = r#"
+            return [1, "a"].sort()
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+    let Control::Throw(Val::Error(err)) = &vm.control else {
+        panic!("Expected Throw, got {:?}", vm.control);
+    };
+    assert_eq!(err.code, "TypeError");
+}
+
+#[test]
+fn test_array_flat() {
+    let source = r#"
+            let a = [1, [2, [3, [4]]]]
+            return [a.flat(), a.flat(2).length, a.flat(10)]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![
+            Val::List(vec![
+                Val::Num(1.0),
+                Val::Num(2.0),
+                Val::List(vec![Val::Num(3.0), nums(&[4.0])]),
+            ]),
+            Val::Num(4.0),
+            nums(&[1.0, 2.0, 3.0, 4.0]),
+        ]))
+    );
+}
+
 /* ===================== String.includes Tests ===================== */
 
 #[test]
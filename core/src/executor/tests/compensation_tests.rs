@@ -0,0 +1,140 @@
+//! Tests for Compensate.register() and unwinding a failed workflow
+
+use super::helpers::parse_workflow_and_build_vm;
+use crate::executor::{errors, run_until_done, Awaitable, Control, Val, VM};
+use std::collections::HashMap;
+
+const TRIP: &str = r#"
+    let hotel = await Task.run("book_hotel", {})
+    Compensate.register("cancel_hotel", { hotel })
+    let flight = await Task.run("book_flight", {})
+    Compensate.register("cancel_flight", { flight })
+    let payment = await Task.run("charge", {})
+    if (payment.declined) {
+        throw Error("Payment declined", "DECLINED")
+    }
+    return { hotel, flight }
+"#;
+
+/// Run until the VM suspends, resume it with `result`, and run again
+fn resume(vm: &mut VM, result: Val) {
+    vm.outbox.executions.clear();
+    assert!(vm.resume(result));
+    run_until_done(vm);
+}
+
+/// The task the VM is suspended on, as (name, inputs)
+fn awaited_task(vm: &VM) -> (String, HashMap<String, Val>) {
+    let Control::Suspend(Awaitable::Execution(id)) = &vm.control else {
+        panic!("Expected to be suspended on a task, got {:?}", vm.control);
+    };
+    let creation = vm
+        .outbox
+        .executions
+        .iter()
+        .find(|creation| &creation.id == id)
+        .expect("awaited task is in the outbox");
+    (creation.target_name.clone(), creation.inputs.clone())
+}
+
+fn payment(declined: bool) -> Val {
+    Val::Obj(HashMap::from([(
+        "declined".to_string(),
+        Val::Bool(declined),
+    )]))
+}
+
+#[test]
+fn test_failure_runs_compensations_in_reverse_then_throws() {
+    let mut vm = parse_workflow_and_build_vm(TRIP, HashMap::new());
+    run_until_done(&mut vm);
+    resume(&mut vm, Val::Str("H-1".to_string()));
+    resume(&mut vm, Val::Str("F-1".to_string()));
+    assert_eq!(vm.compensations.len(), 2);
+
+    resume(&mut vm, payment(true));
+    let (name, inputs) = awaited_task(&vm);
+    assert_eq!(name, "cancel_flight");
+    assert_eq!(inputs["flight"], Val::Str("F-1".to_string()));
+    assert!(vm.frames.is_empty());
+
+    // Unwinding survives being saved, and a failed compensation doesn't
+    // stop the rest
+    let mut vm: VM = serde_json::from_value(serde_json::to_value(&vm).unwrap()).unwrap();
+    resume(&mut vm, Val::Str("not cancelled".to_string()));
+    let (name, inputs) = awaited_task(&vm);
+    assert_eq!(name, "cancel_hotel");
+    assert_eq!(inputs["hotel"], Val::Str("H-1".to_string()));
+
+    resume(&mut vm, Val::Null);
+    let Control::Throw(Val::Error(err)) = &vm.control else {
+        panic!("Expected the original error, got {:?}", vm.control);
+    };
+    assert_eq!(err.code, "DECLINED");
+    assert!(vm.outbox.executions.is_empty());
+    assert!(vm.compensations.is_empty());
+    assert!(vm.unwinding.is_none());
+}
+
+#[test]
+fn test_success_runs_no_compensations() {
+    let mut vm = parse_workflow_and_build_vm(TRIP, HashMap::new());
+    run_until_done(&mut vm);
+    resume(&mut vm, Val::Str("H-1".to_string()));
+    resume(&mut vm, Val::Str("F-1".to_string()));
+    resume(&mut vm, payment(false));
+
+    assert!(matches!(vm.control, Control::Return(Val::Obj(_))));
+    assert!(vm.outbox.executions.is_empty());
+}
+
+#[test]
+fn test_caught_error_runs_no_compensations() {
+    let source = r#"
+        Compensate.register("release_stock", { sku: "A-1" })
+        try {
+            throw Error("Out of stock")
+        } catch (e) {
+            return "backordered"
+        }
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::Str("backordered".to_string()))
+    );
+    assert!(vm.outbox.executions.is_empty());
+}
+
+#[test]
+fn test_failure_without_compensations_throws_at_once() {
+    let mut vm = parse_workflow_and_build_vm(r#"throw Error("boom")"#, HashMap::new());
+    run_until_done(&mut vm);
+
+    assert!(matches!(vm.control, Control::Throw(_)));
+    assert!(vm.unwinding.is_none());
+}
+
+#[test]
+fn test_register_checks_its_arguments() {
+    for (source, code) in [
+        (r#"Compensate.register("refund")"#, errors::WRONG_ARG_COUNT),
+        (r#"Compensate.register(42, {})"#, errors::WRONG_ARG_TYPE),
+        (
+            r#"Compensate.register("refund", 42)"#,
+            errors::WRONG_ARG_TYPE,
+        ),
+    ] {
+        let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+        run_until_done(&mut vm);
+
+        match &vm.control {
+            Control::Throw(Val::Error(err)) => assert_eq!(err.code, code, "{}", source),
+            _ => panic!("Expected Throw for {}, got {:?}", source, vm.control),
+        }
+        assert!(vm.compensations.is_empty());
+    }
+}
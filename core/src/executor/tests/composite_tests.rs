@@ -138,6 +138,27 @@ fn test_task_all_wrong_arg_type() {
     assert_eq!(err.code, errors::WRONG_ARG_TYPE);
 }
 
+#[test]
+fn test_task_all_is_promise_all() {
+    let source = r#"
+        return Task.all([Task.run("task1", {}), Task.run("task2", {})])
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    match &vm.control {
+        Control::Return(Val::Promise(Awaitable::All { items, is_object })) => {
+            assert_eq!(items.len(), 2);
+            assert!(!*is_object);
+        }
+        _ => panic!(
+            "Expected Control::Return(Val::Promise(Awaitable::All)), got {:?}",
+            vm.control
+        ),
+    }
+}
+
 /* ===================== Promise.any() Tests ===================== */
 
 #[test]
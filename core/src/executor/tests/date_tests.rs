@@ -0,0 +1,112 @@
+//! Tests for Date.now() and Date.iso()
+
+use super::helpers::parse_workflow_and_build_vm;
+use crate::executor::{errors, run_until_done, Awaitable, Control, Val, VM};
+use chrono::Utc;
+use maplit::hashmap;
+use std::collections::HashMap;
+
+#[test]
+fn test_date_now_reads_and_records_clock() {
+    let source = r#"
+        return Date.now()
+    "#;
+
+    let before = Utc::now().timestamp_millis() as f64;
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+    let after = Utc::now().timestamp_millis() as f64;
+
+    let Control::Return(Val::Num(now)) = vm.control else {
+        panic!("Expected a number, got {:?}", vm.control);
+    };
+    assert!(
+        before <= now && now <= after,
+        "{} not in {}..{}",
+        now,
+        before,
+        after
+    );
+    assert_eq!(vm.recorded, vec![Val::Num(now)]);
+    assert!(vm.outbox.recorded.is_empty());
+}
+
+#[test]
+fn test_date_now_survives_suspension() {
+    let source = r#"
+        let started = Date.now()
+        let result = await Inputs.task
+        return { started: started, finished: Date.now() }
+    "#;
+    let inputs = hashmap! {
+        "task".to_string() => Val::Promise(Awaitable::Execution("task-1".to_string())),
+    };
+
+    let mut vm = parse_workflow_and_build_vm(source, inputs);
+    run_until_done(&mut vm);
+    assert!(matches!(vm.control, Control::Suspend(_)));
+    let Val::Num(started) = vm.env["started"] else {
+        panic!("Expected a number, got {:?}", vm.env["started"]);
+    };
+
+    let serialized = serde_json::to_string(&vm).unwrap();
+    let mut vm: VM = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(vm.recorded, vec![Val::Num(started)]);
+
+    assert!(vm.resume(Val::Null));
+    run_until_done(&mut vm);
+    let Control::Return(Val::Obj(result)) = &vm.control else {
+        panic!("Expected an object, got {:?}", vm.control);
+    };
+    assert_eq!(result["started"], Val::Num(started));
+    assert_eq!(vm.recorded.len(), 2);
+    assert_eq!(vm.recorded[1], result["finished"]);
+}
+
+#[test]
+fn test_date_iso_formats_given_time() {
+    let source = r#"
+        return Date.iso(1736424000000)
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::Str("2025-01-09T12:00:00.000Z".to_string()))
+    );
+    // Formatting a given time doesn't read the clock
+    assert!(vm.recorded.is_empty());
+}
+
+#[test]
+fn test_date_iso_reads_clock() {
+    let source = r#"
+        return Date.iso()
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Return(Val::Str(iso)) = &vm.control else {
+        panic!("Expected a string, got {:?}", vm.control);
+    };
+    assert!(iso.ends_with('Z'), "{}", iso);
+    assert_eq!(vm.recorded.len(), 1);
+}
+
+#[test]
+fn test_date_iso_rejects_non_number() {
+    let source = r#"
+        return Date.iso("today")
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Throw(Val::Error(err)) = &vm.control else {
+        panic!("Expected Throw, got {:?}", vm.control);
+    };
+    assert_eq!(err.code, errors::WRONG_ARG_TYPE);
+}
@@ -597,3 +597,38 @@ fn test_destructure_trailing_comma() {
 
     assert_eq!(vm.control, Control::Return(Val::Num(3.0)));
 }
+
+/* ===================== Type Annotation Tests ===================== */
+
+#[test]
+fn test_type_annotations_ignored_at_runtime() {
+    // Annotations are advisory; a mismatch still runs
+    let source = r#"
+            let total: number = "not a number"
+            let items: string[] = []
+            return total
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::Str("not a number".to_string()))
+    );
+}
+
+#[test]
+fn test_main_inputs_parameter_binds_inputs() {
+    let source = r#"
+            async function main(inputs: {amount: number}) {
+                return inputs.amount * 2
+            }
+        "#;
+
+    let mut vm =
+        parse_workflow_and_build_vm(source, hashmap! {"amount".to_string() => Val::Num(21.0)});
+    run_until_done(&mut vm);
+
+    assert_eq!(vm.control, Control::Return(Val::Num(42.0)));
+}
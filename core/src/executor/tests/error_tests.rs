@@ -463,3 +463,76 @@ fn test_try_with_multiple_statements_completes() {
     // Should return 3 (1 + 2)
     assert_eq!(vm.control, Control::Return(Val::Num(3.0)));
 }
+
+/* ===================== Throw Tests ===================== */
+
+#[test]
+fn test_throw_error_fails_with_code_and_message() {
+    let source = r#"
+            throw Error("Out of stock", "OUT_OF_STOCK")
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Throw(Val::Error(err)) = vm.control else {
+        unreachable!("Expected Control::Throw with Error, got {:?}", vm.control);
+    };
+    assert_eq!(err.code, "OUT_OF_STOCK");
+    assert_eq!(err.message, "Out of stock");
+}
+
+#[test]
+fn test_throw_is_caught_and_error_fields_are_readable() {
+    let source = r#"
+            let result = null
+            try {
+                throw Error("boom")
+                result = "not reached"
+            } catch (e) {
+                result = `${e.code}: ${e.message}`
+            }
+            return result
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::Str("Error: boom".to_string()))
+    );
+}
+
+#[test]
+fn test_throw_any_value() {
+    let source = r#"
+            throw { reason: "declined" }
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Throw(Val::Obj(obj)) = vm.control else {
+        unreachable!(
+            "Expected Control::Throw with an object, got {:?}",
+            vm.control
+        );
+    };
+    assert_eq!(obj["reason"], Val::Str("declined".to_string()));
+}
+
+#[test]
+fn test_error_requires_string_message() {
+    let source = r#"
+            throw Error(42)
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Throw(Val::Error(err)) = vm.control else {
+        unreachable!("Expected Control::Throw with Error, got {:?}", vm.control);
+    };
+    assert_eq!(err.code, "TypeError");
+}
@@ -0,0 +1,104 @@
+//! Tests for ExternalTask.create() function implementation
+
+use super::helpers::parse_workflow_and_build_vm;
+use crate::executor::{errors, run_until_done, Awaitable, Control, Val};
+use crate::types::ExecutionType;
+use std::collections::HashMap;
+
+#[test]
+fn test_external_task_create_returns_token_and_promise() {
+    let source = r#"
+        return ExternalTask.create("await-callback", {order: 1})
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Return(Val::Obj(handle)) = &vm.control else {
+        panic!("Expected Return(Obj), got {:?}", vm.control);
+    };
+    let Some(Val::Str(token)) = handle.get("token") else {
+        panic!("Expected string token, got {:?}", handle);
+    };
+    assert_eq!(
+        handle.get("promise"),
+        Some(&Val::Promise(Awaitable::Execution(token.clone())))
+    );
+
+    // Recorded as an external execution whose ID is the token
+    assert_eq!(vm.outbox.executions.len(), 1);
+    let exec = &vm.outbox.executions[0];
+    assert_eq!(&exec.id, token);
+    assert_eq!(exec.target_name, "await-callback");
+    assert_eq!(exec.target_type, ExecutionType::External);
+    assert_eq!(exec.inputs.get("order"), Some(&Val::Num(1.0)));
+}
+
+#[test]
+fn test_await_external_task_promise_suspends_and_resumes() {
+    let source = r#"
+        let cb = ExternalTask.create("await-callback")
+        await Task.run("send_webhook", {token: cb.token})
+        let reply = await cb.promise
+        return reply.status
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Suspend(Awaitable::Execution(webhook_id)) = vm.control.clone() else {
+        panic!("Expected Suspend(Execution), got {:?}", vm.control);
+    };
+    let webhook = vm
+        .outbox
+        .executions
+        .iter()
+        .find(|e| e.id == webhook_id)
+        .unwrap();
+    let Some(Val::Str(token)) = webhook.inputs.get("token").cloned() else {
+        panic!("Expected token in webhook inputs, got {:?}", webhook.inputs);
+    };
+
+    vm.resume(Val::Null);
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Suspend(Awaitable::Execution(token.clone()))
+    );
+
+    let mut reply = HashMap::new();
+    reply.insert("status".to_string(), Val::Str("approved".to_string()));
+    vm.resume(Val::Obj(reply));
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::Str("approved".to_string()))
+    );
+}
+
+#[test]
+fn test_external_task_create_wrong_args() {
+    for source in [
+        "return ExternalTask.create()",
+        r#"return ExternalTask.create("a", {}, 1)"#,
+    ] {
+        let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+        run_until_done(&mut vm);
+        let Control::Throw(Val::Error(err)) = vm.control else {
+            panic!("Expected error, got {:?}", vm.control);
+        };
+        assert_eq!(err.code, errors::WRONG_ARG_COUNT);
+    }
+
+    for source in [
+        "return ExternalTask.create(1)",
+        r#"return ExternalTask.create("a", 1)"#,
+    ] {
+        let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+        run_until_done(&mut vm);
+        let Control::Throw(Val::Error(err)) = vm.control else {
+            panic!("Expected error, got {:?}", vm.control);
+        };
+        assert_eq!(err.code, errors::WRONG_ARG_TYPE);
+    }
+}
@@ -1,4 +1,4 @@
-//! Tests for literal expressions (arrays and objects)
+//! Tests for literal expressions (arrays, objects and templates)
 
 use super::helpers::parse_workflow_and_build_vm;
 use crate::executor::{run_until_done, Control, Val};
@@ -366,3 +366,45 @@ fn test_object_shorthand_from_inputs() {
 
     assert_eq!(vm.control, Control::Return(Val::Obj(expected)));
 }
+
+/* ===================== Template Literal Tests ===================== */
+
+#[test]
+fn test_template_literal_interpolation() {
+    let source = r#"
+            let order = { id: 42, items: ["a"] }
+            return `order ${order.id} failed after ${1 + 2} tries (${order.items.length} item, ${null}, ${true})`
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::Str(
+            "order 42 failed after 3 tries (1 item, null, true)".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_template_literal_nested() {
+    let source = r#"
+            let name = "x"
+            return `a${`b${name}c`}d${""}`
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+    assert_eq!(vm.control, Control::Return(Val::Str("abxcd".to_string())));
+}
+
+#[test]
+fn test_template_literal_propagates_errors() {
+    let source = r#"
+            return `value: ${missing.field}`
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+    assert!(matches!(vm.control, Control::Throw(_)), "{:?}", vm.control);
+}
@@ -0,0 +1,57 @@
+//! Tests for Log.info/warn/error()
+
+use super::helpers::parse_workflow_and_build_vm;
+use crate::executor::{errors, json_to_val, run_until_done, Control, Val};
+use crate::types::LogLevel;
+use serde_json::json;
+use std::collections::HashMap;
+
+#[test]
+fn test_log_records_messages_in_order() {
+    let source = r#"
+        Log.info("Starting")
+        Log.warn("Retrying", { attempt: 2 })
+        return Log.error("Gave up")
+    "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    assert_eq!(vm.control, Control::Return(Val::Null));
+    let logs: Vec<_> = vm
+        .outbox
+        .logs
+        .iter()
+        .map(|log| (log.level, log.message.as_str(), log.data.clone()))
+        .collect();
+    assert_eq!(
+        logs,
+        vec![
+            (LogLevel::Info, "Starting", None),
+            (
+                LogLevel::Warn,
+                "Retrying",
+                Some(json_to_val(&json!({ "attempt": 2 })).unwrap())
+            ),
+            (LogLevel::Error, "Gave up", None),
+        ]
+    );
+}
+
+#[test]
+fn test_log_requires_a_string_message() {
+    for (source, code) in [
+        ("return Log.info()", errors::WRONG_ARG_COUNT),
+        ("return Log.info(\"a\", 1, 2)", errors::WRONG_ARG_COUNT),
+        ("return Log.info(42)", errors::WRONG_ARG_TYPE),
+    ] {
+        let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+        run_until_done(&mut vm);
+
+        match &vm.control {
+            Control::Throw(Val::Error(err)) => assert_eq!(err.code, code, "{}", source),
+            _ => panic!("Expected Throw for {}, got {:?}", source, vm.control),
+        }
+        assert!(vm.outbox.logs.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+//! Tests for moving suspended VMs onto a new workflow definition
+
+use super::helpers::parse_workflow_and_build_vm;
+use crate::executor::migrate::{migrate_vm, suspension_line, PointMapping};
+use crate::executor::{run_until_done, Awaitable, Control, Stmt, Val, VM};
+use maplit::hashmap;
+use serde_json::json;
+
+fn program(source: &str) -> Stmt {
+    crate::parser::parse_workflow(source)
+        .expect("Parse workflow failed")
+        .body
+}
+
+fn suspended(source: &str) -> VM {
+    let inputs = hashmap! {
+        "task".to_string() => Val::Promise(Awaitable::Execution("task-1".to_string())),
+    };
+    let mut vm = parse_workflow_and_build_vm(source, inputs);
+    run_until_done(&mut vm);
+    assert!(
+        matches!(vm.control, Control::Suspend(_)),
+        "{:?}",
+        vm.control
+    );
+    vm
+}
+
+/// Round-trip through JSON like a stored VM, then resume with `value`
+fn resume(vm: VM, value: f64) -> VM {
+    let mut vm: VM = serde_json::from_str(&serde_json::to_string(&vm).unwrap()).unwrap();
+    vm.resume(Val::Num(value));
+    run_until_done(&mut vm);
+    vm
+}
+
+#[test]
+fn test_migrate_renames_and_sets_variables() {
+    let old = r#"
+        let count = 2
+        let result = await Inputs.task
+        return count + result
+    "#;
+    let new = r#"
+        let total = 2
+        let bonus = 0
+        let result = await Inputs.task
+        return total + result + bonus
+    "#;
+
+    let vm = suspended(old);
+    assert_eq!(suspension_line(&vm), Some(3));
+
+    let mapping = PointMapping {
+        from_line: 3,
+        to_line: 4,
+        rename: [("total".to_string(), "count".to_string())].into(),
+        set: [("bonus".to_string(), json!(10))].into(),
+    };
+    let migrated = migrate_vm(&vm, &program(new), &mapping).unwrap();
+    assert_eq!(suspension_line(&migrated), Some(4));
+    assert_eq!(
+        migrated.control,
+        Control::Suspend(Awaitable::Execution("task-1".to_string()))
+    );
+
+    let done = resume(migrated, 5.0);
+    assert_eq!(done.control, Control::Return(Val::Num(17.0)));
+}
+
+#[test]
+fn test_migrate_keeps_loop_position() {
+    let old = r#"
+        let sum = 0
+        for (let x of [1, 2, 3]) {
+            let result = await Task.run("step", { x })
+            sum = sum + result
+        }
+        return sum
+    "#;
+    let new = r#"
+        let sum = 0
+        for (let x of [1, 2, 3]) {
+            let result = await Task.run("step", { x })
+            sum = sum + x * result
+        }
+        return sum
+    "#;
+
+    // Suspended in the second iteration, with sum = 1
+    let vm = resume(suspended(old), 1.0);
+    assert_eq!(suspension_line(&vm), Some(4));
+
+    let mapping = PointMapping {
+        from_line: 4,
+        to_line: 4,
+        ..Default::default()
+    };
+    let migrated = migrate_vm(&vm, &program(new), &mapping).unwrap();
+
+    let vm = resume(migrated, 1.0);
+    let done = resume(vm, 1.0);
+    assert_eq!(done.control, Control::Return(Val::Num(6.0)));
+}
+
+#[test]
+fn test_migrate_inside_if_and_try() {
+    let old = r#"
+        let id = "a"
+        let result = await Inputs.task
+        return result
+    "#;
+    let new = r#"
+        let id = "a"
+        if (id == "a") {
+            try {
+                let result = await Inputs.task
+                return result + 1
+            } catch (e) {
+                return 0
+            }
+        }
+        return null
+    "#;
+
+    let mapping = PointMapping {
+        from_line: 3,
+        to_line: 5,
+        ..Default::default()
+    };
+    let migrated = migrate_vm(&suspended(old), &program(new), &mapping).unwrap();
+
+    let done = resume(migrated, 41.0);
+    assert_eq!(done.control, Control::Return(Val::Num(42.0)));
+}
+
+#[test]
+fn test_migrate_rejects_bad_mappings() {
+    let old = r#"
+        let count = 2
+        let result = await Inputs.task
+        return count + result
+    "#;
+    let new = r#"
+        let total = 2
+        let result = await Inputs.task
+        return total + result
+    "#;
+    let vm = suspended(old);
+    let new = program(new);
+
+    let wrong_from = PointMapping {
+        from_line: 2,
+        to_line: 3,
+        ..Default::default()
+    };
+    let err = migrate_vm(&vm, &new, &wrong_from).unwrap_err();
+    assert!(err.to_string().contains("suspended at line 3"), "{}", err);
+
+    let not_await = PointMapping {
+        from_line: 3,
+        to_line: 2,
+        ..Default::default()
+    };
+    let err = migrate_vm(&vm, &new, &not_await).unwrap_err();
+    assert!(err.to_string().contains("No statement awaiting"), "{}", err);
+
+    let missing_var = PointMapping {
+        from_line: 3,
+        to_line: 3,
+        ..Default::default()
+    };
+    let err = migrate_vm(&vm, &new, &missing_var).unwrap_err();
+    assert!(err.to_string().contains("'total'"), "{}", err);
+}
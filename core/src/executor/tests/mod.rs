@@ -5,21 +5,28 @@
 mod assign_tests;
 mod await_tests;
 mod basic_tests;
+mod compensation_tests;
 mod composite_tests;
+mod date_tests;
 mod declare_tests;
 mod error_tests;
+mod external_task_tests;
 mod for_loop_tests;
 pub mod helpers; // Public helper utilities for tests
 mod if_tests;
 mod literal_tests;
+mod log_tests;
+mod migrate_tests;
 mod nullish_coalescing_tests;
 mod operator_tests;
 mod optional_chaining_tests;
+mod replay_tests;
 mod signal_tests;
 mod stdlib_tests;
 mod task_tests;
 mod ternary_tests;
 mod timer_tests;
+mod trace_tests;
 mod truthy_tests;
 mod while_tests;
 mod workflow_tests;
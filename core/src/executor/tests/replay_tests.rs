@@ -0,0 +1,201 @@
+//! Tests for replaying a workflow against its recorded run
+
+use crate::executor::replay::{replay, Divergence, History, RecordedCall};
+use crate::executor::{Val, WorkflowContext};
+use crate::parser::parse_workflow;
+use crate::types::ExecutionType;
+use serde_json::json;
+use std::collections::HashMap;
+
+fn task(name: &str, inputs: serde_json::Value) -> RecordedCall {
+    RecordedCall {
+        target_type: ExecutionType::Task,
+        target_name: name.to_string(),
+        inputs: Some(inputs),
+    }
+}
+
+/// A run that charged at t=1000, was resumed with the receipt, and is
+/// suspended on shipping it
+fn history() -> History {
+    History {
+        calls: vec![
+            task("charge", json!({ "at": 1000 })),
+            task("ship", json!({ "receipt": "r1" })),
+        ],
+        resumes: vec![Val::Str("r1".to_string())],
+        recorded: vec![Val::Num(1000.0)],
+    }
+}
+
+fn replay_source(source: &str, history: &History, at_await: bool) -> Option<Divergence> {
+    let workflow = parse_workflow(source).unwrap();
+    let context = WorkflowContext {
+        execution_id: "wf".to_string(),
+    };
+    replay(
+        &workflow.body,
+        HashMap::new(),
+        context,
+        history,
+        at_await,
+        100_000,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_replay_of_the_same_code_matches() {
+    let source = r#"
+        let receipt = await Task.run("charge", { at: Date.now() })
+        Log.info("Charged")
+        await Timer.delay(1)
+        return await Task.run("ship", { receipt: receipt })
+    "#;
+    // The timer was resumed with null
+    let mut history = history();
+    history.resumes.push(Val::Null);
+
+    assert_eq!(replay_source(source, &history, true), None);
+}
+
+#[test]
+fn test_replay_reports_the_first_changed_call() {
+    let source = r#"
+        let receipt = await Task.run("charge", { at: Date.now() })
+        return await Task.run("refund", { receipt: receipt })
+    "#;
+
+    let divergence = replay_source(source, &history(), true).unwrap();
+    let Divergence::Call {
+        position,
+        expected,
+        actual,
+        span,
+    } = &divergence
+    else {
+        panic!("Expected a call divergence, got {:?}", divergence);
+    };
+    assert_eq!(*position, 1);
+    assert_eq!(expected.as_ref().unwrap().target_name, "ship");
+    assert_eq!(
+        actual.as_ref(),
+        Some(&task("refund", json!({ "receipt": "r1" })))
+    );
+    assert_eq!(span.unwrap().start_line, 2);
+
+    let error = divergence.to_error_json();
+    assert_eq!(error["type"], "NonDeterministic");
+    assert_eq!(
+        error["message"],
+        "Workflow is not deterministic: Call #2 was to task 'ship' when recorded, \
+         but to task 'refund' on replay"
+    );
+    assert_eq!(error["divergence"]["kind"], "call");
+}
+
+#[test]
+fn test_replay_compares_inputs_using_recorded_reads() {
+    // Random draws are handed back too, so only a real change in inputs shows
+    let source = r#"
+        let receipt = await Task.run("charge", { at: Date.now() })
+        return await Task.run("ship", { receipt: receipt, jitter: Math.random() })
+    "#;
+    let mut history = history();
+    history.recorded.push(Val::Num(0.5));
+    history.calls[1] = task("ship", json!({ "receipt": "r1", "jitter": 0.5 }));
+    assert_eq!(replay_source(source, &history, true), None);
+
+    history.calls[1] = task("ship", json!({ "receipt": "r1", "jitter": 0.25 }));
+    let divergence = replay_source(source, &history, true).unwrap();
+    assert_eq!(
+        divergence.to_string(),
+        "Call #2 to task 'ship' has other inputs on replay"
+    );
+}
+
+#[test]
+fn test_replay_reports_missing_calls_and_early_finish() {
+    let source = r#"
+        let receipt = await Task.run("charge", { at: Date.now() })
+        return receipt
+    "#;
+    assert_eq!(
+        replay_source(source, &history(), true),
+        Some(Divergence::Finished)
+    );
+
+    // A yielded run only needs its recorded calls made
+    let mut history = history();
+    history.resumes.clear();
+    let divergence = replay_source(source, &history, false).unwrap();
+    assert!(matches!(
+        divergence,
+        Divergence::Call {
+            position: 1,
+            actual: None,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_replay_of_a_yielded_run_allows_later_calls() {
+    let source = r#"
+        let receipt = await Task.run("charge", { at: Date.now() })
+        return await Task.run("ship", { receipt: receipt })
+    "#;
+    let mut history = history();
+    history.calls.pop();
+    assert_eq!(replay_source(source, &history, false), None);
+
+    // But a run saved at an await made every call it was going to
+    assert!(matches!(
+        replay_source(source, &history, true),
+        Some(Divergence::Call {
+            position: 1,
+            expected: None,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_replay_reports_a_change_in_reads() {
+    let source = r#"
+        let receipt = await Task.run("charge", { at: Date.now() })
+        let shipped = await Task.run("ship", { receipt: receipt })
+        return shipped
+    "#;
+    let mut history = history();
+    history.recorded.push(Val::Num(2000.0));
+    assert_eq!(
+        replay_source(source, &history, true),
+        Some(Divergence::Reads {
+            expected: 2,
+            actual: 1
+        })
+    );
+}
+
+#[test]
+fn test_replay_follows_compensations_after_a_failure() {
+    let source = r#"
+        let receipt = await Task.run("charge", { at: 1000 })
+        Compensate.register("refund", { receipt })
+        Compensate.register("release", {})
+        throw Error("Shipping failed")
+    "#;
+    // Suspended on the refund, after the release ran
+    let history = History {
+        calls: vec![
+            task("charge", json!({ "at": 1000 })),
+            task("release", json!({})),
+            task("refund", json!({ "receipt": "r1" })),
+        ],
+        resumes: vec![Val::Str("r1".to_string()), Val::Null],
+        recorded: vec![],
+    };
+
+    assert_eq!(replay_source(source, &history, true), None);
+}
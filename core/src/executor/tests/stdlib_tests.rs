@@ -167,6 +167,98 @@ fn test_wrong_arg_type() {
     assert!(err.message.contains("must be a number"));
 }
 
+/* ===================== Math.min / Math.max Tests ===================== */
+
+#[test]
+fn test_math_min_max() {
+    let source = r#"
+            return [Math.min(3, -1, 2), Math.max(3, -1, 2), Math.min(7)]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![
+            Val::Num(-1.0),
+            Val::Num(3.0),
+            Val::Num(7.0)
+        ]))
+    );
+}
+
+#[test]
+fn test_math_min_requires_arguments() {
+    let source = r#"
+            return Math.min()
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+
+    let Control::Throw(Val::Error(err)) = &vm.control else {
+        panic!("Expected Throw, got {:?}", vm.control);
+    };
+    assert_eq!(err.code, errors::WRONG_ARG_COUNT);
+}
+
+/* ===================== Math.pow / Math.sqrt Tests ===================== */
+
+#[test]
+fn test_math_pow_and_sqrt() {
+    let source = r#"
+            return [Math.pow(2, 10), Math.pow(4, 0.5), Math.sqrt(81)]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+
+    assert_eq!(
+        vm.control,
+        Control::Return(Val::List(vec![
+            Val::Num(1024.0),
+            Val::Num(2.0),
+            Val::Num(9.0)
+        ]))
+    );
+}
+
+#[test]
+fn test_math_sqrt_negative_is_nan() {
+    let source = r#"
+            return Math.sqrt(-1)
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+
+    assert!(matches!(vm.control, Control::Return(Val::Num(n)) if n.is_nan()));
+}
+
+/* ===================== Math.random Tests ===================== */
+
+#[test]
+fn test_math_random_draws_are_recorded() {
+    let source = r#"
+            let a = Math.random()
+            let b = Math.random()
+            return [a, b]
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    run_until_done(&mut vm);
+
+    let Control::Return(Val::List(draws)) = &vm.control else {
+        panic!("Expected a list, got {:?}", vm.control);
+    };
+    for draw in draws {
+        assert!(matches!(draw, Val::Num(n) if (0.0..1.0).contains(n)));
+    }
+    assert_ne!(draws[0], draws[1]);
+    assert_eq!(&vm.recorded, draws);
+}
+
 /* ===================== Nested/Complex Tests ===================== */
 
 #[test]
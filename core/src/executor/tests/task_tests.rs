@@ -1,7 +1,7 @@
 //! Tests for Task.run() and outbox functionality
 
 use super::helpers::parse_workflow_and_build_vm;
-use crate::executor::{errors, run_until_done, Awaitable, Control, Val};
+use crate::executor::{errors, run_until_done, Awaitable, Control, ExecutionOptions, Val};
 use std::collections::HashMap;
 
 /* ===================== Task.run() Tests ===================== */
@@ -174,15 +174,15 @@ fn test_task_run_wrong_arg_count_one_arg() {
         panic!("Expected Control::Throw with Error, got {:?}", vm.control);
     };
     assert_eq!(err.code, errors::WRONG_ARG_COUNT);
-    assert!(err.message.contains("Expected 2 arguments"));
+    assert!(err.message.contains("Expected 2 or 3 arguments"));
 }
 
 #[test]
-fn test_task_run_wrong_arg_count_three_args() {
-    // Task.run("my_task", {}, extra) - too many arguments
+fn test_task_run_wrong_arg_count_four_args() {
+    // Task.run("my_task", {}, {}, extra) - too many arguments
     let source = r#"
             obj = {}
-            return Task.run("my_task", obj, 42)
+            return Task.run("my_task", obj, obj, 42)
         "#;
 
     let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
@@ -193,7 +193,7 @@ fn test_task_run_wrong_arg_count_three_args() {
         panic!("Expected Control::Throw with Error, got {:?}", vm.control);
     };
     assert_eq!(err.code, errors::WRONG_ARG_COUNT);
-    assert!(err.message.contains("Expected 2 arguments, got 3"));
+    assert!(err.message.contains("Expected 2 or 3 arguments, got 4"));
 }
 
 #[test]
@@ -234,3 +234,76 @@ fn test_task_run_second_arg_not_object() {
     assert!(err.message.contains("inputs"));
     assert!(err.message.contains("object"));
 }
+
+/* ===================== Task.run() Options Tests ===================== */
+
+#[test]
+fn test_task_run_options() {
+    let source = r#"
+            Task.run("charge", {}, {
+                timeout_seconds: 30,
+                max_retries: 5,
+                retry_on: ["RATE_LIMIT", "HTTP_5*"],
+                give_up_on: ["VALIDATION"],
+                queue: "payments",
+                priority: 10
+            })
+            return Task.run("receipt", {}, { queue: null })
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    assert_eq!(vm.outbox.executions.len(), 2);
+    assert_eq!(
+        vm.outbox.executions[0].options,
+        ExecutionOptions {
+            timeout_seconds: Some(30),
+            max_retries: Some(5),
+            retry_on: vec!["RATE_LIMIT".to_string(), "HTTP_5*".to_string()],
+            give_up_on: vec!["VALIDATION".to_string()],
+            queue: Some("payments".to_string()),
+            priority: Some(10),
+        }
+    );
+    // Null fields are left to the defaults
+    assert_eq!(vm.outbox.executions[1].options, ExecutionOptions::default());
+}
+
+#[test]
+fn test_task_run_invalid_options() {
+    for (options, message) in [
+        ("42", "Third argument (options) must be an object"),
+        (
+            "{ max_retries: -1 }",
+            "max_retries must be a non-negative integer",
+        ),
+        (
+            "{ timeout_seconds: 1.5 }",
+            "timeout_seconds must be a non-negative integer",
+        ),
+        ("{ queue: \"\" }", "queue must be a non-empty string"),
+        (
+            "{ retry_on: \"RATE_LIMIT\" }",
+            "retry_on must be a list of error codes",
+        ),
+        (
+            "{ give_up_on: [\"\"] }",
+            "give_up_on must be a list of error codes",
+        ),
+        ("{ priority: \"high\" }", "priority must be an integer"),
+        ("{ retries: 3 }", "Unknown option 'retries'"),
+    ] {
+        let source = format!(r#"return Task.run("charge", {{}}, {})"#, options);
+
+        let mut vm = parse_workflow_and_build_vm(&source, HashMap::new());
+        run_until_done(&mut vm);
+
+        let Control::Throw(Val::Error(err)) = &vm.control else {
+            panic!("Expected Control::Throw with Error, got {:?}", vm.control);
+        };
+        assert_eq!(err.code, errors::WRONG_ARG_TYPE);
+        assert!(err.message.contains(message), "{}", err.message);
+        assert!(vm.outbox.executions.is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+//! Tests for VM step tracing
+
+use super::helpers::parse_workflow_and_build_vm;
+use crate::executor::{run_until_done, Control, VmTrace};
+use maplit::hashmap;
+
+#[test]
+fn test_trace_records_steps_and_control_changes() {
+    let source = r#"
+let x = 1
+return x.missing.field
+"#;
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    vm.trace = Some(VmTrace::default());
+    run_until_done(&mut vm);
+    assert!(matches!(vm.control, Control::Throw(_)));
+
+    let trace = vm.trace.as_ref().unwrap();
+    let steps = trace.steps();
+    assert_eq!(steps.len() as u64, trace.total());
+    assert_eq!(steps[0].frame, "Block");
+    assert_eq!(steps[0].depth, 1);
+
+    // The throw is pinned to the return statement on line 3
+    let throw = steps
+        .iter()
+        .find(|step| step.control.as_deref() == Some("none -> throw"))
+        .unwrap();
+    assert_eq!(throw.frame, "Return");
+    assert_eq!(throw.span.start_line, 2);
+}
+
+#[test]
+fn test_trace_keeps_most_recent_steps() {
+    let source = r#"
+let i = 0
+while (i < 50) {
+    i = i + 1
+}
+return i
+"#;
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    vm.trace = Some(VmTrace::new(10));
+    run_until_done(&mut vm);
+
+    let trace = vm.trace.as_ref().unwrap();
+    let steps = trace.steps();
+    assert_eq!(steps.len(), 10);
+    assert!(trace.total() > 100);
+    assert_eq!(steps.last().unwrap().step, trace.total() - 1);
+    assert!(steps
+        .iter()
+        .any(|step| step.control.as_deref() == Some("none -> return")));
+}
+
+#[test]
+fn test_untraced_vm_records_nothing() {
+    let mut vm = parse_workflow_and_build_vm("return 1", hashmap! {});
+    run_until_done(&mut vm);
+    assert!(vm.trace.is_none());
+}
@@ -273,3 +273,29 @@ fn test_nested_while_with_breaks() {
 
     assert_eq!(vm.control, Control::Return(Val::Num(77.0)));
 }
+
+#[test]
+fn test_while_loop_resumes_after_step_budget_runs_out() {
+    let source = r#"
+            i = 0
+            while (i < 100) {
+                i = i + 1
+            }
+            return i
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, hashmap! {});
+    assert_eq!(run_for_steps(&mut vm, 50), None);
+    assert_eq!(vm.control, Control::None);
+
+    // Stopped mid-loop; the state round-trips and finishes where it left off
+    let json = serde_json::to_string(&vm).unwrap();
+    let mut vm: VM = serde_json::from_str(&json).unwrap();
+    let mut slices = 1;
+    while run_for_steps(&mut vm, 50).is_none() {
+        slices += 1;
+    }
+
+    assert!(slices > 1);
+    assert_eq!(vm.control, Control::Return(Val::Num(100.0)));
+}
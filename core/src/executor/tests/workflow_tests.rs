@@ -342,3 +342,40 @@ fn test_workflow_run_second_arg_not_object() {
     assert!(err.message.contains("inputs"));
     assert!(err.message.contains("object"));
 }
+
+/* ===================== Workflow.continueAsNew() Tests ===================== */
+
+#[test]
+fn test_workflow_continue_as_new_stops_the_vm() {
+    let source = r#"
+            Workflow.continueAsNew({cursor: Inputs.cursor + 1})
+            return "unreachable"
+        "#;
+
+    let mut env = HashMap::new();
+    env.insert("cursor".to_string(), Val::Num(1.0));
+
+    let mut vm = parse_workflow_and_build_vm(source, env);
+    run_until_done(&mut vm);
+
+    assert_eq!(vm.control, Control::None);
+    let mut expected = HashMap::new();
+    expected.insert("cursor".to_string(), Val::Num(2.0));
+    assert_eq!(vm.outbox.continue_as_new, Some(expected));
+}
+
+#[test]
+fn test_workflow_continue_as_new_inputs_not_object() {
+    let source = r#"
+            return Workflow.continueAsNew(42)
+        "#;
+
+    let mut vm = parse_workflow_and_build_vm(source, HashMap::new());
+    run_until_done(&mut vm);
+
+    let Control::Throw(Val::Error(err)) = vm.control else {
+        panic!("Expected Control::Throw with Error, got {:?}", vm.control);
+    };
+    assert_eq!(err.code, errors::WRONG_ARG_TYPE);
+    assert!(vm.outbox.continue_as_new.is_none());
+}
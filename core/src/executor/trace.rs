@@ -0,0 +1,135 @@
+//! Step tracing for debugging the interpreter
+//!
+//! With a `VmTrace` attached, `step()` records each step it takes: the frame
+//! it ran, where in that statement the frame was, and any change of control
+//! flow. Only the most recent steps are kept, so a long-running workflow
+//! costs a bounded amount of memory while tracing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::types::ast::Span;
+use super::types::{Control, FrameKind, Stmt};
+
+/// Steps kept when no capacity is given
+pub const DEFAULT_TRACE_CAPACITY: usize = 512;
+
+/// One step of the VM
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    /// Steps taken before this one in the traced run
+    pub step: u64,
+    /// Frames on the stack when the step began
+    pub depth: usize,
+    /// Kind of the frame that ran, e.g. `Block`
+    pub frame: String,
+    /// Phase the frame was in, with the statement or item index for blocks
+    /// and loops, e.g. `Execute@2`
+    pub pc: String,
+    /// The frame's statement
+    pub span: Span,
+    /// Control flow change the step made, e.g. `none -> throw`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control: Option<String>,
+}
+
+/// Ring buffer of the most recent steps
+#[derive(Debug, Clone)]
+pub struct VmTrace {
+    capacity: usize,
+    steps: VecDeque<TraceStep>,
+    total: u64,
+}
+
+impl Default for VmTrace {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_CAPACITY)
+    }
+}
+
+impl VmTrace {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            steps: VecDeque::new(),
+            total: 0,
+        }
+    }
+
+    /// Record a step that began at `start`, leaving control at `after`
+    pub fn record(&mut self, start: StepStart, after: &Control) {
+        let after = control_label(after);
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(TraceStep {
+            step: self.total,
+            depth: start.depth,
+            frame: start.frame.to_string(),
+            pc: start.pc,
+            span: start.span,
+            control: (start.control != after).then(|| format!("{} -> {}", start.control, after)),
+        });
+        self.total += 1;
+    }
+
+    /// Recorded steps, oldest first
+    pub fn steps(&self) -> Vec<TraceStep> {
+        self.steps.iter().cloned().collect()
+    }
+
+    /// Steps taken in all, including those no longer kept
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+/// Where a step began, taken before it runs
+pub struct StepStart {
+    depth: usize,
+    frame: &'static str,
+    pc: String,
+    span: Span,
+    control: &'static str,
+}
+
+impl StepStart {
+    pub fn new(depth: usize, kind: &FrameKind, node: &Stmt, control: &Control) -> Self {
+        let (frame, pc) = frame_pc(kind);
+        Self {
+            depth,
+            frame,
+            pc,
+            span: node.span(),
+            control: control_label(control),
+        }
+    }
+}
+
+pub(crate) fn frame_pc(kind: &FrameKind) -> (&'static str, String) {
+    match kind {
+        FrameKind::Return { phase } => ("Return", format!("{:?}", phase)),
+        FrameKind::Throw { phase } => ("Throw", format!("{:?}", phase)),
+        FrameKind::Block { phase, idx, .. } => ("Block", format!("{:?}@{}", phase, idx)),
+        FrameKind::Try { phase, .. } => ("Try", format!("{:?}", phase)),
+        FrameKind::Expr { phase } => ("Expr", format!("{:?}", phase)),
+        FrameKind::Assign { phase } => ("Assign", format!("{:?}", phase)),
+        FrameKind::If { phase } => ("If", format!("{:?}", phase)),
+        FrameKind::While { phase, .. } => ("While", format!("{:?}", phase)),
+        FrameKind::ForLoop { phase, idx, .. } => ("ForLoop", format!("{:?}@{}", phase, idx)),
+        FrameKind::Break { phase } => ("Break", format!("{:?}", phase)),
+        FrameKind::Continue { phase } => ("Continue", format!("{:?}", phase)),
+        FrameKind::Declare { phase } => ("Declare", format!("{:?}", phase)),
+    }
+}
+
+fn control_label(control: &Control) -> &'static str {
+    match control {
+        Control::None => "none",
+        Control::Break(_) => "break",
+        Control::Continue(_) => "continue",
+        Control::Return(_) => "return",
+        Control::Throw(_) => "throw",
+        Control::Suspend(_) => "suspend",
+    }
+}
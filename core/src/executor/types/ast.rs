@@ -102,6 +102,60 @@ pub enum DeclareTarget {
     },
 }
 
+/// Type annotation AST node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t")]
+pub enum TypeExpr {
+    /// A named type: number, string, boolean, null, any, object, Task
+    Named {
+        name: String,
+        #[serde(default, skip_serializing_if = "is_default_span")]
+        span: Span,
+    },
+    /// `T[]`
+    Array {
+        element: Box<TypeExpr>,
+        #[serde(default, skip_serializing_if = "is_default_span")]
+        span: Span,
+    },
+    /// `{ name: T, other?: U }`
+    Object {
+        fields: Vec<TypeField>,
+        #[serde(default, skip_serializing_if = "is_default_span")]
+        span: Span,
+    },
+    /// `A | B`
+    Union {
+        variants: Vec<TypeExpr>,
+        #[serde(default, skip_serializing_if = "is_default_span")]
+        span: Span,
+    },
+}
+
+/// Field of an object type annotation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeField {
+    pub name: String,
+    /// Declared with `?:` - may be missing or null
+    #[serde(default)]
+    pub optional: bool,
+    pub ty: TypeExpr,
+    #[serde(default, skip_serializing_if = "is_default_span")]
+    pub span: Span,
+}
+
+impl TypeExpr {
+    /// Get the span of this type annotation
+    pub fn span(&self) -> Span {
+        match self {
+            TypeExpr::Named { span, .. } => *span,
+            TypeExpr::Array { span, .. } => *span,
+            TypeExpr::Object { span, .. } => *span,
+            TypeExpr::Union { span, .. } => *span,
+        }
+    }
+}
+
 /// Member access segment for assignment paths
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "t")]
@@ -130,6 +184,9 @@ pub enum Stmt {
     Declare {
         var_kind: VarKind,
         target: DeclareTarget,
+        /// Optional type annotation (`let x: number`), ignored at runtime
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        type_ann: Option<TypeExpr>,
         init: Option<Expr>,
         #[serde(default, skip_serializing_if = "is_default_span")]
         span: Span,
@@ -171,6 +228,11 @@ pub enum Stmt {
         #[serde(default, skip_serializing_if = "is_default_span")]
         span: Span,
     },
+    Throw {
+        value: Expr,
+        #[serde(default, skip_serializing_if = "is_default_span")]
+        span: Span,
+    },
     Try {
         body: Box<Stmt>,
         catch_var: String,
@@ -206,6 +268,7 @@ impl Stmt {
             Stmt::While { span, .. } => *span,
             Stmt::ForLoop { span, .. } => *span,
             Stmt::Return { span, .. } => *span,
+            Stmt::Throw { span, .. } => *span,
             Stmt::Try { span, .. } => *span,
             Stmt::Expr { span, .. } => *span,
             Stmt::Break { span } => *span,
@@ -245,6 +308,16 @@ pub enum Expr {
         #[serde(default, skip_serializing_if = "is_default_span")]
         span: Span,
     },
+    /// Template literal: `` `order ${id} failed` ``
+    ///
+    /// `quasis` holds the text around the interpolations, so it always has
+    /// one more element than `exprs`.
+    Template {
+        quasis: Vec<String>,
+        exprs: Vec<Expr>,
+        #[serde(default, skip_serializing_if = "is_default_span")]
+        span: Span,
+    },
     LitList {
         elements: Vec<Expr>,
         #[serde(default, skip_serializing_if = "is_default_span")]
@@ -305,6 +378,7 @@ impl Expr {
             Expr::LitNum { span, .. } => *span,
             Expr::LitStr { span, .. } => *span,
             Expr::LitNull { span } => *span,
+            Expr::Template { span, .. } => *span,
             Expr::LitList { span, .. } => *span,
             Expr::LitObj { span, .. } => *span,
             Expr::Ident { span, .. } => *span,
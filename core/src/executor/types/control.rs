@@ -3,7 +3,7 @@
 use super::ast::Stmt;
 use super::phase::{
     AssignPhase, BlockPhase, BreakPhase, ContinuePhase, DeclarePhase, ExprPhase, ForLoopPhase,
-    IfPhase, ReturnPhase, TryPhase, WhilePhase,
+    IfPhase, ReturnPhase, ThrowPhase, TryPhase, WhilePhase,
 };
 use super::values::{Awaitable, Val};
 use serde::{Deserialize, Serialize};
@@ -35,6 +35,9 @@ pub enum FrameKind {
     Return {
         phase: ReturnPhase,
     },
+    Throw {
+        phase: ThrowPhase,
+    },
     Block {
         phase: BlockPhase,
         idx: usize,
@@ -12,6 +12,13 @@ pub enum ReturnPhase {
     Eval = 0,
 }
 
+/// Execution phase for Throw statements
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum ThrowPhase {
+    Eval = 0,
+}
+
 /// Execution phase for Block statements
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
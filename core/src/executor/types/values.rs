@@ -12,6 +12,7 @@ use std::collections::HashMap;
 /// - Execution: waiting for a child execution (task or workflow) to complete
 /// - Timer: waiting for a specific time to pass (identified by fire_at timestamp)
 /// - All/Any/Race: composite awaitables that combine multiple awaitables
+/// - Stream: waiting for a child task's partial results
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "t", content = "v")]
 pub enum Awaitable {
@@ -42,6 +43,9 @@ pub enum Awaitable {
     /// Wait for a signal on a named channel.
     /// claim_id uniquely identifies this request for idempotent resolution.
     Signal { name: String, claim_id: String },
+    /// Wait for partial results a task emitted, numbered `from` onwards, or
+    /// for the task to finish. Resolves to { chunks, next, done }.
+    Stream { execution_id: String, from: usize },
 }
 
 /// Runtime value type
@@ -4,13 +4,16 @@
 //! - frames: Stack of active statements
 //! - control: Current control flow state (return, break, etc.)
 
+use super::compensation::Compensation;
 use super::outbox::Outbox;
+use super::trace::VmTrace;
 use super::types::{
-    AssignPhase, BlockPhase, BreakPhase, ContinuePhase, Control, DeclarePhase, ExprPhase,
-    ForLoopPhase, Frame, FrameKind, IfPhase, ReturnPhase, Stmt, TryPhase, Val, WhilePhase,
+    AssignPhase, Awaitable, BlockPhase, BreakPhase, ContinuePhase, Control, DeclarePhase,
+    ExprPhase, ForLoopPhase, Frame, FrameKind, IfPhase, ReturnPhase, Stmt, ThrowPhase, TryPhase,
+    Val, WhilePhase,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /* ===================== WorkflowContext ===================== */
 
@@ -53,6 +56,32 @@ pub struct VM {
     /// Variable environment (name -> value mapping)
     pub env: HashMap<String, Val>,
 
+    /// Values the workflow has read from outside it (like `Date.now()` or
+    /// `Math.random()`), in order
+    ///
+    /// These reads are not deterministic, so each value is kept once it has
+    /// been handed out: here until the state is saved, when the worker moves
+    /// them to the execution's history, so the state doesn't grow with
+    /// every read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recorded: Vec<Val>,
+
+    /// Results of tasks and workflows awaited through a handle that is
+    /// still in a variable, by execution ID
+    ///
+    /// Awaiting such a handle again resumes with its result straight away
+    /// instead of suspending (see `exec_loop::step`).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub settled: HashMap<String, Val>,
+
+    /// Compensations registered so far, in order (see `compensation`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compensations: Vec<Compensation>,
+
+    /// The error the workflow failed with, while its compensations run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unwinding: Option<Val>,
+
     /// Resume value for await expressions
     ///
     /// When resuming from suspension, this holds the task result.
@@ -68,6 +97,12 @@ pub struct VM {
     /// should extract and process these after execution.
     #[serde(skip)]
     pub outbox: Outbox,
+
+    /// Recent steps, when tracing (see `trace`)
+    ///
+    /// Runtime-only: each run decides whether to trace.
+    #[serde(skip)]
+    pub trace: Option<VmTrace>,
 }
 
 impl VM {
@@ -93,8 +128,13 @@ impl VM {
             frames: vec![],
             control: Control::None,
             env,
+            recorded: vec![],
+            settled: HashMap::new(),
+            compensations: vec![],
+            unwinding: None,
             resume_value: None,
             outbox: Outbox::new(),
+            trace: None,
         };
 
         // Push initial frame for the program
@@ -111,8 +151,11 @@ impl VM {
     /// Returns true if resume was successful, false if VM was not in suspended state.
     pub fn resume(&mut self, task_result: Val) -> bool {
         // Check that we're actually suspended
-        if !matches!(self.control, Control::Suspend(_)) {
+        let Control::Suspend(awaitable) = &self.control else {
             return false;
+        };
+        if let Awaitable::Execution(execution_id) = awaitable {
+            self.settle(execution_id.clone(), &task_result);
         }
 
         // Set the resume value for the await expression to consume
@@ -123,6 +166,37 @@ impl VM {
 
         true
     }
+
+    /// Keep `result` if a variable holds a handle to `execution_id`
+    ///
+    /// Results no variable holds a handle to any more are dropped, so the
+    /// state doesn't grow with every task a loop awaits.
+    fn settle(&mut self, execution_id: String, result: &Val) {
+        let mut handles = HashSet::new();
+        for val in self.env.values() {
+            collect_execution_handles(val, &mut handles);
+        }
+        self.settled.retain(|id, _| handles.contains(id.as_str()));
+        if handles.contains(execution_id.as_str()) {
+            self.settled.insert(execution_id, result.clone());
+        }
+    }
+}
+
+/// IDs of the executions `val` holds handles to
+fn collect_execution_handles<'a>(val: &'a Val, handles: &mut HashSet<&'a str>) {
+    match val {
+        Val::Promise(Awaitable::Execution(id)) => {
+            handles.insert(id);
+        }
+        Val::List(items) => items
+            .iter()
+            .for_each(|item| collect_execution_handles(item, handles)),
+        Val::Obj(map) => map
+            .values()
+            .for_each(|item| collect_execution_handles(item, handles)),
+        _ => {}
+    }
 }
 
 /* ===================== Frame Management ===================== */
@@ -136,6 +210,10 @@ pub fn push_stmt(vm: &mut VM, stmt: &Stmt) {
             phase: ReturnPhase::Eval,
         },
 
+        Stmt::Throw { .. } => FrameKind::Throw {
+            phase: ThrowPhase::Eval,
+        },
+
         Stmt::Block { .. } => FrameKind::Block {
             phase: BlockPhase::Execute,
             idx: 0,
@@ -0,0 +1,390 @@
+//! In-memory workflow runner
+//!
+//! Drives the VM the way the worker does, but settles awaitables from the
+//! test case instead of the database: tasks and child workflows resolve
+//! to their stubs, timers fire at once, task streams end with no chunks, and
+//! signals take the next stubbed payload for their name. Anything without a stub never settles, so a
+//! workflow that awaits it fails the case at that await.
+
+use anyhow::Result;
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, VecDeque};
+
+use super::{Stub, TestCase};
+use crate::executor::compensation::needs_unwind;
+use crate::executor::errors::ErrorInfo;
+use crate::executor::types::ast::Span;
+use crate::executor::{
+    json_to_val, json_to_val_map, step, Awaitable, Control, Val, WorkflowContext, VM,
+};
+use crate::parser::WorkflowDef;
+
+/// Steps a case may take before it is treated as a runaway loop
+const MAX_STEPS: usize = 1_000_000;
+
+/// A child execution the workflow started
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub execution_id: String,
+    pub target_name: String,
+    pub inputs: JsonValue,
+    /// Index of the stub in `TestCase::tasks` that answered it
+    pub stub: Option<usize>,
+    /// Statement that started it
+    pub span: Option<Span>,
+}
+
+/// How a workflow run ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunOutcome {
+    Returned(JsonValue),
+    Threw(JsonValue),
+    /// Awaiting something that will never settle
+    Stuck(String),
+    /// Ran out of steps
+    Runaway,
+}
+
+/// A point where the workflow suspended, as it would between worker runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suspension {
+    pub awaitable: Awaitable,
+    /// Statement that awaited
+    pub span: Option<Span>,
+}
+
+/// A finished run: its outcome, where it ended, and what it started
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub outcome: RunOutcome,
+    /// Statement that returned, threw, or is stuck
+    pub span: Option<Span>,
+    pub calls: Vec<Call>,
+    pub suspensions: Vec<Suspension>,
+}
+
+/// Settlement of one awaitable
+pub(crate) enum Status {
+    Success(Val),
+    Error(Val),
+    /// Will never settle, and why
+    Never(String),
+}
+
+/// Stubs and what the workflow started, settling its awaitables
+pub(crate) struct Harness {
+    stubs: HashMap<String, VecDeque<(usize, Stub)>>,
+    /// Stubs added so far, numbering the next one
+    stub_count: usize,
+    signals: HashMap<String, VecDeque<JsonValue>>,
+    /// Outcome of children without a stub; without it they never settle
+    unstubbed: Option<Val>,
+    /// Child execution id -> its stubbed outcome, if it had one
+    executions: HashMap<String, Result<Val, Val>>,
+    /// Child execution id -> target name, for messages
+    targets: HashMap<String, String>,
+    /// Signal claim id -> signal name, and the payload once delivered
+    claims: HashMap<String, String>,
+    delivered: HashMap<String, Val>,
+    calls: Vec<Call>,
+    suspensions: Vec<Suspension>,
+}
+
+/// Run `workflow` with the case's inputs and stubs
+pub fn run(workflow: &WorkflowDef, case: &TestCase) -> Result<Run> {
+    let mut harness = Harness::new(case);
+    let context = WorkflowContext {
+        execution_id: "test".to_string(),
+    };
+    let mut vm = VM::new(
+        workflow.body.clone(),
+        json_to_val_map(&case.inputs)?,
+        context,
+    );
+
+    let mut steps = 0;
+    let mut settled_at = None;
+    loop {
+        while (!vm.frames.is_empty() || needs_unwind(&vm))
+            && !matches!(vm.control, Control::Suspend(_))
+        {
+            if steps >= MAX_STEPS {
+                return Ok(harness.finish(RunOutcome::Runaway, current_span(&vm)));
+            }
+            let span = current_span(&vm);
+            step(&mut vm);
+            steps += 1;
+            if !vm.outbox.executions.is_empty() {
+                harness.collect(&mut vm, span)?;
+            }
+
+            // Remember where a return or throw started, before unwinding
+            // pops the statement. Running compensations keeps it.
+            match vm.control {
+                _ if span.is_none() => {}
+                Control::Return(_) | Control::Throw(_) => settled_at = settled_at.or(span),
+                _ => settled_at = None,
+            }
+        }
+        harness.collect_signals(&mut vm);
+
+        let Control::Suspend(awaitable) = &vm.control else {
+            break;
+        };
+        let awaitable = awaitable.clone();
+        harness.suspensions.push(Suspension {
+            awaitable: awaitable.clone(),
+            span: current_span(&vm),
+        });
+        match harness.resolve(&awaitable) {
+            // The worker resumes with a failed child's error value too
+            Status::Success(val) | Status::Error(val) => {
+                vm.resume(val);
+            }
+            Status::Never(reason) => {
+                let span = current_span(&vm);
+                return Ok(harness.finish(RunOutcome::Stuck(reason), span));
+            }
+        }
+    }
+
+    let outcome = match &vm.control {
+        Control::Return(val) => RunOutcome::Returned(crate::executor::val_to_json(val)?),
+        Control::None => RunOutcome::Returned(JsonValue::Null),
+        Control::Throw(val) => RunOutcome::Threw(crate::executor::val_to_json(val)?),
+        control => anyhow::bail!("Unexpected control state at top level: {:?}", control),
+    };
+    Ok(harness.finish(outcome, settled_at))
+}
+
+fn current_span(vm: &VM) -> Option<Span> {
+    vm.frames.last().map(|frame| frame.node.span())
+}
+
+impl Harness {
+    pub(crate) fn new(case: &TestCase) -> Self {
+        let mut harness = Self {
+            stubs: HashMap::new(),
+            stub_count: 0,
+            signals: HashMap::new(),
+            unstubbed: None,
+            executions: HashMap::new(),
+            targets: HashMap::new(),
+            claims: HashMap::new(),
+            delivered: HashMap::new(),
+            calls: Vec::new(),
+            suspensions: Vec::new(),
+        };
+        for stub in &case.tasks {
+            harness.add_stub(stub.clone());
+        }
+        for (name, payloads) in &case.signals {
+            for payload in payloads {
+                harness.add_signal(name, payload.clone());
+            }
+        }
+        harness
+    }
+
+    /// Settle children without a stub with `value` instead of never
+    pub(crate) fn with_unstubbed(mut self, value: Val) -> Self {
+        self.unstubbed = Some(value);
+        self
+    }
+
+    /// Answer calls to `stub.name` started from now on, after its earlier stubs
+    pub(crate) fn add_stub(&mut self, stub: Stub) {
+        self.stubs
+            .entry(stub.name.clone())
+            .or_default()
+            .push_back((self.stub_count, stub));
+        self.stub_count += 1;
+    }
+
+    /// Deliver `payload` to the next wait for signal `name`
+    pub(crate) fn add_signal(&mut self, name: &str, payload: JsonValue) {
+        self.signals
+            .entry(name.to_string())
+            .or_default()
+            .push_back(payload);
+    }
+
+    pub(crate) fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    /// Answer the executions started by the statement at `span`
+    pub(crate) fn collect(&mut self, vm: &mut VM, span: Option<Span>) -> Result<()> {
+        for creation in vm.outbox.executions.drain(..) {
+            let inputs = crate::executor::val_map_to_json(&creation.inputs)?;
+            let stub = self.next_stub(&creation.target_name);
+            if let Some((_, stub)) = &stub {
+                let outcome = match &stub.error {
+                    Some(error) => Err(json_to_val(error)?),
+                    None => Ok(json_to_val(
+                        stub.result.as_ref().unwrap_or(&JsonValue::Null),
+                    )?),
+                };
+                self.executions.insert(creation.id.clone(), outcome);
+            } else if let Some(value) = &self.unstubbed {
+                self.executions
+                    .insert(creation.id.clone(), Ok(value.clone()));
+            }
+            self.targets
+                .insert(creation.id.clone(), creation.target_name.clone());
+            self.calls.push(Call {
+                execution_id: creation.id.clone(),
+                target_name: creation.target_name,
+                inputs,
+                stub: stub.map(|(index, _)| index),
+                span,
+            });
+        }
+        Ok(())
+    }
+
+    /// Note signal requests; timers need nothing, as they fire at once
+    pub(crate) fn collect_signals(&mut self, vm: &mut VM) {
+        for request in vm.outbox.signals.drain(..) {
+            self.claims.insert(request.claim_id, request.signal_name);
+        }
+        vm.outbox.timers.clear();
+    }
+
+    /// Stubs for a target are used in order; the last one repeats
+    fn next_stub(&mut self, target_name: &str) -> Option<(usize, Stub)> {
+        let queue = self.stubs.get_mut(target_name)?;
+        if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        }
+    }
+
+    pub(crate) fn resolve(&mut self, awaitable: &Awaitable) -> Status {
+        match awaitable {
+            Awaitable::Execution(id) => match self.executions.get(id) {
+                Some(Ok(val)) => Status::Success(val.clone()),
+                Some(Err(val)) => Status::Error(val.clone()),
+                None => Status::Never(format!(
+                    "'{}' has no stub",
+                    self.targets.get(id).map_or(id.as_str(), String::as_str)
+                )),
+            },
+            Awaitable::Timer { .. } => Status::Success(Val::Null),
+            Awaitable::Stream { execution_id, from } => {
+                match self.resolve(&Awaitable::Execution(execution_id.clone())) {
+                    Status::Success(_) => Status::Success(Val::Obj(HashMap::from([
+                        ("chunks".to_string(), Val::List(vec![])),
+                        ("next".to_string(), Val::Num(*from as f64)),
+                        ("done".to_string(), Val::Bool(true)),
+                    ]))),
+                    settled => settled,
+                }
+            }
+            Awaitable::Signal { name, claim_id } => self.resolve_signal(name, claim_id),
+            Awaitable::All { items, is_object } => {
+                let mut values = Vec::new();
+                for (key, item) in items {
+                    match self.resolve(item) {
+                        Status::Success(val) => values.push((key.clone(), val)),
+                        // Fail fast on the first error, like Promise.all
+                        settled => return settled,
+                    }
+                }
+                Status::Success(if *is_object {
+                    Val::Obj(values.into_iter().collect())
+                } else {
+                    Val::List(values.into_iter().map(|(_, val)| val).collect())
+                })
+            }
+            Awaitable::Any {
+                items,
+                is_object,
+                with_kv,
+            } => {
+                let mut never = None;
+                for (key, item) in items {
+                    match self.resolve(item) {
+                        Status::Success(val) => {
+                            return Status::Success(winner(key, val, *is_object, *with_kv));
+                        }
+                        Status::Error(_) => {}
+                        Status::Never(reason) => never = never.or(Some(reason)),
+                    }
+                }
+                match never {
+                    Some(reason) => Status::Never(reason),
+                    None => Status::Error(Val::Error(ErrorInfo::new(
+                        "AggregateError",
+                        "All promises rejected",
+                    ))),
+                }
+            }
+            Awaitable::Race {
+                items,
+                is_object,
+                with_kv,
+            } => {
+                let mut never = None;
+                for (key, item) in items {
+                    match self.resolve(item) {
+                        Status::Success(val) => {
+                            return Status::Success(winner(key, val, *is_object, *with_kv));
+                        }
+                        Status::Error(val) => return Status::Error(val),
+                        Status::Never(reason) => never = never.or(Some(reason)),
+                    }
+                }
+                Status::Never(never.unwrap_or_else(|| "empty race".to_string()))
+            }
+        }
+    }
+
+    fn resolve_signal(&mut self, name: &str, claim_id: &str) -> Status {
+        if let Some(val) = self.delivered.get(claim_id) {
+            return Status::Success(val.clone());
+        }
+        let name = self.claims.get(claim_id).map_or(name, String::as_str);
+        let payload = self
+            .signals
+            .get_mut(name)
+            .and_then(VecDeque::pop_front)
+            .map(|payload| json_to_val(&payload));
+        match payload {
+            Some(Ok(val)) => {
+                self.delivered.insert(claim_id.to_string(), val.clone());
+                Status::Success(val)
+            }
+            Some(Err(e)) => Status::Never(format!("signal '{}' payload is invalid: {}", name, e)),
+            None => Status::Never(format!("signal '{}' has no stubbed payload left", name)),
+        }
+    }
+
+    fn finish(self, outcome: RunOutcome, span: Option<Span>) -> Run {
+        Run {
+            outcome,
+            span,
+            calls: self.calls,
+            suspensions: self.suspensions,
+        }
+    }
+}
+
+/// The value Promise.any/race settle with: the value, or `{ key, value }`
+fn winner(key: &str, value: Val, is_object: bool, with_kv: bool) -> Val {
+    if !with_kv {
+        return value;
+    }
+    let key = if is_object {
+        Val::Str(key.to_string())
+    } else {
+        key.parse::<f64>()
+            .map(Val::Num)
+            .unwrap_or_else(|_| Val::Str(key.to_string()))
+    };
+    Val::Obj(HashMap::from([
+        ("key".to_string(), key),
+        ("value".to_string(), value),
+    ]))
+}
@@ -0,0 +1,301 @@
+//! Workflow unit tests
+//!
+//! A `<name>.flow.test` file sits next to `<name>.flow` and describes cases
+//! that run the workflow in memory, with no database or workers: tasks and
+//! child workflows are stubbed, timers fire at once, and signals are fed
+//! from the case. `rhythm test` runs every test file under a directory.
+//!
+//! ```toml
+//! # Defaults to this file's name without `.test`
+//! workflow = "refund.flow"
+//!
+//! [[cases]]
+//! name = "refunds the full amount"
+//! inputs = { order_id = "o1" }
+//! expect_output = { refunded = 100 }
+//! expect_calls = ["lookup_order", "issue_refund"]
+//!
+//! [[cases.tasks]]
+//! name = "lookup_order"
+//! inputs = { order_id = "o1" }
+//! result = { amount = 100 }
+//!
+//! [[cases.tasks]]
+//! name = "issue_refund"
+//! result = { refunded = 100 }
+//!
+//! [[cases]]
+//! name = "skips unknown orders"
+//! inputs = { order_id = "missing" }
+//! expect_output = { refunded = 0 }
+//! expect_calls = ["lookup_order"]
+//!
+//! [[cases.tasks]]
+//! name = "lookup_order"
+//! error = { code = "NOT_FOUND", message = "no such order" }
+//! ```
+//!
+//! As on a worker, a stubbed `error` is what the workflow's `await` returns.
+//! `expect_error` matches the code of an error the workflow itself throws,
+//! such as `TYPE_ERROR`. Several stubs for one name are used in call order,
+//! the last repeating. Signals are stubbed per name, e.g.
+//! `signals = { approval = [{ ok = true }] }`.
+
+pub mod harness;
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::executor::json_to_val;
+use crate::executor::types::ast::Span;
+use crate::parser::parse_workflow;
+use harness::RunOutcome;
+
+/// Suffix test files are discovered by
+pub const TEST_FILE_SUFFIX: &str = ".flow.test";
+
+/// A parsed `.flow.test` file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestFile {
+    /// Workflow under test, relative to the test file
+    pub workflow: Option<PathBuf>,
+    #[serde(default)]
+    pub cases: Vec<TestCase>,
+}
+
+/// One run of the workflow and what it should do
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    pub name: String,
+    #[serde(default = "empty_object")]
+    pub inputs: JsonValue,
+    /// Stubbed tasks and child workflows
+    #[serde(default)]
+    pub tasks: Vec<Stub>,
+    /// Payloads delivered to each signal name, in order
+    #[serde(default)]
+    pub signals: HashMap<String, Vec<JsonValue>>,
+    /// Value the workflow returns
+    pub expect_output: Option<JsonValue>,
+    /// Code of the error the workflow throws
+    pub expect_error: Option<String>,
+    /// Names of the tasks and workflows started, in order
+    pub expect_calls: Option<Vec<String>>,
+}
+
+/// Canned outcome for a task or child workflow
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stub {
+    pub name: String,
+    /// Inputs the call must be made with
+    pub inputs: Option<JsonValue>,
+    pub result: Option<JsonValue>,
+    /// Fails the call with this error instead of returning `result`
+    pub error: Option<JsonValue>,
+}
+
+fn empty_object() -> JsonValue {
+    JsonValue::Object(Default::default())
+}
+
+/// Why a case failed, and where in the workflow
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+/// Result of one case
+#[derive(Debug, Clone)]
+pub struct CaseReport {
+    pub name: String,
+    pub failure: Option<Failure>,
+}
+
+/// Results for one test file
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub workflow: PathBuf,
+    /// The workflow failed to load, so no case ran
+    pub error: Option<Failure>,
+    pub cases: Vec<CaseReport>,
+}
+
+impl FileReport {
+    pub fn passed(&self) -> usize {
+        self.cases.iter().filter(|c| c.failure.is_none()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.cases.len() - self.passed()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none() && self.failed() == 0
+    }
+}
+
+/// Find test files at `path`: the file itself, or every one under a directory
+pub fn discover(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut found = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(TEST_FILE_SUFFIX))
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Load a test file and run its cases against its workflow
+pub fn run_file(path: &Path) -> Result<FileReport> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read test file: {}", path.display()))?;
+    let file: TestFile = toml::from_str(&text)
+        .with_context(|| format!("Failed to parse test file: {}", path.display()))?;
+
+    let workflow = match &file.workflow {
+        Some(workflow) => path.with_file_name(workflow),
+        None => {
+            let name = path.to_string_lossy();
+            PathBuf::from(name.strip_suffix(".test").unwrap_or(&name))
+        }
+    };
+    let source = std::fs::read_to_string(&workflow)
+        .with_context(|| format!("Failed to read workflow: {}", workflow.display()))?;
+
+    let mut report = FileReport {
+        path: path.to_path_buf(),
+        workflow,
+        error: None,
+        cases: Vec::new(),
+    };
+    let def = match parse_workflow(&source) {
+        Ok(def) => def,
+        Err(e) => {
+            report.error = Some(Failure {
+                message: e.message().to_string(),
+                span: e.span(),
+            });
+            return Ok(report);
+        }
+    };
+
+    for case in &file.cases {
+        report.cases.push(CaseReport {
+            name: case.name.clone(),
+            failure: run_case(&def, case)?,
+        });
+    }
+    Ok(report)
+}
+
+/// Run one case, returning why it failed
+pub fn run_case(workflow: &crate::parser::WorkflowDef, case: &TestCase) -> Result<Option<Failure>> {
+    // A VM panic fails this case, as it would fail the execution on a worker
+    let run = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        harness::run(workflow, case)
+    })) {
+        Ok(run) => run?,
+        Err(payload) => {
+            return Ok(Some(Failure {
+                message: format!(
+                    "workflow panicked: {}",
+                    crate::worker::runner::panic_message(payload.as_ref())
+                ),
+                span: None,
+            }));
+        }
+    };
+    let fail = |message: String| {
+        Ok(Some(Failure {
+            message,
+            span: run.span,
+        }))
+    };
+
+    match (&run.outcome, &case.expect_error) {
+        (RunOutcome::Stuck(reason), _) => {
+            return fail(format!("workflow never finishes: {}", reason))
+        }
+        (RunOutcome::Runaway, _) => {
+            return fail("workflow did not finish within the step limit".to_string())
+        }
+        (RunOutcome::Returned(output), Some(code)) => {
+            return fail(format!("expected error {}, but returned {}", code, output));
+        }
+        (RunOutcome::Threw(error), None) => return fail(format!("threw {}", error)),
+        (RunOutcome::Threw(error), Some(code)) => {
+            if crate::worker::retry::error_code(error) != Some(code.as_str()) {
+                return fail(format!("expected error {}, but threw {}", code, error));
+            }
+        }
+        (RunOutcome::Returned(output), None) => {
+            if let Some(expected) = &case.expect_output {
+                // Compare as the VM sees values, so 1 and 1.0 are equal
+                let expected = crate::executor::val_to_json(&json_to_val(expected)?)?;
+                if *output != expected {
+                    return fail(format!("expected output {}, got {}", expected, output));
+                }
+            }
+        }
+    }
+
+    let names: Vec<&str> = run.calls.iter().map(|c| c.target_name.as_str()).collect();
+    if let Some(expected) = &case.expect_calls {
+        if names != *expected {
+            return fail(format!(
+                "expected calls [{}], got [{}]",
+                expected.join(", "),
+                names.join(", ")
+            ));
+        }
+    }
+
+    for call in &run.calls {
+        let Some(expected) = call
+            .stub
+            .and_then(|index| case.tasks[index].inputs.as_ref())
+        else {
+            continue;
+        };
+        let expected = crate::executor::val_to_json(&json_to_val(expected)?)?;
+        if call.inputs != expected {
+            return Ok(Some(Failure {
+                message: format!(
+                    "'{}' expected inputs {}, got {}",
+                    call.target_name, expected, call.inputs
+                ),
+                span: call.span,
+            }));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,218 @@
+use super::harness::{self, RunOutcome};
+use super::*;
+use serde_json::json;
+
+fn workflow(source: &str) -> crate::parser::WorkflowDef {
+    parse_workflow(source).unwrap()
+}
+
+fn case(toml_case: &str) -> TestCase {
+    toml::from_str(toml_case).unwrap()
+}
+
+const REFUND: &str = r#"
+let order = await Task.run("lookup_order", { id: Inputs.order_id })
+if (order.amount == 0) {
+    return { refunded: 0 }
+}
+let refund = await Task.run("issue_refund", { amount: order.amount })
+return { refunded: refund.amount }
+"#;
+
+#[test]
+fn test_stubs_answer_tasks_in_order() {
+    let case = case(
+        r#"
+        name = "refunds"
+        inputs = { order_id = "o1" }
+        expect_output = { refunded = 100 }
+        expect_calls = ["lookup_order", "issue_refund"]
+
+        [[tasks]]
+        name = "lookup_order"
+        inputs = { id = "o1" }
+        result = { amount = 100 }
+
+        [[tasks]]
+        name = "issue_refund"
+        result = { amount = 100 }
+        "#,
+    );
+
+    assert_eq!(run_case(&workflow(REFUND), &case).unwrap(), None);
+}
+
+#[test]
+fn test_stubbed_error_is_the_await_result() {
+    let def = workflow(
+        r#"
+        let order = await Task.run("lookup_order", {})
+        return order.code
+        "#,
+    );
+    let case = case(
+        r#"
+        name = "unknown order"
+        expect_output = "NOT_FOUND"
+
+        [[tasks]]
+        name = "lookup_order"
+        error = { code = "NOT_FOUND", message = "no such order" }
+        "#,
+    );
+
+    assert_eq!(run_case(&def, &case).unwrap(), None);
+}
+
+#[test]
+fn test_awaiting_unstubbed_task_fails_at_the_await() {
+    let case = case(
+        r#"
+        name = "missing stub"
+        inputs = { order_id = "o1" }
+
+        [[tasks]]
+        name = "lookup_order"
+        result = { amount = 5 }
+        "#,
+    );
+
+    let failure = run_case(&workflow(REFUND), &case).unwrap().unwrap();
+    assert_eq!(
+        failure.message,
+        "workflow never finishes: 'issue_refund' has no stub"
+    );
+    assert_eq!(failure.span.unwrap().start_line, 5);
+}
+
+#[test]
+fn test_mismatches_are_reported() {
+    let def = workflow(REFUND);
+    let failure = run_case(
+        &def,
+        &case(
+            r#"
+            name = "wrong output"
+            inputs = { order_id = "o1" }
+            expect_output = { refunded = 1 }
+            tasks = [{ name = "lookup_order", result = { amount = 2 } }, { name = "issue_refund", result = { amount = 2 } }]
+            "#,
+        ),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        failure.message,
+        r#"expected output {"refunded":1.0}, got {"refunded":2.0}"#
+    );
+    assert_eq!(failure.span.unwrap().start_line, 6);
+
+    let failure = run_case(
+        &def,
+        &case(
+            r#"
+            name = "wrong inputs"
+            inputs = { order_id = "o2" }
+            tasks = [{ name = "lookup_order", inputs = { id = "o1" }, result = { amount = 0 } }]
+            "#,
+        ),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        failure.message,
+        r#"'lookup_order' expected inputs {"id":"o1"}, got {"id":"o2"}"#
+    );
+    assert_eq!(failure.span.unwrap().start_line, 1);
+}
+
+#[test]
+fn test_expect_error_matches_thrown_code() {
+    let def = workflow("let x = null\nreturn x.y");
+
+    assert_eq!(
+        run_case(&def, &case("name = 'throws'\nexpect_error = 'TYPE_ERROR'")).unwrap(),
+        None
+    );
+    let failure = run_case(&def, &case("name = 'throws'")).unwrap().unwrap();
+    assert!(failure.message.starts_with("threw "), "{}", failure.message);
+    assert_eq!(failure.span.unwrap().start_line, 1);
+}
+
+#[test]
+fn test_composites_timers_and_signals_settle_from_stubs() {
+    let def = workflow(
+        r#"
+        await Timer.delay(60)
+        let approval = await Signal.next("approval")
+        let results = await Promise.all([Task.run("a", {}), Task.run("b", {})])
+        return { approved: approval.ok, results: results }
+        "#,
+    );
+    let case = case(
+        r#"
+        name = "composite"
+        signals = { approval = [{ ok = true }] }
+        tasks = [{ name = "a", result = 1 }, { name = "b", result = 2 }]
+        "#,
+    );
+
+    let run = harness::run(&def, &case).unwrap();
+    assert_eq!(
+        run.outcome,
+        RunOutcome::Returned(json!({"approved": true, "results": [1.0, 2.0]}))
+    );
+
+    let no_signal = TestCase {
+        signals: HashMap::new(),
+        ..case
+    };
+    let run = harness::run(&def, &no_signal).unwrap();
+    assert_eq!(
+        run.outcome,
+        RunOutcome::Stuck("signal 'approval' has no stubbed payload left".to_string())
+    );
+}
+
+#[test]
+fn test_runaway_loop_is_stopped() {
+    let def = workflow("while (true) {}");
+    let failure = run_case(&def, &case("name = 'loop'")).unwrap().unwrap();
+    assert_eq!(
+        failure.message,
+        "workflow did not finish within the step limit"
+    );
+}
+
+#[test]
+fn test_run_file_resolves_workflow_next_to_test_file() {
+    let dir = std::env::temp_dir().join(format!("rhythm-flow-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(dir.join("nested/double.flow"), "return Inputs.n * 2").unwrap();
+    std::fs::write(
+        dir.join("nested/double.flow.test"),
+        r#"
+        [[cases]]
+        name = "doubles"
+        inputs = { n = 2 }
+        expect_output = 4
+
+        [[cases]]
+        name = "wrong"
+        inputs = { n = 2 }
+        expect_output = 5
+        "#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("ignored.flow"), "return 1").unwrap();
+
+    let files = discover(&dir).unwrap();
+    assert_eq!(files, vec![dir.join("nested/double.flow.test")]);
+
+    let report = run_file(&files[0]).unwrap();
+    assert_eq!(report.workflow, dir.join("nested/double.flow"));
+    assert_eq!((report.passed(), report.failed()), (1, 1));
+    assert!(!report.is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
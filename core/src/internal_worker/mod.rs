@@ -1,23 +1,41 @@
 //! Internal Worker
 //!
 //! Background worker that handles internal maintenance tasks like
-//! promoting scheduled work to the ready queue.
+//! promoting scheduled work to the ready queue as it falls due (see
+//! `timers`), expiring work whose TTL ran out, reaping tasks whose worker
+//! stopped heartbeating, watching SLOs, recording payload shapes, and
+//! sending webhook deliveries.
+
+mod timer_wheel;
+mod timers;
 
 use std::time::Duration;
+use tokio::time::MissedTickBehavior;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
-use crate::services::SchedulerService;
+use crate::services::{SchedulerService, SchemaService, SloService, WebhookService, WorkerService};
+use timers::Timers;
 
 #[cfg(test)]
 mod tests;
 
 const POLL_INTERVAL: Duration = Duration::from_millis(1000);
 const BATCH_SIZE: i32 = 100;
+const SLO_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const SCHEMA_ANALYZE_INTERVAL: Duration = Duration::from_secs(30);
+const SCHEMA_BATCH_SIZE: i32 = 1000;
+const REAP_INTERVAL: Duration = Duration::from_secs(15);
+const WEBHOOK_INTERVAL: Duration = Duration::from_secs(1);
+const WEBHOOK_BATCH_SIZE: i64 = 50;
 
 /// Internal worker that handles background maintenance tasks.
 pub struct InternalWorker {
     scheduler_service: SchedulerService,
+    slo_service: Option<SloService>,
+    schema_service: Option<SchemaService>,
+    reaper: Option<WorkerService>,
+    webhook_service: Option<WebhookService>,
     shutdown_token: CancellationToken,
 }
 
@@ -26,38 +44,108 @@ impl InternalWorker {
     pub fn new(scheduler_service: SchedulerService, shutdown_token: CancellationToken) -> Self {
         Self {
             scheduler_service,
+            slo_service: None,
+            schema_service: None,
+            reaper: None,
+            webhook_service: None,
             shutdown_token,
         }
     }
 
+    /// Also evaluate SLOs periodically, warning about any that are missed
+    pub fn with_slo_monitor(mut self, slo_service: SloService) -> Self {
+        if !slo_service.slos().is_empty() {
+            self.slo_service = Some(slo_service);
+        }
+        self
+    }
+
+    /// Also record the payload shapes of completed executions, warning
+    /// about drift
+    pub fn with_schema_analyzer(mut self, schema_service: SchemaService) -> Self {
+        self.schema_service = Some(schema_service);
+        self
+    }
+
+    /// Also reclaim or fail running tasks that missed their heartbeats,
+    /// under `worker_service`'s reaper policy
+    pub fn with_reaper(mut self, worker_service: WorkerService) -> Self {
+        self.reaper = Some(worker_service);
+        self
+    }
+
+    /// Also send the webhook deliveries that are due
+    pub fn with_webhooks(mut self, webhook_service: WebhookService) -> Self {
+        self.webhook_service = Some(webhook_service);
+        self
+    }
+
     /// Run the internal worker loop.
     ///
     /// This loop runs continuously until the shutdown token is cancelled.
     /// It handles internal maintenance tasks like promoting scheduled work.
     pub async fn run(self) {
+        let mut timers = Timers::new(self.scheduler_service.clone());
+        let mut refill_timers = tokio::time::interval(timers::REFILL_INTERVAL);
+        refill_timers.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        let mut slo_check = tokio::time::interval(SLO_CHECK_INTERVAL);
+        let mut schema_analyze = tokio::time::interval(SCHEMA_ANALYZE_INTERVAL);
+        let mut reap = tokio::time::interval(REAP_INTERVAL);
+        let mut deliver_webhooks = tokio::time::interval(WEBHOOK_INTERVAL);
         loop {
+            let next_timer = timers.next_due();
             tokio::select! {
                 _ = self.shutdown_token.cancelled() => {
                     debug!("Internal worker received shutdown signal");
                     break;
                 }
-                _ = tokio::time::sleep(POLL_INTERVAL) => {
-                    if let Err(e) = self.process_scheduled_work().await {
+                _ = refill_timers.tick() => {
+                    if let Err(e) = timers.refill().await {
+                        error!("Error loading scheduled work: {}", e);
+                    }
+                }
+                _ = tokio::time::sleep_until(next_timer.unwrap_or_else(tokio::time::Instant::now)),
+                    if next_timer.is_some() =>
+                {
+                    if let Err(e) = Self::fire_scheduled_work(&mut timers).await {
                         error!("Error processing scheduled work: {}", e);
                     }
                 }
+                _ = poll.tick() => {
+                    if let Err(e) = self.expire_unclaimed_work().await {
+                        error!("Error expiring unclaimed work: {}", e);
+                    }
+                }
+                _ = slo_check.tick(), if self.slo_service.is_some() => {
+                    if let Err(e) = self.check_slos().await {
+                        error!("Error evaluating SLOs: {}", e);
+                    }
+                }
+                _ = reap.tick(), if self.reaper.is_some() => {
+                    if let Err(e) = self.reap_stale_tasks().await {
+                        error!("Error reaping stale tasks: {}", e);
+                    }
+                }
+                _ = schema_analyze.tick(), if self.schema_service.is_some() => {
+                    if let Err(e) = self.analyze_payload_shapes().await {
+                        error!("Error analyzing payload shapes: {}", e);
+                    }
+                }
+                _ = deliver_webhooks.tick(), if self.webhook_service.is_some() => {
+                    if let Err(e) = self.deliver_webhooks().await {
+                        error!("Error delivering webhooks: {}", e);
+                    }
+                }
             }
         }
 
         debug!("Internal worker stopped");
     }
 
-    /// Process ready items from the scheduled queue.
-    async fn process_scheduled_work(&self) -> anyhow::Result<()> {
-        let count = self
-            .scheduler_service
-            .process_ready_items(BATCH_SIZE)
-            .await?;
+    /// Process the scheduled items that have fallen due.
+    async fn fire_scheduled_work(timers: &mut Timers) -> anyhow::Result<()> {
+        let count = timers.fire_due().await?;
 
         if count > 0 {
             debug!("Promoted {} scheduled items to work queue", count);
@@ -65,4 +153,82 @@ impl InternalWorker {
 
         Ok(())
     }
+
+    /// Expire pending executions whose TTL has run out.
+    async fn expire_unclaimed_work(&self) -> anyhow::Result<()> {
+        let expired = self.scheduler_service.expire_unclaimed(BATCH_SIZE).await?;
+
+        if !expired.is_empty() {
+            debug!("Expired {} unclaimed executions", expired.len());
+        }
+
+        Ok(())
+    }
+
+    /// Reclaim or fail running tasks whose heartbeat is too old
+    async fn reap_stale_tasks(&self) -> anyhow::Result<()> {
+        let Some(worker_service) = &self.reaper else {
+            return Ok(());
+        };
+
+        let reaped = worker_service.reap_stale_tasks(BATCH_SIZE as i64).await?;
+        if !reaped.reclaimed.is_empty() || !reaped.failed.is_empty() {
+            debug!(
+                "Reaped stale tasks: {} reclaimed, {} failed",
+                reaped.reclaimed.len(),
+                reaped.failed.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Record the payload shapes of recently completed executions
+    async fn analyze_payload_shapes(&self) -> anyhow::Result<()> {
+        let Some(schema_service) = &self.schema_service else {
+            return Ok(());
+        };
+
+        let observed = schema_service.analyze(SCHEMA_BATCH_SIZE).await?;
+        if observed > 0 {
+            debug!("Recorded payload shapes of {} executions", observed);
+        }
+
+        Ok(())
+    }
+
+    /// Send the webhook deliveries that are due
+    async fn deliver_webhooks(&self) -> anyhow::Result<()> {
+        let Some(webhook_service) = &self.webhook_service else {
+            return Ok(());
+        };
+
+        let delivered = webhook_service.deliver_due(WEBHOOK_BATCH_SIZE).await?;
+        if delivered > 0 {
+            debug!("Delivered {} webhook notifications", delivered);
+        }
+
+        Ok(())
+    }
+
+    /// Warn about SLOs currently below their objective
+    async fn check_slos(&self) -> anyhow::Result<()> {
+        let Some(slo_service) = &self.slo_service else {
+            return Ok(());
+        };
+
+        for status in slo_service.evaluate().await? {
+            if !status.met {
+                warn!(
+                    slo = %status.slo.name,
+                    compliance = status.compliance,
+                    objective = status.slo.objective,
+                    error_budget_remaining = status.error_budget_remaining,
+                    "Claim latency SLO missed"
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -42,6 +42,7 @@ async fn test_internal_worker_processes_ready_items(pool: PgPool) -> anyhow::Res
         queue: "default".to_string(),
         inputs: json!({}),
         run_at: now_plus_seconds(-10),
+        namespace: None,
     };
     scheduler_service.schedule_execution(params).await?;
 
@@ -97,6 +98,7 @@ async fn test_internal_worker_skips_future_items(pool: PgPool) -> anyhow::Result
         queue: "default".to_string(),
         inputs: json!({}),
         run_at: now_plus_seconds(60), // 1 minute in future
+        namespace: None,
     };
     scheduler_service.schedule_execution(params).await?;
 
@@ -117,3 +119,35 @@ async fn test_internal_worker_skips_future_items(pool: PgPool) -> anyhow::Result
 
     Ok(())
 }
+
+#[sqlx::test]
+async fn test_internal_worker_fires_items_on_time(pool: PgPool) -> anyhow::Result<()> {
+    let scheduler_service = SchedulerService::new(pool.clone());
+    let shutdown_token = CancellationToken::new();
+
+    let worker = InternalWorker::new(scheduler_service.clone(), shutdown_token.clone());
+    let worker_handle = tokio::spawn(worker.run());
+
+    // Scheduled after the worker started, due well within a poll interval
+    let params = ScheduleExecutionParams {
+        exec_type: ExecutionType::Task,
+        target_name: "my_task".to_string(),
+        queue: "default".to_string(),
+        inputs: json!({}),
+        run_at: (Utc::now() + chrono::Duration::milliseconds(600)).naive_utc(),
+        namespace: None,
+    };
+    scheduler_service.schedule_execution(params).await?;
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(count_work_queue_items(&pool).await?, 0);
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(count_work_queue_items(&pool).await?, 1);
+    assert_eq!(count_scheduled_items(&pool).await?, 0);
+
+    shutdown_token.cancel();
+    worker_handle.await?;
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Hierarchical timing wheel
+//!
+//! Timers sit in `LEVELS` rings of `SLOTS` slots. A level-0 slot is one tick
+//! wide, and each slot of a higher level spans a whole ring of the level
+//! below. A timer goes in the lowest level whose current ring holds its due
+//! tick and drops a level each time the wheel reaches its slot, so adding
+//! and firing a timer cost the same however many are waiting. Timers due
+//! beyond the top ring wait in an overflow list until the wheel gets there.
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 4;
+
+/// Ticks in a full turn of the top ring
+const SPAN_BITS: u32 = SLOT_BITS * LEVELS as u32;
+
+pub(crate) struct TimerWheel<T> {
+    origin: Instant,
+    tick: Duration,
+    /// First tick not fired yet
+    now: u64,
+    levels: Vec<Vec<Vec<(u64, T)>>>,
+    overflow: Vec<(u64, T)>,
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    /// An empty wheel whose tick 0 is `origin`
+    pub fn new(origin: Instant, tick: Duration) -> Self {
+        Self {
+            origin,
+            tick,
+            now: 0,
+            levels: (0..LEVELS)
+                .map(|_| (0..SLOTS).map(|_| Vec::new()).collect())
+                .collect(),
+            overflow: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Add `item`, to fire on the first tick at or after `due`
+    ///
+    /// An item already due fires on the next `advance`.
+    pub fn insert(&mut self, due: Instant, item: T) {
+        let nanos = due.saturating_duration_since(self.origin).as_nanos();
+        let tick = nanos.div_ceil(self.tick.as_nanos()) as u64;
+        self.place(tick.max(self.now), item);
+        self.len += 1;
+    }
+
+    /// Take the items due at or before `to`, earliest first
+    pub fn advance(&mut self, to: Instant) -> Vec<T> {
+        let to =
+            (to.saturating_duration_since(self.origin).as_nanos() / self.tick.as_nanos()) as u64;
+        let mut fired = Vec::new();
+        // Jump from one occupied slot to the next; no ring boundary in
+        // between has anything to cascade
+        while let Some(next) = self.next_tick().filter(|&next| next <= to) {
+            self.move_to(next);
+            let slot = std::mem::take(&mut self.levels[0][slot_of(self.now, 0)]);
+            self.len -= slot.len();
+            fired.extend(slot.into_iter().map(|(_, item)| item));
+            self.move_to(next + 1);
+        }
+        self.move_to(self.now.max(to + 1));
+        fired
+    }
+
+    /// When the earliest item might be due: the start of the first occupied
+    /// slot, so the wheel should be advanced then and asked again
+    pub fn next_due(&self) -> Option<Instant> {
+        let tick = self.next_tick()?;
+        Some(self.origin + Duration::from_nanos((self.tick.as_nanos() as u64).saturating_mul(tick)))
+    }
+
+    fn next_tick(&self) -> Option<u64> {
+        if self.is_empty() {
+            return None;
+        }
+        for (level, slots) in self.levels.iter().enumerate() {
+            let shift = SLOT_BITS * level as u32;
+            // A higher level's current slot was emptied on entering it
+            let current = slot_of(self.now, level);
+            let first = if level == 0 { current } else { current + 1 };
+            if let Some(slot) = (first..SLOTS).find(|&slot| !slots[slot].is_empty()) {
+                let ring = (self.now >> (shift + SLOT_BITS)) << (shift + SLOT_BITS);
+                return Some(ring + ((slot as u64) << shift));
+            }
+        }
+        if self.overflow.is_empty() {
+            None
+        } else {
+            Some(((self.now >> SPAN_BITS) + 1) << SPAN_BITS)
+        }
+    }
+
+    fn place(&mut self, tick: u64, item: T) {
+        match level_of(self.now, tick) {
+            Some(level) => self.levels[level][slot_of(tick, level)].push((tick, item)),
+            None => self.overflow.push((tick, item)),
+        }
+    }
+
+    /// Set `now` to `tick`, no later than the next occupied slot, and move
+    /// the items of the slots starting there down a level
+    ///
+    /// So a higher level's current slot is always empty.
+    fn move_to(&mut self, tick: u64) {
+        self.now = tick;
+        let now = self.now;
+        let starts_ring = |bits: u32| now & ((1 << bits) - 1) == 0;
+        if starts_ring(SPAN_BITS) {
+            for (tick, item) in std::mem::take(&mut self.overflow) {
+                self.place(tick, item);
+            }
+        }
+        for level in (1..LEVELS).rev() {
+            if starts_ring(SLOT_BITS * level as u32) {
+                let slot = std::mem::take(&mut self.levels[level][slot_of(self.now, level)]);
+                for (tick, item) in slot {
+                    self.place(tick, item);
+                }
+            }
+        }
+    }
+}
+
+/// The level for an item due at `tick`: the highest digit it differs from
+/// `now` in, or `None` if that is beyond the top ring
+fn level_of(now: u64, tick: u64) -> Option<usize> {
+    let level = ((now ^ tick) | (SLOTS as u64 - 1)).ilog2() / SLOT_BITS;
+    Some(level as usize).filter(|&level| level < LEVELS)
+}
+
+fn slot_of(tick: u64, level: usize) -> usize {
+    ((tick >> (SLOT_BITS * level as u32)) as usize) & (SLOTS - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_fires_items_on_their_tick() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, TICK);
+        wheel.insert(origin + ms(25), "a");
+        wheel.insert(origin + ms(30), "b");
+        wheel.insert(origin + ms(500), "c");
+        assert_eq!(wheel.len(), 3);
+
+        // Due times round up to the next tick
+        assert!(wheel.advance(origin + ms(29)).is_empty());
+        assert_eq!(wheel.next_due(), Some(origin + ms(30)));
+        assert_eq!(wheel.advance(origin + ms(30)), ["a", "b"]);
+        assert!(wheel.advance(origin + ms(499)).is_empty());
+        assert_eq!(wheel.advance(origin + ms(510)), ["c"]);
+        assert!(wheel.is_empty());
+        assert_eq!(wheel.next_due(), None);
+    }
+
+    #[test]
+    fn test_items_cascade_down_from_higher_levels() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, TICK);
+        // Levels 1, 2 and 3 from the start
+        let dues = [ms(12_340), ms(600_000), ms(8 * 3_600_000 + 5)];
+        for (n, due) in dues.iter().enumerate() {
+            wheel.insert(origin + *due, n);
+        }
+
+        for (n, due) in dues.iter().enumerate() {
+            let on_time = origin + ms((due.as_millis() as u64).div_ceil(10) * 10);
+            // Waking at each lower bound it gives only gets closer
+            while let Some(next) = wheel.next_due().filter(|&next| next < on_time) {
+                assert!(wheel.advance(next).is_empty());
+            }
+            assert!(wheel.advance(on_time - ms(1)).is_empty());
+            assert_eq!(wheel.advance(on_time), [n]);
+        }
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn test_items_past_due_fire_on_next_advance() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, TICK);
+        assert!(wheel.advance(origin + ms(1_000)).is_empty());
+
+        wheel.insert(origin, "late");
+        assert_eq!(wheel.next_due(), Some(origin + ms(1_010)));
+        assert_eq!(wheel.advance(origin + ms(1_010)), ["late"]);
+    }
+
+    #[test]
+    fn test_items_beyond_the_top_ring_wait_in_overflow() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, Duration::from_millis(1));
+        // The top ring turns every 2^24 ms, about 4.7 hours
+        let due = origin + Duration::from_secs(6 * 3600);
+        wheel.insert(due, "far");
+        wheel.insert(origin + ms(5), "near");
+
+        assert_eq!(wheel.advance(origin + ms(5)), ["near"]);
+        assert_eq!(wheel.next_due(), Some(origin + ms(1 << 24)));
+        assert!(wheel.advance(due - ms(1)).is_empty());
+        assert_eq!(wheel.advance(due), ["far"]);
+    }
+
+    #[test]
+    fn test_random_timers_fire_exactly_on_time() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, Duration::from_millis(1));
+        let mut seed: u64 = 42;
+        let mut random = |below: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % below
+        };
+
+        let mut now = 0;
+        let mut pending = Vec::new();
+        for n in 0..5_000u64 {
+            // Spread over every level and the overflow
+            let reach = [100, 10_000, 1_000_000, 50_000_000][random(4) as usize];
+            let due = now + 1 + random(reach);
+            wheel.insert(origin + ms(due), n);
+            pending.push((due, n));
+
+            now += random(3_000);
+            let (due_now, rest): (Vec<_>, Vec<_>) =
+                pending.into_iter().partition(|&(due, _)| due <= now);
+            pending = rest;
+            let mut fired = wheel.advance(origin + ms(now));
+            fired.sort();
+            let mut expected: Vec<u64> = due_now.into_iter().map(|(_, n)| n).collect();
+            expected.sort();
+            assert_eq!(fired, expected);
+        }
+        assert_eq!(wheel.len(), pending.len());
+    }
+}
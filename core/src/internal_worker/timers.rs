@@ -0,0 +1,107 @@
+//! Firing scheduled items on time
+//!
+//! Workflow timers and scheduled executions wait as rows of
+//! `scheduled_queue`. Every `REFILL_INTERVAL` the internal worker loads the
+//! rows due within `LOOKAHEAD`, `REFILL_BATCH_SIZE` at a time, into a
+//! `TimerWheel`, and queues each one's work within a `TICK` of it falling
+//! due. Rows further out stay in Postgres until they come within reach, so
+//! the wheel holds at most about `MAX_LOADED` rows however many workflows
+//! are asleep.
+//!
+//! The wheel only decides when to look. Firing claims the rows in
+//! Postgres, skipping any not due by the database's clock or taken by
+//! another worker, so workers loading the same rows queue each one once.
+//! A fired row that was skipped is dropped from the wheel and loaded again
+//! by a later refill if it is still there.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use super::timer_wheel::TimerWheel;
+use crate::services::SchedulerService;
+
+/// How precisely items fire
+const TICK: Duration = Duration::from_millis(10);
+
+/// How often due rows are loaded, and so the longest a row created due
+/// sooner than that can wait past its time
+pub(super) const REFILL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How far ahead rows are loaded
+const LOOKAHEAD: Duration = Duration::from_secs(2);
+
+const REFILL_BATCH_SIZE: i64 = 1000;
+const MAX_LOADED: usize = 100_000;
+
+/// Most items processed in one transaction
+const FIRE_BATCH_SIZE: usize = 1000;
+
+pub(super) struct Timers {
+    scheduler_service: SchedulerService,
+    wheel: TimerWheel<Uuid>,
+    loaded: HashSet<Uuid>,
+}
+
+impl Timers {
+    pub fn new(scheduler_service: SchedulerService) -> Self {
+        Self {
+            scheduler_service,
+            wheel: TimerWheel::new(Instant::now(), TICK),
+            loaded: HashSet::new(),
+        }
+    }
+
+    /// Load the rows due within `LOOKAHEAD` that aren't in the wheel yet
+    ///
+    /// Returns how many were added.
+    pub async fn refill(&mut self) -> Result<usize> {
+        let mut added = 0;
+        let mut after = None;
+        while self.wheel.len() < MAX_LOADED {
+            let items = self
+                .scheduler_service
+                .upcoming_items(LOOKAHEAD, after, REFILL_BATCH_SIZE)
+                .await?;
+            let now = Instant::now();
+            for item in &items {
+                if self.loaded.insert(item.id) {
+                    let due_in = Duration::from_millis(item.due_in_ms.max(0) as u64);
+                    self.wheel.insert(now + due_in, item.id);
+                    added += 1;
+                }
+            }
+
+            match items.last() {
+                Some(last) if items.len() as i64 == REFILL_BATCH_SIZE => {
+                    after = Some((last.run_at, last.id));
+                }
+                _ => break,
+            }
+        }
+        Ok(added)
+    }
+
+    /// When to call `fire_due` next, if anything is loaded
+    pub fn next_due(&self) -> Option<Instant> {
+        self.wheel.next_due()
+    }
+
+    /// Queue the work of the loaded items that are due
+    ///
+    /// Returns how many were processed.
+    pub async fn fire_due(&mut self) -> Result<u32> {
+        let due = self.wheel.advance(Instant::now());
+        for id in &due {
+            self.loaded.remove(id);
+        }
+
+        let mut processed = 0;
+        for batch in due.chunks(FIRE_BATCH_SIZE) {
+            processed += self.scheduler_service.process_items(batch).await?;
+        }
+        Ok(processed)
+    }
+}
@@ -0,0 +1,169 @@
+//! Execution state machine and the checks that enforce it
+//!
+//! # Delivery semantics
+//!
+//! Rhythm runs work **at least once**. A worker claims a work queue entry
+//! with a one-minute lease (extended by heartbeats while a host runs a
+//! task); if the worker dies or stalls past the lease, the entry is claimed
+//! again and the work runs again. Consequently:
+//!
+//! - Task code may run more than once for the same execution and must be
+//!   idempotent, or guard its side effects with the execution id.
+//! - A workflow run commits all of its effects (child executions, timers,
+//!   signals, its saved state and status) in one transaction, so a re-run
+//!   after a crash starts from the last committed state and nothing it did
+//!   in the lost run is kept.
+//! - Only the first outcome recorded for an execution counts; a finished
+//!   execution never changes status again.
+//!
+//! # Legal transitions
+//!
+//! | from        | to                                                   |
+//! |-------------|------------------------------------------------------|
+//! | `pending`   | `running` (claimed), `expired` (TTL)                 |
+//! | `running`   | `completed`, `failed`, `suspended`, `pending` (retry), `running` (re-claimed) |
+//! | `suspended` | `running` (resumed)                                  |
+//!
+//! External executions are never claimed, so they go straight from
+//! `pending` to `completed` or `failed` when their token is used. Finished
+//! executions (`completed`, `failed`, `expired`) have no transitions.
+//!
+//! A workflow suspended by its VM always has a saved context row to resume
+//! from. (A workflow suspended after a transient runner error may not, if it
+//! failed before saving any state; it then restarts from the beginning.)
+//!
+//! # Checks
+//!
+//! The db layer calls `check_transition` and `check_suspended_context` on
+//! the rows it updates. They are active in debug builds and with the
+//! `invariants` feature, turning what would be silent corruption into an
+//! `InvariantViolation` that rolls back the caller's transaction. Tests can
+//! use `is_legal_transition` as the single definition of what is allowed.
+
+use crate::types::{ExecutionStatus, ExecutionType};
+
+/// Whether the checks run in this build
+pub const ENABLED: bool = cfg!(any(debug_assertions, feature = "invariants"));
+
+/// An update that would break the execution state machine
+///
+/// Adapters can find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvariantViolation {
+    pub execution_id: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invariant violated for execution {}: {}",
+            self.execution_id, self.message
+        )
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+/// Whether an execution of `exec_type` may go from `from` to `to`
+pub fn is_legal_transition(
+    exec_type: &ExecutionType,
+    from: &ExecutionStatus,
+    to: &ExecutionStatus,
+) -> bool {
+    use ExecutionStatus::*;
+    match (from, to) {
+        (Pending, Running | Expired) => true,
+        (Pending | Running | Suspended, Cancelled) => true,
+        (Pending, Completed | Failed) => *exec_type == ExecutionType::External,
+        (Running, Completed | Failed | Suspended | Pending | Running) => true,
+        (Suspended, Running) => true,
+        _ => false,
+    }
+}
+
+/// Check a status change made by the db layer
+pub fn check_transition(
+    execution_id: &str,
+    exec_type: &ExecutionType,
+    from: &ExecutionStatus,
+    to: &ExecutionStatus,
+) -> Result<(), InvariantViolation> {
+    if !ENABLED || is_legal_transition(exec_type, from, to) {
+        return Ok(());
+    }
+    Err(InvariantViolation {
+        execution_id: execution_id.to_string(),
+        message: format!(
+            "illegal transition {} -> {} for a {:?} execution",
+            from.as_str(),
+            to.as_str(),
+            exec_type
+        ),
+    })
+}
+
+/// Check that a workflow suspended by its VM has state to resume from
+pub fn check_suspended_context(
+    execution_id: &str,
+    exec_type: &ExecutionType,
+    has_context: bool,
+) -> Result<(), InvariantViolation> {
+    if !ENABLED || *exec_type != ExecutionType::Workflow || has_context {
+        return Ok(());
+    }
+    Err(InvariantViolation {
+        execution_id: execution_id.to_string(),
+        message: "suspended without a saved workflow context".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ExecutionStatus::*;
+
+    const ALL: [ExecutionStatus; 7] = [
+        Pending, Running, Suspended, Completed, Failed, Expired, Cancelled,
+    ];
+
+    #[test]
+    fn test_finished_executions_never_change() {
+        for from in [Completed, Failed, Expired, Cancelled] {
+            for to in ALL {
+                assert!(
+                    !is_legal_transition(&ExecutionType::Task, &from, &to),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_only_external_executions_finish_unclaimed() {
+        for to in [Completed, Failed] {
+            assert!(is_legal_transition(&ExecutionType::External, &Pending, &to));
+            assert!(!is_legal_transition(&ExecutionType::Task, &Pending, &to));
+            assert!(!is_legal_transition(
+                &ExecutionType::Workflow,
+                &Pending,
+                &to
+            ));
+        }
+    }
+
+    #[test]
+    fn test_violations_are_descriptive() {
+        let err =
+            check_transition("exec-1", &ExecutionType::Task, &Completed, &Failed).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invariant violated for execution exec-1: illegal transition completed -> failed for a Task execution"
+        );
+        assert!(check_suspended_context("wf-1", &ExecutionType::Workflow, false).is_err());
+        assert!(check_suspended_context("wf-1", &ExecutionType::Workflow, true).is_ok());
+    }
+}
@@ -1,11 +1,33 @@
 pub mod application;
+pub mod auth;
+pub mod blob_store;
+pub mod cli;
 pub mod client;
+pub mod compression;
 pub mod config;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
 pub mod db;
+pub mod diagnostics;
+pub mod encryption;
+pub mod errors;
+pub mod execution_diff;
 pub mod executor;
+pub mod flow_test;
 pub mod internal_worker;
+pub mod invariants;
 pub mod parser;
+pub mod payload;
+pub mod payload_schema;
+pub mod queue_backend;
+pub mod queue_limits;
+pub mod quotas;
+pub mod repl;
 pub mod services;
+pub mod simulation;
+pub mod state_encoding;
+pub mod telemetry;
+pub mod testing;
 pub mod types;
 pub mod worker;
 
@@ -18,8 +40,12 @@ mod tests;
 // Re-export main types
 pub use types::*;
 
+// Re-export error kinds adapters map to their own errors
+pub use errors::{ErrorCode, RhythmError};
+
 // Re-export client for FFI layers
 pub use client::Client;
+pub use payload::PayloadEncoding;
 
 // Re-export application API
-pub use application::{Application, InitBuilder, InitOptions, WorkflowFile};
+pub use application::{Application, InitBuilder, InitOptions, ReadOnly, WorkflowFile};
@@ -0,0 +1,1247 @@
+//! Static analysis for Flow workflows
+//!
+//! Unlike `semantic_validator`, which rejects workflows outright, the rules here
+//! report advisory diagnostics for code that parses and runs but is almost
+//! certainly a mistake. Editors surface them as squiggles; the engine does not
+//! refuse to register a workflow because of them.
+//!
+//! Type checking is gradual: unannotated values are inferred where that is
+//! cheap and treated as `any` otherwise, and `any` is compatible with every
+//! annotation, so untyped workflows never produce type warnings.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use super::input_schema::InputSchema;
+use super::WorkflowDef;
+use crate::executor::types::ast::{
+    BinaryOp, DeclareTarget, Expr, ForLoopKind, MemberAccess, Span, Stmt, TypeExpr,
+};
+
+/* ===================== Diagnostics ===================== */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A finding from a static analysis rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Stable rule identifier, e.g. `await-non-task`
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+}
+
+/// `await` applied to a value that can never be a task or promise
+pub const AWAIT_NON_TASK: &str = "await-non-task";
+
+/// A task handle used as an operand of arithmetic or comparison
+pub const UNAWAITED_TASK_IN_OPERATOR: &str = "unawaited-task-in-operator";
+
+/// A value that does not match its annotation, or an operand of the wrong type
+pub const TYPE_MISMATCH: &str = "type-mismatch";
+
+/// An annotation naming a type that does not exist
+pub const UNKNOWN_TYPE: &str = "unknown-type";
+
+/// Reading a property that an object type does not declare
+pub const UNKNOWN_PROPERTY: &str = "unknown-property";
+
+/// Front matter that doesn't parse, or whose `inputs:` schema doesn't
+pub const INVALID_FRONT_MATTER: &str = "invalid-front-matter";
+
+/// `Task.run` of a task name no worker registers (only checked when the
+/// registered names are known)
+pub const UNKNOWN_TASK: &str = "unknown-task";
+
+/* ===================== Public API ===================== */
+
+/// Run all analysis rules over a workflow
+pub fn analyze_workflow(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    analyze(workflow, None)
+}
+
+/// Run all analysis rules, also checking literal task names passed to
+/// `Task.run` against `known_tasks`
+pub fn analyze_workflow_with_tasks(
+    workflow: &WorkflowDef,
+    known_tasks: &BTreeSet<String>,
+) -> Vec<Diagnostic> {
+    analyze(workflow, Some(known_tasks))
+}
+
+fn analyze(workflow: &WorkflowDef, known_tasks: Option<&BTreeSet<String>>) -> Vec<Diagnostic> {
+    let mut analyzer = Analyzer {
+        known_tasks: known_tasks.cloned(),
+        ..Default::default()
+    };
+    analyzer.scopes.push(HashMap::new());
+
+    // An annotation on `main` wins over the front matter schema
+    let schema_type = match InputSchema::of_workflow(workflow) {
+        Ok(schema) => schema.map(|schema| schema.to_type_expr()),
+        Err(e) => {
+            analyzer.report(
+                INVALID_FRONT_MATTER,
+                Severity::Error,
+                e.to_string(),
+                Span::default(),
+            );
+            None
+        }
+    };
+    if let Some(input_type) = workflow.input_type.as_ref().or(schema_type.as_ref()) {
+        let ty = analyzer.resolve(input_type);
+        analyzer.declare(
+            "Inputs",
+            Binding {
+                ty: ty.clone(),
+                declared: Some(ty),
+            },
+        );
+    }
+    analyzer.stmt(&workflow.body);
+    analyzer.diagnostics
+}
+
+/* ===================== Types ===================== */
+
+/// What is statically known about the value an expression produces
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    /// Nothing is known; may even be a task handle
+    Any,
+    /// Some value that is known not to be a task handle
+    Value,
+    /// A task/timer/signal handle that must be awaited to get its result
+    Task,
+    Null,
+    Bool,
+    Num,
+    Str,
+    List(Box<Ty>),
+    /// `open` objects may carry properties beyond `fields`
+    Obj {
+        fields: BTreeMap<String, Field>,
+        open: bool,
+    },
+    /// Never nested, never contains `Any`, at least two variants
+    Union(Vec<Ty>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    ty: Ty,
+    optional: bool,
+}
+
+impl Field {
+    /// Type seen when reading the field
+    fn read_ty(&self) -> Ty {
+        if self.optional {
+            Ty::union(self.ty.clone(), Ty::Null)
+        } else {
+            self.ty.clone()
+        }
+    }
+}
+
+impl Ty {
+    fn union(a: Ty, b: Ty) -> Ty {
+        let mut variants: Vec<Ty> = Vec::new();
+        for ty in [a, b] {
+            match ty {
+                Ty::Any => return Ty::Any,
+                Ty::Union(vs) => variants.extend(vs),
+                other => variants.push(other),
+            }
+        }
+
+        // `Value` already covers every non-task variant
+        if variants.contains(&Ty::Value) {
+            variants.retain(|v| matches!(v, Ty::Value | Ty::Task));
+        }
+
+        let mut unique: Vec<Ty> = Vec::new();
+        for v in variants {
+            if !unique.contains(&v) {
+                unique.push(v);
+            }
+        }
+
+        if unique.len() == 1 {
+            unique.pop().unwrap()
+        } else {
+            Ty::Union(unique)
+        }
+    }
+
+    /// Known never to be a task handle
+    fn is_plain(&self) -> bool {
+        match self {
+            Ty::Any | Ty::Task => false,
+            Ty::Union(vs) => vs.iter().all(Ty::is_plain),
+            _ => true,
+        }
+    }
+
+    fn may_be_num(&self) -> bool {
+        match self {
+            Ty::Any | Ty::Value | Ty::Num => true,
+            Ty::Union(vs) => vs.iter().any(Ty::may_be_num),
+            _ => false,
+        }
+    }
+
+    /// Type of `await value`: handles resolve to their (unknown) result
+    fn awaited(self) -> Ty {
+        match self {
+            Ty::Task => Ty::Any,
+            Ty::Union(vs) => vs
+                .into_iter()
+                .map(Ty::awaited)
+                .reduce(Ty::union)
+                .unwrap_or(Ty::Any),
+            other => other,
+        }
+    }
+
+    fn without_null(self) -> Ty {
+        match self {
+            Ty::Union(vs) => vs
+                .into_iter()
+                .filter(|v| *v != Ty::Null)
+                .reduce(Ty::union)
+                .unwrap_or(Ty::Null),
+            other => other,
+        }
+    }
+
+    /// Whether a value of type `self` may be stored where `target` is declared
+    fn assignable_to(&self, target: &Ty) -> bool {
+        match (self, target) {
+            (Ty::Any, _) | (_, Ty::Any) => true,
+            (Ty::Union(vs), _) => vs.iter().all(|v| v.assignable_to(target)),
+            (_, Ty::Union(ts)) => ts.iter().any(|t| self.assignable_to(t)),
+            (Ty::Value, t) => *t != Ty::Task,
+            (v, Ty::Value) => *v != Ty::Task,
+            (Ty::List(a), Ty::List(b)) => a.assignable_to(b),
+            (
+                Ty::Obj {
+                    fields: have,
+                    open: have_open,
+                },
+                Ty::Obj { fields: want, .. },
+            ) => want.iter().all(|(name, field)| match have.get(name) {
+                Some(have) => have.read_ty().assignable_to(&field.read_ty()),
+                None => field.optional || *have_open,
+            }),
+            (v, t) => v == t,
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Any => write!(f, "any"),
+            Ty::Value => write!(f, "value"),
+            Ty::Task => write!(f, "Task"),
+            Ty::Null => write!(f, "null"),
+            Ty::Bool => write!(f, "boolean"),
+            Ty::Num => write!(f, "number"),
+            Ty::Str => write!(f, "string"),
+            Ty::List(inner) if matches!(**inner, Ty::Union(_)) => write!(f, "({})[]", inner),
+            Ty::List(inner) => write!(f, "{}[]", inner),
+            Ty::Obj { fields, open } if fields.is_empty() && *open => write!(f, "object"),
+            Ty::Obj { fields, .. } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, field)| {
+                        let mark = if field.optional { "?" } else { "" };
+                        format!("{}{}: {}", name, mark, field.ty)
+                    })
+                    .collect();
+                write!(f, "{{{}}}", fields.join(", "))
+            }
+            Ty::Union(vs) => {
+                let vs: Vec<String> = vs.iter().map(Ty::to_string).collect();
+                write!(f, "{}", vs.join(" | "))
+            }
+        }
+    }
+}
+
+/// Stdlib calls that return a handle: `(object, method)`
+const TASK_FACTORIES: &[(&str, &str)] = &[
+    ("Task", "run"),
+    ("Task", "stream"),
+    ("Task", "all"),
+    ("Workflow", "run"),
+    ("Timer", "delay"),
+    ("Signal", "next"),
+    ("Promise", "all"),
+    ("Promise", "any"),
+    ("Promise", "any_kv"),
+    ("Promise", "race"),
+    ("Promise", "race_kv"),
+];
+
+/// Operator functions the parser desugars `+ - * / == != < <= > >=` into
+const OPERATOR_FUNCS: &[&str] = &[
+    "add", "sub", "mul", "div", "eq", "ne", "lt", "lte", "gt", "gte",
+];
+
+/// What the analyzer tracks per variable
+#[derive(Debug, Clone, PartialEq)]
+struct Binding {
+    /// Type of the value currently held
+    ty: Ty,
+    /// Annotated type, which every assignment must respect
+    declared: Option<Ty>,
+}
+
+impl Binding {
+    fn inferred(ty: Ty) -> Self {
+        Self { ty, declared: None }
+    }
+}
+
+type Scope = HashMap<String, Binding>;
+
+#[derive(Default)]
+struct Analyzer {
+    scopes: Vec<Scope>,
+    diagnostics: Vec<Diagnostic>,
+    /// When set, rules do not report (used for the loop pre-pass)
+    muted: bool,
+    /// Task names workers register, if known
+    known_tasks: Option<BTreeSet<String>>,
+}
+
+impl Analyzer {
+    fn report(&mut self, code: &'static str, severity: Severity, message: String, span: Span) {
+        if !self.muted {
+            self.diagnostics.push(Diagnostic {
+                code,
+                severity,
+                message,
+                span,
+            });
+        }
+    }
+
+    /* ---------- scopes ---------- */
+
+    fn binding(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup(&self, name: &str) -> Ty {
+        self.binding(name).map_or(Ty::Any, |b| b.ty.clone())
+    }
+
+    fn declare(&mut self, name: &str, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    fn binding_mut(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    /// Merge another branch's view of the enclosing scopes into ours
+    fn merge_from(&mut self, other: &[Scope]) {
+        for (mine, theirs) in self.scopes.iter_mut().zip(other) {
+            for (name, binding) in mine.iter_mut() {
+                let their_ty = theirs.get(name).map_or(Ty::Any, |b| b.ty.clone());
+                if binding.ty != their_ty {
+                    binding.ty = Ty::union(binding.ty.clone(), their_ty);
+                }
+            }
+        }
+    }
+
+    fn in_block(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    /* ---------- annotations ---------- */
+
+    fn resolve(&mut self, ty: &TypeExpr) -> Ty {
+        match ty {
+            TypeExpr::Named { name, span } => match name.as_str() {
+                "any" => Ty::Any,
+                "number" => Ty::Num,
+                "string" => Ty::Str,
+                "boolean" => Ty::Bool,
+                "null" => Ty::Null,
+                "object" => Ty::Obj {
+                    fields: BTreeMap::new(),
+                    open: true,
+                },
+                "Task" | "Promise" => Ty::Task,
+                _ => {
+                    self.report(
+                        UNKNOWN_TYPE,
+                        Severity::Warning,
+                        format!("Unknown type `{}`", name),
+                        *span,
+                    );
+                    Ty::Any
+                }
+            },
+            TypeExpr::Array { element, .. } => Ty::List(Box::new(self.resolve(element))),
+            TypeExpr::Object { fields, .. } => {
+                let mut resolved = BTreeMap::new();
+                for field in fields {
+                    let ty = self.resolve(&field.ty);
+                    resolved.insert(
+                        field.name.clone(),
+                        Field {
+                            ty,
+                            optional: field.optional,
+                        },
+                    );
+                }
+                Ty::Obj {
+                    fields: resolved,
+                    open: false,
+                }
+            }
+            TypeExpr::Union { variants, .. } => variants
+                .iter()
+                .map(|v| self.resolve(v))
+                .reduce(Ty::union)
+                .unwrap_or(Ty::Any),
+        }
+    }
+
+    /// Report a mismatch if `value` cannot be stored in a `declared` slot
+    fn check_assignable(&mut self, value: &Ty, declared: &Ty, span: Span) {
+        if !value.assignable_to(declared) {
+            self.report(
+                TYPE_MISMATCH,
+                Severity::Warning,
+                format!(
+                    "Type `{}` is not assignable to declared type `{}`",
+                    value, declared
+                ),
+                span,
+            );
+        }
+    }
+
+    /* ---------- statements ---------- */
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { body, .. } => self.in_block(|a| {
+                for s in body {
+                    a.stmt(s);
+                }
+            }),
+            Stmt::Declare {
+                target,
+                type_ann,
+                init,
+                ..
+            } => {
+                let declared = type_ann.as_ref().map(|t| self.resolve(t));
+                let init_ty = init.as_ref().map(|e| (self.expr(e), e.span()));
+
+                if let (Some(declared), Some((init_ty, span))) = (&declared, &init_ty) {
+                    self.check_assignable(init_ty, declared, *span);
+                }
+
+                // Narrow to the initializer when it fits the annotation
+                let ty = match (&declared, init_ty) {
+                    (Some(declared), Some((init_ty, _)))
+                        if init_ty != Ty::Any && init_ty.assignable_to(declared) =>
+                    {
+                        init_ty
+                    }
+                    (Some(declared), _) => declared.clone(),
+                    (None, Some((init_ty, _))) => init_ty,
+                    (None, None) => Ty::Null,
+                };
+
+                match target {
+                    DeclareTarget::Simple { name, .. } => {
+                        self.declare(name, Binding { ty, declared })
+                    }
+                    DeclareTarget::Destructure { names, spans, .. } => {
+                        for (i, name) in names.iter().enumerate() {
+                            let span = spans.get(i).copied().unwrap_or_default();
+                            let field_ty = self.property(&ty, name, false, span);
+                            self.declare(name, Binding::inferred(field_ty));
+                        }
+                    }
+                }
+            }
+            Stmt::Assign {
+                var,
+                path,
+                value,
+                span,
+                ..
+            } => {
+                for access in path {
+                    if let MemberAccess::Index { expr, .. } = access {
+                        self.expr(expr);
+                    }
+                }
+                let ty = self.expr(value);
+                if path.is_empty() {
+                    self.assign(var, ty, value.span());
+                } else {
+                    self.assign_path(var, path, ty, *span);
+                }
+            }
+            Stmt::If {
+                test,
+                then_s,
+                else_s,
+                ..
+            } => {
+                self.expr(test);
+                let before = self.scopes.clone();
+                self.stmt(then_s);
+                let after_then = std::mem::replace(&mut self.scopes, before);
+                if let Some(else_s) = else_s {
+                    self.stmt(else_s);
+                }
+                self.merge_from(&after_then);
+            }
+            Stmt::While { test, body, .. } => {
+                let run_body = |a: &mut Self| {
+                    a.expr(test);
+                    a.stmt(body);
+                };
+                self.run_loop(run_body);
+            }
+            Stmt::ForLoop {
+                kind,
+                binding,
+                iterable,
+                body,
+                ..
+            } => {
+                let element = match (kind, self.expr(iterable)) {
+                    (ForLoopKind::Of, Ty::List(element)) => *element,
+                    (ForLoopKind::In, _) => Ty::Str,
+                    _ => Ty::Any,
+                };
+                let run_body = |a: &mut Self| {
+                    a.in_block(|a| {
+                        a.declare(binding, Binding::inferred(element.clone()));
+                        a.stmt(body);
+                    })
+                };
+                self.run_loop(run_body);
+            }
+            Stmt::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Stmt::Throw { value, .. } => {
+                self.expr(value);
+            }
+            Stmt::Try {
+                body,
+                catch_var,
+                catch_body,
+                ..
+            } => {
+                let before = self.scopes.clone();
+                self.stmt(body);
+                // The catch body may start from any point in the try body
+                self.merge_from(&before);
+                let after_try = self.scopes.clone();
+                self.in_block(|a| {
+                    a.declare(catch_var, Binding::inferred(Ty::Any));
+                    a.stmt(catch_body);
+                });
+                self.merge_from(&after_try);
+            }
+            Stmt::Expr { expr, .. } => {
+                self.expr(expr);
+            }
+            Stmt::Break { .. } | Stmt::Continue { .. } => {}
+        }
+    }
+
+    fn assign(&mut self, name: &str, ty: Ty, span: Span) {
+        let declared = self.binding(name).and_then(|b| b.declared.clone());
+        if let Some(declared) = &declared {
+            self.check_assignable(&ty, declared, span);
+        }
+        if let Some(binding) = self.binding_mut(name) {
+            binding.ty = match declared {
+                Some(declared) if ty == Ty::Any || !ty.assignable_to(&declared) => declared,
+                _ => ty,
+            };
+        }
+    }
+
+    /// `obj.prop = value` / `obj[i] = value`
+    fn assign_path(&mut self, name: &str, path: &[MemberAccess], ty: Ty, span: Span) {
+        let Some(binding) = self.binding(name).cloned() else {
+            return;
+        };
+
+        if let Some(MemberAccess::Prop { property, .. }) = path.first() {
+            if let Some(Ty::Obj {
+                fields,
+                open: false,
+            }) = &binding.declared
+            {
+                match fields.get(property) {
+                    None => self.report(
+                        UNKNOWN_PROPERTY,
+                        Severity::Warning,
+                        format!(
+                            "Property `{}` does not exist on type `{}`",
+                            property,
+                            binding.declared.as_ref().unwrap()
+                        ),
+                        span,
+                    ),
+                    Some(field) if path.len() == 1 => {
+                        self.check_assignable(&ty, &field.read_ty(), span)
+                    }
+                    Some(_) => {}
+                }
+                return;
+            }
+        }
+
+        // Inferred object shapes grow with their assignments
+        if binding.declared.is_none() {
+            if let Some(Binding {
+                ty: Ty::Obj { fields, open },
+                ..
+            }) = self.binding_mut(name)
+            {
+                match path {
+                    [MemberAccess::Prop { property, .. }] => {
+                        fields.insert(
+                            property.clone(),
+                            Field {
+                                ty,
+                                optional: false,
+                            },
+                        );
+                    }
+                    [MemberAccess::Prop { property, .. }, ..] => {
+                        if let Some(field) = fields.get_mut(property) {
+                            field.ty = Ty::Any;
+                        }
+                    }
+                    _ => *open = true,
+                }
+            }
+        }
+    }
+
+    /// Check a loop body against what holds on every iteration
+    ///
+    /// A muted pre-pass widens variables the body reassigns, then the body is
+    /// checked for real; the loop may also run zero times.
+    fn run_loop(&mut self, body: impl Fn(&mut Self)) {
+        let before = self.scopes.clone();
+        let muted = std::mem::replace(&mut self.muted, true);
+        body(self);
+        self.muted = muted;
+        let after = std::mem::replace(&mut self.scopes, before);
+        self.merge_from(&after);
+
+        let entry = self.scopes.clone();
+        body(self);
+        self.merge_from(&entry);
+    }
+
+    /* ---------- expressions ---------- */
+
+    fn expr(&mut self, expr: &Expr) -> Ty {
+        match expr {
+            Expr::LitBool { .. } => Ty::Bool,
+            Expr::LitNum { .. } => Ty::Num,
+            Expr::LitStr { .. } => Ty::Str,
+            Expr::LitNull { .. } => Ty::Null,
+            Expr::Template { exprs, .. } => {
+                for e in exprs {
+                    self.expr(e);
+                }
+                Ty::Str
+            }
+            Expr::LitList { elements, .. } => {
+                let element = elements
+                    .iter()
+                    .map(|e| self.expr(e))
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .reduce(Ty::union)
+                    .unwrap_or(Ty::Any);
+                Ty::List(Box::new(element))
+            }
+            Expr::LitObj { properties, .. } => {
+                let mut fields = BTreeMap::new();
+                for (name, _, e) in properties {
+                    let ty = self.expr(e);
+                    fields.insert(
+                        name.clone(),
+                        Field {
+                            ty,
+                            optional: false,
+                        },
+                    );
+                }
+                Ty::Obj {
+                    fields,
+                    open: false,
+                }
+            }
+            Expr::Ident { name, .. } => self.lookup(name),
+            Expr::Member {
+                object,
+                property,
+                property_span,
+                optional,
+                ..
+            } => {
+                let object = self.expr(object);
+                self.property(&object, property, *optional, *property_span)
+            }
+            Expr::Call { callee, args, .. } => self.call(callee, args),
+            Expr::Await { inner, span } => {
+                let ty = self.expr(inner);
+                if ty.is_plain() {
+                    self.report(
+                        AWAIT_NON_TASK,
+                        Severity::Warning,
+                        "`await` on a value that is not a task or promise has no effect"
+                            .to_string(),
+                        *span,
+                    );
+                }
+                ty.awaited()
+            }
+            Expr::BinaryOp {
+                op, left, right, ..
+            } => {
+                let l = self.expr(left);
+                let r = self.expr(right);
+                match op {
+                    BinaryOp::Nullish => Ty::union(l.without_null(), r),
+                    BinaryOp::And | BinaryOp::Or => Ty::union(l, r),
+                }
+            }
+            Expr::Ternary {
+                condition,
+                consequent,
+                alternate,
+                ..
+            } => {
+                self.expr(condition);
+                let c = self.expr(consequent);
+                let a = self.expr(alternate);
+                Ty::union(c, a)
+            }
+        }
+    }
+
+    /// Type of `object.property`, reporting reads of undeclared properties
+    fn property(&mut self, object: &Ty, property: &str, optional: bool, span: Span) -> Ty {
+        match object {
+            Ty::Null if optional => Ty::Null,
+            Ty::Union(vs) => {
+                let vs: Vec<&Ty> = vs
+                    .iter()
+                    .filter(|v| !(optional && **v == Ty::Null))
+                    .collect();
+                let muted = std::mem::replace(&mut self.muted, true);
+                let ty = vs
+                    .into_iter()
+                    .map(|v| self.property(v, property, optional, span))
+                    .reduce(Ty::union)
+                    .unwrap_or(Ty::Null);
+                self.muted = muted;
+                ty
+            }
+            Ty::Obj { fields, open } => match fields.get(property) {
+                Some(field) => field.read_ty(),
+                None if *open => Ty::Any,
+                None => {
+                    self.report(
+                        UNKNOWN_PROPERTY,
+                        Severity::Warning,
+                        format!(
+                            "Property `{}` does not exist on type `{}`",
+                            property, object
+                        ),
+                        span,
+                    );
+                    Ty::Null
+                }
+            },
+            Ty::List(_) | Ty::Str if property == "length" => Ty::Num,
+            _ => Ty::Any,
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr]) -> Ty {
+        let arg_tys: Vec<Ty> = args.iter().map(|a| self.expr(a)).collect();
+
+        match callee {
+            Expr::Ident { name, .. } if OPERATOR_FUNCS.contains(&name.as_str()) => {
+                for (arg, ty) in args.iter().zip(&arg_tys) {
+                    if *ty == Ty::Task {
+                        self.report(
+                            UNAWAITED_TASK_IN_OPERATOR,
+                            Severity::Warning,
+                            "Task handle used as an operand without `await`; await it to use its result"
+                                .to_string(),
+                            arg.span(),
+                        );
+                    }
+                }
+                self.operator(name, args, &arg_tys)
+            }
+            Expr::Ident { name, .. } if name == "not" => Ty::Bool,
+            Expr::Member {
+                object, property, ..
+            } => {
+                if let Expr::Ident { name, .. } = object.as_ref() {
+                    if self.binding(name).is_none() {
+                        let is_factory = TASK_FACTORIES
+                            .iter()
+                            .any(|(obj, method)| obj == name && method == property);
+                        if is_factory {
+                            if name == "Task" && property == "run" {
+                                self.check_task_name(args.first());
+                            }
+                            return Ty::Task;
+                        }
+                        if name == "Math" {
+                            return Ty::Num;
+                        }
+                        if name == "Log" {
+                            return Ty::Null;
+                        }
+                        if name == "Compensate" && property == "register" {
+                            self.check_task_name(args.first());
+                            return Ty::Null;
+                        }
+                        if name == "ExternalTask" && property == "create" {
+                            let field = |ty| Field {
+                                ty,
+                                optional: false,
+                            };
+                            return Ty::Obj {
+                                fields: BTreeMap::from([
+                                    ("token".to_string(), field(Ty::Str)),
+                                    ("promise".to_string(), field(Ty::Task)),
+                                ]),
+                                open: false,
+                            };
+                        }
+                    }
+                }
+                self.expr(callee);
+                Ty::Any
+            }
+            _ => {
+                self.expr(callee);
+                Ty::Any
+            }
+        }
+    }
+
+    fn check_task_name(&mut self, arg: Option<&Expr>) {
+        let (Some(known), Some(Expr::LitStr { v, span })) = (&self.known_tasks, arg) else {
+            return;
+        };
+        if !known.contains(v) {
+            let message = format!("No registered task is named `{}`", v);
+            self.report(UNKNOWN_TASK, Severity::Warning, message, *span);
+        }
+    }
+
+    fn operator(&mut self, name: &str, args: &[Expr], arg_tys: &[Ty]) -> Ty {
+        let symbol = match name {
+            "add" => {
+                return match arg_tys {
+                    [Ty::Num, Ty::Num] => Ty::Num,
+                    [Ty::Str, _] | [_, Ty::Str] => Ty::Str,
+                    _ => Ty::Value,
+                }
+            }
+            "sub" => "-",
+            "mul" => "*",
+            "div" => "/",
+            _ => return Ty::Bool,
+        };
+
+        for (arg, ty) in args.iter().zip(arg_tys) {
+            if *ty != Ty::Task && !ty.may_be_num() {
+                self.report(
+                    TYPE_MISMATCH,
+                    Severity::Warning,
+                    format!(
+                        "Operand of `{}` has type `{}`, expected `number`",
+                        symbol, ty
+                    ),
+                    arg.span(),
+                );
+            }
+        }
+        Ty::Num
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(source: &str) -> Vec<Diagnostic> {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        analyze_workflow(&workflow)
+    }
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        analyze(source).into_iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_await_task_is_clean() {
+        assert!(codes(
+            r#"
+            let t = Task.run("charge", {})
+            let r = await t
+            let s = await Task.run("ship", {})
+            return r
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_await_literal_flagged() {
+        assert_eq!(codes("let x = await 5"), vec![AWAIT_NON_TASK]);
+        assert_eq!(codes(r#"await {a: 1}"#), vec![AWAIT_NON_TASK]);
+    }
+
+    #[test]
+    fn test_await_plain_variable_flagged() {
+        let diags = analyze(
+            r#"
+            let x = [1, 2]
+            let y = await x
+        "#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, AWAIT_NON_TASK);
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert_eq!(diags[0].span.start_line, 2);
+    }
+
+    #[test]
+    fn test_await_unknown_is_clean() {
+        // Inputs and member reads could hold anything
+        assert!(codes("let x = await inputs.handle").is_empty());
+        assert!(codes("let x = await foo").is_empty());
+    }
+
+    #[test]
+    fn test_task_in_arithmetic_flagged() {
+        let diags = analyze(
+            r#"
+            let t = Task.run("price", {})
+            let total = t + 1
+        "#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, UNAWAITED_TASK_IN_OPERATOR);
+        assert_eq!(diags[0].span.start_line, 2);
+    }
+
+    #[test]
+    fn test_task_in_comparison_flagged() {
+        assert_eq!(
+            codes(r#"if (Task.run("check", {}) == true) { return 1 }"#),
+            vec![UNAWAITED_TASK_IN_OPERATOR]
+        );
+    }
+
+    #[test]
+    fn test_awaited_task_in_arithmetic_is_clean() {
+        assert!(codes(
+            r#"
+            let t = Task.run("price", {})
+            let total = (await t) + 1
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_reassignment_updates_kind() {
+        assert!(codes(
+            r#"
+            let t = Task.run("price", {})
+            t = await t
+            let total = t + 1
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_branches_merge_to_unknown() {
+        // After the if, t may or may not be a task - don't guess
+        assert!(codes(
+            r#"
+            let t = 5
+            if (inputs.fast) { t = Task.run("a", {}) }
+            let r = await t
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_loop_reassignment_widens_before_body() {
+        // On the second iteration t is a task, so awaiting it is fine
+        assert!(codes(
+            r#"
+            let t = 0
+            while (inputs.more) {
+                let r = await t
+                t = Task.run("next", {})
+            }
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_shadowed_stdlib_object_not_treated_as_factory() {
+        assert!(codes(
+            r#"
+            let Task = {run: 1}
+            let x = Task.run("a", {}) + 1
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_annotations_matching_init_are_clean() {
+        assert!(codes(
+            r#"
+            let total: number = 0
+            let name: string | null = null
+            let items: {sku: string, qty?: number}[] = [{sku: "a"}, {sku: "b", qty: 2}]
+            let t: Task = Task.run("a", {})
+            const r: any = await t
+            total = total + 1
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_declare_mismatch_flagged() {
+        let diags = analyze(r#"let total: number = "zero""#);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, TYPE_MISMATCH);
+        assert_eq!(
+            diags[0].message,
+            "Type `string` is not assignable to declared type `number`"
+        );
+        assert_eq!(diags[0].span.start_col, 20);
+    }
+
+    #[test]
+    fn test_assignment_checked_against_declaration() {
+        assert_eq!(
+            codes(
+                r#"
+            let total: number = 0
+            if (inputs.reset) { total = null }
+        "#
+            ),
+            vec![TYPE_MISMATCH]
+        );
+    }
+
+    #[test]
+    fn test_unknown_values_are_compatible() {
+        // Gradual: anything not inferred is assumed to fit
+        assert!(codes(
+            r#"
+            let a: number = inputs.count
+            let b: string = await Task.run("name", {})
+            let c: number = inputs.x + 1
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_object_annotation_requires_fields() {
+        assert_eq!(
+            codes(r#"let p: {id: string, note?: string} = {note: "x"}"#),
+            vec![TYPE_MISMATCH]
+        );
+        assert!(codes(r#"let p: {id: string, note?: string} = {id: "x", extra: 1}"#).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_type_flagged() {
+        let diags = analyze("let x: Number = 1");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, UNKNOWN_TYPE);
+        assert_eq!(diags[0].span.start_col, 7);
+    }
+
+    #[test]
+    fn test_typed_inputs_parameter() {
+        let diags = analyze(
+            r#"
+            async function main(inputs: {orderId: string, amount: number}) {
+                let id: string = inputs.orderId
+                let total: string = inputs.amount
+                return inputs.orderID
+            }
+        "#,
+        );
+        let codes: Vec<_> = diags.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec![TYPE_MISMATCH, UNKNOWN_PROPERTY]);
+        assert_eq!(diags[1].span.start_line, 4);
+    }
+
+    #[test]
+    fn test_front_matter_inputs_schema() {
+        let diags = analyze(
+            r#"
+```
+inputs:
+  orderId: string
+  amount:
+    type: number
+    required: false
+```
+let id: string = Inputs.orderId
+let total: number = Inputs.amount ?? 0
+return Inputs.orderID
+        "#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, UNKNOWN_PROPERTY);
+
+        assert_eq!(
+            codes("```\ninputs:\n  id: strin\n```\nreturn 1"),
+            vec![INVALID_FRONT_MATTER]
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_on_non_number_flagged() {
+        assert_eq!(codes(r#"let x = "a" * 2"#), vec![TYPE_MISMATCH]);
+        assert!(codes(r#"let x = "a" + 2"#).is_empty());
+    }
+
+    #[test]
+    fn test_inferred_object_grows_with_assignments() {
+        assert!(codes(
+            r#"
+            let o = {}
+            o.total = 1
+            let t: number = o.total
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_branch_merge_with_declaration() {
+        assert!(codes(
+            r#"
+            let x: number | string = 1
+            if (inputs.s) { x = "one" }
+            let y: number | string = x
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_optional_input_with_nullish_default() {
+        assert!(codes(
+            r#"
+            async function main(inputs: {orderId: string, amount?: number}) {
+                let total: number = inputs.amount ?? 0
+                return await Task.run("charge", {orderId: inputs.orderId, total})
+            }
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_external_task_handle() {
+        assert!(codes(
+            r#"
+            let cb = ExternalTask.create("await-callback")
+            let token: string = cb.token
+            await Task.run("send_webhook", {token})
+            return await cb.promise
+        "#
+        )
+        .is_empty());
+        assert_eq!(
+            codes(r#"await ExternalTask.create("await-callback")"#),
+            vec![AWAIT_NON_TASK]
+        );
+    }
+
+    #[test]
+    fn test_unknown_task_names_flagged_when_known() {
+        let source = r#"
+            let name = "dynamic"
+            await Task.run("charge", {})
+            await Task.run("chrage", {})
+            await Task.run(name, {})
+            await Workflow.run("chrage", {})
+            Compensate.register("refnud", {})
+        "#;
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        assert!(analyze_workflow(&workflow).is_empty());
+
+        let known = BTreeSet::from(["charge".to_string()]);
+        let diags = analyze_workflow_with_tasks(&workflow, &known);
+        let messages: Vec<_> = diags.iter().map(|d| (d.code, d.message.as_str())).collect();
+        assert_eq!(
+            messages,
+            vec![
+                (UNKNOWN_TASK, "No registered task is named `chrage`"),
+                (UNKNOWN_TASK, "No registered task is named `refnud`"),
+            ]
+        );
+        assert_eq!(diags[0].span.start_line, 3);
+        assert_eq!(diags[1].span.start_line, 6);
+    }
+}
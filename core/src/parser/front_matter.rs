@@ -0,0 +1,204 @@
+//! Workflow front matter
+//!
+//! The YAML block between the ``` fences at the top of a workflow holds its
+//! settings (queue, retries, timeout, ...). Settings it leaves out fall back
+//! to `[workflow_defaults]` in rhythm.toml; the two are merged when the
+//! workflow is registered.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value as JsonValue};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use super::WorkflowDef;
+
+/// Parse front matter into a JSON object
+///
+/// Empty front matter is an empty object. Anything other than a mapping at
+/// the top level is an error.
+pub fn parse(text: &str) -> Result<Map<String, JsonValue>> {
+    let docs =
+        YamlLoader::load_from_str(text).map_err(|e| anyhow!("Invalid front matter: {}", e))?;
+    match docs.into_iter().next() {
+        None | Some(Yaml::Null) => Ok(Map::new()),
+        Some(doc @ Yaml::Hash(_)) => match yaml_to_json(doc)? {
+            JsonValue::Object(map) => Ok(map),
+            _ => unreachable!("a YAML mapping converts to an object"),
+        },
+        Some(_) => bail!("Invalid front matter: expected `key: value` settings"),
+    }
+}
+
+/// The front matter text at the top of `source`, if it has any
+///
+/// Only looks at the fences, so it works on sources whose body doesn't
+/// parse yet, as an editor sees them mid-edit.
+pub fn extract(source: &str) -> Option<&str> {
+    let rest = source.trim_start().strip_prefix("```")?;
+    rest.find("```").map(|end| &rest[..end])
+}
+
+/// Overlay `settings` on `defaults`
+///
+/// Nested objects merge key by key, so a workflow can override one
+/// validator severity without dropping the rest. Any other value in
+/// `settings` replaces the default outright.
+pub fn merge(
+    defaults: &Map<String, JsonValue>,
+    settings: &Map<String, JsonValue>,
+) -> Map<String, JsonValue> {
+    let mut merged = defaults.clone();
+    for (key, value) in settings {
+        let value = match (merged.get(key), value) {
+            (Some(JsonValue::Object(base)), JsonValue::Object(own)) => {
+                JsonValue::Object(merge(base, own))
+            }
+            _ => value.clone(),
+        };
+        merged.insert(key.clone(), value);
+    }
+    merged
+}
+
+/// The settings a workflow runs with: its front matter over `defaults`
+pub fn effective_settings(
+    workflow: &WorkflowDef,
+    defaults: &Map<String, JsonValue>,
+) -> Result<Map<String, JsonValue>> {
+    let own = match &workflow.front_matter {
+        Some(text) => parse(text)?,
+        None => Map::new(),
+    };
+    Ok(merge(defaults, &own))
+}
+
+/// Flatten nested settings into dotted keys, e.g. `validator.unused_variable`
+pub fn flatten(settings: &Map<String, JsonValue>) -> Vec<(String, JsonValue)> {
+    let mut flat = Vec::new();
+    for (key, value) in settings {
+        match value {
+            JsonValue::Object(nested) if !nested.is_empty() => {
+                for (path, value) in flatten(nested) {
+                    flat.push((format!("{}.{}", key, path), value));
+                }
+            }
+            _ => flat.push((key.clone(), value.clone())),
+        }
+    }
+    flat
+}
+
+fn yaml_to_json(yaml: Yaml) -> Result<JsonValue> {
+    Ok(match yaml {
+        Yaml::Null => JsonValue::Null,
+        Yaml::Boolean(b) => JsonValue::Bool(b),
+        Yaml::Integer(i) => JsonValue::from(i),
+        Yaml::Real(_) => yaml
+            .as_f64()
+            .and_then(serde_json::Number::from_f64)
+            .map(JsonValue::Number)
+            .ok_or_else(|| anyhow!("Invalid front matter: unsupported number"))?,
+        Yaml::String(s) => JsonValue::String(s),
+        Yaml::Array(items) => {
+            JsonValue::Array(items.into_iter().map(yaml_to_json).collect::<Result<_>>()?)
+        }
+        Yaml::Hash(entries) => {
+            let mut map = Map::new();
+            for (key, value) in entries {
+                let key = match key {
+                    Yaml::String(s) => s,
+                    Yaml::Integer(i) => i.to_string(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    _ => bail!("Invalid front matter: keys must be strings"),
+                };
+                map.insert(key, yaml_to_json(value)?);
+            }
+            JsonValue::Object(map)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => {
+            bail!("Invalid front matter: aliases are not supported")
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: JsonValue) -> Map<String, JsonValue> {
+        match value {
+            JsonValue::Object(map) => map,
+            _ => panic!("expected an object"),
+        }
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        let settings = parse(
+            "\nqueue: payments\nretries: 3\ntimeout: 30s\nvalidator:\n  unused_variable: off\n",
+        )
+        .unwrap();
+        assert_eq!(
+            JsonValue::Object(settings),
+            json!({
+                "queue": "payments",
+                "retries": 3,
+                "timeout": "30s",
+                "validator": { "unused_variable": "off" },
+            })
+        );
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse("- a\n- b").is_err());
+    }
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(
+            extract("\n```\nqueue: q\n```\nreturn Inputs."),
+            Some("\nqueue: q\n")
+        );
+        assert_eq!(extract("return 1"), None);
+        assert_eq!(extract("```\nqueue: q"), None);
+    }
+
+    #[test]
+    fn test_front_matter_wins_over_defaults() {
+        let defaults = object(json!({
+            "queue": "default",
+            "retries": 5,
+            "validator": { "unused_variable": "warn", "shadowing": "error" },
+        }));
+        let own = object(json!({
+            "queue": "payments",
+            "validator": { "unused_variable": "off" },
+        }));
+
+        assert_eq!(
+            JsonValue::Object(merge(&defaults, &own)),
+            json!({
+                "queue": "payments",
+                "retries": 5,
+                "validator": { "unused_variable": "off", "shadowing": "error" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_effective_settings_without_front_matter() {
+        let workflow = crate::parser::parse_workflow("return 1").unwrap();
+        let defaults = object(json!({ "queue": "default" }));
+        assert_eq!(effective_settings(&workflow, &defaults).unwrap(), defaults);
+    }
+
+    #[test]
+    fn test_flatten() {
+        let settings = object(json!({ "queue": "q", "budgets": { "max_steps": 100 } }));
+        assert_eq!(
+            flatten(&settings),
+            vec![
+                ("budgets.max_steps".to_string(), json!(100)),
+                ("queue".to_string(), json!("q")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,439 @@
+//! Typed workflow inputs
+//!
+//! The `inputs:` key of a workflow's front matter declares the inputs it
+//! takes, by name:
+//!
+//! ```yaml
+//! inputs:
+//!   order_id: string
+//!   amount:
+//!     type: number
+//!     description: In cents
+//!   currency:
+//!     type: string
+//!     default: USD
+//!   tags:
+//!     type: string[]
+//!     required: false
+//! ```
+//!
+//! Types are those of `main(inputs: T)` annotations: `string`, `number`,
+//! `boolean`, `object`, `any`, and arrays as `T[]` (`array` for `any[]`).
+//! An input is required unless it has a default or says `required: false`.
+//!
+//! `start_workflow` checks inputs against the schema of the workflow's
+//! latest definition and fills in defaults; the static analysis treats the
+//! schema as the type of `Inputs` when `main` doesn't annotate one.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+
+use super::WorkflowDef;
+use crate::executor::types::ast::{Span, TypeExpr, TypeField};
+
+/// Type of a declared input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum InputType {
+    Any,
+    String,
+    Number,
+    Boolean,
+    Object,
+    Array(Box<InputType>),
+}
+
+impl InputType {
+    /// Whether `value` has this type
+    pub fn accepts(&self, value: &JsonValue) -> bool {
+        match self {
+            InputType::Any => true,
+            InputType::String => value.is_string(),
+            InputType::Number => value.is_number(),
+            InputType::Boolean => value.is_boolean(),
+            InputType::Object => value.is_object(),
+            InputType::Array(element) => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(|item| element.accepts(item))),
+        }
+    }
+
+    fn to_type_expr(&self) -> TypeExpr {
+        match self {
+            InputType::Array(element) => TypeExpr::Array {
+                element: Box::new(element.to_type_expr()),
+                span: Span::default(),
+            },
+            other => TypeExpr::Named {
+                name: other.to_string(),
+                span: Span::default(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for InputType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputType::Any => f.write_str("any"),
+            InputType::String => f.write_str("string"),
+            InputType::Number => f.write_str("number"),
+            InputType::Boolean => f.write_str("boolean"),
+            InputType::Object => f.write_str("object"),
+            InputType::Array(element) if **element == InputType::Any => f.write_str("array"),
+            InputType::Array(element) => write!(f, "{}[]", element),
+        }
+    }
+}
+
+impl std::str::FromStr for InputType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(element) = s.strip_suffix("[]") {
+            return Ok(InputType::Array(Box::new(element.parse()?)));
+        }
+        Ok(match s {
+            "any" => InputType::Any,
+            "string" => InputType::String,
+            "number" => InputType::Number,
+            "boolean" => InputType::Boolean,
+            "object" => InputType::Object,
+            "array" => InputType::Array(Box::new(InputType::Any)),
+            _ => bail!("unknown type `{}`", s),
+        })
+    }
+}
+
+impl From<InputType> for String {
+    fn from(ty: InputType) -> Self {
+        ty.to_string()
+    }
+}
+
+impl TryFrom<String> for InputType {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// One declared input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: InputType,
+    pub required: bool,
+    /// Used when the input is missing or null
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The inputs a workflow declares, ordered by name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputSchema {
+    pub fields: Vec<InputField>,
+}
+
+impl InputSchema {
+    /// The schema under `inputs` in resolved settings, if there is one
+    pub fn from_settings(settings: &Map<String, JsonValue>) -> Result<Option<Self>> {
+        match settings.get("inputs") {
+            Some(inputs) => Self::from_declared(inputs, "inputs", "input").map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// A schema of `name: type` entries, as under `inputs:`
+    ///
+    /// `what` names the payload and `item` one of its fields in errors,
+    /// e.g. "output" and "field".
+    pub fn from_declared(declared: &JsonValue, what: &str, item: &str) -> Result<Self> {
+        let JsonValue::Object(declared) = declared else {
+            bail!("Invalid {} schema: expected `name: type` entries", what);
+        };
+
+        let mut fields = Vec::with_capacity(declared.len());
+        for (name, spec) in declared {
+            let field = parse_field(name, spec)
+                .map_err(|e| anyhow!("Invalid {} schema: {} `{}`: {}", what, item, name, e))?;
+            fields.push(field);
+        }
+        Ok(Self { fields })
+    }
+
+    /// The schema in a workflow's own front matter, if there is one
+    pub fn of_workflow(workflow: &WorkflowDef) -> Result<Option<Self>> {
+        match &workflow.front_matter {
+            Some(text) => Self::from_settings(&super::front_matter::parse(text)?),
+            None => Ok(None),
+        }
+    }
+
+    /// The schema in the front matter of workflow source that may not parse
+    pub fn of_source(source: &str) -> Result<Option<Self>> {
+        match super::front_matter::extract(source) {
+            Some(text) => Self::from_settings(&super::front_matter::parse(text)?),
+            None => Ok(None),
+        }
+    }
+
+    /// Check `inputs` against the schema, filling in defaults
+    ///
+    /// Inputs the schema doesn't declare are passed through. On failure,
+    /// returns one message per input that doesn't fit.
+    pub fn apply(&self, inputs: JsonValue) -> std::result::Result<JsonValue, Vec<String>> {
+        self.apply_to("inputs", inputs)
+    }
+
+    /// Check the payload `what` (e.g. "output") against the schema, filling
+    /// in defaults, as `apply` does for inputs
+    pub fn apply_to(
+        &self,
+        what: &str,
+        inputs: JsonValue,
+    ) -> std::result::Result<JsonValue, Vec<String>> {
+        let mut inputs = match inputs {
+            JsonValue::Object(map) => map,
+            JsonValue::Null => Map::new(),
+            other => {
+                return Err(vec![format!(
+                    "{} must be an object, got {}",
+                    what,
+                    json_type(&other)
+                )])
+            }
+        };
+
+        let mut problems = Vec::new();
+        for field in &self.fields {
+            match inputs.get(&field.name) {
+                None | Some(JsonValue::Null) => {
+                    if let Some(default) = &field.default {
+                        inputs.insert(field.name.clone(), default.clone());
+                    } else if field.required {
+                        problems.push(format!("{} is required", field.name));
+                    }
+                }
+                Some(value) if !field.ty.accepts(value) => problems.push(format!(
+                    "{} must be {}, got {}",
+                    field.name,
+                    describe(&field.ty),
+                    json_type(value)
+                )),
+                Some(_) => {}
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(JsonValue::Object(inputs))
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// The schema as a type annotation of `Inputs`
+    ///
+    /// Inputs with a default are always present, so only those that are
+    /// neither required nor defaulted are optional.
+    pub fn to_type_expr(&self) -> TypeExpr {
+        TypeExpr::Object {
+            fields: self
+                .fields
+                .iter()
+                .map(|field| TypeField {
+                    name: field.name.clone(),
+                    optional: !field.required && field.default.is_none(),
+                    ty: field.ty.to_type_expr(),
+                    span: Span::default(),
+                })
+                .collect(),
+            span: Span::default(),
+        }
+    }
+}
+
+fn parse_field(name: &str, spec: &JsonValue) -> Result<InputField> {
+    let (ty, required, default, description) = match spec {
+        JsonValue::String(ty) => (ty.parse()?, None, None, None),
+        JsonValue::Object(spec) => {
+            if let Some(key) = spec.keys().find(|key| {
+                !matches!(
+                    key.as_str(),
+                    "type" | "required" | "default" | "description"
+                )
+            }) {
+                bail!("unknown key `{}`", key);
+            }
+            let ty = match spec.get("type") {
+                Some(JsonValue::String(ty)) => ty.parse()?,
+                Some(_) => bail!("`type` must be a string"),
+                None => InputType::Any,
+            };
+            let required = match spec.get("required") {
+                Some(JsonValue::Bool(required)) => Some(*required),
+                Some(_) => bail!("`required` must be true or false"),
+                None => None,
+            };
+            let description = match spec.get("description") {
+                Some(JsonValue::String(description)) => Some(description.clone()),
+                Some(_) => bail!("`description` must be a string"),
+                None => None,
+            };
+            let default = spec
+                .get("default")
+                .filter(|value| !value.is_null())
+                .cloned();
+            (ty, required, default, description)
+        }
+        _ => bail!("expected a type or a mapping with `type`"),
+    };
+
+    if let Some(default) = &default {
+        if !ty.accepts(default) {
+            bail!(
+                "default must be {}, got {}",
+                describe(&ty),
+                json_type(default)
+            );
+        }
+    }
+    Ok(InputField {
+        name: name.to_string(),
+        required: required.unwrap_or(default.is_none()),
+        ty,
+        default,
+        description,
+    })
+}
+
+fn describe(ty: &InputType) -> String {
+    match ty {
+        InputType::Array(_) => format!("an array ({})", ty),
+        InputType::Object | InputType::Any => format!("an {}", ty),
+        _ => format!("a {}", ty),
+    }
+}
+
+fn json_type(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(yaml: &str) -> Result<Option<InputSchema>> {
+        InputSchema::from_settings(&super::super::front_matter::parse(yaml)?)
+    }
+
+    const ORDER: &str = "
+inputs:
+  order_id: string
+  amount:
+    type: number
+    description: In cents
+  currency:
+    type: string
+    default: USD
+  tags:
+    type: string[]
+    required: false
+";
+
+    #[test]
+    fn test_parse_schema() {
+        let schema = schema(ORDER).unwrap().unwrap();
+        let names: Vec<_> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["amount", "currency", "order_id", "tags"]);
+
+        assert_eq!(
+            serde_json::to_value(&schema.fields[0]).unwrap(),
+            json!({ "name": "amount", "type": "number", "required": true, "description": "In cents" })
+        );
+        assert_eq!(schema.fields[1].default, Some(json!("USD")));
+        assert!(!schema.fields[1].required);
+        assert_eq!(
+            schema.fields[3].ty,
+            InputType::Array(Box::new(InputType::String))
+        );
+        assert!(!schema.fields[3].required);
+
+        assert_eq!(self::schema("queue: q").unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_schemas_are_rejected() {
+        let error = |yaml: &str| schema(yaml).unwrap_err().to_string();
+        assert_eq!(
+            error("inputs:\n  id: strin"),
+            "Invalid inputs schema: input `id`: unknown type `strin`"
+        );
+        assert_eq!(
+            error("inputs:\n  n:\n    type: number\n    default: one"),
+            "Invalid inputs schema: input `n`: default must be a number, got string"
+        );
+        assert_eq!(
+            error("inputs:\n  n:\n    type: number\n    min: 1"),
+            "Invalid inputs schema: input `n`: unknown key `min`"
+        );
+        assert!(schema("inputs: [a, b]").is_err());
+    }
+
+    #[test]
+    fn test_apply_fills_defaults_and_reports_every_problem() {
+        let schema = schema(ORDER).unwrap().unwrap();
+
+        assert_eq!(
+            schema.apply(json!({ "order_id": "o1", "amount": 5, "extra": true })),
+            Ok(json!({ "order_id": "o1", "amount": 5, "currency": "USD", "extra": true }))
+        );
+        assert_eq!(
+            schema.apply(json!({ "amount": "5", "tags": ["a", 1], "currency": null })),
+            Err(vec![
+                "amount must be a number, got string".to_string(),
+                "order_id is required".to_string(),
+                "tags must be an array (string[]), got array".to_string(),
+            ])
+        );
+        assert_eq!(
+            schema.apply(json!([1])),
+            Err(vec!["inputs must be an object, got array".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_schema_as_type_annotation() {
+        let schema = schema(ORDER).unwrap().unwrap();
+        let TypeExpr::Object { fields, .. } = schema.to_type_expr() else {
+            panic!("Expected an object type");
+        };
+        let optional: Vec<_> = fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.optional))
+            .collect();
+        assert_eq!(
+            optional,
+            vec![
+                ("amount", false),
+                ("currency", false),
+                ("order_id", false),
+                ("tags", true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Legacy workflow syntax
+//!
+//! Workflows written for the old engine wrap their body in a header and
+//! reach the runtime through its context parameter:
+//!
+//! ```text
+//! workflow(ctx, inputs) {
+//!     let order = await ctx.task("load_order", { id: inputs.id })
+//!     await ctx.sleep(60)
+//!     return order
+//! }
+//! ```
+//!
+//! `translate` rewrites such a file into Flow source: the header becomes
+//! `async function main(inputs)` and the context calls with a Flow
+//! equivalent become stdlib calls. Anything else done with the context is
+//! left as is and reported, so the file can be fixed by hand. Lines keep
+//! their numbers, so errors in the translation point at the original file.
+
+/// Context methods with a direct Flow equivalent
+const CONTEXT_CALLS: &[(&str, &str)] = &[
+    ("task", "Task.run"),
+    ("workflow", "Workflow.run"),
+    ("sleep", "Timer.delay"),
+    ("wait_for_signal", "Signal.next"),
+];
+
+/// A legacy file rewritten as Flow source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Translation {
+    pub source: String,
+    /// Uses of the context that have no Flow equivalent
+    pub unsupported: Vec<Unsupported>,
+}
+
+/// A legacy construct `translate` could not rewrite
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unsupported {
+    /// The construct, e.g. `ctx.log`
+    pub construct: String,
+    /// 0-based line it appears on
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Punct(char),
+}
+
+/// Tokens with their byte offsets, skipping whitespace, comments and strings
+fn tokenize(source: &str) -> Vec<(usize, Token<'_>)> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if source[i..].starts_with("//") {
+            i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+        } else if source[i..].starts_with("/*") {
+            i = source[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |n| i + n + 4);
+        } else if c == b'"' {
+            i = source[i + 1..].find('"').map_or(bytes.len(), |n| i + n + 2);
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push((start, Token::Ident(&source[start..i])));
+        } else {
+            let ch = source[i..].chars().next().unwrap();
+            tokens.push((i, Token::Punct(ch)));
+            i += ch.len_utf8();
+        }
+    }
+    tokens
+}
+
+/// The header's parameters and the offset just past its `{`
+fn header<'a>(tokens: &[(usize, Token<'a>)]) -> Option<(Vec<&'a str>, usize, usize)> {
+    let mut iter = tokens.iter();
+    if iter.next()?.1 != Token::Ident("workflow") || iter.next()?.1 != Token::Punct('(') {
+        return None;
+    }
+    let mut params = Vec::new();
+    loop {
+        match iter.next()?.1 {
+            Token::Punct(')') => break,
+            Token::Ident(name) if params.is_empty() => params.push(name),
+            Token::Punct(',') if !params.is_empty() => match iter.next()?.1 {
+                Token::Ident(name) => params.push(name),
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    match iter.next()? {
+        (offset, Token::Punct('{')) => Some((params, tokens.len() - iter.len(), offset + 1)),
+        _ => None,
+    }
+}
+
+/// Rewrite a legacy file as Flow source
+///
+/// Returns `None` if the source is not in the legacy
+/// `workflow(ctx, inputs) { ... }` form.
+pub fn translate(source: &str) -> Option<Translation> {
+    let tokens = tokenize(source);
+    let (params, body_start, header_end) = header(&tokens)?;
+    let ctx = params.first().copied();
+    let inputs = params.get(1).copied().unwrap_or("");
+
+    let mut out = String::with_capacity(source.len());
+    out.push_str(&source[..tokens[0].0]);
+    out.push_str(&format!("async function main({}) {{", inputs));
+    let mut copied = header_end;
+    let mut unsupported = Vec::new();
+
+    for window in tokens[body_start..].windows(4) {
+        let [(start, Token::Ident(name)), (_, Token::Punct('.')), (method_start, Token::Ident(method)), next] =
+            window
+        else {
+            continue;
+        };
+        if Some(*name) != ctx {
+            continue;
+        }
+        let replacement = CONTEXT_CALLS
+            .iter()
+            .find(|(legacy, _)| legacy == method)
+            .filter(|_| next.1 == Token::Punct('('));
+        match replacement {
+            Some((_, flow)) => {
+                out.push_str(&source[copied..*start]);
+                out.push_str(flow);
+                copied = method_start + method.len();
+            }
+            None => unsupported.push(Unsupported {
+                construct: format!("{}.{}", name, method),
+                line: source[..*start].matches('\n').count(),
+            }),
+        }
+    }
+    out.push_str(&source[copied..]);
+
+    Some(Translation {
+        source: out,
+        unsupported,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translates_header_and_context_calls() {
+        let source = r#"workflow(ctx, inputs) {
+    let order = await ctx.task("load_order", { id: inputs.id })
+    await ctx.sleep(60)
+    return order
+}"#;
+        let translation = translate(source).unwrap();
+        assert_eq!(
+            translation.source,
+            r#"async function main(inputs) {
+    let order = await Task.run("load_order", { id: inputs.id })
+    await Timer.delay(60)
+    return order
+}"#
+        );
+        assert!(translation.unsupported.is_empty());
+        crate::parser::parse_workflow(&translation.source).unwrap();
+    }
+
+    #[test]
+    fn test_reports_context_uses_without_equivalent() {
+        let source = "workflow(ctx) {\n  ctx.log(\"hi\")\n  return ctx.execution_id\n}";
+        let translation = translate(source).unwrap();
+        assert_eq!(
+            translation.unsupported,
+            vec![
+                Unsupported {
+                    construct: "ctx.log".to_string(),
+                    line: 1
+                },
+                Unsupported {
+                    construct: "ctx.execution_id".to_string(),
+                    line: 2
+                },
+            ]
+        );
+        assert!(translation.source.starts_with("async function main() {"));
+    }
+
+    #[test]
+    fn test_leaves_strings_comments_and_flow_source_alone() {
+        let source = "// old file\nworkflow(c, i) {\n  return \"c.task(\" + i.x // c.sleep(1)\n}";
+        let translation = translate(source).unwrap();
+        assert_eq!(
+            translation.source,
+            "// old file\nasync function main(i) {\n  return \"c.task(\" + i.x // c.sleep(1)\n}"
+        );
+
+        assert!(translate("let workflow = 1\nreturn workflow").is_none());
+        assert!(translate("return await Task.run(\"a\", {})").is_none());
+    }
+}
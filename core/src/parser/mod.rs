@@ -7,10 +7,16 @@ use pest_derive::Parser;
 use serde::{Deserialize, Serialize};
 
 use super::executor::types::ast::{
-    BinaryOp, DeclareTarget, Expr, ForLoopKind, MemberAccess, Span, Stmt, VarKind,
+    BinaryOp, DeclareTarget, Expr, ForLoopKind, MemberAccess, Span, Stmt, TypeExpr, TypeField,
+    VarKind,
 };
 
+pub mod analysis;
+pub mod front_matter;
+pub mod input_schema;
+pub mod legacy;
 pub mod semantic_validator;
+pub mod task_graph;
 
 #[cfg(test)]
 mod tests;
@@ -25,6 +31,9 @@ pub struct WorkflowDef {
     /// Optional YAML front matter
     #[serde(skip_serializing_if = "Option::is_none")]
     pub front_matter: Option<String>,
+    /// Declared type of `Inputs`, from `async function main(inputs: T)`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_type: Option<TypeExpr>,
     /// Span of the entire workflow
     #[serde(default, skip_serializing_if = "is_default_span")]
     pub span: Span,
@@ -163,6 +172,33 @@ pub fn parse_workflow(source: &str) -> ParseResult<WorkflowDef> {
     }
 }
 
+/// Serialize a workflow's AST as canonical JSON
+///
+/// Object keys are sorted and the output is pretty-printed with a trailing
+/// newline, so an unchanged AST always produces byte-identical text. Golden
+/// tests and downstream tools diff this to catch unintended AST changes.
+pub fn to_canonical_json(workflow: &WorkflowDef) -> String {
+    let value = serde_json::to_value(workflow).expect("AST serializes to JSON");
+    let mut json = serde_json::to_string_pretty(&sort_keys(value)).expect("JSON value serializes");
+    json.push('\n');
+    json
+}
+
+/// Rebuild objects with sorted keys, whatever map ordering serde_json uses
+fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let sorted: std::collections::BTreeMap<_, _> =
+                map.into_iter().map(|(k, v)| (k, sort_keys(v))).collect();
+            serde_json::Value::Object(sorted.into_iter().collect())
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(sort_keys).collect())
+        }
+        other => other,
+    }
+}
+
 /// Parse a Flow source string into an AST statement (testing API)
 pub fn parse(source: &str) -> ParseResult<Stmt> {
     let mut pairs = FlowParser::parse(Rule::program, source)?;
@@ -233,6 +269,7 @@ fn build_bare_workflow(
     Ok(WorkflowDef {
         body,
         front_matter,
+        input_type: None,
         span: program_span,
     })
 }
@@ -242,17 +279,135 @@ fn build_main_function(
     source: &str,
     program_span: Span,
 ) -> ParseResult<WorkflowDef> {
-    let mut inner = pair.into_inner();
-    let block_pair = inner.next().unwrap();
-    let body = build_block(block_pair, source)?;
+    let mut input_type = None;
+    let mut param = None;
+    let mut body = None;
+
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::main_param => {
+                let param_span = pair_to_span(&inner, source);
+                let mut parts = inner.into_inner();
+                let name_pair = parts.next().unwrap();
+                param = Some((
+                    name_pair.as_str().to_string(),
+                    pair_to_span(&name_pair, source),
+                    param_span,
+                ));
+                if let Some(ann_pair) = parts.next() {
+                    input_type = Some(build_type_annotation(ann_pair, source)?);
+                }
+            }
+            Rule::block => body = Some(build_block(inner, source)?),
+            _ => {
+                return Err(ParseError::BuildError(
+                    format!("Unexpected main_function content: {:?}", inner.as_rule()),
+                    Some(pair_to_span(&inner, source)),
+                ))
+            }
+        }
+    }
+
+    let mut body = body.unwrap();
+
+    // `main(inputs)` binds the parameter name to Inputs for the body
+    if let Some((name, name_span, param_span)) = param {
+        if name != "Inputs" {
+            if let Stmt::Block { body: stmts, .. } = &mut body {
+                stmts.insert(
+                    0,
+                    Stmt::Declare {
+                        var_kind: VarKind::Const,
+                        target: DeclareTarget::Simple {
+                            name,
+                            span: name_span,
+                        },
+                        type_ann: None,
+                        init: Some(Expr::Ident {
+                            name: "Inputs".to_string(),
+                            span: param_span,
+                        }),
+                        span: param_span,
+                    },
+                );
+            }
+        }
+    }
 
     Ok(WorkflowDef {
         body,
         front_matter: None,
+        input_type,
         span: program_span,
     })
 }
 
+/* ===================== Type Annotations ===================== */
+
+fn build_type_annotation(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseResult<TypeExpr> {
+    let type_pair = pair.into_inner().next().unwrap();
+    build_type_expr(type_pair, source)
+}
+
+fn build_type_expr(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseResult<TypeExpr> {
+    let span = pair_to_span(&pair, source);
+
+    match pair.as_rule() {
+        Rule::type_expr => {
+            let mut variants = pair
+                .into_inner()
+                .map(|p| build_type_expr(p, source))
+                .collect::<ParseResult<Vec<_>>>()?;
+            if variants.len() == 1 {
+                Ok(variants.pop().unwrap())
+            } else {
+                Ok(TypeExpr::Union { variants, span })
+            }
+        }
+        Rule::type_postfix => {
+            let mut inner = pair.into_inner();
+            let mut ty = build_type_expr(inner.next().unwrap(), source)?;
+            for suffix in inner {
+                let suffix_span = pair_to_span(&suffix, source);
+                ty = TypeExpr::Array {
+                    span: ty.span().merge(&suffix_span),
+                    element: Box::new(ty),
+                };
+            }
+            Ok(ty)
+        }
+        Rule::type_primary => build_type_expr(pair.into_inner().next().unwrap(), source),
+        Rule::identifier => Ok(TypeExpr::Named {
+            name: pair.as_str().to_string(),
+            span,
+        }),
+        Rule::object_type => {
+            let mut fields = Vec::new();
+            for field_pair in pair.into_inner() {
+                let field_span = pair_to_span(&field_pair, source);
+                let mut parts = field_pair.into_inner();
+                let name = parts.next().unwrap().as_str().to_string();
+                let mut next = parts.next().unwrap();
+                let optional = next.as_rule() == Rule::type_optional;
+                if optional {
+                    next = parts.next().unwrap();
+                }
+                fields.push(TypeField {
+                    name,
+                    optional,
+                    ty: build_type_expr(next, source)?,
+                    span: field_span,
+                });
+            }
+            Ok(TypeExpr::Object { fields, span })
+        }
+        _ => Err(ParseError::BuildError(
+            format!("Unexpected type rule: {:?}", pair.as_rule()),
+            Some(span),
+        )),
+    }
+}
+
 fn build_block(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseResult<Stmt> {
     let span = pair_to_span(&pair, source);
     let statements: Result<Vec<Stmt>, ParseError> = pair
@@ -375,11 +530,15 @@ fn build_declare_stmt(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseR
     let target_pair = inner.next().unwrap();
     let target = build_declare_target(target_pair, source)?;
 
-    let init = if let Some(expr_pair) = inner.next() {
-        Some(build_expression(expr_pair, source)?)
-    } else {
-        None
-    };
+    let mut type_ann = None;
+    let mut init = None;
+    for rest in inner {
+        if rest.as_rule() == Rule::type_annotation {
+            type_ann = Some(build_type_annotation(rest, source)?);
+        } else {
+            init = Some(build_expression(rest, source)?);
+        }
+    }
 
     if matches!(target, DeclareTarget::Destructure { .. }) && init.is_none() {
         return Err(ParseError::BuildError(
@@ -391,6 +550,7 @@ fn build_declare_stmt(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseR
     Ok(Stmt::Declare {
         var_kind,
         target,
+        type_ann,
         init,
         span,
     })
@@ -608,6 +768,12 @@ fn build_statement(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseResu
                 span,
             })
         }
+        Rule::throw_stmt => {
+            // Skip the keyword
+            let expr_pair = pair.into_inner().nth(1).unwrap();
+            let value = build_expression(expr_pair, source)?;
+            Ok(Stmt::Throw { value, span })
+        }
         Rule::if_stmt => build_if_stmt(pair, source),
         Rule::while_stmt => build_while_stmt(pair, source),
         Rule::for_loop_stmt => build_for_loop_stmt(pair, source),
@@ -780,6 +946,7 @@ fn build_expression(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseRes
             let value = content.as_str().to_string();
             Ok(Expr::LitStr { v: value, span })
         }
+        Rule::template => build_template(pair, source),
         Rule::null_lit => Ok(Expr::LitNull { span }),
         Rule::object_lit => build_object_literal(pair, source),
         Rule::array_lit => build_array_literal(pair, source),
@@ -790,6 +957,34 @@ fn build_expression(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseRes
     }
 }
 
+/// Split a template literal into its text and interpolated expressions
+///
+/// There is always one more text part than expressions, so the parts
+/// interleave as `quasis[0] exprs[0] quasis[1] ... quasis[n]`.
+fn build_template(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseResult<Expr> {
+    let span = pair_to_span(&pair, source);
+    let mut quasis = vec![String::new()];
+    let mut exprs = Vec::new();
+
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::template_chars => quasis.last_mut().unwrap().push_str(part.as_str()),
+            Rule::template_sub => {
+                let inner = part.into_inner().next().unwrap();
+                exprs.push(build_expression(inner, source)?);
+                quasis.push(String::new());
+            }
+            _ => unreachable!("Unexpected template rule: {:?}", part.as_rule()),
+        }
+    }
+
+    Ok(Expr::Template {
+        quasis,
+        exprs,
+        span,
+    })
+}
+
 fn build_arg_list(pair: pest::iterators::Pair<Rule>, source: &str) -> ParseResult<Vec<Expr>> {
     pair.into_inner()
         .map(|expr_pair| build_expression(expr_pair, source))
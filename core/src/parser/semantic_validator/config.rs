@@ -0,0 +1,262 @@
+//! Rule settings
+//!
+//! The `validator` key of a workflow's settings sets the level of each rule
+//! by code, either in its front matter or for every workflow under
+//! `[workflow_defaults.validator]` in rhythm.toml:
+//!
+//! ```yaml
+//! validator:
+//!   unawaited-task: error
+//!   type-mismatch: off
+//! ```
+//!
+//! Levels are `off`, `warn` and `error`; underscores in codes are read as
+//! dashes. Settings for rules this version doesn't have are ignored. Raising
+//! a rule to `error` makes registration reject workflows it fires on.
+//!
+//! A `// rhythm-ignore: code, ...` comment drops those findings on its own
+//! line, or on the next line when the comment stands alone. Without codes it
+//! drops every finding there.
+//!
+//! Findings the engine can't run past (`await-position`,
+//! `invalid-front-matter`) can't be relaxed or ignored.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value as JsonValue};
+
+use super::rules::AWAIT_POSITION;
+use crate::parser::analysis::{Diagnostic, Severity, INVALID_FRONT_MATTER};
+
+/// Rules whose findings always stand
+const FIXED: &[&str] = &[AWAIT_POSITION, INVALID_FRONT_MATTER];
+
+const IGNORE_DIRECTIVE: &str = "rhythm-ignore";
+
+/// Level a rule is set to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleLevel {
+    Off,
+    Warn,
+    Error,
+}
+
+impl RuleLevel {
+    fn parse(value: &JsonValue) -> Option<Self> {
+        match value {
+            JsonValue::String(s) => match s.as_str() {
+                "off" => Some(RuleLevel::Off),
+                "warn" | "warning" => Some(RuleLevel::Warn),
+                "error" => Some(RuleLevel::Error),
+                _ => None,
+            },
+            // YAML 1.1 reads a bare `off` as false
+            JsonValue::Bool(false) => Some(RuleLevel::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Rule levels set for a workflow
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidatorConfig {
+    levels: BTreeMap<String, RuleLevel>,
+}
+
+impl ValidatorConfig {
+    /// The rule levels under `validator` in resolved settings
+    pub fn from_settings(settings: &Map<String, JsonValue>) -> Result<Self> {
+        let Some(validator) = settings.get("validator") else {
+            return Ok(Self::default());
+        };
+        let JsonValue::Object(rules) = validator else {
+            bail!("Invalid validator settings: expected `rule: level` entries");
+        };
+
+        let mut levels = BTreeMap::new();
+        for (rule, value) in rules {
+            let code = rule.replace('_', "-");
+            let level = RuleLevel::parse(value).ok_or_else(|| {
+                anyhow!(
+                    "Invalid validator settings: `{}` must be off, warn or error",
+                    rule
+                )
+            })?;
+            if FIXED.contains(&code.as_str()) && level != RuleLevel::Error {
+                bail!(
+                    "Invalid validator settings: `{}` can't be relaxed, the engine can't run past it",
+                    rule
+                );
+            }
+            levels.insert(code, level);
+        }
+        Ok(Self { levels })
+    }
+
+    /// Set `code` to `level`, as a `validator` setting would
+    pub fn with_level(mut self, code: &str, level: RuleLevel) -> Self {
+        self.levels.insert(code.replace('_', "-"), level);
+        self
+    }
+
+    /// The level `code` is set to, if any
+    pub fn level(&self, code: &str) -> Option<RuleLevel> {
+        self.levels.get(code).copied()
+    }
+
+    /// Apply the rule levels and the `rhythm-ignore` comments in `source`
+    pub fn apply(&self, diagnostics: Vec<Diagnostic>, source: &str) -> Vec<Diagnostic> {
+        let ignored = ignored_lines(source);
+        diagnostics
+            .into_iter()
+            .filter_map(|mut d| {
+                if FIXED.contains(&d.code) {
+                    return Some(d);
+                }
+                if is_ignored(&ignored, &d) {
+                    return None;
+                }
+                match self.level(d.code) {
+                    Some(RuleLevel::Off) => return None,
+                    Some(RuleLevel::Warn) => d.severity = Severity::Warning,
+                    Some(RuleLevel::Error) => d.severity = Severity::Error,
+                    None => {}
+                }
+                Some(d)
+            })
+            .collect()
+    }
+}
+
+/// Codes ignored per line; `None` ignores every code
+type IgnoredLines = HashMap<usize, Option<BTreeSet<String>>>;
+
+fn ignored_lines(source: &str) -> IgnoredLines {
+    let mut ignored = IgnoredLines::new();
+    for (line, text) in source.lines().enumerate() {
+        let Some(at) = text.find(IGNORE_DIRECTIVE) else {
+            continue;
+        };
+        let before = text[..at].trim_end();
+        let Some(code_before) = before.strip_suffix("//") else {
+            continue;
+        };
+        let rest = &text[at + IGNORE_DIRECTIVE.len()..];
+        let codes = match rest.trim_start().strip_prefix(':') {
+            Some(list) => Some(
+                list.split(',')
+                    .map(|code| code.trim().replace('_', "-"))
+                    .filter(|code| !code.is_empty())
+                    .collect(),
+            ),
+            None if rest.trim().is_empty() => None,
+            None => continue,
+        };
+        // A comment on its own line covers the line below it
+        let target = if code_before.trim().is_empty() {
+            line + 1
+        } else {
+            line
+        };
+        ignored.insert(target, codes);
+    }
+    ignored
+}
+
+fn is_ignored(ignored: &IgnoredLines, diagnostic: &Diagnostic) -> bool {
+    match ignored.get(&diagnostic.span.start_line) {
+        Some(None) => true,
+        Some(Some(codes)) => codes.contains(diagnostic.code),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::semantic_validator::rules::UNAWAITED_TASK;
+    use serde_json::json;
+
+    fn settings(value: JsonValue) -> Map<String, JsonValue> {
+        match value {
+            JsonValue::Object(map) => map,
+            _ => panic!("expected an object"),
+        }
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<(&'static str, Severity)> {
+        diagnostics.iter().map(|d| (d.code, d.severity)).collect()
+    }
+
+    fn check(config: &ValidatorConfig, source: &str) -> Vec<Diagnostic> {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        config.apply(super::super::check_workflow(&workflow), source)
+    }
+
+    #[test]
+    fn test_levels_read_from_settings() {
+        let config = ValidatorConfig::from_settings(&settings(json!({
+            "validator": { "unawaited_task": "error", "type-mismatch": "off", "shadowing": "warn" }
+        })))
+        .unwrap();
+        assert_eq!(config.level(UNAWAITED_TASK), Some(RuleLevel::Error));
+        assert_eq!(config.level("type-mismatch"), Some(RuleLevel::Off));
+        assert_eq!(config.level("await-non-task"), None);
+
+        let err = ValidatorConfig::from_settings(&settings(json!({
+            "validator": { "unawaited-task": "loud" }
+        })))
+        .unwrap_err();
+        assert!(err.to_string().contains("must be off, warn or error"));
+
+        let err = ValidatorConfig::from_settings(&settings(json!({
+            "validator": { "await_position": "off" }
+        })))
+        .unwrap_err();
+        assert!(err.to_string().contains("can't be relaxed"));
+    }
+
+    #[test]
+    fn test_levels_override_severity() {
+        let source = "Task.run(\"a\", {})\nlet n: number = \"one\"\nreturn n";
+        let defaults = ValidatorConfig::default();
+        assert_eq!(
+            codes(&check(&defaults, source)),
+            vec![
+                (UNAWAITED_TASK, Severity::Warning),
+                ("type-mismatch", Severity::Warning)
+            ]
+        );
+
+        let config = defaults
+            .with_level(UNAWAITED_TASK, RuleLevel::Error)
+            .with_level("type_mismatch", RuleLevel::Off);
+        assert_eq!(
+            codes(&check(&config, source)),
+            vec![(UNAWAITED_TASK, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn test_ignore_comments() {
+        let config = ValidatorConfig::default().with_level(UNAWAITED_TASK, RuleLevel::Error);
+        let source = r#"
+            // rhythm-ignore: unawaited-task
+            Task.run("a", {})
+            Task.run("b", {}) // rhythm-ignore
+            Task.run("c", {}) // rhythm-ignore: type-mismatch
+            let t = Task.run("d", {})
+            // rhythm-ignore: await-position
+            Log.info(await t)
+        "#;
+        let found = check(&config, source);
+        assert_eq!(
+            found
+                .iter()
+                .map(|d| (d.code, d.span.start_line))
+                .collect::<Vec<_>>(),
+            vec![(UNAWAITED_TASK, 4), (AWAIT_POSITION, 7)]
+        );
+    }
+}
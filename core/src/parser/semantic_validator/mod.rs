@@ -0,0 +1,274 @@
+//! Semantic validation for Flow v2 workflows
+//!
+//! This module validates WorkflowDef structures after parsing to ensure they meet
+//! semantic requirements that can't be enforced by the grammar alone. The
+//! rules live in `rules`, one per file; `config` reads the settings that
+//! adjust or silence them.
+
+use std::collections::BTreeSet;
+
+use super::analysis::{analyze_workflow, analyze_workflow_with_tasks, Diagnostic, Severity};
+use super::WorkflowDef;
+
+mod config;
+pub mod rules;
+
+pub use config::{RuleLevel, ValidatorConfig};
+
+/* ===================== Error Types ===================== */
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// Validation errors (for future expansion)
+    Custom(String),
+    /// `Error` findings of the validation rules
+    Rules(Vec<Diagnostic>),
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::Custom(msg) => write!(f, "{}", msg),
+            ValidationError::Rules(diagnostics) => {
+                for (i, d) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(
+                        f,
+                        "{}:{}: {} [{}]",
+                        d.span.start_line + 1,
+                        d.span.start_col + 1,
+                        d.message,
+                        d.code
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+pub type ValidationResult<T> = Result<T, ValidationError>;
+
+/* ===================== Public API ===================== */
+
+/// Reserved identifiers that cannot be used as parameter names
+#[allow(dead_code)]
+const RESERVED_IDENTIFIERS: &[&str] = &[
+    "await",
+    "async",
+    "let",
+    "const",
+    "var",
+    "function",
+    "return",
+    "if",
+    "else",
+    "for",
+    "while",
+    "break",
+    "continue",
+    "throw",
+    "try",
+    "catch",
+    "true",
+    "false",
+    "null",
+    "undefined",
+];
+
+/// Validate a workflow definition
+///
+/// Performs semantic validation on parsed workflows. The parser already enforces syntax,
+/// so this function is reserved for semantic rules that can't be enforced by grammar.
+/// Fails with the `Error` findings of `rules`, which the VM can't run:
+///
+/// - `await-position`: `await` nested in an expression instead of heading a statement
+/// - `const-reassignment`: assigning to a `const`
+/// - `discarded-push`: `xs.push(...)` as a statement, which leaves `xs` as it was
+/// - `use-before-declaration`: a `let`/`const` used above its declaration
+///
+/// Type annotations are checked by `check_workflow`, which warns instead of rejecting.
+pub fn validate_workflow(workflow: &WorkflowDef) -> ValidationResult<()> {
+    let errors: Vec<Diagnostic> = rules::check(workflow)
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError::Rules(errors))
+    }
+}
+
+/// Collect every diagnostic for a workflow, in source order
+///
+/// Runs the validation rules, the gradual type checker and the other
+/// analysis rules. Only the validation errors block registration; callers
+/// log the rest or show them in the editor.
+pub fn check_workflow(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    in_source_order(rules::check(workflow), analyze_workflow(workflow))
+}
+
+/// `check_workflow`, also checking `Task.run` names against `known_tasks`
+pub fn check_workflow_with_tasks(
+    workflow: &WorkflowDef,
+    known_tasks: &BTreeSet<String>,
+) -> Vec<Diagnostic> {
+    in_source_order(
+        rules::check(workflow),
+        analyze_workflow_with_tasks(workflow, known_tasks),
+    )
+}
+
+/// Validation with a workflow's rule settings applied
+///
+/// The free functions above validate with every rule at its default level.
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    config: ValidatorConfig,
+    known_tasks: Option<BTreeSet<String>>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: ValidatorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Check `Task.run` names against `known_tasks`
+    pub fn with_known_tasks(mut self, known_tasks: BTreeSet<String>) -> Self {
+        self.known_tasks = Some(known_tasks);
+        self
+    }
+
+    /// Collect the diagnostics for a workflow parsed from `source`, in
+    /// source order
+    ///
+    /// `source` is only read for `rhythm-ignore` comments.
+    pub fn check(&self, workflow: &WorkflowDef, source: &str) -> Vec<Diagnostic> {
+        let diagnostics = match &self.known_tasks {
+            Some(known) => check_workflow_with_tasks(workflow, known),
+            None => check_workflow(workflow),
+        };
+        self.config.apply(diagnostics, source)
+    }
+
+    /// Fails with the findings at `Error` level, rules raised to it included
+    pub fn validate(&self, workflow: &WorkflowDef, source: &str) -> ValidationResult<()> {
+        let errors: Vec<Diagnostic> = self
+            .check(workflow, source)
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError::Rules(errors))
+        }
+    }
+}
+
+fn in_source_order(mut diagnostics: Vec<Diagnostic>, more: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics.extend(more);
+    diagnostics.sort_by_key(|d| (d.span.start_line, d.span.start_col));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_workflow_simple() {
+        // Valid workflow with bare syntax
+        let source = r#"
+            return 42
+        "#;
+
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        assert!(validate_workflow(&workflow).is_ok());
+    }
+
+    #[test]
+    fn test_validate_workflow_with_front_matter() {
+        // Valid workflow with front matter
+        let source = r#"
+```
+name: test_workflow
+```
+let x = 42
+return x
+        "#;
+
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        assert!(validate_workflow(&workflow).is_ok());
+        assert!(workflow.front_matter.is_some());
+    }
+
+    #[test]
+    fn test_validate_workflow_complex() {
+        // Valid workflow with multiple statements
+        let source = r#"
+            let x = 42
+            let y = x + 10
+            return y
+        "#;
+
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        assert!(validate_workflow(&workflow).is_ok());
+    }
+
+    #[test]
+    fn test_check_workflow_reports_type_mismatch() {
+        let source = r#"
+            let count: number = "three"
+            return count
+        "#;
+
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        assert!(validate_workflow(&workflow).is_ok());
+        let warnings = check_workflow(&workflow);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, crate::parser::analysis::TYPE_MISMATCH);
+    }
+
+    #[test]
+    fn test_validate_workflow_rejects_nested_await() {
+        let source = r#"
+            let t = Task.run("a", {})
+            Task.run("b", {})
+            return (await t) + 1
+        "#;
+
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        let error = validate_workflow(&workflow).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("4:21: `await` must head its statement"));
+
+        // Warnings are reported alongside, but don't fail validation
+        let codes: Vec<_> = check_workflow(&workflow).iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec![rules::UNAWAITED_TASK, rules::AWAIT_POSITION]);
+    }
+
+    #[test]
+    fn test_validator_applies_rule_levels() {
+        let source = "Task.run(\"a\", {})\nreturn 1";
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        assert!(Validator::new().validate(&workflow, source).is_ok());
+
+        let strict = Validator::new().with_config(
+            ValidatorConfig::default().with_level(rules::UNAWAITED_TASK, RuleLevel::Error),
+        );
+        let error = strict.validate(&workflow, source).unwrap_err();
+        assert!(error.to_string().ends_with("[unawaited-task]"));
+    }
+}
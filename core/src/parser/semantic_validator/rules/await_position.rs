@@ -0,0 +1,100 @@
+//! `await` where the VM can't suspend
+//!
+//! A workflow can only suspend between statements, so `await` must be the
+//! whole expression of a statement: `await x` on its own, `let y = await x`,
+//! `y = await x`, `return await x` or `throw await x`. Anywhere else, such as
+//! in a condition, an argument, an operand or a literal, the VM fails at
+//! runtime with an internal error.
+
+use super::{own_exprs, walk_exprs, walk_stmts};
+use crate::executor::types::ast::{Expr, Stmt};
+use crate::parser::analysis::{Diagnostic, Severity};
+use crate::parser::WorkflowDef;
+
+/// `await` nested in an expression instead of heading a statement
+pub const AWAIT_POSITION: &str = "await-position";
+
+pub(super) fn check(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    walk_stmts(&workflow.body, &mut |stmt| {
+        let root = suspendable_root(stmt);
+        for expr in own_exprs(stmt) {
+            let checked = match (root, expr) {
+                (Some(root), Expr::Await { inner, .. }) if std::ptr::eq(root, expr) => inner,
+                _ => expr,
+            };
+            walk_exprs(checked, &mut |e| {
+                if let Expr::Await { span, .. } = e {
+                    diagnostics.push(Diagnostic {
+                        code: AWAIT_POSITION,
+                        severity: Severity::Error,
+                        message: "`await` must head its statement (`await x`, `let y = await x`, \
+                                  `y = await x`, `return await x`); await into a variable first"
+                            .to_string(),
+                        span: *span,
+                    });
+                }
+            });
+        }
+    });
+    diagnostics
+}
+
+/// The expression of a statement the VM can suspend on, if any
+fn suspendable_root(stmt: &Stmt) -> Option<&Expr> {
+    match stmt {
+        Stmt::Declare { init: Some(e), .. }
+        | Stmt::Return { value: Some(e), .. }
+        | Stmt::Throw { value: e, .. }
+        | Stmt::Expr { expr: e, .. } => Some(e),
+        Stmt::Assign { path, value, .. } if path.is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(source: &str) -> Vec<usize> {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        check(&workflow).iter().map(|d| d.span.start_line).collect()
+    }
+
+    #[test]
+    fn test_await_heading_a_statement_is_allowed() {
+        assert!(lines(
+            r#"
+            let t = Task.run("a", {})
+            await t
+            let x = await t
+            x = await Task.run("b", {x})
+            if (x) { return await t }
+            throw await t
+        "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_nested_await_is_flagged() {
+        assert_eq!(
+            lines(
+                r#"
+                let t = Task.run("a", {})
+                let x = (await t) + 1
+                if (await t) { x = 1 }
+                Log.info(await t)
+                let o = {a: await t}
+                o.a = await t
+                while (await t) { }
+                for (let v of await t) { }
+                let s = `${await t}`
+                let y = true ? await t : 1
+                return await (await t)
+            "#
+            ),
+            vec![2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Assigning to a `const`
+//!
+//! The VM doesn't enforce `const`, so without this rule the assignment
+//! silently succeeds. Changing a property of a `const` object is allowed,
+//! as in JavaScript.
+
+use super::scopes;
+use crate::executor::types::ast::{Stmt, VarKind};
+use crate::parser::analysis::{Diagnostic, Severity};
+use crate::parser::WorkflowDef;
+
+/// `x = ...` where `x` is declared with `const`
+pub const CONST_REASSIGNMENT: &str = "const-reassignment";
+
+pub(super) fn check(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    scopes::walk(&workflow.body, &mut |stmt, scopes| {
+        let Stmt::Assign {
+            var,
+            var_span,
+            path,
+            ..
+        } = stmt
+        else {
+            return;
+        };
+        if path.is_empty() && scopes.lookup(var) == Some(VarKind::Const) {
+            diagnostics.push(Diagnostic {
+                code: CONST_REASSIGNMENT,
+                severity: Severity::Error,
+                message: format!("Cannot assign to `{}`: it is declared with `const`", var),
+                span: *var_span,
+            });
+        }
+    });
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(source: &str) -> Vec<usize> {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        check(&workflow).iter().map(|d| d.span.start_line).collect()
+    }
+
+    #[test]
+    fn test_assigning_to_const_is_flagged() {
+        assert_eq!(
+            lines(
+                r#"
+                const total = 1
+                let count = 0
+                count = 2
+                total = 2
+                if (count) { total = 3 }
+            "#
+            ),
+            vec![4, 5]
+        );
+    }
+
+    #[test]
+    fn test_shadowing_and_property_writes_are_allowed() {
+        assert!(lines(
+            r#"
+            const order = {id: 1}
+            order.id = 2
+            const x = 1
+            for (let x of [1, 2]) { x = 3 }
+            try { throw 1 } catch (x) { x = 2 }
+        "#
+        )
+        .is_empty());
+    }
+}
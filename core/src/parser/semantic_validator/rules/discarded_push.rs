@@ -0,0 +1,92 @@
+//! `push` results that are dropped
+//!
+//! Values are immutable, so `push` returns a new array and leaves the one
+//! it was called on as it was. `items.push(x)` on its own line, as it would
+//! be written in JavaScript, therefore does nothing, and the value is lost
+//! without a trace. Assign the result back: `items = items.push(x)`.
+
+use super::walk_stmts;
+use crate::executor::types::ast::{Expr, Stmt};
+use crate::parser::analysis::{Diagnostic, Severity};
+use crate::parser::WorkflowDef;
+
+/// `xs.push(...)` as a statement, its result thrown away
+pub const DISCARDED_PUSH: &str = "discarded-push";
+
+pub(super) fn check(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    walk_stmts(&workflow.body, &mut |stmt| {
+        let Stmt::Expr {
+            expr: Expr::Call { callee, span, .. },
+            ..
+        } = stmt
+        else {
+            return;
+        };
+        let Expr::Member {
+            object, property, ..
+        } = callee.as_ref()
+        else {
+            return;
+        };
+        if property != "push" {
+            return;
+        }
+        let message = match object.as_ref() {
+            Expr::Ident { name, .. } => format!(
+                "`push` returns a new array and leaves `{0}` unchanged; write `{0} = {0}.push(...)`",
+                name
+            ),
+            _ => "`push` returns a new array and leaves the receiver unchanged; assign the result"
+                .to_string(),
+        };
+        diagnostics.push(Diagnostic {
+            code: DISCARDED_PUSH,
+            severity: Severity::Error,
+            message,
+            span: *span,
+        });
+    });
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<String> {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        check(&workflow).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn test_push_as_a_statement_is_flagged() {
+        assert_eq!(
+            messages(
+                r#"
+                let items = []
+                for (let x of [1, 2]) { items.push(x) }
+                return items
+            "#
+            ),
+            vec!["`push` returns a new array and leaves `items` unchanged; write `items = items.push(...)`"]
+        );
+        assert_eq!(
+            messages("let order = {lines: []}\norder.lines.push(1)").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_kept_push_is_clean() {
+        assert!(messages(
+            r#"
+            let items = [1]
+            items = items.push(2)
+            const more = items.push(3, 4)
+            return items.push(5)
+        "#
+        )
+        .is_empty());
+    }
+}
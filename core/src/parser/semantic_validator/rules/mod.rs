@@ -0,0 +1,141 @@
+//! Semantic validation rules
+//!
+//! Each rule walks a workflow and reports diagnostics. `Error` findings make
+//! `validate_workflow` reject the workflow, because the VM can't run it;
+//! `Warning`s are advisory, like the analysis rules.
+
+mod await_position;
+mod const_reassignment;
+mod discarded_push;
+mod scopes;
+mod unawaited_task;
+mod use_before_declaration;
+
+use crate::executor::types::ast::{Expr, MemberAccess, Stmt};
+use crate::parser::analysis::Diagnostic;
+use crate::parser::WorkflowDef;
+
+pub use await_position::AWAIT_POSITION;
+pub use const_reassignment::CONST_REASSIGNMENT;
+pub use discarded_push::DISCARDED_PUSH;
+pub use unawaited_task::UNAWAITED_TASK;
+pub use use_before_declaration::USE_BEFORE_DECLARATION;
+
+type Rule = fn(&WorkflowDef) -> Vec<Diagnostic>;
+
+const RULES: &[Rule] = &[
+    await_position::check,
+    unawaited_task::check,
+    const_reassignment::check,
+    discarded_push::check,
+    use_before_declaration::check,
+];
+
+/// Run every rule over a workflow
+pub fn check(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    RULES.iter().flat_map(|rule| rule(workflow)).collect()
+}
+
+/// Call `f` on `stmt` and every statement nested in it
+fn walk_stmts<'a>(stmt: &'a Stmt, f: &mut impl FnMut(&'a Stmt)) {
+    f(stmt);
+    match stmt {
+        Stmt::Block { body, .. } => {
+            for child in body {
+                walk_stmts(child, f);
+            }
+        }
+        Stmt::If { then_s, else_s, .. } => {
+            walk_stmts(then_s, f);
+            if let Some(else_s) = else_s {
+                walk_stmts(else_s, f);
+            }
+        }
+        Stmt::While { body, .. } | Stmt::ForLoop { body, .. } => walk_stmts(body, f),
+        Stmt::Try {
+            body, catch_body, ..
+        } => {
+            walk_stmts(body, f);
+            walk_stmts(catch_body, f);
+        }
+        Stmt::Declare { .. }
+        | Stmt::Assign { .. }
+        | Stmt::Return { .. }
+        | Stmt::Throw { .. }
+        | Stmt::Expr { .. }
+        | Stmt::Break { .. }
+        | Stmt::Continue { .. } => {}
+    }
+}
+
+/// Call `f` on `expr` and every expression nested in it
+fn walk_exprs<'a>(expr: &'a Expr, f: &mut impl FnMut(&'a Expr)) {
+    f(expr);
+    match expr {
+        Expr::Template { exprs, .. } => {
+            for e in exprs {
+                walk_exprs(e, f);
+            }
+        }
+        Expr::LitList { elements, .. } => {
+            for e in elements {
+                walk_exprs(e, f);
+            }
+        }
+        Expr::LitObj { properties, .. } => {
+            for (_, _, e) in properties {
+                walk_exprs(e, f);
+            }
+        }
+        Expr::Member { object, .. } => walk_exprs(object, f),
+        Expr::Call { callee, args, .. } => {
+            walk_exprs(callee, f);
+            for arg in args {
+                walk_exprs(arg, f);
+            }
+        }
+        Expr::Await { inner, .. } => walk_exprs(inner, f),
+        Expr::BinaryOp { left, right, .. } => {
+            walk_exprs(left, f);
+            walk_exprs(right, f);
+        }
+        Expr::Ternary {
+            condition,
+            consequent,
+            alternate,
+            ..
+        } => {
+            walk_exprs(condition, f);
+            walk_exprs(consequent, f);
+            walk_exprs(alternate, f);
+        }
+        Expr::LitBool { .. }
+        | Expr::LitNum { .. }
+        | Expr::LitStr { .. }
+        | Expr::LitNull { .. }
+        | Expr::Ident { .. } => {}
+    }
+}
+
+/// The expressions a statement evaluates itself, not counting nested
+/// statements
+fn own_exprs(stmt: &Stmt) -> Vec<&Expr> {
+    match stmt {
+        Stmt::Declare { init, .. } => init.iter().collect(),
+        Stmt::Assign { path, value, .. } => path
+            .iter()
+            .filter_map(|segment| match segment {
+                MemberAccess::Index { expr, .. } => Some(expr),
+                MemberAccess::Prop { .. } => None,
+            })
+            .chain(std::iter::once(value))
+            .collect(),
+        Stmt::If { test, .. } | Stmt::While { test, .. } => vec![test],
+        Stmt::ForLoop { iterable, .. } => vec![iterable],
+        Stmt::Return { value, .. } => value.iter().collect(),
+        Stmt::Throw { value, .. } | Stmt::Expr { expr: value, .. } => vec![value],
+        Stmt::Block { .. } | Stmt::Try { .. } | Stmt::Break { .. } | Stmt::Continue { .. } => {
+            vec![]
+        }
+    }
+}
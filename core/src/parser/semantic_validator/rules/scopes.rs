@@ -0,0 +1,127 @@
+//! Block scopes, for rules that resolve names
+//!
+//! Mirrors the VM: a block's `let`/`const` live until the block ends, a
+//! `for` binding lives in the loop body and a `catch` variable in the catch
+//! body.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::executor::types::ast::{DeclareTarget, Stmt, VarKind};
+
+#[derive(Default)]
+struct Scope {
+    declared: HashMap<String, VarKind>,
+    /// Declared further down this block, but not yet
+    pending: HashSet<String>,
+}
+
+/// The names in scope at a point of a walk
+#[derive(Default)]
+pub(super) struct Scopes {
+    stack: Vec<Scope>,
+}
+
+impl Scopes {
+    /// How the innermost binding of `name` was declared, if it is in scope
+    pub(super) fn lookup(&self, name: &str) -> Option<VarKind> {
+        self.stack
+            .iter()
+            .rev()
+            .find_map(|scope| scope.declared.get(name).copied())
+    }
+
+    /// Whether `name` is declared later in an enclosing block, and isn't
+    /// in scope until then
+    pub(super) fn is_pending(&self, name: &str) -> bool {
+        self.lookup(name).is_none() && self.stack.iter().any(|s| s.pending.contains(name))
+    }
+
+    fn push(&mut self, pending: HashSet<String>) {
+        self.stack.push(Scope {
+            declared: HashMap::new(),
+            pending,
+        });
+    }
+
+    fn declare(&mut self, name: &str, kind: VarKind) {
+        if let Some(scope) = self.stack.last_mut() {
+            scope.pending.remove(name);
+            scope.declared.insert(name.to_string(), kind);
+        }
+    }
+}
+
+/// Walk `stmt` in execution order, calling `f` on each statement with the
+/// scopes it runs in, before its own declaration takes effect
+pub(super) fn walk<'a>(stmt: &'a Stmt, f: &mut impl FnMut(&'a Stmt, &Scopes)) {
+    let mut scopes = Scopes::default();
+    scopes.push(HashSet::new());
+    visit(stmt, &mut scopes, f);
+}
+
+fn visit<'a>(stmt: &'a Stmt, scopes: &mut Scopes, f: &mut impl FnMut(&'a Stmt, &Scopes)) {
+    f(stmt, scopes);
+    match stmt {
+        Stmt::Block { body, .. } => {
+            let pending = body
+                .iter()
+                .filter_map(|child| match child {
+                    Stmt::Declare { target, .. } => Some(names(target)),
+                    _ => None,
+                })
+                .flatten()
+                .map(str::to_string)
+                .collect();
+            scopes.push(pending);
+            for child in body {
+                visit(child, scopes, f);
+            }
+            scopes.stack.pop();
+        }
+        Stmt::Declare {
+            var_kind, target, ..
+        } => {
+            for name in names(target) {
+                scopes.declare(name, *var_kind);
+            }
+        }
+        Stmt::If { then_s, else_s, .. } => {
+            visit(then_s, scopes, f);
+            if let Some(else_s) = else_s {
+                visit(else_s, scopes, f);
+            }
+        }
+        Stmt::While { body, .. } => visit(body, scopes, f),
+        Stmt::ForLoop { binding, body, .. } => {
+            scopes.push(HashSet::new());
+            scopes.declare(binding, VarKind::Let);
+            visit(body, scopes, f);
+            scopes.stack.pop();
+        }
+        Stmt::Try {
+            body,
+            catch_var,
+            catch_body,
+            ..
+        } => {
+            visit(body, scopes, f);
+            scopes.push(HashSet::new());
+            scopes.declare(catch_var, VarKind::Let);
+            visit(catch_body, scopes, f);
+            scopes.stack.pop();
+        }
+        Stmt::Assign { .. }
+        | Stmt::Return { .. }
+        | Stmt::Throw { .. }
+        | Stmt::Expr { .. }
+        | Stmt::Break { .. }
+        | Stmt::Continue { .. } => {}
+    }
+}
+
+fn names(target: &DeclareTarget) -> Vec<&str> {
+    match target {
+        DeclareTarget::Simple { name, .. } => vec![name],
+        DeclareTarget::Destructure { names, .. } => names.iter().map(String::as_str).collect(),
+    }
+}
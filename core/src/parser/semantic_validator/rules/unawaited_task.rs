@@ -0,0 +1,77 @@
+//! Task results that are dropped
+//!
+//! `Task.run(...)` on its own line starts the task but keeps neither its
+//! result nor its handle, so the workflow can finish before the task does
+//! and never sees it fail. Await it, or keep the handle to await later.
+
+use super::walk_stmts;
+use crate::executor::types::ast::{Expr, Stmt};
+use crate::parser::analysis::{Diagnostic, Severity};
+use crate::parser::WorkflowDef;
+
+/// `Task.run(...)` as a statement, neither awaited nor assigned
+pub const UNAWAITED_TASK: &str = "unawaited-task";
+
+pub(super) fn check(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    walk_stmts(&workflow.body, &mut |stmt| {
+        let Stmt::Expr {
+            expr: Expr::Call { callee, span, .. },
+            ..
+        } = stmt
+        else {
+            return;
+        };
+        let Expr::Member {
+            object, property, ..
+        } = callee.as_ref()
+        else {
+            return;
+        };
+        if matches!(object.as_ref(), Expr::Ident { name, .. } if name == "Task")
+            && property == "run"
+        {
+            diagnostics.push(Diagnostic {
+                code: UNAWAITED_TASK,
+                severity: Severity::Warning,
+                message: "Result of `Task.run` is never awaited; `await` it or keep the handle"
+                    .to_string(),
+                span: *span,
+            });
+        }
+    });
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        check(&workflow).iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_dropped_task_run_is_flagged() {
+        assert_eq!(codes(r#"Task.run("notify", {})"#), vec![UNAWAITED_TASK]);
+        assert_eq!(
+            codes(r#"if (true) { Task.run("notify", {}) }"#),
+            vec![UNAWAITED_TASK]
+        );
+    }
+
+    #[test]
+    fn test_awaited_or_kept_task_run_is_clean() {
+        assert!(codes(
+            r#"
+            await Task.run("a", {})
+            let t = Task.run("b", {})
+            const all = Promise.all([t, Task.run("c", {})])
+            Log.info("started")
+            return await all
+        "#
+        )
+        .is_empty());
+    }
+}
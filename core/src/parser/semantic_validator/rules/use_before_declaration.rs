@@ -0,0 +1,88 @@
+//! Using a variable before its declaration runs
+//!
+//! Like JavaScript's temporal dead zone: a `let` or `const` can't be read
+//! or assigned above its declaration, including in its own initializer.
+//! The VM would fail with an undefined variable at that point.
+
+use super::{own_exprs, scopes, walk_exprs};
+use crate::executor::types::ast::{Expr, Stmt};
+use crate::parser::analysis::{Diagnostic, Severity};
+use crate::parser::WorkflowDef;
+
+/// A variable read or assigned before the `let`/`const` declaring it
+pub const USE_BEFORE_DECLARATION: &str = "use-before-declaration";
+
+pub(super) fn check(workflow: &WorkflowDef) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    scopes::walk(&workflow.body, &mut |stmt, scopes| {
+        let mut report = |name: &str, span| {
+            if scopes.is_pending(name) {
+                diagnostics.push(Diagnostic {
+                    code: USE_BEFORE_DECLARATION,
+                    severity: Severity::Error,
+                    message: format!("`{}` is used before its declaration", name),
+                    span,
+                });
+            }
+        };
+        if let Stmt::Assign { var, var_span, .. } = stmt {
+            report(var, *var_span);
+        }
+        for expr in own_exprs(stmt) {
+            walk_exprs(expr, &mut |e| {
+                if let Expr::Ident { name, span } = e {
+                    report(name, *span);
+                }
+            });
+        }
+    });
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<String> {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        check(&workflow).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn test_use_before_declaration_is_flagged() {
+        assert_eq!(
+            messages(
+                r#"
+                Log.info(total)
+                if (true) { count = 1 }
+                let total = total + 1
+                let count = 0
+            "#
+            ),
+            vec![
+                "`total` is used before its declaration",
+                "`count` is used before its declaration",
+                "`total` is used before its declaration",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declared_or_outer_names_are_clean() {
+        assert!(messages(
+            r#"
+            let x = 1
+            if (x) {
+                Log.info(x)
+                let y = x + 1
+                Log.info(y)
+            }
+            let y = Inputs.y
+            for (let i of [1]) { Log.info(i) }
+            try { throw 1 } catch (e) { Log.info(e) }
+            return y
+        "#
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,795 @@
+//! Task graph of a workflow
+//!
+//! `task_graph` walks a workflow's AST without running it and lays out what
+//! it may do: the tasks and child workflows it starts, the timers and
+//! signals it waits on, where it awaits and the loops around them. A call
+//! keeps running from where it is started until something awaits it, so
+//! calls outstanding at the same await may run in parallel; the graph lists
+//! those groups too.
+//!
+//! Every branch is taken and each loop body walked once, so the graph shows
+//! what the workflow *may* do. Names computed at runtime are unknown, and
+//! handles are followed through variables but not through lists built up
+//! in other ways. `to_dot` renders the graph for Graphviz.
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use super::WorkflowDef;
+use crate::executor::types::ast::{DeclareTarget, Expr, Span, Stmt};
+
+/// What a workflow may start and wait on, in the order it may do so
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TaskGraph {
+    /// Tasks the workflow may run, compensations included
+    pub tasks: BTreeSet<String>,
+    /// Child workflows it may start
+    pub workflows: BTreeSet<String>,
+    /// Signals it may wait for
+    pub signals: BTreeSet<String>,
+    /// Some task or workflow is named by a value computed at runtime
+    pub dynamic_calls: bool,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Calls (node ids) that may be running at the same time; a group of
+    /// one is a call started many times over in a loop
+    pub parallel: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphNode {
+    pub id: usize,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+    /// 1-based; 0 for the start and end
+    pub line: usize,
+    /// The workflow waits for the call right where it starts it
+    pub awaited: bool,
+    /// Started in a loop and not awaited in the same iteration, so any
+    /// number of them may run at once
+    pub fan_out: bool,
+}
+
+/// What a node does; names are `None` when not written as a literal
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NodeKind {
+    Start,
+    End,
+    Task {
+        name: Option<String>,
+    },
+    Workflow {
+        name: Option<String>,
+    },
+    Timer,
+    Signal {
+        name: Option<String>,
+    },
+    /// Waiting on calls started earlier
+    Await {
+        mode: AwaitMode,
+    },
+    Loop {
+        repeat: LoopKind,
+    },
+}
+
+/// How an await waits on the calls it is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AwaitMode {
+    /// A single handle
+    One,
+    /// `Promise.all`, `Task.all`
+    All,
+    /// `Promise.any`, `Promise.any_kv`
+    Any,
+    /// `Promise.race`, `Promise.race_kv`
+    Race,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopKind {
+    For,
+    While,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// What may run next
+    Next,
+    /// Back to the top of a loop
+    Repeat,
+    /// From a call to the await that waits on it
+    Awaited,
+}
+
+/// Build the task graph of `workflow`
+pub fn task_graph(workflow: &WorkflowDef) -> TaskGraph {
+    let mut builder = Builder::default();
+    builder.node(NodeKind::Start, None);
+    builder.stmt(&workflow.body);
+    let exits = std::mem::take(&mut builder.exits);
+    builder.frontier.extend(exits);
+    builder.node(NodeKind::End, None);
+    // Calls never awaited run on alongside each other
+    builder.record_parallel();
+    builder.graph
+}
+
+impl TaskGraph {
+    /// The graph in Graphviz DOT, as a digraph named `name`
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut dot = format!("digraph {} {{\n", quote(name));
+        dot.push_str("    node [fontname=\"Helvetica\"];\n");
+        for node in &self.nodes {
+            let (label, shape) = match &node.kind {
+                NodeKind::Start => ("start".to_string(), "circle"),
+                NodeKind::End => ("end".to_string(), "doublecircle"),
+                NodeKind::Task { name } => (format!("task {}", or_unknown(name)), "box"),
+                NodeKind::Workflow { name } => (format!("workflow {}", or_unknown(name)), "box"),
+                NodeKind::Timer => ("timer".to_string(), "ellipse"),
+                NodeKind::Signal { name } => (format!("signal {}", or_unknown(name)), "ellipse"),
+                NodeKind::Await { mode } => (format!("await {}", mode.as_str()), "diamond"),
+                NodeKind::Loop { repeat } => (repeat.as_str().to_string(), "hexagon"),
+            };
+            let label = if node.line > 0 {
+                format!("{}\nline {}", label, node.line)
+            } else {
+                label
+            };
+            let mut attrs = format!("label={}, shape={}", quote(&label), shape);
+            if node.awaited {
+                attrs.push_str(", style=bold");
+            }
+            if node.fan_out {
+                attrs.push_str(", peripheries=2");
+            }
+            let _ = writeln!(dot, "    n{} [{}];", node.id, attrs);
+        }
+        for edge in &self.edges {
+            let attrs = match edge.kind {
+                EdgeKind::Next => "",
+                EdgeKind::Repeat => " [style=dashed, label=\"repeat\"]",
+                EdgeKind::Awaited => " [style=dotted, arrowhead=empty]",
+            };
+            let _ = writeln!(dot, "    n{} -> n{}{};", edge.from, edge.to, attrs);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl AwaitMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AwaitMode::One => "one",
+            AwaitMode::All => "all",
+            AwaitMode::Any => "any",
+            AwaitMode::Race => "race",
+        }
+    }
+}
+
+impl LoopKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoopKind::For => "for",
+            LoopKind::While => "while",
+        }
+    }
+}
+
+fn or_unknown(name: &Option<String>) -> &str {
+    name.as_deref().unwrap_or("?")
+}
+
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+/* ===================== Builder ===================== */
+
+#[derive(Default)]
+struct Builder {
+    graph: TaskGraph,
+    /// Nodes the next node follows
+    frontier: Vec<usize>,
+    /// Calls started and not awaited yet
+    outstanding: Vec<usize>,
+    /// Calls whose handles each variable holds
+    handles: HashMap<String, Vec<usize>>,
+    /// Nodes a return or uncaught throw leaves from
+    exits: Vec<usize>,
+    loops: Vec<LoopScope>,
+    /// Nodes a throw leaves from, per enclosing `try`
+    throws: Vec<Vec<usize>>,
+}
+
+struct LoopScope {
+    head: usize,
+    breaks: Vec<usize>,
+}
+
+/// A stdlib call's object and method, like `("Task", "run")`
+fn stdlib_call(callee: &Expr) -> Option<(&str, &str)> {
+    match callee {
+        Expr::Member {
+            object, property, ..
+        } => match object.as_ref() {
+            Expr::Ident { name, .. } => Some((name.as_str(), property.as_str())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn combinator(object: &str, method: &str) -> Option<AwaitMode> {
+    match (object, method) {
+        ("Promise", "all") | ("Task", "all") => Some(AwaitMode::All),
+        ("Promise", "any" | "any_kv") => Some(AwaitMode::Any),
+        ("Promise", "race" | "race_kv") => Some(AwaitMode::Race),
+        _ => None,
+    }
+}
+
+fn literal(arg: Option<&Expr>) -> Option<String> {
+    match arg {
+        Some(Expr::LitStr { v, .. }) => Some(v.clone()),
+        _ => None,
+    }
+}
+
+fn line(span: Span) -> usize {
+    span.start_line + 1
+}
+
+impl Builder {
+    /// Add a node following the frontier, which it then replaces
+    fn node(&mut self, kind: NodeKind, span: Option<Span>) -> usize {
+        let id = self.graph.nodes.len();
+        self.graph.nodes.push(GraphNode {
+            id,
+            kind,
+            line: span.map_or(0, line),
+            awaited: false,
+            fan_out: false,
+        });
+        for from in std::mem::take(&mut self.frontier) {
+            self.edge(from, id, EdgeKind::Next);
+        }
+        self.frontier.push(id);
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, kind: EdgeKind) {
+        let edge = GraphEdge { from, to, kind };
+        if !self.graph.edges.contains(&edge) {
+            self.graph.edges.push(edge);
+        }
+    }
+
+    /// Start a call, leaving it outstanding
+    fn call(&mut self, kind: NodeKind, span: Span) -> usize {
+        match &kind {
+            NodeKind::Task { name: Some(name) } => {
+                self.graph.tasks.insert(name.clone());
+            }
+            NodeKind::Workflow { name: Some(name) } => {
+                self.graph.workflows.insert(name.clone());
+            }
+            NodeKind::Task { name: None } | NodeKind::Workflow { name: None } => {
+                self.graph.dynamic_calls = true;
+            }
+            NodeKind::Signal { name: Some(name) } => {
+                self.graph.signals.insert(name.clone());
+            }
+            _ => {}
+        }
+        let id = self.node(kind, Some(span));
+        self.outstanding.push(id);
+        id
+    }
+
+    /// Note the outstanding calls as running together, if more than one
+    /// may be
+    fn record_parallel(&mut self) {
+        let mut group = self.outstanding.clone();
+        group.sort_unstable();
+        group.dedup();
+        let fan_out = group.iter().any(|&id| self.graph.nodes[id].fan_out);
+        if (group.len() > 1 || fan_out) && !self.graph.parallel.contains(&group) {
+            self.graph.parallel.push(group);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { body, .. } => {
+                for stmt in body {
+                    self.stmt(stmt);
+                }
+            }
+            Stmt::Declare { target, init, .. } => {
+                let ids = init
+                    .as_ref()
+                    .map(|init| self.expr(init))
+                    .unwrap_or_default();
+                match target {
+                    DeclareTarget::Simple { name, .. } => {
+                        self.handles.insert(name.clone(), ids);
+                    }
+                    DeclareTarget::Destructure { names, .. } => {
+                        for name in names {
+                            self.handles.insert(name.clone(), ids.clone());
+                        }
+                    }
+                }
+            }
+            Stmt::Assign {
+                var, path, value, ..
+            } => {
+                let ids = self.expr(value);
+                if path.is_empty() {
+                    self.handles.insert(var.clone(), ids);
+                } else {
+                    self.handles.entry(var.clone()).or_default().extend(ids);
+                }
+            }
+            Stmt::If {
+                test,
+                then_s,
+                else_s,
+                ..
+            } => {
+                self.expr(test);
+                let frontier = self.frontier.clone();
+                let outstanding = self.outstanding.clone();
+                self.stmt(then_s);
+                let then_frontier = std::mem::replace(&mut self.frontier, frontier);
+                let then_outstanding = std::mem::replace(&mut self.outstanding, outstanding);
+                if let Some(else_s) = else_s {
+                    self.stmt(else_s);
+                }
+                self.join(then_frontier, then_outstanding);
+            }
+            Stmt::While { test, body, span } => {
+                self.repeat(LoopKind::While, *span, |builder| {
+                    builder.expr(test);
+                    builder.stmt(body);
+                });
+            }
+            Stmt::ForLoop {
+                iterable,
+                body,
+                span,
+                ..
+            } => {
+                self.expr(iterable);
+                self.repeat(LoopKind::For, *span, |builder| builder.stmt(body));
+            }
+            Stmt::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                let frontier = std::mem::take(&mut self.frontier);
+                self.exits.extend(frontier);
+            }
+            Stmt::Throw { value, .. } => {
+                self.expr(value);
+                let frontier = std::mem::take(&mut self.frontier);
+                match self.throws.last_mut() {
+                    Some(throws) => throws.extend(frontier),
+                    None => self.exits.extend(frontier),
+                }
+            }
+            Stmt::Try {
+                body, catch_body, ..
+            } => {
+                let mut entry = self.frontier.clone();
+                let first = self.graph.nodes.len();
+                self.throws.push(Vec::new());
+                self.stmt(body);
+                let throws = self.throws.pop().unwrap_or_default();
+                // Any call or await in the body may fail into the catch
+                entry.extend(first..self.graph.nodes.len());
+                entry.extend(throws);
+                let body_frontier = std::mem::replace(&mut self.frontier, entry);
+                let outstanding = self.outstanding.clone();
+                self.stmt(catch_body);
+                self.join(body_frontier, outstanding);
+            }
+            Stmt::Expr { expr, .. } => {
+                let ids = self.expr(expr);
+                // `handles.push(Task.run(...))` keeps the handle in `handles`
+                if let Expr::Call { callee, .. } = expr {
+                    if let Expr::Member { object, .. } = callee.as_ref() {
+                        if let Expr::Ident { name, .. } = object.as_ref() {
+                            if !ids.is_empty() {
+                                self.handles.entry(name.clone()).or_default().extend(ids);
+                            }
+                        }
+                    }
+                }
+            }
+            Stmt::Break { .. } => {
+                let frontier = std::mem::take(&mut self.frontier);
+                if let Some(scope) = self.loops.last_mut() {
+                    scope.breaks.extend(frontier);
+                }
+            }
+            Stmt::Continue { .. } => {
+                let frontier = std::mem::take(&mut self.frontier);
+                if let Some(head) = self.loops.last().map(|scope| scope.head) {
+                    for from in frontier {
+                        self.edge(from, head, EdgeKind::Repeat);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Merge another branch's frontier and outstanding calls into these
+    fn join(&mut self, frontier: Vec<usize>, outstanding: Vec<usize>) {
+        for id in frontier {
+            if !self.frontier.contains(&id) {
+                self.frontier.push(id);
+            }
+        }
+        for id in outstanding {
+            if !self.outstanding.contains(&id) {
+                self.outstanding.push(id);
+            }
+        }
+    }
+
+    /// Walk a loop body once under a loop node, which is left out if the
+    /// body starts and awaits nothing
+    fn repeat(&mut self, kind: LoopKind, span: Span, body: impl FnOnce(&mut Self)) {
+        let frontier = self.frontier.clone();
+        let edges = self.graph.edges.len();
+        let head = self.node(NodeKind::Loop { repeat: kind }, Some(span));
+        self.loops.push(LoopScope {
+            head,
+            breaks: Vec::new(),
+        });
+        body(self);
+        let scope = self.loops.pop().expect("loop scope");
+
+        if self.graph.nodes.len() == head + 1 {
+            self.graph.nodes.pop();
+            self.graph.edges.truncate(edges);
+            self.frontier = frontier;
+            return;
+        }
+        for from in std::mem::take(&mut self.frontier) {
+            self.edge(from, head, EdgeKind::Repeat);
+        }
+        // Calls the body leaves running pile up, one per iteration
+        for &id in &self.outstanding {
+            if id > head {
+                self.graph.nodes[id].fan_out = true;
+            }
+        }
+        self.frontier.push(head);
+        self.frontier.extend(scope.breaks);
+    }
+
+    /// Walk an expression, returning the calls whose handles its value holds
+    fn expr(&mut self, expr: &Expr) -> Vec<usize> {
+        match expr {
+            Expr::Call { callee, args, span } => {
+                if let Some((object, method)) = stdlib_call(callee) {
+                    let kind = match (object, method) {
+                        ("Task", "run" | "stream") => Some(NodeKind::Task {
+                            name: literal(args.first()),
+                        }),
+                        ("Workflow", "run") => Some(NodeKind::Workflow {
+                            name: literal(args.first()),
+                        }),
+                        ("Timer", "delay") => Some(NodeKind::Timer),
+                        ("Signal", "next") => Some(NodeKind::Signal {
+                            name: literal(args.first()),
+                        }),
+                        _ => None,
+                    };
+                    if let Some(kind) = kind {
+                        self.exprs(args);
+                        return vec![self.call(kind, *span)];
+                    }
+                    if (object, method) == ("Compensate", "register") {
+                        if let Some(name) = literal(args.first()) {
+                            self.graph.tasks.insert(name);
+                        }
+                        self.exprs(args);
+                        return Vec::new();
+                    }
+                }
+                self.expr(callee);
+                self.exprs(args)
+            }
+            Expr::Await { inner, .. } => {
+                self.await_(inner);
+                Vec::new()
+            }
+            Expr::Ident { name, .. } => self.handles.get(name).cloned().unwrap_or_default(),
+            Expr::Member { object, .. } => self.expr(object),
+            Expr::LitList { elements, .. } => self.exprs(elements),
+            Expr::LitObj { properties, .. } => {
+                let mut ids = Vec::new();
+                for (_, _, value) in properties {
+                    ids.extend(self.expr(value));
+                }
+                ids
+            }
+            Expr::Template { exprs, .. } => self.exprs(exprs),
+            Expr::BinaryOp { left, right, .. } => {
+                let mut ids = self.expr(left);
+                ids.extend(self.expr(right));
+                ids
+            }
+            Expr::Ternary {
+                condition,
+                consequent,
+                alternate,
+                ..
+            } => {
+                self.expr(condition);
+                let mut ids = self.expr(consequent);
+                ids.extend(self.expr(alternate));
+                ids
+            }
+            Expr::LitBool { .. }
+            | Expr::LitNum { .. }
+            | Expr::LitStr { .. }
+            | Expr::LitNull { .. } => Vec::new(),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) -> Vec<usize> {
+        let mut ids = Vec::new();
+        for expr in exprs {
+            ids.extend(self.expr(expr));
+        }
+        ids
+    }
+
+    fn await_(&mut self, inner: &Expr) {
+        let mode = match inner {
+            Expr::Call { callee, .. } => {
+                stdlib_call(callee).and_then(|(object, method)| combinator(object, method))
+            }
+            _ => Some(AwaitMode::One),
+        };
+        let (mode, mut targets) = match (mode, inner) {
+            (Some(mode), Expr::Call { args, .. }) if mode != AwaitMode::One => {
+                (Some(mode), self.exprs(args))
+            }
+            (Some(mode), _) => (Some(mode), self.expr(inner)),
+            // Something started and awaited in one go, or a call whose
+            // value isn't a handle the graph knows
+            (None, _) => {
+                let count = self.graph.nodes.len();
+                let ids = self.expr(inner);
+                if ids.len() == 1 && ids[0] >= count {
+                    self.graph.nodes[ids[0]].awaited = true;
+                    (None, ids)
+                } else {
+                    (Some(AwaitMode::One), ids)
+                }
+            }
+        };
+        // Handles the graph lost track of are most likely all awaited here
+        if targets.is_empty() && matches!(mode, Some(mode) if mode != AwaitMode::One) {
+            targets = self.outstanding.clone();
+        }
+
+        self.record_parallel();
+        if let Some(mode) = mode {
+            let id = self.node(NodeKind::Await { mode }, Some(inner.span()));
+            for &target in &targets {
+                self.edge(target, id, EdgeKind::Awaited);
+            }
+        }
+        self.outstanding.retain(|id| !targets.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(source: &str) -> TaskGraph {
+        let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+        task_graph(&workflow)
+    }
+
+    fn kinds(graph: &TaskGraph) -> Vec<String> {
+        graph
+            .nodes
+            .iter()
+            .map(|node| match &node.kind {
+                NodeKind::Start => "start".to_string(),
+                NodeKind::End => "end".to_string(),
+                NodeKind::Task { name } => format!("task {}", or_unknown(name)),
+                NodeKind::Workflow { name } => format!("workflow {}", or_unknown(name)),
+                NodeKind::Timer => "timer".to_string(),
+                NodeKind::Signal { name } => format!("signal {}", or_unknown(name)),
+                NodeKind::Await { mode } => format!("await {}", mode.as_str()),
+                NodeKind::Loop { repeat } => repeat.as_str().to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sequential_awaits_run_one_at_a_time() {
+        let graph = graph(
+            r#"
+            let order = await Task.run("load", {})
+            let charge = await Task.run("charge", { order: order })
+            return charge
+        "#,
+        );
+        assert_eq!(kinds(&graph), ["start", "task load", "task charge", "end"]);
+        assert!(graph.nodes[1].awaited && graph.nodes[2].awaited);
+        assert_eq!(
+            graph.tasks,
+            BTreeSet::from(["charge".into(), "load".into()])
+        );
+        assert!(graph.parallel.is_empty());
+        assert!(graph
+            .edges
+            .iter()
+            .all(|edge| edge.kind == EdgeKind::Next && edge.to == edge.from + 1));
+    }
+
+    #[test]
+    fn test_calls_awaited_together_run_in_parallel() {
+        let graph = graph(
+            r#"
+            let a = Task.run("reserve", {})
+            let b = Task.run("charge", {})
+            let both = await Promise.all([a, b])
+            let first = await Promise.race([Task.run("ship_a", {}), Task.run("ship_b", {})])
+            return both
+        "#,
+        );
+        assert_eq!(
+            kinds(&graph),
+            [
+                "start",
+                "task reserve",
+                "task charge",
+                "await all",
+                "task ship_a",
+                "task ship_b",
+                "await race",
+                "end"
+            ]
+        );
+        assert_eq!(graph.parallel, [vec![1, 2], vec![4, 5]]);
+        let awaited: Vec<_> = graph
+            .edges
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Awaited)
+            .map(|edge| (edge.from, edge.to))
+            .collect();
+        assert_eq!(awaited, [(1, 3), (2, 3), (4, 6), (5, 6)]);
+    }
+
+    #[test]
+    fn test_calls_left_running_in_a_loop_fan_out() {
+        let graph = graph(
+            r#"
+            let handles = []
+            for (let item of Inputs.items) {
+                handles.push(Task.run("pack", { item: item }))
+            }
+            await Promise.all(handles)
+            while (true) {
+                let status = await Task.run("poll", {})
+                if (status == "done") {
+                    break
+                }
+                await Timer.delay(5)
+            }
+        "#,
+        );
+        assert_eq!(
+            kinds(&graph),
+            [
+                "start",
+                "for",
+                "task pack",
+                "await all",
+                "while",
+                "task poll",
+                "timer",
+                "end"
+            ]
+        );
+        assert!(graph.nodes[2].fan_out);
+        assert!(!graph.nodes[5].fan_out);
+        assert_eq!(graph.parallel, [vec![2]]);
+        let edge = |from, to, kind| GraphEdge { from, to, kind };
+        assert!(graph.edges.contains(&edge(2, 1, EdgeKind::Repeat)));
+        assert!(graph.edges.contains(&edge(1, 3, EdgeKind::Next)));
+        assert!(graph.edges.contains(&edge(2, 3, EdgeKind::Awaited)));
+        assert!(graph.edges.contains(&edge(6, 4, EdgeKind::Repeat)));
+        // Breaking out and the loop ending both lead to the end
+        assert!(graph.edges.contains(&edge(5, 7, EdgeKind::Next)));
+        assert!(graph.edges.contains(&edge(4, 7, EdgeKind::Next)));
+    }
+
+    #[test]
+    fn test_branches_and_loops_without_calls() {
+        let graph = graph(
+            r#"
+            let total = 0
+            for (let n of [1, 2]) {
+                total = total + n
+            }
+            if (Inputs.express) {
+                await Workflow.run("ship_express", {})
+            } else {
+                await Task.run(Inputs.carrier, {})
+            }
+            let approval = await Signal.next("approve")
+            Compensate.register("refund", {})
+        "#,
+        );
+        assert_eq!(
+            kinds(&graph),
+            [
+                "start",
+                "workflow ship_express",
+                "task ?",
+                "signal approve",
+                "end"
+            ]
+        );
+        assert_eq!(graph.workflows, BTreeSet::from(["ship_express".into()]));
+        assert_eq!(graph.signals, BTreeSet::from(["approve".into()]));
+        assert_eq!(graph.tasks, BTreeSet::from(["refund".into()]));
+        assert!(graph.dynamic_calls);
+        let into_signal: Vec<_> = graph
+            .edges
+            .iter()
+            .filter(|edge| edge.to == 3)
+            .map(|edge| edge.from)
+            .collect();
+        assert_eq!(into_signal, [2, 1]);
+    }
+
+    #[test]
+    fn test_dot_output() {
+        let graph = graph(
+            r#"
+            let a = Task.run("greet", {})
+            await a
+        "#,
+        );
+        let dot = graph.to_dot(r#"say "hi""#);
+        assert!(dot.starts_with("digraph \"say \\\"hi\\\"\" {\n"));
+        assert!(dot.contains(r#"n1 [label="task greet\nline 2", shape=box];"#));
+        assert!(dot.contains(r#"n2 [label="await one\nline 3", shape=diamond];"#));
+        assert!(dot.contains("n1 -> n2;\n"));
+        assert!(dot.contains("n1 -> n2 [style=dotted, arrowhead=empty];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -219,6 +219,44 @@ fn test_parse_string_with_spaces() {
     }
 }
 
+/* ===================== Template Literal Tests ===================== */
+
+#[test]
+fn test_parse_template_literal() {
+    let ast = crate::parser::parse("return `order ${ order.id } failed: ${reason}`")
+        .expect("Should parse");
+    let stmt = unwrap_block(ast);
+
+    match stmt {
+        Stmt::Return {
+            value: Some(Expr::Template { quasis, exprs, .. }),
+            ..
+        } => {
+            assert_eq!(quasis, vec!["order ", " failed: ", ""]);
+            assert!(matches!(&exprs[0], Expr::Member { property, .. } if property == "id"));
+            assert!(matches!(&exprs[1], Expr::Ident { name, .. } if name == "reason"));
+        }
+        _ => panic!("Expected Return with Template, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn test_parse_template_literal_without_interpolation() {
+    let ast = crate::parser::parse("return `plain \"text\"  `").expect("Should parse");
+    let stmt = unwrap_block(ast);
+
+    match stmt {
+        Stmt::Return {
+            value: Some(Expr::Template { quasis, exprs, .. }),
+            ..
+        } => {
+            assert_eq!(quasis, vec!["plain \"text\"  "]);
+            assert!(exprs.is_empty());
+        }
+        _ => panic!("Expected Return with Template, got {:?}", stmt),
+    }
+}
+
 /* ===================== Identifier Tests ===================== */
 
 #[test]
@@ -865,6 +903,30 @@ fn test_parse_continue_standalone() {
     assert!(matches!(stmt, Stmt::Continue { .. }));
 }
 
+#[test]
+fn test_parse_throw() {
+    let ast = crate::parser::parse(r#"throw Error("boom")"#).expect("Should parse");
+    let stmt = unwrap_block(ast);
+    match stmt {
+        Stmt::Throw {
+            value: Expr::Call { args, .. },
+            ..
+        } => assert!(matches!(&args[0], Expr::LitStr { v, .. } if v == "boom")),
+        _ => panic!("Expected Throw with Call, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn test_parse_throw_prefixed_identifier_is_not_throw() {
+    let ast = crate::parser::parse("thrown = 1").expect("Should parse");
+    let stmt = unwrap_block(ast);
+    assert!(
+        matches!(stmt, Stmt::Assign { ref var, .. } if var == "thrown"),
+        "{:?}",
+        stmt
+    );
+}
+
 /* ===================== Assignment Tests ===================== */
 
 #[test]
@@ -2047,3 +2109,206 @@ fn test_parse_property_after_call() {
         _ => panic!("Expected Return"),
     }
 }
+
+/* ===================== Type Annotation Tests ===================== */
+
+use crate::executor::types::ast::TypeExpr;
+
+/// Shorthand for a named type with its span stripped
+fn named(name: &str) -> TypeExpr {
+    TypeExpr::Named {
+        name: name.to_string(),
+        span: Default::default(),
+    }
+}
+
+/// Drop spans so annotations can be compared structurally
+fn strip_type_spans(ty: TypeExpr) -> TypeExpr {
+    match ty {
+        TypeExpr::Named { name, .. } => TypeExpr::Named {
+            name,
+            span: Default::default(),
+        },
+        TypeExpr::Array { element, .. } => TypeExpr::Array {
+            element: Box::new(strip_type_spans(*element)),
+            span: Default::default(),
+        },
+        TypeExpr::Object { fields, .. } => TypeExpr::Object {
+            fields: fields
+                .into_iter()
+                .map(|mut f| {
+                    f.ty = strip_type_spans(f.ty);
+                    f.span = Default::default();
+                    f
+                })
+                .collect(),
+            span: Default::default(),
+        },
+        TypeExpr::Union { variants, .. } => TypeExpr::Union {
+            variants: variants.into_iter().map(strip_type_spans).collect(),
+            span: Default::default(),
+        },
+    }
+}
+
+fn declared_type(source: &str) -> Option<TypeExpr> {
+    match unwrap_block(crate::parser::parse(source).expect("Should parse")) {
+        Stmt::Declare { type_ann, .. } => type_ann.map(strip_type_spans),
+        other => panic!("Expected Declare, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_declare_with_named_type() {
+    assert_eq!(
+        declared_type("let total: number = 0"),
+        Some(named("number"))
+    );
+    assert_eq!(declared_type("let total = 0"), None);
+    assert_eq!(declared_type("let name: string"), Some(named("string")));
+}
+
+#[test]
+fn test_parse_declare_with_union_and_array_types() {
+    assert_eq!(
+        declared_type("let x: (number | null)[] = []"),
+        Some(TypeExpr::Array {
+            element: Box::new(TypeExpr::Union {
+                variants: vec![named("number"), named("null")],
+                span: Default::default(),
+            }),
+            span: Default::default(),
+        })
+    );
+}
+
+#[test]
+fn test_parse_declare_with_object_type() {
+    let ty = declared_type("const p: {id: string; tags?: string[],} = Inputs").unwrap();
+    match ty {
+        TypeExpr::Object { fields, .. } => {
+            assert_eq!(fields.len(), 2);
+            assert_eq!(fields[0].name, "id");
+            assert!(!fields[0].optional);
+            assert_eq!(fields[1].name, "tags");
+            assert!(fields[1].optional);
+            assert_eq!(
+                fields[1].ty,
+                TypeExpr::Array {
+                    element: Box::new(named("string")),
+                    span: Default::default(),
+                }
+            );
+        }
+        other => panic!("Expected Object type, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_main_with_typed_inputs() {
+    let source = r#"
+        async function main(inputs: {orderId: string}) {
+            return inputs.orderId
+        }
+    "#;
+
+    let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+    assert!(matches!(workflow.input_type, Some(TypeExpr::Object { .. })));
+
+    // The parameter is bound to Inputs ahead of the body
+    match workflow.body {
+        Stmt::Block { body, .. } => {
+            assert_eq!(body.len(), 2);
+            match &body[0] {
+                Stmt::Declare {
+                    target: DeclareTarget::Simple { name, .. },
+                    init: Some(Expr::Ident { name: init, .. }),
+                    ..
+                } => {
+                    assert_eq!(name, "inputs");
+                    assert_eq!(init, "Inputs");
+                }
+                other => panic!("Expected Declare, got {:?}", other),
+            }
+        }
+        _ => panic!("Expected Block for workflow body"),
+    }
+}
+
+#[test]
+fn test_parse_main_with_inputs_param_named_inputs() {
+    let source = r#"
+        async function main(Inputs) {
+            return Inputs.id
+        }
+    "#;
+
+    let workflow = crate::parser::parse_workflow(source).expect("Should parse");
+    assert!(workflow.input_type.is_none());
+    match workflow.body {
+        Stmt::Block { body, .. } => assert_eq!(body.len(), 1),
+        _ => panic!("Expected Block for workflow body"),
+    }
+}
+
+/* ===================== Golden AST Tests ===================== */
+
+/// Compare each `golden/*.flow` file's canonical AST with its `.json` twin
+///
+/// Set `RHYTHM_UPDATE_GOLDEN=1` to rewrite the `.json` files after an
+/// intentional AST change, then review the diff.
+#[test]
+fn test_golden_ast_output() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/parser/golden");
+    let update = std::env::var_os("RHYTHM_UPDATE_GOLDEN").is_some();
+
+    let mut sources: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "flow"))
+        .collect();
+    sources.sort();
+    assert!(
+        !sources.is_empty(),
+        "No golden sources in {}",
+        dir.display()
+    );
+
+    for source_path in sources {
+        let source = std::fs::read_to_string(&source_path).unwrap();
+        let workflow = crate::parser::parse_workflow(&source)
+            .unwrap_or_else(|e| panic!("{}: {}", source_path.display(), e));
+        let actual = crate::parser::to_canonical_json(&workflow);
+
+        let golden_path = source_path.with_extension("json");
+        if update {
+            std::fs::write(&golden_path, &actual).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&golden_path).unwrap_or_else(|_| {
+            panic!(
+                "Missing {}; run with RHYTHM_UPDATE_GOLDEN=1 to create it",
+                golden_path.display()
+            )
+        });
+        assert!(
+            actual == expected,
+            "AST for {} changed; if intended, rerun with RHYTHM_UPDATE_GOLDEN=1 and review the diff",
+            source_path.display()
+        );
+    }
+}
+
+#[test]
+fn test_canonical_json_sorts_keys_and_is_stable() {
+    let workflow = crate::parser::parse_workflow("let x = { b: 1, a: 2 }\nreturn x").unwrap();
+    let json = crate::parser::to_canonical_json(&workflow);
+    assert_eq!(json, crate::parser::to_canonical_json(&workflow));
+    assert!(json.ends_with("}\n"));
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let keys: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+}
@@ -0,0 +1,123 @@
+//! Payload encodings for the FFI boundary
+//!
+//! Adapters pass inputs/outputs either as JSON text or as raw bytes. Bytes may
+//! be JSON or MessagePack; the adapter names the encoding it used and the core
+//! decodes straight from the borrowed buffer without an intermediate copy.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::str::FromStr;
+
+/// Wire encoding of a payload passed across the FFI boundary
+///
+/// Also names the encoding saved workflow states are written in (see
+/// `state_encoding`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadEncoding {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl PayloadEncoding {
+    /// Encodings supported by this build, in order of preference for large payloads
+    pub const SUPPORTED: &'static [PayloadEncoding] =
+        &[PayloadEncoding::MsgPack, PayloadEncoding::Json];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadEncoding::Json => "json",
+            PayloadEncoding::MsgPack => "msgpack",
+        }
+    }
+
+    /// Pick the first encoding the adapter offers that the core also supports
+    ///
+    /// Falls back to JSON, which every adapter must support.
+    pub fn negotiate<S: AsRef<str>>(offered: &[S]) -> PayloadEncoding {
+        offered
+            .iter()
+            .filter_map(|name| name.as_ref().parse().ok())
+            .find(|encoding| Self::SUPPORTED.contains(encoding))
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for PayloadEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(PayloadEncoding::Json),
+            "msgpack" | "messagepack" => Ok(PayloadEncoding::MsgPack),
+            other => Err(anyhow!("Unsupported payload encoding: {}", other)),
+        }
+    }
+}
+
+/// Decode a payload from a borrowed byte buffer
+pub fn decode(bytes: &[u8], encoding: PayloadEncoding) -> Result<JsonValue> {
+    match encoding {
+        PayloadEncoding::Json => {
+            serde_json::from_slice(bytes).context("Failed to decode JSON payload")
+        }
+        PayloadEncoding::MsgPack => {
+            rmp_serde::from_slice(bytes).context("Failed to decode MessagePack payload")
+        }
+    }
+}
+
+/// Encode a payload into bytes
+pub fn encode(value: &JsonValue, encoding: PayloadEncoding) -> Result<Vec<u8>> {
+    match encoding {
+        PayloadEncoding::Json => serde_json::to_vec(value).context("Failed to encode JSON payload"),
+        PayloadEncoding::MsgPack => {
+            rmp_serde::to_vec_named(value).context("Failed to encode MessagePack payload")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_roundtrip_all_encodings() {
+        let value = json!({"a": [1, 2.5, null, {"b": true}], "s": "text"});
+        for encoding in PayloadEncoding::SUPPORTED {
+            let bytes = encode(&value, *encoding).unwrap();
+            assert_eq!(decode(&bytes, *encoding).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_msgpack_is_smaller_than_json() {
+        let value = json!({"numbers": (0..1000).collect::<Vec<_>>()});
+        let json_len = encode(&value, PayloadEncoding::Json).unwrap().len();
+        let msgpack_len = encode(&value, PayloadEncoding::MsgPack).unwrap().len();
+        assert!(msgpack_len < json_len);
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            PayloadEncoding::negotiate(&["cbor", "msgpack", "json"]),
+            PayloadEncoding::MsgPack
+        );
+        assert_eq!(PayloadEncoding::negotiate(&["json"]), PayloadEncoding::Json);
+        assert_eq!(
+            PayloadEncoding::negotiate::<&str>(&[]),
+            PayloadEncoding::Json
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_wrong_encoding() {
+        let bytes = encode(&json!({"a": 1}), PayloadEncoding::MsgPack).unwrap();
+        assert!(decode(&bytes, PayloadEncoding::Json).is_err());
+        assert!("yaml".parse::<PayloadEncoding>().is_err());
+    }
+}
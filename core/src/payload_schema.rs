@@ -0,0 +1,258 @@
+//! Payload shape inference and drift detection
+//!
+//! Catches silent contract breaks between the code that starts a target and
+//! the code that implements it. The shape of each completed execution's
+//! inputs and output is recorded per target: the first shape seen is the
+//! baseline, and drift is any difference between it and the shape seen most
+//! recently.
+//!
+//! A shape is itself JSON: `"string"`, `"number"`, `"boolean"` or `"null"`
+//! for scalars, an object of field shapes for objects, and a one-element
+//! array holding the item shape for arrays (`[]` when only empty arrays were
+//! seen). Items of different kinds make `"mixed"`.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+
+/// Which payload of an execution a shape describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadKind {
+    Inputs,
+    Output,
+}
+
+impl PayloadKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadKind::Inputs => "inputs",
+            PayloadKind::Output => "output",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "inputs" => Some(PayloadKind::Inputs),
+            "output" => Some(PayloadKind::Output),
+            _ => None,
+        }
+    }
+}
+
+/// One difference from the baseline shape
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ShapeChange {
+    /// A field the baseline doesn't have
+    Added { path: String, shape: JsonValue },
+    /// A baseline field that's no longer sent
+    Missing { path: String, shape: JsonValue },
+    /// A value of a different kind than the baseline's
+    TypeChanged {
+        path: String,
+        from: JsonValue,
+        to: JsonValue,
+    },
+}
+
+/// Shapes recorded for one payload of one target
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayloadShapes {
+    pub target_name: String,
+    pub kind: PayloadKind,
+    pub baseline: JsonValue,
+    pub latest: JsonValue,
+    /// Completed executions observed
+    pub samples: i64,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// A payload whose latest shape differs from its baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaDrift {
+    #[serde(flatten)]
+    pub shapes: PayloadShapes,
+    pub changes: Vec<ShapeChange>,
+}
+
+impl SchemaDrift {
+    /// Compare the recorded shapes; `None` if they match
+    pub fn detect(shapes: PayloadShapes) -> Option<Self> {
+        let changes = diff_shapes(&shapes.baseline, &shapes.latest);
+        (!changes.is_empty()).then_some(Self { shapes, changes })
+    }
+}
+
+/// Infer the shape of a value
+pub fn infer_shape(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Null => JsonValue::from("null"),
+        JsonValue::Bool(_) => JsonValue::from("boolean"),
+        JsonValue::Number(_) => JsonValue::from("number"),
+        JsonValue::String(_) => JsonValue::from("string"),
+        JsonValue::Array(items) => {
+            let item = items
+                .iter()
+                .map(infer_shape)
+                .reduce(|a, b| merge_shapes(&a, &b));
+            JsonValue::Array(item.into_iter().collect())
+        }
+        JsonValue::Object(fields) => JsonValue::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), infer_shape(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Combine the shapes of two items of the same array
+fn merge_shapes(a: &JsonValue, b: &JsonValue) -> JsonValue {
+    match (a, b) {
+        _ if a == b => a.clone(),
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            let mut merged = a.clone();
+            for (key, shape) in b {
+                let shape = match a.get(key) {
+                    Some(existing) => merge_shapes(existing, shape),
+                    None => shape.clone(),
+                };
+                merged.insert(key.clone(), shape);
+            }
+            JsonValue::Object(merged)
+        }
+        (JsonValue::Array(a), JsonValue::Array(b)) => match (a.first(), b.first()) {
+            (Some(a), Some(b)) => JsonValue::Array(vec![merge_shapes(a, b)]),
+            (Some(item), None) | (None, Some(item)) => JsonValue::Array(vec![item.clone()]),
+            (None, None) => JsonValue::Array(Vec::new()),
+        },
+        _ => JsonValue::from("mixed"),
+    }
+}
+
+/// Differences between a baseline shape and a later one
+pub fn diff_shapes(baseline: &JsonValue, latest: &JsonValue) -> Vec<ShapeChange> {
+    let mut changes = Vec::new();
+    diff_at("", baseline, latest, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, baseline: &JsonValue, latest: &JsonValue, changes: &mut Vec<ShapeChange>) {
+    match (baseline, latest) {
+        _ if baseline == latest => {}
+        (JsonValue::Object(baseline), JsonValue::Object(latest)) => {
+            diff_fields(path, baseline, latest, changes)
+        }
+        (JsonValue::Array(baseline), JsonValue::Array(latest)) => {
+            // An empty array says nothing about its items
+            if let (Some(baseline), Some(latest)) = (baseline.first(), latest.first()) {
+                diff_at(&format!("{}[]", path), baseline, latest, changes);
+            }
+        }
+        _ => changes.push(ShapeChange::TypeChanged {
+            path: path.to_string(),
+            from: baseline.clone(),
+            to: latest.clone(),
+        }),
+    }
+}
+
+fn diff_fields(
+    path: &str,
+    baseline: &Map<String, JsonValue>,
+    latest: &Map<String, JsonValue>,
+    changes: &mut Vec<ShapeChange>,
+) {
+    let field_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    for (key, shape) in baseline {
+        match latest.get(key) {
+            Some(latest) => diff_at(&field_path(key), shape, latest, changes),
+            None => changes.push(ShapeChange::Missing {
+                path: field_path(key),
+                shape: shape.clone(),
+            }),
+        }
+    }
+    for (key, shape) in latest {
+        if !baseline.contains_key(key) {
+            changes.push(ShapeChange::Added {
+                path: field_path(key),
+                shape: shape.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_infer_shape() {
+        assert_eq!(
+            infer_shape(&json!({
+                "id": "a1",
+                "amount": 12.5,
+                "tags": [],
+                "lines": [{ "sku": "x" }, { "sku": "y", "qty": 2 }],
+                "mixed": [1, "two"],
+            })),
+            json!({
+                "id": "string",
+                "amount": "number",
+                "tags": [],
+                "lines": [{ "sku": "string", "qty": "number" }],
+                "mixed": ["mixed"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_diff_shapes() {
+        let baseline = json!({
+            "id": "string",
+            "amount": "number",
+            "customer": { "email": "string" },
+            "lines": [{ "sku": "string" }],
+        });
+        let latest = json!({
+            "id": "number",
+            "customer": { "email": "string", "phone": "string" },
+            "lines": [{ "sku": "null" }],
+        });
+
+        assert_eq!(
+            diff_shapes(&baseline, &latest),
+            vec![
+                ShapeChange::Missing {
+                    path: "amount".to_string(),
+                    shape: json!("number"),
+                },
+                ShapeChange::Added {
+                    path: "customer.phone".to_string(),
+                    shape: json!("string"),
+                },
+                ShapeChange::TypeChanged {
+                    path: "id".to_string(),
+                    from: json!("string"),
+                    to: json!("number"),
+                },
+                ShapeChange::TypeChanged {
+                    path: "lines[].sku".to_string(),
+                    from: json!("string"),
+                    to: json!("null"),
+                },
+            ]
+        );
+        assert!(diff_shapes(&json!({ "tags": ["string"] }), &json!({ "tags": [] })).is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+//! Where workers claim work from
+//!
+//! Executions, their state and the record of queued work always live in
+//! Postgres: every path that queues work writes a `work_queue` row in the
+//! same transaction as the state change behind it, so a wakeup is never
+//! lost. A `QueueBackend` decides how workers get at that work.
+//!
+//! `PostgresQueue`, the default, claims straight from `work_queue`. With
+//! `[queue] backend = "redis"` and the `redis` feature, `RedisQueue` hands
+//! out work from Redis, refilled from `work_queue` in batches, so idle
+//! workers poll Redis instead of Postgres. Other backends, e.g. SQS, are set
+//! with `Application::set_queue_backend`.
+//!
+//! Claims made through a backend are `work_queue` leases either way, so
+//! heartbeats, the reaper and completions inside transactions are the same
+//! for every backend. Workers with concurrency groups claim from Postgres.
+
+#[cfg(feature = "redis")]
+mod redis_queue;
+
+use anyhow::Result;
+use sqlx::PgPool;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::config::{QueueBackendKind, QueueConfig};
+use crate::db;
+
+#[cfg(feature = "redis")]
+pub use redis_queue::RedisQueue;
+
+/// What `QueueBackend` methods return
+pub type QueueFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Source of work for workers
+///
+/// Work claimed here must hold a `work_queue` lease on return, as
+/// `db::work_queue::claim_work` takes, so the rest of the worker can treat
+/// it the same whatever the backend.
+pub trait QueueBackend: Send + Sync + Debug {
+    /// Queue work for `execution_id` outside any transaction
+    ///
+    /// Paths that change execution state queue work in their own
+    /// transaction with `db::work_queue::enqueue_work` instead.
+    fn enqueue<'a>(
+        &'a self,
+        execution_id: &'a str,
+        queue: &'a str,
+        priority: i32,
+    ) -> QueueFuture<'a, ()>;
+
+    /// Claim up to `limit` pieces of work from `queues` for `worker_id`
+    ///
+    /// Returns the claimed execution IDs with the queue each came from.
+    fn claim<'a>(
+        &'a self,
+        queues: &'a [String],
+        limit: usize,
+        worker_id: Option<&'a str>,
+    ) -> QueueFuture<'a, Vec<(String, String)>>;
+
+    /// Finish claimed work that isn't finished in a transaction of its own
+    fn complete<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()>;
+
+    /// Give up a claim without processing the work, so it can be claimed
+    /// again
+    fn release<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()>;
+}
+
+/// Work claimed straight from the `work_queue` table
+#[derive(Debug, Clone)]
+pub struct PostgresQueue {
+    pool: PgPool,
+}
+
+impl PostgresQueue {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl QueueBackend for PostgresQueue {
+    fn enqueue<'a>(
+        &'a self,
+        execution_id: &'a str,
+        queue: &'a str,
+        priority: i32,
+    ) -> QueueFuture<'a, ()> {
+        Box::pin(db::work_queue::enqueue_work(
+            &self.pool,
+            execution_id,
+            queue,
+            priority,
+        ))
+    }
+
+    fn claim<'a>(
+        &'a self,
+        queues: &'a [String],
+        limit: usize,
+        worker_id: Option<&'a str>,
+    ) -> QueueFuture<'a, Vec<(String, String)>> {
+        let limit = i32::try_from(limit).unwrap_or(i32::MAX);
+        Box::pin(db::work_queue::claim_work_batch(
+            &self.pool, queues, limit, worker_id,
+        ))
+    }
+
+    fn complete<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()> {
+        Box::pin(db::work_queue::complete_work(&self.pool, execution_id))
+    }
+
+    fn release<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()> {
+        Box::pin(db::work_queue::release_work(&self.pool, execution_id))
+    }
+}
+
+/// The backend `config` names, on `pool`
+pub fn from_config(config: &QueueConfig, pool: PgPool) -> Result<Arc<dyn QueueBackend>> {
+    check_config(config)?;
+    Ok(match config.backend {
+        QueueBackendKind::Postgres => Arc::new(PostgresQueue::new(pool)),
+        #[cfg(feature = "redis")]
+        QueueBackendKind::Redis => Arc::new(RedisQueue::from_config(config, pool)?),
+        #[cfg(not(feature = "redis"))]
+        QueueBackendKind::Redis => unreachable!("check_config rejects the redis backend"),
+    })
+}
+
+/// Check `config` without connecting to anything
+pub fn check_config(config: &QueueConfig) -> Result<()> {
+    match config.backend {
+        QueueBackendKind::Postgres => Ok(()),
+        #[cfg(feature = "redis")]
+        QueueBackendKind::Redis => RedisQueue::check_config(config),
+        #[cfg(not(feature = "redis"))]
+        QueueBackendKind::Redis => {
+            anyhow::bail!(
+                "The redis queue backend needs rhythm-core built with the `redis` feature"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_config() {
+        assert!(check_config(&QueueConfig::default()).is_ok());
+
+        let redis = QueueConfig {
+            backend: QueueBackendKind::Redis,
+            ..Default::default()
+        };
+        let result = check_config(&redis);
+        if cfg!(feature = "redis") {
+            assert!(result.unwrap_err().to_string().contains("redis_url"));
+        } else {
+            assert!(result.unwrap_err().to_string().contains("`redis` feature"));
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Work handed out from Redis
+//!
+//! Each queue is a sorted set, `<key_prefix>:queue:<name>`, of executions
+//! whose work is already claimed in `work_queue`, scored in the order they
+//! were added. A claim pops the lowest scores and renews their leases,
+//! dropping any whose work was finished, cancelled or released while it
+//! waited. When a queue runs short, the worker that finds it so claims a
+//! batch from Postgres and adds it, holding `<key_prefix>:refill:<name>` for
+//! `REFILL_INTERVAL` so that one worker at a time polls Postgres per queue.
+//!
+//! Work waits in Redis under its lease. If nobody pops it before the lease
+//! runs out, a later refill claims it again, and the set keeps one entry per
+//! execution. Work is handed out in the order it reached Redis, which is
+//! priority order within a batch. A sticky workflow is only held back for
+//! its worker's refill; any worker may pop it after that.
+
+use anyhow::{Context, Result};
+use redis::aio::ConnectionManager;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+use super::{QueueBackend, QueueFuture};
+use crate::config::QueueConfig;
+use crate::db;
+
+/// How long a refill keeps other workers from refilling the same queue
+const REFILL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Work claimed from Redis, refilled from the `work_queue` table
+pub struct RedisQueue {
+    pool: PgPool,
+    client: redis::Client,
+    // Connected on first use, so it is always made inside a runtime
+    connection: OnceCell<ConnectionManager>,
+    key_prefix: String,
+    refill_batch_size: usize,
+}
+
+impl std::fmt::Debug for RedisQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisQueue")
+            .field("key_prefix", &self.key_prefix)
+            .field("refill_batch_size", &self.refill_batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisQueue {
+    /// A queue on the Redis at `config.redis_url`; nothing is connected yet
+    pub fn from_config(config: &QueueConfig, pool: PgPool) -> Result<Self> {
+        Ok(Self {
+            pool,
+            client: client(config)?,
+            connection: OnceCell::new(),
+            key_prefix: config.key_prefix.clone(),
+            refill_batch_size: config.refill_batch_size.max(1),
+        })
+    }
+
+    pub(super) fn check_config(config: &QueueConfig) -> Result<()> {
+        client(config).map(drop)
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await
+            .context("Failed to connect to Redis")?;
+        Ok(connection.clone())
+    }
+
+    fn queue_key(&self, queue: &str) -> String {
+        format!("{}:queue:{}", self.key_prefix, queue)
+    }
+
+    /// Take up to `limit` executions from `queues`, in order
+    async fn pop(
+        &self,
+        connection: &mut ConnectionManager,
+        queues: &[String],
+        limit: usize,
+    ) -> Result<Vec<(String, String)>> {
+        let mut popped = Vec::new();
+        for queue in queues {
+            if popped.len() >= limit {
+                break;
+            }
+            let entries: Vec<(String, f64)> = redis::cmd("ZPOPMIN")
+                .arg(self.queue_key(queue))
+                .arg(limit - popped.len())
+                .query_async(connection)
+                .await
+                .context("Failed to claim work from Redis")?;
+            popped.extend(entries.into_iter().map(|(id, _)| (id, queue.clone())));
+        }
+        Ok(popped)
+    }
+
+    /// Move a batch of work from Postgres to Redis for the `queues` no other
+    /// worker refilled in the last `REFILL_INTERVAL`
+    async fn refill(
+        &self,
+        connection: &mut ConnectionManager,
+        queues: &[String],
+        worker_id: Option<&str>,
+    ) -> Result<()> {
+        let mut refilling = Vec::new();
+        for queue in queues {
+            let locked: Option<String> = redis::cmd("SET")
+                .arg(format!("{}:refill:{}", self.key_prefix, queue))
+                .arg(worker_id.unwrap_or("-"))
+                .arg("NX")
+                .arg("PX")
+                .arg(REFILL_INTERVAL.as_millis() as u64)
+                .query_async(connection)
+                .await
+                .context("Failed to lock a Redis queue for refilling")?;
+            if locked.is_some() {
+                refilling.push(queue.clone());
+            }
+        }
+        if refilling.is_empty() {
+            return Ok(());
+        }
+
+        let limit = i32::try_from(self.refill_batch_size).unwrap_or(i32::MAX);
+        let claimed =
+            db::work_queue::claim_work_batch(&self.pool, &refilling, limit, worker_id).await?;
+        if claimed.is_empty() {
+            return Ok(());
+        }
+
+        // Scores come from a shared counter, so batches keep their order
+        // across workers whatever their clocks say. Work that isn't added
+        // is claimed again once its lease runs out.
+        let last: i64 = redis::cmd("INCRBY")
+            .arg(format!("{}:sequence", self.key_prefix))
+            .arg(claimed.len())
+            .query_async(connection)
+            .await
+            .context("Failed to add work to Redis")?;
+        let first = last - claimed.len() as i64 + 1;
+        let mut pipe = redis::pipe();
+        for (n, (execution_id, queue)) in claimed.iter().enumerate() {
+            pipe.cmd("ZADD")
+                .arg(self.queue_key(queue))
+                .arg("NX")
+                .arg(first + n as i64)
+                .arg(execution_id)
+                .ignore();
+        }
+        pipe.query_async::<()>(connection)
+            .await
+            .context("Failed to add work to Redis")?;
+        Ok(())
+    }
+}
+
+impl QueueBackend for RedisQueue {
+    /// Queued in Postgres, and moved to Redis by the next refill
+    fn enqueue<'a>(
+        &'a self,
+        execution_id: &'a str,
+        queue: &'a str,
+        priority: i32,
+    ) -> QueueFuture<'a, ()> {
+        Box::pin(db::work_queue::enqueue_work(
+            &self.pool,
+            execution_id,
+            queue,
+            priority,
+        ))
+    }
+
+    fn claim<'a>(
+        &'a self,
+        queues: &'a [String],
+        limit: usize,
+        worker_id: Option<&'a str>,
+    ) -> QueueFuture<'a, Vec<(String, String)>> {
+        Box::pin(async move {
+            let mut connection = self.connection().await?;
+            let mut popped = self.pop(&mut connection, queues, limit).await?;
+            if popped.len() < limit {
+                self.refill(&mut connection, queues, worker_id).await?;
+                let more = self
+                    .pop(&mut connection, queues, limit - popped.len())
+                    .await?;
+                popped.extend(more);
+            }
+            if popped.is_empty() {
+                return Ok(popped);
+            }
+
+            let ids: Vec<String> = popped.iter().map(|(id, _)| id.clone()).collect();
+            let held: HashSet<String> = db::work_queue::extend_claims(&self.pool, &ids)
+                .await?
+                .into_iter()
+                .collect();
+            popped.retain(|(id, _)| held.contains(id));
+            Ok(popped)
+        })
+    }
+
+    fn complete<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()> {
+        Box::pin(db::work_queue::complete_work(&self.pool, execution_id))
+    }
+
+    /// Released in Postgres, and moved to Redis again by a later refill
+    fn release<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()> {
+        Box::pin(db::work_queue::release_work(&self.pool, execution_id))
+    }
+}
+
+fn client(config: &QueueConfig) -> Result<redis::Client> {
+    let url = config
+        .redis_url
+        .as_deref()
+        .context("The redis queue backend needs a redis_url")?;
+    redis::Client::open(url).context("Invalid redis_url")
+}
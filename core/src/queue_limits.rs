@@ -0,0 +1,209 @@
+//! Queue depth limits
+//!
+//! `[queue] max_depth` caps the work waiting in a queue, i.e. queued but not
+//! yet claimed, so producers can't grow a queue faster than workers drain it
+//! without bound. Creating an execution on a full queue waits up to
+//! `full_wait_ms` for room and then fails with `QueueFull`.
+//!
+//! Child executions a workflow starts are checked too. A run that would
+//! queue a child on a full queue is retried with the runner's backoff (see
+//! `runner_retry`) from the last saved state, so the workflow waits for room
+//! instead of failing; it fails only once the runner's retries run out.
+//! Work queued for executions that already exist (wakeups, retries, due
+//! scheduled executions) is never held back.
+//!
+//! Depth is checked inside the transaction that queues the work, under a
+//! per-queue lock, so concurrent creations can't both take the last slot.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::config::QueueConfig;
+use crate::db;
+
+/// How often a creation waiting for room tries again
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Error returned when queueing an execution would exceed its queue's depth
+///
+/// Adapters can find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueFull {
+    pub queue: String,
+    pub limit: i64,
+    /// Work waiting at the time of the check, before the rejected execution
+    pub depth: i64,
+}
+
+impl std::fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Queue '{}' is full: max_depth is {} (current depth {})",
+            self.queue, self.limit, self.depth
+        )
+    }
+}
+
+impl std::error::Error for QueueFull {}
+
+/// Checks queued work against the configured depths
+#[derive(Debug, Clone, Default)]
+pub struct QueueLimits {
+    max_depth: HashMap<String, i64>,
+    default_max_depth: Option<i64>,
+    full_wait: Duration,
+}
+
+impl QueueLimits {
+    pub fn from_config(config: &QueueConfig) -> Self {
+        Self {
+            max_depth: config.max_depth.clone(),
+            default_max_depth: config.default_max_depth,
+            full_wait: Duration::from_millis(config.full_wait_ms),
+        }
+    }
+
+    /// Most work that may wait in `queue`, if it is limited
+    pub fn max_depth(&self, queue: &str) -> Option<i64> {
+        self.max_depth
+            .get(queue)
+            .copied()
+            .or(self.default_max_depth)
+    }
+
+    /// Admit one more piece of work for each entry of `queues`, about to be
+    /// queued in `tx`
+    ///
+    /// Fails with `QueueFull` if any queue would go over its max depth. Holds
+    /// a lock on each limited queue until `tx` ends, taken in name order so
+    /// transactions checking several queues can't deadlock.
+    pub async fn admit(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        queues: &[&str],
+    ) -> Result<()> {
+        let mut incoming: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+        for queue in queues {
+            if let Some(limit) = self.max_depth(queue) {
+                incoming.entry(queue).or_insert((limit, 0)).1 += 1;
+            }
+        }
+
+        for (queue, (limit, count)) in incoming {
+            db::work_queue::lock_queue_depth(tx, queue).await?;
+            let depth = db::work_queue::pending_depth(&mut **tx, queue).await?;
+            if depth + count > limit {
+                return Err(QueueFull {
+                    queue: queue.to_string(),
+                    limit,
+                    depth,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Run `attempt` until it doesn't fail with `QueueFull`, or until
+    /// `full_wait_ms` has passed, returning its last result
+    pub async fn wait_for_room<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let deadline = Instant::now() + self.full_wait;
+        loop {
+            match attempt().await {
+                Err(e) if e.is::<QueueFull>() => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(e);
+                    }
+                    tokio::time::sleep(RETRY_INTERVAL.min(deadline - now)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full() -> anyhow::Error {
+        QueueFull {
+            queue: "emails".to_string(),
+            limit: 2,
+            depth: 2,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_max_depth_falls_back_to_default() {
+        let mut config = QueueConfig::default();
+        assert_eq!(QueueLimits::from_config(&config).max_depth("emails"), None);
+
+        config.max_depth.insert("emails".to_string(), 50);
+        config.default_max_depth = Some(1000);
+        let limits = QueueLimits::from_config(&config);
+        assert_eq!(limits.max_depth("emails"), Some(50));
+        assert_eq!(limits.max_depth("default"), Some(1000));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_room_retries_until_the_wait_runs_out() {
+        let limits = QueueLimits::from_config(&QueueConfig {
+            full_wait_ms: 300,
+            ..Default::default()
+        });
+
+        let mut attempts = 0;
+        let result = limits
+            .wait_for_room(|| {
+                attempts += 1;
+                let result = if attempts < 3 {
+                    Err(full())
+                } else {
+                    Ok(attempts)
+                };
+                async move { result }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let started = Instant::now();
+        let err = limits
+            .wait_for_room(|| async { Err::<(), _>(full()) })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Queue 'emails' is full: max_depth is 2 (current depth 2)"
+        );
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let limits = QueueLimits::from_config(&QueueConfig {
+            full_wait_ms: 60_000,
+            ..Default::default()
+        });
+
+        let mut attempts = 0;
+        let result = limits
+            .wait_for_room(|| {
+                attempts += 1;
+                async { Err::<(), _>(anyhow::anyhow!("Database is down")) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,273 @@
+//! Per-namespace execution quotas
+//!
+//! Limits from `[quotas]` config are checked inside the transaction that
+//! creates an execution, so one tenant cannot exhaust shared infrastructure.
+//! Child executions started by a running workflow count toward their
+//! namespace's usage but are never rejected: failing a workflow halfway
+//! through is worse than letting it finish.
+//!
+//! Only the configured limits are counted on a creation. Running executions
+//! and recent enqueues are counted by index; stored bytes are kept as a
+//! running total per namespace (see the `namespace_storage` migration), so
+//! the check doesn't grow with the namespace's history.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::config::{NamespaceQuota, QuotasConfig};
+use crate::db;
+use crate::types::{CreateExecutionParams, NamespaceUsage};
+
+/// Which limit a creation ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaKind {
+    MaxRunning,
+    MaxEnqueuesPerMinute,
+    MaxStorageBytes,
+}
+
+impl QuotaKind {
+    /// Name of the limit as written in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaKind::MaxRunning => "max_running",
+            QuotaKind::MaxEnqueuesPerMinute => "max_enqueues_per_minute",
+            QuotaKind::MaxStorageBytes => "max_storage_bytes",
+        }
+    }
+}
+
+/// Error returned when creating an execution would exceed a namespace quota
+///
+/// Adapters can find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaExceeded {
+    pub namespace: String,
+    pub kind: QuotaKind,
+    pub limit: i64,
+    /// Usage at the time of the check, before the rejected execution
+    pub current: i64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Quota exceeded for namespace '{}': {} is {} (current usage {})",
+            self.namespace,
+            self.kind.as_str(),
+            self.limit,
+            self.current
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Checks creations against the configured quotas
+///
+/// Each Application owns one, along with its rejection counters.
+#[derive(Debug, Default)]
+pub struct QuotaEnforcer {
+    config: QuotasConfig,
+    /// Rejections per namespace, then per limit name
+    rejections: Mutex<BTreeMap<String, BTreeMap<&'static str, u64>>>,
+}
+
+impl QuotaEnforcer {
+    pub fn new(config: QuotasConfig) -> Self {
+        Self {
+            config,
+            rejections: Mutex::default(),
+        }
+    }
+
+    /// Limits that apply to a namespace
+    pub fn limits(&self, namespace: &str) -> &NamespaceQuota {
+        self.config.for_namespace(namespace)
+    }
+
+    /// Admit an execution about to be created in `tx`
+    ///
+    /// Resolves the namespace onto `params` and fails with `QuotaExceeded` if
+    /// the execution would push the namespace over a limit. Holds a
+    /// per-namespace lock until `tx` ends so concurrent creations can't both
+    /// squeeze under a limit.
+    pub async fn admit(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        params: &mut CreateExecutionParams,
+    ) -> Result<()> {
+        let namespace = db::quotas::resolve_namespace(
+            tx,
+            params.namespace.as_deref(),
+            params.parent_workflow_id.as_deref(),
+        )
+        .await?;
+
+        let limits = self.limits(&namespace);
+        if !limits.is_unlimited() {
+            db::quotas::lock_namespace(tx, &namespace).await?;
+            let usage = usage_for(tx, &namespace, limits).await?;
+
+            if let Some(exceeded) = check(limits, &usage, payload_size(&params.inputs)) {
+                self.record_rejection(&exceeded);
+                return Err(exceeded.into());
+            }
+        }
+
+        params.namespace = Some(namespace);
+        Ok(())
+    }
+
+    /// Point-in-time copy of this enforcer's counters
+    pub fn metrics(&self) -> QuotaMetrics {
+        let rejections = self.rejections.lock().unwrap_or_else(|e| e.into_inner());
+        QuotaMetrics {
+            rejections: rejections
+                .iter()
+                .map(|(namespace, kinds)| {
+                    let kinds = kinds
+                        .iter()
+                        .map(|(kind, count)| (kind.to_string(), *count))
+                        .collect();
+                    (namespace.clone(), kinds)
+                })
+                .collect(),
+        }
+    }
+
+    fn record_rejection(&self, exceeded: &QuotaExceeded) {
+        let mut rejections = self.rejections.lock().unwrap_or_else(|e| e.into_inner());
+        *rejections
+            .entry(exceeded.namespace.clone())
+            .or_default()
+            .entry(exceeded.kind.as_str())
+            .or_default() += 1;
+    }
+}
+
+/// The usage `limits` are checked against, counting only what they limit
+async fn usage_for(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    namespace: &str,
+    limits: &NamespaceQuota,
+) -> Result<NamespaceUsage> {
+    let mut usage = NamespaceUsage {
+        namespace: namespace.to_string(),
+        ..Default::default()
+    };
+    if limits.max_running.is_some() {
+        usage.running = db::quotas::count_running(&mut **tx, namespace).await?;
+    }
+    if limits.max_enqueues_per_minute.is_some() {
+        usage.enqueued_last_minute =
+            db::quotas::count_enqueued_last_minute(&mut **tx, namespace).await?;
+    }
+    if limits.max_storage_bytes.is_some() {
+        usage.storage_bytes = db::quotas::get_storage_bytes(&mut **tx, namespace).await?;
+    }
+    Ok(usage)
+}
+
+/// First limit that one more execution with `incoming_bytes` of inputs would break
+pub fn check(
+    limits: &NamespaceQuota,
+    usage: &NamespaceUsage,
+    incoming_bytes: i64,
+) -> Option<QuotaExceeded> {
+    let exceeded = |kind, limit, current| QuotaExceeded {
+        namespace: usage.namespace.clone(),
+        kind,
+        limit,
+        current,
+    };
+
+    if let Some(limit) = limits.max_running {
+        if usage.running + 1 > limit {
+            return Some(exceeded(QuotaKind::MaxRunning, limit, usage.running));
+        }
+    }
+    if let Some(limit) = limits.max_enqueues_per_minute {
+        if usage.enqueued_last_minute + 1 > limit {
+            return Some(exceeded(
+                QuotaKind::MaxEnqueuesPerMinute,
+                limit,
+                usage.enqueued_last_minute,
+            ));
+        }
+    }
+    if let Some(limit) = limits.max_storage_bytes {
+        if usage.storage_bytes + incoming_bytes > limit {
+            return Some(exceeded(
+                QuotaKind::MaxStorageBytes,
+                limit,
+                usage.storage_bytes,
+            ));
+        }
+    }
+    None
+}
+
+fn payload_size(inputs: &JsonValue) -> i64 {
+    serde_json::to_vec(inputs).map_or(0, |bytes| bytes.len() as i64)
+}
+
+/// Point-in-time copy of the quota counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QuotaMetrics {
+    /// `namespace -> limit name -> rejected creations`
+    pub rejections: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(running: i64, enqueued: i64, bytes: i64) -> NamespaceUsage {
+        NamespaceUsage {
+            namespace: "acme".to_string(),
+            running,
+            enqueued_last_minute: enqueued,
+            storage_bytes: bytes,
+        }
+    }
+
+    #[test]
+    fn test_unlimited_never_rejects() {
+        let limits = NamespaceQuota::default();
+        assert_eq!(
+            check(&limits, &usage(1_000_000, 1_000_000, i64::MAX / 2), 10),
+            None
+        );
+    }
+
+    #[test]
+    fn test_limits_checked_in_order() {
+        let limits = NamespaceQuota {
+            max_running: Some(2),
+            max_enqueues_per_minute: Some(5),
+            max_storage_bytes: Some(100),
+        };
+
+        assert_eq!(check(&limits, &usage(1, 4, 90), 10), None);
+
+        let exceeded = check(&limits, &usage(2, 5, 200), 10).unwrap();
+        assert_eq!(exceeded.kind, QuotaKind::MaxRunning);
+        assert_eq!(exceeded.limit, 2);
+        assert_eq!(exceeded.current, 2);
+
+        let exceeded = check(&limits, &usage(1, 5, 0), 10).unwrap();
+        assert_eq!(exceeded.kind, QuotaKind::MaxEnqueuesPerMinute);
+
+        let exceeded = check(&limits, &usage(1, 4, 91), 10).unwrap();
+        assert_eq!(exceeded.kind, QuotaKind::MaxStorageBytes);
+        assert_eq!(
+            exceeded.to_string(),
+            "Quota exceeded for namespace 'acme': max_storage_bytes is 100 (current usage 91)"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! Interactive evaluation of Flow
+//!
+//! `Repl` runs Flow source one input at a time on a single VM, so variables
+//! declared by one input are in scope for the next. An input ending with an
+//! expression evaluates to its value. Nothing leaves the process: tasks and
+//! child workflows settle with a stub given with `stub` or `fail`, or `null`
+//! without one, timers fire at once, and signals take payloads given with
+//! `signal`, as in workflow unit tests (see `flow_test`). `rhythm repl`
+//! reads inputs from a terminal.
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::executor::exec_loop::is_running;
+use crate::executor::types::ast::Span;
+use crate::executor::vm::push_stmt;
+use crate::executor::{
+    json_to_val_map, step, val_to_json, Control, LogEntry, Outbox, Stmt, Val, WorkflowContext, VM,
+};
+use crate::flow_test::harness::{Call, Harness, Status};
+use crate::flow_test::{Stub, TestCase};
+use crate::parser::parse_workflow;
+
+/// Steps one input may take before it is treated as a runaway loop
+const MAX_STEPS: usize = 1_000_000;
+
+/// What evaluating an input came to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Evaluation {
+    /// The value of the input's last expression, or of a `return`
+    Value(JsonValue),
+    /// The input ran without producing a value
+    Done,
+    /// The input threw this
+    Threw(JsonValue),
+    /// The input was stopped partway, and why
+    Stopped(String),
+}
+
+/// A Flow session on one in-memory VM
+pub struct Repl {
+    vm: VM,
+    harness: Harness,
+    /// Names the VM starts with (stdlib, `Inputs`, `Context`), left out of
+    /// `variables`
+    globals: HashSet<String>,
+    logs: Vec<LogEntry>,
+}
+
+impl Repl {
+    /// Start a session where `Inputs` is `inputs`
+    pub fn new(inputs: &JsonValue) -> Result<Self> {
+        let mut vm = VM::new(
+            Stmt::Block {
+                body: vec![],
+                span: Span::default(),
+            },
+            json_to_val_map(inputs).context("Inputs must be a JSON object")?,
+            WorkflowContext {
+                execution_id: "repl".to_string(),
+            },
+        );
+        vm.frames.clear();
+        let globals = vm.env.keys().cloned().collect();
+
+        Ok(Self {
+            vm,
+            harness: Harness::new(&TestCase::default()).with_unstubbed(Val::Null),
+            globals,
+            logs: Vec::new(),
+        })
+    }
+
+    /// Run `source`, one or more statements
+    ///
+    /// Fails only if `source` doesn't parse; errors it throws are an
+    /// `Evaluation::Threw`.
+    pub fn eval(&mut self, source: &str) -> Result<Evaluation> {
+        let workflow = parse_workflow(source).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut statements = match workflow.body {
+            Stmt::Block { body, .. } => body,
+            statement => vec![statement],
+        };
+        // Show the value of a trailing expression by returning it
+        if let Some(Stmt::Expr { expr, span }) =
+            statements.pop_if(|s| matches!(s, Stmt::Expr { .. }))
+        {
+            statements.push(Stmt::Return {
+                value: Some(expr),
+                span,
+            });
+        }
+
+        let mut steps = 0;
+        let mut evaluation = Ok(Evaluation::Done);
+        for statement in &statements {
+            push_stmt(&mut self.vm, statement);
+            evaluation = self.run(&mut steps);
+            if !matches!(evaluation, Ok(Evaluation::Done)) {
+                break;
+            }
+        }
+        self.reset();
+        evaluation
+    }
+
+    /// Settle calls to task or workflow `name` with `result`
+    ///
+    /// Stubs for one name are used in call order, the last repeating.
+    pub fn stub(&mut self, name: &str, result: JsonValue) {
+        self.harness.add_stub(Stub {
+            name: name.to_string(),
+            result: Some(result),
+            ..Default::default()
+        });
+    }
+
+    /// Fail calls to task or workflow `name` with `error`
+    pub fn fail(&mut self, name: &str, error: JsonValue) {
+        self.harness.add_stub(Stub {
+            name: name.to_string(),
+            error: Some(error),
+            ..Default::default()
+        });
+    }
+
+    /// Deliver `payload` to the next wait for signal `name`
+    pub fn signal(&mut self, name: &str, payload: JsonValue) {
+        self.harness.add_signal(name, payload);
+    }
+
+    /// Variables declared so far
+    pub fn variables(&self) -> BTreeMap<&str, &Val> {
+        self.vm
+            .env
+            .iter()
+            .filter(|(name, _)| !self.globals.contains(*name))
+            .map(|(name, val)| (name.as_str(), val))
+            .collect()
+    }
+
+    /// Tasks and workflows started so far, in order
+    pub fn calls(&self) -> &[Call] {
+        self.harness.calls()
+    }
+
+    /// Messages logged since the last call
+    pub fn take_logs(&mut self) -> Vec<LogEntry> {
+        std::mem::take(&mut self.logs)
+    }
+
+    /// Run the pushed statement to its end, settling what it awaits
+    fn run(&mut self, steps: &mut usize) -> Result<Evaluation> {
+        loop {
+            while is_running(&self.vm) {
+                if *steps >= MAX_STEPS {
+                    return Ok(Evaluation::Stopped(format!(
+                        "still running after {} steps",
+                        MAX_STEPS
+                    )));
+                }
+                step(&mut self.vm);
+                *steps += 1;
+                if !self.vm.outbox.executions.is_empty() {
+                    self.harness.collect(&mut self.vm, None)?;
+                }
+            }
+            self.harness.collect_signals(&mut self.vm);
+            self.logs.append(&mut self.vm.outbox.logs);
+
+            if self.vm.outbox.continue_as_new.is_some() {
+                return Ok(Evaluation::Stopped(
+                    "Workflow.continueAsNew has no workflow to continue here".to_string(),
+                ));
+            }
+            let Control::Suspend(awaitable) = &self.vm.control else {
+                break;
+            };
+            match self.harness.resolve(&awaitable.clone()) {
+                // A worker resumes with a failed child's error value too
+                Status::Success(val) | Status::Error(val) => {
+                    self.vm.resume(val);
+                }
+                Status::Never(reason) => return Ok(Evaluation::Stopped(reason)),
+            }
+        }
+
+        match &self.vm.control {
+            Control::None => Ok(Evaluation::Done),
+            Control::Return(val) => Ok(Evaluation::Value(to_json(val)?)),
+            Control::Throw(val) => Ok(Evaluation::Threw(to_json(val)?)),
+            Control::Break(_) => anyhow::bail!("`break` outside a loop"),
+            Control::Continue(_) => anyhow::bail!("`continue` outside a loop"),
+            control => anyhow::bail!("Unexpected control state at top level: {:?}", control),
+        }
+    }
+
+    /// Get ready for the next input, keeping variables and registered
+    /// compensations
+    fn reset(&mut self) {
+        self.vm.frames.clear();
+        self.vm.control = Control::None;
+        self.vm.unwinding = None;
+        self.vm.resume_value = None;
+        self.vm.recorded.clear();
+        self.logs.append(&mut self.vm.outbox.logs);
+        self.vm.outbox = Outbox::new();
+    }
+}
+
+/// `val` as JSON, with whole numbers as Flow prints them
+pub fn to_json(val: &Val) -> Result<JsonValue> {
+    Ok(whole_numbers(val_to_json(val)?))
+}
+
+/// `json` with whole numbers written without a fraction, e.g. `5` for `5.0`
+pub fn whole_numbers(json: JsonValue) -> JsonValue {
+    match json {
+        JsonValue::Number(n) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => JsonValue::from(f as i64),
+            _ => JsonValue::Number(n),
+        },
+        JsonValue::Array(items) => JsonValue::Array(items.into_iter().map(whole_numbers).collect()),
+        JsonValue::Object(map) => JsonValue::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, whole_numbers(value)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Whether `source` is cut off: a bracket or string is still open
+///
+/// A terminal reads more lines before evaluating such input.
+pub fn needs_more(source: &str) -> bool {
+    let mut depth = 0i32;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' | '`' => {
+                let mut closed = false;
+                while let Some(next) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c {
+                        closed = true;
+                        break;
+                    } else if next == '\n' && c != '`' {
+                        break;
+                    }
+                }
+                if !closed {
+                    return c == '`';
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars
+                    .by_ref()
+                    .take_while(|&next| next != '\n')
+                    .for_each(drop);
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn repl() -> Repl {
+        Repl::new(&json!({ "order": "o1" })).unwrap()
+    }
+
+    #[test]
+    fn test_variables_outlive_their_input() {
+        let mut repl = repl();
+        assert_eq!(repl.eval("let total = 2 + 3").unwrap(), Evaluation::Done);
+        assert_eq!(
+            repl.eval("total * 2").unwrap(),
+            Evaluation::Value(json!(10))
+        );
+        assert_eq!(
+            repl.eval("Inputs.order").unwrap(),
+            Evaluation::Value(json!("o1"))
+        );
+        assert_eq!(
+            repl.variables().into_keys().collect::<Vec<_>>(),
+            vec!["total"]
+        );
+    }
+
+    #[test]
+    fn test_tasks_settle_with_stubs() {
+        let mut repl = repl();
+        assert_eq!(
+            repl.eval(r#"await Task.run("charge", {})"#).unwrap(),
+            Evaluation::Value(JsonValue::Null)
+        );
+
+        repl.stub("charge", json!({ "id": "ch_1" }));
+        repl.eval(r#"let charge = Task.run("charge", { amount: 5 })"#)
+            .unwrap();
+        assert_eq!(
+            repl.eval("let result = await charge\nresult.id").unwrap(),
+            Evaluation::Value(json!("ch_1"))
+        );
+        let call = repl.calls().last().unwrap();
+        assert_eq!(call.target_name, "charge");
+        assert_eq!(whole_numbers(call.inputs.clone()), json!({ "amount": 5 }));
+    }
+
+    #[test]
+    fn test_errors_and_stops_dont_end_the_session() {
+        let mut repl = repl();
+        let Evaluation::Threw(error) = repl.eval("missing + 1").unwrap() else {
+            panic!("Expected a throw");
+        };
+        assert!(error.to_string().contains("missing"), "{}", error);
+
+        assert!(matches!(
+            repl.eval(r#"await Signal.next("approval")"#).unwrap(),
+            Evaluation::Stopped(_)
+        ));
+        assert!(repl.eval("let = ").is_err());
+
+        repl.signal("approval", json!({ "ok": true }));
+        assert_eq!(
+            repl.eval(r#"await Signal.next("approval")"#).unwrap(),
+            Evaluation::Value(json!({ "ok": true }))
+        );
+    }
+
+    #[test]
+    fn test_needs_more() {
+        assert!(needs_more("if (x) {"));
+        assert!(needs_more("let s = `line"));
+        assert!(!needs_more("let s = \"{\""));
+        assert!(!needs_more("f(1) // (unclosed in a comment"));
+        assert!(!needs_more("}"));
+    }
+}
@@ -0,0 +1,89 @@
+//! API Key Service
+//!
+//! Creates, lists and revokes API keys, and checks the keys callers present
+//! (see `auth`).
+
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+
+use crate::auth::{self, ApiKey, NewApiKey, Scope, Unauthenticated};
+use crate::db;
+use crate::errors::RhythmError;
+
+/// Service for API keys
+#[derive(Clone)]
+pub struct ApiKeyService {
+    pool: PgPool,
+}
+
+impl ApiKeyService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create a key with `scopes`, returning it with its secret
+    pub async fn create(&self, name: &str, scopes: &[Scope]) -> Result<NewApiKey> {
+        if name.trim().is_empty() {
+            bail!(RhythmError::Validation(
+                "An API key needs a name".to_string()
+            ));
+        }
+        if scopes.is_empty() {
+            bail!(RhythmError::Validation(
+                "An API key needs at least one scope".to_string()
+            ));
+        }
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+
+        let (id, key) = auth::generate_key();
+        let api_key =
+            db::api_keys::create_api_key(&self.pool, &id, name, &auth::hash_key(&key), &scopes)
+                .await?;
+        Ok(NewApiKey { key, api_key })
+    }
+
+    /// Every key, revoked ones included
+    pub async fn list(&self) -> Result<Vec<ApiKey>> {
+        db::api_keys::list_api_keys(&self.pool).await
+    }
+
+    /// Stop accepting a key
+    pub async fn revoke(&self, id: &str) -> Result<()> {
+        if !db::api_keys::revoke_api_key(&self.pool, id).await? {
+            bail!(RhythmError::not_found("API key", id));
+        }
+        Ok(())
+    }
+
+    /// The key `key` is, if it is accepted
+    ///
+    /// Fails with `Unauthenticated` for a key that is malformed, unknown or
+    /// revoked.
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKey> {
+        let reject = |reason: &str| {
+            Err(Unauthenticated {
+                reason: reason.to_string(),
+            }
+            .into())
+        };
+        let Some(id) = auth::key_id(key) else {
+            return reject("not an API key");
+        };
+        let Some((api_key, key_hash)) = db::api_keys::get_api_key(&self.pool, id).await? else {
+            return reject("unknown API key");
+        };
+        if !auth::hashes_match(&auth::hash_key(key), &key_hash) {
+            return reject("unknown API key");
+        }
+        if api_key.revoked_at.is_some() {
+            return reject("API key has been revoked");
+        }
+        // A read-only application can still check keys
+        if let Err(e) = db::api_keys::touch_api_key(&self.pool, id).await {
+            tracing::debug!("Failed to record use of API key {}: {:#}", id, e);
+        }
+        Ok(api_key)
+    }
+}
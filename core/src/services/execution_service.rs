@@ -1,25 +1,149 @@
 use anyhow::Result;
+use serde::Serialize;
 use serde_json::Value as JsonValue;
 use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::blob_store::PayloadStore;
 use crate::db;
-use crate::types::{CreateExecutionParams, Execution, ExecutionFilters};
+use crate::encryption::Binding;
+use crate::errors::RhythmError;
+use crate::execution_diff::{self, ExecutionDiff};
+use crate::queue_limits::QueueLimits;
+use crate::quotas::QuotaEnforcer;
+use crate::telemetry::{self, TraceContext};
+use crate::types::{
+    ChangeCursor, CreateExecutionParams, Execution, ExecutionChanges, ExecutionEvent,
+    ExecutionFilters, ExecutionLog, ExecutionOutcome, ExecutionPage, ExecutionPageCursor,
+    ExecutionType, ExecutionVmTrace, NamespaceUsage, QueueDepth, QueueStats,
+};
+
+/// Outcome of re-encrypting executions' inputs and outputs
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReencryptionSummary {
+    pub scanned: usize,
+    /// Already under the active key, or offloaded
+    pub current: usize,
+    /// Re-encrypted, or that would be without `apply`
+    pub reencrypted: usize,
+    /// Changed meanwhile, e.g. by completing; written under the active key
+    pub skipped: usize,
+    /// Executions whose payloads don't decrypt, with why
+    pub failed: Vec<ReencryptionFailure>,
+}
+
+/// An execution `reencrypt_payloads` could not decrypt
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReencryptionFailure {
+    pub execution_id: String,
+    pub error: String,
+}
 
 /// Service for managing execution lifecycle
 #[derive(Clone)]
 pub struct ExecutionService {
     pool: PgPool,
+    quotas: Arc<QuotaEnforcer>,
+    queue_limits: QueueLimits,
+    idempotency_window: Duration,
+    payloads: PayloadStore,
 }
 
 impl ExecutionService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            quotas: Arc::default(),
+            queue_limits: QueueLimits::default(),
+            idempotency_window: Duration::from_secs(24 * 60 * 60),
+            payloads: PayloadStore::default(),
+        }
+    }
+
+    /// Enforce the given namespace quotas on creation
+    pub fn with_quotas(mut self, quotas: Arc<QuotaEnforcer>) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Hold back creations on queues that are over their max depth
+    pub fn with_queue_limits(mut self, limits: QueueLimits) -> Self {
+        self.queue_limits = limits;
+        self
+    }
+
+    /// How long an idempotency key returns the execution created with it
+    pub fn with_idempotency_window(mut self, window: Duration) -> Self {
+        self.idempotency_window = window;
+        self
+    }
+
+    /// Check inputs against the payload limits, offloading oversized ones
+    pub fn with_payloads(mut self, payloads: PayloadStore) -> Self {
+        self.payloads = payloads;
+        self
     }
 
     /// Create a new execution and enqueue it for processing
-    pub async fn create_execution(&self, params: CreateExecutionParams) -> Result<String> {
+    ///
+    /// Fails with `QuotaExceeded` if the namespace is over one of its quotas,
+    /// `QueueFull` if the queue stays full for the configured wait, or
+    /// `Validation` if the inputs are over the payload limits or a tag key
+    /// is empty.
+    /// With an idempotency key already used for the same queue and target
+    /// within the idempotency window, returns that execution's ID instead.
+    /// An invalid `traceparent` is ignored, as W3C trace context asks.
+    pub async fn create_execution(&self, mut params: CreateExecutionParams) -> Result<String> {
+        params.traceparent = match params.traceparent.take() {
+            Some(traceparent) if TraceContext::parse(&traceparent).is_none() => {
+                tracing::debug!(traceparent = %traceparent, "Ignoring invalid traceparent");
+                None
+            }
+            traceparent => traceparent,
+        }
+        .or_else(telemetry::current_traceparent);
+        check_tags(&params.tags)?;
+        // Inputs are encrypted for their row, so it needs its ID up front
+        let id = params
+            .id
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+            .clone();
+        params.inputs = self
+            .payloads
+            .offload(
+                std::mem::take(&mut params.inputs),
+                "Execution inputs",
+                Binding::inputs(&id),
+            )
+            .await?;
+
+        self.queue_limits
+            .wait_for_room(|| self.insert_execution(params.clone()))
+            .await
+    }
+
+    async fn insert_execution(&self, mut params: CreateExecutionParams) -> Result<String> {
         let mut tx = self.pool.begin().await?;
 
+        if let Some(key) = &params.idempotency_key {
+            if let Some(existing_id) = db::executions::find_by_idempotency_key(
+                &mut tx,
+                &params.queue,
+                &params.target_name,
+                key,
+                self.idempotency_window,
+            )
+            .await?
+            {
+                return Ok(existing_id);
+            }
+        }
+
+        self.quotas.admit(&mut tx, &mut params).await?;
+        self.queue_limits.admit(&mut tx, &[&params.queue]).await?;
+
         let execution_id = db::executions::create_execution(&mut tx, params.clone()).await?;
 
         // Enqueue work for processing
@@ -30,18 +154,377 @@ impl ExecutionService {
         Ok(execution_id)
     }
 
-    /// Get execution by ID
+    /// Current usage counted against a namespace's quotas
+    pub async fn get_namespace_usage(&self, namespace: &str) -> Result<NamespaceUsage> {
+        db::quotas::get_namespace_usage(&self.pool, namespace).await
+    }
+
+    /// Pending and claimed work per queue
+    pub async fn queue_depths(&self) -> Result<Vec<QueueDepth>> {
+        db::work_queue::queue_depths(&self.pool).await
+    }
+
+    /// Load on each queue: its work, suspended workflows, and executions
+    /// finished in the last `window_minutes` (default 5)
+    pub async fn queue_stats(&self, window_minutes: Option<u32>) -> Result<Vec<QueueStats>> {
+        db::work_queue::queue_stats(&self.pool, window_minutes.unwrap_or(5).max(1)).await
+    }
+
+    /// Get execution by ID, with offloaded inputs and output read back in
+    /// and decrypted
     pub async fn get_execution(&self, execution_id: &str) -> Result<Option<Execution>> {
-        db::executions::get_execution(&self.pool, execution_id).await
+        let Some(mut execution) = db::executions::get_execution(&self.pool, execution_id).await?
+        else {
+            return Ok(None);
+        };
+        let id = execution.id.clone();
+        execution.inputs = self
+            .payloads
+            .resolve(execution.inputs, Binding::inputs(&id))
+            .await?;
+        if let Some(output) = execution.output.take() {
+            execution.output = Some(self.payloads.resolve(output, Binding::output(&id)).await?);
+        }
+        Ok(Some(execution))
+    }
+
+    /// Compare two executions and the children each started
+    pub async fn diff_executions(&self, left_id: &str, right_id: &str) -> Result<ExecutionDiff> {
+        let mut sides = Vec::with_capacity(2);
+        for id in [left_id, right_id] {
+            let execution = self
+                .get_execution(id)
+                .await?
+                .ok_or_else(|| RhythmError::not_found("Execution", id))?;
+            let children = db::executions::query_executions(
+                &self.pool,
+                ExecutionFilters {
+                    parent_workflow_id: Some(id.to_string()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+            sides.push((execution, children));
+        }
+        let (right, right_children) = sides.pop().unwrap();
+        let (left, left_children) = sides.pop().unwrap();
+        Ok(execution_diff::diff_executions(
+            left,
+            left_children,
+            right,
+            right_children,
+        ))
+    }
+
+    /// Trace the VM steps of a workflow's runs from now on
+    ///
+    /// The steps of a run that fails are saved for `get_vm_trace`.
+    pub async fn enable_vm_trace(&self, execution_id: &str) -> Result<()> {
+        if self.get_execution(execution_id).await?.is_none() {
+            return Err(RhythmError::not_found("Execution", execution_id).into());
+        }
+        db::vm_traces::enable(&self.pool, execution_id).await
+    }
+
+    /// Record what a workflow's runs read, to replay it with `rhythm replay`
+    ///
+    /// The values it was already resumed with are taken from its history,
+    /// and those it read from outside it are kept in its state until it
+    /// finishes, so a workflow can be recorded until then.
+    pub async fn enable_recording(&self, execution_id: &str) -> Result<()> {
+        let Some(execution) = self.get_execution(execution_id).await? else {
+            return Err(RhythmError::not_found("Execution", execution_id).into());
+        };
+        if execution.exec_type != ExecutionType::Workflow {
+            return Err(RhythmError::Validation(format!(
+                "Execution {} is not a workflow",
+                execution_id
+            ))
+            .into());
+        }
+        if execution.status.is_terminal()
+            && !db::execution_recordings::is_enabled(&self.pool, execution_id).await?
+        {
+            return Err(RhythmError::Conflict(format!(
+                "Workflow {} has already finished, so what it read is gone",
+                execution_id
+            ))
+            .into());
+        }
+        db::execution_recordings::enable(&self.pool, execution_id).await
+    }
+
+    /// The VM trace of an execution, if it opted in to tracing
+    pub async fn get_vm_trace(&self, execution_id: &str) -> Result<Option<ExecutionVmTrace>> {
+        db::vm_traces::get_trace(&self.pool, execution_id).await
+    }
+
+    /// The history of an execution, oldest event first
+    pub async fn get_execution_history(&self, execution_id: &str) -> Result<Vec<ExecutionEvent>> {
+        db::execution_events::get_history(&self.pool, execution_id).await
+    }
+
+    /// The logs of an execution, in the order they were written
+    pub async fn get_execution_logs(&self, execution_id: &str) -> Result<Vec<ExecutionLog>> {
+        db::execution_logs::get_logs(&self.pool, execution_id).await
     }
 
     /// Query executions with filters
+    ///
+    /// Offloaded inputs and outputs are left as references, and encrypted
+    /// ones encrypted.
     pub async fn query_executions(&self, filters: ExecutionFilters) -> Result<Vec<Execution>> {
         db::executions::query_executions(&self.pool, filters).await
     }
 
+    /// One page of executions matching `filters`
+    ///
+    /// `filters.limit` defaults to 100 and `filters.offset` is ignored; pass
+    /// the returned cursor to `filters.after` for the next page.
+    pub async fn list_executions(&self, mut filters: ExecutionFilters) -> Result<ExecutionPage> {
+        let limit = filters.limit.unwrap_or(100).max(1);
+        // One extra row tells whether there is a next page
+        filters.limit = Some(limit + 1);
+        filters.offset = None;
+
+        let mut executions = db::executions::query_executions(&self.pool, filters).await?;
+        let cursor = if executions.len() as i64 > limit {
+            executions.truncate(limit as usize);
+            executions
+                .last()
+                .map(|last| ExecutionPageCursor::after(last).to_string())
+        } else {
+            None
+        };
+        Ok(ExecutionPage { executions, cursor })
+    }
+
+    /// Get executions whose status changed since `since_cursor`
+    ///
+    /// Pass `None` to start from the beginning; pass the returned cursor on
+    /// the next call. `limit` defaults to 100.
+    pub async fn get_changes(
+        &self,
+        since_cursor: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<ExecutionChanges> {
+        let since = since_cursor
+            .map(str::parse::<ChangeCursor>)
+            .transpose()?
+            .unwrap_or_default();
+        let limit = limit.unwrap_or(100).max(1);
+
+        let (executions, cursor, has_more) =
+            db::executions::get_changes(&self.pool, &since, limit).await?;
+
+        Ok(ExecutionChanges {
+            executions,
+            cursor: cursor.to_string(),
+            has_more,
+        })
+    }
+
+    /// Encrypt every execution's inputs and output with the active key
+    ///
+    /// Finishes a key rotation, or encrypts what was stored before
+    /// encryption was turned on; saved workflow states are re-encrypted by
+    /// `WorkflowService::rewrite_saved_states`. Offloaded payloads keep the
+    /// key they were written with. Works through the table `batch_size`
+    /// rows at a time; with `apply` false nothing is written.
+    pub async fn reencrypt_payloads(
+        &self,
+        apply: bool,
+        batch_size: i64,
+    ) -> Result<ReencryptionSummary> {
+        let mut summary = ReencryptionSummary::default();
+        let mut after: Option<String> = None;
+        loop {
+            let page = db::executions::list_payloads_page(&self.pool, after.as_deref(), batch_size)
+                .await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some(last.execution_id.clone());
+
+            for stored in page {
+                summary.scanned += 1;
+                let id = stored.execution_id.as_str();
+                let reencrypted = self
+                    .payloads
+                    .reencrypt(&stored.inputs, Binding::inputs(id))
+                    .and_then(|inputs| {
+                        let output = match &stored.output {
+                            Some(output) => self.payloads.reencrypt(output, Binding::output(id))?,
+                            None => None,
+                        };
+                        Ok((inputs, output))
+                    });
+                let (inputs, output) = match reencrypted {
+                    Ok((None, None)) => {
+                        summary.current += 1;
+                        continue;
+                    }
+                    Ok((inputs, output)) => (
+                        inputs.unwrap_or_else(|| stored.inputs.clone()),
+                        output.or_else(|| stored.output.clone()),
+                    ),
+                    Err(e) => {
+                        summary.failed.push(ReencryptionFailure {
+                            execution_id: stored.execution_id,
+                            error: format!("{:#}", e),
+                        });
+                        continue;
+                    }
+                };
+
+                let replaced = !apply
+                    || db::executions::replace_payloads(
+                        &self.pool,
+                        &stored,
+                        &inputs,
+                        output.as_ref(),
+                    )
+                    .await?;
+                if replaced {
+                    summary.reencrypted += 1;
+                } else {
+                    summary.skipped += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
     /// Mark execution as failed
     pub async fn fail_execution(&self, execution_id: &str, error: JsonValue) -> Result<()> {
+        let error = self.payloads.seal(error, Binding::output(execution_id))?;
         crate::worker::complete_work(&self.pool, execution_id, None, Some(error)).await
     }
+
+    /// Cancel a workflow, its child executions and its queued work
+    ///
+    /// With `finish_running_tasks`, tasks a worker is running are left to
+    /// finish. Returns `false` if the workflow had already finished.
+    pub async fn cancel_workflow(
+        &self,
+        execution_id: &str,
+        reason: &str,
+        finish_running_tasks: bool,
+    ) -> Result<bool> {
+        crate::worker::cancel_workflow(&self.pool, execution_id, reason, finish_running_tasks).await
+    }
+
+    /// Cancel a workflow tree from its leaves up, so each workflow runs its
+    /// compensations after those of the workflows it started
+    ///
+    /// Waits up to `level_timeout` for each level of the tree to wind down,
+    /// see `worker::cancel_child_first`. Returns `false` if the workflow had
+    /// already finished.
+    pub async fn cancel_workflow_child_first(
+        &self,
+        execution_id: &str,
+        reason: &str,
+        level_timeout: Duration,
+    ) -> Result<bool> {
+        crate::worker::cancel_child_first(&self.pool, execution_id, reason, level_timeout).await
+    }
+
+    /// Add `tags` to an execution, replacing any with the same keys
+    ///
+    /// Fails with `NotFound` if there is no such execution, or `Validation`
+    /// for an empty key. Children it already started keep the tags they had.
+    pub async fn tag_execution(
+        &self,
+        execution_id: &str,
+        tags: HashMap<String, String>,
+    ) -> Result<()> {
+        check_tags(&tags)?;
+        if !db::executions::add_tags(&self.pool, execution_id, &tags).await? {
+            return Err(RhythmError::not_found("Execution", execution_id).into());
+        }
+        Ok(())
+    }
+
+    /// An execution's tags
+    ///
+    /// Fails with `NotFound` if there is no such execution.
+    pub async fn get_execution_tags(&self, execution_id: &str) -> Result<HashMap<String, String>> {
+        db::executions::get_tags(&self.pool, execution_id)
+            .await?
+            .ok_or_else(|| RhythmError::not_found("Execution", execution_id).into())
+    }
+
+    /// Pause a workflow: its queued work, timer and signal resumptions
+    /// included, isn't claimed until it is resumed
+    ///
+    /// A run in progress finishes its current step. Tasks the workflow
+    /// started still run; their results wait for the workflow. Returns
+    /// `false` if it was already paused.
+    pub async fn pause_execution(&self, execution_id: &str) -> Result<bool> {
+        self.set_paused(execution_id, true).await
+    }
+
+    /// Resume a paused workflow, letting its queued work be claimed again
+    ///
+    /// Returns `false` if it wasn't paused.
+    pub async fn resume_execution(&self, execution_id: &str) -> Result<bool> {
+        self.set_paused(execution_id, false).await
+    }
+
+    async fn set_paused(&self, execution_id: &str, paused: bool) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        match db::executions::lock_execution(&mut tx, execution_id).await? {
+            Some((ExecutionType::Workflow, status)) if status.is_terminal() => {
+                return Err(RhythmError::Conflict(format!(
+                    "Workflow {} has already finished",
+                    execution_id
+                ))
+                .into())
+            }
+            Some((ExecutionType::Workflow, _)) => {}
+            Some(_) => {
+                return Err(RhythmError::Validation(format!(
+                    "Execution {} is not a workflow",
+                    execution_id
+                ))
+                .into())
+            }
+            None => return Err(RhythmError::not_found("Execution", execution_id).into()),
+        }
+        let changed = db::executions::set_paused(&mut *tx, execution_id, paused).await?;
+        tx.commit().await?;
+
+        if changed {
+            tracing::info!(execution_id, paused, "Workflow pause changed");
+        }
+        Ok(changed)
+    }
+
+    /// Resolve an external task's promise with a result
+    ///
+    /// A result over the payload limits rejects the promise with
+    /// `PAYLOAD_TOO_LARGE` instead.
+    pub async fn complete_external_task(&self, token: &str, result: JsonValue) -> Result<()> {
+        // The token is the external task's execution ID
+        let outcome = self
+            .payloads
+            .offload_result(result, "Task result", token)
+            .await?;
+        crate::worker::complete_external_task(&self.pool, token, outcome).await
+    }
+
+    /// Reject an external task's promise with an error
+    pub async fn fail_external_task(&self, token: &str, error: JsonValue) -> Result<()> {
+        let error = self.payloads.seal(error, Binding::output(token))?;
+        crate::worker::complete_external_task(&self.pool, token, ExecutionOutcome::Failure(error))
+            .await
+    }
+}
+
+/// Tag keys can't be empty: `tags.<key>` names them in listing filters
+fn check_tags(tags: &HashMap<String, String>) -> Result<()> {
+    if tags.contains_key("") {
+        return Err(RhythmError::Validation("Tag keys can't be empty".to_string()).into());
+    }
+    Ok(())
 }
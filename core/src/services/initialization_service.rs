@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value as JsonValue};
 use sqlx::PgPool;
 
 use crate::application::WorkflowFile;
@@ -8,11 +9,21 @@ use crate::db;
 #[derive(Clone)]
 pub struct InitializationService {
     pool: PgPool,
+    defaults: Map<String, JsonValue>,
 }
 
 impl InitializationService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            defaults: Map::new(),
+        }
+    }
+
+    /// Merge these settings under each registered workflow's front matter
+    pub fn with_workflow_defaults(mut self, defaults: Map<String, JsonValue>) -> Self {
+        self.defaults = defaults;
+        self
     }
 
     /// Run initialization tasks (migrations, workflow registration)
@@ -41,12 +52,12 @@ impl InitializationService {
 
     /// Register workflows in the database (idempotent)
     pub async fn register_workflows(&self, workflows: Vec<WorkflowFile>) -> Result<()> {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
         for workflow in workflows {
+            let source =
+                super::workflow_service::translate_legacy_source(&workflow.name, &workflow.source);
+
             // Parse and validate the workflow source
-            let _ast = crate::parser::parse(&workflow.source).map_err(|e| {
+            let ast = crate::parser::parse_workflow(&source).map_err(|e| {
                 anyhow!(
                     "Failed to parse workflow '{}' from {}: {:?}",
                     workflow.name,
@@ -54,11 +65,26 @@ impl InitializationService {
                     e
                 )
             })?;
+            let settings =
+                super::workflow_service::resolve_settings(&workflow.name, &ast, &self.defaults)?;
+            let validator = super::workflow_service::validator_for(&workflow.name, &settings)?;
+            validator.validate(&ast, &source).map_err(|e| {
+                anyhow!(
+                    "Invalid workflow '{}' in {}: {}",
+                    workflow.name,
+                    workflow.file_path,
+                    e
+                )
+            })?;
+            super::workflow_service::log_workflow_warnings(
+                &workflow.name,
+                &validator,
+                &ast,
+                &source,
+            );
 
             // Generate version hash
-            let mut hasher = DefaultHasher::new();
-            workflow.source.hash(&mut hasher);
-            let version_hash = format!("{:x}", hasher.finish());
+            let version_hash = super::workflow_service::version_hash(&source, &settings);
 
             // Check if workflow already exists
             let existing_id = db::workflow_definitions::get_workflow_by_name_and_hash(
@@ -77,11 +103,12 @@ impl InitializationService {
             }
 
             // Register the new workflow definition
-            db::workflow_definitions::create_workflow_definition(
+            db::workflow_definitions::create_workflow_definition_with_settings(
                 &self.pool,
                 &workflow.name,
                 &version_hash,
-                &workflow.source,
+                &source,
+                &JsonValue::Object(settings),
             )
             .await
             .with_context(|| format!("Failed to register workflow '{}'", workflow.name))?;
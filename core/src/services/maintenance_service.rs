@@ -0,0 +1,71 @@
+//! Maintenance Service
+//!
+//! Pauses claims cluster-wide for a bounded time so disruptive operations,
+//! like schema migrations, can run without work in flight.
+
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::db;
+use crate::types::{MaintenanceStatus, MaintenanceWindow};
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Service for maintenance windows
+#[derive(Clone)]
+pub struct MaintenanceService {
+    pool: PgPool,
+}
+
+impl MaintenanceService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Stop all workers claiming new work until `exit` or `duration` passes
+    ///
+    /// Entering again replaces the current window.
+    pub async fn enter(
+        &self,
+        duration: Duration,
+        reason: Option<&str>,
+    ) -> Result<MaintenanceWindow> {
+        db::maintenance::enter_maintenance(&self.pool, duration.as_secs(), reason).await
+    }
+
+    /// Resume claims; returns whether a window was open
+    pub async fn exit(&self) -> Result<bool> {
+        db::maintenance::exit_maintenance(&self.pool).await
+    }
+
+    /// The open window, if any, and how much claimed work is still running
+    pub async fn status(&self) -> Result<MaintenanceStatus> {
+        Ok(MaintenanceStatus {
+            window: db::maintenance::get_maintenance_window(&self.pool).await?,
+            in_flight: db::maintenance::count_in_flight_work(&self.pool).await?,
+        })
+    }
+
+    /// Wait until no claimed work is in flight, for at most `timeout`
+    ///
+    /// Returns the last status seen. It is safe to run disruptive operations
+    /// when its window is open and nothing is in flight. Stops early if the
+    /// window closes.
+    pub async fn wait_for_drain(&self, timeout: Duration) -> Result<MaintenanceStatus> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let status = self.status().await?;
+            if status.in_flight == 0
+                || status.window.is_none()
+                || tokio::time::Instant::now() >= deadline
+            {
+                return Ok(status);
+            }
+            tokio::time::sleep_until(
+                deadline.min(tokio::time::Instant::now() + DRAIN_POLL_INTERVAL),
+            )
+            .await;
+        }
+    }
+}
@@ -1,16 +1,28 @@
+pub mod api_key_service;
 pub mod execution_service;
 pub mod initialization_service;
+pub mod maintenance_service;
 pub mod scheduler_service;
+pub mod schema_service;
 pub mod signal_service;
+pub mod slo_service;
+pub mod transfer_service;
+pub mod webhook_service;
 pub mod worker_service;
 pub mod workflow_service;
 
 #[cfg(test)]
 mod tests;
 
+pub use api_key_service::ApiKeyService;
 pub use execution_service::ExecutionService;
 pub use initialization_service::InitializationService;
+pub use maintenance_service::MaintenanceService;
 pub use scheduler_service::{ScheduledParams, SchedulerService};
+pub use schema_service::SchemaService;
 pub use signal_service::SignalService;
+pub use slo_service::SloService;
+pub use transfer_service::TransferService;
+pub use webhook_service::WebhookService;
 pub use worker_service::WorkerService;
 pub use workflow_service::WorkflowService;
@@ -6,8 +6,20 @@ use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
 
+use crate::blob_store::PayloadStore;
 use crate::db;
+use crate::db::scheduled_queue::{ScheduledItem, UpcomingItem};
+use crate::encryption::Binding;
+use crate::quotas::QuotaEnforcer;
+use crate::types::Execution;
+
+/// Error code an expired execution fails its awaiting parent with
+pub const EXPIRED_ERROR_CODE: &str = "EXPIRED";
 
 /// Parameters for scheduled items, tagged by type
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,11 +43,29 @@ pub enum ScheduledParams {
 #[derive(Clone)]
 pub struct SchedulerService {
     pool: PgPool,
+    quotas: Arc<QuotaEnforcer>,
+    payloads: PayloadStore,
 }
 
 impl SchedulerService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            quotas: Arc::default(),
+            payloads: PayloadStore::default(),
+        }
+    }
+
+    /// Enforce the given namespace quotas on creation
+    pub fn with_quotas(mut self, quotas: Arc<QuotaEnforcer>) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Check inputs against the payload limits, offloading oversized ones
+    pub fn with_payloads(mut self, payloads: PayloadStore) -> Self {
+        self.payloads = payloads;
+        self
     }
 
     /// Schedule a workflow continuation for later execution
@@ -64,22 +94,36 @@ impl SchedulerService {
     /// Schedule a new execution (workflow or task) to start at a future time.
     ///
     /// Creates the execution immediately in Pending status, then schedules
-    /// it to be enqueued in the work queue at the specified time.
+    /// it to be enqueued in the work queue at the specified time. Quotas are
+    /// checked now, since the execution exists from this point on.
     pub async fn schedule_execution(
         &self,
         params: crate::types::ScheduleExecutionParams,
     ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let inputs = self
+            .payloads
+            .offload(params.inputs, "Execution inputs", Binding::inputs(&id))
+            .await?;
         let mut tx = self.pool.begin().await?;
 
         // Create the execution immediately in Pending status
-        let create_params = crate::types::CreateExecutionParams {
-            id: None,
+        let mut create_params = crate::types::CreateExecutionParams {
+            id: Some(id),
             exec_type: params.exec_type,
             target_name: params.target_name,
             queue: params.queue.clone(),
-            inputs: params.inputs,
+            inputs,
             parent_workflow_id: None,
+            namespace: params.namespace,
+            ttl_seconds: None,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            traceparent: None,
+            tags: HashMap::new(),
         };
+        self.quotas.admit(&mut tx, &mut create_params).await?;
         let execution_id = db::executions::create_execution(&mut tx, create_params).await?;
 
         // Schedule it to be enqueued later
@@ -111,6 +155,42 @@ impl SchedulerService {
         // 1. Claim ready items (SELECT FOR UPDATE SKIP LOCKED)
         let items = db::scheduled_queue::claim_ready_items(&mut tx, limit).await?;
 
+        let count = Self::promote(&mut tx, &items).await?;
+        tx.commit().await?;
+
+        Ok(count)
+    }
+
+    /// Scheduled items due within `within`, in due order, continuing after
+    /// the `(run_at, id)` of `after` if given
+    pub async fn upcoming_items(
+        &self,
+        within: Duration,
+        after: Option<(NaiveDateTime, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<UpcomingItem>> {
+        let within_ms = i64::try_from(within.as_millis()).unwrap_or(i64::MAX);
+        db::scheduled_queue::upcoming_items(&self.pool, within_ms, after, limit).await
+    }
+
+    /// Process the items of `ids` as `process_ready_items` does
+    ///
+    /// Items not due yet by the database's clock, gone, or being processed
+    /// elsewhere are skipped. Returns the number of items processed.
+    pub async fn process_items(&self, ids: &[Uuid]) -> Result<u32> {
+        let mut tx = self.pool.begin().await?;
+        let items = db::scheduled_queue::claim_due_items(&mut tx, ids).await?;
+        let count = Self::promote(&mut tx, &items).await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    /// Enqueue the work of claimed `items` and remove them from the
+    /// scheduled queue
+    async fn promote(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        items: &[ScheduledItem],
+    ) -> Result<u32> {
         if items.is_empty() {
             return Ok(0);
         }
@@ -118,7 +198,7 @@ impl SchedulerService {
         let mut item_ids = Vec::with_capacity(items.len());
 
         // 2. Process each item based on type
-        for item in &items {
+        for item in items {
             item_ids.push(item.id);
 
             let params: ScheduledParams = serde_json::from_value(item.params.clone())
@@ -130,25 +210,64 @@ impl SchedulerService {
                     queue,
                     priority,
                 } => {
-                    db::work_queue::enqueue_work(&mut *tx, &execution_id, &queue, priority).await?;
+                    db::work_queue::enqueue_work(&mut **tx, &execution_id, &queue, priority)
+                        .await?;
                 }
                 ScheduledParams::ScheduledExecution {
                     execution_id,
                     queue,
                     priority,
                 } => {
-                    db::work_queue::enqueue_work(&mut *tx, &execution_id, &queue, priority).await?;
+                    db::work_queue::enqueue_work(&mut **tx, &execution_id, &queue, priority)
+                        .await?;
                 }
             }
         }
 
         // 3. Delete processed items
-        db::scheduled_queue::delete_items(&mut tx, &item_ids).await?;
+        db::scheduled_queue::delete_items(tx, &item_ids).await?;
+
+        Ok(items.len() as u32)
+    }
+
+    /// Expire pending executions whose TTL has run out
+    ///
+    /// Each moves to `expired` with an `EXPIRED` error as its output, its
+    /// queue entry is dropped, and a waiting parent workflow is woken, where
+    /// its `await` returns that error as for a failed task.
+    ///
+    /// Returns the expired executions.
+    pub async fn expire_unclaimed(&self, limit: i32) -> Result<Vec<Execution>> {
+        let mut tx = self.pool.begin().await?;
+
+        let error = serde_json::json!({
+            "code": EXPIRED_ERROR_CODE,
+            "message": "Not claimed before its TTL ran out",
+        });
+        let expired = db::executions::expire_due_executions(&mut *tx, &error, limit as i64).await?;
+        if expired.is_empty() {
+            return Ok(expired);
+        }
 
-        let count = items.len() as u32;
+        let ids: Vec<String> = expired.iter().map(|e| e.id.clone()).collect();
+        db::work_queue::remove_unclaimed_work(&mut *tx, &ids).await?;
+        for execution in &expired {
+            if let Some(parent_id) = &execution.parent_workflow_id {
+                db::work_queue::enqueue_work(&mut *tx, parent_id, &execution.queue, 0).await?;
+            }
+        }
 
         tx.commit().await?;
 
-        Ok(count)
+        for execution in &expired {
+            tracing::info!(
+                execution_id = %execution.id,
+                target_name = %execution.target_name,
+                queue = %execution.queue,
+                parent_workflow_id = execution.parent_workflow_id.as_deref(),
+                "Execution expired before it was claimed"
+            );
+        }
+        Ok(expired)
     }
 }
@@ -0,0 +1,85 @@
+//! Schema Service
+//!
+//! Infers the shapes of completed executions' payloads and reports drift
+//! from each target's baseline (see `payload_schema`).
+
+use anyhow::Result;
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::db;
+use crate::payload_schema::{diff_shapes, infer_shape, PayloadKind, SchemaDrift};
+
+/// Service for payload schema drift detection
+#[derive(Clone)]
+pub struct SchemaService {
+    pool: PgPool,
+}
+
+impl SchemaService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record the payload shapes of up to `limit` executions completed since
+    /// the last pass, returning how many were observed
+    ///
+    /// Warns when a target's payload changes to a shape that differs from
+    /// its baseline.
+    pub async fn analyze(&self, limit: i32) -> Result<usize> {
+        let watermark = db::payload_shapes::get_watermark(&self.pool).await?;
+        let completed =
+            db::payload_shapes::get_completed_since(&self.pool, watermark, limit).await?;
+
+        for execution in &completed {
+            let payloads = [
+                (PayloadKind::Inputs, Some(&execution.inputs)),
+                (PayloadKind::Output, execution.output.as_ref()),
+            ];
+            for (kind, payload) in payloads {
+                let Some(payload) = payload else { continue };
+                let shape = infer_shape(payload);
+                let previous = db::payload_shapes::observe_shape(
+                    &self.pool,
+                    &execution.target_name,
+                    kind,
+                    &shape,
+                    execution.completed_at,
+                )
+                .await?;
+                if previous.is_some_and(|previous| previous != shape) {
+                    self.warn_if_drifted(&execution.target_name, kind).await?;
+                }
+            }
+        }
+
+        Ok(completed.len())
+    }
+
+    async fn warn_if_drifted(&self, target_name: &str, kind: PayloadKind) -> Result<()> {
+        let shapes = db::payload_shapes::list_shapes(&self.pool, Some(target_name)).await?;
+        for shapes in shapes.iter().filter(|s| s.kind == kind) {
+            let changes = diff_shapes(&shapes.baseline, &shapes.latest);
+            if !changes.is_empty() {
+                warn!(
+                    target_name,
+                    kind = kind.as_str(),
+                    changes = changes.len(),
+                    "Payload shape drifted from its baseline"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Payloads whose latest shape differs from their baseline
+    pub async fn drift(&self, target_name: Option<&str>) -> Result<Vec<SchemaDrift>> {
+        let shapes = db::payload_shapes::list_shapes(&self.pool, target_name).await?;
+        Ok(shapes.into_iter().filter_map(SchemaDrift::detect).collect())
+    }
+
+    /// Accept a target's current payload shapes as its new baseline
+    pub async fn accept(&self, target_name: &str) -> Result<u64> {
+        db::payload_shapes::accept_latest(&self.pool, target_name).await
+    }
+}
@@ -6,6 +6,7 @@ use anyhow::{Context, Result};
 use serde_json::Value as JsonValue;
 use sqlx::PgPool;
 
+use crate::blob_store::check_reserved_keys;
 use crate::db;
 
 /// Service for signal operations
@@ -23,7 +24,8 @@ impl SignalService {
     ///
     /// Inserts the signal and enqueues the workflow for processing.
     /// The workflow will pick up the signal on its next resumption via
-    /// resolve_signal_claims.
+    /// resolve_signal_claims. Payloads are stored as they are, so they
+    /// can't use the keys reserved for offloaded or encrypted results.
     pub async fn send_signal(
         &self,
         workflow_id: &str,
@@ -31,6 +33,7 @@ impl SignalService {
         payload: JsonValue,
         queue: &str,
     ) -> Result<()> {
+        check_reserved_keys(&payload, "Signal payload")?;
         let mut tx = self.pool.begin().await?;
 
         // Insert the signal
@@ -0,0 +1,49 @@
+//! SLO Service
+//!
+//! Evaluates the claim latency SLOs from config against recent executions.
+
+use anyhow::Result;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::config::SloConfig;
+use crate::db;
+use crate::types::SloStatus;
+
+/// Service for claim latency SLOs
+#[derive(Clone)]
+pub struct SloService {
+    pool: PgPool,
+    slos: Arc<Vec<SloConfig>>,
+}
+
+impl SloService {
+    pub fn new(pool: PgPool, slos: Vec<SloConfig>) -> Self {
+        Self {
+            pool,
+            slos: Arc::new(slos),
+        }
+    }
+
+    /// The configured SLOs
+    pub fn slos(&self) -> &[SloConfig] {
+        &self.slos
+    }
+
+    /// Current compliance and error budget of every configured SLO
+    pub async fn evaluate(&self) -> Result<Vec<SloStatus>> {
+        let mut statuses = Vec::with_capacity(self.slos.len());
+        for slo in self.slos.iter() {
+            let counts = db::slos::get_claim_latency_counts(
+                &self.pool,
+                slo.queue.as_deref(),
+                slo.target_name.as_deref(),
+                slo.threshold_ms,
+                slo.window_secs,
+            )
+            .await?;
+            statuses.push(SloStatus::from_counts(slo.clone(), counts));
+        }
+        Ok(statuses)
+    }
+}
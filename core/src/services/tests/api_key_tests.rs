@@ -0,0 +1,61 @@
+//! Tests for API keys
+
+use crate::auth::Scope;
+use crate::errors::ErrorCode;
+use crate::services::ApiKeyService;
+use sqlx::PgPool;
+
+#[sqlx::test]
+async fn test_created_keys_authenticate_until_revoked(pool: PgPool) -> anyhow::Result<()> {
+    let service = ApiKeyService::new(pool);
+
+    let created = service
+        .create(
+            "billing worker",
+            &[Scope::Worker, Scope::Enqueue, Scope::Worker],
+        )
+        .await?;
+    assert!(created
+        .key
+        .starts_with(&format!("rhythm_{}_", created.api_key.id)));
+    assert_eq!(created.api_key.scopes, [Scope::Enqueue, Scope::Worker]);
+    assert_eq!(created.api_key.last_used_at, None);
+
+    let api_key = service.authenticate(&created.key).await?;
+    assert_eq!(api_key.id, created.api_key.id);
+    assert_eq!(api_key.scopes, created.api_key.scopes);
+    let listed = service.list().await?;
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].last_used_at.is_some());
+
+    service.revoke(&created.api_key.id).await?;
+    let err = service.authenticate(&created.key).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::Unauthenticated);
+    assert!(service.list().await?[0].revoked_at.is_some());
+
+    // Revoking twice finds nothing left to revoke
+    let err = service.revoke(&created.api_key.id).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::NotFound);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_wrong_keys_are_rejected(pool: PgPool) -> anyhow::Result<()> {
+    let service = ApiKeyService::new(pool);
+    let created = service.create("enqueuer", &[Scope::Enqueue]).await?;
+
+    // The right id with the wrong secret
+    let forged = format!("rhythm_{}_{}", created.api_key.id, "0".repeat(64));
+    for key in [forged.as_str(), "rhythm_missing_secret", "not-a-key", ""] {
+        let err = service.authenticate(key).await.unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::Unauthenticated, "{}", key);
+    }
+
+    let err = service.create("nothing", &[]).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::Validation);
+    let err = service.create(" ", &[Scope::Admin]).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::Validation);
+
+    Ok(())
+}
@@ -0,0 +1,348 @@
+//! Tests for cancelling workflows
+
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::types::{ExecutionEventType, ExecutionStatus, ExecutionType};
+use crate::worker::cancel::CANCELLED_ERROR_CODE;
+use crate::worker::{ClaimAuthorizer, DelegatedAction, TaskOutcome};
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const FAN_OUT: &str = r#"
+let a = Task.run("charge", { n: 1 })
+let b = Task.run("charge", { n: 2 })
+return await Promise.all([a, b])
+"#;
+
+const PARENT: &str = r#"
+return await Workflow.run("fan_out", {})
+"#;
+
+const SAGA_CHILD: &str = r#"
+Compensate.register("refund", { step: "child" })
+return await Task.run("charge", {})
+"#;
+
+const SAGA_PARENT: &str = r#"
+Compensate.register("refund", { step: "parent" })
+return await Workflow.run("saga_child", {})
+"#;
+
+fn worker(pool: &PgPool) -> WorkerService {
+    WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+}
+
+/// Run the worker until it hands out a task
+async fn claim_task(worker: &WorkerService) -> anyhow::Result<String> {
+    loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => return Ok(execution_id),
+            DelegatedAction::Continue => {}
+            other => panic!("Expected a task to execute, got {:?}", other),
+        }
+    }
+}
+
+/// Start the saga parent and run it until its child awaits its charge
+///
+/// Returns the parent's and the child's IDs.
+async fn start_saga(
+    pool: &PgPool,
+    workflows: &WorkflowService,
+    worker: &WorkerService,
+) -> anyhow::Result<(String, String)> {
+    workflows
+        .register_workflow("saga_child", SAGA_CHILD)
+        .await?;
+    workflows
+        .register_workflow("saga_parent", SAGA_PARENT)
+        .await?;
+    let parent = workflows
+        .start_workflow("saga_parent", json!({}), "default", None)
+        .await?;
+    loop {
+        let queued_workflows: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM work_queue w JOIN executions e ON e.id = w.execution_id WHERE e.type = 'workflow'",
+        )
+        .fetch_one(pool)
+        .await?;
+        if queued_workflows == 0 {
+            break;
+        }
+        worker.run_cooperative_worker_loop().await?;
+    }
+    let child = workflows.get_workflow_tasks(&parent).await?.remove(0);
+    Ok((parent, child.id))
+}
+
+async fn cancel_progress(
+    executions: &ExecutionService,
+    id: &str,
+) -> anyhow::Result<Vec<serde_json::Value>> {
+    Ok(executions
+        .get_execution_history(id)
+        .await?
+        .into_iter()
+        .filter(|event| event.event_type == ExecutionEventType::CancelProgress)
+        .map(|event| event.details.unwrap())
+        .collect())
+}
+
+async fn status(executions: &ExecutionService, id: &str) -> anyhow::Result<ExecutionStatus> {
+    Ok(executions.get_execution(id).await?.unwrap().status)
+}
+
+async fn count(pool: &PgPool, table: &str) -> anyhow::Result<i64> {
+    Ok(
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(pool)
+            .await?,
+    )
+}
+
+#[sqlx::test]
+async fn test_cancel_workflow_cancels_children_and_work(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    workflows.register_workflow("fan_out", FAN_OUT).await?;
+    let id = workflows
+        .start_workflow("fan_out", json!({}), "default", None)
+        .await?;
+
+    let running = claim_task(&worker).await?;
+    assert!(
+        executions
+            .cancel_workflow(&id, "order withdrawn", false)
+            .await?
+    );
+
+    let workflow = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(workflow.status, ExecutionStatus::Cancelled);
+    assert_eq!(
+        workflow.output,
+        Some(json!({ "code": CANCELLED_ERROR_CODE, "message": "order withdrawn" }))
+    );
+    for task in workflows.get_workflow_tasks(&id).await? {
+        assert_eq!(task.status, ExecutionStatus::Cancelled, "{}", task.id);
+    }
+    assert_eq!(count(&pool, "work_queue").await?, 0);
+    assert_eq!(count(&pool, "workflow_execution_context").await?, 0);
+
+    // The worker that was running a task reports late; its result is dropped
+    worker
+        .record_outcome(&running, TaskOutcome::Complete(json!("charged")))
+        .await?;
+    assert_eq!(
+        status(&executions, &running).await?,
+        ExecutionStatus::Cancelled
+    );
+
+    assert!(!executions.cancel_workflow(&id, "again", false).await?);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_cancel_workflow_can_let_running_tasks_finish(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    workflows.register_workflow("fan_out", FAN_OUT).await?;
+    let id = workflows
+        .start_workflow("fan_out", json!({}), "default", None)
+        .await?;
+
+    let running = claim_task(&worker).await?;
+    assert!(
+        executions
+            .cancel_workflow(&id, "order withdrawn", true)
+            .await?
+    );
+
+    for task in workflows.get_workflow_tasks(&id).await? {
+        let expected = if task.id == running {
+            ExecutionStatus::Running
+        } else {
+            ExecutionStatus::Cancelled
+        };
+        assert_eq!(task.status, expected, "{}", task.id);
+    }
+
+    worker
+        .record_outcome(&running, TaskOutcome::Complete(json!("charged")))
+        .await?;
+    assert_eq!(
+        status(&executions, &running).await?,
+        ExecutionStatus::Completed
+    );
+    assert_eq!(status(&executions, &id).await?, ExecutionStatus::Cancelled);
+
+    // Waking the cancelled workflow runs nothing
+    assert!(matches!(
+        worker.run_cooperative_worker_loop().await?,
+        DelegatedAction::Continue | DelegatedAction::Wait { .. }
+    ));
+    assert_eq!(count(&pool, "work_queue").await?, 0);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_cancelled_child_workflow_fails_awaiting_parent(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    workflows.register_workflow("fan_out", FAN_OUT).await?;
+    workflows.register_workflow("parent", PARENT).await?;
+    let parent = workflows
+        .start_workflow("parent", json!({}), "default", None)
+        .await?;
+
+    claim_task(&worker).await?;
+    let child = executions
+        .query_executions(Default::default())
+        .await?
+        .into_iter()
+        .find(|e| e.exec_type == ExecutionType::Workflow && e.id != parent)
+        .unwrap();
+    assert!(
+        executions
+            .cancel_workflow(&child.id, "not needed", false)
+            .await?
+    );
+
+    // Only the parent is queued, to resume with the cancellation error as
+    // the value of its await
+    let queued: Vec<String> = sqlx::query_scalar("SELECT execution_id FROM work_queue")
+        .fetch_all(&pool)
+        .await?;
+    assert_eq!(queued, vec![parent.clone()]);
+    worker.run_cooperative_worker_loop().await?;
+
+    let parent = executions.get_execution(&parent).await?.unwrap();
+    assert_eq!(parent.status, ExecutionStatus::Completed);
+    assert_eq!(parent.output.unwrap()["code"], CANCELLED_ERROR_CODE);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_cancel_child_first_runs_child_compensations_first(
+    pool: PgPool,
+) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    let (parent, child) = start_saga(&pool, &workflows, &worker).await?;
+    let charge = workflows.get_workflow_tasks(&child).await?.remove(0).id;
+
+    let cancel = tokio::spawn({
+        let executions = executions.clone();
+        let parent = parent.clone();
+        async move {
+            executions
+                .cancel_workflow_child_first(&parent, "order withdrawn", Duration::from_secs(30))
+                .await
+        }
+    });
+    // The unclaimed charge is cancelled before any workflow winds down
+    while status(&executions, &charge).await? != ExecutionStatus::Cancelled {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let mut compensated = Vec::new();
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while !cancel.is_finished() {
+            match worker.run_cooperative_worker_loop().await? {
+                DelegatedAction::ExecuteTask {
+                    execution_id,
+                    target_name,
+                    inputs,
+                    ..
+                } => {
+                    assert_eq!(target_name, "refund");
+                    compensated.push(inputs["step"].clone());
+                    worker
+                        .record_outcome(&execution_id, TaskOutcome::Complete(json!(null)))
+                        .await?;
+                }
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+        anyhow::Ok(())
+    })
+    .await??;
+    assert!(cancel.await??);
+
+    assert_eq!(compensated, vec![json!("child"), json!("parent")]);
+    for id in [&parent, &child] {
+        let execution = executions.get_execution(id).await?.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Cancelled, "{}", id);
+        assert_eq!(
+            execution.output,
+            Some(json!({ "code": CANCELLED_ERROR_CODE, "message": "order withdrawn" }))
+        );
+    }
+    assert_eq!(
+        cancel_progress(&executions, &parent).await?,
+        vec![
+            json!({ "depth": 1, "workflows": 1, "timed_out": [] }),
+            json!({ "depth": 0, "workflows": 1, "timed_out": [] }),
+        ]
+    );
+    assert_eq!(count(&pool, "work_queue").await?, 0);
+    assert_eq!(count(&pool, "workflow_execution_context").await?, 0);
+
+    assert!(
+        !executions
+            .cancel_workflow_child_first(&parent, "again", Duration::from_secs(1))
+            .await?
+    );
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_cancel_child_first_cancels_levels_that_time_out(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    let (parent, child) = start_saga(&pool, &workflows, &worker).await?;
+
+    // No worker runs the workflows, so neither level winds down in time
+    assert!(
+        executions
+            .cancel_workflow_child_first(&parent, "order withdrawn", Duration::from_millis(200))
+            .await?
+    );
+
+    assert_eq!(
+        status(&executions, &parent).await?,
+        ExecutionStatus::Cancelled
+    );
+    assert_eq!(
+        status(&executions, &child).await?,
+        ExecutionStatus::Cancelled
+    );
+    assert_eq!(
+        cancel_progress(&executions, &parent).await?,
+        vec![
+            json!({ "depth": 1, "workflows": 1, "timed_out": [child] }),
+            json!({ "depth": 0, "workflows": 1, "timed_out": [parent] }),
+        ]
+    );
+    let requested = executions
+        .get_execution_history(&child)
+        .await?
+        .into_iter()
+        .find(|event| event.event_type == ExecutionEventType::CancelRequested)
+        .unwrap();
+    assert_eq!(
+        requested.details,
+        Some(json!({ "reason": "order withdrawn" }))
+    );
+    assert_eq!(count(&pool, "work_queue").await?, 0);
+    Ok(())
+}
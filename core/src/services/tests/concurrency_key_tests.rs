@@ -0,0 +1,169 @@
+//! Tests for workflow concurrency keys
+
+use crate::errors::ErrorCode;
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::types::{ConcurrencyKey, ConcurrencyPolicy, Execution, ExecutionStatus};
+use crate::worker::{ClaimAuthorizer, DelegatedAction, TaskOutcome};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+const SYNC_ACCOUNT: &str = r#"
+return await Task.run("sync", { account: Inputs.account })
+"#;
+
+fn worker(pool: &PgPool) -> WorkerService {
+    WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+}
+
+fn key(policy: ConcurrencyPolicy) -> ConcurrencyKey {
+    ConcurrencyKey {
+        key: "sync:acct-1".to_string(),
+        policy,
+    }
+}
+
+async fn start(workflows: &WorkflowService, policy: ConcurrencyPolicy) -> anyhow::Result<String> {
+    workflows
+        .start_workflow_with_concurrency_key(
+            "sync_account",
+            json!({ "account": "acct-1" }),
+            "default",
+            None,
+            &key(policy),
+        )
+        .await
+}
+
+/// Run the worker until it hands out a task
+async fn claim_task(
+    worker: &WorkerService,
+    executions: &ExecutionService,
+) -> anyhow::Result<Execution> {
+    loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => {
+                return Ok(executions.get_execution(&execution_id).await?.unwrap())
+            }
+            DelegatedAction::Continue => {}
+            other => panic!("Expected a task to execute, got {:?}", other),
+        }
+    }
+}
+
+async fn queued(pool: &PgPool, id: &str) -> anyhow::Result<bool> {
+    Ok(
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM work_queue WHERE execution_id = $1)")
+            .bind(id)
+            .fetch_one(pool)
+            .await?,
+    )
+}
+
+#[sqlx::test]
+async fn test_taken_key_is_rejected(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    workflows
+        .register_workflow("sync_account", SYNC_ACCOUNT)
+        .await?;
+
+    let holder = start(&workflows, ConcurrencyPolicy::Reject).await?;
+    let error = start(&workflows, ConcurrencyPolicy::Reject)
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&error), ErrorCode::Conflict);
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Workflow {} is already running with concurrency key 'sync:acct-1'",
+            holder
+        )
+    );
+
+    // Workflows without the key, and once the holder finished, start freely
+    workflows
+        .start_workflow(
+            "sync_account",
+            json!({ "account": "acct-1" }),
+            "default",
+            None,
+        )
+        .await?;
+    executions.cancel_workflow(&holder, "done", false).await?;
+    let next = start(&workflows, ConcurrencyPolicy::Reject).await?;
+    assert!(queued(&pool, &next).await?);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_queued_start_waits_for_the_holder(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    workflows
+        .register_workflow("sync_account", SYNC_ACCOUNT)
+        .await?;
+
+    let holder = start(&workflows, ConcurrencyPolicy::Queue).await?;
+    let second = start(&workflows, ConcurrencyPolicy::Queue).await?;
+    let third = start(&workflows, ConcurrencyPolicy::Queue).await?;
+    assert!(!queued(&pool, &second).await?);
+    assert!(!queued(&pool, &third).await?);
+
+    // Only the holder runs; when it completes, the oldest waiter takes over
+    let task = claim_task(&worker, &executions).await?;
+    assert_eq!(task.parent_workflow_id.as_deref(), Some(holder.as_str()));
+    worker
+        .record_outcome(&task.id, TaskOutcome::Complete(json!("synced")))
+        .await?;
+    let task = claim_task(&worker, &executions).await?;
+    assert_eq!(task.parent_workflow_id.as_deref(), Some(second.as_str()));
+    assert_eq!(
+        executions.get_execution(&holder).await?.unwrap().status,
+        ExecutionStatus::Completed
+    );
+    assert!(!queued(&pool, &third).await?);
+
+    // A cancelled holder passes the key on too
+    executions.cancel_workflow(&second, "stop", false).await?;
+    assert!(queued(&pool, &third).await?);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_replacing_start_cancels_holder_and_waiters(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    workflows
+        .register_workflow("sync_account", SYNC_ACCOUNT)
+        .await?;
+
+    let holder = start(&workflows, ConcurrencyPolicy::Reject).await?;
+    let waiter = start(&workflows, ConcurrencyPolicy::Queue).await?;
+    let replacement = start(&workflows, ConcurrencyPolicy::ReplaceExisting).await?;
+
+    for id in [&holder, &waiter] {
+        let execution = executions.get_execution(id).await?.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Cancelled, "{}", id);
+        assert_eq!(
+            execution.output.unwrap()["message"],
+            "Replaced by a workflow with the same concurrency key"
+        );
+        assert!(!queued(&pool, id).await?);
+    }
+    assert!(queued(&pool, &replacement).await?);
+
+    let error = start(&workflows, ConcurrencyPolicy::Reject)
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&error), ErrorCode::Conflict);
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Tests for encryption at rest of payloads and saved workflow states
+
+use crate::blob_store::PayloadStore;
+use crate::config::EncryptionConfig;
+use crate::db;
+use crate::encryption::{envelope_key, Keyring};
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionStatus};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use base64::Engine;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+const CHECKOUT: &str = r#"
+let receipt = await Task.run("charge", { card: Inputs.card })
+return receipt
+"#;
+
+fn payloads(active: &str) -> PayloadStore {
+    let key = |byte: u8| base64::engine::general_purpose::STANDARD.encode([byte; 32]);
+    let keyring = Keyring::from_config(&EncryptionConfig {
+        active_key: Some(active.to_string()),
+        keys: [("k1".to_string(), key(1)), ("k2".to_string(), key(2))].into(),
+    })
+    .unwrap()
+    .unwrap();
+    PayloadStore::default().with_encryption(keyring)
+}
+
+fn worker(pool: &PgPool, payloads: PayloadStore) -> WorkerService {
+    WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_payloads(payloads)
+}
+
+#[sqlx::test]
+async fn test_task_payloads_are_encrypted_in_rows(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone()).with_payloads(payloads("k1"));
+    let worker = worker(&pool, payloads("k1"));
+
+    let id = executions
+        .create_execution(CreateExecutionParams {
+            inputs: json!({ "card": "4242 4242 4242 4242" }),
+            ..task_params("charge")
+        })
+        .await?;
+    let row = db::executions::get_execution(&pool, &id).await?.unwrap();
+    assert_eq!(envelope_key(&row.inputs), Some("k1"));
+    assert!(!row.inputs.to_string().contains("4242"));
+
+    // The host gets the inputs themselves
+    let DelegatedAction::ExecuteTask { inputs, .. } = worker.run_cooperative_worker_loop().await?
+    else {
+        panic!("expected a task");
+    };
+    assert_eq!(inputs["card"], "4242 4242 4242 4242");
+
+    worker
+        .fail_work(&id, json!({ "message": "card 4242 declined" }), false)
+        .await?;
+    let row = db::executions::get_execution(&pool, &id).await?.unwrap();
+    assert_eq!(envelope_key(row.output.as_ref().unwrap()), Some("k1"));
+
+    let execution = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    assert_eq!(execution.output.unwrap()["message"], "card 4242 declined");
+
+    // Rotating to k2 re-encrypts what k1 encrypted
+    let rotated = ExecutionService::new(pool.clone()).with_payloads(payloads("k2"));
+    let dry_run = rotated.reencrypt_payloads(false, 10).await?;
+    assert_eq!((dry_run.scanned, dry_run.reencrypted), (1, 1));
+    let row = db::executions::get_execution(&pool, &id).await?.unwrap();
+    assert_eq!(envelope_key(&row.inputs), Some("k1"));
+
+    rotated.reencrypt_payloads(true, 10).await?;
+    let row = db::executions::get_execution(&pool, &id).await?.unwrap();
+    assert_eq!(envelope_key(&row.inputs), Some("k2"));
+    assert_eq!(envelope_key(row.output.as_ref().unwrap()), Some("k2"));
+    assert_eq!(rotated.reencrypt_payloads(true, 10).await?.current, 1);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_workflow_state_is_encrypted(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone()).with_payloads(payloads("k1"));
+    let worker = worker(&pool, payloads("k1"));
+
+    workflows.register_workflow("checkout", CHECKOUT).await?;
+    let workflow_id = workflows
+        .start_workflow(
+            "checkout",
+            json!({ "card": "4242 4242 4242 4242" }),
+            "default",
+            None,
+        )
+        .await?;
+
+    // Runs the workflow until it suspends, then hands out its task
+    let (task_id, inputs) = loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask {
+                execution_id,
+                inputs,
+                ..
+            } => break (execution_id, inputs),
+            DelegatedAction::Continue => {}
+            _ => panic!("expected a task"),
+        }
+    };
+    assert_eq!(inputs["card"], "4242 4242 4242 4242");
+
+    let context = db::workflow_execution_context::get_context(&pool, &workflow_id)
+        .await?
+        .unwrap();
+    let vm_state = context.vm_state.as_json().unwrap();
+    assert_eq!(envelope_key(vm_state), Some("k1"));
+    assert!(!vm_state.to_string().contains("4242"));
+    assert!(workflows
+        .get_suspension_point(&workflow_id)
+        .await?
+        .is_some());
+
+    // A rotated worker resumes what the old key saved
+    let rotated = payloads("k2");
+    let summary = WorkflowService::new(pool.clone())
+        .with_payloads(rotated.clone())
+        .rewrite_saved_states(true, 10)
+        .await?;
+    assert_eq!(summary.rewritten, 1);
+
+    let worker = self::worker(&pool, rotated);
+    worker
+        .complete_work(&task_id, Some(json!({ "receipt": "r-1" })), None)
+        .await?;
+    while !matches!(
+        worker.run_cooperative_worker_loop().await?,
+        DelegatedAction::Wait { .. } | DelegatedAction::Shutdown
+    ) {}
+
+    let execution = ExecutionService::new(pool.clone())
+        .with_payloads(payloads("k2"))
+        .get_execution(&workflow_id)
+        .await?
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+    assert_eq!(execution.output, Some(json!({ "receipt": "r-1" })));
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_payloads_only_decrypt_in_their_own_row(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone()).with_payloads(payloads("k1"));
+    let card = executions
+        .create_execution(CreateExecutionParams {
+            inputs: json!({ "card": "4242 4242 4242 4242" }),
+            ..task_params("charge")
+        })
+        .await?;
+    let other = executions.create_execution(task_params("charge")).await?;
+
+    // Another execution's inputs copied into a row don't decrypt there
+    let row = db::executions::get_execution(&pool, &card).await?.unwrap();
+    sqlx::query("UPDATE executions SET inputs = $1 WHERE id = $2")
+        .bind(&row.inputs)
+        .bind(&other)
+        .execute(&pool)
+        .await?;
+    let err = executions.get_execution(&other).await.unwrap_err();
+    assert!(err
+        .to_string()
+        .contains(&format!("belongs to inputs:{}", card)));
+    assert!(executions.get_execution(&card).await?.is_some());
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_envelopes_are_reserved_without_encryption(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let err = executions
+        .create_execution(CreateExecutionParams {
+            inputs: json!({ "$rhythm_enc": { "key": "k1", "nonce": "", "data": "" } }),
+            ..task_params("charge")
+        })
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("`$rhythm_enc` is a reserved key"));
+    assert!(db::executions::query_executions(&pool, Default::default())
+        .await?
+        .is_empty());
+    Ok(())
+}
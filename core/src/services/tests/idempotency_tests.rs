@@ -0,0 +1,60 @@
+//! Tests for idempotency keys on execution creation
+
+use crate::services::ExecutionService;
+use crate::test_helpers::task_params;
+use crate::types::CreateExecutionParams;
+use sqlx::PgPool;
+use std::time::Duration;
+
+fn task(target_name: &str, queue: &str, key: Option<&str>) -> CreateExecutionParams {
+    CreateExecutionParams {
+        queue: queue.to_string(),
+        idempotency_key: key.map(str::to_string),
+        ..task_params(target_name)
+    }
+}
+
+#[sqlx::test]
+async fn test_idempotency_key_returns_existing_execution(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool.clone());
+
+    let id = service
+        .create_execution(task("charge", "default", Some("order-1")))
+        .await?;
+    let again = service
+        .create_execution(task("charge", "default", Some("order-1")))
+        .await?;
+    assert_eq!(again, id);
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM executions")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(count, 1);
+
+    // The key is scoped to queue and target, and absent keys never match
+    for params in [
+        task("charge", "default", Some("order-2")),
+        task("charge", "billing", Some("order-1")),
+        task("refund", "default", Some("order-1")),
+        task("charge", "default", None),
+    ] {
+        assert_ne!(service.create_execution(params).await?, id);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_idempotency_key_expires_after_window(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool.clone()).with_idempotency_window(Duration::ZERO);
+
+    let id = service
+        .create_execution(task("charge", "default", Some("order-1")))
+        .await?;
+    let again = service
+        .create_execution(task("charge", "default", Some("order-1")))
+        .await?;
+    assert_ne!(again, id);
+
+    Ok(())
+}
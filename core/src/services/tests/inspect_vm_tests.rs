@@ -0,0 +1,60 @@
+//! Tests for inspecting a workflow's saved VM state
+
+use crate::services::{WorkerService, WorkflowService};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+const WORKFLOW: &str = r#"
+let base = 2
+let result = await Task.run("step", { n: base })
+return base + result
+"#;
+
+#[sqlx::test]
+async fn test_inspect_suspended_workflow(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    workflows.register_workflow("scaled", WORKFLOW).await?;
+    let workflow_id = workflows
+        .start_workflow("scaled", json!({}), "default", None)
+        .await?;
+    // Nothing is saved before the workflow first runs
+    assert!(workflows.inspect_vm(&workflow_id).await?.is_none());
+
+    let task_id = loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => break execution_id,
+            DelegatedAction::Continue => {}
+            _ => panic!("Expected a task to execute"),
+        }
+    };
+
+    let inspection = workflows
+        .inspect_vm(&workflow_id)
+        .await?
+        .expect("workflow has a saved state");
+    assert_eq!(inspection.workflow_name, "scaled");
+    assert_eq!(inspection.line, Some(3));
+    assert_eq!(
+        inspection.source_line.as_deref(),
+        Some(r#"let result = await Task.run("step", { n: base })"#)
+    );
+    assert!(!inspection.frames.is_empty());
+    assert_eq!(inspection.locals.get("base"), Some(&json!(2)));
+    assert!(!inspection.locals.contains_key("Inputs"));
+    assert_eq!(
+        inspection.awaiting,
+        Some(json!({ "t": "Execution", "v": task_id }))
+    );
+    let pending: Vec<_> = inspection.pending.iter().map(|e| &e.id).collect();
+    assert_eq!(pending, [&task_id]);
+
+    Ok(())
+}
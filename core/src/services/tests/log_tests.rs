@@ -0,0 +1,86 @@
+//! Tests for execution logs
+
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionStatus, ExecutionType, LogLevel};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+#[sqlx::test]
+async fn test_workflow_and_task_logs_are_kept_in_order(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows
+        .register_workflow(
+            "checkout",
+            r#"
+                Log.info("Charging", { amount: 10 })
+                let receipt = await Task.run("charge", {})
+                Log.warn("Charged")
+                return receipt
+            "#,
+        )
+        .await?;
+    let id = executions
+        .create_execution(CreateExecutionParams {
+            exec_type: ExecutionType::Workflow,
+            ..task_params("checkout")
+        })
+        .await?;
+
+    let task_id = loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => break execution_id,
+            DelegatedAction::Continue => {}
+            other => panic!("Expected a task to execute, got {:?}", other),
+        }
+    };
+    worker
+        .append_log(&task_id, LogLevel::Error, "Card declined once", None)
+        .await?;
+    worker
+        .complete_work(&task_id, Some(json!("receipt")), None)
+        .await?;
+    for _ in 0..10 {
+        if executions.get_execution(&id).await?.unwrap().status == ExecutionStatus::Completed {
+            break;
+        }
+        worker.run_cooperative_worker_loop().await?;
+    }
+    assert_eq!(
+        executions.get_execution(&id).await?.unwrap().status,
+        ExecutionStatus::Completed
+    );
+
+    // Resuming the workflow doesn't log its first message again
+    let workflow_logs = executions.get_execution_logs(&id).await?;
+    let messages: Vec<_> = workflow_logs
+        .iter()
+        .map(|log| (log.level, log.message.as_str(), log.data.clone()))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            (LogLevel::Info, "Charging", Some(json!({ "amount": 10 }))),
+            (LogLevel::Warn, "Charged", None),
+        ]
+    );
+
+    let task_logs = executions.get_execution_logs(&task_id).await?;
+    assert_eq!(task_logs.len(), 1);
+    assert_eq!(task_logs[0].level, LogLevel::Error);
+    assert_eq!(task_logs[0].message, "Card declined once");
+
+    let missing = worker
+        .append_log("missing", LogLevel::Info, "Lost", None)
+        .await;
+    assert!(missing.is_err());
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Tests for maintenance windows
+
+use crate::db;
+use crate::services::{ExecutionService, MaintenanceService};
+use crate::test_helpers::task_params;
+use crate::types::CreateExecutionParams;
+use sqlx::PgPool;
+use std::time::Duration;
+
+fn task() -> CreateExecutionParams {
+    task_params("work")
+}
+
+#[sqlx::test]
+async fn test_maintenance_pauses_claims_until_exit(pool: PgPool) -> anyhow::Result<()> {
+    let maintenance = MaintenanceService::new(pool.clone());
+    ExecutionService::new(pool.clone())
+        .create_execution(task())
+        .await?;
+
+    let window = maintenance
+        .enter(Duration::from_secs(600), Some("upgrade"))
+        .await?;
+    assert_eq!(window.reason.as_deref(), Some("upgrade"));
+    assert!(db::claim_work(&pool, "default", 10, None).await?.is_empty());
+
+    let status = maintenance.status().await?;
+    assert_eq!(status.window, Some(window));
+    assert_eq!(status.in_flight, 0);
+
+    assert!(maintenance.exit().await?);
+    assert!(!maintenance.exit().await?);
+    assert_eq!(db::claim_work(&pool, "default", 10, None).await?.len(), 1);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_maintenance_window_expires(pool: PgPool) -> anyhow::Result<()> {
+    let maintenance = MaintenanceService::new(pool.clone());
+    ExecutionService::new(pool.clone())
+        .create_execution(task())
+        .await?;
+
+    maintenance.enter(Duration::from_secs(600), None).await?;
+    sqlx::query("UPDATE maintenance_window SET expires_at = NOW() - INTERVAL '1 second'")
+        .execute(&pool)
+        .await?;
+
+    assert!(maintenance.status().await?.window.is_none());
+    assert_eq!(db::claim_work(&pool, "default", 10, None).await?.len(), 1);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_wait_for_drain_reports_in_flight_work(pool: PgPool) -> anyhow::Result<()> {
+    let maintenance = MaintenanceService::new(pool.clone());
+    let id = ExecutionService::new(pool.clone())
+        .create_execution(task())
+        .await?;
+    db::claim_work(&pool, "default", 1, None).await?;
+
+    maintenance.enter(Duration::from_secs(600), None).await?;
+    let status = maintenance
+        .wait_for_drain(Duration::from_millis(100))
+        .await?;
+    assert_eq!(status.in_flight, 1);
+
+    db::complete_work(&pool, &id).await?;
+    let status = maintenance.wait_for_drain(Duration::from_secs(5)).await?;
+    assert_eq!(status.in_flight, 0);
+    assert!(status.window.is_some());
+    Ok(())
+}
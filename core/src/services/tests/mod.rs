@@ -1,3 +1,33 @@
 //! Service layer tests
 
+mod api_key_tests;
+mod cancel_tests;
+mod concurrency_key_tests;
+mod encryption_tests;
+mod idempotency_tests;
+mod inspect_vm_tests;
+mod log_tests;
+mod maintenance_service_tests;
+mod output_schema_tests;
+mod pause_tests;
+mod payload_tests;
+mod queue_limit_tests;
+mod queue_stats_tests;
+mod quota_tests;
+mod reaper_tests;
+mod recording_tests;
+mod retry_tests;
 mod scheduler_service_tests;
+mod schema_service_tests;
+mod shutdown_tests;
+mod slo_service_tests;
+mod sync_run_tests;
+mod tag_tests;
+mod task_graph_tests;
+mod trace_tests;
+mod transfer_tests;
+mod webhook_tests;
+mod work_cleanup_tests;
+mod worker_service_tests;
+mod workflow_migration_tests;
+mod workflow_settings_tests;
@@ -0,0 +1,111 @@
+//! Tests for checking task results against their output schemas
+
+use crate::config::TaskConfig;
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::types::ExecutionStatus;
+use crate::worker::output_schema::INVALID_OUTPUT_CODE;
+use crate::worker::{ClaimAuthorizer, DelegatedAction, OutputSchemas};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+const CHECKOUT: &str = r#"
+let charge = await Task.run("charge_card", {})
+return charge
+"#;
+
+fn worker(pool: &PgPool) -> WorkerService {
+    let schemas = OutputSchemas::from_task_configs(&[TaskConfig {
+        target_name: "charge_card".to_string(),
+        output_schema: Some(json!({
+            "charge_id": "string",
+            "captured": { "type": "boolean", "default": true },
+        })),
+        ..Default::default()
+    }])
+    .unwrap();
+    WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_output_schemas(schemas)
+}
+
+/// Start the checkout workflow and run it until its task is handed out
+async fn start(pool: &PgPool, worker: &WorkerService) -> anyhow::Result<(String, String)> {
+    let workflows = WorkflowService::new(pool.clone());
+    workflows.register_workflow("checkout", CHECKOUT).await?;
+    let workflow_id = workflows
+        .start_workflow("checkout", json!({}), "default", None)
+        .await?;
+    loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => {
+                return Ok((workflow_id, execution_id))
+            }
+            DelegatedAction::Continue => {}
+            other => panic!("expected a task, got {:?}", other),
+        }
+    }
+}
+
+/// Run the worker until it has nothing left to do
+async fn settle(worker: &WorkerService) -> anyhow::Result<()> {
+    while !matches!(
+        worker.run_cooperative_worker_loop().await?,
+        DelegatedAction::Wait { .. } | DelegatedAction::Shutdown
+    ) {}
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_invalid_output_fails_the_task_and_wakes_the_workflow(
+    pool: PgPool,
+) -> anyhow::Result<()> {
+    let worker = worker(&pool);
+    let (workflow_id, task_id) = start(&pool, &worker).await?;
+
+    worker
+        .complete_work(&task_id, Some(json!({ "charge_id": 42 })), None)
+        .await?;
+
+    let executions = ExecutionService::new(pool.clone());
+    let task = executions.get_execution(&task_id).await?.unwrap();
+    assert_eq!(task.status, ExecutionStatus::Failed);
+    let error = task.output.unwrap();
+    assert_eq!(error["code"], INVALID_OUTPUT_CODE);
+    assert_eq!(
+        error["problems"],
+        json!(["charge_id must be a string, got number"])
+    );
+
+    // The failure woke the workflow, which got the error as the result
+    settle(&worker).await?;
+    let workflow = executions.get_execution(&workflow_id).await?.unwrap();
+    assert_eq!(workflow.status, ExecutionStatus::Completed);
+    assert_eq!(workflow.output.unwrap()["code"], INVALID_OUTPUT_CODE);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_valid_output_is_stored_with_defaults(pool: PgPool) -> anyhow::Result<()> {
+    let worker = worker(&pool);
+    let (workflow_id, task_id) = start(&pool, &worker).await?;
+
+    worker
+        .complete_work(&task_id, Some(json!({ "charge_id": "ch_1" })), None)
+        .await?;
+    settle(&worker).await?;
+
+    let workflow = ExecutionService::new(pool.clone())
+        .get_execution(&workflow_id)
+        .await?
+        .unwrap();
+    assert_eq!(workflow.status, ExecutionStatus::Completed);
+    assert_eq!(
+        workflow.output,
+        Some(json!({ "charge_id": "ch_1", "captured": true }))
+    );
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Tests for pausing and resuming workflows
+
+use crate::db;
+use crate::errors::ErrorCode;
+use crate::services::{ExecutionService, WorkflowService};
+use crate::test_helpers::task_params;
+use serde_json::json;
+use sqlx::PgPool;
+
+const SYNC_ACCOUNT: &str = r#"
+return await Task.run("sync", {})
+"#;
+
+async fn claim(pool: &PgPool) -> anyhow::Result<Vec<String>> {
+    db::work_queue::claim_work(pool, "default", 10, None).await
+}
+
+#[sqlx::test]
+async fn test_paused_workflow_is_not_claimed(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    workflows
+        .register_workflow("sync_account", SYNC_ACCOUNT)
+        .await?;
+    let id = workflows
+        .start_workflow("sync_account", json!({}), "default", None)
+        .await?;
+
+    assert!(executions.pause_execution(&id).await?);
+    assert!(!executions.pause_execution(&id).await?);
+    assert!(claim(&pool).await?.is_empty());
+    assert!(claim(&pool).await?.is_empty());
+
+    assert!(executions.resume_execution(&id).await?);
+    assert!(!executions.resume_execution(&id).await?);
+    assert_eq!(claim(&pool).await?, vec![id]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_only_unfinished_workflows_pause(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    workflows
+        .register_workflow("sync_account", SYNC_ACCOUNT)
+        .await?;
+    let id = workflows
+        .start_workflow("sync_account", json!({}), "default", None)
+        .await?;
+
+    let missing = executions.pause_execution("missing").await.unwrap_err();
+    assert_eq!(ErrorCode::of(&missing), ErrorCode::NotFound);
+
+    let task = executions.create_execution(task_params("sync")).await?;
+    let error = executions.pause_execution(&task).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&error), ErrorCode::Validation);
+
+    executions.cancel_workflow(&id, "done", false).await?;
+    let error = executions.resume_execution(&id).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&error), ErrorCode::Conflict);
+    assert_eq!(
+        error.to_string(),
+        format!("Workflow {} has already finished", id)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Tests for payload limits and offloading to a blob store
+
+use crate::blob_store::{PayloadStore, BLOB_REFERENCE_KEY, PAYLOAD_TOO_LARGE_CODE};
+use crate::config::{BlobStoreConfig, PayloadsConfig};
+use crate::db;
+use crate::errors::RhythmError;
+use crate::services::{ExecutionService, WorkerService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionStatus};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+use std::path::PathBuf;
+use tokio_util::sync::CancellationToken;
+
+fn payloads(store: Option<PathBuf>) -> PayloadStore {
+    PayloadStore::from_config(&PayloadsConfig {
+        max_inline_bytes: Some(256),
+        max_bytes: Some(64 * 1024),
+        store: store.map(|path| BlobStoreConfig { path }),
+        ..Default::default()
+    })
+}
+
+fn task(inputs: JsonValue) -> CreateExecutionParams {
+    CreateExecutionParams {
+        inputs,
+        ..task_params("export")
+    }
+}
+
+fn rows(n: usize) -> JsonValue {
+    json!({ "rows": vec!["a row of the export"; n] })
+}
+
+#[sqlx::test]
+async fn test_large_payloads_are_stored_as_references(pool: PgPool) -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rhythm-blobs-{}", uuid::Uuid::new_v4()));
+    let executions = ExecutionService::new(pool.clone()).with_payloads(payloads(Some(dir.clone())));
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_payloads(payloads(Some(dir.clone())));
+
+    let id = executions.create_execution(task(rows(100))).await?;
+    let row = db::executions::get_execution(&pool, &id).await?.unwrap();
+    assert!(row.inputs.get(BLOB_REFERENCE_KEY).is_some());
+
+    // The host gets the inputs themselves
+    let DelegatedAction::ExecuteTask { inputs, .. } = worker.run_cooperative_worker_loop().await?
+    else {
+        panic!("expected a task");
+    };
+    assert_eq!(inputs, rows(100));
+
+    worker.complete_work(&id, Some(rows(200)), None).await?;
+    let row = db::executions::get_execution(&pool, &id).await?.unwrap();
+    assert!(row.output.unwrap().get(BLOB_REFERENCE_KEY).is_some());
+
+    let execution = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+    assert_eq!(execution.inputs, rows(100));
+    assert_eq!(execution.output, Some(rows(200)));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_payloads_over_the_limits_are_rejected(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone()).with_payloads(payloads(None));
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_payloads(payloads(None));
+
+    // Without a store, the inline limit is the limit
+    let err = executions
+        .create_execution(task(rows(100)))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<RhythmError>(),
+        Some(RhythmError::Validation(_))
+    ));
+
+    let id = executions.create_execution(task(json!({ "n": 1 }))).await?;
+    worker.run_cooperative_worker_loop().await?;
+    worker.complete_work(&id, Some(rows(100)), None).await?;
+
+    let execution = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    assert_eq!(execution.output.unwrap()["code"], PAYLOAD_TOO_LARGE_CODE);
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Tests for queue depth limits
+
+use crate::config::QueueConfig;
+use crate::db;
+use crate::queue_limits::{QueueFull, QueueLimits};
+use crate::services::{ExecutionService, WorkflowService};
+use crate::test_helpers::task_params;
+use crate::types::CreateExecutionParams;
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+
+fn limits(queue: &str, max_depth: i64, full_wait_ms: u64) -> QueueLimits {
+    QueueLimits::from_config(&QueueConfig {
+        max_depth: [(queue.to_string(), max_depth)].into(),
+        full_wait_ms,
+        ..Default::default()
+    })
+}
+
+fn task(queue: &str) -> CreateExecutionParams {
+    CreateExecutionParams {
+        queue: queue.to_string(),
+        ..task_params("work")
+    }
+}
+
+#[sqlx::test]
+async fn test_full_queue_rejects_with_queue_full(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool.clone()).with_queue_limits(limits("emails", 2, 0));
+
+    service.create_execution(task("emails")).await?;
+    service.create_execution(task("emails")).await?;
+
+    let err = service.create_execution(task("emails")).await.unwrap_err();
+    let full = err
+        .downcast_ref::<QueueFull>()
+        .expect("should be a queue full error");
+    assert_eq!(full.queue, "emails");
+    assert_eq!((full.limit, full.depth), (2, 2));
+
+    // Nothing was created by the rejected call, other queues are unaffected
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM executions WHERE queue = 'emails'")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(count, 2);
+    service.create_execution(task("default")).await?;
+
+    // Claimed work no longer counts
+    let claimed = db::work_queue::claim_work(&pool, "emails", 1, None).await?;
+    assert_eq!(claimed.len(), 1);
+    service.create_execution(task("emails")).await?;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_start_waits_for_room(pool: PgPool) -> anyhow::Result<()> {
+    let service = WorkflowService::new(pool.clone()).with_queue_limits(limits("flows", 1, 5_000));
+
+    service
+        .start_workflow("flow", json!({}), "flows", None)
+        .await?;
+
+    let claimer = tokio::spawn({
+        let pool = pool.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            db::work_queue::claim_work(&pool, "flows", 1, None).await
+        }
+    });
+    service
+        .start_workflow("flow", json!({}), "flows", None)
+        .await?;
+    assert_eq!(claimer.await??.len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Tests for per-queue statistics
+
+use crate::services::{ExecutionService, WorkerService};
+use crate::test_helpers::task_params;
+use crate::types::CreateExecutionParams;
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+fn task(queue: &str) -> CreateExecutionParams {
+    CreateExecutionParams {
+        queue: queue.to_string(),
+        ..task_params("work")
+    }
+}
+
+#[sqlx::test]
+async fn test_queue_stats_count_work_and_throughput(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    assert!(executions.queue_stats(None).await?.is_empty());
+
+    for _ in 0..3 {
+        executions.create_execution(task("default")).await?;
+    }
+    executions.create_execution(task("email")).await?;
+
+    // One claimed and completed, one claimed and still running
+    for completed in [true, false] {
+        let DelegatedAction::ExecuteTask { execution_id, .. } =
+            worker.run_cooperative_worker_loop().await?
+        else {
+            panic!("expected a task");
+        };
+        if completed {
+            worker
+                .complete_work(&execution_id, Some(json!({})), None)
+                .await?;
+        }
+    }
+
+    let stats = executions.queue_stats(Some(10)).await?;
+    assert_eq!(
+        stats.iter().map(|s| s.queue.as_str()).collect::<Vec<_>>(),
+        ["default", "email"]
+    );
+    let default = &stats[0];
+    assert_eq!((default.pending, default.claimed), (1, 1));
+    assert_eq!(
+        (default.completed, default.failed, default.suspended),
+        (1, 0, 0)
+    );
+    assert_eq!(default.throughput_per_minute, 0.1);
+    assert!(default.oldest_pending_secs.unwrap() >= 0.0);
+
+    let email = &stats[1];
+    assert_eq!((email.pending, email.claimed, email.completed), (1, 0, 0));
+    assert_eq!(email.throughput_per_minute, 0.0);
+    Ok(())
+}
@@ -0,0 +1,204 @@
+//! Tests for namespace quota enforcement
+
+use crate::config::{NamespaceQuota, QuotasConfig};
+use crate::quotas::{QuotaEnforcer, QuotaExceeded, QuotaKind};
+use crate::services::{ExecutionService, SchedulerService, WorkflowService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionType, ScheduleExecutionParams};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn enforcer(namespace: &str, quota: NamespaceQuota) -> Arc<QuotaEnforcer> {
+    Arc::new(QuotaEnforcer::new(QuotasConfig {
+        default: NamespaceQuota::default(),
+        namespaces: HashMap::from([(namespace.to_string(), quota)]),
+    }))
+}
+
+fn task(namespace: Option<&str>) -> CreateExecutionParams {
+    CreateExecutionParams {
+        inputs: json!({"n": 1}),
+        namespace: namespace.map(str::to_string),
+        ..task_params("work")
+    }
+}
+
+#[sqlx::test]
+async fn test_max_running_rejects_with_quota_error(pool: PgPool) -> anyhow::Result<()> {
+    let quotas = enforcer(
+        "acme",
+        NamespaceQuota {
+            max_running: Some(2),
+            ..Default::default()
+        },
+    );
+    let service = ExecutionService::new(pool.clone()).with_quotas(quotas.clone());
+
+    service.create_execution(task(Some("acme"))).await?;
+    service.create_execution(task(Some("acme"))).await?;
+
+    let err = service
+        .create_execution(task(Some("acme")))
+        .await
+        .unwrap_err();
+    let exceeded = err
+        .downcast_ref::<QuotaExceeded>()
+        .expect("should be a quota error");
+    assert_eq!(exceeded.namespace, "acme");
+    assert_eq!(exceeded.kind, QuotaKind::MaxRunning);
+    assert_eq!(exceeded.current, 2);
+
+    // Nothing was created by the rejected call, other namespaces are unaffected
+    let usage = service.get_namespace_usage("acme").await?;
+    assert_eq!(usage.running, 2);
+    service.create_execution(task(None)).await?;
+    service.create_execution(task(Some("other"))).await?;
+
+    // Counted on this enforcer only
+    assert_eq!(quotas.metrics().rejections["acme"]["max_running"], 1);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_finished_executions_free_running_quota(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool.clone()).with_quotas(enforcer(
+        "acme",
+        NamespaceQuota {
+            max_running: Some(1),
+            ..Default::default()
+        },
+    ));
+
+    let id = service.create_execution(task(Some("acme"))).await?;
+    assert!(service.create_execution(task(Some("acme"))).await.is_err());
+
+    sqlx::query("UPDATE executions SET status = 'completed' WHERE id = $1")
+        .bind(&id)
+        .execute(&pool)
+        .await?;
+    service.create_execution(task(Some("acme"))).await?;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_enqueue_rate_limit(pool: PgPool) -> anyhow::Result<()> {
+    let service = WorkflowService::new(pool.clone()).with_quotas(enforcer(
+        "acme",
+        NamespaceQuota {
+            max_enqueues_per_minute: Some(1),
+            ..Default::default()
+        },
+    ));
+
+    service
+        .start_workflow("flow", json!({}), "default", Some("acme"))
+        .await?;
+    let err = service
+        .start_workflow("flow", json!({}), "default", Some("acme"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<QuotaExceeded>().unwrap().kind,
+        QuotaKind::MaxEnqueuesPerMinute
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_storage_quota_counts_incoming_inputs(pool: PgPool) -> anyhow::Result<()> {
+    let service = SchedulerService::new(pool.clone()).with_quotas(enforcer(
+        "acme",
+        NamespaceQuota {
+            max_storage_bytes: Some(64),
+            ..Default::default()
+        },
+    ));
+
+    let params = |inputs| ScheduleExecutionParams {
+        exec_type: ExecutionType::Task,
+        target_name: "work".to_string(),
+        queue: "default".to_string(),
+        inputs,
+        run_at: Utc::now().naive_utc(),
+        namespace: Some("acme".to_string()),
+    };
+
+    service.schedule_execution(params(json!({"a": 1}))).await?;
+    let err = service
+        .schedule_execution(params(json!({"blob": "x".repeat(100)})))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<QuotaExceeded>().unwrap().kind,
+        QuotaKind::MaxStorageBytes
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_storage_is_counted_as_executions_change(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool.clone());
+    let storage = || service.get_namespace_usage("acme");
+
+    let id = service.create_execution(task(Some("acme"))).await?;
+    service.create_execution(task(Some("other"))).await?;
+    let created = storage().await?.storage_bytes;
+    assert!(created > 0);
+
+    // Completing stores the output, and another namespace is counted apart
+    sqlx::query("UPDATE executions SET status = 'completed', output = $1 WHERE id = $2")
+        .bind(json!({ "rows": vec!["a row"; 20] }))
+        .bind(&id)
+        .execute(&pool)
+        .await?;
+    let completed = storage().await?.storage_bytes;
+    assert!(completed > created);
+    assert_eq!(
+        service.get_namespace_usage("other").await?.storage_bytes,
+        created
+    );
+
+    // Moving or deleting it takes its bytes away
+    sqlx::query("UPDATE executions SET namespace = 'moved' WHERE id = $1")
+        .bind(&id)
+        .execute(&pool)
+        .await?;
+    assert_eq!(storage().await?.storage_bytes, 0);
+    assert_eq!(
+        service.get_namespace_usage("moved").await?.storage_bytes,
+        completed
+    );
+    sqlx::query("DELETE FROM executions WHERE id = $1")
+        .bind(&id)
+        .execute(&pool)
+        .await?;
+    assert_eq!(service.get_namespace_usage("moved").await?.storage_bytes, 0);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_children_inherit_namespace(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool.clone());
+
+    let parent = service.create_execution(task(Some("acme"))).await?;
+    let mut child = task(None);
+    child.parent_workflow_id = Some(parent);
+    let child_id = service.create_execution(child).await?;
+
+    let child = service.get_execution(&child_id).await?.unwrap();
+    assert_eq!(child.namespace, "acme");
+
+    let orphan = service.create_execution(task(None)).await?;
+    let orphan = service.get_execution(&orphan).await?.unwrap();
+    assert_eq!(orphan.namespace, crate::types::DEFAULT_NAMESPACE);
+
+    Ok(())
+}
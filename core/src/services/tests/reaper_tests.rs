@@ -0,0 +1,96 @@
+//! Tests for heartbeats and reaping tasks whose worker went silent
+
+use crate::services::{ExecutionService, WorkerService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionStatus};
+use crate::worker::reaper::HEARTBEAT_TIMEOUT_CODE;
+use crate::worker::{ClaimAuthorizer, DelegatedAction, ReaperPolicy};
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+fn export() -> CreateExecutionParams {
+    task_params("export")
+}
+
+fn worker(pool: &PgPool) -> WorkerService {
+    WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_reaper(ReaperPolicy {
+        heartbeat_timeout: Duration::from_secs(60),
+        max_reclaims: 1,
+    })
+}
+
+async fn claim(worker: &WorkerService) -> anyhow::Result<String> {
+    match worker.run_cooperative_worker_loop().await? {
+        DelegatedAction::ExecuteTask { execution_id, .. } => Ok(execution_id),
+        _ => panic!("Expected a task to execute"),
+    }
+}
+
+/// Make the task's last heartbeat look two minutes old
+async fn go_silent(pool: &PgPool, execution_id: &str) -> anyhow::Result<()> {
+    sqlx::query("UPDATE executions SET heartbeat_at = NOW() - INTERVAL '2 minutes' WHERE id = $1")
+        .bind(execution_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_heartbeat_keeps_task_from_reaper(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    let id = executions.create_execution(export()).await?;
+    claim(&worker).await?;
+
+    go_silent(&pool, &id).await?;
+    assert!(worker.heartbeat(&id, Some("host-7")).await?);
+    let reaped = worker.reap_stale_tasks(10).await?;
+    assert!(reaped.reclaimed.is_empty() && reaped.failed.is_empty());
+
+    let worker_id: Option<String> =
+        sqlx::query_scalar("SELECT worker_id FROM executions WHERE id = $1")
+            .bind(&id)
+            .fetch_one(&pool)
+            .await?;
+    assert_eq!(worker_id.as_deref(), Some("host-7"));
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_reaper_reclaims_then_fails_silent_task(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    let id = executions.create_execution(export()).await?;
+
+    claim(&worker).await?;
+    go_silent(&pool, &id).await?;
+    let reaped = worker.reap_stale_tasks(10).await?;
+    assert_eq!(reaped.reclaimed, vec![id.clone()]);
+
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Pending);
+    assert_eq!(stored.attempt, 1);
+    // The silent worker learns it lost the task
+    assert!(!worker.heartbeat(&id, None).await?);
+
+    // Another worker takes it and goes silent too
+    assert_eq!(claim(&worker).await?, id);
+    go_silent(&pool, &id).await?;
+    let reaped = worker.reap_stale_tasks(10).await?;
+    assert_eq!(reaped.failed, vec![id.clone()]);
+
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Failed);
+    assert_eq!(stored.output.unwrap()["code"], HEARTBEAT_TIMEOUT_CODE);
+    let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM work_queue")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(queued, 0);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Tests for recording workflow runs and replaying them
+
+use crate::errors::ErrorCode;
+use crate::executor::{json_to_val, Control, Stop};
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::types::ExecutionStatus;
+use crate::worker::{ClaimAuthorizer, DelegatedAction, TaskOutcome};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+const CHECKOUT: &str = r#"
+let token = Math.random()
+let charge = await Task.run("charge", { token: token })
+if (charge.declined) {
+    throw Error("Declined at " + Date.now(), "DECLINED")
+}
+return charge.id
+"#;
+
+/// Run the worker until `id` finishes, completing its tasks with `result`
+async fn run_to_end(
+    worker: &WorkerService,
+    executions: &ExecutionService,
+    id: &str,
+    result: serde_json::Value,
+) -> anyhow::Result<()> {
+    for _ in 0..20 {
+        if let DelegatedAction::ExecuteTask { execution_id, .. } =
+            worker.run_cooperative_worker_loop().await?
+        {
+            worker
+                .record_outcome(&execution_id, TaskOutcome::Complete(result.clone()))
+                .await?;
+        }
+        if executions
+            .get_execution(id)
+            .await?
+            .unwrap()
+            .status
+            .is_terminal()
+        {
+            return Ok(());
+        }
+    }
+    anyhow::bail!("Workflow {} did not finish", id)
+}
+
+#[sqlx::test]
+async fn test_replay_reproduces_a_recorded_failure(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows.register_workflow("checkout", CHECKOUT).await?;
+    let id = workflows
+        .start_workflow("checkout", json!({}), "default", None)
+        .await?;
+    assert!(workflows.get_recording(&id).await?.is_none());
+
+    // Recorded after its first run, which read the random token
+    let task_id = loop {
+        if let DelegatedAction::ExecuteTask { execution_id, .. } =
+            worker.run_cooperative_worker_loop().await?
+        {
+            break execution_id;
+        }
+    };
+    executions.enable_recording(&id).await?;
+    worker
+        .record_outcome(&task_id, TaskOutcome::Complete(json!({ "declined": true })))
+        .await?;
+    run_to_end(&worker, &executions, &id, json!(null)).await?;
+
+    let recorded = workflows.get_recording(&id).await?.unwrap();
+    assert_eq!(recorded.workflow_name, "checkout");
+    assert_eq!(recorded.execution.status, ExecutionStatus::Failed);
+    assert_eq!(recorded.reads.len(), 2);
+    assert_eq!(recorded.resumes.len(), 1);
+
+    let mut debugger = recorded.debugger()?;
+    loop {
+        match debugger.continue_running() {
+            Stop::Suspended(_) => assert!(debugger.resume_recorded().is_some()),
+            Stop::Finished => break,
+            stop => panic!("Unexpected stop {:?}", stop),
+        }
+    }
+    let Control::Throw(error) = debugger.control() else {
+        panic!("Expected the replay to throw, got {:?}", debugger.control());
+    };
+    assert_eq!(
+        Some(error),
+        recorded
+            .execution
+            .output
+            .as_ref()
+            .map(json_to_val)
+            .transpose()?
+            .as_ref()
+    );
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_record_runs_records_every_workflow(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_recorded_runs();
+    workflows.register_workflow("checkout", CHECKOUT).await?;
+    let id = workflows
+        .start_workflow("checkout", json!({}), "default", None)
+        .await?;
+    run_to_end(&worker, &executions, &id, json!({ "id": "ch_1" })).await?;
+
+    let recorded = workflows.get_recording(&id).await?.unwrap();
+    assert_eq!(recorded.execution.status, ExecutionStatus::Completed);
+    assert_eq!(recorded.reads.len(), 1);
+    assert_eq!(recorded.resumes, [json_to_val(&json!({ "id": "ch_1" }))?]);
+
+    // Finished before it was recorded, so what it read is gone
+    let other = workflows
+        .start_workflow("checkout", json!({}), "default", None)
+        .await?;
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    run_to_end(&worker, &executions, &other, json!({ "id": "ch_2" })).await?;
+    let err = executions.enable_recording(&other).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::Conflict);
+    // Enabling one already recorded is a no-op
+    executions.enable_recording(&id).await?;
+
+    let task = workflows.get_workflow_tasks(&id).await?.remove(0);
+    let err = executions.enable_recording(&task.id).await.unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::Validation);
+    let err = executions.enable_recording("missing").await.unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::NotFound);
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! Tests for retrying failed tasks by error code
+
+use crate::config::TaskConfig;
+use crate::db;
+use crate::services::{ExecutionService, WorkerService};
+use crate::test_helpers::task_params;
+use crate::types::{Backoff, CreateExecutionParams, ExecutionStatus, RetryPolicy};
+use crate::worker::{ClaimAuthorizer, DelegatedAction, RetryRules};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+fn charge() -> CreateExecutionParams {
+    task_params("charge_card")
+}
+
+fn worker(pool: &PgPool) -> WorkerService {
+    WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_retry_rules(RetryRules::new(vec![TaskConfig {
+        target_name: "charge_card".to_string(),
+        max_retries: Some(1),
+        retry_on: vec!["RATE_LIMIT".to_string()],
+        give_up_on: vec!["VALIDATION".to_string()],
+        ..Default::default()
+    }]))
+}
+
+async fn claim(worker: &WorkerService) -> anyhow::Result<String> {
+    match worker.run_cooperative_worker_loop().await? {
+        DelegatedAction::ExecuteTask { execution_id, .. } => Ok(execution_id),
+        _ => panic!("Expected a task to execute"),
+    }
+}
+
+#[sqlx::test]
+async fn test_retry_on_code_requeues_until_retries_run_out(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    let id = executions.create_execution(charge()).await?;
+    let error = json!({"code": "RATE_LIMIT"});
+
+    assert_eq!(claim(&worker).await?, id);
+    assert!(worker.fail_work(&id, error.clone(), false).await?);
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Pending);
+    assert_eq!(stored.attempt, 1);
+    assert_eq!(stored.output, None);
+
+    assert_eq!(claim(&worker).await?, id);
+    assert!(!worker.fail_work(&id, error.clone(), false).await?);
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Failed);
+    assert_eq!(stored.output, Some(error));
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_give_up_on_code_fails_despite_retry_flag(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    let id = executions.create_execution(charge()).await?;
+
+    claim(&worker).await?;
+    let retried = worker
+        .fail_work(&id, json!({"code": "VALIDATION"}), true)
+        .await?;
+
+    assert!(!retried);
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Failed);
+    assert_eq!(stored.attempt, 0);
+    let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM work_queue")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(queued, 0);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_retry_policy_redelivers_after_backoff(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    let id = executions
+        .create_execution(CreateExecutionParams {
+            retry_policy: Some(RetryPolicy {
+                max_retries: Some(3),
+                backoff: Backoff::Exponential,
+                initial_interval_ms: 60_000,
+                max_interval_ms: 600_000,
+                jitter: 0.0,
+                retry_on: Vec::new(),
+                give_up_on: Vec::new(),
+            }),
+            ..charge()
+        })
+        .await?;
+
+    // The policy's limit replaces the task config's max_retries of 1
+    for attempt in 1..=3 {
+        sqlx::query("DELETE FROM scheduled_queue")
+            .execute(&pool)
+            .await?;
+        if attempt > 1 {
+            db::work_queue::enqueue_work(&pool, &id, "default", 0).await?;
+        }
+        assert_eq!(claim(&worker).await?, id);
+        assert!(
+            worker
+                .fail_work(&id, json!({"code": "RATE_LIMIT"}), true)
+                .await?
+        );
+
+        let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM work_queue")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(queued, 0, "redelivery waits for the backoff");
+        let wait: f64 = sqlx::query_scalar(
+            "SELECT EXTRACT(EPOCH FROM run_at - (NOW() AT TIME ZONE 'UTC'))::float8 FROM scheduled_queue",
+        )
+        .fetch_one(&pool)
+        .await?;
+        let expected = 60.0 * f64::from(1 << (attempt - 1));
+        assert!(
+            (expected - 5.0..=expected).contains(&wait),
+            "attempt {} waits {}s",
+            attempt,
+            wait
+        );
+    }
+
+    db::work_queue::enqueue_work(&pool, &id, "default", 0).await?;
+    assert_eq!(claim(&worker).await?, id);
+    assert!(
+        !worker
+            .fail_work(&id, json!({"code": "RATE_LIMIT"}), true)
+            .await?
+    );
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Failed);
+    assert_eq!(stored.attempt, 3);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_retry_policy_codes_override_task_config(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = worker(&pool);
+    // As stored by Task.run("charge_card", ..., { retry_on, give_up_on })
+    let policy = RetryPolicy {
+        max_retries: None,
+        backoff: Backoff::Fixed,
+        initial_interval_ms: 0,
+        max_interval_ms: 0,
+        jitter: 0.0,
+        retry_on: vec!["VALIDATION".to_string()],
+        give_up_on: vec!["RATE_LIMIT".to_string()],
+    };
+
+    let id = executions
+        .create_execution(CreateExecutionParams {
+            retry_policy: Some(policy.clone()),
+            ..charge()
+        })
+        .await?;
+    assert_eq!(claim(&worker).await?, id);
+    assert!(
+        !worker
+            .fail_work(&id, json!({"code": "RATE_LIMIT"}), true)
+            .await?
+    );
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Failed);
+
+    let id = executions
+        .create_execution(CreateExecutionParams {
+            retry_policy: Some(policy),
+            ..charge()
+        })
+        .await?;
+    assert_eq!(claim(&worker).await?, id);
+    assert!(
+        worker
+            .fail_work(&id, json!({"code": "VALIDATION"}), false)
+            .await?
+    );
+    let stored = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Pending);
+    assert_eq!(stored.attempt, 1);
+    Ok(())
+}
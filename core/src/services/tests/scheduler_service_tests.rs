@@ -1,7 +1,12 @@
 //! Tests for scheduler service operations
 
-use crate::services::SchedulerService;
-use crate::types::{ExecutionType, ScheduleExecutionParams};
+use crate::db;
+use crate::services::scheduler_service::EXPIRED_ERROR_CODE;
+use crate::services::{ExecutionService, SchedulerService};
+use crate::test_helpers::task_params;
+use crate::types::{
+    CreateExecutionParams, ExecutionStatus, ExecutionType, ScheduleExecutionParams,
+};
 use chrono::{NaiveDateTime, Utc};
 use serde_json::json;
 use sqlx::PgPool;
@@ -46,6 +51,7 @@ async fn test_schedule_execution_creates_pending_execution(pool: PgPool) -> anyh
         queue: "default".to_string(),
         inputs: json!({"key": "value"}),
         run_at: now_plus_seconds(60),
+        namespace: None,
     };
 
     let execution_id = service.schedule_execution(params).await?;
@@ -72,6 +78,7 @@ async fn test_process_ready_items_moves_to_work_queue(pool: PgPool) -> anyhow::R
         queue: "default".to_string(),
         inputs: json!({}),
         run_at: now_plus_seconds(-10), // in the past
+        namespace: None,
     };
 
     let execution_id = service.schedule_execution(params).await?;
@@ -106,6 +113,7 @@ async fn test_process_ready_items_skips_future(pool: PgPool) -> anyhow::Result<(
         queue: "default".to_string(),
         inputs: json!({}),
         run_at: now_plus_seconds(60), // in the future
+        namespace: None,
     };
 
     service.schedule_execution(params).await?;
@@ -121,6 +129,54 @@ async fn test_process_ready_items_skips_future(pool: PgPool) -> anyhow::Result<(
     Ok(())
 }
 
+#[sqlx::test]
+async fn test_upcoming_items_are_processed_once_due(pool: PgPool) -> anyhow::Result<()> {
+    let service = SchedulerService::new(pool.clone());
+
+    for (target_name, seconds) in [("late", -5), ("soon", 1), ("later", 60)] {
+        service
+            .schedule_execution(ScheduleExecutionParams {
+                exec_type: ExecutionType::Task,
+                target_name: target_name.to_string(),
+                queue: "default".to_string(),
+                inputs: json!({}),
+                run_at: now_plus_seconds(seconds),
+                namespace: None,
+            })
+            .await?;
+    }
+
+    // Due within 10 seconds, in due order, with how long until each is due
+    let upcoming = service
+        .upcoming_items(std::time::Duration::from_secs(10), None, 10)
+        .await?;
+    assert_eq!(upcoming.len(), 2);
+    assert!(upcoming[0].due_in_ms <= -4_000);
+    assert!((0..=1_000).contains(&upcoming[1].due_in_ms));
+
+    // Continuing after the first
+    let rest = service
+        .upcoming_items(
+            std::time::Duration::from_secs(10),
+            Some((upcoming[0].run_at, upcoming[0].id)),
+            10,
+        )
+        .await?;
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].id, upcoming[1].id);
+
+    // Only the item that is due is processed
+    let ids: Vec<_> = upcoming.iter().map(|item| item.id).collect();
+    assert_eq!(service.process_items(&ids).await?, 1);
+    assert_eq!(count_work_queue_items(&pool).await?, 1);
+    assert_eq!(count_scheduled_items(&pool).await?, 2);
+
+    // Processing it again finds nothing
+    assert_eq!(service.process_items(&ids[..1]).await?, 0);
+
+    Ok(())
+}
+
 #[sqlx::test]
 async fn test_process_ready_items_respects_limit(pool: PgPool) -> anyhow::Result<()> {
     let service = SchedulerService::new(pool.clone());
@@ -133,6 +189,7 @@ async fn test_process_ready_items_respects_limit(pool: PgPool) -> anyhow::Result
             queue: "default".to_string(),
             inputs: json!({}),
             run_at: now_plus_seconds(-10 - i as i64),
+            namespace: None,
         };
         service.schedule_execution(params).await?;
     }
@@ -158,3 +215,79 @@ async fn test_process_ready_items_handles_empty_queue(pool: PgPool) -> anyhow::R
 
     Ok(())
 }
+
+fn task_with_ttl(parent_workflow_id: Option<String>, ttl_seconds: u64) -> CreateExecutionParams {
+    CreateExecutionParams {
+        parent_workflow_id,
+        ttl_seconds: Some(ttl_seconds),
+        ..task_params("send_otp")
+    }
+}
+
+/// Move an execution's expiry into the past
+async fn backdate_expiry(pool: &PgPool, id: &str) -> anyhow::Result<()> {
+    sqlx::query("UPDATE executions SET expires_at = NOW() - INTERVAL '1 second' WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_expired_work_is_not_claimed(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let expired = executions.create_execution(task_with_ttl(None, 60)).await?;
+    let fresh = executions.create_execution(task_with_ttl(None, 60)).await?;
+    backdate_expiry(&pool, &expired).await?;
+
+    let claimed = db::work_queue::claim_work(&pool, "default", 10, None).await?;
+    assert_eq!(claimed, vec![fresh]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_expire_unclaimed_wakes_parent_with_error(pool: PgPool) -> anyhow::Result<()> {
+    let service = SchedulerService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+
+    let mut tx = pool.begin().await?;
+    let parent = db::executions::create_execution(
+        &mut tx,
+        CreateExecutionParams {
+            exec_type: ExecutionType::Workflow,
+            ..task_params("login")
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    let otp = executions
+        .create_execution(task_with_ttl(Some(parent.clone()), 60))
+        .await?;
+    let later = executions.create_execution(task_with_ttl(None, 60)).await?;
+
+    // Nothing is due yet
+    assert!(service.expire_unclaimed(10).await?.is_empty());
+
+    backdate_expiry(&pool, &otp).await?;
+    let expired = service.expire_unclaimed(10).await?;
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].id, otp);
+
+    let otp = executions.get_execution(&otp).await?.unwrap();
+    assert_eq!(otp.status, ExecutionStatus::Expired);
+    assert!(otp.status.is_terminal());
+    assert_eq!(otp.output.unwrap()["code"], EXPIRED_ERROR_CODE);
+
+    // The expired task's entry is gone and the parent is queued to resume
+    let mut queued: Vec<String> = sqlx::query_scalar("SELECT execution_id FROM work_queue")
+        .fetch_all(&pool)
+        .await?;
+    queued.sort();
+    let mut expected = vec![parent, later];
+    expected.sort();
+    assert_eq!(queued, expected);
+
+    Ok(())
+}
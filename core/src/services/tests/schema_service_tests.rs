@@ -0,0 +1,53 @@
+//! Tests for payload schema drift detection
+
+use crate::payload_schema::{PayloadKind, ShapeChange};
+use crate::services::{ExecutionService, SchemaService};
+use crate::test_helpers::{complete_task, task_params};
+use crate::types::CreateExecutionParams;
+use serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+
+/// Create a `charge` task and complete it
+async fn charge(pool: &PgPool, inputs: JsonValue, output: JsonValue) -> anyhow::Result<()> {
+    let id = ExecutionService::new(pool.clone())
+        .create_execution(CreateExecutionParams {
+            inputs,
+            ..task_params("charge")
+        })
+        .await?;
+    complete_task(pool, &id, output).await?;
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_drift_from_baseline(pool: PgPool) -> anyhow::Result<()> {
+    let schema = SchemaService::new(pool.clone());
+
+    charge(&pool, json!({ "amount": 10 }), json!({ "ok": true })).await?;
+    charge(&pool, json!({ "amount": 12 }), json!({ "ok": false })).await?;
+    assert_eq!(schema.analyze(100).await?, 2);
+    assert!(schema.drift(None).await?.is_empty());
+
+    // Producers start sending the amount as a string
+    charge(&pool, json!({ "amount": "14" }), json!({ "ok": true })).await?;
+    assert_eq!(schema.analyze(100).await?, 1);
+    assert_eq!(schema.analyze(100).await?, 0);
+
+    let drift = schema.drift(Some("charge")).await?;
+    assert_eq!(drift.len(), 1);
+    assert_eq!(drift[0].shapes.kind, PayloadKind::Inputs);
+    assert_eq!(drift[0].shapes.samples, 3);
+    assert_eq!(
+        drift[0].changes,
+        vec![ShapeChange::TypeChanged {
+            path: "amount".to_string(),
+            from: json!("number"),
+            to: json!("string"),
+        }]
+    );
+
+    // Accepting the new shape clears the drift
+    assert_eq!(schema.accept("charge").await?, 1);
+    assert!(schema.drift(None).await?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Tests for draining a worker on shutdown
+
+use crate::services::{ExecutionService, WorkerService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionStatus};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+fn task() -> CreateExecutionParams {
+    task_params("work")
+}
+
+#[sqlx::test]
+async fn test_drain_waits_for_in_flight_and_releases_the_rest(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let service = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    let worker = service.worker();
+
+    executions.create_execution(task()).await?;
+    executions.create_execution(task()).await?;
+    executions.create_execution(task()).await?;
+    let claimed = service.claim_executions(None, &[], 2).await?;
+    let (done, stuck) = (&claimed[0].execution_id, &claimed[1].execution_id);
+    assert_eq!(worker.in_flight().len(), 2);
+
+    // One task finishes while draining, the other outlives the deadline
+    let finisher = {
+        let service = service.clone();
+        let done = done.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            service.complete_work(&done, Some(json!(1)), None).await
+        })
+    };
+    let report = worker.shutdown(Duration::from_millis(500)).await?;
+    finisher.await??;
+
+    assert!(!report.idle);
+    assert_eq!(report.released, vec![stuck.clone()]);
+    assert!(worker.in_flight().is_empty());
+
+    let stuck = executions.get_execution(stuck).await?.unwrap();
+    assert_eq!(stuck.status, ExecutionStatus::Pending);
+    let done = executions.get_execution(done).await?.unwrap();
+    assert_eq!(done.status, ExecutionStatus::Completed);
+
+    // No new claims once shutting down
+    assert!(matches!(
+        service.run_cooperative_worker_loop().await?,
+        DelegatedAction::Shutdown
+    ));
+    assert!(service.claim_executions(None, &[], 5).await?.is_empty());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_wait_idle_returns_once_tasks_report(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let service = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    let worker = service.worker();
+    assert!(worker.wait_idle(Duration::ZERO).await);
+
+    executions.create_execution(task()).await?;
+    let id = match service.run_cooperative_worker_loop().await? {
+        DelegatedAction::ExecuteTask { execution_id, .. } => execution_id,
+        other => panic!("Expected a task to execute, got {:?}", other),
+    };
+    worker.begin_shutdown();
+    assert!(worker.is_shutting_down());
+    assert!(!worker.wait_idle(Duration::from_millis(20)).await);
+
+    service
+        .fail_work(&id, json!({"message": "boom"}), false)
+        .await?;
+    assert!(worker.wait_idle(Duration::from_millis(20)).await);
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Tests for claim latency SLOs
+
+use crate::config::SloConfig;
+use crate::db;
+use crate::services::{ExecutionService, SloService};
+use crate::test_helpers::task_params;
+use crate::types::{ClaimLatencyCounts, CreateExecutionParams, SloStatus};
+use sqlx::PgPool;
+
+fn slo(queue: Option<&str>, threshold_ms: u64, objective: f64) -> SloConfig {
+    SloConfig {
+        name: "claimed-fast".to_string(),
+        queue: queue.map(str::to_string),
+        target_name: None,
+        threshold_ms,
+        objective,
+        window_secs: 3600,
+    }
+}
+
+/// Create a task `created_secs_ago`, claimed `claimed_after_secs` later if given
+async fn task(
+    pool: &PgPool,
+    queue: &str,
+    created_secs_ago: i64,
+    claimed_after_secs: Option<i64>,
+) -> anyhow::Result<String> {
+    let id = ExecutionService::new(pool.clone())
+        .create_execution(CreateExecutionParams {
+            queue: queue.to_string(),
+            ..task_params("work")
+        })
+        .await?;
+    if claimed_after_secs.is_some() {
+        db::executions::start_execution_unless_finished(pool, &id).await?;
+    }
+    sqlx::query(
+        "UPDATE executions
+         SET created_at = NOW() - $2 * INTERVAL '1 second',
+             started_at = NOW() - ($2 - $3) * INTERVAL '1 second'
+         WHERE id = $1",
+    )
+    .bind(&id)
+    .bind(created_secs_ago as f64)
+    .bind(claimed_after_secs.map(|s| s as f64))
+    .execute(pool)
+    .await?;
+    Ok(id)
+}
+
+#[sqlx::test]
+async fn test_evaluate_counts_claims_within_threshold(pool: PgPool) -> anyhow::Result<()> {
+    task(&pool, "payments", 60, Some(1)).await?;
+    task(&pool, "payments", 60, Some(2)).await?;
+    task(&pool, "payments", 60, Some(30)).await?; // claimed late
+    task(&pool, "payments", 60, None).await?; // still waiting past the threshold
+    task(&pool, "payments", 0, None).await?; // too new to judge
+    task(&pool, "payments", 7200, Some(60)).await?; // outside the window
+    task(&pool, "other", 60, Some(30)).await?;
+
+    let service = SloService::new(pool.clone(), vec![slo(Some("payments"), 5000, 0.5)]);
+    let statuses = service.evaluate().await?;
+
+    assert_eq!(statuses.len(), 1);
+    let status = &statuses[0];
+    assert_eq!(
+        status.counts,
+        ClaimLatencyCounts {
+            total: 4,
+            within: 2
+        }
+    );
+    assert_eq!(status.compliance, 0.5);
+    assert!(status.met);
+    assert_eq!(status.error_budget_remaining, 0.0);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_first_claim_sets_started_at_once(pool: PgPool) -> anyhow::Result<()> {
+    let id = task(&pool, "default", 60, Some(1)).await?;
+    let first: Option<chrono::DateTime<chrono::Utc>> =
+        sqlx::query_scalar("SELECT started_at FROM executions WHERE id = $1")
+            .bind(&id)
+            .fetch_one(&pool)
+            .await?;
+
+    db::executions::start_execution_unless_finished(&pool, &id).await?;
+    let second: Option<chrono::DateTime<chrono::Utc>> =
+        sqlx::query_scalar("SELECT started_at FROM executions WHERE id = $1")
+            .bind(&id)
+            .fetch_one(&pool)
+            .await?;
+
+    assert!(first.is_some());
+    assert_eq!(first, second);
+    Ok(())
+}
+
+#[test]
+fn test_status_spends_error_budget_per_miss() {
+    let status = SloStatus::from_counts(
+        slo(None, 5000, 0.9),
+        ClaimLatencyCounts {
+            total: 100,
+            within: 85,
+        },
+    );
+    assert!(!status.met);
+    assert_eq!(status.compliance, 0.85);
+    assert!((status.error_budget_remaining - -0.5).abs() < 1e-9);
+
+    let idle = SloStatus::from_counts(slo(None, 5000, 0.99), ClaimLatencyCounts::default());
+    assert!(idle.met);
+    assert_eq!(idle.compliance, 1.0);
+    assert_eq!(idle.error_budget_remaining, 1.0);
+
+    let strict = SloStatus::from_counts(
+        slo(None, 5000, 1.0),
+        ClaimLatencyCounts {
+            total: 10,
+            within: 8,
+        },
+    );
+    assert!(!strict.met);
+    assert_eq!(strict.error_budget_remaining, -1.0);
+}
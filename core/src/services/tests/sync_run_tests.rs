@@ -0,0 +1,174 @@
+//! Tests for synchronous workflow runs in the express lane and waiting for
+//! workflow results
+
+use crate::db;
+use crate::errors::ErrorCode;
+use crate::services::workflow_service::{SyncRunFailed, SyncRunTimeout};
+use crate::services::{WorkerService, WorkflowService};
+use crate::worker::{ClaimAuthorizer, DelegatedAction, TaskOutcome};
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const QUOTE: &str = r#"
+let price = await Task.run("price", { sku: Inputs.sku })
+return { sku: Inputs.sku, price: price }
+"#;
+
+async fn priority(pool: &PgPool, execution_id: &str) -> anyhow::Result<i32> {
+    Ok(
+        sqlx::query_scalar("SELECT priority FROM work_queue WHERE execution_id = $1")
+            .bind(execution_id)
+            .fetch_one(pool)
+            .await?,
+    )
+}
+
+#[sqlx::test]
+async fn test_sync_run_returns_output(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows.register_workflow("quote", QUOTE).await?;
+
+    let run = workflows.run_workflow_sync(
+        "quote",
+        json!({ "sku": "abc" }),
+        "default",
+        None,
+        Duration::from_secs(30),
+    );
+    tokio::pin!(run);
+    let output = loop {
+        tokio::select! {
+            output = &mut run => break output?,
+            action = worker.run_cooperative_worker_loop() => {
+                if let DelegatedAction::ExecuteTask { execution_id, .. } = action? {
+                    // The task inherits the express lane from its workflow
+                    assert_eq!(
+                        priority(&pool, &execution_id).await?,
+                        db::work_queue::EXPRESS_PRIORITY
+                    );
+                    worker
+                        .record_outcome(&execution_id, TaskOutcome::Complete(json!(9.5)))
+                        .await?;
+                }
+            }
+        }
+    };
+
+    assert_eq!(output, json!({ "sku": "abc", "price": 9.5 }));
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_sync_run_times_out_in_express_lane(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    workflows.register_workflow("quote", QUOTE).await?;
+    let normal = workflows
+        .start_workflow("quote", json!({}), "default", None)
+        .await?;
+
+    let err = workflows
+        .run_workflow_sync("quote", json!({}), "default", None, Duration::ZERO)
+        .await
+        .unwrap_err();
+    let timeout = err.downcast_ref::<SyncRunTimeout>().unwrap();
+
+    assert_eq!(
+        priority(&pool, &timeout.execution_id).await?,
+        db::work_queue::EXPRESS_PRIORITY
+    );
+    assert_eq!(priority(&pool, &normal).await?, 0);
+
+    // Queued later, but claimed first
+    let claimed = db::work_queue::claim_work(&pool, "default", 1, None).await?;
+    assert_eq!(claimed, vec![timeout.execution_id.clone()]);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_sync_run_reports_failure(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows
+        .register_workflow("broken", "return Inputs.missing.field")
+        .await?;
+
+    let run = workflows.run_workflow_sync(
+        "broken",
+        json!({}),
+        "default",
+        None,
+        Duration::from_secs(30),
+    );
+    tokio::pin!(run);
+    let err = loop {
+        tokio::select! {
+            result = &mut run => break result.unwrap_err(),
+            action = worker.run_cooperative_worker_loop() => { action?; }
+        }
+    };
+
+    assert!(err.downcast_ref::<SyncRunFailed>().is_some());
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_get_workflow_result_waits_for_output(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows.register_workflow("quote", QUOTE).await?;
+    let workflow_id = workflows
+        .start_workflow("quote", json!({ "sku": "abc" }), "default", None)
+        .await?;
+
+    assert_eq!(
+        workflows
+            .get_workflow_result(&workflow_id, false, Duration::ZERO)
+            .await?,
+        None
+    );
+    let missing = workflows
+        .get_workflow_result("missing", true, Duration::from_secs(30))
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&missing), ErrorCode::NotFound);
+
+    let wait = workflows.get_workflow_result(&workflow_id, true, Duration::from_secs(30));
+    tokio::pin!(wait);
+    let output = loop {
+        tokio::select! {
+            output = &mut wait => break output?,
+            action = worker.run_cooperative_worker_loop() => {
+                if let DelegatedAction::ExecuteTask { execution_id, .. } = action? {
+                    worker
+                        .record_outcome(&execution_id, TaskOutcome::Complete(json!(9.5)))
+                        .await?;
+                }
+            }
+        }
+    };
+    assert_eq!(output, Some(json!({ "sku": "abc", "price": 9.5 })));
+
+    // A finished workflow's result is there without waiting
+    assert_eq!(
+        workflows
+            .get_workflow_result(&workflow_id, false, Duration::ZERO)
+            .await?,
+        output
+    );
+    Ok(())
+}
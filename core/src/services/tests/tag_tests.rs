@@ -0,0 +1,166 @@
+//! Tests for execution tags
+
+use crate::errors::ErrorCode;
+use crate::services::ExecutionService;
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionFilters, ExecutionType};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn params(
+    exec_type: ExecutionType,
+    parent_workflow_id: Option<String>,
+    tags: HashMap<String, String>,
+) -> CreateExecutionParams {
+    CreateExecutionParams {
+        exec_type,
+        parent_workflow_id,
+        tags,
+        ..task_params("work")
+    }
+}
+
+async fn ids_tagged(
+    service: &ExecutionService,
+    tags: HashMap<String, String>,
+) -> anyhow::Result<Vec<String>> {
+    let mut ids: Vec<String> = service
+        .query_executions(ExecutionFilters {
+            tags,
+            ..Default::default()
+        })
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+#[sqlx::test]
+async fn test_list_filters_by_tags(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool);
+
+    let acme = service
+        .create_execution(params(
+            ExecutionType::Task,
+            None,
+            tags(&[("customer_id", "123"), ("plan", "pro")]),
+        ))
+        .await?;
+    let other = service
+        .create_execution(params(
+            ExecutionType::Task,
+            None,
+            tags(&[("customer_id", "456")]),
+        ))
+        .await?;
+    let untagged = service
+        .create_execution(params(ExecutionType::Task, None, HashMap::new()))
+        .await?;
+
+    assert_eq!(
+        ids_tagged(&service, tags(&[("customer_id", "123")])).await?,
+        vec![acme.clone()]
+    );
+    assert_eq!(
+        ids_tagged(&service, tags(&[("customer_id", "123"), ("plan", "free")])).await?,
+        Vec::<String>::new()
+    );
+
+    // No tag filter lists everything
+    let mut all = vec![acme, other, untagged.clone()];
+    all.sort();
+    assert_eq!(ids_tagged(&service, HashMap::new()).await?, all);
+    assert!(service.get_execution_tags(&untagged).await?.is_empty());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_children_get_the_workflows_tags(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool);
+
+    let workflow = service
+        .create_execution(params(
+            ExecutionType::Workflow,
+            None,
+            tags(&[("customer_id", "123"), ("region", "eu")]),
+        ))
+        .await?;
+    let child = service
+        .create_execution(params(
+            ExecutionType::Task,
+            Some(workflow.clone()),
+            tags(&[("region", "us"), ("step", "charge")]),
+        ))
+        .await?;
+
+    // The child's own tags win over the workflow's
+    assert_eq!(
+        service.get_execution_tags(&child).await?,
+        tags(&[("customer_id", "123"), ("region", "us"), ("step", "charge")])
+    );
+
+    let mut both = vec![workflow, child];
+    both.sort();
+    assert_eq!(
+        ids_tagged(&service, tags(&[("customer_id", "123")])).await?,
+        both
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_tag_execution_merges_tags(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool);
+
+    let id = service
+        .create_execution(params(
+            ExecutionType::Task,
+            None,
+            tags(&[("customer_id", "123"), ("plan", "free")]),
+        ))
+        .await?;
+    service
+        .tag_execution(&id, tags(&[("plan", "pro"), ("ticket", "T-42")]))
+        .await?;
+
+    assert_eq!(
+        service.get_execution_tags(&id).await?,
+        tags(&[("customer_id", "123"), ("plan", "pro"), ("ticket", "T-42")])
+    );
+    assert_eq!(
+        ids_tagged(&service, tags(&[("ticket", "T-42")])).await?,
+        vec![id]
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_tag_errors(pool: PgPool) -> anyhow::Result<()> {
+    let service = ExecutionService::new(pool);
+
+    let err = service
+        .tag_execution("missing", tags(&[("customer_id", "123")]))
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::NotFound);
+
+    let err = service
+        .create_execution(params(ExecutionType::Task, None, tags(&[("", "123")])))
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::Validation);
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! Tests for reading a registered workflow's task graph
+
+use crate::errors::ErrorCode;
+use crate::services::WorkflowService;
+use sqlx::PgPool;
+
+const ORDER: &str = r#"
+```
+queue: orders
+```
+let reserve = Task.run("reserve", {})
+let charge = Task.run("charge", {})
+await Promise.all([reserve, charge])
+await Workflow.run("ship", {})
+return 1
+"#;
+
+#[sqlx::test]
+async fn test_analyze_registered_workflow(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool);
+    workflows.register_workflow("order", ORDER).await?;
+
+    let graph = workflows.analyze_workflow("order").await?;
+    assert_eq!(
+        graph.tasks.into_iter().collect::<Vec<_>>(),
+        vec!["charge".to_string(), "reserve".to_string()]
+    );
+    assert_eq!(
+        graph.workflows.into_iter().collect::<Vec<_>>(),
+        vec!["ship".to_string()]
+    );
+    assert_eq!(graph.parallel.len(), 1);
+    assert_eq!(graph.parallel[0].len(), 2);
+
+    let missing = workflows.analyze_workflow("missing").await.unwrap_err();
+    assert_eq!(ErrorCode::of(&missing), ErrorCode::NotFound);
+    Ok(())
+}
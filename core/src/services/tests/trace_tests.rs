@@ -0,0 +1,72 @@
+//! Tests for trace context propagation
+
+use crate::db;
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::telemetry::TraceContext;
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionType};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+const CALLER: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+fn workflow(traceparent: Option<&str>) -> CreateExecutionParams {
+    CreateExecutionParams {
+        exec_type: ExecutionType::Workflow,
+        traceparent: traceparent.map(str::to_string),
+        ..task_params("checkout")
+    }
+}
+
+#[sqlx::test]
+async fn test_child_tasks_join_the_callers_trace(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows
+        .register_workflow("checkout", r#"return await Task.run("charge", {})"#)
+        .await?;
+    let id = executions.create_execution(workflow(Some(CALLER))).await?;
+
+    let traceparent = loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { traceparent, .. } => break traceparent,
+            DelegatedAction::Continue => {}
+            other => panic!("Expected a task to execute, got {:?}", other),
+        }
+    };
+    assert_eq!(traceparent.as_deref(), Some(CALLER));
+
+    let tasks = workflows.get_workflow_tasks(&id).await?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(
+        db::executions::get_traceparent(&pool, &tasks[0].id)
+            .await?
+            .as_deref(),
+        Some(CALLER)
+    );
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_executions_without_a_caller_trace_start_one(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+
+    let id = executions.create_execution(workflow(None)).await?;
+    let stored = db::executions::get_traceparent(&pool, &id).await?.unwrap();
+    assert!(TraceContext::parse(&stored).is_some());
+
+    // An invalid context is dropped rather than stored
+    let id = executions
+        .create_execution(workflow(Some("00-not-a-trace-01")))
+        .await?;
+    let stored = db::executions::get_traceparent(&pool, &id).await?.unwrap();
+    assert_ne!(stored, "00-not-a-trace-01");
+    assert!(TraceContext::parse(&stored).is_some());
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Tests for exporting and importing executions
+
+use crate::services::{ExecutionService, TransferService, WorkerService, WorkflowService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionStatus, ExecutionType, TransferRecord};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+#[sqlx::test]
+async fn test_imported_workflow_picks_up_where_it_was_exported(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let transfer = TransferService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows
+        .register_workflow(
+            "checkout",
+            r#"
+                let receipt = await Task.run("charge", { amount: 10 })
+                return receipt
+            "#,
+        )
+        .await?;
+    let id = executions
+        .create_execution(CreateExecutionParams {
+            exec_type: ExecutionType::Workflow,
+            ..task_params("checkout")
+        })
+        .await?;
+    let task_id = loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => break execution_id,
+            DelegatedAction::Continue => {}
+            other => panic!("Expected a task to execute, got {:?}", other),
+        }
+    };
+
+    // Export mid-run, with the task claimed, then start over empty
+    let mut file = Vec::new();
+    let exported = transfer.export(None, &mut file).await?;
+    assert_eq!(
+        (
+            exported.workflow_definitions,
+            exported.executions,
+            exported.contexts
+        ),
+        (1, 2, 1)
+    );
+    let records: Vec<TransferRecord> = file
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(serde_json::from_slice)
+        .collect::<Result<_, _>>()?;
+    assert!(matches!(
+        records[0],
+        TransferRecord::Header { version: 1, .. }
+    ));
+    assert!(matches!(records[1], TransferRecord::WorkflowDefinition(_)));
+    sqlx::query("DELETE FROM executions").execute(&pool).await?;
+    sqlx::query("DELETE FROM work_queue").execute(&pool).await?;
+    sqlx::query("DELETE FROM workflow_definitions")
+        .execute(&pool)
+        .await?;
+
+    let imported = transfer.import(file.as_slice()).await?;
+    assert_eq!(imported, exported);
+    let task = executions.get_execution(&task_id).await?.unwrap();
+    assert_eq!(task.status, ExecutionStatus::Pending);
+    assert_eq!(task.parent_workflow_id.as_deref(), Some(id.as_str()));
+
+    // The task runs again here and the workflow resumes from its saved state
+    let rerun = match worker.run_cooperative_worker_loop().await? {
+        DelegatedAction::ExecuteTask { execution_id, .. } => execution_id,
+        other => panic!("Expected the imported task, got {:?}", other),
+    };
+    assert_eq!(rerun, task_id);
+    worker
+        .complete_work(&task_id, Some(json!("receipt")), None)
+        .await?;
+    worker.run_cooperative_worker_loop().await?;
+    let workflow = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(workflow.status, ExecutionStatus::Completed);
+    assert_eq!(workflow.output, Some(json!("receipt")));
+
+    // Importing again changes nothing
+    let again = transfer.import(file.as_slice()).await?;
+    assert_eq!(
+        again.executions + again.contexts + again.workflow_definitions,
+        0
+    );
+    assert_eq!(again.skipped, 4);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_import_requires_an_export_header(pool: PgPool) -> anyhow::Result<()> {
+    let transfer = TransferService::new(pool);
+    let file = br#"{"kind":"execution","data":{"id":"abc"}}"#;
+    let error = transfer.import(&file[..]).await.unwrap_err();
+    assert_eq!(
+        crate::errors::ErrorCode::of(&error),
+        crate::errors::ErrorCode::Validation
+    );
+    Ok(())
+}
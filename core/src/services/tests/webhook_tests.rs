@@ -0,0 +1,275 @@
+//! Tests for webhook registration and delivery
+
+use crate::blob_store::{PayloadStore, BLOB_REFERENCE_KEY};
+use crate::config::{BlobStoreConfig, PayloadsConfig, WebhooksConfig};
+use crate::services::webhook_service::sign;
+use crate::services::{ExecutionService, WebhookService, WorkerService};
+use crate::test_helpers::task_params;
+use crate::types::{
+    CreateExecutionParams, CreateWebhookParams, WebhookDeliveryStatus, WebhookEvent,
+};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// A request the test endpoint received: lowercased headers and body
+type Received = (HashMap<String, String>, Vec<u8>);
+
+/// Serve HTTP on a local port, answering each request with the next of
+/// `statuses` (200 once they run out)
+async fn endpoint(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut statuses = statuses.into_iter();
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = Vec::new();
+            let header_end = loop {
+                let mut chunk = [0u8; 1024];
+                let n = socket.read(&mut chunk).await.unwrap();
+                buffer.extend_from_slice(&chunk[..n]);
+                if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break end + 4;
+                }
+            };
+            let headers: HashMap<String, String> = String::from_utf8_lossy(&buffer[..header_end])
+                .lines()
+                .skip(1)
+                .filter_map(|line| line.split_once(':'))
+                .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+                .collect();
+            let length: usize = headers["content-length"].parse().unwrap();
+            while buffer.len() < header_end + length {
+                let mut chunk = [0u8; 1024];
+                let n = socket.read(&mut chunk).await.unwrap();
+                buffer.extend_from_slice(&chunk[..n]);
+            }
+            sender
+                .send((headers, buffer[header_end..].to_vec()))
+                .unwrap();
+
+            let status = statuses.next().unwrap_or(200);
+            let response = format!(
+                "HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    (url, receiver)
+}
+
+fn task(target_name: &str) -> CreateExecutionParams {
+    task_params(target_name)
+}
+
+#[test]
+fn test_signature_is_hmac_of_timestamp_and_body() {
+    assert_eq!(
+        sign("shh", 1_700_000_000, br#"{"event":"completed"}"#),
+        "sha256=b3f2abfa63a98bc9bdfbe6bf8fa208ba4eb0b791f3a340970916abfa9525480d"
+    );
+}
+
+#[sqlx::test]
+async fn test_matching_completions_are_delivered_signed_with_retries(
+    pool: PgPool,
+) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    let webhooks = WebhookService::new(pool.clone()).with_config(WebhooksConfig {
+        base_delay_ms: 0,
+        ..Default::default()
+    });
+    let (url, mut received) = endpoint(vec![500]).await;
+
+    let charges = webhooks
+        .register(CreateWebhookParams {
+            url: url.clone(),
+            secret: "shh".to_string(),
+            events: vec![WebhookEvent::Completed],
+            queue: None,
+            target_name: Some("charge".to_string()),
+        })
+        .await?;
+    let failures = webhooks
+        .register(CreateWebhookParams {
+            url,
+            secret: "shh".to_string(),
+            events: vec![WebhookEvent::Failed],
+            queue: None,
+            target_name: None,
+        })
+        .await?;
+
+    let charge_id = executions.create_execution(task("charge")).await?;
+    executions.create_execution(task("ship")).await?;
+    for _ in 0..2 {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => {
+                worker
+                    .complete_work(&execution_id, Some(json!({ "ok": true })), None)
+                    .await?;
+            }
+            other => panic!("Expected a task to execute, got {:?}", other),
+        }
+    }
+
+    // Only the charge completion matched a filter
+    assert!(webhooks.deliveries(&failures.id, 10).await?.is_empty());
+    let deliveries = webhooks.deliveries(&charges.id, 10).await?;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].execution_id, charge_id);
+    assert_eq!(
+        deliveries[0].payload["execution"]["output"],
+        json!({ "ok": true })
+    );
+
+    // The endpoint fails the first attempt, which is retried
+    assert_eq!(webhooks.deliver_due(10).await?, 0);
+    let delivery = &webhooks.deliveries(&charges.id, 10).await?[0];
+    assert_eq!(delivery.status, WebhookDeliveryStatus::Pending);
+    assert_eq!(delivery.attempts, 1);
+    assert!(delivery.last_error.as_deref().unwrap().contains("500"));
+
+    assert_eq!(webhooks.deliver_due(10).await?, 1);
+    let delivery = &webhooks.deliveries(&charges.id, 10).await?[0];
+    assert_eq!(delivery.status, WebhookDeliveryStatus::Delivered);
+    assert_eq!(delivery.attempts, 2);
+
+    // Both attempts carry the same delivery id and a valid signature
+    for _ in 0..2 {
+        let (headers, body) = received.recv().await.unwrap();
+        assert_eq!(headers["x-rhythm-event"], "completed");
+        assert_eq!(headers["x-rhythm-delivery"], delivery.id.to_string());
+        let timestamp: i64 = headers["x-rhythm-timestamp"].parse()?;
+        assert_eq!(headers["x-rhythm-signature"], sign("shh", timestamp, &body));
+        let payload: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(payload["event"], "completed");
+        assert_eq!(payload["execution"]["id"], charge_id);
+    }
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_delivery_fails_after_max_attempts_and_can_be_redelivered(
+    pool: PgPool,
+) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    let webhooks = WebhookService::new(pool.clone()).with_config(WebhooksConfig {
+        max_attempts: 2,
+        base_delay_ms: 0,
+        ..Default::default()
+    });
+    let (url, _received) = endpoint(vec![503, 503]).await;
+    let webhook = webhooks
+        .register(CreateWebhookParams {
+            url,
+            secret: "shh".to_string(),
+            events: vec![WebhookEvent::Completed],
+            queue: Some("default".to_string()),
+            target_name: None,
+        })
+        .await?;
+
+    executions.create_execution(task("charge")).await?;
+    let DelegatedAction::ExecuteTask { execution_id, .. } =
+        worker.run_cooperative_worker_loop().await?
+    else {
+        panic!("Expected a task to execute");
+    };
+    worker
+        .complete_work(&execution_id, Some(json!(1)), None)
+        .await?;
+
+    webhooks.deliver_due(10).await?;
+    webhooks.deliver_due(10).await?;
+    let delivery = webhooks.deliveries(&webhook.id, 10).await?.remove(0);
+    assert_eq!(delivery.status, WebhookDeliveryStatus::Failed);
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(webhooks.deliver_due(10).await?, 0);
+
+    webhooks.redeliver(delivery.id).await?;
+    assert_eq!(webhooks.deliver_due(10).await?, 1);
+    assert!(webhooks.redeliver(delivery.id).await.is_err());
+
+    // Removing the webhook drops its deliveries
+    webhooks.remove(&webhook.id).await?;
+    assert!(webhooks.list().await?.is_empty());
+    assert!(webhooks.deliveries(&webhook.id, 10).await?.is_empty());
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_offloaded_payloads_are_delivered_resolved(pool: PgPool) -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rhythm-blobs-{}", uuid::Uuid::new_v4()));
+    let payloads = PayloadStore::from_config(&PayloadsConfig {
+        max_inline_bytes: Some(64),
+        store: Some(BlobStoreConfig { path: dir.clone() }),
+        ..Default::default()
+    });
+    let executions = ExecutionService::new(pool.clone()).with_payloads(payloads.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_payloads(payloads.clone());
+    let webhooks = WebhookService::new(pool.clone()).with_payloads(payloads);
+    let (url, mut received) = endpoint(vec![]).await;
+    let webhook = webhooks
+        .register(CreateWebhookParams {
+            url,
+            secret: "shh".to_string(),
+            events: vec![WebhookEvent::Completed],
+            queue: None,
+            target_name: None,
+        })
+        .await?;
+
+    let rows = |n: usize| json!({ "rows": vec!["a row of the export"; n] });
+    let id = executions
+        .create_execution(CreateExecutionParams {
+            inputs: rows(10),
+            ..task("export")
+        })
+        .await?;
+    worker.run_cooperative_worker_loop().await?;
+    worker.complete_work(&id, Some(rows(20)), None).await?;
+
+    // The delivery keeps the references the row holds
+    let delivery = webhooks.deliveries(&webhook.id, 10).await?.remove(0);
+    let stored = &delivery.payload["execution"];
+    assert!(stored["inputs"].get(BLOB_REFERENCE_KEY).is_some());
+    assert!(stored["output"].get(BLOB_REFERENCE_KEY).is_some());
+
+    // The endpoint gets the payloads themselves, signed as sent
+    assert_eq!(webhooks.deliver_due(10).await?, 1);
+    let (headers, body) = received.recv().await.unwrap();
+    let timestamp: i64 = headers["x-rhythm-timestamp"].parse()?;
+    assert_eq!(headers["x-rhythm-signature"], sign("shh", timestamp, &body));
+    let payload: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(payload["execution"]["id"], id);
+    assert_eq!(payload["execution"]["inputs"], rows(10));
+    assert_eq!(payload["execution"]["output"], rows(20));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
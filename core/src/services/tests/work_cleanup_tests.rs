@@ -0,0 +1,97 @@
+//! Tests for deferred work queue cleanup
+
+use crate::services::{ExecutionService, WorkerService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, ExecutionStatus};
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+fn task() -> CreateExecutionParams {
+    task_params("work")
+}
+
+async fn work_rows(pool: &PgPool) -> anyhow::Result<i64> {
+    Ok(sqlx::query_scalar("SELECT COUNT(*) FROM work_queue")
+        .fetch_one(pool)
+        .await?)
+}
+
+async fn claim(worker: &WorkerService) -> anyhow::Result<String> {
+    match worker.run_cooperative_worker_loop().await? {
+        DelegatedAction::ExecuteTask { execution_id, .. } => Ok(execution_id),
+        _ => panic!("Expected a task to execute"),
+    }
+}
+
+#[sqlx::test]
+async fn test_deferred_cleanup_batches_work_row_deletes(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_deferred_cleanup();
+
+    for _ in 0..5 {
+        executions.create_execution(task()).await?;
+    }
+    for _ in 0..5 {
+        let id = claim(&worker).await?;
+        worker.complete_work(&id, Some(json!(1)), None).await?;
+
+        // The outcome commits with the completion, whatever the cleanup does
+        let stored = executions.get_execution(&id).await?.unwrap();
+        assert_eq!(stored.status, ExecutionStatus::Completed);
+    }
+
+    worker.flush_cleanup().await;
+    assert_eq!(work_rows(&pool).await?, 0);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_shutdown_flushes_pending_cleanup(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let shutdown = CancellationToken::new();
+    let worker = WorkerService::new(pool.clone(), shutdown.clone(), ClaimAuthorizer::default())
+        .with_deferred_cleanup();
+
+    executions.create_execution(task()).await?;
+    let id = claim(&worker).await?;
+    worker.complete_work(&id, Some(json!(1)), None).await?;
+
+    shutdown.cancel();
+    worker.flush_cleanup().await;
+    assert_eq!(work_rows(&pool).await?, 0);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_lost_cleanup_is_replayed_after_lease_expiry(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    // A completed task whose deferred delete never ran, lease now expired
+    let id = executions.create_execution(task()).await?;
+    sqlx::query("UPDATE work_queue SET claimed_until = NOW() - INTERVAL '1 second'")
+        .execute(&pool)
+        .await?;
+    sqlx::query("UPDATE executions SET status = 'completed', output = '1' WHERE id = $1")
+        .bind(&id)
+        .execute(&pool)
+        .await?;
+
+    assert!(matches!(
+        worker.run_cooperative_worker_loop().await?,
+        DelegatedAction::Continue
+    ));
+    assert_eq!(work_rows(&pool).await?, 0);
+    Ok(())
+}
@@ -0,0 +1,440 @@
+//! Tests for worker middleware and host-driven worker loops
+
+use crate::queue_backend::{PostgresQueue, QueueBackend, QueueFuture};
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::test_helpers::task_params;
+use crate::types::{CreateExecutionParams, Execution, ExecutionEventType, ExecutionStatus};
+use crate::worker::{
+    ClaimAuthorizer, DelegatedAction, HostTask, TaskCompletion, TaskDispatcher, TaskOutcome,
+    WorkerMiddleware,
+};
+use serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Unwraps inputs on claim, wraps results on completion and records failures
+#[derive(Debug, Default)]
+struct Envelope {
+    calls: Mutex<Vec<String>>,
+}
+
+impl WorkerMiddleware for Envelope {
+    fn before_claim(&self, queue: &str) {
+        self.calls.lock().unwrap().push(format!("poll:{}", queue));
+    }
+
+    fn after_claim(&self, execution: &mut Execution) {
+        execution.inputs = execution.inputs["sealed"].clone();
+    }
+
+    fn before_complete(&self, _execution_id: &str, result: &mut JsonValue) {
+        *result = json!({ "sealed": result.take() });
+    }
+
+    fn after_fail(&self, execution_id: &str, _error: &JsonValue) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("failed:{}", execution_id));
+    }
+}
+
+fn task(target_name: &str) -> CreateExecutionParams {
+    CreateExecutionParams {
+        inputs: json!({"sealed": {"n": 1}}),
+        ..task_params(target_name)
+    }
+}
+
+#[sqlx::test]
+async fn test_middleware_transforms_claims_and_completions(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let envelope = Arc::new(Envelope::default());
+    let mut worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    worker.add_middleware(envelope.clone());
+
+    let ok_id = executions.create_execution(task("ok")).await?;
+    let DelegatedAction::ExecuteTask {
+        execution_id,
+        inputs,
+        ..
+    } = worker.run_cooperative_worker_loop().await?
+    else {
+        panic!("Expected a task to execute");
+    };
+    assert_eq!(execution_id, ok_id);
+    assert_eq!(inputs, json!({"n": 1}), "host sees the unwrapped inputs");
+
+    worker
+        .complete_work(&ok_id, Some(json!({"total": 2})), None)
+        .await?;
+    let stored = executions.get_execution(&ok_id).await?.unwrap();
+    assert_eq!(stored.status, ExecutionStatus::Completed);
+    assert_eq!(stored.output, Some(json!({"sealed": {"total": 2}})));
+
+    let failing_id = executions.create_execution(task("failing")).await?;
+    worker.run_cooperative_worker_loop().await?;
+    worker
+        .complete_work(&failing_id, None, Some(json!({"message": "boom"})))
+        .await?;
+
+    assert_eq!(
+        *envelope.calls.lock().unwrap(),
+        vec![
+            "poll:default".to_string(),
+            "poll:default".to_string(),
+            format!("failed:{}", failing_id),
+        ]
+    );
+    Ok(())
+}
+
+/// Completes `ok` tasks, fails the rest, and shuts down after `limit` tasks
+struct Dispatcher {
+    shutdown: CancellationToken,
+    limit: usize,
+    seen: Vec<String>,
+}
+
+impl TaskDispatcher for Dispatcher {
+    fn dispatch(&mut self, task: HostTask) -> TaskOutcome {
+        self.seen.push(task.target_name.clone());
+        if self.seen.len() >= self.limit {
+            self.shutdown.cancel();
+        }
+        if task.target_name == "ok" {
+            TaskOutcome::Complete(json!({"n": task.inputs["sealed"]["n"]}))
+        } else {
+            TaskOutcome::Fail {
+                error: json!({"message": "boom"}),
+                retry: false,
+            }
+        }
+    }
+}
+
+#[sqlx::test]
+async fn test_host_loop_runs_tasks_across_queues(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let shutdown = CancellationToken::new();
+    let worker = WorkerService::new(pool.clone(), shutdown.clone(), ClaimAuthorizer::default());
+
+    let ok_id = executions
+        .create_execution(CreateExecutionParams {
+            queue: "emails".to_string(),
+            ..task("ok")
+        })
+        .await?;
+    let failing_id = executions.create_execution(task("failing")).await?;
+    let ignored_id = executions
+        .create_execution(CreateExecutionParams {
+            queue: "reports".to_string(),
+            ..task("ok")
+        })
+        .await?;
+
+    let mut dispatcher = Dispatcher {
+        shutdown,
+        limit: 2,
+        seen: Vec::new(),
+    };
+    let queues = vec!["emails".to_string(), "default".to_string()];
+    worker.run_host_loop(&queues, &mut dispatcher).await?;
+
+    assert_eq!(dispatcher.seen, vec!["ok", "failing"]);
+    let ok = executions.get_execution(&ok_id).await?.unwrap();
+    assert_eq!(ok.status, ExecutionStatus::Completed);
+    assert_eq!(ok.output, Some(json!({"n": 1})));
+    let failing = executions.get_execution(&failing_id).await?.unwrap();
+    assert_eq!(failing.status, ExecutionStatus::Failed);
+    let ignored = executions.get_execution(&ignored_id).await?.unwrap();
+    assert_eq!(ignored.status, ExecutionStatus::Pending);
+    Ok(())
+}
+
+/// Never gets work, interrupts on the first check
+struct Interrupting;
+
+impl TaskDispatcher for Interrupting {
+    fn dispatch(&mut self, _task: HostTask) -> TaskOutcome {
+        unreachable!("no tasks queued")
+    }
+
+    fn check_interrupt(&mut self) -> anyhow::Result<()> {
+        anyhow::bail!("interrupted")
+    }
+}
+
+#[sqlx::test]
+async fn test_host_loop_stops_on_interrupt(pool: PgPool) -> anyhow::Result<()> {
+    let worker = WorkerService::new(pool, CancellationToken::new(), ClaimAuthorizer::default());
+    let err = worker
+        .run_host_loop(&[], &mut Interrupting)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "interrupted");
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_record_outcomes_records_each_completion(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    let ok_id = executions.create_execution(task("ok")).await?;
+    let failing_id = executions.create_execution(task("failing")).await?;
+    worker.run_cooperative_worker_loop().await?;
+    worker.run_cooperative_worker_loop().await?;
+
+    let err = worker
+        .record_outcomes(vec![
+            TaskCompletion {
+                execution_id: "missing".to_string(),
+                outcome: TaskOutcome::Complete(json!(null)),
+            },
+            TaskCompletion {
+                execution_id: ok_id.clone(),
+                outcome: TaskOutcome::Complete(json!(1)),
+            },
+            TaskCompletion {
+                execution_id: failing_id.clone(),
+                outcome: TaskOutcome::Fail {
+                    error: json!({"message": "boom"}),
+                    retry: false,
+                },
+            },
+        ])
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("missing"), "{:#}", err);
+
+    let ok = executions.get_execution(&ok_id).await?.unwrap();
+    assert_eq!(ok.status, ExecutionStatus::Completed);
+    let failing = executions.get_execution(&failing_id).await?.unwrap();
+    assert_eq!(failing.status, ExecutionStatus::Failed);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_record_outcomes_wakes_each_parent_once(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    workflows
+        .register_workflow(
+            "fan_out",
+            r#"return await Promise.all([Task.run("a", {}), Task.run("b", {})])"#,
+        )
+        .await?;
+    let workflow_id = workflows
+        .start_workflow("fan_out", json!({}), "default", None)
+        .await?;
+    worker.run_cooperative_worker_loop().await?;
+
+    let mut tasks = worker.claim_executions(None, &[], 10).await?;
+    tasks.sort_by(|a, b| a.target_name.cmp(&b.target_name));
+    assert_eq!(tasks.len(), 2);
+    worker
+        .record_outcomes(
+            tasks
+                .iter()
+                .map(|task| TaskCompletion {
+                    execution_id: task.execution_id.clone(),
+                    outcome: TaskOutcome::Complete(json!(task.target_name)),
+                })
+                .collect(),
+        )
+        .await?;
+
+    let queued: Vec<(String, bool)> =
+        sqlx::query_as("SELECT execution_id, claimed_until IS NULL FROM work_queue")
+            .fetch_all(&pool)
+            .await?;
+    assert_eq!(queued, vec![(workflow_id.clone(), true)]);
+    let completed = executions
+        .get_execution_history(&workflow_id)
+        .await?
+        .into_iter()
+        .filter(|event| event.event_type == ExecutionEventType::TaskCompleted)
+        .count();
+    assert_eq!(completed, 2);
+
+    worker.run_cooperative_worker_loop().await?;
+    let workflow = executions.get_execution(&workflow_id).await?.unwrap();
+    assert_eq!(workflow.status, ExecutionStatus::Completed);
+    assert_eq!(workflow.output, Some(json!(["a", "b"])));
+
+    // A finished task is not recorded again
+    let err = worker
+        .record_outcomes(vec![TaskCompletion {
+            execution_id: tasks[0].execution_id.clone(),
+            outcome: TaskOutcome::Complete(json!("again")),
+        }])
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("already finished"),
+        "{:#}",
+        err
+    );
+    let task = executions
+        .get_execution(&tasks[0].execution_id)
+        .await?
+        .unwrap();
+    assert_eq!(task.output, Some(json!("a")));
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_partial_results_only_from_running_tasks(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+    let id = executions.create_execution(task("export_rows")).await?;
+
+    // Not started yet
+    assert!(worker.emit_partial_result(&id, json!(1)).await.is_err());
+
+    worker.run_cooperative_worker_loop().await?;
+    assert_eq!(worker.emit_partial_result(&id, json!(1)).await?, 0);
+    assert_eq!(worker.emit_partial_result(&id, json!(2)).await?, 1);
+
+    worker.complete_work(&id, Some(json!(null)), None).await?;
+    assert!(worker.emit_partial_result(&id, json!(3)).await.is_err());
+    assert_eq!(
+        crate::db::task_chunks::get_chunks(&pool, &id, 0).await?,
+        vec![json!(1), json!(2)]
+    );
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_claim_executions_claims_batch_across_queues(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    let mut ids = Vec::new();
+    for queue in ["a", "a", "b"] {
+        let params = CreateExecutionParams {
+            queue: queue.to_string(),
+            ..task("batch")
+        };
+        ids.push(executions.create_execution(params).await?);
+    }
+    let queues = ["a".to_string(), "b".to_string()];
+
+    let first = worker.claim_executions(Some("host-1"), &queues, 2).await?;
+    assert_eq!(first.len(), 2);
+    let second = worker.claim_executions(Some("host-1"), &queues, 2).await?;
+    assert_eq!(second.len(), 1);
+    assert!(worker
+        .claim_executions(Some("host-1"), &queues, 2)
+        .await?
+        .is_empty());
+
+    let mut claimed: Vec<_> = first
+        .iter()
+        .chain(&second)
+        .map(|task| task.execution_id.clone())
+        .collect();
+    claimed.sort();
+    ids.sort();
+    assert_eq!(claimed, ids);
+    for id in &ids {
+        let execution = executions.get_execution(id).await?.unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Running);
+    }
+    Ok(())
+}
+
+/// Postgres work, with the queues each claim asked for recorded
+#[derive(Debug)]
+struct RecordingQueue {
+    inner: PostgresQueue,
+    claims: Mutex<Vec<Vec<String>>>,
+}
+
+impl QueueBackend for RecordingQueue {
+    fn enqueue<'a>(
+        &'a self,
+        execution_id: &'a str,
+        queue: &'a str,
+        priority: i32,
+    ) -> QueueFuture<'a, ()> {
+        self.inner.enqueue(execution_id, queue, priority)
+    }
+
+    fn claim<'a>(
+        &'a self,
+        queues: &'a [String],
+        limit: usize,
+        worker_id: Option<&'a str>,
+    ) -> QueueFuture<'a, Vec<(String, String)>> {
+        self.claims.lock().unwrap().push(queues.to_vec());
+        self.inner.claim(queues, limit, worker_id)
+    }
+
+    fn complete<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()> {
+        self.inner.complete(execution_id)
+    }
+
+    fn release<'a>(&'a self, execution_id: &'a str) -> QueueFuture<'a, ()> {
+        self.inner.release(execution_id)
+    }
+}
+
+#[sqlx::test]
+async fn test_claims_go_through_the_queue_backend(pool: PgPool) -> anyhow::Result<()> {
+    let executions = ExecutionService::new(pool.clone());
+    let backend = Arc::new(RecordingQueue {
+        inner: PostgresQueue::new(pool.clone()),
+        claims: Mutex::default(),
+    });
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    )
+    .with_queue_backend(backend.clone());
+
+    let id = executions.create_execution(task("queued")).await?;
+    let DelegatedAction::ExecuteTask { execution_id, .. } =
+        worker.run_cooperative_worker_loop().await?
+    else {
+        panic!("Expected a task to execute");
+    };
+    assert_eq!(execution_id, id);
+    assert!(worker
+        .claim_executions(None, &["a".to_string(), "b".to_string()], 5)
+        .await?
+        .is_empty());
+
+    assert_eq!(
+        *backend.claims.lock().unwrap(),
+        vec![
+            vec!["default".to_string()],
+            vec!["a".to_string(), "b".to_string()]
+        ]
+    );
+    Ok(())
+}
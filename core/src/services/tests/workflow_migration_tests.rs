@@ -0,0 +1,191 @@
+//! Tests for moving suspended executions to a new workflow definition
+
+use crate::executor::migrate::{MigrationPlan, PointMapping};
+use crate::services::{ExecutionService, WorkerService, WorkflowService};
+use crate::types::ExecutionStatus;
+use crate::worker::{ClaimAuthorizer, DelegatedAction};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+const V1: &str = r#"
+let base = 2
+let result = await Task.run("step", {})
+return base + result
+"#;
+
+const V2: &str = r#"
+let start = 2
+let factor = 1
+let result = await Task.run("step", {})
+return start + result * factor
+"#;
+
+fn plan() -> MigrationPlan {
+    MigrationPlan {
+        points: vec![PointMapping {
+            from_line: 3,
+            to_line: 4,
+            rename: [("start".to_string(), "base".to_string())].into(),
+            set: [("factor".to_string(), json!(10))].into(),
+        }],
+    }
+}
+
+/// Run workflows until a task is handed out
+async fn next_task(worker: &WorkerService) -> anyhow::Result<String> {
+    loop {
+        match worker.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask { execution_id, .. } => return Ok(execution_id),
+            DelegatedAction::Continue => {}
+            _ => panic!("Expected a task to execute"),
+        }
+    }
+}
+
+#[sqlx::test]
+async fn test_migrate_suspended_execution(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    workflows.register_workflow("scaled", V1).await?;
+    let workflow_id = workflows
+        .start_workflow("scaled", json!({}), "default", None)
+        .await?;
+
+    // Runs the workflow until it suspends, then hands out its task
+    let task_id = next_task(&worker).await?;
+
+    // Nothing to move while the execution is on the latest definition
+    assert!(workflows
+        .migrate_executions("scaled", &plan(), false)
+        .await?
+        .is_empty());
+
+    workflows.register_workflow("scaled", V2).await?;
+    let dry_run = workflows
+        .migrate_executions("scaled", &plan(), false)
+        .await?;
+    assert_eq!(dry_run.len(), 1);
+    assert_eq!(dry_run[0].execution_id, workflow_id);
+    assert_eq!(
+        (dry_run[0].from_line, dry_run[0].to_line),
+        (Some(3), Some(4))
+    );
+    assert!(dry_run[0].is_ok(), "{:?}", dry_run[0].error);
+
+    // A dry run writes nothing, so the execution is still listed
+    let applied = workflows
+        .migrate_executions("scaled", &plan(), true)
+        .await?;
+    assert_eq!(applied, dry_run);
+    assert!(workflows
+        .migrate_executions("scaled", &plan(), false)
+        .await?
+        .is_empty());
+
+    // Resumes in the new definition
+    worker.complete_work(&task_id, Some(json!(4)), None).await?;
+    worker.run_cooperative_worker_loop().await?;
+    let workflow = ExecutionService::new(pool.clone())
+        .get_execution(&workflow_id)
+        .await?
+        .unwrap();
+    assert_eq!(workflow.status, ExecutionStatus::Completed);
+    assert_eq!(workflow.output, Some(json!(42.0)));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_migrate_reports_unmapped_points(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    workflows.register_workflow("scaled", V1).await?;
+    workflows
+        .start_workflow("scaled", json!({}), "default", None)
+        .await?;
+    next_task(&worker).await?;
+    workflows.register_workflow("scaled", V2).await?;
+
+    let reports = workflows
+        .migrate_executions("scaled", &MigrationPlan::default(), true)
+        .await?;
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].error.as_deref(), Some("No mapping for line 3"));
+
+    // Left where it was
+    let reports = workflows
+        .migrate_executions("scaled", &MigrationPlan::default(), false)
+        .await?;
+    assert_eq!(reports.len(), 1);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_rewrite_saved_states_in_current_format(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let worker = WorkerService::new(
+        pool.clone(),
+        CancellationToken::new(),
+        ClaimAuthorizer::default(),
+    );
+
+    workflows.register_workflow("scaled", V1).await?;
+    let old_layout = workflows
+        .start_workflow("scaled", json!({}), "default", None)
+        .await?;
+    let broken = workflows
+        .start_workflow("scaled", json!({}), "default", None)
+        .await?;
+    next_task(&worker).await?;
+    next_task(&worker).await?;
+
+    let summary = workflows.rewrite_saved_states(false, 1).await?;
+    assert_eq!((summary.scanned, summary.current), (2, 2));
+
+    // An empty field the current format leaves out, and a state that won't load
+    sqlx::query(
+        "UPDATE workflow_execution_context SET locals = locals || '{\"recorded\": []}' WHERE execution_id = $1",
+    )
+    .bind(&old_layout)
+    .execute(&pool)
+    .await?;
+    sqlx::query(
+        "UPDATE workflow_execution_context SET locals = '{\"frames\": 1}' WHERE execution_id = $1",
+    )
+    .bind(&broken)
+    .execute(&pool)
+    .await?;
+
+    let dry_run = workflows.rewrite_saved_states(false, 1).await?;
+    assert_eq!((dry_run.rewritten, dry_run.current), (1, 0));
+    assert_eq!(dry_run.failed.len(), 1);
+    assert_eq!(dry_run.failed[0].execution_id, broken);
+
+    let applied = workflows.rewrite_saved_states(true, 1).await?;
+    assert_eq!(applied.rewritten, 1);
+    let again = workflows.rewrite_saved_states(true, 1).await?;
+    assert_eq!((again.rewritten, again.current), (0, 1));
+
+    // The rewritten state still resumes
+    let stored: serde_json::Value =
+        sqlx::query_scalar("SELECT locals FROM workflow_execution_context WHERE execution_id = $1")
+            .bind(&old_layout)
+            .fetch_one(&pool)
+            .await?;
+    assert!(stored.get("recorded").is_none());
+    serde_json::from_value::<crate::executor::VM>(stored)?;
+
+    Ok(())
+}
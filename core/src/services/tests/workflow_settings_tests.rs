@@ -0,0 +1,149 @@
+//! Tests for merging `[workflow_defaults]` under workflow front matter, and
+//! for the inputs schema it declares
+
+use crate::errors::ErrorCode;
+use crate::services::{ExecutionService, WorkflowService};
+use serde_json::json;
+use sqlx::PgPool;
+
+const WITH_FRONT_MATTER: &str = r#"
+```
+queue: payments
+validator:
+  unused_variable: off
+```
+return 1
+"#;
+
+fn defaults() -> serde_json::Map<String, serde_json::Value> {
+    match json!({
+        "queue": "default",
+        "retries": 3,
+        "validator": { "unused_variable": "warn", "shadowing": "error" },
+    }) {
+        serde_json::Value::Object(map) => map,
+        _ => unreachable!(),
+    }
+}
+
+#[sqlx::test]
+async fn test_front_matter_merges_over_defaults(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool).with_workflow_defaults(defaults());
+    workflows
+        .register_workflow("charge", WITH_FRONT_MATTER)
+        .await?;
+
+    let config = workflows.effective_config("charge").await?.unwrap();
+    assert_eq!(
+        serde_json::Value::Object(config.settings),
+        json!({
+            "queue": "payments",
+            "retries": 3,
+            "validator": { "unused_variable": "off", "shadowing": "error" },
+        })
+    );
+    assert_eq!(
+        config.from_front_matter,
+        vec!["queue".to_string(), "validator.unused_variable".to_string()]
+    );
+
+    assert!(workflows.effective_config("missing").await?.is_none());
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_invalid_front_matter_is_rejected(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool);
+    let result = workflows
+        .register_workflow("bad", "```\n- not\n- settings\n```\nreturn 1")
+        .await;
+
+    assert!(format!("{:#}", result.unwrap_err()).contains("front matter"));
+    Ok(())
+}
+
+const WITH_INPUTS: &str = r#"
+```
+inputs:
+  order_id: string
+  currency:
+    type: string
+    default: USD
+```
+return Inputs.order_id
+"#;
+
+#[sqlx::test]
+async fn test_start_workflow_checks_inputs_against_schema(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool.clone());
+    let executions = ExecutionService::new(pool);
+    workflows.register_workflow("order", WITH_INPUTS).await?;
+
+    let error = workflows
+        .start_workflow("order", json!({ "order_id": 7 }), "default", None)
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&error), ErrorCode::Validation);
+    assert!(error
+        .to_string()
+        .contains("order_id must be a string, got number"));
+
+    let id = workflows
+        .start_workflow("order", json!({ "order_id": "o1" }), "default", None)
+        .await?;
+    let execution = executions.get_execution(&id).await?.unwrap();
+    assert_eq!(
+        execution.inputs,
+        json!({ "order_id": "o1", "currency": "USD" })
+    );
+
+    let schema = workflows.get_workflow_schema("order").await?.unwrap();
+    assert_eq!(schema.fields.len(), 2);
+    let missing = workflows.get_workflow_schema("missing").await.unwrap_err();
+    assert_eq!(ErrorCode::of(&missing), ErrorCode::NotFound);
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_invalid_inputs_schema_is_rejected(pool: PgPool) -> anyhow::Result<()> {
+    let workflows = WorkflowService::new(pool);
+    let result = workflows
+        .register_workflow("bad", "```\ninputs:\n  id: strin\n```\nreturn 1")
+        .await;
+
+    assert!(format!("{:#}", result.unwrap_err()).contains("unknown type `strin`"));
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_validator_settings_raise_and_silence_rules(pool: PgPool) -> anyhow::Result<()> {
+    let source = "Task.run(\"notify\", {})\nreturn 1";
+    let strict = WorkflowService::new(pool.clone()).with_workflow_defaults(
+        match json!({ "validator": { "unawaited-task": "error" } }) {
+            serde_json::Value::Object(map) => map,
+            _ => unreachable!(),
+        },
+    );
+
+    let err = strict
+        .register_workflow("notify", source)
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorCode::of(&err), ErrorCode::Validation);
+    assert!(err.to_string().contains("[unawaited-task]"));
+
+    // Front matter and inline comments relax the project setting
+    strict
+        .register_workflow(
+            "notify",
+            "```\nvalidator:\n  unawaited_task: warn\n```\nTask.run(\"notify\", {})",
+        )
+        .await?;
+    strict
+        .register_workflow(
+            "notify",
+            "Task.run(\"notify\", {}) // rhythm-ignore: unawaited-task",
+        )
+        .await?;
+    Ok(())
+}
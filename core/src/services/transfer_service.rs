@@ -0,0 +1,161 @@
+//! Transfer Service
+//!
+//! Moves executions between environments, e.g. copying staging data or
+//! attaching a customer's workflows to a support escalation. An export is
+//! NDJSON: a header line, then the workflow definitions the exported
+//! executions run on, then the executions oldest first, each page followed
+//! by the saved states of the workflows in it (see `TransferRecord`).
+//!
+//! Import inserts what the database doesn't have yet, in one transaction,
+//! and queues the executions that were pending or running to run here.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::io::{BufRead, Write};
+
+use crate::db;
+use crate::errors::RhythmError;
+use crate::types::{ExecutionStatus, TransferRecord, TransferSummary};
+
+/// Version of the export format written in the header
+pub const TRANSFER_FORMAT_VERSION: u32 = 1;
+
+/// Executions read per query while exporting
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+/// Service for exporting and importing executions
+#[derive(Clone)]
+pub struct TransferService {
+    pool: PgPool,
+}
+
+impl TransferService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Write the executions created at or after `since` (all of them if
+    /// `None`) to `out`, with their saved states and workflow definitions
+    ///
+    /// A child whose parent was created before `since` is exported without
+    /// its parent, and is imported as a root execution.
+    pub async fn export<W: Write>(
+        &self,
+        since: Option<DateTime<Utc>>,
+        out: &mut W,
+    ) -> Result<TransferSummary> {
+        let mut summary = TransferSummary::default();
+        write_record(
+            out,
+            &TransferRecord::Header {
+                version: TRANSFER_FORMAT_VERSION,
+                exported_at: Utc::now(),
+            },
+        )?;
+
+        for definition in db::transfer::export_definitions(&self.pool, since).await? {
+            write_record(out, &TransferRecord::WorkflowDefinition(definition))?;
+            summary.workflow_definitions += 1;
+        }
+
+        let mut after = None;
+        loop {
+            let page = db::transfer::export_executions(
+                &self.pool,
+                since,
+                after.as_ref(),
+                EXPORT_PAGE_SIZE,
+            )
+            .await?;
+            let Some((created_at, id, _)) = page.last() else {
+                break;
+            };
+            after = Some((*created_at, id.clone()));
+
+            let ids: Vec<String> = page.iter().map(|(_, id, _)| id.clone()).collect();
+            for (_, _, execution) in page {
+                write_record(out, &TransferRecord::Execution(execution))?;
+                summary.executions += 1;
+            }
+            for context in db::transfer::export_contexts(&self.pool, &ids).await? {
+                write_record(out, &TransferRecord::Context(context))?;
+                summary.contexts += 1;
+            }
+        }
+
+        out.flush().context("Failed to write export")?;
+        Ok(summary)
+    }
+
+    /// Insert the records of an export read from `input`
+    ///
+    /// Records already in the database (by execution id, or definition name
+    /// and hash) are skipped, so an export can be imported again. Nothing is
+    /// imported if any line can't be.
+    pub async fn import<R: BufRead>(&self, input: R) -> Result<TransferSummary> {
+        let mut summary = TransferSummary::default();
+        let mut tx = self.pool.begin().await?;
+        let mut has_header = false;
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line.context("Failed to read import")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: TransferRecord = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid record on line {}", index + 1))?;
+
+            match record {
+                TransferRecord::Header { version, .. } => {
+                    if version != TRANSFER_FORMAT_VERSION {
+                        bail!(RhythmError::Validation(format!(
+                            "Unsupported export format version {} (expected {})",
+                            version, TRANSFER_FORMAT_VERSION
+                        )));
+                    }
+                    has_header = true;
+                }
+                _ if !has_header => {
+                    bail!(RhythmError::Validation(
+                        "Not an export: the first line is not a header".to_string()
+                    ));
+                }
+                TransferRecord::WorkflowDefinition(definition) => {
+                    if db::transfer::import_definition(&mut *tx, &definition).await? {
+                        summary.workflow_definitions += 1;
+                    } else {
+                        summary.skipped += 1;
+                    }
+                }
+                TransferRecord::Execution(execution) => {
+                    match db::transfer::import_execution(&mut *tx, &execution).await? {
+                        Some((id, status, queue, priority)) => {
+                            if status == ExecutionStatus::Pending {
+                                db::work_queue::enqueue_work(&mut *tx, &id, &queue, priority)
+                                    .await?;
+                            }
+                            summary.executions += 1;
+                        }
+                        None => summary.skipped += 1,
+                    }
+                }
+                TransferRecord::Context(context) => {
+                    if db::transfer::import_context(&mut *tx, &context).await? {
+                        summary.contexts += 1;
+                    } else {
+                        summary.skipped += 1;
+                    }
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(summary)
+    }
+}
+
+fn write_record<W: Write>(out: &mut W, record: &TransferRecord) -> Result<()> {
+    serde_json::to_writer(&mut *out, record).context("Failed to write export")?;
+    out.write_all(b"\n").context("Failed to write export")
+}
@@ -0,0 +1,242 @@
+//! Webhook Service
+//!
+//! Registers webhooks and sends the deliveries queued for them. A delivery
+//! is a POST of the JSON payload queued when the execution finished, with
+//! headers saying what it is:
+//!
+//! - `X-Rhythm-Event`: `completed`, `failed` or `cancelled`
+//! - `X-Rhythm-Delivery`: the delivery id, the same on every retry
+//! - `X-Rhythm-Timestamp`: Unix seconds when this attempt was signed
+//! - `X-Rhythm-Signature`: `sha256=` and the hex HMAC-SHA256, keyed with the
+//!   webhook's secret, of `<timestamp>.<body>`
+//!
+//! The execution's inputs and output are sent as `get_execution` returns
+//! them, with offloaded payloads read back in and decrypted, as each attempt
+//! is signed. Deliveries keep them as stored, so listing them doesn't.
+//!
+//! Any 2xx response accepts the delivery. Anything else, or no response
+//! within the timeout, is retried with exponential backoff until
+//! `[webhooks] max_attempts` is reached. Deliveries are at least once: an
+//! endpoint should dedupe on `X-Rhythm-Delivery`.
+
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use serde_json::Value as JsonValue;
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+use crate::blob_store::PayloadStore;
+use crate::config::WebhooksConfig;
+use crate::db;
+use crate::db::webhooks::DueDelivery;
+use crate::encryption::Binding;
+use crate::errors::RhythmError;
+use crate::types::{CreateWebhookParams, Webhook, WebhookDelivery};
+
+/// Service for webhooks and their deliveries
+#[derive(Clone)]
+pub struct WebhookService {
+    pool: PgPool,
+    http: reqwest::Client,
+    config: WebhooksConfig,
+    payloads: PayloadStore,
+}
+
+impl WebhookService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            http: reqwest::Client::new(),
+            config: WebhooksConfig::default(),
+            payloads: PayloadStore::default(),
+        }
+    }
+
+    /// Retry and time out deliveries as `config` says
+    pub fn with_config(mut self, config: WebhooksConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Read offloaded payloads back in and decrypt them from `payloads`
+    pub fn with_payloads(mut self, payloads: PayloadStore) -> Self {
+        self.payloads = payloads;
+        self
+    }
+
+    /// Register a webhook; it is notified of executions that finish from now on
+    pub async fn register(&self, params: CreateWebhookParams) -> Result<Webhook> {
+        if !(params.url.starts_with("http://") || params.url.starts_with("https://")) {
+            bail!(RhythmError::Validation(format!(
+                "Webhook URL must be http(s): {}",
+                params.url
+            )));
+        }
+        if params.events.is_empty() {
+            bail!(RhythmError::Validation(
+                "A webhook needs at least one event".to_string()
+            ));
+        }
+        if params.secret.is_empty() {
+            bail!(RhythmError::Validation(
+                "A webhook needs a signing secret".to_string()
+            ));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        db::webhooks::create_webhook(&self.pool, &id, &params).await
+    }
+
+    /// Every registered webhook
+    pub async fn list(&self) -> Result<Vec<Webhook>> {
+        db::webhooks::list_webhooks(&self.pool).await
+    }
+
+    /// Remove a webhook, dropping its undelivered notifications
+    pub async fn remove(&self, id: &str) -> Result<()> {
+        if !db::webhooks::delete_webhook(&self.pool, id).await? {
+            bail!(RhythmError::not_found("Webhook", id));
+        }
+        Ok(())
+    }
+
+    /// A webhook's `limit` most recent deliveries, newest first
+    pub async fn deliveries(&self, webhook_id: &str, limit: i64) -> Result<Vec<WebhookDelivery>> {
+        db::webhooks::list_deliveries(&self.pool, webhook_id, limit).await
+    }
+
+    /// Send a delivery that ran out of attempts again, with fresh attempts
+    pub async fn redeliver(&self, delivery_id: i64) -> Result<()> {
+        if !db::webhooks::redeliver(&self.pool, delivery_id).await? {
+            bail!(RhythmError::Conflict(format!(
+                "Webhook delivery {} is not a failed delivery",
+                delivery_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Send up to `limit` due deliveries at once, returning how many the
+    /// endpoints accepted
+    pub async fn deliver_due(&self, limit: i64) -> Result<usize> {
+        // Held past the timeout, so a slow attempt isn't sent twice
+        let lease = Duration::from_millis(self.config.timeout_ms) * 2;
+        let due =
+            db::webhooks::claim_due_deliveries(&self.pool, limit, lease.as_secs_f64()).await?;
+
+        let mut attempts = JoinSet::new();
+        for delivery in due {
+            let service = self.clone();
+            attempts.spawn(async move { service.attempt(delivery).await });
+        }
+        let mut delivered = 0;
+        while let Some(result) = attempts.join_next().await {
+            if result.context("Webhook delivery panicked")?? {
+                delivered += 1;
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// Post one delivery and record the outcome; returns whether it was
+    /// accepted
+    async fn attempt(&self, delivery: DueDelivery) -> Result<bool> {
+        match self.post(&delivery).await {
+            Ok(()) => {
+                db::webhooks::mark_delivered(&self.pool, delivery.id).await?;
+                Ok(true)
+            }
+            Err(e) => {
+                let attempt = delivery.attempts as u32 + 1;
+                let retry_in = self.retry_delay(attempt);
+                tracing::warn!(
+                    webhook_id = %delivery.webhook_id,
+                    delivery_id = delivery.id,
+                    attempt,
+                    "Webhook delivery failed{}: {:#}",
+                    if retry_in.is_some() { ", will retry" } else { "" },
+                    e
+                );
+                db::webhooks::record_failed_attempt(
+                    &self.pool,
+                    delivery.id,
+                    &format!("{:#}", e),
+                    retry_in,
+                )
+                .await?;
+                Ok(false)
+            }
+        }
+    }
+
+    async fn post(&self, delivery: &DueDelivery) -> Result<()> {
+        let payload = self
+            .resolve(delivery)
+            .await
+            .context("Failed to read the execution's payloads")?;
+        let body = serde_json::to_vec(&payload)?;
+        let timestamp = chrono::Utc::now().timestamp();
+        let response = self
+            .http
+            .post(&delivery.url)
+            .timeout(Duration::from_millis(self.config.timeout_ms))
+            .header("Content-Type", "application/json")
+            .header("X-Rhythm-Event", delivery.event.as_str())
+            .header("X-Rhythm-Delivery", delivery.id.to_string())
+            .header("X-Rhythm-Timestamp", timestamp.to_string())
+            .header(
+                "X-Rhythm-Signature",
+                sign(&delivery.secret, timestamp, &body),
+            )
+            .body(body)
+            .send()
+            .await
+            .context("Request failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("Endpoint responded {}", status);
+        }
+        Ok(())
+    }
+
+    /// `delivery`'s payload with the execution's inputs and output as
+    /// `get_execution` returns them
+    async fn resolve(&self, delivery: &DueDelivery) -> Result<JsonValue> {
+        let mut payload = delivery.payload.clone();
+        let id = delivery.execution_id.as_str();
+        let execution = &mut payload["execution"];
+        for (field, binding) in [
+            ("inputs", Binding::inputs(id)),
+            ("output", Binding::output(id)),
+        ] {
+            let stored = execution[field].take();
+            execution[field] = self.payloads.resolve(stored, binding).await?;
+        }
+        Ok(payload)
+    }
+
+    /// Delay before retrying after the `attempt`th failed attempt, or `None`
+    /// once attempts are used up
+    fn retry_delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt >= self.config.max_attempts {
+            return None;
+        }
+        let base = Duration::from_millis(self.config.base_delay_ms);
+        let max = Duration::from_millis(self.config.max_delay_ms);
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        Some(base.checked_mul(factor).unwrap_or(max).min(max))
+    }
+}
+
+/// The `X-Rhythm-Signature` of a delivery `body` sent at `timestamp`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
@@ -3,23 +3,217 @@ use serde_json::Value as JsonValue;
 use sqlx::PgPool;
 use tokio_util::sync::CancellationToken;
 
-use crate::worker::{self, DelegatedAction};
+use crate::blob_store::PayloadStore;
+use crate::config::{ConcurrencyGroupConfig, StepBudgetConfig, StickyConfig};
+use crate::db;
+use crate::diagnostics::DiagnosticsSampler;
+use crate::encryption::Binding;
+use crate::errors::RhythmError;
+use crate::queue_backend::{PostgresQueue, QueueBackend};
+use crate::queue_limits::QueueLimits;
+use crate::types::{ExecutionOutcome, ExecutionStatus, ExecutionType, GroupOccupancy, LogLevel};
+use crate::worker::host::INTERRUPT_CHECK_INTERVAL;
+use crate::worker::shutdown::InFlightTasks;
+use crate::worker::{
+    self, ClaimAuthorizer, ClaimPolicy, DelegatedAction, HostTask, MiddlewareChain, OutputSchemas,
+    ReapedTasks, ReaperPolicy, RetryRules, RunnerOptions, RunnerRetryPolicy, StickyOptions,
+    TaskCompletion, TaskDispatcher, TaskOutcome, WorkCleanup, Worker, WorkerCounters,
+    WorkerMetrics, WorkerMiddleware,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A task's outcome as `WorkerService::prepare_outcome` stores it
+struct PreparedOutcome {
+    result: Option<JsonValue>,
+    /// Sealed for storage
+    error: Option<JsonValue>,
+    /// The error as reported, for the middleware
+    failure: Option<JsonValue>,
+}
 
 /// Service for worker operations (claiming and completing work)
 #[derive(Clone)]
 pub struct WorkerService {
     pool: PgPool,
+    queue_backend: Arc<dyn QueueBackend>,
     shutdown_token: CancellationToken,
+    authorizer: ClaimAuthorizer,
+    middleware: MiddlewareChain,
+    counters: Arc<WorkerCounters>,
+    in_flight: Arc<InFlightTasks>,
+    cleanup: Option<Arc<WorkCleanup>>,
+    retry_rules: Arc<RetryRules>,
+    output_schemas: Arc<OutputSchemas>,
+    reaper: ReaperPolicy,
+    runner: RunnerOptions,
+    concurrency_groups: Arc<Vec<ConcurrencyGroupConfig>>,
 }
 
 impl WorkerService {
-    pub fn new(pool: PgPool, shutdown_token: CancellationToken) -> Self {
+    pub fn new(
+        pool: PgPool,
+        shutdown_token: CancellationToken,
+        authorizer: ClaimAuthorizer,
+    ) -> Self {
         Self {
+            queue_backend: Arc::new(PostgresQueue::new(pool.clone())),
             pool,
             shutdown_token,
+            authorizer,
+            middleware: MiddlewareChain::default(),
+            counters: Arc::default(),
+            in_flight: Arc::default(),
+            cleanup: None,
+            retry_rules: Arc::default(),
+            output_schemas: Arc::default(),
+            reaper: ReaperPolicy::default(),
+            runner: RunnerOptions::default(),
+            concurrency_groups: Arc::default(),
+        }
+    }
+
+    /// Batch deletes of finished tasks' work queue entries
+    ///
+    /// Completions then skip the delete; pending deletes are flushed on
+    /// shutdown.
+    pub fn with_deferred_cleanup(mut self) -> Self {
+        self.cleanup = Some(Arc::new(WorkCleanup::new(
+            self.pool.clone(),
+            self.shutdown_token.clone(),
+        )));
+        self
+    }
+
+    /// Classify task failures with the given retry rules
+    pub fn with_retry_rules(mut self, rules: RetryRules) -> Self {
+        self.retry_rules = Arc::new(rules);
+        self
+    }
+
+    /// Check task results against their output schemas
+    pub fn with_output_schemas(mut self, schemas: OutputSchemas) -> Self {
+        self.output_schemas = Arc::new(schemas);
+        self
+    }
+
+    /// Back off workflow runs that hit transient errors with the given policy
+    pub fn with_runner_retry(mut self, policy: RunnerRetryPolicy) -> Self {
+        self.runner.retry = policy;
+        self
+    }
+
+    /// Reclaim or fail tasks whose worker went silent according to `policy`
+    pub fn with_reaper(mut self, policy: ReaperPolicy) -> Self {
+        self.reaper = policy;
+        self
+    }
+
+    /// Record verbose diagnostics for the workflow runs `sampler` picks
+    pub fn with_diagnostics(mut self, sampler: DiagnosticsSampler) -> Self {
+        self.runner.diagnostics = sampler;
+        self
+    }
+
+    /// Limit workflow runs to `config.max_steps` VM steps, yielding or
+    /// failing runs that take them all
+    pub fn with_step_budget(mut self, config: StepBudgetConfig) -> Self {
+        self.runner.step_budget = config;
+        self
+    }
+
+    /// Check that each workflow replays the way it ran before resuming it
+    pub fn with_replay_check(mut self) -> Self {
+        self.runner.replay_check = true;
+        self
+    }
+
+    /// Record what every workflow run reads, so any of them can be replayed
+    pub fn with_recorded_runs(mut self) -> Self {
+        self.runner.record_runs = true;
+        self
+    }
+
+    /// Resume the workflows this worker saves here, from VMs kept in memory
+    ///
+    /// Other workers leave a saved workflow to this one for
+    /// `config.timeout_ms` after it is queued again.
+    pub fn with_sticky_workflows(mut self, config: &StickyConfig) -> Self {
+        self.runner.sticky = Some(StickyOptions::new(
+            self.authorizer.identity.id.clone(),
+            config,
+        ));
+        self
+    }
+
+    /// Check results against the payload limits, offloading oversized ones,
+    /// and read offloaded inputs back in for the host
+    pub fn with_payloads(mut self, payloads: PayloadStore) -> Self {
+        self.runner.payloads = payloads;
+        self
+    }
+
+    /// Hold workflows back from starting children on queues over their max
+    /// depth, retrying their runs until there is room
+    pub fn with_queue_limits(mut self, limits: QueueLimits) -> Self {
+        self.runner.queue_limits = limits;
+        self
+    }
+
+    /// Claim work through `backend` instead of straight from Postgres
+    pub fn with_queue_backend(mut self, backend: Arc<dyn QueueBackend>) -> Self {
+        self.queue_backend = backend;
+        self
+    }
+
+    /// Hold back claims of executions whose concurrency group is full
+    pub fn with_concurrency_groups(mut self, groups: Vec<ConcurrencyGroupConfig>) -> Self {
+        self.concurrency_groups = Arc::new(groups);
+        self
+    }
+
+    /// Slots in use and executions waiting in each concurrency group
+    pub async fn group_occupancy(&self) -> Result<Vec<GroupOccupancy>> {
+        db::concurrency_groups::get_group_occupancy(&self.pool, &self.concurrency_groups).await
+    }
+
+    /// Wait for deferred work queue cleanup queued so far
+    pub async fn flush_cleanup(&self) {
+        if let Some(cleanup) = &self.cleanup {
+            cleanup.flush().await;
         }
     }
 
+    /// Replace the claim policy, keeping the worker identity
+    pub fn set_claim_policy(&mut self, policy: Arc<dyn ClaimPolicy>) {
+        self.authorizer.policy = policy;
+    }
+
+    /// Add a middleware around claims and completions
+    pub fn add_middleware(&mut self, middleware: Arc<dyn WorkerMiddleware>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Counters for work run through this service
+    pub fn metrics(&self) -> WorkerMetrics {
+        self.counters.snapshot()
+    }
+
+    /// Handle for draining this worker on shutdown
+    pub fn worker(&self) -> Worker {
+        Worker::new(
+            self.pool.clone(),
+            self.shutdown_token.clone(),
+            self.in_flight.clone(),
+        )
+    }
+
+    /// Identity and policy used when claiming work
+    pub fn authorizer(&self) -> &ClaimAuthorizer {
+        &self.authorizer
+    }
+
     /// Run cooperative worker loop - blocks until task needs host execution
     ///
     /// This method blocks/retries until work is available. When it finds work:
@@ -28,13 +222,332 @@ impl WorkerService {
     ///
     /// Only returns when it has a task that needs to be executed by the host.
     pub async fn run_cooperative_worker_loop(&self) -> Result<DelegatedAction> {
-        worker::run_cooperative_worker_loop(&self.pool, &self.shutdown_token).await
+        self.run_cooperative_worker_loop_on("default").await
+    }
+
+    /// Same as `run_cooperative_worker_loop`, claiming from `queue`
+    pub async fn run_cooperative_worker_loop_on(&self, queue: &str) -> Result<DelegatedAction> {
+        let action = worker::run_cooperative_worker_loop(
+            &self.pool,
+            self.queue_backend.as_ref(),
+            queue,
+            &self.shutdown_token,
+            &self.authorizer,
+            &self.middleware,
+            &self.counters,
+            &self.runner,
+            &self.concurrency_groups,
+        )
+        .await?;
+        if let DelegatedAction::ExecuteTask { execution_id, .. } = &action {
+            self.in_flight.start(execution_id);
+        }
+        Ok(action)
+    }
+
+    /// Claim up to `max_count` executions from `queues` in one round trip
+    ///
+    /// Workflows are run internally; the claimed tasks are returned for the
+    /// host to run and report with `record_outcomes`. `worker_id` defaults
+    /// to this worker's identity. An empty `queues` means "default".
+    pub async fn claim_executions(
+        &self,
+        worker_id: Option<&str>,
+        queues: &[String],
+        max_count: usize,
+    ) -> Result<Vec<HostTask>> {
+        let default_queues = ["default".to_string()];
+        let queues = if queues.is_empty() {
+            &default_queues[..]
+        } else {
+            queues
+        };
+        let tasks = worker::claim_batch(
+            &self.pool,
+            self.queue_backend.as_ref(),
+            queues,
+            max_count,
+            worker_id.unwrap_or(&self.authorizer.identity.id),
+            &self.shutdown_token,
+            &self.authorizer,
+            &self.middleware,
+            &self.counters,
+            &self.runner,
+            &self.concurrency_groups,
+        )
+        .await?;
+        for task in &tasks {
+            self.in_flight.start(&task.execution_id);
+        }
+        Ok(tasks)
+    }
+
+    /// Run a worker until shutdown, with the host only running tasks
+    ///
+    /// Polls `queues` in order, running workflows internally and handing tasks
+    /// to `dispatcher`. Task claims are kept alive with heartbeats while the
+    /// host runs them, and their outcomes recorded as with `complete_work` and
+    /// `fail_work`. Returns on shutdown or when `check_interrupt` fails.
+    pub async fn run_host_loop(
+        &self,
+        queues: &[String],
+        dispatcher: &mut dyn TaskDispatcher,
+    ) -> Result<()> {
+        let default_queues = ["default".to_string()];
+        let queues = if queues.is_empty() {
+            &default_queues[..]
+        } else {
+            queues
+        };
+
+        loop {
+            dispatcher.check_interrupt()?;
+
+            let mut wait = None;
+            for queue in queues {
+                match self.run_cooperative_worker_loop_on(queue).await? {
+                    DelegatedAction::ExecuteTask {
+                        execution_id,
+                        target_name,
+                        inputs,
+                        traceparent,
+                    } => {
+                        self.run_host_task(
+                            dispatcher,
+                            HostTask {
+                                execution_id,
+                                target_name,
+                                inputs,
+                                traceparent,
+                            },
+                        )
+                        .await?;
+                        wait = None;
+                        break;
+                    }
+                    DelegatedAction::Continue => {
+                        wait = None;
+                        break;
+                    }
+                    DelegatedAction::Wait { duration_ms } => {
+                        wait = Some(wait.map_or(duration_ms, |w: u64| w.min(duration_ms)));
+                    }
+                    DelegatedAction::Shutdown => return Ok(()),
+                }
+            }
+
+            if let Some(duration_ms) = wait {
+                let until = tokio::time::Instant::now() + Duration::from_millis(duration_ms);
+                while tokio::time::Instant::now() < until {
+                    tokio::select! {
+                        _ = self.shutdown_token.cancelled() => return Ok(()),
+                        _ = tokio::time::sleep(INTERRUPT_CHECK_INTERVAL) => {}
+                    }
+                    dispatcher.check_interrupt()?;
+                }
+            }
+        }
+    }
+
+    async fn run_host_task(
+        &self,
+        dispatcher: &mut dyn TaskDispatcher,
+        task: HostTask,
+    ) -> Result<()> {
+        let execution_id = task.execution_id.clone();
+        let stop = CancellationToken::new();
+        let heartbeat = worker::host::spawn_heartbeat(
+            self.pool.clone(),
+            execution_id.clone(),
+            self.authorizer.identity.id.clone(),
+            stop.clone(),
+        );
+        let outcome = dispatcher.dispatch(task);
+        stop.cancel();
+        let _ = heartbeat.await;
+
+        self.record_outcome(&execution_id, outcome).await
+    }
+
+    /// Record that a task is still running and extend its claim
+    ///
+    /// `worker_id` defaults to this worker's identity. Returns `false` once
+    /// the task is no longer running under a claim; the host should then
+    /// expect its result to be dropped or superseded.
+    pub async fn heartbeat(&self, execution_id: &str, worker_id: Option<&str>) -> Result<bool> {
+        let worker_id = worker_id.unwrap_or(&self.authorizer.identity.id);
+        worker::host::heartbeat(&self.pool, execution_id, worker_id).await
+    }
+
+    /// Reclaim or fail up to `limit` running tasks that missed their heartbeats
+    pub async fn reap_stale_tasks(&self, limit: i64) -> Result<ReapedTasks> {
+        worker::reaper::reap_stale_tasks(&self.pool, &self.reaper, limit).await
+    }
+
+    /// Record how a task run by the host went
+    pub async fn record_outcome(&self, execution_id: &str, outcome: TaskOutcome) -> Result<()> {
+        match outcome {
+            TaskOutcome::Complete(result) => {
+                self.complete_work(execution_id, Some(result), None).await
+            }
+            TaskOutcome::Fail { error, retry } => {
+                self.fail_work(execution_id, error, retry).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Record the outcomes of several tasks, e.g. from a host's thread pool
+    ///
+    /// Results are checked and stored, and retries decided, for each task as
+    /// by `record_outcome`; then every final outcome is recorded in one
+    /// transaction (`worker::complete_work_batch`). A completion that can't
+    /// be recorded doesn't hold up the rest; the first error is returned
+    /// once all were attempted.
+    pub async fn record_outcomes(&self, completions: Vec<TaskCompletion>) -> Result<()> {
+        let mut errors = Vec::new();
+        let mut outcomes = Vec::new();
+        let mut failures = HashMap::new();
+        // Where each completion was given, so errors come back in order
+        let mut positions = HashMap::new();
+        for (
+            position,
+            TaskCompletion {
+                execution_id,
+                outcome,
+            },
+        ) in completions.into_iter().enumerate()
+        {
+            positions.entry(execution_id.clone()).or_insert(position);
+            let prepared = match outcome {
+                TaskOutcome::Complete(result) => {
+                    self.prepare_outcome(&execution_id, Some(result), None)
+                        .await
+                }
+                TaskOutcome::Fail { error, retry } => {
+                    match worker::complete::retry_failed(
+                        &self.pool,
+                        &execution_id,
+                        &error,
+                        retry,
+                        &self.retry_rules,
+                    )
+                    .await
+                    {
+                        Ok(true) => {
+                            self.in_flight.finish(&execution_id);
+                            continue;
+                        }
+                        Ok(false) => self.prepare_outcome(&execution_id, None, Some(error)).await,
+                        Err(e) => Err(e),
+                    }
+                }
+            };
+            match prepared {
+                Ok(PreparedOutcome {
+                    result: Some(result),
+                    ..
+                }) => outcomes.push((execution_id, ExecutionOutcome::Success(result))),
+                Ok(PreparedOutcome {
+                    error: Some(error),
+                    failure,
+                    ..
+                }) => {
+                    if let Some(failure) = failure {
+                        failures.insert(execution_id.clone(), failure);
+                    }
+                    outcomes.push((execution_id, ExecutionOutcome::Failure(error)));
+                }
+                Ok(_) => unreachable!("a task outcome has a result or an error"),
+                Err(e) => errors.push((execution_id, e)),
+            }
+        }
+
+        let recorded: Vec<String> = outcomes.iter().map(|(id, _)| id.clone()).collect();
+        let rejected =
+            worker::complete_work_batch(&self.pool, outcomes, self.cleanup.as_deref()).await?;
+        for id in &recorded {
+            if rejected.iter().any(|(rejected, _)| rejected == id) {
+                continue;
+            }
+            self.in_flight.finish(id);
+            if let Some(failure) = failures.get(id) {
+                self.middleware.after_fail(id, failure);
+            }
+        }
+        errors.extend(rejected);
+        errors.sort_by_key(|(id, _)| positions.get(id).copied());
+
+        let failed = errors.len();
+        match errors.into_iter().next() {
+            Some((id, e)) => {
+                let e = e.context(format!("Failed to record outcome of {}", id));
+                if failed > 1 {
+                    Err(e.context(format!("{} completions failed", failed)))
+                } else {
+                    Err(e)
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record a partial result of a running task and wake its workflow
+    ///
+    /// Chunks are numbered in emit order and read by the workflow with
+    /// `Task.stream`. A task run again after losing its claim emits its
+    /// chunks again, so consumers should tolerate repeats. Returns the
+    /// chunk's number.
+    pub async fn emit_partial_result(&self, execution_id: &str, chunk: JsonValue) -> Result<i32> {
+        let execution = db::executions::get_execution(&self.pool, execution_id)
+            .await?
+            .ok_or_else(|| RhythmError::not_found("Execution", execution_id))?;
+
+        let mut tx = self.pool.begin().await?;
+        // Checked under the lock, so no chunk lands after the task finishes
+        match db::executions::lock_execution(&mut tx, execution_id).await? {
+            Some((ExecutionType::Task, ExecutionStatus::Running)) => {}
+            _ => {
+                return Err(RhythmError::Conflict(format!(
+                    "Execution {} is not a running task",
+                    execution_id
+                ))
+                .into())
+            }
+        }
+        let seq = db::task_chunks::append_chunk(&mut tx, execution_id, &chunk).await?;
+
+        if let Some(parent_id) = &execution.parent_workflow_id {
+            db::work_queue::enqueue_work(&mut *tx, parent_id, &execution.queue, 0).await?;
+        }
+        tx.commit().await?;
+        Ok(seq)
+    }
+
+    /// Write a message to a running execution's logs, e.g. from a task handler
+    pub async fn append_log(
+        &self,
+        execution_id: &str,
+        level: LogLevel,
+        message: &str,
+        data: Option<JsonValue>,
+    ) -> Result<()> {
+        if db::executions::get_execution(&self.pool, execution_id)
+            .await?
+            .is_none()
+        {
+            return Err(RhythmError::not_found("Execution", execution_id).into());
+        }
+        db::execution_logs::append_log(&self.pool, execution_id, level, message, data.as_ref())
+            .await
     }
 
     /// Complete work after task execution
     ///
     /// Either result OR error should be Some, not both.
-    /// If result is Some, marks the task as completed.
+    /// If result is Some, marks the task as completed, or as failed with
+    /// `INVALID_OUTPUT` if it doesn't fit the task's output schema or
+    /// `PAYLOAD_TOO_LARGE` if it is over the payload limits.
     /// If error is Some, marks the task as failed.
     pub async fn complete_work(
         &self,
@@ -42,6 +555,114 @@ impl WorkerService {
         result: Option<JsonValue>,
         error: Option<JsonValue>,
     ) -> Result<()> {
-        worker::complete_work(&self.pool, execution_id, result, error).await
+        let prepared = self.prepare_outcome(execution_id, result, error).await?;
+        worker::complete_work_with_cleanup(
+            &self.pool,
+            execution_id,
+            prepared.result,
+            prepared.error,
+            self.cleanup.as_deref(),
+        )
+        .await?;
+        self.in_flight.finish(execution_id);
+
+        if let Some(error) = prepared.failure {
+            self.middleware.after_fail(execution_id, &error);
+        }
+        Ok(())
+    }
+
+    /// A task's result or error as it is stored
+    ///
+    /// A result is checked against the task's output schema and offloaded,
+    /// and becomes an error if it fails either; an error is sealed.
+    async fn prepare_outcome(
+        &self,
+        execution_id: &str,
+        result: Option<JsonValue>,
+        error: Option<JsonValue>,
+    ) -> Result<PreparedOutcome> {
+        let (result, error) = match result {
+            Some(result) => match self.check_output(execution_id, result).await? {
+                ExecutionOutcome::Success(result) => (Some(result), error),
+                ExecutionOutcome::Failure(invalid) => (None, Some(invalid)),
+                ExecutionOutcome::Suspended => unreachable!("results never suspend"),
+            },
+            None => (None, error),
+        };
+        let (result, error) = match result {
+            Some(mut result) => {
+                self.middleware.before_complete(execution_id, &mut result);
+                match self
+                    .runner
+                    .payloads
+                    .offload_result(result, "Task result", execution_id)
+                    .await?
+                {
+                    ExecutionOutcome::Success(result) => (Some(result), error),
+                    ExecutionOutcome::Failure(too_large) => (None, Some(too_large)),
+                    ExecutionOutcome::Suspended => unreachable!("results never suspend"),
+                }
+            }
+            None => (None, error),
+        };
+        let failure = error.clone();
+        let error = error
+            .map(|error| {
+                self.runner
+                    .payloads
+                    .seal(error, Binding::output(execution_id))
+            })
+            .transpose()?;
+        Ok(PreparedOutcome {
+            result,
+            error,
+            failure,
+        })
+    }
+
+    /// `result` checked against the output schema of the task it completes
+    async fn check_output(
+        &self,
+        execution_id: &str,
+        result: JsonValue,
+    ) -> Result<ExecutionOutcome> {
+        if self.output_schemas.is_empty() {
+            return Ok(ExecutionOutcome::Success(result));
+        }
+        match db::executions::get_execution(&self.pool, execution_id).await? {
+            Some(execution) if execution.exec_type == ExecutionType::Task => {
+                Ok(self.output_schemas.check(&execution.target_name, result))
+            }
+            _ => Ok(ExecutionOutcome::Success(result)),
+        }
+    }
+
+    /// Report a task failure, retrying it if the retry rules allow
+    ///
+    /// `retry` is the host's own judgement; `[[task_configs]]` rules on the
+    /// error code take precedence. Returns `true` when the task was re-queued.
+    pub async fn fail_work(
+        &self,
+        execution_id: &str,
+        error: JsonValue,
+        retry: bool,
+    ) -> Result<bool> {
+        let retried = worker::fail_work(
+            &self.pool,
+            execution_id,
+            error.clone(),
+            retry,
+            &self.retry_rules,
+            self.cleanup.as_deref(),
+            &self.runner.payloads,
+        )
+        .await?;
+        self.in_flight.finish(execution_id);
+
+        if !retried {
+            self.middleware.after_fail(execution_id, &error);
+        }
+        Ok(retried)
     }
 }
@@ -1,46 +1,494 @@
-use anyhow::Result;
-use serde_json::Value as JsonValue;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
 use sqlx::PgPool;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::blob_store::PayloadStore;
 use crate::db;
-use crate::types::{CreateExecutionParams, Execution, ExecutionFilters, ExecutionType};
+use crate::encryption::Binding;
+use crate::errors::RhythmError;
+use crate::executor::migrate::{migrate_vm, suspension_line, MigrationPlan};
+use crate::executor::{
+    json_to_val_map, val_to_json, Control, Debugger, FrameInfo, Val, WorkflowContext, VM,
+};
+use crate::parser::input_schema::InputSchema;
+use crate::parser::semantic_validator::{Validator, ValidatorConfig};
+use crate::parser::task_graph::{task_graph, TaskGraph};
+use crate::queue_limits::QueueLimits;
+use crate::quotas::QuotaEnforcer;
+use crate::telemetry;
+use crate::types::{
+    ConcurrencyKey, ConcurrencyPolicy, CreateExecutionParams, Execution, ExecutionFilters,
+    ExecutionStatus, ExecutionType, WorkflowSummary,
+};
+use crate::worker::cancel;
+
+/// How often waiting for a workflow's result first checks whether it has
+/// finished
+const RESULT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Longest gap between checks once a wait has gone on for a while
+const MAX_RESULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Error returned when a workflow waited on doesn't finish in time
+///
+/// The workflow keeps running; its result can be fetched by id later.
+/// Adapters can find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncRunTimeout {
+    pub execution_id: String,
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for SyncRunTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Workflow {} did not finish within {:?}",
+            self.execution_id, self.timeout
+        )
+    }
+}
+
+impl std::error::Error for SyncRunTimeout {}
+
+/// Error returned when a workflow waited on fails, expires or is cancelled
+///
+/// Adapters can find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncRunFailed {
+    pub execution_id: String,
+    pub status: ExecutionStatus,
+    /// The workflow's error output
+    pub error: JsonValue,
+}
+
+impl std::fmt::Display for SyncRunFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Workflow {} {}: {}",
+            self.execution_id,
+            self.status.as_str(),
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for SyncRunFailed {}
+
+/// Outcome of moving one suspended execution to a new definition
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MigrationReport {
+    pub execution_id: String,
+    /// Line it is suspended at in its current definition
+    pub from_line: Option<usize>,
+    /// Line it resumes at in the new definition
+    pub to_line: Option<usize>,
+    /// Why it was not migrated
+    pub error: Option<String>,
+}
+
+impl MigrationReport {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A workflow's saved VM state, as shown by `rhythm inspect-vm`
+#[derive(Debug, Clone, Serialize)]
+pub struct VmInspection {
+    pub execution_id: String,
+    pub workflow_name: String,
+    /// Line of the statement the VM is at, 1-based
+    pub line: Option<usize>,
+    /// That line of the workflow's source
+    pub source_line: Option<String>,
+    /// Frames on the VM's stack, innermost last
+    pub frames: Vec<FrameInfo>,
+    /// Variables the workflow has declared that are in scope
+    pub locals: BTreeMap<String, JsonValue>,
+    /// What the workflow is suspended on, as a serialized `Awaitable`
+    pub awaiting: Option<JsonValue>,
+    /// Tasks and workflows it started that haven't finished
+    pub pending: Vec<Execution>,
+}
+
+impl VmInspection {
+    fn new(execution_id: &str, workflow_name: String, source: &str, vm: VM) -> Result<Self> {
+        let awaiting = match &vm.control {
+            Control::Suspend(_) => Some(serde_json::to_value(&vm.control)?["v"].clone()),
+            _ => None,
+        };
+        let debugger = Debugger::new(vm);
+        let line = debugger.current_line();
+        let locals = debugger
+            .locals()
+            .into_iter()
+            .map(|(name, val)| {
+                // Handles and other runtime-only values have no JSON form
+                let json =
+                    val_to_json(val).unwrap_or_else(|_| JsonValue::String(format!("{:?}", val)));
+                (name.to_string(), json)
+            })
+            .collect();
+        Ok(Self {
+            execution_id: execution_id.to_string(),
+            workflow_name,
+            line,
+            source_line: line
+                .and_then(|line| source.lines().nth(line - 1))
+                .map(|text| text.trim_end().to_string()),
+            frames: debugger.frames(),
+            locals,
+            awaiting,
+            pending: Vec::new(),
+        })
+    }
+}
+
+/// A recorded workflow, ready to replay with `rhythm replay`
+#[derive(Debug, Clone)]
+pub struct RecordedRun {
+    /// The workflow, with its inputs and, if it finished, its output
+    pub execution: Execution,
+    pub workflow_name: String,
+    /// Source of the definition its last recorded run ran
+    pub source: String,
+    /// Values it read from outside it, in order
+    pub reads: Vec<Val>,
+    /// Values it was resumed with after each await, in order
+    pub resumes: Vec<Val>,
+}
+
+impl RecordedRun {
+    /// A debugger at the start of the workflow, replaying the recorded values
+    pub fn debugger(&self) -> Result<Debugger> {
+        let workflow = crate::parser::parse_workflow(&self.source).map_err(|e| {
+            anyhow::anyhow!("Failed to parse workflow '{}': {:?}", self.workflow_name, e)
+        })?;
+        let vm = VM::new(
+            workflow.body,
+            json_to_val_map(&self.execution.inputs)?,
+            WorkflowContext {
+                execution_id: self.execution.id.clone(),
+            },
+        );
+        Ok(Debugger::new(vm).replaying(self.reads.clone(), self.resumes.clone()))
+    }
+}
+
+/// Outcome of rewriting saved workflow states in the current format
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StateRewriteSummary {
+    pub scanned: usize,
+    /// Already stored exactly as the current format writes them
+    pub current: usize,
+    /// Rewritten, or that would be without `apply`
+    pub rewritten: usize,
+    /// Saved again by a worker while being rewritten; already current
+    pub skipped: usize,
+    /// States that don't load, with why
+    pub failed: Vec<StateRewriteFailure>,
+}
+
+/// A saved state `rewrite_saved_states` could not load
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StateRewriteFailure {
+    pub execution_id: String,
+    pub error: String,
+}
+
+/// A workflow's resolved settings, as shown by `rhythm workflow effective-config`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveConfig {
+    pub name: String,
+    /// Front matter merged over `[workflow_defaults]` at registration
+    pub settings: Map<String, JsonValue>,
+    /// Dotted keys the workflow's own front matter sets; the rest are defaults
+    pub from_front_matter: Vec<String>,
+}
 
 /// Service for workflow operations
 #[derive(Clone)]
 pub struct WorkflowService {
     pool: PgPool,
+    quotas: Arc<QuotaEnforcer>,
+    queue_limits: QueueLimits,
+    defaults: Map<String, JsonValue>,
+    payloads: PayloadStore,
 }
 
 impl WorkflowService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            quotas: Arc::default(),
+            queue_limits: QueueLimits::default(),
+            defaults: Map::new(),
+            payloads: PayloadStore::default(),
+        }
+    }
+
+    /// Merge these settings under each registered workflow's front matter
+    pub fn with_workflow_defaults(mut self, defaults: Map<String, JsonValue>) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Enforce the given namespace quotas on creation
+    pub fn with_quotas(mut self, quotas: Arc<QuotaEnforcer>) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Hold back starts on queues that are over their max depth
+    pub fn with_queue_limits(mut self, limits: QueueLimits) -> Self {
+        self.queue_limits = limits;
+        self
+    }
+
+    /// Check inputs against the payload limits, offloading oversized ones
+    pub fn with_payloads(mut self, payloads: PayloadStore) -> Self {
+        self.payloads = payloads;
+        self
     }
 
     /// Start a workflow execution
+    ///
+    /// Fails with `QuotaExceeded` if the namespace is over one of its quotas,
+    /// `QueueFull` if the queue stays full for the configured wait, or
+    /// `Validation` if the inputs don't fit the workflow's inputs schema.
     pub async fn start_workflow(
         &self,
         workflow_name: &str,
         inputs: JsonValue,
         queue: &str,
+        namespace: Option<&str>,
+    ) -> Result<String> {
+        self.start(workflow_name, inputs, queue, namespace, None, false)
+            .await
+    }
+
+    /// Start a workflow execution that holds `concurrency`'s key
+    ///
+    /// While another unfinished workflow holds the key, the start is
+    /// rejected with `Conflict`, queued behind it, or replaces it and the
+    /// workflows queued for the key, as `concurrency.policy` says. A queued
+    /// workflow is claimed once the key passes to it.
+    pub async fn start_workflow_with_concurrency_key(
+        &self,
+        workflow_name: &str,
+        inputs: JsonValue,
+        queue: &str,
+        namespace: Option<&str>,
+        concurrency: &ConcurrencyKey,
+    ) -> Result<String> {
+        self.start(
+            workflow_name,
+            inputs,
+            queue,
+            namespace,
+            Some(concurrency),
+            false,
+        )
+        .await
+    }
+
+    /// Run a workflow in the express lane and wait for its output
+    ///
+    /// The workflow and everything it starts are claimed ahead of normal
+    /// work. Fails with `SyncRunFailed` if the workflow fails or expires,
+    /// and with `SyncRunTimeout` if it hasn't finished within `timeout`;
+    /// it keeps running in that case.
+    pub async fn run_workflow_sync(
+        &self,
+        workflow_name: &str,
+        inputs: JsonValue,
+        queue: &str,
+        namespace: Option<&str>,
+        timeout: Duration,
+    ) -> Result<JsonValue> {
+        let execution_id = self
+            .start(workflow_name, inputs, queue, namespace, None, true)
+            .await?;
+        let output = self
+            .get_workflow_result(&execution_id, true, timeout)
+            .await?;
+        Ok(output.unwrap_or(JsonValue::Null))
+    }
+
+    /// Output of a finished workflow, waiting up to `timeout` for it to
+    /// finish if `wait` is set
+    ///
+    /// Returns `None` for a workflow that hasn't finished when `wait` is
+    /// not set. Fails with `SyncRunFailed` if the workflow failed, expired or
+    /// was cancelled, with `SyncRunTimeout` if it is still running when
+    /// `timeout` is up, and with `NotFound` if there is no such execution.
+    /// Waiting polls the execution, every 20ms at first and less often the
+    /// longer it goes on.
+    pub async fn get_workflow_result(
+        &self,
+        execution_id: &str,
+        wait: bool,
+        timeout: Duration,
+    ) -> Result<Option<JsonValue>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut interval = RESULT_POLL_INTERVAL;
+
+        loop {
+            let execution = db::executions::get_execution(&self.pool, execution_id)
+                .await?
+                .ok_or_else(|| RhythmError::not_found("Execution", execution_id))?;
+            match execution.status {
+                ExecutionStatus::Completed => {
+                    let output = execution.output.unwrap_or(JsonValue::Null);
+                    let binding = Binding::output(&execution.id);
+                    return Ok(Some(self.payloads.resolve(output, binding).await?));
+                }
+                ExecutionStatus::Failed | ExecutionStatus::Expired | ExecutionStatus::Cancelled => {
+                    let error = self
+                        .payloads
+                        .resolve(
+                            execution.output.unwrap_or(JsonValue::Null),
+                            Binding::output(&execution.id),
+                        )
+                        .await?;
+                    return Err(SyncRunFailed {
+                        execution_id: execution.id,
+                        status: execution.status,
+                        error,
+                    }
+                    .into());
+                }
+                _ => {}
+            }
+            if !wait {
+                return Ok(None);
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(SyncRunTimeout {
+                    execution_id: execution.id,
+                    timeout,
+                }
+                .into());
+            }
+            tokio::time::sleep(interval.min(deadline - now)).await;
+            interval = (interval * 2).min(MAX_RESULT_POLL_INTERVAL);
+        }
+    }
+
+    async fn start(
+        &self,
+        workflow_name: &str,
+        inputs: JsonValue,
+        queue: &str,
+        namespace: Option<&str>,
+        concurrency: Option<&ConcurrencyKey>,
+        express: bool,
+    ) -> Result<String> {
+        let inputs = self.check_inputs(workflow_name, inputs).await?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let inputs = self
+            .payloads
+            .offload(inputs, "Execution inputs", Binding::inputs(&id))
+            .await?;
+        self.queue_limits
+            .wait_for_room(|| {
+                self.insert(
+                    &id,
+                    workflow_name,
+                    inputs.clone(),
+                    queue,
+                    namespace,
+                    concurrency,
+                    express,
+                )
+            })
+            .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert(
+        &self,
+        id: &str,
+        workflow_name: &str,
+        inputs: JsonValue,
+        queue: &str,
+        namespace: Option<&str>,
+        concurrency: Option<&ConcurrencyKey>,
+        express: bool,
     ) -> Result<String> {
         let mut tx = self.pool.begin().await?;
 
+        let mut waiting = false;
+        if let Some(concurrency) = concurrency {
+            let key = &concurrency.key;
+            db::executions::lock_concurrency_key(&mut tx, key).await?;
+            if let Some(holder) = db::executions::concurrency_key_holder(&mut *tx, key).await? {
+                match concurrency.policy {
+                    ConcurrencyPolicy::Reject => {
+                        return Err(RhythmError::Conflict(format!(
+                            "Workflow {} is already running with concurrency key '{}'",
+                            holder, key
+                        ))
+                        .into())
+                    }
+                    ConcurrencyPolicy::Queue => waiting = true,
+                    ConcurrencyPolicy::ReplaceExisting => {
+                        // Waiters first, so the key isn't passed on to one
+                        let reason = "Replaced by a workflow with the same concurrency key";
+                        for waiter in db::executions::concurrency_key_waiters(&mut *tx, key).await?
+                        {
+                            cancel::cancel_in(&mut tx, &waiter, reason, false).await?;
+                        }
+                        cancel::cancel_in(&mut tx, &holder, reason, false).await?;
+                    }
+                }
+            }
+        }
+
+        let mut params = CreateExecutionParams {
+            id: Some(id.to_string()),
+            exec_type: ExecutionType::Workflow,
+            target_name: workflow_name.to_string(),
+            queue: queue.to_string(),
+            inputs,
+            parent_workflow_id: None,
+            namespace: namespace.map(str::to_string),
+            ttl_seconds: None,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            traceparent: telemetry::current_traceparent(),
+            tags: HashMap::new(),
+        };
+        self.quotas.admit(&mut tx, &mut params).await?;
+        // A workflow waiting for its key isn't queued yet
+        if !waiting {
+            self.queue_limits.admit(&mut tx, &[queue]).await?;
+        }
+
         // Create execution record
-        let execution_id = db::executions::create_execution(
-            &mut tx,
-            CreateExecutionParams {
-                id: None,
-                exec_type: ExecutionType::Workflow,
-                target_name: workflow_name.to_string(),
-                queue: queue.to_string(),
-                inputs,
-                parent_workflow_id: None,
-            },
-        )
-        .await?;
+        let execution_id = db::executions::create_execution(&mut tx, params).await?;
+        if express {
+            db::executions::mark_express(&mut *tx, &execution_id).await?;
+        }
+        if let Some(concurrency) = concurrency {
+            db::executions::set_concurrency_key(&mut *tx, &execution_id, &concurrency.key, waiting)
+                .await?;
+        }
 
-        // Enqueue work
-        db::work_queue::enqueue_work(&mut *tx, &execution_id, queue, 0).await?;
+        // Enqueue work, unless waiting for the key to be passed on
+        if !waiting {
+            db::work_queue::enqueue_work(&mut *tx, &execution_id, queue, 0).await?;
+        }
 
         tx.commit().await?;
 
@@ -48,29 +496,115 @@ impl WorkflowService {
     }
 
     /// Register a workflow definition
+    ///
+    /// Fails if the source or its front matter doesn't parse, or breaks a
+    /// validation rule. Source in the legacy syntax is registered translated
+    /// to Flow.
     pub async fn register_workflow(&self, name: &str, source: &str) -> Result<i32> {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
+        let source = &*translate_legacy_source(name, source);
 
         // Parse and validate the workflow source
-        let _ast = crate::parser::parse(source)
-            .map_err(|e| anyhow::anyhow!("Failed to parse workflow '{}': {:?}", name, e))?;
-
-        // Generate version hash
-        let mut hasher = DefaultHasher::new();
-        source.hash(&mut hasher);
-        let version_hash = format!("{:x}", hasher.finish());
+        let workflow = crate::parser::parse_workflow(source).map_err(|e| {
+            RhythmError::Validation(format!("Failed to parse workflow '{}': {:?}", name, e))
+        })?;
+        let settings = resolve_settings(name, &workflow, &self.defaults)?;
+        let validator = validator_for(name, &settings)?;
+        validator
+            .validate(&workflow, source)
+            .map_err(|e| RhythmError::Validation(format!("Invalid workflow '{}': {}", name, e)))?;
+        log_workflow_warnings(name, &validator, &workflow, source);
 
         // Register the workflow definition (stores raw source)
-        db::workflow_definitions::create_workflow_definition(
+        db::workflow_definitions::create_workflow_definition_with_settings(
             &self.pool,
             name,
-            &version_hash,
+            &version_hash(source, &settings),
             source,
+            &JsonValue::Object(settings),
         )
         .await
     }
 
+    /// Get the settings the latest definition of a workflow was registered with
+    pub async fn effective_config(&self, name: &str) -> Result<Option<EffectiveConfig>> {
+        let Some((source, settings)) =
+            db::workflow_definitions::get_workflow_settings(&self.pool, name).await?
+        else {
+            return Ok(None);
+        };
+        let JsonValue::Object(settings) = settings else {
+            anyhow::bail!("Workflow '{}' has malformed settings", name);
+        };
+
+        let workflow = crate::parser::parse_workflow(&source)
+            .map_err(|e| anyhow::anyhow!("Failed to parse workflow '{}': {:?}", name, e))?;
+        let own = match &workflow.front_matter {
+            Some(text) => crate::parser::front_matter::parse(text)?,
+            None => Map::new(),
+        };
+        let from_front_matter = crate::parser::front_matter::flatten(&own)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+
+        Ok(Some(EffectiveConfig {
+            name: name.to_string(),
+            settings,
+            from_front_matter,
+        }))
+    }
+
+    /// The inputs schema of a workflow's latest definition, if it declares one
+    ///
+    /// Fails with `NotFound` if no workflow of that name is registered.
+    pub async fn get_workflow_schema(&self, name: &str) -> Result<Option<InputSchema>> {
+        let Some((_, settings)) =
+            db::workflow_definitions::get_workflow_settings(&self.pool, name).await?
+        else {
+            return Err(RhythmError::not_found("Workflow", name).into());
+        };
+        schema_of(name, &settings)
+    }
+
+    /// The task graph of a workflow's latest definition: the tasks, child
+    /// workflows and signals it may use, its awaits and loops, and the calls
+    /// that may run in parallel (see `parser::task_graph`)
+    ///
+    /// Fails with `NotFound` if no workflow of that name is registered.
+    pub async fn analyze_workflow(&self, name: &str) -> Result<TaskGraph> {
+        let Some((source, _)) =
+            db::workflow_definitions::get_workflow_settings(&self.pool, name).await?
+        else {
+            return Err(RhythmError::not_found("Workflow", name).into());
+        };
+        let workflow = crate::parser::parse_workflow(&source)
+            .map_err(|e| anyhow::anyhow!("Failed to parse workflow '{}': {:?}", name, e))?;
+        Ok(task_graph(&workflow))
+    }
+
+    /// Check inputs against the workflow's schema, filling in defaults
+    ///
+    /// Inputs of a workflow that isn't registered yet, or declares no
+    /// schema, are left as they are.
+    async fn check_inputs(&self, name: &str, inputs: JsonValue) -> Result<JsonValue> {
+        let Some((_, settings)) =
+            db::workflow_definitions::get_workflow_settings(&self.pool, name).await?
+        else {
+            return Ok(inputs);
+        };
+        let Some(schema) = schema_of(name, &settings)? else {
+            return Ok(inputs);
+        };
+        schema.apply(inputs).map_err(|problems| {
+            RhythmError::Validation(format!(
+                "Invalid inputs for workflow '{}': {}",
+                name,
+                problems.join("; ")
+            ))
+            .into()
+        })
+    }
+
     /// Get all child task executions for a workflow
     pub async fn get_workflow_tasks(&self, workflow_id: &str) -> Result<Vec<Execution>> {
         db::executions::query_executions(
@@ -83,6 +617,262 @@ impl WorkflowService {
         .await
     }
 
+    /// Every execution under a workflow, nested workflows' included
+    ///
+    /// Level by level, up to `limit`; group by `parent_workflow_id` to get
+    /// the tree.
+    pub async fn get_workflow_descendants(
+        &self,
+        workflow_id: &str,
+        limit: i64,
+    ) -> Result<Vec<Execution>> {
+        db::executions::get_descendants(&self.pool, workflow_id, limit).await
+    }
+
+    /// What a suspended workflow is waiting on, from its saved VM state
+    ///
+    /// Returns the serialized `Awaitable`, or `None` when the workflow has no
+    /// saved state or is not suspended.
+    pub async fn get_suspension_point(&self, workflow_id: &str) -> Result<Option<JsonValue>> {
+        let Some(context) =
+            db::workflow_execution_context::get_context(&self.pool, workflow_id).await?
+        else {
+            return Ok(None);
+        };
+        let vm = self.payloads.open_state(workflow_id, context.vm_state)?;
+        if !matches!(vm.control, Control::Suspend(_)) {
+            return Ok(None);
+        }
+        let control = serde_json::to_value(&vm.control)?;
+        Ok(Some(control["v"].clone()))
+    }
+
+    /// A workflow's saved VM state: where it is in its source, its frames
+    /// and variables, what it awaits, and the children still running
+    ///
+    /// Returns `None` when the workflow has no saved state.
+    pub async fn inspect_vm(&self, workflow_id: &str) -> Result<Option<VmInspection>> {
+        let Some(context) =
+            db::workflow_execution_context::get_context(&self.pool, workflow_id).await?
+        else {
+            return Ok(None);
+        };
+        let (name, source) = db::workflow_definitions::get_workflow_definition(
+            &self.pool,
+            context.workflow_definition_id,
+        )
+        .await?;
+        let vm = self.payloads.open_state(workflow_id, context.vm_state)?;
+        let mut inspection = VmInspection::new(workflow_id, name, &source, vm)?;
+        inspection.pending = self
+            .get_workflow_tasks(workflow_id)
+            .await?
+            .into_iter()
+            .filter(|child| !child.status.is_terminal())
+            .collect();
+        Ok(Some(inspection))
+    }
+
+    /// What a recorded workflow read while it ran, with offloaded payloads
+    /// read back in
+    ///
+    /// Returns `None` when the workflow isn't recorded. One recorded before
+    /// its first run is replayed on its latest definition.
+    pub async fn get_recording(&self, workflow_id: &str) -> Result<Option<RecordedRun>> {
+        let Some(recording) =
+            db::execution_recordings::get_recording(&self.pool, workflow_id).await?
+        else {
+            return Ok(None);
+        };
+        let mut execution = db::executions::get_execution(&self.pool, workflow_id)
+            .await?
+            .ok_or_else(|| RhythmError::not_found("Execution", workflow_id))?;
+        execution.inputs = self
+            .payloads
+            .resolve(execution.inputs, Binding::inputs(workflow_id))
+            .await?;
+        if let Some(output) = execution.output.take() {
+            let binding = Binding::output(workflow_id);
+            execution.output = Some(self.payloads.resolve(output, binding).await?);
+        }
+
+        let (workflow_name, source) = match recording.workflow_definition_id {
+            Some(id) => db::workflow_definitions::get_workflow_definition(&self.pool, id).await?,
+            None => {
+                let (_, source) = db::workflow_definitions::get_workflow_by_name(
+                    &self.pool,
+                    &execution.target_name,
+                )
+                .await?;
+                (execution.target_name.clone(), source)
+            }
+        };
+        let mut resumes = Vec::with_capacity(recording.resumes.len());
+        for value in recording.resumes {
+            resumes.push(self.payloads.resolve_val(value).await?);
+        }
+        Ok(Some(RecordedRun {
+            execution,
+            workflow_name,
+            source,
+            reads: recording.reads,
+            resumes,
+        }))
+    }
+
+    /// Move suspended executions of a workflow to its latest definition
+    ///
+    /// Each execution is rebuilt at the point `plan` maps its suspension
+    /// point to (see `executor::migrate`). With `apply` false nothing is
+    /// written, so the report shows what a real run would do. Executions
+    /// already on the latest definition are left out of the report.
+    pub async fn migrate_executions(
+        &self,
+        workflow_name: &str,
+        plan: &MigrationPlan,
+        apply: bool,
+    ) -> Result<Vec<MigrationReport>> {
+        let (definition_id, source) =
+            db::workflow_definitions::get_workflow_by_name(&self.pool, workflow_name).await?;
+        let program = crate::parser::parse_workflow(&source)
+            .map_err(|e| anyhow::anyhow!("Failed to parse workflow '{}': {:?}", workflow_name, e))?
+            .body;
+
+        let contexts =
+            db::workflow_execution_context::list_suspended_contexts(&self.pool, workflow_name)
+                .await?;
+        let mut reports = Vec::new();
+        for context in contexts {
+            if context.workflow_definition_id == definition_id {
+                continue;
+            }
+            let mut report = MigrationReport {
+                execution_id: context.execution_id.clone(),
+                from_line: None,
+                to_line: None,
+                error: None,
+            };
+
+            let id = context.execution_id.as_str();
+            let migrated = self
+                .payloads
+                .open_state(id, context.vm_state)
+                .and_then(|vm| {
+                    report.from_line = suspension_line(&vm);
+                    let point = report
+                        .from_line
+                        .and_then(|line| plan.point(line))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "No mapping for line {}",
+                                report.from_line.map_or("?".to_string(), |l| l.to_string())
+                            )
+                        })?;
+                    report.to_line = Some(point.to_line);
+                    let vm = migrate_vm(&vm, &program, point)?;
+                    // The worker must be able to load what we store
+                    serde_json::from_value::<VM>(serde_json::to_value(&vm)?)?;
+                    self.payloads.seal_state(id, &vm)
+                });
+
+            match migrated {
+                Ok(state) if apply => {
+                    let replaced = db::workflow_execution_context::replace_suspended_context(
+                        &self.pool,
+                        &context.execution_id,
+                        context.updated_at,
+                        definition_id,
+                        &state,
+                    )
+                    .await?;
+                    if !replaced {
+                        report.error = Some("resumed before it could be migrated".to_string());
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => report.error = Some(format!("{:#}", e)),
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    /// Rewrite every saved workflow state in the format this version writes
+    ///
+    /// States saved by older versions load through compatibility defaults;
+    /// once all of them have been rewritten, a release can stop reading the
+    /// older layouts. Works through the table `batch_size` rows at a time.
+    /// With `apply` false nothing is written. A state a worker saves again
+    /// meanwhile is left alone, since the worker wrote the current format.
+    /// States not encrypted with the active encryption key, or not in the
+    /// configured `state_encoding`, are rewritten too, so this also finishes
+    /// a key rotation or a switch between JSON and binary states. A state
+    /// that can't be rewritten, e.g. because it is over `max_state_bytes`,
+    /// is reported as a failure.
+    pub async fn rewrite_saved_states(
+        &self,
+        apply: bool,
+        batch_size: i64,
+    ) -> Result<StateRewriteSummary> {
+        let mut summary = StateRewriteSummary::default();
+        let mut after: Option<String> = None;
+        loop {
+            let contexts = db::workflow_execution_context::list_contexts_page(
+                &self.pool,
+                after.as_deref(),
+                batch_size,
+            )
+            .await?;
+            let Some(last) = contexts.last() else {
+                break;
+            };
+            after = Some(last.execution_id.clone());
+
+            for context in contexts {
+                summary.scanned += 1;
+                let id = context.execution_id.as_str();
+                let state = self
+                    .payloads
+                    .open_state(id, context.vm_state.clone())
+                    .and_then(|vm| {
+                        if self.payloads.is_current_state(id, &context.vm_state, &vm)? {
+                            return Ok(None);
+                        }
+                        self.payloads.seal_state(id, &vm).map(Some)
+                    });
+                let state = match state {
+                    Ok(Some(state)) => state,
+                    Ok(None) => {
+                        summary.current += 1;
+                        continue;
+                    }
+                    Err(e) => {
+                        summary.failed.push(StateRewriteFailure {
+                            execution_id: context.execution_id,
+                            error: e.to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+                let replaced = !apply
+                    || db::workflow_execution_context::replace_context_state(
+                        &self.pool,
+                        &context.execution_id,
+                        context.updated_at,
+                        &state,
+                    )
+                    .await?;
+                if replaced {
+                    summary.rewritten += 1;
+                } else {
+                    summary.skipped += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
     /// Get workflow definition by name
     pub async fn get_workflow_definition(&self, name: &str) -> Result<Option<String>> {
         match db::workflow_definitions::get_workflow_by_name(&self.pool, name).await {
@@ -90,4 +880,110 @@ impl WorkflowService {
             Err(_) => Ok(None),
         }
     }
+
+    /// The latest definition of every registered workflow
+    pub async fn list_workflows(&self) -> Result<Vec<WorkflowSummary>> {
+        db::workflow_definitions::list_workflows(&self.pool).await
+    }
+}
+
+fn schema_of(name: &str, settings: &JsonValue) -> Result<Option<InputSchema>> {
+    let JsonValue::Object(settings) = settings else {
+        anyhow::bail!("Workflow '{}' has malformed settings", name);
+    };
+    InputSchema::from_settings(settings)
+}
+
+/// Merge a workflow's front matter over the configured defaults
+pub(crate) fn resolve_settings(
+    name: &str,
+    workflow: &crate::parser::WorkflowDef,
+    defaults: &Map<String, JsonValue>,
+) -> Result<Map<String, JsonValue>> {
+    let settings = crate::parser::front_matter::effective_settings(workflow, defaults)
+        .with_context(|| format!("Failed to read front matter of workflow '{}'", name))?;
+    InputSchema::from_settings(&settings)
+        .with_context(|| format!("Failed to read front matter of workflow '{}'", name))?;
+    Ok(settings)
+}
+
+/// The validator for a workflow, with the rule levels in its settings
+pub(crate) fn validator_for(name: &str, settings: &Map<String, JsonValue>) -> Result<Validator> {
+    let config = ValidatorConfig::from_settings(settings)
+        .with_context(|| format!("Failed to read front matter of workflow '{}'", name))?;
+    Ok(Validator::new().with_config(config))
+}
+
+/// Version hash of a definition
+///
+/// Settings only count when there are any, so definitions registered
+/// without defaults or front matter keep their hash.
+pub(crate) fn version_hash(source: &str, settings: &Map<String, JsonValue>) -> String {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    if !settings.is_empty() {
+        JsonValue::Object(settings.clone())
+            .to_string()
+            .hash(&mut hasher);
+    }
+    format!("{:x}", hasher.finish())
+}
+
+/// Rewrite a workflow in the legacy syntax as Flow source
+///
+/// Logs a deprecation warning naming what could not be translated. Flow
+/// source is returned as is.
+pub(crate) fn translate_legacy_source<'a>(name: &str, source: &'a str) -> Cow<'a, str> {
+    let Some(translation) = crate::parser::legacy::translate(source) else {
+        return Cow::Borrowed(source);
+    };
+    let unsupported: Vec<String> = translation
+        .unsupported
+        .iter()
+        .map(|u| format!("{} (line {})", u.construct, u.line + 1))
+        .collect();
+    if unsupported.is_empty() {
+        tracing::warn!(
+            workflow = name,
+            "{}: legacy `workflow(ctx, inputs)` syntax is deprecated; registered as translated to Flow",
+            name
+        );
+    } else {
+        tracing::warn!(
+            workflow = name,
+            "{}: legacy `workflow(ctx, inputs)` syntax is deprecated; registered as translated to Flow, leaving unsupported: {}",
+            name,
+            unsupported.join(", ")
+        );
+    }
+    Cow::Owned(translation.source)
+}
+
+/// Log advisory warnings (type mismatches etc.) found in a workflow
+///
+/// Warnings never block registration.
+pub(crate) fn log_workflow_warnings(
+    name: &str,
+    validator: &Validator,
+    workflow: &crate::parser::WorkflowDef,
+    source: &str,
+) {
+    for warning in validator
+        .check(workflow, source)
+        .into_iter()
+        .filter(|d| d.severity == crate::parser::analysis::Severity::Warning)
+    {
+        tracing::warn!(
+            workflow = name,
+            code = warning.code,
+            "{}:{}:{}: {}",
+            name,
+            warning.span.start_line + 1,
+            warning.span.start_col + 1,
+            warning.message
+        );
+    }
 }
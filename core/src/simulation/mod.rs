@@ -0,0 +1,409 @@
+//! Simulation harness for soak testing
+//!
+//! `run` drives an `Application` through a randomized workload: workflows
+//! that fan out tasks, wait on signals and start child workflows, worked by
+//! a simulated worker that fails tasks and crashes at random, while some
+//! workflows are cancelled part way through. A crashed
+//! worker abandons its claim, the lease is ended early so the work is
+//! claimed again, and the dead worker may later wake up and report a result
+//! for a task that was re-run. Once the workload drains, the global
+//! invariants are checked:
+//!
+//! - no lost executions: every execution reached a final state
+//! - no double completion: each task was completed at most once, with the
+//!   output of the first report that was accepted
+//!
+//! The random choices are made from the seed, so a failing seed can be
+//! replayed. `rhythm simulate` runs a simulation in a temporary database
+//! and exits non-zero on a violation, for nightly soak runs.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::application::Application;
+use crate::db;
+use crate::invariants::InvariantViolation;
+use crate::types::{ExecutionFilters, ExecutionStatus, ExecutionType};
+use crate::worker::{DelegatedAction, TaskOutcome};
+
+/// Workflows the workload starts, registered by `run`
+const WORKFLOWS: &[(&str, &str)] = &[
+    (
+        "sim_fan_out",
+        r#"
+let tasks = []
+for (let i of Inputs.items) {
+    tasks = tasks.concat([Task.run("sim_task", { i: i })])
+}
+return await Promise.all(tasks)
+"#,
+    ),
+    (
+        "sim_signal",
+        r#"
+let message = await Signal.next("go")
+return await Task.run("sim_task", { message: message })
+"#,
+    ),
+    (
+        "sim_parent",
+        r#"
+let first = await Workflow.run("sim_fan_out", Inputs)
+let second = await Task.run("sim_task", { first: first })
+return { first: first, second: second }
+"#,
+    ),
+];
+
+/// Shape of a simulated workload
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationConfig {
+    pub seed: u64,
+    /// Workflows to start
+    pub workflows: usize,
+    /// Most tasks a fan-out starts
+    pub max_fan_out: usize,
+    /// Chance a task run fails
+    pub failure_rate: f64,
+    /// Chance the worker crashes on a claim
+    pub crash_rate: f64,
+    /// Chance a started workflow is cancelled at each step
+    pub cancel_rate: f64,
+    /// Give up on the workload draining after this long
+    pub drain_timeout: Duration,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            workflows: 50,
+            max_fan_out: 5,
+            failure_rate: 0.1,
+            crash_rate: 0.05,
+            cancel_rate: 0.02,
+            drain_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+/// What a simulation did and which invariants it broke
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SimulationReport {
+    pub seed: u64,
+    pub workflows_started: usize,
+    pub tasks_run: usize,
+    pub tasks_failed: usize,
+    pub crashes: usize,
+    /// Reports from crashed workers for tasks that were run again
+    pub stale_reports: usize,
+    /// Stale reports that were accepted because they came first
+    pub stale_reports_accepted: usize,
+    pub signals_sent: usize,
+    pub workflows_cancelled: usize,
+    pub violations: Vec<String>,
+}
+
+impl SimulationReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Small deterministic generator (splitmix64), so runs replay from a seed
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// True with probability `p`
+    fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// Uniform in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+/// A crashed worker's result for a task, which it may still report
+struct Zombie {
+    execution_id: String,
+    output: JsonValue,
+}
+
+/// Run a simulated workload against `app` and check the invariants
+///
+/// The database should hold nothing but what the simulation creates.
+pub async fn run(app: &Application, config: &SimulationConfig) -> Result<SimulationReport> {
+    let mut rng = Rng(config.seed);
+    let mut report = SimulationReport {
+        seed: config.seed,
+        ..Default::default()
+    };
+
+    for (name, source) in WORKFLOWS {
+        app.workflow_service
+            .register_workflow(name, source)
+            .await
+            .with_context(|| format!("Failed to register {}", name))?;
+    }
+
+    let mut started = Vec::new();
+    let mut awaiting_signal = Vec::new();
+    for _ in 0..config.workflows {
+        let (name, _) = WORKFLOWS[rng.below(WORKFLOWS.len())];
+        let items: Vec<usize> = (0..=rng.below(config.max_fan_out)).collect();
+        let id = app
+            .workflow_service
+            .start_workflow(name, json!({ "items": items }), "default", None)
+            .await?;
+        if name == "sim_signal" {
+            awaiting_signal.push(id.clone());
+        }
+        started.push(id);
+        report.workflows_started += 1;
+    }
+
+    // Outputs of accepted completions, by task
+    let mut completions: HashMap<String, Vec<JsonValue>> = HashMap::new();
+    let mut zombies: Vec<Zombie> = Vec::new();
+    let deadline = tokio::time::Instant::now() + config.drain_timeout;
+
+    loop {
+        if !awaiting_signal.is_empty() && rng.chance(0.2) {
+            let id = awaiting_signal.swap_remove(rng.below(awaiting_signal.len()));
+            app.signal_service
+                .send_signal(&id, "go", json!({ "seq": report.signals_sent }), "default")
+                .await?;
+            report.signals_sent += 1;
+        }
+
+        if !started.is_empty() && rng.chance(config.cancel_rate) {
+            let id = started.swap_remove(rng.below(started.len()));
+            let finish_running_tasks = rng.chance(0.5);
+            if app
+                .execution_service
+                .cancel_workflow(&id, "simulated cancellation", finish_running_tasks)
+                .await?
+            {
+                report.workflows_cancelled += 1;
+            }
+            awaiting_signal.retain(|waiting| *waiting != id);
+        }
+
+        if !zombies.is_empty() && rng.chance(0.2) {
+            let zombie = zombies.swap_remove(rng.below(zombies.len()));
+            report_stale(app, zombie, &mut completions, &mut report).await?;
+        }
+
+        // The worker dies right after claiming, whatever it claimed
+        if rng.chance(config.crash_rate) {
+            if let Some(id) = db::work_queue::claim_work(&app.pool, "default", 1, None)
+                .await?
+                .pop()
+            {
+                db::work_queue::expire_claim(&app.pool, &id).await?;
+                report.crashes += 1;
+            }
+            continue;
+        }
+
+        match app.worker_service.run_cooperative_worker_loop().await? {
+            DelegatedAction::ExecuteTask {
+                execution_id,
+                inputs,
+                ..
+            } => {
+                report.tasks_run += 1;
+                let output = json!({ "inputs": inputs, "run": report.tasks_run });
+                if rng.chance(config.crash_rate) {
+                    // Dies mid-task; the result may still be reported later
+                    db::work_queue::expire_claim(&app.pool, &execution_id).await?;
+                    report.crashes += 1;
+                    zombies.push(Zombie {
+                        execution_id,
+                        output,
+                    });
+                } else if rng.chance(config.failure_rate) {
+                    report.tasks_failed += 1;
+                    let outcome = TaskOutcome::Fail {
+                        error: json!({ "code": "SIM_FAILURE", "message": "injected" }),
+                        retry: rng.chance(0.5),
+                    };
+                    record(app, &execution_id, outcome).await?;
+                } else {
+                    let outcome = TaskOutcome::Complete(output.clone());
+                    if record(app, &execution_id, outcome).await? {
+                        completions.entry(execution_id).or_default().push(output);
+                    }
+                }
+            }
+            DelegatedAction::Continue => {}
+            DelegatedAction::Wait { duration_ms } => {
+                app.scheduler_service.process_ready_items(100).await?;
+                if awaiting_signal.is_empty() && unfinished(&app.pool).await? == 0 {
+                    break;
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(duration_ms.min(50))).await;
+            }
+            DelegatedAction::Shutdown => break,
+        }
+    }
+
+    // Crashed workers that never woke up before the end report now
+    for zombie in zombies {
+        report_stale(app, zombie, &mut completions, &mut report).await?;
+    }
+
+    check_invariants(&app.pool, &completions, &mut report).await?;
+    Ok(report)
+}
+
+/// Record a task's outcome, or `false` if it was refused as too late
+///
+/// A task that already finished refuses further outcomes with an
+/// `InvariantViolation`, except a cancelled one, which drops them; any
+/// other error ends the simulation.
+async fn record(app: &Application, execution_id: &str, outcome: TaskOutcome) -> Result<bool> {
+    match app
+        .worker_service
+        .record_outcome(execution_id, outcome)
+        .await
+    {
+        Ok(()) => Ok(true),
+        Err(e) if e.downcast_ref::<InvariantViolation>().is_some() => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Report a crashed worker's result, as it would on waking up
+async fn report_stale(
+    app: &Application,
+    zombie: Zombie,
+    completions: &mut HashMap<String, Vec<JsonValue>>,
+    report: &mut SimulationReport,
+) -> Result<()> {
+    report.stale_reports += 1;
+    let outcome = TaskOutcome::Complete(zombie.output.clone());
+    if record(app, &zombie.execution_id, outcome).await? {
+        report.stale_reports_accepted += 1;
+        completions
+            .entry(zombie.execution_id)
+            .or_default()
+            .push(zombie.output);
+    }
+    Ok(())
+}
+
+async fn unfinished(pool: &PgPool) -> Result<i64> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM executions WHERE status NOT IN ('completed', 'failed', 'expired', 'cancelled')",
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count unfinished executions")
+}
+
+async fn check_invariants(
+    pool: &PgPool,
+    completions: &HashMap<String, Vec<JsonValue>>,
+    report: &mut SimulationReport,
+) -> Result<()> {
+    let executions = db::executions::query_executions(pool, ExecutionFilters::default()).await?;
+
+    for execution in &executions {
+        if !execution.status.is_terminal() {
+            report.violations.push(format!(
+                "lost execution: {} {} is still {}",
+                execution.target_name,
+                execution.id,
+                execution.status.as_str()
+            ));
+        }
+        // Results reported for a cancelled task were dropped
+        if execution.exec_type != ExecutionType::Task
+            || execution.status == ExecutionStatus::Cancelled
+        {
+            continue;
+        }
+        match completions.get(&execution.id).map(Vec::as_slice) {
+            None | Some([]) => {}
+            Some([first]) => {
+                if execution.output.as_ref() != Some(first) {
+                    report.violations.push(format!(
+                        "task {} has output {:?}, not that of its accepted completion",
+                        execution.id, execution.output
+                    ));
+                }
+            }
+            Some(outputs) => report.violations.push(format!(
+                "double completion: task {} was completed {} times",
+                execution.id,
+                outputs.len()
+            )),
+        }
+    }
+    Ok(())
+}
+
+/// A database created for one simulation and dropped after it
+pub struct TempDatabase {
+    admin: PgPool,
+    name: String,
+    pub pool: PgPool,
+}
+
+impl TempDatabase {
+    /// Create an empty database on the server `url` points at
+    pub async fn create(url: &str) -> Result<Self> {
+        let options = PgConnectOptions::from_str(url).context("Invalid database URL")?;
+        let admin = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(options.clone())
+            .await
+            .context("Failed to connect to database")?;
+
+        let name = format!("rhythm_sim_{}", uuid::Uuid::new_v4().simple());
+        sqlx::query(&format!("CREATE DATABASE {}", name))
+            .execute(&admin)
+            .await
+            .with_context(|| format!("Failed to create database {}", name))?;
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options.database(&name))
+            .await
+            .with_context(|| format!("Failed to connect to database {}", name))?;
+        Ok(Self { admin, name, pool })
+    }
+
+    /// Close the pool and drop the database
+    pub async fn drop_database(self) -> Result<()> {
+        self.pool.close().await;
+        sqlx::query(&format!("DROP DATABASE IF EXISTS {}", self.name))
+            .execute(&self.admin)
+            .await
+            .with_context(|| format!("Failed to drop database {}", self.name))?;
+        Ok(())
+    }
+}
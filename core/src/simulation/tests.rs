@@ -0,0 +1,62 @@
+use super::*;
+use crate::config::Config;
+
+fn config() -> Config {
+    Config {
+        database: Default::default(),
+        worker: Default::default(),
+        claim_policy: Default::default(),
+        quotas: Default::default(),
+        slos: Vec::new(),
+        task_configs: Vec::new(),
+        diagnostics: Default::default(),
+        concurrency_groups: Vec::new(),
+        workflow_defaults: Default::default(),
+        schema_drift: Default::default(),
+        idempotency: Default::default(),
+        webhooks: Default::default(),
+        telemetry: Default::default(),
+        payloads: Default::default(),
+        encryption: Default::default(),
+        queue: Default::default(),
+        auth: Default::default(),
+    }
+}
+
+#[test]
+fn test_rng_replays_from_seed() {
+    let draws = |seed| {
+        let mut rng = Rng(seed);
+        (0..8).map(|_| rng.below(100)).collect::<Vec<_>>()
+    };
+    assert_eq!(draws(7), draws(7));
+    assert_ne!(draws(7), draws(8));
+
+    let mut rng = Rng(1);
+    assert!(!(0..100).any(|_| rng.chance(0.0)));
+    assert!((0..100).all(|_| rng.chance(1.0)));
+}
+
+#[sqlx::test]
+async fn test_faulty_workload_keeps_invariants(pool: PgPool) -> anyhow::Result<()> {
+    let app = Application::with_pool(config(), pool);
+    let report = run(
+        &app,
+        &SimulationConfig {
+            seed: 42,
+            workflows: 12,
+            max_fan_out: 3,
+            failure_rate: 0.2,
+            crash_rate: 0.2,
+            cancel_rate: 0.05,
+            drain_timeout: Duration::from_secs(60),
+        },
+    )
+    .await?;
+
+    assert!(report.is_ok(), "{:#?}", report.violations);
+    assert_eq!(report.workflows_started, 12);
+    assert!(report.crashes > 0);
+    assert!(report.tasks_run > 0);
+    Ok(())
+}
@@ -0,0 +1,210 @@
+//! Binary encoding of saved workflow states
+//!
+//! Saved VM states are JSON in `workflow_execution_context.locals` by
+//! default. With `[payloads] state_encoding = "msgpack"` they are written to
+//! its `state` column instead, as MessagePack, which is smaller and quicker
+//! to write and read back than JSON. The bytes start with a two byte header:
+//!
+//! - byte 0: the format, `FORMAT_MSGPACK_V1`
+//! - byte 1: flags, `COMPRESSED` (zstd), `ENCRYPTED` (see
+//!   `Keyring::encrypt_bytes`) and `BOUND`, for encrypted bytes bound to the
+//!   execution they were saved for
+//!
+//! followed by the MessagePack of the VM, compressed and then encrypted as
+//! flagged. A reader that doesn't know the format fails instead of guessing.
+//!
+//! States in either column are read whatever the setting, so it can be
+//! changed on a running deployment; `rhythm migrate-state --apply` rewrites
+//! what is stored in the configured encoding.
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::compression;
+use crate::encryption::{self, Binding, Keyring};
+use crate::executor::VM;
+
+/// The only binary format so far: a VM as MessagePack with named fields
+pub const FORMAT_MSGPACK_V1: u8 = 1;
+
+const COMPRESSED: u8 = 0b01;
+const ENCRYPTED: u8 = 0b10;
+const BOUND: u8 = 0b100;
+const HEADER_BYTES: usize = 2;
+
+/// `vm` as MessagePack, before `seal` frames it
+pub fn encode(vm: &VM) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(vm).context("Failed to serialize VM state")
+}
+
+/// The VM in MessagePack from `encode` or `open`
+pub fn decode(bytes: &[u8]) -> Result<VM> {
+    rmp_serde::from_slice(bytes).context("Failed to deserialize VM state")
+}
+
+/// Encoded VM `bytes` with their header, compressed if `compress`, and
+/// encrypted with `keyring`'s active key for `binding` if given
+pub fn seal(
+    bytes: Vec<u8>,
+    compress: bool,
+    keyring: Option<&Keyring>,
+    binding: Binding<'_>,
+) -> Result<Vec<u8>> {
+    let mut flags = 0;
+    let mut body = bytes;
+    if compress {
+        body = compression::compress_bytes(&body)?;
+        flags |= COMPRESSED;
+    }
+    if let Some(keyring) = keyring {
+        body = keyring
+            .encrypt_bytes(&body, binding)
+            .context("Failed to encrypt VM state")?;
+        flags |= ENCRYPTED | BOUND;
+    }
+
+    let mut stored = Vec::with_capacity(HEADER_BYTES + body.len());
+    stored.extend_from_slice(&[FORMAT_MSGPACK_V1, flags]);
+    stored.extend_from_slice(&body);
+    Ok(stored)
+}
+
+/// The encoded VM in `stored`, decrypted and decompressed
+///
+/// Fails for encrypted bytes when `keyring` is `None`, or when they were
+/// saved for another execution than `binding` names.
+pub fn open(stored: &[u8], keyring: Option<&Keyring>, binding: Binding<'_>) -> Result<Vec<u8>> {
+    let (flags, body) = parse_header(stored)?;
+    let body = if flags & ENCRYPTED != 0 {
+        let keyring = keyring.ok_or_else(|| {
+            anyhow!(
+                "VM state is encrypted with key '{}', but encryption is not configured",
+                encryption::bytes_key(body).unwrap_or("?")
+            )
+        })?;
+        keyring
+            .decrypt_bytes(body, (flags & BOUND != 0).then_some(binding))
+            .context("Failed to decrypt VM state")?
+    } else {
+        body.to_vec()
+    };
+    if flags & COMPRESSED != 0 {
+        compression::decompress_bytes(&body)
+    } else {
+        Ok(body)
+    }
+}
+
+/// The ID of the key `stored` is encrypted with, if it is
+pub fn key_id(stored: &[u8]) -> Option<&str> {
+    match parse_header(stored) {
+        Ok((flags, body)) if flags & ENCRYPTED != 0 => encryption::bytes_key(body),
+        _ => None,
+    }
+}
+
+/// Whether `stored` is encrypted for the execution it was saved for, as
+/// `seal` encrypts now
+pub fn is_bound(stored: &[u8]) -> bool {
+    matches!(parse_header(stored), Ok((flags, _)) if flags & BOUND != 0)
+}
+
+fn parse_header(stored: &[u8]) -> Result<(u8, &[u8])> {
+    match stored {
+        [FORMAT_MSGPACK_V1, flags, body @ ..] => Ok((*flags, body)),
+        [format, ..] => Err(anyhow!("Unknown VM state format {}", format)),
+        [] => Err(anyhow!("VM state is empty")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncryptionConfig;
+    use crate::executor::{json_to_val_map, WorkflowContext};
+    use crate::parser::parse_workflow;
+    use base64::Engine;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn vm() -> VM {
+        let program = parse_workflow(
+            r#"
+            let order = await Task.run("charge", { amount: Inputs.amount })
+            return order
+            "#,
+        )
+        .unwrap()
+        .body;
+        let mut vm = VM::new(
+            program,
+            json_to_val_map(&json!({ "amount": 12.5, "lines": ["a", "b"] })).unwrap(),
+            WorkflowContext {
+                execution_id: "wf-1".to_string(),
+            },
+        );
+        crate::executor::run_until_done(&mut vm);
+        vm
+    }
+
+    fn keyring() -> Keyring {
+        Keyring::from_config(&EncryptionConfig {
+            active_key: Some("k1".to_string()),
+            keys: HashMap::from([(
+                "k1".to_string(),
+                base64::engine::general_purpose::STANDARD.encode([3u8; 32]),
+            )]),
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_is_smaller_than_json() {
+        let vm = vm();
+        let bytes = encode(&vm).unwrap();
+        let json = serde_json::to_vec(&vm).unwrap();
+        assert!(bytes.len() < json.len());
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&vm).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_seal_and_open_with_each_flag() {
+        let keyring = keyring();
+        let row = Binding::state("wf");
+        let bytes = encode(&vm()).unwrap();
+        for (compress, keyring) in [
+            (false, None),
+            (true, None),
+            (false, Some(&keyring)),
+            (true, Some(&keyring)),
+        ] {
+            let stored = seal(bytes.clone(), compress, keyring, row).unwrap();
+            assert_eq!(stored[0], FORMAT_MSGPACK_V1);
+            assert_eq!(key_id(&stored), keyring.map(|_| "k1"));
+            assert_eq!(is_bound(&stored), keyring.is_some());
+            assert_eq!(open(&stored, keyring, row).unwrap(), bytes);
+        }
+
+        let encrypted = seal(bytes, false, Some(&keyring), row).unwrap();
+        let err = open(&encrypted, None, row).unwrap_err();
+        assert!(err.to_string().contains("key 'k1'"), "{}", err);
+
+        // Another workflow's state doesn't load in its place
+        assert!(open(&encrypted, Some(&keyring), Binding::state("other")).is_err());
+    }
+
+    #[test]
+    fn test_unknown_formats_are_rejected() {
+        assert!(open(&[9, 0, 1, 2], None, Binding::state("wf"))
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown VM state format 9"));
+        assert!(open(&[], None, Binding::state("wf")).is_err());
+        assert_eq!(key_id(&[9, ENCRYPTED]), None);
+    }
+}
@@ -0,0 +1,290 @@
+//! Distributed tracing through the execution lifecycle
+//!
+//! Each execution row stores the W3C `traceparent` it was created under: the
+//! caller's, or for a child without one its workflow's. Claiming and running
+//! an execution happen in a `rhythm.execution` span parented to that context,
+//! and the context handed to the host with a task is the claim span's, so
+//! the caller, the runner and the task handlers share one trace.
+//!
+//! Spans are ordinary `tracing` spans. With the `otel` feature and
+//! `[telemetry] otlp_endpoint` set, `init` installs a subscriber exporting
+//! them over OTLP; without it the trace context is still stored and passed
+//! on, so host-side tracing joins up.
+
+use std::fmt;
+
+use tracing::field;
+
+use crate::config::TelemetryConfig;
+use crate::types::Execution;
+
+/// A W3C trace context, as carried by a `traceparent` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Start a new, sampled trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: uuid::Uuid::new_v4().as_u128(),
+            span_id: new_span_id(),
+            sampled: true,
+        }
+    }
+
+    /// A new span in the same trace
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: new_span_id(),
+            ..*self
+        }
+    }
+
+    /// Parse a `traceparent`; `None` for anything the spec says to ignore
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        // Later versions may append fields; version 00 has exactly four
+        if version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        let fields = [(version, 2), (trace_id, 32), (span_id, 16), (flags, 2)];
+        if !fields.iter().all(|(field, len)| is_lower_hex(field, *len)) {
+            return None;
+        }
+
+        let context = Self {
+            trace_id: u128::from_str_radix(trace_id, 16).ok()?,
+            span_id: u64::from_str_radix(span_id, 16).ok()?,
+            sampled: u8::from_str_radix(flags, 16).ok()? & 1 == 1,
+        };
+        (context.trace_id != 0 && context.span_id != 0).then_some(context)
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+}
+
+fn is_lower_hex(field: &str, len: usize) -> bool {
+    field.len() == len
+        && field
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn new_span_id() -> u64 {
+    (uuid::Uuid::new_v4().as_u128() as u64).max(1)
+}
+
+/// Span for one `stage` (e.g. "claim") of `execution`'s lifecycle
+///
+/// Parented to `traceparent`, the context stored with the execution, when
+/// it is a valid one.
+pub fn execution_span(
+    stage: &'static str,
+    execution: &Execution,
+    traceparent: Option<&str>,
+) -> tracing::Span {
+    let span = tracing::info_span!(
+        "rhythm.execution",
+        otel.name = %format_args!("rhythm.{} {}", stage, execution.target_name),
+        stage,
+        execution_id = %execution.id,
+        target_name = %execution.target_name,
+        attempt = execution.attempt,
+        trace_id = field::Empty,
+        parent_span_id = field::Empty,
+    );
+    if let Some(parent) = traceparent.and_then(TraceContext::parse) {
+        span.record(
+            "trace_id",
+            field::display(format_args!("{:032x}", parent.trace_id)),
+        );
+        span.record(
+            "parent_span_id",
+            field::display(format_args!("{:016x}", parent.span_id)),
+        );
+        #[cfg(feature = "otel")]
+        otel::set_parent(&span, parent);
+    }
+    span
+}
+
+/// `traceparent` of the current span, when an exporter is tracking it
+///
+/// Always `None` without the `otel` feature, leaving children to inherit
+/// their workflow's context.
+pub fn current_traceparent() -> Option<String> {
+    #[cfg(feature = "otel")]
+    {
+        otel::current().map(|context| context.to_string())
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        None
+    }
+}
+
+/// Install the OTLP exporter `config` asks for, if any
+///
+/// Only the first call in a process installs one; a subscriber the host
+/// installed first is left alone.
+pub fn init(config: &TelemetryConfig) {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return;
+    };
+
+    #[cfg(feature = "otel")]
+    if let Err(e) = otel::install(endpoint, &config.service_name) {
+        tracing::warn!(endpoint = %endpoint, error = %e, "Not exporting spans over OTLP");
+    }
+    #[cfg(not(feature = "otel"))]
+    tracing::warn!(
+        endpoint = %endpoint,
+        "telemetry.otlp_endpoint is set but rhythm was built without the otel feature"
+    );
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use anyhow::{Context, Result};
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider,
+    };
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use std::sync::OnceLock;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::filter::LevelFilter;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::Layer;
+
+    use super::TraceContext;
+
+    static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+    pub(super) fn install(endpoint: &str, service_name: &str) -> Result<()> {
+        if PROVIDER.get().is_some() {
+            return Ok(());
+        }
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+            .context("Failed to build OTLP exporter")?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(service_name.to_string())
+                    .build(),
+            )
+            .build();
+        let tracer = provider.tracer("rhythm");
+
+        tracing_subscriber::registry()
+            .with(
+                tracing_opentelemetry::layer()
+                    .with_tracer(tracer)
+                    .with_filter(LevelFilter::INFO),
+            )
+            .try_init()
+            .context("A tracing subscriber is already installed")?;
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        opentelemetry::global::set_tracer_provider(provider.clone());
+        let _ = PROVIDER.set(provider);
+        Ok(())
+    }
+
+    pub(super) fn set_parent(span: &tracing::Span, parent: TraceContext) {
+        let flags = if parent.sampled {
+            TraceFlags::SAMPLED
+        } else {
+            TraceFlags::default()
+        };
+        let context = SpanContext::new(
+            TraceId::from_bytes(parent.trace_id.to_be_bytes()),
+            SpanId::from_bytes(parent.span_id.to_be_bytes()),
+            flags,
+            true,
+            TraceState::default(),
+        );
+        let _ = span.set_parent(opentelemetry::Context::new().with_remote_span_context(context));
+    }
+
+    pub(super) fn current() -> Option<TraceContext> {
+        let context = tracing::Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        span_context.is_valid().then(|| TraceContext {
+            trace_id: u128::from_be_bytes(span_context.trace_id().to_bytes()),
+            span_id: u64::from_be_bytes(span_context.span_id().to_bytes()),
+            sampled: span_context.is_sampled(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_round_trip() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let context = TraceContext::parse(header).unwrap();
+        assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.span_id, 0x00f067aa0ba902b7);
+        assert!(context.sampled);
+        assert_eq!(context.to_string(), header);
+
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_ne!(child.span_id, context.span_id);
+        assert_eq!(TraceContext::parse(&child.to_string()), Some(child));
+
+        let root = TraceContext::new_root();
+        assert_eq!(TraceContext::parse(&root.to_string()), Some(root));
+    }
+
+    #[test]
+    fn test_invalid_traceparents_are_ignored() {
+        for header in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e473g-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(TraceContext::parse(header), None, "{}", header);
+        }
+
+        // Later versions may carry more fields
+        let context =
+            TraceContext::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra")
+                .unwrap();
+        assert!(!context.sampled);
+    }
+}
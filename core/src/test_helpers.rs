@@ -1,8 +1,9 @@
 //! Test helpers for V2 workflow engine tests
 
 use anyhow::Result;
-use serde_json::Value as JsonValue;
+use serde_json::{json, Value as JsonValue};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use std::ops::Deref;
 
 use crate::db;
@@ -65,6 +66,29 @@ pub async fn with_test_db() -> TestPool {
     TestPool { pool }
 }
 
+/// Params for a `target_name` task on the default queue, with empty inputs
+/// and every optional setting unset
+///
+/// Tests change what they need with struct update syntax:
+/// `CreateExecutionParams { queue: "emails".to_string(), ..task_params("work") }`
+pub fn task_params(target_name: &str) -> CreateExecutionParams {
+    CreateExecutionParams {
+        id: None,
+        exec_type: ExecutionType::Task,
+        target_name: target_name.to_string(),
+        queue: "default".to_string(),
+        inputs: json!({}),
+        parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
+    }
+}
+
 /// Helper to set up a workflow test
 ///
 /// Creates workflow, submits execution, and claims work.
@@ -106,12 +130,9 @@ pub async fn setup_workflow_test_with_pool(
 
     // Create execution and enqueue work
     let params = CreateExecutionParams {
-        id: None,
         exec_type: ExecutionType::Workflow,
-        target_name: workflow_name.to_string(),
-        queue: "default".to_string(),
         inputs,
-        parent_workflow_id: None,
+        ..task_params(workflow_name)
     };
 
     let mut tx = pool.begin().await.unwrap();
@@ -125,10 +146,13 @@ pub async fn setup_workflow_test_with_pool(
 
     tx.commit().await.unwrap();
 
-    // Claim the work
+    // Claim the work and start the execution, as a worker would
     db::work_queue::claim_specific_execution(&pool, &execution_id)
         .await
         .unwrap();
+    db::executions::start_execution_unless_finished(pool.as_ref(), &execution_id)
+        .await
+        .unwrap();
 
     // Fetch the execution from the database
     let execution = db::executions::get_execution(&pool, &execution_id)
@@ -158,6 +182,7 @@ pub async fn enqueue_and_claim_execution(
     .bind(execution_id)
     .execute(pool)
     .await?;
+    db::executions::start_execution_unless_finished(pool, execution_id).await?;
 
     Ok(())
 }
@@ -251,3 +276,23 @@ pub async fn get_child_executions_with_type(
     .await?;
     Ok(executions)
 }
+
+/// Complete a child task the way a worker would: start it, then complete it
+pub async fn complete_task(
+    pool: &PgPool,
+    execution_id: &str,
+    output: JsonValue,
+) -> Result<Option<Execution>> {
+    db::executions::start_execution_unless_finished(pool, execution_id).await?;
+    db::executions::complete_execution(pool, execution_id, output).await
+}
+
+/// Fail a child task the way a worker would: start it, then fail it
+pub async fn fail_task(
+    pool: &PgPool,
+    execution_id: &str,
+    output: JsonValue,
+) -> Result<Option<Execution>> {
+    db::executions::start_execution_unless_finished(pool, execution_id).await?;
+    db::executions::fail_execution(pool, execution_id, output).await
+}
@@ -0,0 +1,425 @@
+//! Unit-testing workflows from code
+//!
+//! `WorkflowTestHarness` runs Flow source in memory on the `flow_test`
+//! runner, with no database or workers: tasks and child workflows answer
+//! with the stubs registered for their name, timers fire at once, and
+//! signals take the payloads queued for them.
+//!
+//! ```ignore
+//! let mut harness = WorkflowTestHarness::new(source)?;
+//! harness.on_task("charge").returns(json!({ "id": "ch_1" }));
+//! harness.on_task("ship").fails(json!({ "code": "OUT_OF_STOCK", "message": "gone" }));
+//!
+//! let run = harness.run(json!({ "order_id": "o1" }))?;
+//! run.assert_output(json!({ "charged": "ch_1", "shipped": false }));
+//! run.assert_calls(&["charge", "ship"]);
+//! run.assert_suspended_at(&[2, 3]);
+//! ```
+//!
+//! Several stubs for one name answer calls in order, the last repeating. A
+//! stubbed failure is what the workflow's `await` returns, as on a worker.
+//! A run that awaits something with no stub ends stuck at that await.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+
+use crate::errors::RhythmError;
+use crate::executor::{json_to_val, val_to_json, Awaitable};
+use crate::flow_test::harness::{self, Call, RunOutcome, Suspension};
+use crate::flow_test::{Stub, TestCase};
+use crate::parser::WorkflowDef;
+
+/// Runs a workflow in memory against stubbed tasks and signals
+#[derive(Debug, Clone)]
+pub struct WorkflowTestHarness {
+    workflow: WorkflowDef,
+    case: TestCase,
+}
+
+/// Stub for the next call to a task, finished by `returns` or `fails`
+pub struct TaskStub<'a> {
+    harness: &'a mut WorkflowTestHarness,
+    name: String,
+}
+
+impl TaskStub<'_> {
+    /// Answer the call with `result`
+    pub fn returns(self, result: JsonValue) {
+        self.harness.stub(Stub {
+            name: self.name,
+            result: Some(result),
+            ..Default::default()
+        });
+    }
+
+    /// Fail the call with `error`, e.g. `{ "code": "...", "message": "..." }`
+    pub fn fails(self, error: JsonValue) {
+        self.harness.stub(Stub {
+            name: self.name,
+            error: Some(error),
+            ..Default::default()
+        });
+    }
+}
+
+impl WorkflowTestHarness {
+    /// Parse and validate a workflow's source
+    pub fn new(source: &str) -> Result<Self> {
+        let workflow = crate::parser::parse_workflow(source).map_err(|e| {
+            RhythmError::Validation(format!("Failed to parse workflow: {}", e.message()))
+        })?;
+        crate::parser::semantic_validator::validate_workflow(&workflow)
+            .map_err(|e| RhythmError::Validation(format!("Invalid workflow: {}", e)))?;
+        Ok(Self {
+            workflow,
+            case: TestCase {
+                name: "harness".to_string(),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Stub calls to the task or child workflow `name`
+    pub fn on_task(&mut self, name: &str) -> TaskStub<'_> {
+        TaskStub {
+            harness: self,
+            name: name.to_string(),
+        }
+    }
+
+    /// Add a stub as a `.flow.test` file declares it
+    pub fn stub(&mut self, stub: Stub) -> &mut Self {
+        self.case.tasks.push(stub);
+        self
+    }
+
+    /// Queue a payload for the signal `name`
+    pub fn on_signal(&mut self, name: &str, payload: JsonValue) -> &mut Self {
+        self.case
+            .signals
+            .entry(name.to_string())
+            .or_default()
+            .push(payload);
+        self
+    }
+
+    /// Run the workflow to its end with `inputs`
+    pub fn run(&self, inputs: JsonValue) -> Result<TestRun> {
+        let case = TestCase {
+            inputs,
+            ..self.case.clone()
+        };
+        let run = harness::run(&self.workflow, &case)?;
+        let names: HashMap<&str, &str> = run
+            .calls
+            .iter()
+            .map(|call| (call.execution_id.as_str(), call.target_name.as_str()))
+            .collect();
+        let suspensions = run
+            .suspensions
+            .iter()
+            .map(|suspension| SuspendPoint::new(suspension, &names))
+            .collect();
+
+        Ok(TestRun {
+            line: line_of(run.span),
+            calls: run.calls.iter().map(TaskCall::from).collect(),
+            suspensions,
+            outcome: run.outcome,
+        })
+    }
+}
+
+/// A task or child workflow the run started
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskCall {
+    pub name: String,
+    pub inputs: JsonValue,
+    /// 1-based line of the statement that started it
+    pub line: Option<usize>,
+}
+
+impl From<&Call> for TaskCall {
+    fn from(call: &Call) -> Self {
+        Self {
+            name: call.target_name.clone(),
+            inputs: call.inputs.clone(),
+            line: line_of(call.span),
+        }
+    }
+}
+
+/// Where the run suspended, and what it waited for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuspendPoint {
+    /// 1-based line of the awaiting statement
+    pub line: Option<usize>,
+    /// `task`, `timer`, `signal`, `stream`, `all`, `any` or `race`
+    pub kind: &'static str,
+    /// Tasks and signals waited for, by name
+    pub targets: Vec<String>,
+}
+
+impl SuspendPoint {
+    fn new(suspension: &Suspension, names: &HashMap<&str, &str>) -> Self {
+        let mut targets = Vec::new();
+        collect_targets(&suspension.awaitable, names, &mut targets);
+        Self {
+            line: line_of(suspension.span),
+            kind: match &suspension.awaitable {
+                Awaitable::Execution(_) => "task",
+                Awaitable::Timer { .. } => "timer",
+                Awaitable::Signal { .. } => "signal",
+                Awaitable::Stream { .. } => "stream",
+                Awaitable::All { .. } => "all",
+                Awaitable::Any { .. } => "any",
+                Awaitable::Race { .. } => "race",
+            },
+            targets,
+        }
+    }
+}
+
+fn collect_targets(awaitable: &Awaitable, names: &HashMap<&str, &str>, targets: &mut Vec<String>) {
+    match awaitable {
+        Awaitable::Execution(id)
+        | Awaitable::Stream {
+            execution_id: id, ..
+        } => targets.push(names.get(id.as_str()).unwrap_or(&id.as_str()).to_string()),
+        Awaitable::Signal { name, .. } => targets.push(name.clone()),
+        Awaitable::Timer { .. } => {}
+        Awaitable::All { items, .. }
+        | Awaitable::Any { items, .. }
+        | Awaitable::Race { items, .. } => {
+            for (_, item) in items {
+                collect_targets(item, names, targets);
+            }
+        }
+    }
+}
+
+fn line_of(span: Option<crate::executor::types::ast::Span>) -> Option<usize> {
+    span.map(|span| span.start_line + 1)
+}
+
+/// A finished run of a `WorkflowTestHarness`
+#[derive(Debug, Clone)]
+pub struct TestRun {
+    pub outcome: RunOutcome,
+    /// 1-based line of the statement that returned, threw, or is stuck
+    pub line: Option<usize>,
+    pub calls: Vec<TaskCall>,
+    pub suspensions: Vec<SuspendPoint>,
+}
+
+impl TestRun {
+    /// The value the workflow returned
+    pub fn output(&self) -> Option<&JsonValue> {
+        match &self.outcome {
+            RunOutcome::Returned(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// The error the workflow threw
+    pub fn error(&self) -> Option<&JsonValue> {
+        match &self.outcome {
+            RunOutcome::Threw(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Names of the tasks and child workflows started, in order
+    pub fn call_names(&self) -> Vec<&str> {
+        self.calls.iter().map(|call| call.name.as_str()).collect()
+    }
+
+    /// Lines of the statements the run suspended at, in order
+    pub fn suspend_lines(&self) -> Vec<usize> {
+        self.suspensions.iter().filter_map(|s| s.line).collect()
+    }
+
+    /// Panics unless the workflow returned `expected`
+    ///
+    /// Values compare as the VM sees them, so `1` and `1.0` are equal.
+    pub fn assert_output(&self, expected: JsonValue) {
+        let expected = json_to_val(&expected)
+            .and_then(|val| val_to_json(&val))
+            .expect("expected output is not a workflow value");
+        match self.output() {
+            Some(output) => assert_eq!(*output, expected, "unexpected workflow output"),
+            None => panic!("expected output {}, but {}", expected, self.describe()),
+        }
+    }
+
+    /// Panics unless the workflow threw an error with `code`
+    pub fn assert_error_code(&self, code: &str) {
+        let thrown = self
+            .error()
+            .and_then(|error| crate::worker::retry::error_code(error));
+        assert!(
+            thrown == Some(code),
+            "expected error {}, but {}",
+            code,
+            self.describe()
+        );
+    }
+
+    /// Panics unless exactly these tasks were started, in this order
+    pub fn assert_calls(&self, expected: &[&str]) {
+        assert_eq!(self.call_names(), expected, "unexpected task calls");
+    }
+
+    /// Panics unless the run suspended at exactly these lines, in order
+    pub fn assert_suspended_at(&self, lines: &[usize]) {
+        assert_eq!(self.suspend_lines(), lines, "unexpected suspend points");
+    }
+
+    /// The run as JSON, for language bindings
+    pub fn to_json(&self) -> JsonValue {
+        let (status, value) = match &self.outcome {
+            RunOutcome::Returned(output) => ("returned", json!({ "output": output })),
+            RunOutcome::Threw(error) => ("threw", json!({ "error": error })),
+            RunOutcome::Stuck(reason) => ("stuck", json!({ "reason": reason })),
+            RunOutcome::Runaway => ("runaway", json!({})),
+        };
+        let mut run = json!({
+            "status": status,
+            "line": self.line,
+            "calls": self.calls,
+            "suspensions": self.suspensions,
+        });
+        if let (JsonValue::Object(run), JsonValue::Object(value)) = (&mut run, value) {
+            run.extend(value);
+        }
+        run
+    }
+
+    fn describe(&self) -> String {
+        let at = self
+            .line
+            .map(|line| format!(" at line {}", line))
+            .unwrap_or_default();
+        match &self.outcome {
+            RunOutcome::Returned(output) => format!("returned {}{}", output, at),
+            RunOutcome::Threw(error) => format!("threw {}{}", error, at),
+            RunOutcome::Stuck(reason) => format!("never finished{}: {}", at, reason),
+            RunOutcome::Runaway => format!("did not finish within the step limit{}", at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKOUT: &str = r#"
+let charge = await Task.run("charge", { order: Inputs.order_id })
+let shipment = await Task.run("ship", { charge: charge.id })
+if (shipment != "shipped") {
+    await Timer.delay(60)
+    return { charged: charge.id, shipped: false }
+}
+return { charged: charge.id, shipped: true }
+"#;
+
+    #[test]
+    fn test_stubs_answer_calls_by_name() {
+        let mut harness = WorkflowTestHarness::new(CHECKOUT).unwrap();
+        harness.on_task("charge").returns(json!({ "id": "ch_1" }));
+        harness.on_task("ship").returns(json!("shipped"));
+
+        let run = harness.run(json!({ "order_id": "o1" })).unwrap();
+        run.assert_output(json!({ "charged": "ch_1", "shipped": true }));
+        run.assert_calls(&["charge", "ship"]);
+        run.assert_suspended_at(&[2, 3]);
+        assert_eq!(run.calls[0].inputs, json!({ "order": "o1" }));
+        assert_eq!(
+            run.suspensions[1],
+            SuspendPoint {
+                line: Some(3),
+                kind: "task",
+                targets: vec!["ship".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_failed_stub_is_the_await_result() {
+        let mut harness = WorkflowTestHarness::new(CHECKOUT).unwrap();
+        harness.on_task("charge").returns(json!({ "id": "ch_1" }));
+        harness
+            .on_task("ship")
+            .fails(json!({ "code": "OUT_OF_STOCK", "message": "gone" }));
+
+        let run = harness.run(json!({ "order_id": "o1" })).unwrap();
+        run.assert_output(json!({ "charged": "ch_1", "shipped": false }));
+        assert_eq!(run.suspensions[2].kind, "timer");
+        run.assert_suspended_at(&[2, 3, 5]);
+    }
+
+    #[test]
+    fn test_missing_stub_leaves_run_stuck() {
+        let harness = WorkflowTestHarness::new(CHECKOUT).unwrap();
+        let run = harness.run(json!({ "order_id": "o1" })).unwrap();
+        assert_eq!(
+            run.outcome,
+            RunOutcome::Stuck("'charge' has no stub".to_string())
+        );
+        assert_eq!(run.line, Some(2));
+        assert_eq!(run.to_json()["status"], "stuck");
+    }
+
+    #[test]
+    fn test_signals_and_thrown_errors() {
+        let mut harness = WorkflowTestHarness::new(
+            r#"
+let approval = await Signal.next("approval")
+if (!approval.ok) {
+    throw { code: "REJECTED", message: "not approved" }
+}
+return approval
+"#,
+        )
+        .unwrap();
+        harness.on_signal("approval", json!({ "ok": false }));
+
+        let run = harness.run(json!({})).unwrap();
+        run.assert_error_code("REJECTED");
+        assert_eq!(run.suspensions[0].targets, vec!["approval".to_string()]);
+        assert_eq!(run.to_json()["error"]["code"], "REJECTED");
+    }
+
+    #[test]
+    fn test_compensations_run_before_the_error() {
+        let mut harness = WorkflowTestHarness::new(
+            r#"
+let charge = await Task.run("charge", {})
+Compensate.register("refund", { charge: charge.id })
+await Task.run("ship", {})
+throw Error("Shipping failed", "SHIPPING")
+"#,
+        )
+        .unwrap();
+        harness.on_task("charge").returns(json!({ "id": "ch_1" }));
+        harness.on_task("ship").returns(json!(null));
+        harness.on_task("refund").returns(json!(null));
+
+        let run = harness.run(json!({})).unwrap();
+        run.assert_error_code("SHIPPING");
+        run.assert_calls(&["charge", "ship", "refund"]);
+        assert_eq!(run.calls[2].inputs, json!({ "charge": "ch_1" }));
+        assert_eq!(run.line, Some(5));
+    }
+
+    #[test]
+    fn test_invalid_source_is_a_validation_error() {
+        let err = WorkflowTestHarness::new("let = ").unwrap_err();
+        assert_eq!(
+            crate::errors::ErrorCode::of(&err),
+            crate::errors::ErrorCode::Validation
+        );
+    }
+}
@@ -5,6 +5,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
 #[sqlx(type_name = "text", rename_all = "lowercase")]
@@ -12,6 +13,19 @@ use serde_json::Value as JsonValue;
 pub enum ExecutionType {
     Task,
     Workflow,
+    /// Completed by an outside system through its token, never claimed by a worker
+    External,
+}
+
+impl ExecutionType {
+    /// Stable lowercase label, matching the database and serde representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionType::Task => "task",
+            ExecutionType::Workflow => "workflow",
+            ExecutionType::External => "external",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
@@ -23,15 +37,48 @@ pub enum ExecutionStatus {
     Suspended,
     Completed,
     Failed,
+    /// Not claimed before its TTL ran out
+    Expired,
+    /// Stopped by `cancel_workflow` or `cancel_child_first`, or as a child
+    /// of a cancelled workflow
+    Cancelled,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ExecutionStatus {
+    /// Stable lowercase label, matching the database and serde representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionStatus::Pending => "pending",
+            ExecutionStatus::Running => "running",
+            ExecutionStatus::Suspended => "suspended",
+            ExecutionStatus::Completed => "completed",
+            ExecutionStatus::Failed => "failed",
+            ExecutionStatus::Expired => "expired",
+            ExecutionStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Whether the execution has reached a final state
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            ExecutionStatus::Completed
+                | ExecutionStatus::Failed
+                | ExecutionStatus::Expired
+                | ExecutionStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Execution {
     pub id: String,
     #[serde(rename = "type")]
     pub exec_type: ExecutionType,
     pub target_name: String,
     pub queue: String,
+    /// Tenant grouping used for quotas; children inherit their parent's
+    pub namespace: String,
     pub status: ExecutionStatus,
 
     pub inputs: JsonValue,
@@ -53,6 +100,87 @@ pub struct CreateExecutionParams {
     pub queue: String,
     pub inputs: JsonValue,
     pub parent_workflow_id: Option<String>,
+    /// Defaults to the parent workflow's namespace, else `default`
+    pub namespace: Option<String>,
+    /// Expire the execution if it is not claimed within this many seconds
+    pub ttl_seconds: Option<u64>,
+    /// How a failed task is retried; `[[task_configs]]` apply without one
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Higher is claimed first; defaults to the parent workflow's, else 0
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Creating again with the same key, queue and target within the
+    /// idempotency window returns the first execution instead
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    /// W3C trace context of the caller; defaults to the parent workflow's,
+    /// else a new trace
+    #[serde(default)]
+    pub traceparent: Option<String>,
+    /// Labels to find the execution by; added to the parent workflow's
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+/// How a failed task is retried, stored on its execution
+///
+/// The retry flag and `retry_on`/`give_up_on` still decide whether a
+/// failure is retried; the policy decides how often and how soon. Its own
+/// limit and codes, when set, replace those of the task's `[[task_configs]]`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    #[serde(default)]
+    pub backoff: Backoff,
+    /// Delay before the first retry
+    pub initial_interval_ms: u64,
+    /// Longest delay between retries
+    pub max_interval_ms: u64,
+    /// Fraction of each delay that is randomized, from 0 to 1
+    #[serde(default)]
+    pub jitter: f64,
+    /// Error codes that are retried even when the host gave up
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retry_on: Vec<String>,
+    /// Error codes that are never retried
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub give_up_on: Vec<String>,
+}
+
+/// How the delay grows between retries
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backoff {
+    /// Always the initial interval
+    Fixed,
+    /// The initial interval, doubled after each retry
+    #[default]
+    Exponential,
+}
+
+/// A key at most one unfinished workflow holds at a time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConcurrencyKey {
+    pub key: String,
+    /// What starting a workflow does while another holds the key
+    #[serde(default)]
+    pub policy: ConcurrencyPolicy,
+}
+
+/// What starting a workflow with a taken concurrency key does
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcurrencyPolicy {
+    /// Fail with `Conflict`
+    #[default]
+    Reject,
+    /// Wait, without being claimed, until the holder and the workflows
+    /// queued before it have finished
+    Queue,
+    /// Cancel the holder and the workflows waiting for the key
+    ReplaceExisting,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +190,324 @@ pub struct ScheduleExecutionParams {
     pub queue: String,
     pub inputs: JsonValue,
     pub run_at: chrono::NaiveDateTime,
+    pub namespace: Option<String>,
+}
+
+/// Namespace executions are created in when none is given
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Usage a namespace's quotas are checked against
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceUsage {
+    pub namespace: String,
+    /// Executions that have not finished (pending, running or suspended)
+    pub running: i64,
+    /// Executions created in the trailing 60 seconds
+    pub enqueued_last_minute: i64,
+    /// Stored bytes of inputs and outputs
+    pub storage_bytes: i64,
+}
+
+/// How full a concurrency group is
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GroupOccupancy {
+    pub name: String,
+    pub max_running: i64,
+    /// Executions holding a slot: running, suspended, or claimed to start
+    pub running: i64,
+    /// Pending executions queued for a slot
+    pub waiting: i64,
+}
+
+/// VM step trace of an execution that opted in to tracing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionVmTrace {
+    pub execution_id: String,
+    pub enabled_at: DateTime<Utc>,
+    /// When the last failed run's steps were saved; `None` until one fails
+    pub recorded_at: Option<DateTime<Utc>>,
+    /// Steps the failed run took, including those no longer kept
+    pub total_steps: Option<i64>,
+    /// The failed run's most recent steps, oldest first
+    pub steps: Vec<crate::executor::TraceStep>,
+}
+
+/// What a recorded workflow read while it ran, to replay it with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionRecording {
+    pub execution_id: String,
+    pub enabled_at: DateTime<Utc>,
+    /// When the last run was saved; `None` until one is
+    pub recorded_at: Option<DateTime<Utc>>,
+    /// Definition the last recorded run ran
+    pub workflow_definition_id: Option<i32>,
+    /// Values the workflow read from outside it (see `VM::recorded`), in order
+    pub reads: Vec<crate::executor::Val>,
+    /// Values it was resumed with after each await, in order, with
+    /// offloaded payloads left as references
+    pub resumes: Vec<crate::executor::Val>,
+}
+
+/// Kind of entry in an execution's history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionEventType {
+    Created,
+    /// Moved to running by a worker
+    Claimed,
+    /// Put back to pending for another attempt
+    Retried,
+    Suspended,
+    /// A workflow started a child execution
+    TaskScheduled,
+    /// A child of the workflow finished
+    TaskCompleted,
+    /// A workflow picked up the result it was suspended on
+    Resumed,
+    /// A workflow read values from outside it, like the clock, during a run
+    Read,
+    /// Asked to cancel child-first, once its child workflows have been
+    /// cancelled and its compensations have run
+    CancelRequested,
+    /// A level of a child-first cancellation wound down, recorded on the
+    /// workflow being cancelled
+    CancelProgress,
+    Completed,
+    Failed,
+    Expired,
+    Cancelled,
+}
+
+/// One entry in an execution's history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionEvent {
+    /// Orders events across executions
+    pub id: i64,
+    pub execution_id: String,
+    pub event_type: ExecutionEventType,
+    pub details: Option<JsonValue>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Severity of an execution log message
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+/// A message logged while running an execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionLog {
+    /// Orders logs across executions
+    pub id: i64,
+    pub execution_id: String,
+    pub level: LogLevel,
+    pub message: String,
+    pub data: Option<JsonValue>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A cluster-wide pause on claims, closed early or on expiry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub started_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+/// Maintenance window state and the work still running under it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    pub window: Option<MaintenanceWindow>,
+    /// Claims still within their lease
+    pub in_flight: i64,
+}
+
+/// One line of an export file (see `TransferService`)
+///
+/// Rows are kept as JSON, as they came out of the database they were
+/// exported from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum TransferRecord {
+    /// First line of every file
+    Header {
+        version: u32,
+        exported_at: DateTime<Utc>,
+    },
+    WorkflowDefinition(JsonValue),
+    Execution(JsonValue),
+    /// A workflow's saved state
+    Context(JsonValue),
+}
+
+/// How many records of each kind an export wrote or an import inserted
+///
+/// On import, records already present are counted as skipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransferSummary {
+    pub workflow_definitions: u64,
+    pub executions: u64,
+    pub contexts: u64,
+    pub skipped: u64,
+}
+
+/// Status change a webhook can be notified of
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl WebhookEvent {
+    /// Stable lowercase label, matching the database and serde representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Completed => "completed",
+            WebhookEvent::Failed => "failed",
+            WebhookEvent::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl std::str::FromStr for WebhookEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "completed" => Ok(WebhookEvent::Completed),
+            "failed" => Ok(WebhookEvent::Failed),
+            "cancelled" => Ok(WebhookEvent::Cancelled),
+            _ => anyhow::bail!(
+                "Unknown webhook event '{}' (expected completed, failed or cancelled)",
+                s
+            ),
+        }
+    }
+}
+
+/// A URL notified when matching executions finish (see `WebhookService`)
+///
+/// The signing secret is never read back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    /// Only executions on this queue, when set
+    pub queue: Option<String>,
+    /// Only executions of this task or workflow, when set
+    pub target_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWebhookParams {
+    pub url: String,
+    /// Key the `X-Rhythm-Signature` header is computed with
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    #[serde(default)]
+    pub queue: Option<String>,
+    #[serde(default)]
+    pub target_name: Option<String>,
+}
+
+/// Where a webhook delivery stands
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+    /// Not sent yet, or to be retried
+    Pending,
+    Delivered,
+    /// Gave up after the configured number of attempts
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    /// Stable lowercase label, matching the database and serde representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+/// One notification of one execution to one webhook
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: String,
+    pub execution_id: String,
+    pub event: WebhookEvent,
+    /// The JSON body posted
+    pub payload: JsonValue,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Executions measured for a claim latency SLO
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClaimLatencyCounts {
+    /// Executions claimed, or unclaimed for longer than the threshold
+    pub total: i64,
+    /// Executions first claimed within the threshold
+    pub within: i64,
+}
+
+/// Current compliance with one claim latency SLO
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SloStatus {
+    pub slo: crate::config::SloConfig,
+    pub counts: ClaimLatencyCounts,
+    /// Fraction of executions within the threshold (1.0 when none were measured)
+    pub compliance: f64,
+    /// Fraction of the allowed misses still unspent; negative once overspent
+    pub error_budget_remaining: f64,
+    pub met: bool,
+}
+
+impl SloStatus {
+    pub fn from_counts(slo: crate::config::SloConfig, counts: ClaimLatencyCounts) -> Self {
+        let misses = (counts.total - counts.within) as f64;
+        let compliance = if counts.total == 0 {
+            1.0
+        } else {
+            counts.within as f64 / counts.total as f64
+        };
+        let allowed_misses = (1.0 - slo.objective) * counts.total as f64;
+        // With nothing allowed (a 100% objective or no executions yet), each
+        // miss spends a whole budget
+        let budget = if allowed_misses > 0.0 {
+            allowed_misses
+        } else {
+            1.0
+        };
+        let error_budget_remaining = 1.0 - misses / budget;
+
+        Self {
+            met: compliance >= slo.objective,
+            slo,
+            counts,
+            compliance,
+            error_budget_remaining,
+        }
+    }
 }
 
 /// Filters for querying executions
@@ -73,9 +519,36 @@ pub struct ExecutionFilters {
     /// Filter by execution status
     pub status: Option<ExecutionStatus>,
 
+    /// Filter by execution type
+    pub exec_type: Option<ExecutionType>,
+
     /// Filter by function/workflow name
     pub target_name: Option<String>,
 
+    /// Filter by queue
+    pub queue: Option<String>,
+
+    /// Filter by namespace
+    pub namespace: Option<String>,
+
+    /// Only executions with all of these tags
+    pub tags: HashMap<String, String>,
+
+    /// Only executions created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+
+    /// Only executions created before this time
+    pub created_before: Option<DateTime<Utc>>,
+
+    /// Order by creation time, newest first by default
+    pub order: SortOrder,
+
+    /// Continue after this execution, in `order`
+    ///
+    /// Keyset pagination: stable while executions are being created, unlike
+    /// `offset`.
+    pub after: Option<ExecutionPageCursor>,
+
     /// Limit number of results
     pub limit: Option<i64>,
 
@@ -83,8 +556,161 @@ pub struct ExecutionFilters {
     pub offset: Option<i64>,
 }
 
+/// Order of queried executions by creation time
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+/// Position in a list of queried executions
+///
+/// Serialized as an opaque string: `"<created_at micros>:<execution id>"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionPageCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: String,
+}
+
+impl ExecutionPageCursor {
+    /// Cursor for the page after `execution`
+    pub fn after(execution: &Execution) -> Self {
+        Self {
+            created_at: execution.created_at,
+            id: execution.id.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for ExecutionPageCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.created_at.timestamp_micros(), self.id)
+    }
+}
+
+impl std::str::FromStr for ExecutionPageCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let invalid = || {
+            crate::errors::RhythmError::Validation(format!("Invalid execution page cursor: {}", s))
+        };
+        let (micros, id) = s.split_once(':').ok_or_else(invalid)?;
+        let created_at = micros
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            created_at,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// One page of queried executions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionPage {
+    pub executions: Vec<Execution>,
+
+    /// Cursor for the next page, or `None` if this page is the last
+    pub cursor: Option<String>,
+}
+
+/// Work entries of one queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueDepth {
+    pub queue: String,
+    /// Waiting to be claimed, including work whose claim lapsed
+    pub pending: i64,
+    /// Under a live claim
+    pub claimed: i64,
+}
+
+/// Load on one queue over a recent window, for scaling workers to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub queue: String,
+    /// Work waiting to be claimed, including work whose claim lapsed
+    pub pending: i64,
+    /// Work under a live claim
+    pub claimed: i64,
+    /// Workflows waiting on a task, timer or signal
+    pub suspended: i64,
+    /// How long the longest-waiting pending work has waited, if any is pending
+    pub oldest_pending_secs: Option<f64>,
+    /// Executions that completed within the window
+    pub completed: i64,
+    /// Executions that failed within the window
+    pub failed: i64,
+    /// Completed and failed executions per minute over the window
+    pub throughput_per_minute: f64,
+}
+
+/// The latest registered version of a workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowSummary {
+    pub name: String,
+    pub version_hash: String,
+    /// Versions registered so far, this one included
+    pub versions: i64,
+    /// Effective settings: front matter over the configured defaults
+    pub settings: JsonValue,
+    pub registered_at: DateTime<Utc>,
+}
+
+/// Position in the execution change feed
+///
+/// Serialized as an opaque string: `"<xid>"` or `"<xid>:<execution id>"`
+/// when a page ended partway through the changes of one transaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeCursor {
+    pub xid: i64,
+    pub after_id: String,
+}
+
+impl std::fmt::Display for ChangeCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.after_id.is_empty() {
+            write!(f, "{}", self.xid)
+        } else {
+            write!(f, "{}:{}", self.xid, self.after_id)
+        }
+    }
+}
+
+impl std::str::FromStr for ChangeCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (xid, after_id) = s.split_once(':').unwrap_or((s, ""));
+        let xid = xid
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid change cursor: {}", s))?;
+        Ok(Self {
+            xid,
+            after_id: after_id.to_string(),
+        })
+    }
+}
+
+/// One page of executions whose status changed since a cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionChanges {
+    /// Changed executions in change order (current state, not history)
+    pub executions: Vec<Execution>,
+
+    /// Cursor to pass to the next call
+    pub cursor: String,
+
+    /// Whether more changes are immediately available past `cursor`
+    pub has_more: bool,
+}
+
 /// Outcome of an execution (success, failure, or suspended)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ExecutionOutcome {
     Success(JsonValue),
     Failure(JsonValue),
@@ -0,0 +1,154 @@
+//! Claim authorization
+//!
+//! A `ClaimPolicy` is consulted after a worker claims an execution and before
+//! the execution is started. Denied claims are released back to the queue so a
+//! worker that is allowed to run them can pick them up; the denied worker
+//! skips them for a while and claims the work behind them instead.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::config::{ClaimPolicyConfig, ClaimRule, WorkerConfig};
+use crate::types::Execution;
+
+/// Identity a worker presents when claiming work
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkerIdentity {
+    pub id: String,
+    pub labels: HashMap<String, String>,
+}
+
+impl WorkerIdentity {
+    /// Build the identity from config, generating an id if none is configured
+    pub fn from_config(config: &WorkerConfig) -> Self {
+        Self {
+            id: config
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("worker-{}", uuid::Uuid::new_v4())),
+            labels: config.labels.clone(),
+        }
+    }
+}
+
+/// Everything a policy can inspect when deciding on a claim
+#[derive(Debug)]
+pub struct ClaimRequest<'a> {
+    pub worker: &'a WorkerIdentity,
+    pub queue: &'a str,
+    pub execution: &'a Execution,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaimDecision {
+    Allow,
+    Deny { reason: String },
+}
+
+/// Pluggable hook that can veto a claim
+///
+/// Policies run on the claim path, so they should not block.
+pub trait ClaimPolicy: Send + Sync + Debug {
+    fn authorize(&self, request: &ClaimRequest<'_>) -> ClaimDecision;
+}
+
+/// Default policy: every worker may run everything
+#[derive(Debug, Default)]
+pub struct AllowAllPolicy;
+
+impl ClaimPolicy for AllowAllPolicy {
+    fn authorize(&self, _request: &ClaimRequest<'_>) -> ClaimDecision {
+        ClaimDecision::Allow
+    }
+}
+
+/// Policy built from `[[claim_policy.rules]]` in the config file
+///
+/// Every rule that matches the execution's queue and target must be satisfied
+/// by the worker's labels; executions matched by no rule are allowed.
+#[derive(Debug, Default)]
+pub struct LabelRulePolicy {
+    rules: Vec<ClaimRule>,
+}
+
+impl LabelRulePolicy {
+    pub fn new(rules: Vec<ClaimRule>) -> Self {
+        Self { rules }
+    }
+}
+
+impl ClaimPolicy for LabelRulePolicy {
+    fn authorize(&self, request: &ClaimRequest<'_>) -> ClaimDecision {
+        for rule in &self.rules {
+            if !rule_matches(rule, request) {
+                continue;
+            }
+
+            let missing: Vec<String> = rule
+                .require_labels
+                .iter()
+                .filter(|(key, value)| request.worker.labels.get(*key) != Some(*value))
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+
+            if !missing.is_empty() {
+                return ClaimDecision::Deny {
+                    reason: format!(
+                        "worker '{}' is missing required labels: {}",
+                        request.worker.id,
+                        missing.join(", ")
+                    ),
+                };
+            }
+        }
+
+        ClaimDecision::Allow
+    }
+}
+
+fn rule_matches(rule: &ClaimRule, request: &ClaimRequest<'_>) -> bool {
+    let queue_matches = rule.queue.as_deref().is_none_or(|q| q == request.queue);
+    let target_matches = rule
+        .target_name
+        .as_deref()
+        .is_none_or(|t| t == request.execution.target_name);
+    queue_matches && target_matches
+}
+
+/// Worker identity paired with the policy that authorizes its claims
+#[derive(Debug, Clone)]
+pub struct ClaimAuthorizer {
+    pub identity: WorkerIdentity,
+    pub policy: Arc<dyn ClaimPolicy>,
+}
+
+impl ClaimAuthorizer {
+    pub fn new(identity: WorkerIdentity, policy: Arc<dyn ClaimPolicy>) -> Self {
+        Self { identity, policy }
+    }
+
+    /// Build from config; uses `AllowAllPolicy` when no rules are configured
+    pub fn from_config(worker: &WorkerConfig, claim_policy: &ClaimPolicyConfig) -> Self {
+        let policy: Arc<dyn ClaimPolicy> = if claim_policy.rules.is_empty() {
+            Arc::new(AllowAllPolicy)
+        } else {
+            Arc::new(LabelRulePolicy::new(claim_policy.rules.clone()))
+        };
+        Self::new(WorkerIdentity::from_config(worker), policy)
+    }
+
+    pub fn authorize(&self, queue: &str, execution: &Execution) -> ClaimDecision {
+        self.policy.authorize(&ClaimRequest {
+            worker: &self.identity,
+            queue,
+            execution,
+        })
+    }
+}
+
+impl Default for ClaimAuthorizer {
+    fn default() -> Self {
+        Self::from_config(&WorkerConfig::default(), &ClaimPolicyConfig::default())
+    }
+}
@@ -1,6 +1,6 @@
 //! Awaitable resolution logic
 //!
-//! Recursively resolves awaitables (Execution, Timer, All, Any, Race, Signal) to determine
+//! Recursively resolves awaitables (Execution, Timer, All, Any, Race, Signal, Stream) to determine
 //! if they're ready and what value to resume with.
 
 use anyhow::Result;
@@ -8,9 +8,11 @@ use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use std::collections::HashMap;
 
+use crate::blob_store::bind_stored;
 use crate::db;
+use crate::encryption::Binding;
 use crate::executor::{errors::ErrorInfo, json_to_val, Awaitable, Outbox, Val};
-use crate::types::ExecutionStatus;
+use crate::types::{Execution, ExecutionStatus};
 
 /// Result of checking an awaitable's status
 pub enum AwaitableStatus {
@@ -57,6 +59,9 @@ pub fn resolve_awaitable<'a>(
                 with_kv,
             } => resolve_race(pool, items, *is_object, *with_kv, db_now, outbox).await,
             Awaitable::Signal { name: _, claim_id } => resolve_signal(pool, claim_id, outbox).await,
+            Awaitable::Stream { execution_id, from } => {
+                resolve_stream(pool, execution_id, *from, outbox).await
+            }
         }
     })
 }
@@ -103,20 +108,14 @@ async fn resolve_execution(
     if let Some(execution) = db::executions::get_execution(pool, execution_id).await? {
         match execution.status {
             ExecutionStatus::Completed => {
-                let result = execution
-                    .output
-                    .map(|json| json_to_val(&json))
-                    .transpose()?
-                    .unwrap_or(Val::Null);
-                Ok(AwaitableStatus::Success(result))
+                // A workflow that continued as new settles with its last run
+                if let Some(next_id) = db::executions::get_continuation(pool, execution_id).await? {
+                    return Box::pin(resolve_execution(pool, &next_id, outbox)).await;
+                }
+                Ok(AwaitableStatus::Success(output_val(execution)?))
             }
-            ExecutionStatus::Failed => {
-                let result = execution
-                    .output
-                    .map(|json| json_to_val(&json))
-                    .transpose()?
-                    .unwrap_or(Val::Null);
-                Ok(AwaitableStatus::Error(result))
+            ExecutionStatus::Failed | ExecutionStatus::Expired | ExecutionStatus::Cancelled => {
+                Ok(AwaitableStatus::Error(output_val(execution)?))
             }
             _ => Ok(AwaitableStatus::Pending),
         }
@@ -126,6 +125,58 @@ async fn resolve_execution(
     }
 }
 
+/// An execution's output as stored, marked as read from its row so it is
+/// only decrypted as that execution's output
+fn output_val(execution: Execution) -> Result<Val> {
+    let Some(mut output) = execution.output else {
+        return Ok(Val::Null);
+    };
+    bind_stored(&mut output, Binding::output(&execution.id));
+    json_to_val(&output)
+}
+
+/// Resolve a task stream awaitable
+///
+/// Ready with the chunks numbered `from` onwards once there are any, or once
+/// the task has finished. A failed task settles with its error once its
+/// chunks have been read.
+async fn resolve_stream(
+    pool: &PgPool,
+    execution_id: &str,
+    from: usize,
+    outbox: &Outbox,
+) -> Result<AwaitableStatus> {
+    if outbox.has_execution(execution_id) {
+        return Ok(AwaitableStatus::Pending);
+    }
+    let Some(execution) = db::executions::get_execution(pool, execution_id).await? else {
+        return Ok(AwaitableStatus::Pending);
+    };
+
+    let from_seq = i32::try_from(from).unwrap_or(i32::MAX);
+    let chunks = db::task_chunks::get_chunks(pool, execution_id, from_seq).await?;
+    if chunks.is_empty() {
+        match execution.status {
+            ExecutionStatus::Failed | ExecutionStatus::Expired | ExecutionStatus::Cancelled => {
+                return Ok(AwaitableStatus::Error(output_val(execution)?));
+            }
+            ExecutionStatus::Completed => {}
+            _ => return Ok(AwaitableStatus::Pending),
+        }
+    }
+
+    let next = from + chunks.len();
+    let chunks = chunks.iter().map(json_to_val).collect::<Result<Vec<_>>>()?;
+    let mut result = HashMap::new();
+    result.insert("chunks".to_string(), Val::List(chunks));
+    result.insert("next".to_string(), Val::Num(next as f64));
+    result.insert(
+        "done".to_string(),
+        Val::Bool(execution.status == ExecutionStatus::Completed),
+    );
+    Ok(AwaitableStatus::Success(Val::Obj(result)))
+}
+
 fn resolve_timer(fire_at: DateTime<Utc>, db_now: DateTime<Utc>) -> AwaitableStatus {
     if fire_at <= db_now {
         AwaitableStatus::Success(Val::Null)
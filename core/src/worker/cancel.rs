@@ -0,0 +1,264 @@
+//! Cancelling workflows
+//!
+//! Cancelling a workflow stops it and everything it started, in one
+//! transaction: the workflow and its unfinished children move to
+//! `cancelled`, their work queue entries and saved contexts are deleted,
+//! and a parent awaiting the workflow is woken, where its `await` returns
+//! a `CANCELLED` error as for a failed task.
+//!
+//! Tasks a worker is running can be left to finish. Their results are
+//! recorded, but nothing resumes the cancelled workflow. A result reported
+//! for a task that was cancelled while it ran is dropped.
+//!
+//! `cancel_child_first` instead winds the tree down from its leaves, so
+//! each workflow runs its compensations (see `executor::compensation`)
+//! after those of the workflows it started. Every workflow in the tree is
+//! asked to cancel and its unclaimed tasks are cancelled at once. Then,
+//! level by level from the deepest, its workflows are woken: the runner
+//! holds one back while a child workflow is unfinished, then fails it with
+//! the `CANCELLED` error so it unwinds, and records it as cancelled. A
+//! level that doesn't finish within the timeout is cancelled outright.
+//! Each request and level is recorded in the execution history.
+
+use anyhow::Result;
+use serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::db;
+use crate::errors::RhythmError;
+use crate::types::{ExecutionEventType, ExecutionStatus, ExecutionType};
+
+/// How long a level of `cancel_child_first` may take by default
+pub const DEFAULT_LEVEL_TIMEOUT: Duration = Duration::from_secs(60);
+
+const LEVEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Error code of a cancelled execution
+pub const CANCELLED_ERROR_CODE: &str = "CANCELLED";
+
+/// The error stored on executions cancelled for `reason`
+pub fn cancelled_error(reason: &str) -> JsonValue {
+    json!({
+        "code": CANCELLED_ERROR_CODE,
+        "message": reason,
+    })
+}
+
+/// Cancel a workflow and the executions it started
+///
+/// With `finish_running_tasks`, tasks a worker is running are left to
+/// finish instead of being cancelled. Returns `false` if the workflow had
+/// already finished.
+pub async fn cancel_workflow(
+    pool: &PgPool,
+    execution_id: &str,
+    reason: &str,
+    finish_running_tasks: bool,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let cancelled = cancel_in(&mut tx, execution_id, reason, finish_running_tasks).await?;
+    tx.commit().await?;
+    Ok(cancelled)
+}
+
+/// Cancel a workflow as part of `tx`, see `cancel_workflow`
+pub(crate) async fn cancel_in(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+    reason: &str,
+    finish_running_tasks: bool,
+) -> Result<bool> {
+    if !lock_unfinished_workflow(tx, execution_id).await? {
+        return Ok(false);
+    }
+
+    let error = cancelled_error(reason);
+    let workflow = db::executions::cancel_execution(&mut **tx, execution_id, &error)
+        .await?
+        .ok_or_else(|| RhythmError::not_found("Execution", execution_id))?;
+    let cancelled = cancel_children(tx, execution_id, &error, finish_running_tasks).await?;
+
+    if let Some(parent_id) = &workflow.parent_workflow_id {
+        db::work_queue::enqueue_work(&mut **tx, parent_id, &workflow.queue, 0).await?;
+    }
+
+    tracing::info!(
+        execution_id,
+        reason,
+        children_cancelled = cancelled,
+        finish_running_tasks,
+        "Workflow cancelled"
+    );
+    Ok(true)
+}
+
+/// Lock a workflow for `tx`, returning `false` if it has finished
+///
+/// Fails if there is no such execution or it is not a workflow.
+async fn lock_unfinished_workflow(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+) -> Result<bool> {
+    match db::executions::lock_execution(tx, execution_id).await? {
+        Some((ExecutionType::Workflow, status)) => Ok(!status.is_terminal()),
+        Some(_) => Err(RhythmError::Validation(format!(
+            "Execution {} is not a workflow",
+            execution_id
+        ))
+        .into()),
+        None => Err(RhythmError::not_found("Execution", execution_id).into()),
+    }
+}
+
+/// Cancel a workflow tree from its leaves up, running compensations
+///
+/// Each level of workflows, deepest first, gets `level_timeout` to run its
+/// compensations and finish before it is cancelled outright and the next
+/// level is woken. Tasks a worker is running are left to finish. Returns
+/// `false` if the workflow had already finished.
+pub async fn cancel_child_first(
+    pool: &PgPool,
+    execution_id: &str,
+    reason: &str,
+    level_timeout: Duration,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    if !lock_unfinished_workflow(&mut tx, execution_id).await? {
+        return Ok(false);
+    }
+    let tree = db::executions::get_unfinished_workflow_tree(&mut *tx, execution_id).await?;
+    let ids: Vec<String> = tree.iter().map(|(id, _, _)| id.clone()).collect();
+    db::executions::request_cancel(&mut *tx, &ids, reason).await?;
+    // Tasks are leaves: those not yet claimed have nothing to wind down
+    let tasks =
+        db::executions::cancel_pending_child_tasks(&mut *tx, &ids, &cancelled_error(reason))
+            .await?;
+    db::work_queue::remove_work(&mut *tx, &tasks).await?;
+    for id in &ids {
+        db::execution_events::record_event(
+            &mut *tx,
+            id,
+            ExecutionEventType::CancelRequested,
+            Some(json!({ "reason": reason })),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    let mut levels: BTreeMap<i32, Vec<(String, String)>> = BTreeMap::new();
+    for (id, queue, depth) in tree {
+        levels.entry(depth).or_default().push((id, queue));
+    }
+    for (depth, workflows) in levels.into_iter().rev() {
+        let timed_out = cancel_level(pool, &workflows, reason, level_timeout).await?;
+        let details = json!({
+            "depth": depth,
+            "workflows": workflows.len(),
+            "timed_out": timed_out,
+        });
+        db::execution_events::record_event(
+            pool,
+            execution_id,
+            ExecutionEventType::CancelProgress,
+            Some(details),
+        )
+        .await?;
+    }
+
+    tracing::info!(
+        execution_id,
+        reason,
+        workflows = ids.len(),
+        tasks_cancelled = tasks.len(),
+        "Workflow cancelled child-first"
+    );
+    Ok(true)
+}
+
+/// Wake one level of a child-first cancellation and wait for it to finish
+///
+/// Returns the workflows that were still unfinished after `timeout`, which
+/// are then cancelled outright.
+async fn cancel_level(
+    pool: &PgPool,
+    workflows: &[(String, String)],
+    reason: &str,
+    timeout: Duration,
+) -> Result<Vec<String>> {
+    let mut tx = pool.begin().await?;
+    for (id, queue) in workflows {
+        db::work_queue::enqueue_work(&mut *tx, id, queue, 0).await?;
+    }
+    tx.commit().await?;
+
+    let ids: Vec<String> = workflows.iter().map(|(id, _)| id.clone()).collect();
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let unfinished = db::executions::get_unfinished(pool, &ids).await?;
+        if unfinished.is_empty() {
+            return Ok(unfinished);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            for id in &unfinished {
+                tracing::warn!(
+                    execution_id = %id,
+                    "Workflow did not wind down in time, cancelling it outright"
+                );
+                cancel_workflow(pool, id, reason, true).await?;
+            }
+            return Ok(unfinished);
+        }
+        tokio::time::sleep_until(deadline.min(tokio::time::Instant::now() + LEVEL_POLL_INTERVAL))
+            .await;
+    }
+}
+
+/// Record a workflow that was asked to cancel as cancelled, once it has
+/// run its compensations
+///
+/// Finishes its claimed work either way, in case it was meanwhile
+/// cancelled outright.
+pub(crate) async fn finish_cancel_request(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+    reason: &str,
+) -> Result<()> {
+    if !cancel_in(tx, execution_id, reason, true).await? {
+        db::work_queue::complete_work(&mut **tx, execution_id).await?;
+    }
+    Ok(())
+}
+
+/// Cancel what a cancelled workflow started and clear its queued work
+///
+/// Returns how many child executions were cancelled.
+pub(crate) async fn cancel_children(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    workflow_id: &str,
+    error: &JsonValue,
+    finish_running_tasks: bool,
+) -> Result<usize> {
+    let children =
+        db::executions::cancel_descendants(&mut **tx, workflow_id, error, finish_running_tasks)
+            .await?;
+
+    let mut ids = vec![workflow_id.to_string()];
+    ids.extend(children.iter().map(|child| child.id.clone()));
+    db::work_queue::remove_work(&mut **tx, &ids).await?;
+    db::workflow_execution_context::delete_contexts(&mut **tx, &ids).await?;
+
+    Ok(children.len())
+}
+
+/// Whether an execution was cancelled, locking it for the transaction
+pub(crate) async fn lock_if_cancelled(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+) -> Result<bool> {
+    Ok(matches!(
+        db::executions::lock_execution(tx, execution_id).await?,
+        Some((_, ExecutionStatus::Cancelled))
+    ))
+}
@@ -4,11 +4,24 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use sqlx::PgPool;
+use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 
+use super::authorization::{ClaimAuthorizer, ClaimDecision};
+use super::host::HostTask;
+use super::metrics::WorkerCounters;
+use super::middleware::MiddlewareChain;
 use super::runner;
+use super::runner::RunnerOptions;
+use crate::config::ConcurrencyGroupConfig;
 use crate::db;
-use crate::types::{ExecutionStatus, ExecutionType};
+use crate::encryption::Binding;
+use crate::queue_backend::QueueBackend;
+use crate::telemetry;
+use crate::types::ExecutionType;
+
+/// How long a worker skips work its claim policy refused
+const DENIED_CLAIM_BACKOFF: Duration = Duration::from_secs(60);
 
 /// Delegated action returned to the client for cooperative execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +32,9 @@ pub enum DelegatedAction {
         execution_id: String,
         target_name: String,
         inputs: JsonValue,
+        /// Trace context for the host's span running the task
+        #[serde(default)]
+        traceparent: Option<String>,
     },
     /// Continue immediately - workflow was executed, check for more work
     Continue,
@@ -28,7 +44,7 @@ pub enum DelegatedAction {
     Shutdown,
 }
 
-/// Claim and process one unit of work
+/// Claim and process one unit of work from `queue`
 ///
 /// This method attempts to claim work once and returns an action for the host:
 /// - If it's a workflow: executes it internally and returns Continue
@@ -36,64 +52,260 @@ pub enum DelegatedAction {
 /// - If no work: returns Wait with suggested duration
 ///
 /// The host should call this in a loop, handling each action appropriately.
-/// The queue is hardcoded to "default".
+///
+/// Claims the authorizer's policy denies are released back to the queue,
+/// left for an authorized worker, and skipped by this worker for
+/// `DENIED_CLAIM_BACKOFF` while it claims the next piece of work.
+/// `middleware` sees each poll and each started execution; workflow panics
+/// are counted on `counters`. Workflows are run with `runner`'s retry and
+/// diagnostics settings. Work is claimed through `queue_backend`, except
+/// that with concurrency groups in `groups` it is claimed from Postgres and
+/// executions whose group is full are left in the queue.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_cooperative_worker_loop(
     pool: &PgPool,
+    queue_backend: &dyn QueueBackend,
+    queue: &str,
     shutdown_token: &CancellationToken,
+    authorizer: &ClaimAuthorizer,
+    middleware: &MiddlewareChain,
+    counters: &WorkerCounters,
+    runner: &RunnerOptions,
+    groups: &[ConcurrencyGroupConfig],
 ) -> Result<DelegatedAction> {
-    let queue = "default";
-
     // Check for shutdown signal
     if shutdown_token.is_cancelled() {
         return Ok(DelegatedAction::Shutdown);
     }
 
-    // Try to claim work (one attempt)
-    let claimed_ids = db::work_queue::claim_work(pool, queue, 1).await?;
-    if let Some(claimed_execution_id) = claimed_ids.into_iter().next() {
-        let execution =
-            db::executions::start_execution_unless_finished(pool, &claimed_execution_id)
+    middleware.before_claim(queue);
+
+    // Claim until there is work this worker may start, or none is left
+    let worker_id = Some(authorizer.identity.id.as_str());
+    loop {
+        let claimed_id = if groups.is_empty() {
+            queue_backend
+                .claim(&[queue.to_string()], 1, worker_id)
                 .await?
-                .ok_or_else(|| {
-                    anyhow::anyhow!("Claimed execution not found: {}", claimed_execution_id)
-                })?;
+                .into_iter()
+                .map(|(execution_id, _)| execution_id)
+                .next()
+        } else {
+            db::concurrency_groups::claim_grouped_work(pool, queue, groups, worker_id).await?
+        };
+        let Some(claimed_execution_id) = claimed_id else {
+            break;
+        };
+        let started = start_claimed(
+            pool,
+            queue_backend,
+            queue,
+            claimed_execution_id,
+            authorizer,
+            middleware,
+            counters,
+            runner,
+        )
+        .await?;
+        if let Some(action) = started {
+            return Ok(action);
+        }
+    }
 
-        let is_finished = matches!(
-            execution.status,
-            ExecutionStatus::Completed | ExecutionStatus::Failed
-        );
+    // No work available, tell host to wait before retrying
+    Ok(DelegatedAction::Wait { duration_ms: 1000 })
+}
 
-        if is_finished {
-            if execution.exec_type == ExecutionType::Task {
-                tracing::error!(
-                    execution_id = %claimed_execution_id,
-                    status = ?execution.status,
-                    "Task claimed from work queue but already in terminal state - this indicates a bug"
-                );
+/// Claim up to `max_count` executions from `queues` for one host
+///
+/// The claim is a single statement, so a busy host pays one round trip
+/// for the batch instead of one per execution. Workflows among the claimed
+/// executions are run here; the tasks are returned for the host to run,
+/// each under its own claim, with `worker_id` recorded as running them.
+/// Claims the authorizer denies are released and claimed again in their
+/// place. With concurrency groups in `groups`, executions are claimed one at a
+/// time so group limits hold; otherwise they are claimed through
+/// `queue_backend`. Returns no tasks on shutdown.
+#[allow(clippy::too_many_arguments)]
+pub async fn claim_batch(
+    pool: &PgPool,
+    queue_backend: &dyn QueueBackend,
+    queues: &[String],
+    max_count: usize,
+    worker_id: &str,
+    shutdown_token: &CancellationToken,
+    authorizer: &ClaimAuthorizer,
+    middleware: &MiddlewareChain,
+    counters: &WorkerCounters,
+    runner: &RunnerOptions,
+    groups: &[ConcurrencyGroupConfig],
+) -> Result<Vec<HostTask>> {
+    if shutdown_token.is_cancelled() || max_count == 0 {
+        return Ok(Vec::new());
+    }
+    for queue in queues {
+        middleware.before_claim(queue);
+    }
+
+    // Sticky workflows prefer the worker that runs them, not the host
+    let runner_id = Some(authorizer.identity.id.as_str());
+    let mut tasks = Vec::new();
+    let mut wanted = max_count;
+    while wanted > 0 {
+        let claimed = if groups.is_empty() {
+            queue_backend.claim(queues, wanted, runner_id).await?
+        } else {
+            let mut claimed = Vec::new();
+            'claiming: for queue in queues {
+                while claimed.len() < wanted {
+                    match db::concurrency_groups::claim_grouped_work(pool, queue, groups, runner_id)
+                        .await?
+                    {
+                        Some(execution_id) => claimed.push((execution_id, queue.clone())),
+                        None => continue 'claiming,
+                    }
+                }
+                break;
             }
-            db::work_queue::complete_work(pool, &claimed_execution_id).await?;
-            return Ok(DelegatedAction::Continue);
+            claimed
+        };
+        if claimed.is_empty() {
+            break;
         }
 
-        match execution.exec_type {
-            ExecutionType::Workflow => {
-                // Execute the workflow internally
-                runner::run_workflow(pool, execution).await?;
-
-                // Return Continue so host can immediately check for more work
-                return Ok(DelegatedAction::Continue);
-            }
-            ExecutionType::Task => {
-                // Return task details to host for execution
-                return Ok(DelegatedAction::ExecuteTask {
-                    execution_id: execution.id,
-                    target_name: execution.target_name,
-                    inputs: execution.inputs,
-                });
+        // Denied claims are skipped by the next claim, so fill their places
+        wanted = 0;
+        for (execution_id, queue) in claimed {
+            let started = start_claimed(
+                pool,
+                queue_backend,
+                &queue,
+                execution_id,
+                authorizer,
+                middleware,
+                counters,
+                runner,
+            )
+            .await?;
+            match started {
+                Some(DelegatedAction::ExecuteTask {
+                    execution_id,
+                    target_name,
+                    inputs,
+                    traceparent,
+                }) => tasks.push(HostTask {
+                    execution_id,
+                    target_name,
+                    inputs,
+                    traceparent,
+                }),
+                Some(_) => {}
+                None => wanted += 1,
             }
         }
     }
 
-    // No work available, tell host to wait before retrying
-    Ok(DelegatedAction::Wait { duration_ms: 1000 })
+    if !tasks.is_empty() {
+        let ids: Vec<String> = tasks.iter().map(|t| t.execution_id.clone()).collect();
+        db::executions::record_heartbeats(pool, &ids, worker_id).await?;
+    }
+    Ok(tasks)
+}
+
+/// Start an execution claimed from `queue` and say what the host should do
+///
+/// Workflows run here and give `Continue`; tasks give `ExecuteTask`. A claim
+/// the authorizer denies is released, denied to this worker for
+/// `DENIED_CLAIM_BACKOFF`, and gives `None`.
+#[allow(clippy::too_many_arguments)]
+async fn start_claimed(
+    pool: &PgPool,
+    queue_backend: &dyn QueueBackend,
+    queue: &str,
+    claimed_execution_id: String,
+    authorizer: &ClaimAuthorizer,
+    middleware: &MiddlewareChain,
+    counters: &WorkerCounters,
+    runner: &RunnerOptions,
+) -> Result<Option<DelegatedAction>> {
+    if let Some(execution) = db::executions::get_execution(pool, &claimed_execution_id).await? {
+        if let ClaimDecision::Deny { reason } = authorizer.authorize(queue, &execution) {
+            tracing::debug!(
+                execution_id = %claimed_execution_id,
+                worker_id = %authorizer.identity.id,
+                reason = %reason,
+                "Claim denied by policy, releasing work"
+            );
+            db::work_queue::deny_work(
+                pool,
+                &claimed_execution_id,
+                &authorizer.identity.id,
+                DENIED_CLAIM_BACKOFF,
+            )
+            .await?;
+            queue_backend.release(&claimed_execution_id).await?;
+            return Ok(None);
+        }
+    }
+
+    let mut execution =
+        db::executions::start_execution_unless_finished(pool, &claimed_execution_id)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!("Claimed execution not found: {}", claimed_execution_id)
+            })?;
+
+    if execution.status.is_terminal() {
+        // Expected when a deferred work cleanup was lost, e.g. on a crash,
+        // or when the execution expired just after being claimed
+        tracing::debug!(
+            execution_id = %claimed_execution_id,
+            status = ?execution.status,
+            "Claimed execution already finished, removing its work queue entry"
+        );
+        queue_backend.complete(&claimed_execution_id).await?;
+        return Ok(Some(DelegatedAction::Continue));
+    }
+
+    execution.inputs = runner
+        .payloads
+        .resolve(execution.inputs, Binding::inputs(&claimed_execution_id))
+        .await?;
+    middleware.after_claim(&mut execution);
+
+    match execution.exec_type {
+        ExecutionType::Workflow => {
+            // Execute the workflow internally; a panic fails only this execution
+            runner::run_workflow_isolated(pool, counters, runner, execution).await?;
+
+            // Return Continue so host can immediately check for more work
+            Ok(Some(DelegatedAction::Continue))
+        }
+        ExecutionType::Task => {
+            // The host's span continues from the claim's when it is exported
+            let stored = db::executions::get_traceparent(pool, &execution.id).await?;
+            let span = telemetry::execution_span("claim", &execution, stored.as_deref());
+            let traceparent = span.in_scope(|| {
+                tracing::debug!("Handing task to host");
+                telemetry::current_traceparent()
+            });
+
+            // Return task details to host for execution
+            Ok(Some(DelegatedAction::ExecuteTask {
+                execution_id: execution.id,
+                target_name: execution.target_name,
+                inputs: execution.inputs,
+                traceparent: traceparent.or(stored),
+            }))
+        }
+        ExecutionType::External => {
+            // Never enqueued on purpose; drop the entry rather than run it
+            tracing::error!(
+                execution_id = %claimed_execution_id,
+                "External execution claimed from work queue - this indicates a bug"
+            );
+            queue_backend.complete(&claimed_execution_id).await?;
+            Ok(Some(DelegatedAction::Continue))
+        }
+    }
 }
@@ -0,0 +1,164 @@
+//! Deferred work queue cleanup
+//!
+//! Deleting a finished task's claimed work row is bookkeeping: the row only
+//! stops the same execution being claimed again, and a finished task never
+//! is. `WorkCleanup` takes those deletes off the completion path and flushes
+//! them in batches. Batches grow with load: a flush takes whatever has
+//! arrived, waiting at most `FLUSH_INTERVAL` for more, up to `MAX_BATCH`.
+//!
+//! Nothing is persisted. If the process dies before a flush, the rows keep
+//! their lease, and once it expires the claim loop finds the execution
+//! already finished and deletes the row itself.
+
+use anyhow::Result;
+use sqlx::PgPool;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::db;
+
+/// Most deletes sent in one statement
+const MAX_BATCH: usize = 500;
+
+/// Longest a delete waits for others to share its batch
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Pending deletes held before callers fall back to deleting inline
+const CHANNEL_CAPACITY: usize = 10_000;
+
+enum Message {
+    Delete(String),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Batches deletes of finished executions' claimed work rows
+pub struct WorkCleanup {
+    pool: PgPool,
+    shutdown_token: CancellationToken,
+    // Started on first use, so it is always spawned inside a runtime
+    sender: OnceLock<mpsc::Sender<Message>>,
+}
+
+impl std::fmt::Debug for WorkCleanup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkCleanup").finish_non_exhaustive()
+    }
+}
+
+impl WorkCleanup {
+    /// The flusher drains and stops when `shutdown_token` is cancelled
+    pub fn new(pool: PgPool, shutdown_token: CancellationToken) -> Self {
+        Self {
+            pool,
+            shutdown_token,
+            sender: OnceLock::new(),
+        }
+    }
+
+    /// Delete `execution_id`'s claimed work row in a later batch
+    ///
+    /// Deletes inline instead when the backlog is full or the flusher has
+    /// stopped.
+    pub async fn defer(&self, execution_id: String) -> Result<()> {
+        match self.sender().try_send(Message::Delete(execution_id)) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(Message::Delete(id)))
+            | Err(mpsc::error::TrySendError::Closed(Message::Delete(id))) => {
+                db::work_queue::complete_work(&self.pool, &id).await
+            }
+            Err(_) => unreachable!("only deletes are sent with try_send"),
+        }
+    }
+
+    /// Wait until every delete deferred so far has been attempted
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.sender().send(Message::Flush(done)).await.is_ok() {
+            let _ = flushed.await;
+        }
+    }
+
+    fn sender(&self) -> &mpsc::Sender<Message> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+            tokio::spawn(run_flusher(
+                self.pool.clone(),
+                receiver,
+                self.shutdown_token.clone(),
+            ));
+            sender
+        })
+    }
+}
+
+async fn run_flusher(
+    pool: PgPool,
+    mut receiver: mpsc::Receiver<Message>,
+    shutdown_token: CancellationToken,
+) {
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    loop {
+        let first = tokio::select! {
+            _ = shutdown_token.cancelled() => break,
+            message = receiver.recv() => message,
+        };
+        let Some(first) = first else { break };
+
+        let mut waiters = Vec::new();
+        let mut message = Some(first);
+        let deadline = tokio::time::sleep(FLUSH_INTERVAL);
+        tokio::pin!(deadline);
+        while let Some(next) = message.take() {
+            match next {
+                Message::Delete(id) => batch.push(id),
+                // Flush what we have now rather than waiting out the interval
+                Message::Flush(done) => {
+                    waiters.push(done);
+                    break;
+                }
+            }
+            if batch.len() >= MAX_BATCH {
+                break;
+            }
+            message = tokio::select! {
+                _ = &mut deadline => None,
+                message = receiver.recv() => message,
+            };
+        }
+
+        flush_batch(&pool, &mut batch).await;
+        for done in waiters {
+            let _ = done.send(());
+        }
+    }
+
+    // Shutting down: take everything already queued
+    receiver.close();
+    while let Some(message) = receiver.recv().await {
+        match message {
+            Message::Delete(id) => batch.push(id),
+            Message::Flush(done) => {
+                flush_batch(&pool, &mut batch).await;
+                let _ = done.send(());
+            }
+        }
+    }
+    flush_batch(&pool, &mut batch).await;
+}
+
+async fn flush_batch(pool: &PgPool, batch: &mut Vec<String>) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = db::work_queue::complete_work_batch(pool, batch).await {
+        // The rows' leases expire and the claim loop cleans them up
+        tracing::warn!(
+            count = batch.len(),
+            "Deferred work queue cleanup failed, leaving rows to lease expiry: {:#}",
+            e
+        );
+    }
+    batch.clear();
+}
@@ -3,9 +3,17 @@
 use anyhow::{Context, Result};
 use serde_json::Value as JsonValue;
 use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
 
+use super::cancel;
+use super::cleanup::WorkCleanup;
+use super::retry::{retry_delay, RetryDecision, RetryRules};
+use crate::blob_store::PayloadStore;
 use crate::db;
-use crate::types::ExecutionOutcome;
+use crate::encryption::Binding;
+use crate::errors::RhythmError;
+use crate::invariants;
+use crate::types::{ExecutionEventType, ExecutionOutcome, ExecutionStatus, ExecutionType};
 
 /// Finish work (complete, fail, or suspend) and re-queue parent if exists
 ///
@@ -23,6 +31,33 @@ pub async fn finish_work(
     execution_id: &str,
     outcome: ExecutionOutcome,
 ) -> Result<()> {
+    finish_execution(tx, execution_id, outcome).await?;
+
+    // Complete the work queue entry
+    db::work_queue::complete_work(&mut **tx, execution_id)
+        .await
+        .context("Failed to complete work queue entry")?;
+
+    Ok(())
+}
+
+/// Record an execution's outcome and re-queue its parent
+///
+/// Leaves the execution's claimed work queue entry in place. The outcome
+/// of an execution cancelled while it ran is dropped; for a workflow, the
+/// children its run just started are cancelled too.
+async fn finish_execution(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+    outcome: ExecutionOutcome,
+) -> Result<()> {
+    if cancel::lock_if_cancelled(tx, execution_id).await? {
+        let error = cancel::cancelled_error("Workflow was cancelled");
+        cancel::cancel_children(tx, execution_id, &error, true).await?;
+        tracing::debug!(execution_id, "Dropping outcome of a cancelled execution");
+        return Ok(());
+    }
+
     // Handle execution based on outcome
     let execution = match outcome {
         ExecutionOutcome::Success(output) => {
@@ -40,16 +75,26 @@ pub async fn finish_work(
             .context("Failed to suspend execution")?,
     };
 
-    let execution =
-        execution.ok_or_else(|| anyhow::anyhow!("Execution not found: {}", execution_id))?;
-
-    // Complete the work queue entry
-    db::work_queue::complete_work(&mut **tx, execution_id)
-        .await
-        .context("Failed to complete work queue entry")?;
+    let execution = execution.ok_or_else(|| RhythmError::not_found("Execution", execution_id))?;
 
     // Re-queue parent workflow if this execution has a parent
     if let Some(ref parent_id) = execution.parent_workflow_id {
+        if matches!(
+            execution.status,
+            ExecutionStatus::Completed | ExecutionStatus::Failed
+        ) {
+            let details = serde_json::json!({
+                "execution_id": execution.id,
+                "status": execution.status,
+            });
+            db::execution_events::record_event(
+                &mut **tx,
+                parent_id,
+                ExecutionEventType::TaskCompleted,
+                Some(details),
+            )
+            .await?;
+        }
         db::work_queue::enqueue_work(&mut **tx, parent_id, &execution.queue, 0)
             .await
             .context("Failed to re-queue parent workflow")?;
@@ -68,6 +113,20 @@ pub async fn complete_work(
     execution_id: &str,
     result: Option<JsonValue>,
     error: Option<JsonValue>,
+) -> Result<()> {
+    complete_work_with_cleanup(pool, execution_id, result, error, None).await
+}
+
+/// Complete work, optionally leaving the work queue entry to `cleanup`
+///
+/// With `cleanup`, the outcome and the parent's wake-up still commit
+/// together; only the delete of the claimed entry is deferred.
+pub async fn complete_work_with_cleanup(
+    pool: &PgPool,
+    execution_id: &str,
+    result: Option<JsonValue>,
+    error: Option<JsonValue>,
+    cleanup: Option<&WorkCleanup>,
 ) -> Result<()> {
     let mut tx = pool.begin().await?;
 
@@ -75,13 +134,253 @@ pub async fn complete_work(
         (Some(output), None) => ExecutionOutcome::Success(output),
         (None, Some(error_output)) => ExecutionOutcome::Failure(error_output),
         _ => {
-            return Err(anyhow::anyhow!(
-                "Exactly one of result or error must be provided"
-            ));
+            return Err(RhythmError::Validation(
+                "Exactly one of result or error must be provided".to_string(),
+            )
+            .into());
         }
     };
 
-    finish_work(&mut tx, execution_id, outcome).await?;
+    match cleanup {
+        Some(_) => finish_execution(&mut tx, execution_id, outcome).await?,
+        None => finish_work(&mut tx, execution_id, outcome).await?,
+    }
+
+    tx.commit().await?;
+
+    if let Some(cleanup) = cleanup {
+        cleanup.defer(execution_id.to_string()).await?;
+    }
+
+    Ok(())
+}
+
+/// Record the outcomes of several claimed executions in one transaction
+///
+/// Like `complete_work_with_cleanup` for each, but the status updates, the
+/// work queue deletes and the parents' events and wake-ups are one
+/// statement each. Outcomes of cancelled executions are dropped. Returns
+/// the outcomes that were not recorded, with why: no such execution, it
+/// already finished, or it was listed twice. The rest are recorded anyway.
+pub async fn complete_work_batch(
+    pool: &PgPool,
+    outcomes: Vec<(String, ExecutionOutcome)>,
+    cleanup: Option<&WorkCleanup>,
+) -> Result<Vec<(String, anyhow::Error)>> {
+    let mut tx = pool.begin().await?;
+    let ids: Vec<String> = outcomes.iter().map(|(id, _)| id.clone()).collect();
+    let locked: HashMap<String, (ExecutionType, ExecutionStatus)> =
+        db::executions::lock_executions(&mut tx, &ids)
+            .await?
+            .into_iter()
+            .map(|(id, exec_type, status)| (id, (exec_type, status)))
+            .collect();
+
+    let mut rejected = Vec::new();
+    // Whose claimed work is done, cancelled executions' included
+    let mut done = Vec::new();
+    let mut seen = HashSet::new();
+    let (mut finish_ids, mut statuses, mut outputs) = (Vec::new(), Vec::new(), Vec::new());
+    for (id, outcome) in outcomes {
+        let (status, output) = match outcome {
+            ExecutionOutcome::Success(output) => (ExecutionStatus::Completed, output),
+            ExecutionOutcome::Failure(error) => (ExecutionStatus::Failed, error),
+            ExecutionOutcome::Suspended => {
+                let error =
+                    RhythmError::Validation("Only results and errors can be batched".into());
+                rejected.push((id, error.into()));
+                continue;
+            }
+        };
+        if !seen.insert(id.clone()) {
+            let error = RhythmError::Conflict(format!("Outcome of {} was given twice", id));
+            rejected.push((id, error.into()));
+            continue;
+        }
+        match locked.get(&id) {
+            None => {
+                let error = RhythmError::not_found("Execution", &id);
+                rejected.push((id, error.into()));
+            }
+            Some((_, ExecutionStatus::Cancelled)) => {
+                tracing::debug!(execution_id = %id, "Dropping outcome of a cancelled execution");
+                done.push(id);
+            }
+            Some((_, from)) if from.is_terminal() => {
+                let error = RhythmError::Conflict(format!("Execution {} already finished", id));
+                rejected.push((id, error.into()));
+            }
+            Some((exec_type, from)) => {
+                if let Err(e) = invariants::check_transition(&id, exec_type, from, &status) {
+                    rejected.push((id, e.into()));
+                    continue;
+                }
+                done.push(id.clone());
+                finish_ids.push(id);
+                statuses.push(status);
+                outputs.push(output);
+            }
+        }
+    }
+
+    let finished =
+        db::executions::finish_executions(&mut *tx, &finish_ids, &statuses, &outputs).await?;
+    let (mut parents, mut queues, mut details) = (Vec::new(), Vec::new(), Vec::new());
+    for execution in finished {
+        if let Some(parent_id) = execution.parent_workflow_id {
+            details.push(serde_json::json!({
+                "execution_id": execution.id,
+                "status": execution.status,
+            }));
+            parents.push(parent_id);
+            queues.push(execution.queue);
+        }
+    }
+    db::execution_events::record_events(
+        &mut *tx,
+        &parents,
+        ExecutionEventType::TaskCompleted,
+        &details,
+    )
+    .await?;
+    db::work_queue::enqueue_work_batch(&mut *tx, &parents, &queues, 0)
+        .await
+        .context("Failed to re-queue parent workflows")?;
+    if cleanup.is_none() {
+        db::work_queue::complete_work_batch(&mut *tx, &done)
+            .await
+            .context("Failed to complete work queue entries")?;
+    }
+    tx.commit().await?;
+
+    if let Some(cleanup) = cleanup {
+        for id in done {
+            cleanup.defer(id).await?;
+        }
+    }
+    Ok(rejected)
+}
+
+/// Fail a task, or put it back on its queue if `rules` allow a retry
+///
+/// `retry` is whether the host thinks the failure is worth retrying.
+/// Returns `true` when the task was re-queued; its parent is only woken
+/// once the task finally completes or fails. A task created with a retry
+/// policy is redelivered once its backoff delay has passed. Rules see the
+/// error as given; it is stored sealed by `payloads`.
+pub async fn fail_work(
+    pool: &PgPool,
+    execution_id: &str,
+    error: JsonValue,
+    retry: bool,
+    rules: &RetryRules,
+    cleanup: Option<&WorkCleanup>,
+    payloads: &PayloadStore,
+) -> Result<bool> {
+    if retry_failed(pool, execution_id, &error, retry, rules).await? {
+        return Ok(true);
+    }
+
+    let error = payloads.seal(error, Binding::output(execution_id))?;
+    complete_work_with_cleanup(pool, execution_id, None, Some(error), cleanup).await?;
+    Ok(false)
+}
+
+/// Put a failed task back on its queue if `rules` allow a retry
+///
+/// Returns `false`, having changed nothing, when the failure is final.
+pub(crate) async fn retry_failed(
+    pool: &PgPool,
+    execution_id: &str,
+    error: &JsonValue,
+    retry: bool,
+    rules: &RetryRules,
+) -> Result<bool> {
+    let execution = db::executions::get_execution(pool, execution_id)
+        .await?
+        .ok_or_else(|| RhythmError::not_found("Execution", execution_id))?;
+    let policy = db::executions::get_retry_policy(pool, execution_id).await?;
+
+    if execution.exec_type == ExecutionType::Task
+        && rules.decide_with_policy(&execution, policy.as_ref(), error, retry)
+            == RetryDecision::Retry
+    {
+        let db_now = db::get_db_time(pool).await?;
+        let mut tx = pool.begin().await?;
+        if let Some(retried) = db::executions::retry_execution(&mut *tx, execution_id).await? {
+            db::work_queue::complete_work(&mut *tx, execution_id)
+                .await
+                .context("Failed to complete work queue entry")?;
+            let delay = policy
+                .as_ref()
+                .map(|policy| retry_delay(policy, retried.attempt.max(1) as u32, execution_id))
+                .unwrap_or_default();
+            if delay.is_zero() {
+                db::work_queue::enqueue_work(&mut *tx, execution_id, &retried.queue, 0)
+                    .await
+                    .context("Failed to re-queue execution for retry")?;
+            } else {
+                let params =
+                    crate::services::scheduler_service::ScheduledParams::ScheduledExecution {
+                        execution_id: execution_id.to_string(),
+                        queue: retried.queue.clone(),
+                        priority: 0,
+                    };
+                let params_json = serde_json::to_value(&params)
+                    .context("Failed to serialize scheduled params")?;
+                let run_at = db_now + delay;
+                db::scheduled_queue::schedule_item(&mut *tx, run_at.naive_utc(), &params_json)
+                    .await
+                    .context("Failed to schedule execution for retry")?;
+            }
+            tx.commit().await?;
+
+            tracing::info!(
+                execution_id,
+                attempt = retried.attempt,
+                retry_in_ms = delay.as_millis() as u64,
+                code = super::retry::error_code(error),
+                "Retrying failed task"
+            );
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Settle an external task by its completion token
+///
+/// The token is the ID `ExternalTask.create` gave the execution. Only
+/// external executions that haven't finished yet can be settled, so a
+/// replayed callback fails instead of overwriting the first result.
+pub async fn complete_external_task(
+    pool: &PgPool,
+    token: &str,
+    outcome: ExecutionOutcome,
+) -> Result<()> {
+    if matches!(outcome, ExecutionOutcome::Suspended) {
+        return Err(
+            RhythmError::Validation("External tasks cannot be suspended".to_string()).into(),
+        );
+    }
+
+    let mut tx = pool.begin().await?;
+
+    match db::executions::lock_execution(&mut tx, token).await? {
+        Some((ExecutionType::External, ExecutionStatus::Pending)) => {}
+        Some((ExecutionType::External, _)) => {
+            return Err(RhythmError::Conflict(format!(
+                "External task already finished for token: {}",
+                token
+            ))
+            .into());
+        }
+        _ => {
+            return Err(RhythmError::not_found("External task", token).into());
+        }
+    }
+
+    finish_work(&mut tx, token, outcome).await?;
 
     tx.commit().await?;
 
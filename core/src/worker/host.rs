@@ -0,0 +1,121 @@
+//! Worker loops driven by core with the host supplying only task code
+//!
+//! Rather than polling `run_cooperative_worker_loop` itself, a host can hand
+//! core a `TaskDispatcher` and let `WorkerService::run_host_loop` do the rest:
+//! claiming across queues, running workflows, keeping task claims alive with
+//! heartbeats, recording task outcomes and stopping on shutdown.
+//!
+//! Hosts that run tasks off the claiming thread (e.g. a thread pool) can
+//! report finished tasks together with `WorkerService::record_outcomes`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::db;
+
+/// How often a running task sends a heartbeat, extending its claim
+///
+/// Well inside the one-minute lease taken when claiming.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Longest the loop waits without checking back with the host
+pub(crate) const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A task handed to the host to run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostTask {
+    pub execution_id: String,
+    pub target_name: String,
+    pub inputs: JsonValue,
+    /// W3C trace context to parent the host's span running the task to
+    #[serde(default)]
+    pub traceparent: Option<String>,
+}
+
+/// What running a task in the host came to
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskOutcome {
+    Complete(JsonValue),
+    /// `retry` is the host's judgement, subject to the retry rules
+    Fail {
+        error: JsonValue,
+        retry: bool,
+    },
+}
+
+/// A task's outcome, reported back in a batch with others
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskCompletion {
+    pub execution_id: String,
+    pub outcome: TaskOutcome,
+}
+
+/// Host callbacks for `WorkerService::run_host_loop`
+///
+/// Called on the thread running the loop, so a host with a global lock (the
+/// Python GIL) takes it only inside these calls.
+pub trait TaskDispatcher {
+    /// Run one task to completion
+    fn dispatch(&mut self, task: HostTask) -> TaskOutcome;
+
+    /// Called between polls and while waiting; an error stops the loop
+    ///
+    /// Lets the host run its own signal handling.
+    fn check_interrupt(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Record a heartbeat for a running task and extend its claim
+///
+/// Returns `false` once the task is no longer running under a claim, e.g.
+/// after the reaper or a lapsed lease gave it to another worker.
+pub async fn heartbeat(pool: &PgPool, execution_id: &str, worker_id: &str) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let running = db::executions::record_heartbeat(&mut *tx, execution_id, worker_id).await?;
+    let claimed = running && db::work_queue::extend_claim(&mut *tx, execution_id).await?;
+    tx.commit().await?;
+    Ok(claimed)
+}
+
+/// Keep sending heartbeats for `execution_id` until `stop` is cancelled
+pub fn spawn_heartbeat(
+    pool: PgPool,
+    execution_id: String,
+    worker_id: String,
+    stop: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        // The first tick is immediate and the claim is fresh
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => return,
+                _ = interval.tick() => {}
+            }
+            match heartbeat(&pool, &execution_id, &worker_id).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::warn!(
+                        execution_id = %execution_id,
+                        "Task claim lost while running, it may run again elsewhere"
+                    );
+                    return;
+                }
+                Err(e) => {
+                    // The next beat may get through before the lease runs out
+                    tracing::warn!(
+                        execution_id = %execution_id,
+                        error = format!("{:#}", e),
+                        "Failed to extend task claim"
+                    );
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,62 @@
+//! Worker counters
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Live worker counters, one set per Application
+#[derive(Debug, Default)]
+pub struct WorkerCounters {
+    workflow_panics: AtomicU64,
+    runner_retries: AtomicU64,
+    runner_failures: AtomicU64,
+    step_budget_yields: AtomicU64,
+    step_budget_failures: AtomicU64,
+}
+
+impl WorkerCounters {
+    pub fn snapshot(&self) -> WorkerMetrics {
+        WorkerMetrics {
+            workflow_panics: self.workflow_panics.load(Ordering::Relaxed),
+            runner_retries: self.runner_retries.load(Ordering::Relaxed),
+            runner_failures: self.runner_failures.load(Ordering::Relaxed),
+            step_budget_yields: self.step_budget_yields.load(Ordering::Relaxed),
+            step_budget_failures: self.step_budget_failures.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn record_workflow_panic(&self) {
+        self.workflow_panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_runner_retry(&self) {
+        self.runner_retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_runner_failure(&self) {
+        self.runner_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_step_budget_yield(&self) {
+        self.step_budget_yields.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_step_budget_failure(&self) {
+        self.step_budget_failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time copy of the worker counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WorkerMetrics {
+    /// Workflow runs that panicked and were failed instead of crashing the worker
+    pub workflow_panics: u64,
+    /// Workflow runs that hit a transient error and were scheduled to retry
+    pub runner_retries: u64,
+    /// Workflow runs whose error failed the execution
+    pub runner_failures: u64,
+    /// Workflow runs that used up their step budget and were queued to continue
+    pub step_budget_yields: u64,
+    /// Workflow runs that used up their step budget and failed with
+    /// `LOOP_BUDGET_EXCEEDED`
+    pub step_budget_failures: u64,
+}
@@ -0,0 +1,147 @@
+//! Worker middleware
+//!
+//! A `WorkerMiddleware` observes the claim loop and task completions, so
+//! embedders can add logging, metrics, or payload transformation without
+//! forking the loop. Middlewares run in registration order on the way in
+//! (`before_claim`, `before_complete`) and in reverse order on the way out
+//! (`after_claim`, `after_fail`), like nested wrappers.
+
+use serde_json::Value as JsonValue;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::types::Execution;
+
+/// Hooks around claiming and completing work
+///
+/// Every hook has a no-op default. Hooks run on the worker's hot path, so
+/// they should not block.
+pub trait WorkerMiddleware: Send + Sync + Debug {
+    /// Before the worker polls `queue` for work
+    fn before_claim(&self, _queue: &str) {}
+
+    /// After an execution is claimed and started, before it runs
+    ///
+    /// Changes to `execution.inputs` are what the task or workflow receives.
+    fn after_claim(&self, _execution: &mut Execution) {}
+
+    /// Before a task's result is recorded
+    ///
+    /// Changes to `result` are what gets stored and returned to the workflow.
+    fn before_complete(&self, _execution_id: &str, _result: &mut JsonValue) {}
+
+    /// After a task's failure is recorded
+    fn after_fail(&self, _execution_id: &str, _error: &JsonValue) {}
+}
+
+/// Ordered set of middlewares a worker runs
+#[derive(Debug, Clone, Default)]
+pub struct MiddlewareChain {
+    middlewares: Vec<Arc<dyn WorkerMiddleware>>,
+}
+
+impl MiddlewareChain {
+    pub fn new(middlewares: Vec<Arc<dyn WorkerMiddleware>>) -> Self {
+        Self { middlewares }
+    }
+
+    /// Add a middleware inside the ones already registered
+    pub fn push(&mut self, middleware: Arc<dyn WorkerMiddleware>) {
+        self.middlewares.push(middleware);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.middlewares.is_empty()
+    }
+
+    pub fn before_claim(&self, queue: &str) {
+        for middleware in &self.middlewares {
+            middleware.before_claim(queue);
+        }
+    }
+
+    pub fn after_claim(&self, execution: &mut Execution) {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after_claim(execution);
+        }
+    }
+
+    pub fn before_complete(&self, execution_id: &str, result: &mut JsonValue) {
+        for middleware in &self.middlewares {
+            middleware.before_complete(execution_id, result);
+        }
+    }
+
+    pub fn after_fail(&self, execution_id: &str, error: &JsonValue) {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after_fail(execution_id, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[derive(Debug)]
+    struct Recorder {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl WorkerMiddleware for Recorder {
+        fn before_claim(&self, queue: &str) {
+            self.record(format!("before_claim:{}", queue));
+        }
+
+        fn after_fail(&self, execution_id: &str, _error: &JsonValue) {
+            self.record(format!("after_fail:{}", execution_id));
+        }
+
+        fn before_complete(&self, _execution_id: &str, result: &mut JsonValue) {
+            result["seen"]
+                .as_array_mut()
+                .unwrap()
+                .push(json!(self.name));
+        }
+    }
+
+    impl Recorder {
+        fn record(&self, call: String) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{}:{}", self.name, call));
+        }
+    }
+
+    #[test]
+    fn test_chain_runs_before_hooks_in_order_and_after_hooks_reversed() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = MiddlewareChain::default();
+        for name in ["outer", "inner"] {
+            chain.push(Arc::new(Recorder {
+                name,
+                calls: calls.clone(),
+            }));
+        }
+
+        chain.before_claim("default");
+        chain.after_fail("exec-1", &json!({}));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "outer:before_claim:default",
+                "inner:before_claim:default",
+                "inner:after_fail:exec-1",
+                "outer:after_fail:exec-1",
+            ]
+        );
+
+        let mut result = json!({"seen": []});
+        chain.before_complete("exec-1", &mut result);
+        assert_eq!(result, json!({"seen": ["outer", "inner"]}));
+    }
+}
@@ -2,16 +2,47 @@
 //!
 //! This module provides the worker loop logic for claiming and executing work.
 
+pub mod authorization;
 pub mod awaitable;
+pub mod cancel;
 pub mod claim;
+pub mod cleanup;
 pub mod complete;
+pub mod host;
+pub mod metrics;
+pub mod middleware;
+pub mod output_schema;
+pub mod reaper;
+pub mod replay;
+pub mod retry;
 pub mod runner;
+pub mod runner_retry;
+pub mod shutdown;
 pub mod signals;
+pub mod sticky;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export public API
-pub use claim::{run_cooperative_worker_loop, DelegatedAction};
-pub use complete::complete_work;
-pub use runner::run_workflow;
+pub use authorization::{
+    AllowAllPolicy, ClaimAuthorizer, ClaimDecision, ClaimPolicy, ClaimRequest, LabelRulePolicy,
+    WorkerIdentity,
+};
+pub use cancel::{cancel_child_first, cancel_workflow};
+pub use claim::{claim_batch, run_cooperative_worker_loop, DelegatedAction};
+pub use cleanup::WorkCleanup;
+pub use complete::{
+    complete_external_task, complete_work, complete_work_batch, complete_work_with_cleanup,
+    fail_work,
+};
+pub use host::{HostTask, TaskCompletion, TaskDispatcher, TaskOutcome};
+pub use metrics::{WorkerCounters, WorkerMetrics};
+pub use middleware::{MiddlewareChain, WorkerMiddleware};
+pub use output_schema::OutputSchemas;
+pub use reaper::{ReapedTasks, ReaperPolicy};
+pub use retry::{RetryDecision, RetryRules};
+pub use runner::{run_workflow, run_workflow_isolated, RunnerOptions};
+pub use runner_retry::RunnerRetryPolicy;
+pub use shutdown::{DrainReport, Worker};
+pub use sticky::{StickyOptions, VmCache};
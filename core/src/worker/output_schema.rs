@@ -0,0 +1,133 @@
+//! Output validation of completed tasks
+//!
+//! A task config's `output_schema` declares the fields its result must
+//! have, as `name: type` entries like a workflow's `inputs:`. A result that
+//! doesn't fit fails the task with an `INVALID_OUTPUT` error listing each
+//! problem, so the workflow awaiting it sees an error instead of data it
+//! can't use. Defaults in the schema are filled into results that fit.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+
+use crate::config::TaskConfig;
+use crate::parser::input_schema::InputSchema;
+use crate::types::ExecutionOutcome;
+
+/// Error code of a task whose result doesn't fit its output schema
+pub const INVALID_OUTPUT_CODE: &str = "INVALID_OUTPUT";
+
+/// Output schemas by task name
+#[derive(Debug, Clone, Default)]
+pub struct OutputSchemas {
+    schemas: HashMap<String, InputSchema>,
+}
+
+impl OutputSchemas {
+    /// The schemas of the tasks that declare one
+    pub fn from_task_configs(tasks: &[TaskConfig]) -> Result<Self> {
+        let mut schemas = HashMap::new();
+        for task in tasks {
+            if let Some(declared) = &task.output_schema {
+                let schema = InputSchema::from_declared(declared, "output", "field")
+                    .with_context(|| format!("Task '{}'", task.target_name))?;
+                schemas.insert(task.target_name.clone(), schema);
+            }
+        }
+        Ok(Self { schemas })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty()
+    }
+
+    /// Check `result` of a `target_name` task against its schema
+    ///
+    /// Returns the result with defaults filled in, or the task's failure.
+    pub fn check(&self, target_name: &str, result: JsonValue) -> ExecutionOutcome {
+        let Some(schema) = self.schemas.get(target_name) else {
+            return ExecutionOutcome::Success(result);
+        };
+        match schema.apply_to("output", result) {
+            Ok(result) => ExecutionOutcome::Success(result),
+            Err(problems) => ExecutionOutcome::Failure(json!({
+                "code": INVALID_OUTPUT_CODE,
+                "message": format!(
+                    "Invalid output of task '{}': {}",
+                    target_name,
+                    problems.join("; ")
+                ),
+                "problems": problems,
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schemas() -> OutputSchemas {
+        OutputSchemas::from_task_configs(&[TaskConfig {
+            target_name: "charge_card".to_string(),
+            output_schema: Some(json!({
+                "charge_id": "string",
+                "captured": { "type": "boolean", "default": false },
+            })),
+            ..Default::default()
+        }])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fitting_output_gets_defaults() {
+        assert_eq!(
+            schemas().check("charge_card", json!({ "charge_id": "ch_1" })),
+            ExecutionOutcome::Success(json!({ "charge_id": "ch_1", "captured": false }))
+        );
+        assert_eq!(
+            schemas().check("ship", json!(42)),
+            ExecutionOutcome::Success(json!(42))
+        );
+    }
+
+    #[test]
+    fn test_misfit_output_fails_with_each_problem() {
+        let ExecutionOutcome::Failure(error) =
+            schemas().check("charge_card", json!({ "captured": "yes" }))
+        else {
+            panic!("Expected a failure");
+        };
+        assert_eq!(error["code"], INVALID_OUTPUT_CODE);
+        assert_eq!(
+            error["problems"],
+            json!([
+                "captured must be a boolean, got string",
+                "charge_id is required"
+            ])
+        );
+
+        let ExecutionOutcome::Failure(error) = schemas().check("charge_card", json!("ch_1")) else {
+            panic!("Expected a failure");
+        };
+        assert_eq!(
+            error["message"],
+            "Invalid output of task 'charge_card': output must be an object, got string"
+        );
+    }
+
+    #[test]
+    fn test_invalid_schema_is_rejected() {
+        let error = OutputSchemas::from_task_configs(&[TaskConfig {
+            target_name: "charge_card".to_string(),
+            output_schema: Some(json!({ "charge_id": "strng" })),
+            ..Default::default()
+        }])
+        .unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", error),
+            "Task 'charge_card': Invalid output schema: field `charge_id`: unknown type `strng`"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Reaping tasks whose worker went silent
+//!
+//! A worker running a task sends heartbeats (see `host::heartbeat`). If
+//! it dies, the task stays `running` with its heartbeat getting older. The
+//! reaper, run by the internal worker, finds running tasks whose heartbeat
+//! is older than the timeout and puts each back on its queue for another
+//! worker, or fails it with `HEARTBEAT_TIMEOUT` once it has been reclaimed
+//! `max_reclaims` times.
+//!
+//! Should the silent worker come back, its claim is gone, so its heartbeat
+//! returns `false` and the outcome it reports is for a task that has since
+//! been run again or failed.
+
+use anyhow::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::complete::finish_work;
+use crate::config::ReaperConfig;
+use crate::db;
+use crate::types::ExecutionOutcome;
+
+/// Error code of a task failed for missing its heartbeats
+pub const HEARTBEAT_TIMEOUT_CODE: &str = "HEARTBEAT_TIMEOUT";
+
+/// When a running task counts as abandoned, and what to do with it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReaperPolicy {
+    pub heartbeat_timeout: Duration,
+    /// Times a task is put back on its queue before it fails instead
+    pub max_reclaims: u32,
+}
+
+impl Default for ReaperPolicy {
+    fn default() -> Self {
+        Self::from(&ReaperConfig::default())
+    }
+}
+
+impl From<&ReaperConfig> for ReaperPolicy {
+    fn from(config: &ReaperConfig) -> Self {
+        Self {
+            heartbeat_timeout: Duration::from_secs(config.heartbeat_timeout_secs),
+            max_reclaims: config.max_reclaims,
+        }
+    }
+}
+
+/// Tasks handled by one reaper pass
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReapedTasks {
+    /// Put back on their queue
+    pub reclaimed: Vec<String>,
+    /// Failed with `HEARTBEAT_TIMEOUT`
+    pub failed: Vec<String>,
+}
+
+/// Reclaim or fail up to `limit` running tasks with a stale heartbeat
+pub async fn reap_stale_tasks(
+    pool: &PgPool,
+    policy: &ReaperPolicy,
+    limit: i64,
+) -> Result<ReapedTasks> {
+    let mut tx = pool.begin().await?;
+    let timeout_secs = i64::try_from(policy.heartbeat_timeout.as_secs()).unwrap_or(i64::MAX);
+    let stale = db::executions::lock_stale_tasks(&mut tx, timeout_secs, limit).await?;
+
+    let mut reaped = ReapedTasks::default();
+    for (execution_id, attempt) in stale {
+        db::work_queue::remove_work(&mut *tx, std::slice::from_ref(&execution_id)).await?;
+
+        if attempt < 0 || (attempt as u32) < policy.max_reclaims {
+            if let Some(task) = db::executions::retry_execution(&mut *tx, &execution_id).await? {
+                db::work_queue::enqueue_work(&mut *tx, &execution_id, &task.queue, 0).await?;
+                reaped.reclaimed.push(execution_id);
+            }
+        } else {
+            let error = json!({
+                "code": HEARTBEAT_TIMEOUT_CODE,
+                "message": format!(
+                    "No heartbeat from its worker for {}s",
+                    policy.heartbeat_timeout.as_secs()
+                ),
+            });
+            finish_work(&mut tx, &execution_id, ExecutionOutcome::Failure(error)).await?;
+            reaped.failed.push(execution_id);
+        }
+    }
+
+    tx.commit().await?;
+
+    for execution_id in &reaped.reclaimed {
+        tracing::warn!(
+            execution_id = %execution_id,
+            "Task missed its heartbeats, reclaimed it for another worker"
+        );
+    }
+    for execution_id in &reaped.failed {
+        tracing::warn!(
+            execution_id = %execution_id,
+            "Task missed its heartbeats too many times, failed it"
+        );
+    }
+    Ok(reaped)
+}
@@ -0,0 +1,113 @@
+//! Replay checks before resuming a workflow
+//!
+//! With `[worker] replay_check` on, the runner replays each workflow it
+//! resumes against the history it recorded (see `executor::replay`), and
+//! fails one that no longer replays the same way with a `NonDeterministic`
+//! error instead of carrying on from a state its code can't reach.
+//!
+//! A workflow is replayed with the program its VM carries, so a state moved
+//! onto a new definition by `migrate-state` is checked against the new code.
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+
+use crate::blob_store::PayloadStore;
+use crate::db;
+use crate::encryption::Binding;
+use crate::executor::replay::{replay, Divergence, History, RecordedCall};
+use crate::executor::{json_to_val_map, Control, Val, WorkflowContext, VM};
+use crate::types::{Execution, ExecutionEventType};
+
+/// Steps a replay may take before the workflow is resumed unchecked
+const MAX_REPLAY_STEPS: usize = 10_000_000;
+
+/// Where the workflow's replay stops matching its history, if it does
+///
+/// Nothing is checked for a workflow that hasn't run yet, or whose history
+/// was recorded before the values it was resumed with were.
+pub(crate) async fn check_replay(
+    pool: &PgPool,
+    execution: &Execution,
+    vm: &VM,
+    payloads: &PayloadStore,
+) -> Result<Option<Divergence>> {
+    let Some(program) = vm.frames.first().map(|frame| &frame.node) else {
+        return Ok(None);
+    };
+    let Some(history) = load_history(pool, execution, vm, payloads).await? else {
+        return Ok(None);
+    };
+    if history.calls.is_empty() && history.resumes.is_empty() && history.recorded.is_empty() {
+        return Ok(None);
+    }
+
+    let context = WorkflowContext {
+        execution_id: execution.id.clone(),
+    };
+    replay(
+        program,
+        json_to_val_map(&execution.inputs)?,
+        context,
+        &history,
+        matches!(vm.control, Control::Suspend(_)),
+        MAX_REPLAY_STEPS,
+    )
+}
+
+/// The children the workflow started and the values it was resumed with
+///
+/// Payloads offloaded to the blob store are read back in, as they were
+/// when the workflow ran.
+async fn load_history(
+    pool: &PgPool,
+    execution: &Execution,
+    vm: &VM,
+    payloads: &PayloadStore,
+) -> Result<Option<History>> {
+    let events = db::execution_events::get_history(pool, &execution.id).await?;
+    let inputs = db::executions::get_child_inputs(pool, &execution.id).await?;
+
+    let mut history = History::default();
+    for event in events {
+        let details = event.details.unwrap_or(JsonValue::Null);
+        match event.event_type {
+            ExecutionEventType::TaskScheduled => {
+                let id = details["execution_id"].as_str().unwrap_or_default();
+                history.calls.push(RecordedCall {
+                    target_type: serde_json::from_value(details["type"].clone())
+                        .context("Invalid scheduled execution type")?,
+                    target_name: details["target_name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    inputs: match inputs.get(id) {
+                        Some(inputs) => Some(
+                            payloads
+                                .resolve(inputs.clone(), Binding::inputs(id))
+                                .await?,
+                        ),
+                        None => None,
+                    },
+                });
+            }
+            ExecutionEventType::Resumed => {
+                let Some(value) = details.get("value") else {
+                    return Ok(None);
+                };
+                let value = serde_json::from_value::<Val>(value.clone())
+                    .context("Invalid resumed value")?;
+                history.resumes.push(payloads.resolve_val(value).await?);
+            }
+            ExecutionEventType::Read => {
+                let values = serde_json::from_value::<Vec<Val>>(details["values"].clone())
+                    .context("Invalid read values")?;
+                history.recorded.extend(values);
+            }
+            _ => {}
+        }
+    }
+    // States saved before reads moved to the history still hold them
+    history.recorded.extend(vm.recorded.iter().cloned());
+    Ok(Some(history))
+}
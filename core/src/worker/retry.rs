@@ -0,0 +1,374 @@
+//! Retry decisions for failed tasks
+//!
+//! When a host reports a task failure it says whether the failure is worth
+//! retrying. `RetryRules` can overrule that per task from the error's code,
+//! so permanent errors (`give_up_on`) fail at once and known transient ones
+//! (`retry_on`) are retried even when the host would have given up.
+//!
+//! The error code is the error's `code` field, or its `type` (the exception
+//! class name the Python worker reports) when it has no code.
+//!
+//! An execution created with a `RetryPolicy`, such as one from the options
+//! of `Task.run`, takes its retry limit and codes from the policy where it
+//! sets them, and each retry is redelivered after the policy's backoff delay
+//! rather than at once.
+
+use serde_json::Value as JsonValue;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+
+use crate::config::TaskConfig;
+use crate::types::{Backoff, Execution, RetryPolicy};
+
+/// Retries allowed for tasks without a `max_retries` of their own
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// What to do with a failed attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Run the task again
+    Retry,
+    /// Record the failure as final
+    GiveUp,
+}
+
+/// Per-task retry rules, built from `[[task_configs]]`
+#[derive(Debug, Clone, Default)]
+pub struct RetryRules {
+    tasks: Vec<TaskConfig>,
+}
+
+impl RetryRules {
+    pub fn new(tasks: Vec<TaskConfig>) -> Self {
+        Self { tasks }
+    }
+
+    /// Decide whether a failed attempt of `execution` is retried
+    ///
+    /// `requested` is the retry flag the failure was reported with.
+    pub fn decide(
+        &self,
+        execution: &Execution,
+        error: &JsonValue,
+        requested: bool,
+    ) -> RetryDecision {
+        self.decide_with_policy(execution, None, error, requested)
+    }
+
+    /// Like `decide`, with the retry limit and codes of the execution's own
+    /// policy taking precedence over the task's
+    pub fn decide_with_policy(
+        &self,
+        execution: &Execution,
+        policy: Option<&RetryPolicy>,
+        error: &JsonValue,
+        requested: bool,
+    ) -> RetryDecision {
+        let task = self
+            .tasks
+            .iter()
+            .find(|task| task.target_name == execution.target_name);
+        let max_retries = policy
+            .and_then(|policy| policy.max_retries)
+            .or_else(|| task.and_then(|task| task.max_retries))
+            .unwrap_or(DEFAULT_MAX_RETRIES);
+        if execution.attempt < 0 || execution.attempt as u32 >= max_retries {
+            return RetryDecision::GiveUp;
+        }
+
+        let code = error_code(error);
+        let matches_any = |patterns: &[String]| {
+            code.is_some_and(|code| patterns.iter().any(|p| pattern_matches(p, code)))
+        };
+        // Each list of the policy replaces the task's, unless it is empty
+        let codes = |from_policy: fn(&RetryPolicy) -> &[String],
+                     from_task: fn(&TaskConfig) -> &[String]| {
+            policy
+                .map(from_policy)
+                .filter(|codes| !codes.is_empty())
+                .or_else(|| task.map(from_task))
+                .unwrap_or_default()
+        };
+        let give_up_on = codes(|p| &p.give_up_on, |t| &t.give_up_on);
+        let retry_on = codes(|p| &p.retry_on, |t| &t.retry_on);
+        let retry = if matches_any(give_up_on) {
+            false
+        } else if matches_any(retry_on) {
+            true
+        } else {
+            requested
+        };
+
+        if retry {
+            RetryDecision::Retry
+        } else {
+            RetryDecision::GiveUp
+        }
+    }
+}
+
+/// Delay before the `attempt`th retry (1-based) of `execution_id`
+///
+/// Jitter shortens the delay by up to its fraction. It is derived from the
+/// execution and attempt rather than drawn at random, so tasks that failed
+/// together spread out while a given retry always waits the same time.
+pub fn retry_delay(policy: &RetryPolicy, attempt: u32, execution_id: &str) -> Duration {
+    let initial = Duration::from_millis(policy.initial_interval_ms);
+    let max = Duration::from_millis(policy.max_interval_ms);
+    let delay = match policy.backoff {
+        Backoff::Fixed => initial,
+        Backoff::Exponential => {
+            let factor = 1u32
+                .checked_shl(attempt.saturating_sub(1))
+                .unwrap_or(u32::MAX);
+            initial.checked_mul(factor).unwrap_or(max)
+        }
+    }
+    .min(max);
+
+    let jitter = policy.jitter.clamp(0.0, 1.0);
+    if jitter == 0.0 {
+        return delay;
+    }
+    let mut hasher = DefaultHasher::new();
+    (execution_id, attempt).hash(&mut hasher);
+    let unit = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+    delay.mul_f64(1.0 - jitter * unit)
+}
+
+/// The code a failure is classified by
+pub fn error_code(error: &JsonValue) -> Option<&str> {
+    error
+        .get("code")
+        .and_then(JsonValue::as_str)
+        .or_else(|| error.get("type").and_then(JsonValue::as_str))
+}
+
+/// Exact match, or prefix match for patterns ending in `*`
+fn pattern_matches(pattern: &str, code: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => code.starts_with(prefix),
+        None => pattern == code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ExecutionStatus, ExecutionType};
+    use serde_json::json;
+
+    fn execution(target_name: &str, attempt: i32) -> Execution {
+        Execution {
+            id: "exec-1".to_string(),
+            exec_type: ExecutionType::Task,
+            target_name: target_name.to_string(),
+            queue: "default".to_string(),
+            namespace: "default".to_string(),
+            status: ExecutionStatus::Running,
+            inputs: json!({}),
+            output: None,
+            attempt,
+            parent_workflow_id: None,
+            created_at: chrono::Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    fn rules() -> RetryRules {
+        RetryRules::new(vec![TaskConfig {
+            target_name: "charge_card".to_string(),
+            max_retries: Some(2),
+            retry_on: vec!["RATE_LIMIT".to_string(), "HTTP_5*".to_string()],
+            give_up_on: vec!["VALIDATION".to_string()],
+            ..Default::default()
+        }])
+    }
+
+    #[test]
+    fn test_give_up_on_overrides_retry_flag() {
+        let error = json!({"code": "VALIDATION", "message": "bad card"});
+        assert_eq!(
+            rules().decide(&execution("charge_card", 0), &error, true),
+            RetryDecision::GiveUp
+        );
+    }
+
+    #[test]
+    fn test_retry_on_overrides_retry_flag() {
+        let rules = rules();
+        for code in ["RATE_LIMIT", "HTTP_503"] {
+            assert_eq!(
+                rules.decide(&execution("charge_card", 0), &json!({"code": code}), false),
+                RetryDecision::Retry,
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_unmatched_codes_follow_retry_flag() {
+        let rules = rules();
+        let error = json!({"type": "KeyError"});
+        assert_eq!(
+            rules.decide(&execution("charge_card", 0), &error, true),
+            RetryDecision::Retry
+        );
+        assert_eq!(
+            rules.decide(&execution("charge_card", 0), &error, false),
+            RetryDecision::GiveUp
+        );
+        assert_eq!(
+            rules.decide(
+                &execution("other_task", 0),
+                &json!({"code": "RATE_LIMIT"}),
+                false
+            ),
+            RetryDecision::GiveUp
+        );
+    }
+
+    #[test]
+    fn test_retries_stop_at_max_retries() {
+        let rules = rules();
+        let error = json!({"code": "RATE_LIMIT"});
+        assert_eq!(
+            rules.decide(&execution("charge_card", 1), &error, true),
+            RetryDecision::Retry
+        );
+        assert_eq!(
+            rules.decide(&execution("charge_card", 2), &error, true),
+            RetryDecision::GiveUp
+        );
+        assert_eq!(
+            rules.decide(
+                &execution("other_task", DEFAULT_MAX_RETRIES as i32),
+                &error,
+                true
+            ),
+            RetryDecision::GiveUp
+        );
+    }
+
+    fn policy(backoff: Backoff, jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: Some(5),
+            backoff,
+            initial_interval_ms: 1000,
+            max_interval_ms: 5000,
+            jitter,
+            retry_on: Vec::new(),
+            give_up_on: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_policy_max_retries_overrides_task_config() {
+        let rules = rules();
+        let error = json!({"code": "RATE_LIMIT"});
+        let policy = policy(Backoff::Exponential, 0.0);
+        assert_eq!(
+            rules.decide_with_policy(&execution("charge_card", 4), Some(&policy), &error, true),
+            RetryDecision::Retry
+        );
+        assert_eq!(
+            rules.decide_with_policy(&execution("charge_card", 5), Some(&policy), &error, true),
+            RetryDecision::GiveUp
+        );
+        assert_eq!(
+            rules.decide_with_policy(
+                &execution("charge_card", 0),
+                Some(&policy),
+                &json!({"code": "VALIDATION"}),
+                true
+            ),
+            RetryDecision::GiveUp
+        );
+    }
+
+    #[test]
+    fn test_policy_codes_override_task_config() {
+        let rules = rules();
+        let policy = RetryPolicy {
+            max_retries: None,
+            retry_on: vec!["VALIDATION".to_string()],
+            give_up_on: vec!["RATE_LIMIT".to_string()],
+            ..policy(Backoff::Fixed, 0.0)
+        };
+        let decide = |attempt, code| {
+            rules.decide_with_policy(
+                &execution("charge_card", attempt),
+                Some(&policy),
+                &json!({"code": code}),
+                false,
+            )
+        };
+        assert_eq!(decide(0, "VALIDATION"), RetryDecision::Retry);
+        assert_eq!(decide(0, "RATE_LIMIT"), RetryDecision::GiveUp);
+        // Without a limit of its own, the policy keeps the task's
+        assert_eq!(decide(2, "VALIDATION"), RetryDecision::GiveUp);
+
+        // A list the policy leaves empty keeps the task's
+        let policy = RetryPolicy {
+            retry_on: Vec::new(),
+            ..policy.clone()
+        };
+        assert_eq!(
+            rules.decide_with_policy(
+                &execution("charge_card", 0),
+                Some(&policy),
+                &json!({"code": "HTTP_502"}),
+                false
+            ),
+            RetryDecision::Retry
+        );
+    }
+
+    #[test]
+    fn test_exponential_delay_doubles_up_to_max_interval() {
+        let policy = policy(Backoff::Exponential, 0.0);
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| retry_delay(&policy, attempt, "exec-1").as_millis())
+            .collect();
+        assert_eq!(delays, vec![1000, 2000, 4000, 5000, 5000]);
+        assert_eq!(
+            retry_delay(&policy, 64, "exec-1"),
+            Duration::from_millis(5000)
+        );
+    }
+
+    #[test]
+    fn test_fixed_delay_stays_at_initial_interval() {
+        let policy = policy(Backoff::Fixed, 0.0);
+        for attempt in 1..=5 {
+            assert_eq!(
+                retry_delay(&policy, attempt, "exec-1"),
+                Duration::from_millis(1000)
+            );
+        }
+    }
+
+    #[test]
+    fn test_jitter_shortens_delay_deterministically() {
+        let policy = policy(Backoff::Exponential, 0.5);
+        let delays: Vec<_> = (0..20)
+            .map(|i| retry_delay(&policy, 3, &format!("exec-{}", i)))
+            .collect();
+        for delay in &delays {
+            assert!(*delay >= Duration::from_millis(2000) && *delay <= Duration::from_millis(4000));
+        }
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+        assert_eq!(retry_delay(&policy, 3, "exec-0"), delays[0]);
+    }
+
+    #[test]
+    fn test_error_code_prefers_code_over_type() {
+        assert_eq!(
+            error_code(&json!({"code": "RATE_LIMIT", "type": "HTTPError"})),
+            Some("RATE_LIMIT")
+        );
+        assert_eq!(error_code(&json!({"type": "HTTPError"})), Some("HTTPError"));
+        assert_eq!(error_code(&json!("oops")), None);
+    }
+}
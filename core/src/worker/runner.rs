@@ -4,75 +4,555 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
 use sqlx::PgPool;
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use tracing::Instrument;
 
 use super::awaitable::{resolve_awaitable, AwaitableStatus};
+use super::cancel;
 use super::complete::finish_work;
+use super::metrics::WorkerCounters;
+use super::replay::check_replay;
+use super::runner_retry::{self, RunnerRetryPolicy};
 use super::signals::{
     match_outbox_signals_to_unclaimed, process_signal_outbox, resolve_signal_claims,
 };
+use super::sticky::StickyOptions;
+use crate::blob_store::{PayloadStore, STATE_TOO_LARGE_CODE};
+use crate::config::{StepBudgetAction, StepBudgetConfig};
 use crate::db;
+use crate::db::workflow_execution_context::StoredState;
+use crate::diagnostics::DiagnosticsSampler;
+use crate::encryption::Binding;
+use crate::errors::RhythmError;
 use crate::executor::{
-    json_to_val_map, run_until_done, val_map_to_json, val_to_json, Control, WorkflowContext, VM,
+    json_to_val_map, run_for_steps, val_map_to_json, val_to_json, Awaitable, Control, ErrorInfo,
+    Val, VmTrace, WorkflowContext, VM,
 };
 use crate::parser::parse_workflow;
-use crate::types::{CreateExecutionParams, ExecutionOutcome};
+use crate::queue_limits::QueueLimits;
+use crate::telemetry;
+use crate::types::{
+    Backoff, CreateExecutionParams, ExecutionEventType, ExecutionOutcome, ExecutionType,
+    RetryPolicy,
+};
 
-pub async fn run_workflow(pool: &PgPool, execution: crate::types::Execution) -> Result<()> {
-    let maybe_context = db::workflow_execution_context::get_context(pool, &execution.id).await?;
+/// How a worker runs the workflows it claims
+#[derive(Debug, Clone, Default)]
+pub struct RunnerOptions {
+    /// Backoff after transient errors
+    pub retry: RunnerRetryPolicy,
+    /// Which runs get their detail recorded
+    pub diagnostics: DiagnosticsSampler,
+    /// Keep the workflows this worker saves, to resume them here
+    pub sticky: Option<StickyOptions>,
+    /// Replay workflows against their history before resuming them
+    pub replay_check: bool,
+    /// Record every workflow's runs, not only those of executions that opted
+    /// in (see `db::execution_recordings`)
+    pub record_runs: bool,
+    /// Limits on and storage for inputs and outputs
+    pub payloads: PayloadStore,
+    /// Steps per run, and whether a run that takes them yields or fails
+    pub step_budget: StepBudgetConfig,
+    /// Depths the queues children are started on are held to
+    pub queue_limits: QueueLimits,
+}
 
-    let (mut vm, workflow_def_id) = if let Some(context) = maybe_context {
-        // Resuming a workflow - resolve any signal race conditions from previous runs
-        resolve_signal_claims(pool, &execution.id).await?;
+/// Run a workflow, containing any panic or error to this one execution
+///
+/// A panic while running the workflow fails the execution (releasing its
+/// claim and waking the parent) instead of unwinding through the worker loop.
+/// An error is retried with backoff if transient and fails the execution
+/// otherwise (see `runner_retry`).
+///
+/// Panics, retries, runner failures and runs out of step budget are counted
+/// on `counters`. The run is traced in a span joining the execution's stored
+/// trace context.
+pub async fn run_workflow_isolated(
+    pool: &PgPool,
+    counters: &WorkerCounters,
+    options: &RunnerOptions,
+    execution: crate::types::Execution,
+) -> Result<()> {
+    let execution_id = execution.id.clone();
+    let queue = execution.queue.clone();
+    let traceparent = db::executions::get_traceparent(pool, &execution_id).await?;
+    let span = telemetry::execution_span("run_workflow", &execution, traceparent.as_deref());
+    async move {
+        let run = async {
+            if run_workflow_with_step_budget(pool, execution, options).await? {
+                match options.step_budget.on_exceeded {
+                    StepBudgetAction::Yield => counters.record_step_budget_yield(),
+                    StepBudgetAction::Fail => counters.record_step_budget_failure(),
+                }
+            }
+            Ok(())
+        };
+        match isolate_panics(pool, counters, &execution_id, run).await {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                handle_runner_error(pool, counters, &options.retry, &execution_id, &queue, error)
+                    .await
+            }
+        }
+    }
+    .instrument(span)
+    .await
+}
 
-        (
-            serde_json::from_value(context.vm_state).context("Failed to deserialize VM state")?,
-            context.workflow_definition_id,
+/// Retry a workflow run that failed with `error`, or fail its execution
+pub(crate) async fn handle_runner_error(
+    pool: &PgPool,
+    counters: &WorkerCounters,
+    retry: &RunnerRetryPolicy,
+    execution_id: &str,
+    queue: &str,
+    error: anyhow::Error,
+) -> Result<()> {
+    if !runner_retry::is_transient(&error) {
+        counters.record_runner_failure();
+        tracing::error!(
+            execution_id = %execution_id,
+            error = format!("{:#}", error),
+            "Workflow run failed, marking it failed"
+        );
+        return fail_workflow(
+            pool,
+            execution_id,
+            serde_json::json!({
+                "code": runner_retry::RUNNER_ERROR_CODE,
+                "message": format!("Workflow run failed: {:#}", error),
+            }),
         )
-    } else {
-        initialize_workflow(
+        .await;
+    }
+
+    let db_now = db::get_db_time(pool).await?;
+    let mut tx = pool.begin().await?;
+    let Some(attempt) = db::executions::suspend_after_runner_error(&mut *tx, execution_id).await?
+    else {
+        // Finished or taken over meanwhile; nothing to retry
+        return Ok(());
+    };
+    let Some(delay) = retry.delay(attempt as u32) else {
+        drop(tx);
+        counters.record_runner_failure();
+        tracing::error!(
+            execution_id = %execution_id,
+            attempts = attempt,
+            error = format!("{:#}", error),
+            "Workflow run kept failing with transient errors, marking it failed"
+        );
+        return fail_workflow(
             pool,
-            &execution.target_name,
-            &execution.inputs,
-            &execution.id,
+            execution_id,
+            serde_json::json!({
+                "code": runner_retry::RUNNER_RETRIES_EXHAUSTED_CODE,
+                "message": format!(
+                    "Workflow run failed {} times in a row: {:#}",
+                    attempt, error
+                ),
+            }),
         )
-        .await?
+        .await;
+    };
+
+    let params = crate::services::scheduler_service::ScheduledParams::WorkflowContinuation {
+        execution_id: execution_id.to_string(),
+        queue: queue.to_string(),
+        priority: 0,
     };
+    let params_json =
+        serde_json::to_value(&params).context("Failed to serialize scheduled params")?;
+    let run_at = db_now + delay;
+    db::scheduled_queue::schedule_item(&mut *tx, run_at.naive_utc(), &params_json).await?;
+    db::work_queue::complete_work(&mut *tx, execution_id).await?;
+    tx.commit().await?;
+
+    counters.record_runner_retry();
+    tracing::warn!(
+        execution_id = %execution_id,
+        attempt,
+        retry_in_ms = delay.as_millis() as u64,
+        error = format!("{:#}", error),
+        "Transient error running workflow, retrying"
+    );
+    Ok(())
+}
+
+/// Await `fut`, turning a panic into a failure of `execution_id`
+pub(crate) async fn isolate_panics<F>(
+    pool: &PgPool,
+    counters: &WorkerCounters,
+    execution_id: &str,
+    fut: F,
+) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    match CatchUnwind(Box::pin(fut)).await {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            counters.record_workflow_panic();
+            tracing::error!(
+                execution_id = %execution_id,
+                panic = %message,
+                "Workflow execution panicked, marking it failed"
+            );
+            fail_panicked_execution(pool, execution_id, &message).await
+        }
+    }
+}
 
+/// Future adapter that catches panics raised while polling the inner future
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = std::result::Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let inner = self.0.as_mut();
+        match catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+async fn fail_panicked_execution(pool: &PgPool, execution_id: &str, message: &str) -> Result<()> {
+    let error_json = serde_json::json!({
+        "message": format!("Workflow execution panicked: {}", message),
+        "type": "WorkerPanic"
+    });
+    fail_workflow(pool, execution_id, error_json).await
+}
+
+/// Fail a workflow outside of a run, dropping its saved state
+async fn fail_workflow(pool: &PgPool, execution_id: &str, error_json: JsonValue) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    db::workflow_execution_context::delete_context(&mut *tx, execution_id)
+        .await
+        .context("Failed to delete workflow execution context")?;
+    finish_work(&mut tx, execution_id, ExecutionOutcome::Failure(error_json)).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// VM steps a workflow may take per claim before yielding its worker, by
+/// default
+///
+/// A workflow that runs out saves its state and re-enqueues itself, so a
+/// long in-VM loop is spread over several claims instead of holding one
+/// worker for the whole run.
+pub const STEPS_PER_CLAIM: usize = 100_000;
+
+/// Error code of a workflow failed for using up its step budget
+pub const LOOP_BUDGET_EXCEEDED_CODE: &str = "LOOP_BUDGET_EXCEEDED";
+
+pub async fn run_workflow(pool: &PgPool, execution: crate::types::Execution) -> Result<()> {
+    run_workflow_with_step_budget(pool, execution, &RunnerOptions::default()).await?;
+    Ok(())
+}
+
+/// Run a workflow for up to `options.step_budget.max_steps` VM steps
+///
+/// A run that takes them all yields, or fails with `LOOP_BUDGET_EXCEEDED`
+/// per `options.step_budget.on_exceeded`; the error can't be caught by the
+/// workflow. Returns whether the run used up its budget.
+///
+/// The run's detail is recorded if `options.diagnostics` samples the
+/// execution. If the execution opted in to VM tracing, a failed run's steps
+/// are saved, and if it is recorded (or `options.record_runs`), what the
+/// run read is. With `options.sticky`, a saved state is also kept in memory.
+/// With `options.replay_check`, a workflow that doesn't replay the way it
+/// ran is failed instead of resumed.
+pub(crate) async fn run_workflow_with_step_budget(
+    pool: &PgPool,
+    execution: crate::types::Execution,
+    options: &RunnerOptions,
+) -> Result<bool> {
+    let max_steps = options.step_budget.max_steps;
+    let sticky = options.sticky.as_ref();
+    let (mut vm, workflow_def_id) =
+        load_workflow(pool, &execution, sticky, &options.payloads).await?;
+    // A workflow cancelled child-first waits for its child workflows, then
+    // fails with the cancellation so that it runs its compensations
+    let cancel_request = db::executions::get_cancel_request(pool, &execution.id).await?;
+    if let Some(reason) = &cancel_request {
+        if db::executions::has_unfinished_child_workflows(pool, &execution.id).await? {
+            // Woken again as each of them finishes
+            let mut tx = pool.begin().await?;
+            finish_work(&mut tx, &execution.id, ExecutionOutcome::Suspended).await?;
+            tx.commit().await?;
+            return Ok(false);
+        }
+        if vm.unwinding.is_none() {
+            fail_run(&mut vm, cancel::CANCELLED_ERROR_CODE, reason.clone());
+        }
+    }
+    // A cancelled workflow no longer runs the way it was recorded
+    if options.replay_check && cancel_request.is_none() {
+        match check_replay(pool, &execution, &vm, &options.payloads).await {
+            Ok(Some(divergence)) => {
+                tracing::warn!(
+                    execution_id = %execution.id,
+                    %divergence,
+                    "Workflow does not replay the way it ran"
+                );
+                fail_workflow(pool, &execution.id, divergence.to_error_json()).await?;
+                return Ok(false);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(
+                    execution_id = %execution.id,
+                    "Resuming workflow without a replay check: {:#}",
+                    e
+                );
+            }
+        }
+    }
+    if db::vm_traces::is_enabled(pool, &execution.id).await? {
+        vm.trace = Some(VmTrace::default());
+    }
+
+    let mut steps_left = max_steps;
+    let mut out_of_steps = false;
+    let mut resumed = Vec::new();
     loop {
         // Fetch current DB time for timer resolution checks
         let db_now = db::get_db_time(pool).await?;
 
         // If suspended on an awaitable, check if it's ready
-        if !try_resume_suspended_state(pool, &mut vm, db_now).await? {
+        if !try_resume_suspended_state(pool, &mut vm, db_now, &options.payloads, &mut resumed)
+            .await?
+        {
             break; // Awaitable not ready, suspend and save state
         }
 
-        run_until_done(&mut vm);
+        let steps = run_for_steps(&mut vm, steps_left);
 
         // Match outbox signals to unclaimed DB signals (in-memory, no writes)
         match_outbox_signals_to_unclaimed(pool, &mut vm.outbox, &execution.id).await?;
 
+        let Some(steps) = steps else {
+            out_of_steps = true;
+            break; // Step budget spent, give the worker back or fail
+        };
+        steps_left = steps_left.saturating_sub(steps);
+
         if !should_continue_execution(&vm.control)? {
             break; // Workflow completed or errored
         }
     }
 
+    let mut yielded = out_of_steps && options.step_budget.on_exceeded == StepBudgetAction::Yield;
+    if out_of_steps && !yielded {
+        tracing::warn!(
+            execution_id = %execution.id,
+            max_steps,
+            "Workflow used up its step budget, marking it failed"
+        );
+        fail_run(
+            &mut vm,
+            LOOP_BUDGET_EXCEEDED_CODE,
+            format!("Workflow used up its budget of {} steps", max_steps),
+        );
+    }
+
+    // What the run read moves from the state to the history, so a state
+    // saved at each checkpoint doesn't grow with every read
+    let reads = std::mem::take(&mut vm.recorded);
+
+    // Checked against the state size limit before anything is written
+    let mut vm_state = None;
+    if yielded || matches!(vm.control, Control::Suspend(_)) {
+        match options.payloads.seal_state(&execution.id, &vm) {
+            Ok(state) => vm_state = Some(state),
+            Err(e) => match e.downcast_ref::<RhythmError>() {
+                Some(RhythmError::Validation(message)) => {
+                    tracing::warn!(
+                        execution_id = %execution.id,
+                        "{}, marking it failed",
+                        message
+                    );
+                    let message = message.clone();
+                    fail_run(&mut vm, STATE_TOO_LARGE_CODE, message);
+                    yielded = false;
+                }
+                _ => return Err(e),
+            },
+        }
+    }
+
     let mut tx = pool.begin().await?;
-    create_child_executions(&mut tx, &vm.outbox, &execution.id, &execution.queue).await?;
+    // Saved before the run's resumes and reads join the history it is
+    // seeded from
+    if options.record_runs || db::execution_recordings::is_enabled(&mut *tx, &execution.id).await? {
+        let values: Vec<Val> = resumed.iter().map(|(_, value)| value.clone()).collect();
+        db::execution_recordings::save_run(
+            &mut *tx,
+            &execution.id,
+            workflow_def_id,
+            &reads,
+            &values,
+        )
+        .await?;
+    }
+    for (awaited, value) in resumed {
+        // The value is kept for replay checks
+        let details = serde_json::json!({ "awaited": awaited, "value": value });
+        db::execution_events::record_event(
+            &mut *tx,
+            &execution.id,
+            ExecutionEventType::Resumed,
+            Some(details),
+        )
+        .await?;
+    }
+    if !reads.is_empty() {
+        // Kept for replay checks, which read the workflow from the start
+        let details = serde_json::json!({ "values": reads });
+        db::execution_events::record_event(
+            &mut *tx,
+            &execution.id,
+            ExecutionEventType::Read,
+            Some(details),
+        )
+        .await?;
+    }
+    create_child_executions(
+        &mut tx,
+        &vm.outbox,
+        &execution.id,
+        &execution.queue,
+        &options.payloads,
+        &options.queue_limits,
+    )
+    .await?;
     schedule_timers(&mut tx, &vm.outbox, &execution.id, &execution.queue).await?;
     process_signal_outbox(&mut tx, &vm.outbox, &execution.id).await?;
-    handle_workflow_result(&mut tx, &vm, &execution.id, workflow_def_id).await?;
+    record_logs(&mut tx, &vm.outbox, &execution.id).await?;
+    let saved_version = if yielded {
+        Some(
+            yield_workflow(
+                &mut tx,
+                &execution,
+                workflow_def_id,
+                vm_state.as_ref().context("Yielded workflow has no state")?,
+                sticky,
+            )
+            .await?,
+        )
+    } else if let (Some(reason), Control::Throw(_)) = (&cancel_request, &vm.control) {
+        cancel::finish_cancel_request(&mut tx, &execution.id, reason).await?;
+        None
+    } else if let Some(inputs) = &vm.outbox.continue_as_new {
+        continue_as_new(&mut tx, &execution, inputs, &options.payloads).await?;
+        None
+    } else {
+        handle_workflow_result(
+            &mut tx,
+            &vm,
+            &execution.id,
+            workflow_def_id,
+            vm_state.as_ref(),
+            sticky,
+            &options.payloads,
+        )
+        .await?
+    };
+    if let (Control::Throw(_), Some(trace)) = (&vm.control, &vm.trace) {
+        db::vm_traces::save_steps(&mut *tx, &execution.id, trace.total(), &trace.steps()).await?;
+    }
     tx.commit().await?;
 
-    Ok(())
+    let steps = if out_of_steps {
+        max_steps
+    } else {
+        max_steps - steps_left
+    };
+    options
+        .diagnostics
+        .record_run(&execution, &vm, steps, yielded);
+
+    if let (Some(sticky), Some(version)) = (sticky, saved_version) {
+        sticky
+            .cache
+            .insert(&execution.id, version, workflow_def_id, vm);
+    }
+    Ok(out_of_steps)
+}
+
+/// The workflow's VM and definition id, from where it left off if it ran before
+///
+/// A VM `sticky` kept from this worker's last run is used while it is at
+/// the saved state's version; otherwise the saved state is decrypted and
+/// deserialized.
+async fn load_workflow(
+    pool: &PgPool,
+    execution: &crate::types::Execution,
+    sticky: Option<&StickyOptions>,
+    payloads: &PayloadStore,
+) -> Result<(VM, i32)> {
+    if let Some(sticky) = sticky {
+        let version =
+            db::workflow_execution_context::get_context_version(pool, &execution.id).await?;
+        if let Some(cached) = version.and_then(|v| sticky.cache.take(&execution.id, v)) {
+            tracing::debug!(execution_id = %execution.id, "Resuming workflow from cached VM");
+            resolve_signal_claims(pool, &execution.id).await?;
+            return Ok(cached);
+        }
+    }
+
+    let maybe_context = db::workflow_execution_context::get_context(pool, &execution.id).await?;
+    if let Some(context) = maybe_context {
+        // Resuming a workflow - resolve any signal race conditions from previous runs
+        resolve_signal_claims(pool, &execution.id).await?;
+
+        Ok((
+            payloads.open_state(&execution.id, context.vm_state)?,
+            context.workflow_definition_id,
+        ))
+    } else {
+        initialize_workflow(
+            pool,
+            &execution.target_name,
+            &execution.inputs,
+            &execution.id,
+        )
+        .await
+    }
 }
 
 /// Checks if VM is suspended on a completed awaitable and resumes if so.
 /// Returns true if execution should continue, false if it should break.
+/// The awaitable resumed from and its value are added to `resumed`, with
+/// offloaded payloads left as references.
 async fn try_resume_suspended_state(
     pool: &PgPool,
     vm: &mut VM,
     db_now: DateTime<Utc>,
+    payloads: &PayloadStore,
+    resumed: &mut Vec<(Awaitable, Val)>,
 ) -> Result<bool> {
     if let Control::Suspend(awaitable) = &vm.control {
         // Clone to avoid borrow issues
@@ -81,7 +561,8 @@ async fn try_resume_suspended_state(
         match resolve_awaitable(pool, &awaitable, db_now, &vm.outbox).await? {
             AwaitableStatus::Pending => Ok(false),
             AwaitableStatus::Success(val) | AwaitableStatus::Error(val) => {
-                vm.resume(val);
+                vm.resume(payloads.resolve_val(val.clone()).await?);
+                resumed.push((awaitable, val));
                 Ok(true)
             }
         }
@@ -128,28 +609,88 @@ async fn create_child_executions(
     outbox: &crate::executor::Outbox,
     execution_id: &str,
     queue: &str,
+    payloads: &PayloadStore,
+    queue_limits: &QueueLimits,
 ) -> Result<()> {
     if outbox.executions.is_empty() {
         return Ok(());
     }
 
+    // A full queue fails the run, which is retried as a transient error
+    let queued: Vec<&str> = outbox
+        .executions
+        .iter()
+        .filter(|exec| exec.target_type != ExecutionType::External)
+        .map(|exec| exec.options.queue.as_deref().unwrap_or(queue))
+        .collect();
+    queue_limits.admit(tx, &queued).await?;
+
     for exec in &outbox.executions {
-        let inputs_json = val_map_to_json(&exec.inputs)?;
+        let inputs_json = payloads
+            .offload(
+                val_map_to_json(&exec.inputs)?,
+                "Child execution inputs",
+                Binding::inputs(&exec.id),
+            )
+            .await?;
 
+        // Options passed to Task.run, else the parent's queue and priority
+        let options = &exec.options;
+        let child_queue = options.queue.as_deref().unwrap_or(queue);
         let params = CreateExecutionParams {
             id: Some(exec.id.clone()),
             exec_type: exec.target_type.clone(),
             target_name: exec.target_name.clone(),
-            queue: queue.to_string(),
+            queue: child_queue.to_string(),
             inputs: inputs_json,
             parent_workflow_id: Some(execution_id.to_string()),
+            // Inherit the parent's namespace
+            namespace: None,
+            ttl_seconds: options.timeout_seconds,
+            // Retried at once, as without a policy
+            retry_policy: (options.max_retries.is_some()
+                || !options.retry_on.is_empty()
+                || !options.give_up_on.is_empty())
+            .then(|| RetryPolicy {
+                max_retries: options.max_retries,
+                backoff: Backoff::Fixed,
+                initial_interval_ms: 0,
+                max_interval_ms: 0,
+                jitter: 0.0,
+                retry_on: options.retry_on.clone(),
+                give_up_on: options.give_up_on.clone(),
+            }),
+            priority: options.priority,
+            idempotency_key: None,
+            // Else the parent's
+            traceparent: telemetry::current_traceparent(),
+            // Added to the parent's
+            tags: HashMap::new(),
         };
 
         db::executions::create_execution(tx, params)
             .await
             .context("Failed to create child execution")?;
+        let details = serde_json::json!({
+            "execution_id": exec.id,
+            "target_name": exec.target_name,
+            "type": exec.target_type,
+            "queue": child_queue,
+        });
+        db::execution_events::record_event(
+            &mut **tx,
+            execution_id,
+            ExecutionEventType::TaskScheduled,
+            Some(details),
+        )
+        .await?;
+
+        // External executions wait for their token instead of a worker
+        if exec.target_type == ExecutionType::External {
+            continue;
+        }
 
-        db::work_queue::enqueue_work(&mut **tx, &exec.id, queue, 0)
+        db::work_queue::enqueue_work(&mut **tx, &exec.id, child_queue, 0)
             .await
             .context("Failed to enqueue work")?;
     }
@@ -190,15 +731,163 @@ async fn schedule_timers(
     Ok(())
 }
 
+async fn record_logs(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    outbox: &crate::executor::Outbox,
+    execution_id: &str,
+) -> Result<()> {
+    for log in &outbox.logs {
+        let data = log.data.as_ref().map(val_to_json).transpose()?;
+        db::execution_logs::append_log(
+            &mut **tx,
+            execution_id,
+            log.level,
+            &log.message,
+            data.as_ref(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Save a workflow that used up its step budget and queue its continuation
+///
+/// The execution stays running; its claim is released and a fresh queue
+/// entry lets any worker pick up where it left off (this one first, when
+/// `sticky`). Returns the saved state's version.
+async fn yield_workflow(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution: &crate::types::Execution,
+    workflow_def_id: i32,
+    vm_state: &StoredState,
+    sticky: Option<&StickyOptions>,
+) -> Result<i64> {
+    let version = save_state(tx, &execution.id, workflow_def_id, vm_state, sticky).await?;
+
+    db::work_queue::complete_work(&mut **tx, &execution.id)
+        .await
+        .context("Failed to complete work queue entry")?;
+    db::work_queue::enqueue_work(&mut **tx, &execution.id, &execution.queue, 0)
+        .await
+        .context("Failed to re-queue yielded workflow")?;
+
+    tracing::debug!(execution_id = %execution.id, "Workflow yielded after step budget");
+    Ok(version)
+}
+
+/// Save the workflow's state, as `PayloadStore::seal_state` made it,
+/// returning its new version
+async fn save_state(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution_id: &str,
+    workflow_def_id: i32,
+    vm_state: &StoredState,
+    sticky: Option<&StickyOptions>,
+) -> Result<i64> {
+    db::workflow_execution_context::upsert_context(
+        tx,
+        execution_id,
+        workflow_def_id,
+        vm_state,
+        sticky.map(StickyOptions::preference),
+    )
+    .await
+    .context("Failed to upsert workflow execution context")
+}
+
+/// End a run with `code`, past any `catch` or `finally` in the workflow
+fn fail_run(vm: &mut VM, code: &str, message: String) {
+    vm.frames.clear();
+    vm.control = Control::Throw(Val::Error(ErrorInfo::new(code, message)));
+}
+
+/// Complete a workflow that continued as new and start its fresh execution
+///
+/// The fresh execution runs the same workflow on the same queue with the
+/// given inputs, takes over the parent (if any), and is linked back through
+/// `continued_from`. The old execution completes with `{ continuedAs: id }`.
+/// A workflow cancelled while it ran does not continue.
+async fn continue_as_new(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    execution: &crate::types::Execution,
+    inputs: &std::collections::HashMap<String, crate::executor::Val>,
+    payloads: &PayloadStore,
+) -> Result<()> {
+    db::workflow_execution_context::delete_context(&mut **tx, &execution.id)
+        .await
+        .context("Failed to delete workflow execution context")?;
+
+    if cancel::lock_if_cancelled(tx, &execution.id).await? {
+        // finish_work drops the outcome and cancels this run's children
+        let outcome = ExecutionOutcome::Success(serde_json::json!(null));
+        return finish_work(&mut *tx, &execution.id, outcome).await;
+    }
+
+    let next_id = uuid::Uuid::new_v4().to_string();
+    let params = CreateExecutionParams {
+        id: Some(next_id.clone()),
+        exec_type: ExecutionType::Workflow,
+        target_name: execution.target_name.clone(),
+        queue: execution.queue.clone(),
+        inputs: payloads
+            .offload(
+                val_map_to_json(inputs)?,
+                "Continued execution inputs",
+                Binding::inputs(&next_id),
+            )
+            .await?,
+        parent_workflow_id: execution.parent_workflow_id.clone(),
+        namespace: Some(execution.namespace.clone()),
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: match telemetry::current_traceparent() {
+            Some(traceparent) => Some(traceparent),
+            None => db::executions::get_traceparent(&mut **tx, &execution.id).await?,
+        },
+        tags: db::executions::get_tags(&mut **tx, &execution.id)
+            .await?
+            .unwrap_or_default(),
+    };
+    db::executions::create_execution(tx, params)
+        .await
+        .context("Failed to create continued execution")?;
+    db::executions::set_continued_from(&mut **tx, &next_id, &execution.id).await?;
+    db::work_queue::enqueue_work(&mut **tx, &next_id, &execution.queue, 0)
+        .await
+        .context("Failed to enqueue continued execution")?;
+
+    finish_work(
+        &mut *tx,
+        &execution.id,
+        ExecutionOutcome::Success(serde_json::json!({ "continuedAs": next_id })),
+    )
+    .await?;
+
+    tracing::debug!(execution_id = %execution.id, continued_as = %next_id, "Workflow continued as new");
+    Ok(())
+}
+
+/// Finish or suspend the workflow as its VM says
+///
+/// A suspended workflow saves `vm_state`. Returns the saved state's version
+/// when the workflow suspended.
 async fn handle_workflow_result(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     vm: &VM,
     execution_id: &str,
     workflow_def_id: i32,
-) -> Result<()> {
+    vm_state: Option<&StoredState>,
+    sticky: Option<&StickyOptions>,
+    payloads: &PayloadStore,
+) -> Result<Option<i64>> {
+    let mut saved_version = None;
     match &vm.control {
         Control::Return(val) => {
-            let result_json = val_to_json(val)?;
+            let outcome = payloads
+                .offload_result(val_to_json(val)?, "Workflow output", execution_id)
+                .await?;
 
             // Delete workflow execution context before finishing
             db::workflow_execution_context::delete_context(&mut **tx, execution_id)
@@ -206,12 +895,7 @@ async fn handle_workflow_result(
                 .context("Failed to delete workflow execution context")?;
 
             // Use helper to complete execution, complete work, and re-queue parent
-            finish_work(
-                &mut *tx,
-                execution_id,
-                ExecutionOutcome::Success(result_json),
-            )
-            .await?;
+            finish_work(&mut *tx, execution_id, outcome).await?;
         }
         Control::None => {
             // Implicit return null - workflow completed without explicit return statement
@@ -227,23 +911,17 @@ async fn handle_workflow_result(
             .await?;
         }
         Control::Suspend(_awaitable) => {
-            let vm_state = serde_json::to_value(vm).context("Failed to serialize VM state")?;
-
             // Upsert workflow execution context before suspending
-            db::workflow_execution_context::upsert_context(
-                tx,
-                execution_id,
-                workflow_def_id,
-                &vm_state,
-            )
-            .await
-            .context("Failed to upsert workflow execution context")?;
+            let vm_state = vm_state.context("Suspended workflow has no state to save")?;
+            saved_version =
+                Some(save_state(tx, execution_id, workflow_def_id, vm_state, sticky).await?);
 
             // Use helper to suspend execution, complete work, and re-queue parent
             finish_work(&mut *tx, execution_id, ExecutionOutcome::Suspended).await?;
         }
         Control::Throw(error_val) => {
-            let error_json = val_to_json(error_val)?;
+            let error_json =
+                payloads.seal(val_to_json(error_val)?, Binding::output(execution_id))?;
 
             // Delete workflow execution context before finishing
             db::workflow_execution_context::delete_context(&mut **tx, execution_id)
@@ -284,5 +962,5 @@ async fn handle_workflow_result(
         }
     }
 
-    Ok(())
+    Ok(saved_version)
 }
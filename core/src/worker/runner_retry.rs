@@ -0,0 +1,179 @@
+//! Retrying workflow runs after transient errors
+//!
+//! A workflow run that fails because of the infrastructure (a dropped
+//! connection, a serialization conflict, an exhausted pool, a full queue for
+//! one of its children) would succeed if run again, so its execution is suspended and resumed after a backoff
+//! instead of failing. Any other error comes from the workflow itself (a
+//! corrupt saved state, a missing definition) and would fail the same way
+//! every time, so the execution fails at once.
+//!
+//! Nothing a failed run did is kept: its transaction never committed, so
+//! the retry resumes from the last saved state.
+
+use std::time::Duration;
+
+use crate::config::RunnerRetryConfig;
+use crate::queue_limits::QueueFull;
+
+/// Error code of a workflow failed by a non-transient runner error
+pub const RUNNER_ERROR_CODE: &str = "RUNNER_ERROR";
+
+/// Error code of a workflow failed after too many transient runner errors
+pub const RUNNER_RETRIES_EXHAUSTED_CODE: &str = "RUNNER_RETRIES_EXHAUSTED";
+
+/// When to retry a workflow run after a transient error
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunnerRetryPolicy {
+    /// Consecutive transient errors before the execution fails
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RunnerRetryPolicy {
+    fn default() -> Self {
+        Self::from(&RunnerRetryConfig::default())
+    }
+}
+
+impl From<&RunnerRetryConfig> for RunnerRetryPolicy {
+    fn from(config: &RunnerRetryConfig) -> Self {
+        Self {
+            max_attempts: config.max_attempts,
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+        }
+    }
+}
+
+impl RunnerRetryPolicy {
+    /// Delay before retrying after the `attempt`th consecutive error, or
+    /// `None` once retries are used up
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.max_attempts {
+            return None;
+        }
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        Some(
+            self.base_delay
+                .checked_mul(factor)
+                .unwrap_or(self.max_delay)
+                .min(self.max_delay),
+        )
+    }
+}
+
+/// Whether an error from a workflow run is worth retrying
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.is::<QueueFull>()
+            || cause
+                .downcast_ref::<sqlx::Error>()
+                .is_some_and(is_transient_sqlx)
+    })
+}
+
+fn is_transient_sqlx(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_)
+        | sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(db) => db.code().is_some_and(|code| is_transient_sqlstate(&code)),
+        _ => false,
+    }
+}
+
+/// SQLSTATEs for conditions that clear up on their own
+fn is_transient_sqlstate(code: &str) -> bool {
+    // 08: connection exceptions, 53: insufficient resources
+    code.starts_with("08")
+        || code.starts_with("53")
+        || matches!(
+            code,
+            // serialization_failure, deadlock_detected
+            "40001" | "40P01"
+            // lock_not_available
+            | "55P03"
+            // admin_shutdown, crash_shutdown, cannot_connect_now
+            | "57P01" | "57P02" | "57P03"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    fn policy() -> RunnerRetryPolicy {
+        RunnerRetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_max() {
+        let policy = policy();
+        let delays: Vec<_> = (0..=5).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                None,
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(5)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delay_saturates_for_large_attempts() {
+        let policy = RunnerRetryPolicy {
+            max_attempts: u32::MAX,
+            ..policy()
+        };
+        assert_eq!(policy.delay(200), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_is_transient_looks_through_context() {
+        let error = Err::<(), _>(sqlx::Error::PoolTimedOut)
+            .context("Failed to fetch workflow execution context")
+            .unwrap_err();
+        assert!(is_transient(&error));
+
+        let io = sqlx::Error::Io(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(is_transient(&anyhow::Error::from(io)));
+
+        let full = anyhow::Error::new(QueueFull {
+            queue: "emails".to_string(),
+            limit: 10,
+            depth: 10,
+        });
+        assert!(is_transient(&full));
+    }
+
+    #[test]
+    fn test_workflow_errors_are_not_transient() {
+        assert!(!is_transient(&anyhow::anyhow!(
+            "Failed to deserialize VM state"
+        )));
+        assert!(!is_transient(&anyhow::Error::from(
+            sqlx::Error::RowNotFound
+        )));
+    }
+
+    #[test]
+    fn test_transient_sqlstates() {
+        for code in ["40001", "40P01", "08006", "53300", "57P01"] {
+            assert!(is_transient_sqlstate(code), "{}", code);
+        }
+        for code in ["23505", "42P01", "22P02"] {
+            assert!(!is_transient_sqlstate(code), "{}", code);
+        }
+    }
+}
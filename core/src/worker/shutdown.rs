@@ -0,0 +1,151 @@
+//! Graceful worker shutdown
+//!
+//! A `Worker` tracks the tasks its service has handed to the host and not yet
+//! seen finish. Shutting down stops claims, waits for those tasks up to a
+//! deadline, and releases whatever is still unfinished back to the queue so
+//! another worker can take it instead of waiting out the claim lease.
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::db;
+
+/// Tasks handed to the host and not yet reported
+#[derive(Debug)]
+pub(crate) struct InFlightTasks {
+    ids: watch::Sender<HashSet<String>>,
+}
+
+impl Default for InFlightTasks {
+    fn default() -> Self {
+        Self {
+            ids: watch::Sender::new(HashSet::new()),
+        }
+    }
+}
+
+impl InFlightTasks {
+    pub(crate) fn start(&self, execution_id: &str) {
+        self.ids.send_modify(|ids| {
+            ids.insert(execution_id.to_string());
+        });
+    }
+
+    pub(crate) fn finish(&self, execution_id: &str) {
+        self.ids.send_if_modified(|ids| ids.remove(execution_id));
+    }
+
+    fn snapshot(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.ids.borrow().iter().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Wait until nothing is in flight, for at most `timeout`
+    async fn wait_empty(&self, timeout: Duration) -> bool {
+        let mut receiver = self.ids.subscribe();
+        let idle = tokio::time::timeout(timeout, receiver.wait_for(HashSet::is_empty)).await;
+        idle.is_ok()
+    }
+}
+
+/// How a shutdown went
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DrainReport {
+    /// Every in-flight task finished before the deadline
+    pub idle: bool,
+    /// Unfinished tasks put back on their queue for another worker
+    pub released: Vec<String>,
+}
+
+/// Handle for stopping a worker without dropping its work
+///
+/// Shares its shutdown token and in-flight tasks with the `WorkerService` it
+/// came from, so it sees tasks claimed through any of that service's loops.
+#[derive(Debug, Clone)]
+pub struct Worker {
+    pool: PgPool,
+    shutdown_token: CancellationToken,
+    in_flight: Arc<InFlightTasks>,
+}
+
+impl Worker {
+    pub(crate) fn new(
+        pool: PgPool,
+        shutdown_token: CancellationToken,
+        in_flight: Arc<InFlightTasks>,
+    ) -> Self {
+        Self {
+            pool,
+            shutdown_token,
+            in_flight,
+        }
+    }
+
+    /// Stop claiming new work; tasks already handed out keep running
+    pub fn begin_shutdown(&self) {
+        self.shutdown_token.cancel();
+    }
+
+    /// Whether `begin_shutdown` has been called
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown_token.is_cancelled()
+    }
+
+    /// IDs of the tasks handed to the host and not yet reported
+    pub fn in_flight(&self) -> Vec<String> {
+        self.in_flight.snapshot()
+    }
+
+    /// Wait until no task is in flight, for at most `timeout`
+    ///
+    /// Returns whether the worker went idle. Does not stop claims by itself,
+    /// so call `begin_shutdown` first when draining.
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        self.in_flight.wait_empty(timeout).await
+    }
+
+    /// Stop claiming, wait up to `timeout` for in-flight tasks, then release
+    /// the rest
+    ///
+    /// Released tasks go back to pending on their queue as another attempt.
+    /// Their host may still be running them, so a released task can run
+    /// twice, as with one the reaper reclaims.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<DrainReport> {
+        self.begin_shutdown();
+        let mut report = DrainReport {
+            idle: self.wait_idle(timeout).await,
+            released: Vec::new(),
+        };
+
+        for execution_id in self.in_flight() {
+            if release_task(&self.pool, &execution_id).await? {
+                tracing::warn!(
+                    execution_id = %execution_id,
+                    "Task still running at shutdown, released it for another worker"
+                );
+                report.released.push(execution_id.clone());
+            }
+            self.in_flight.finish(&execution_id);
+        }
+        Ok(report)
+    }
+}
+
+/// Put a running task back on its queue; returns `false` if it had finished
+async fn release_task(pool: &PgPool, execution_id: &str) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let Some(task) = db::executions::retry_execution(&mut *tx, execution_id).await? else {
+        return Ok(false);
+    };
+    db::work_queue::remove_work(&mut *tx, &[execution_id.to_string()]).await?;
+    db::work_queue::enqueue_work(&mut *tx, execution_id, &task.queue, 0).await?;
+    tx.commit().await?;
+    Ok(true)
+}
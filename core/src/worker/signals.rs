@@ -53,7 +53,7 @@ pub async fn resolve_signal_claims(pool: &PgPool, workflow_id: &str) -> Result<i
         .await?;
 
         // Match 1:1 in order
-        for (req, signal_id) in reqs.iter().zip(available_signals.into_iter()) {
+        for (req, signal_id) in reqs.iter().zip(available_signals) {
             matches.push((req, signal_id));
         }
     }
@@ -0,0 +1,192 @@
+//! Sticky workflow runs
+//!
+//! Resuming a workflow normally means reading its saved state and
+//! deserializing the VM again. With `[worker.sticky]` enabled, a worker
+//! records itself on each state it saves and keeps the VM it saved in a
+//! `VmCache`. When the workflow is queued again other workers leave it alone
+//! for the sticky timeout, so it usually resumes on the worker holding its
+//! VM; after the timeout (e.g. the worker went away) anyone may claim it.
+//!
+//! A cached VM is only used while its version matches the saved state's, so
+//! a run elsewhere or a rewritten state (see `migrate-state`) is never
+//! missed; the worst case is reloading from the database as before.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::StickyConfig;
+use crate::executor::{Outbox, VM};
+
+/// How a worker keeps the workflows it runs
+#[derive(Debug, Clone)]
+pub struct StickyOptions {
+    /// Worker saved workflows prefer when they are next queued
+    pub worker_id: String,
+    /// How long other workers leave a queued workflow to `worker_id`
+    pub timeout: Duration,
+    pub cache: Arc<VmCache>,
+}
+
+impl StickyOptions {
+    pub fn new(worker_id: String, config: &StickyConfig) -> Self {
+        Self {
+            worker_id,
+            timeout: Duration::from_millis(config.timeout_ms),
+            cache: Arc::new(VmCache::new(config.cache_size)),
+        }
+    }
+
+    /// The `(worker_id, timeout_ms)` to save with a workflow's state
+    pub(crate) fn preference(&self) -> (&str, i32) {
+        let timeout_ms = i32::try_from(self.timeout.as_millis()).unwrap_or(i32::MAX);
+        (&self.worker_id, timeout_ms)
+    }
+}
+
+/// VMs of saved workflows, keyed by execution id and state version
+///
+/// Holds at most `capacity` VMs, dropping the least recently saved first.
+#[derive(Debug)]
+pub struct VmCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_execution: HashMap<String, CachedVm>,
+    /// Increases with each insert, to find the oldest entry
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct CachedVm {
+    version: i64,
+    workflow_def_id: i32,
+    vm: VM,
+    saved_at: u64,
+}
+
+impl VmCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Keep `vm`, saved as version `version` of the execution's state
+    ///
+    /// Runtime-only parts of the VM are reset, so it resumes exactly as if
+    /// it had been deserialized.
+    pub fn insert(&self, execution_id: &str, version: i64, workflow_def_id: i32, mut vm: VM) {
+        if self.capacity == 0 {
+            return;
+        }
+        vm.resume_value = None;
+        vm.outbox = Outbox::new();
+        vm.trace = None;
+
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.by_execution.contains_key(execution_id)
+            && entries.by_execution.len() >= self.capacity
+        {
+            let oldest = entries
+                .by_execution
+                .iter()
+                .min_by_key(|(_, cached)| cached.saved_at)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                entries.by_execution.remove(&oldest);
+            }
+        }
+        entries.clock += 1;
+        let saved_at = entries.clock;
+        entries.by_execution.insert(
+            execution_id.to_string(),
+            CachedVm {
+                version,
+                workflow_def_id,
+                vm,
+                saved_at,
+            },
+        );
+    }
+
+    /// Take the execution's VM and definition id if it is at `version`
+    ///
+    /// The entry is removed either way: the run that takes it saves a newer
+    /// version, and an entry at another version is stale.
+    pub fn take(&self, execution_id: &str, version: i64) -> Option<(VM, i32)> {
+        let cached = self
+            .entries
+            .lock()
+            .unwrap()
+            .by_execution
+            .remove(execution_id)?;
+        (cached.version == version).then_some((cached.vm, cached.workflow_def_id))
+    }
+
+    /// Number of VMs held
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().by_execution.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{Val, WorkflowContext};
+    use crate::parser::parse_workflow;
+
+    fn vm() -> VM {
+        let workflow = parse_workflow("return 1").unwrap();
+        let context = WorkflowContext {
+            execution_id: "wf".to_string(),
+        };
+        VM::new(workflow.body, HashMap::new(), context)
+    }
+
+    #[test]
+    fn test_take_requires_the_saved_version() {
+        let cache = VmCache::new(10);
+        let mut saved = vm();
+        saved.resume_value = Some(Val::Num(1.0));
+        cache.insert("wf", 3, 7, saved);
+
+        let (vm, workflow_def_id) = cache.take("wf", 3).unwrap();
+        assert_eq!(workflow_def_id, 7);
+        assert_eq!(vm.resume_value, None);
+        assert!(cache.is_empty());
+
+        // A stale entry is dropped rather than used
+        cache.insert("wf", 3, 7, vm);
+        assert!(cache.take("wf", 4).is_none());
+        assert!(cache.take("wf", 3).is_none());
+    }
+
+    #[test]
+    fn test_oldest_entry_is_evicted_at_capacity() {
+        let cache = VmCache::new(2);
+        cache.insert("a", 1, 1, vm());
+        cache.insert("b", 1, 1, vm());
+        // Saving a held execution again doesn't evict another
+        cache.insert("a", 2, 1, vm());
+        assert_eq!(cache.len(), 2);
+
+        cache.insert("c", 1, 1, vm());
+        assert_eq!(cache.len(), 2);
+        assert!(cache.take("b", 1).is_none());
+        assert!(cache.take("a", 2).is_some());
+        assert!(cache.take("c", 1).is_some());
+
+        let disabled = VmCache::new(0);
+        disabled.insert("a", 1, 1, vm());
+        assert!(disabled.is_empty());
+    }
+}
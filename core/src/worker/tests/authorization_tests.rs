@@ -0,0 +1,130 @@
+//! Tests for claim authorization policies
+
+use chrono::Utc;
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::config::{ClaimPolicyConfig, ClaimRule, WorkerConfig};
+use crate::types::{Execution, ExecutionStatus, ExecutionType};
+use crate::worker::WorkerIdentity;
+use crate::worker::{ClaimAuthorizer, ClaimDecision, ClaimPolicy, ClaimRequest, LabelRulePolicy};
+
+fn execution(target_name: &str) -> Execution {
+    Execution {
+        id: "exec-1".to_string(),
+        exec_type: ExecutionType::Task,
+        target_name: target_name.to_string(),
+        queue: "payments".to_string(),
+        namespace: "default".to_string(),
+        status: ExecutionStatus::Pending,
+        inputs: json!({}),
+        output: None,
+        attempt: 0,
+        parent_workflow_id: None,
+        created_at: Utc::now(),
+        completed_at: None,
+    }
+}
+
+fn worker(labels: &[(&str, &str)]) -> WorkerIdentity {
+    WorkerIdentity {
+        id: "w1".to_string(),
+        labels: labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    }
+}
+
+fn pci_rule() -> ClaimRule {
+    ClaimRule {
+        queue: Some("payments".to_string()),
+        target_name: None,
+        require_labels: HashMap::from([("pci".to_string(), "true".to_string())]),
+    }
+}
+
+#[test]
+fn test_label_rule_denies_worker_missing_label() {
+    let policy = LabelRulePolicy::new(vec![pci_rule()]);
+    let exec = execution("charge");
+    let decision = policy.authorize(&ClaimRequest {
+        worker: &worker(&[]),
+        queue: "payments",
+        execution: &exec,
+    });
+
+    match decision {
+        ClaimDecision::Deny { reason } => assert!(reason.contains("pci=true")),
+        ClaimDecision::Allow => panic!("Expected denial"),
+    }
+}
+
+#[test]
+fn test_label_rule_allows_labeled_worker() {
+    let policy = LabelRulePolicy::new(vec![pci_rule()]);
+    let exec = execution("charge");
+    let decision = policy.authorize(&ClaimRequest {
+        worker: &worker(&[("pci", "true"), ("region", "eu")]),
+        queue: "payments",
+        execution: &exec,
+    });
+    assert_eq!(decision, ClaimDecision::Allow);
+}
+
+#[test]
+fn test_label_rule_ignores_other_queues() {
+    let policy = LabelRulePolicy::new(vec![pci_rule()]);
+    let exec = execution("send_email");
+    let decision = policy.authorize(&ClaimRequest {
+        worker: &worker(&[]),
+        queue: "default",
+        execution: &exec,
+    });
+    assert_eq!(decision, ClaimDecision::Allow);
+}
+
+#[test]
+fn test_label_rule_wrong_label_value_denied() {
+    let policy = LabelRulePolicy::new(vec![pci_rule()]);
+    let exec = execution("charge");
+    let decision = policy.authorize(&ClaimRequest {
+        worker: &worker(&[("pci", "false")]),
+        queue: "payments",
+        execution: &exec,
+    });
+    assert!(matches!(decision, ClaimDecision::Deny { .. }));
+}
+
+#[test]
+fn test_authorizer_from_config() {
+    let worker_config = WorkerConfig {
+        id: Some("configured".to_string()),
+        labels: HashMap::new(),
+        ..Default::default()
+    };
+
+    let authorizer = ClaimAuthorizer::from_config(&worker_config, &ClaimPolicyConfig::default());
+    assert_eq!(authorizer.identity.id, "configured");
+    assert_eq!(
+        authorizer.authorize("payments", &execution("charge")),
+        ClaimDecision::Allow
+    );
+
+    let authorizer = ClaimAuthorizer::from_config(
+        &worker_config,
+        &ClaimPolicyConfig {
+            rules: vec![pci_rule()],
+        },
+    );
+    assert!(matches!(
+        authorizer.authorize("payments", &execution("charge")),
+        ClaimDecision::Deny { .. }
+    ));
+}
+
+#[test]
+fn test_generated_worker_id_when_unset() {
+    let identity = WorkerIdentity::from_config(&WorkerConfig::default());
+    assert!(identity.id.starts_with("worker-"));
+}
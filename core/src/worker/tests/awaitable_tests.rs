@@ -11,7 +11,9 @@ use serde_json::json;
 
 use super::super::run_workflow;
 use crate::db;
-use crate::test_helpers::{enqueue_and_claim_execution, get_child_tasks, setup_workflow_test};
+use crate::test_helpers::{
+    complete_task, enqueue_and_claim_execution, fail_task, get_child_tasks, setup_workflow_test,
+};
 use crate::types::ExecutionStatus;
 
 /* ===================== Promise.all() Integration Tests ===================== */
@@ -43,7 +45,7 @@ async fn test_task_all_waits_for_all_tasks() {
     assert_eq!(tasks.len(), 2);
 
     let task1_id = &tasks[0].0;
-    db::executions::complete_execution(pool.as_ref(), task1_id, json!("result1"))
+    complete_task(&pool, task1_id, json!("result1"))
         .await
         .unwrap();
 
@@ -69,7 +71,7 @@ async fn test_task_all_waits_for_all_tasks() {
 
     // Complete task2
     let task2_id = &tasks[1].0;
-    db::executions::complete_execution(pool.as_ref(), task2_id, json!("result2"))
+    complete_task(&pool, task2_id, json!("result2"))
         .await
         .unwrap();
 
@@ -113,8 +115,8 @@ async fn test_task_all_fails_fast_on_error() {
     // Fail task1 (task2 still pending)
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task1_id = &tasks[0].0;
-    db::executions::fail_execution(
-        pool.as_ref(),
+    fail_task(
+        &pool,
         task1_id,
         json!({"code": "TASK_FAILED", "message": "Task 1 failed"}),
     )
@@ -161,9 +163,7 @@ async fn test_task_all_with_object() {
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     for (task_id, task_name) in &tasks {
         let result = if task_name == "task1" { "one" } else { "two" };
-        db::executions::complete_execution(pool.as_ref(), task_id, json!(result))
-            .await
-            .unwrap();
+        complete_task(&pool, task_id, json!(result)).await.unwrap();
     }
 
     // Resume - should complete with object result
@@ -207,7 +207,7 @@ async fn test_task_any_returns_first_success() {
     // Complete task2 first (task1 still pending)
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task2_id = &tasks[1].0;
-    db::executions::complete_execution(pool.as_ref(), task2_id, json!("winner"))
+    complete_task(&pool, task2_id, json!("winner"))
         .await
         .unwrap();
 
@@ -249,7 +249,7 @@ async fn test_task_any_skips_failures() {
     // Fail task1
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task1_id = &tasks[0].0;
-    db::executions::fail_execution(pool.as_ref(), task1_id, json!({"error": "failed"}))
+    fail_task(&pool, task1_id, json!({"error": "failed"}))
         .await
         .unwrap();
 
@@ -275,7 +275,7 @@ async fn test_task_any_skips_failures() {
 
     // Complete task2
     let task2_id = &tasks[1].0;
-    db::executions::complete_execution(pool.as_ref(), task2_id, json!("success"))
+    complete_task(&pool, task2_id, json!("success"))
         .await
         .unwrap();
 
@@ -317,7 +317,7 @@ async fn test_task_any_all_fail_returns_aggregate_error() {
     // Fail both tasks
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     for (task_id, _) in &tasks {
-        db::executions::fail_execution(pool.as_ref(), task_id, json!({"error": "failed"}))
+        fail_task(&pool, task_id, json!({"error": "failed"}))
             .await
             .unwrap();
     }
@@ -362,7 +362,7 @@ async fn test_task_race_returns_first_success() {
     // Complete task1 first
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task1_id = &tasks[0].0;
-    db::executions::complete_execution(pool.as_ref(), task1_id, json!("first"))
+    complete_task(&pool, task1_id, json!("first"))
         .await
         .unwrap();
 
@@ -404,8 +404,8 @@ async fn test_task_race_returns_first_error() {
     // Fail task1 first (task2 still pending)
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task1_id = &tasks[0].0;
-    db::executions::fail_execution(
-        pool.as_ref(),
+    fail_task(
+        &pool,
         task1_id,
         json!({"code": "RACE_LOSER", "message": "Failed first"}),
     )
@@ -485,7 +485,7 @@ async fn test_task_all_with_timer() {
     // Complete the task
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     assert_eq!(tasks.len(), 1);
-    db::executions::complete_execution(pool.as_ref(), &tasks[0].0, json!("task_done"))
+    complete_task(&pool, &tasks[0].0, json!("task_done"))
         .await
         .unwrap();
 
@@ -566,7 +566,7 @@ async fn test_nested_race_in_all() {
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     // Find task2 (the one not in the race)
     let task2 = tasks.iter().find(|(_, name)| name == "task2").unwrap();
-    db::executions::complete_execution(pool.as_ref(), &task2.0, json!("task2_done"))
+    complete_task(&pool, &task2.0, json!("task2_done"))
         .await
         .unwrap();
 
@@ -610,7 +610,7 @@ async fn test_task_any_kv_returns_key_and_value() {
     // Complete task2 first (task1 still pending)
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task2_id = &tasks[1].0;
-    db::executions::complete_execution(pool.as_ref(), task2_id, json!("winner"))
+    complete_task(&pool, task2_id, json!("winner"))
         .await
         .unwrap();
 
@@ -654,7 +654,7 @@ async fn test_task_any_kv_with_object() {
     // Complete task1
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task1 = tasks.iter().find(|(_, name)| name == "task1").unwrap();
-    db::executions::complete_execution(pool.as_ref(), &task1.0, json!("first_wins"))
+    complete_task(&pool, &task1.0, json!("first_wins"))
         .await
         .unwrap();
 
@@ -700,7 +700,7 @@ async fn test_task_race_kv_returns_key_and_value() {
     // Complete task1 first
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     let task1_id = &tasks[0].0;
-    db::executions::complete_execution(pool.as_ref(), task1_id, json!("first"))
+    complete_task(&pool, task1_id, json!("first"))
         .await
         .unwrap();
 
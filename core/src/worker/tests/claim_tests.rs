@@ -4,10 +4,15 @@
 //! edge cases like stale timer continuations.
 
 use serde_json::json;
+use std::collections::HashMap;
 use tokio_util::sync::CancellationToken;
 
-use super::super::{run_cooperative_worker_loop, DelegatedAction};
+use super::super::{
+    run_cooperative_worker_loop, ClaimAuthorizer, DelegatedAction, MiddlewareChain, RunnerOptions,
+    WorkerCounters,
+};
 use crate::db;
+use crate::queue_backend::PostgresQueue;
 use crate::test_helpers::with_test_db;
 use crate::types::{CreateExecutionParams, ExecutionStatus, ExecutionType};
 
@@ -41,6 +46,13 @@ async fn test_stale_workflow_continuation_is_skipped() {
         queue: "default".to_string(),
         inputs: json!({}),
         parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
     };
 
     let mut tx = pool.begin().await.unwrap();
@@ -53,9 +65,19 @@ async fn test_stale_workflow_continuation_is_skipped() {
     tx.commit().await.unwrap();
 
     // Run the cooperative worker loop - it should claim and complete the workflow
-    let action = run_cooperative_worker_loop(&pool, &shutdown_token)
-        .await
-        .unwrap();
+    let action = run_cooperative_worker_loop(
+        &pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &shutdown_token,
+        &ClaimAuthorizer::default(),
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        &RunnerOptions::default(),
+        &[],
+    )
+    .await
+    .unwrap();
     assert!(matches!(action, DelegatedAction::Continue));
 
     // Verify workflow completed
@@ -72,9 +94,19 @@ async fn test_stale_workflow_continuation_is_skipped() {
         .unwrap();
 
     // Run the worker loop again - should skip the stale continuation
-    let action = run_cooperative_worker_loop(&pool, &shutdown_token)
-        .await
-        .unwrap();
+    let action = run_cooperative_worker_loop(
+        &pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &shutdown_token,
+        &ClaimAuthorizer::default(),
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        &RunnerOptions::default(),
+        &[],
+    )
+    .await
+    .unwrap();
     assert!(
         matches!(action, DelegatedAction::Continue),
         "Should return Continue after skipping stale continuation"
@@ -139,6 +171,13 @@ async fn test_stale_continuation_for_failed_workflow_is_skipped() {
         queue: "default".to_string(),
         inputs: json!({}),
         parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
     };
 
     let mut tx = pool.begin().await.unwrap();
@@ -151,9 +190,19 @@ async fn test_stale_continuation_for_failed_workflow_is_skipped() {
     tx.commit().await.unwrap();
 
     // Run the workflow - it should fail
-    let action = run_cooperative_worker_loop(&pool, &shutdown_token)
-        .await
-        .unwrap();
+    let action = run_cooperative_worker_loop(
+        &pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &shutdown_token,
+        &ClaimAuthorizer::default(),
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        &RunnerOptions::default(),
+        &[],
+    )
+    .await
+    .unwrap();
     assert!(matches!(action, DelegatedAction::Continue));
 
     // Verify workflow failed
@@ -170,9 +219,19 @@ async fn test_stale_continuation_for_failed_workflow_is_skipped() {
         .unwrap();
 
     // Run the worker loop again - should skip the stale continuation
-    let action = run_cooperative_worker_loop(&pool, &shutdown_token)
-        .await
-        .unwrap();
+    let action = run_cooperative_worker_loop(
+        &pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &shutdown_token,
+        &ClaimAuthorizer::default(),
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        &RunnerOptions::default(),
+        &[],
+    )
+    .await
+    .unwrap();
     assert!(matches!(action, DelegatedAction::Continue));
 
     // Verify workflow is still failed (not restarted)
@@ -199,3 +258,189 @@ async fn test_stale_continuation_for_failed_workflow_is_skipped() {
             .unwrap();
     assert_eq!(work_count, 0, "Work queue should be empty after processing");
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_claim_denied_by_policy_is_released() {
+    use crate::config::ClaimRule;
+    use crate::worker::{LabelRulePolicy, WorkerIdentity};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    let pool = with_test_db().await;
+    let shutdown_token = CancellationToken::new();
+
+    let params = CreateExecutionParams {
+        id: None,
+        exec_type: ExecutionType::Task,
+        target_name: "charge_card".to_string(),
+        queue: "default".to_string(),
+        inputs: json!({}),
+        parent_workflow_id: None,
+        namespace: None,
+        ttl_seconds: None,
+        retry_policy: None,
+        priority: None,
+        idempotency_key: None,
+        traceparent: None,
+        tags: HashMap::new(),
+    };
+    let mut tx = pool.begin().await.unwrap();
+    let task_id = db::executions::create_execution(&mut tx, params)
+        .await
+        .unwrap();
+    db::work_queue::enqueue_work(&mut *tx, &task_id, "default", 0)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let policy = Arc::new(LabelRulePolicy::new(vec![ClaimRule {
+        queue: None,
+        target_name: Some("charge_card".to_string()),
+        require_labels: HashMap::from([("pci".to_string(), "true".to_string())]),
+    }]));
+
+    // An unlabeled worker is denied and the work goes back to the queue untouched
+    let plain = ClaimAuthorizer::new(
+        WorkerIdentity {
+            id: "plain".to_string(),
+            labels: HashMap::new(),
+        },
+        policy.clone(),
+    );
+    let action = run_cooperative_worker_loop(
+        &pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &shutdown_token,
+        &plain,
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        &RunnerOptions::default(),
+        &[],
+    )
+    .await
+    .unwrap();
+    assert!(matches!(action, DelegatedAction::Wait { .. }));
+
+    let execution = db::executions::get_execution(&pool, &task_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Pending);
+    let unclaimed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM work_queue WHERE execution_id = $1 AND claimed_until IS NULL",
+    )
+    .bind(&task_id)
+    .fetch_one(pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(unclaimed, 1, "Denied work should be released, not held");
+
+    // A labeled worker can claim it
+    let pci = ClaimAuthorizer::new(
+        WorkerIdentity {
+            id: "pci".to_string(),
+            labels: HashMap::from([("pci".to_string(), "true".to_string())]),
+        },
+        policy,
+    );
+    let action = run_cooperative_worker_loop(
+        &pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &shutdown_token,
+        &pci,
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        &RunnerOptions::default(),
+        &[],
+    )
+    .await
+    .unwrap();
+    match action {
+        DelegatedAction::ExecuteTask { execution_id, .. } => assert_eq!(execution_id, task_id),
+        other => panic!("Expected ExecuteTask, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_denied_claim_does_not_block_later_work() {
+    use crate::config::ClaimRule;
+    use crate::test_helpers::task_params;
+    use crate::worker::{LabelRulePolicy, WorkerIdentity};
+    use std::sync::Arc;
+
+    let pool = with_test_db().await;
+    let shutdown_token = CancellationToken::new();
+
+    // The denied task is ahead of the allowed one in claim order
+    let mut tx = pool.begin().await.unwrap();
+    let denied_id = db::executions::create_execution(&mut tx, task_params("charge_card"))
+        .await
+        .unwrap();
+    db::work_queue::enqueue_work(&mut *tx, &denied_id, "default", 10)
+        .await
+        .unwrap();
+    let allowed_id = db::executions::create_execution(&mut tx, task_params("send_email"))
+        .await
+        .unwrap();
+    db::work_queue::enqueue_work(&mut *tx, &allowed_id, "default", 0)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let plain = ClaimAuthorizer::new(
+        WorkerIdentity {
+            id: "plain".to_string(),
+            labels: HashMap::new(),
+        },
+        Arc::new(LabelRulePolicy::new(vec![ClaimRule {
+            queue: None,
+            target_name: Some("charge_card".to_string()),
+            require_labels: HashMap::from([("pci".to_string(), "true".to_string())]),
+        }])),
+    );
+    let queue = PostgresQueue::new(pool.clone());
+    let middleware = MiddlewareChain::default();
+    let counters = WorkerCounters::default();
+    let runner = RunnerOptions::default();
+    let poll = || {
+        run_cooperative_worker_loop(
+            &pool,
+            &queue,
+            "default",
+            &shutdown_token,
+            &plain,
+            &middleware,
+            &counters,
+            &runner,
+            &[],
+        )
+    };
+
+    // One poll passes over the denied task to the allowed one
+    match poll().await.unwrap() {
+        DelegatedAction::ExecuteTask { execution_id, .. } => assert_eq!(execution_id, allowed_id),
+        other => panic!("Expected ExecuteTask, got {:?}", other),
+    }
+
+    // The denied task waits in the queue for another worker
+    assert!(matches!(
+        poll().await.unwrap(),
+        DelegatedAction::Wait { .. }
+    ));
+    let unclaimed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM work_queue WHERE execution_id = $1 AND claimed_until IS NULL",
+    )
+    .bind(&denied_id)
+    .fetch_one(pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(unclaimed, 1);
+
+    // Other workers still claim it
+    let claimed = db::work_queue::claim_work(pool.as_ref(), "default", 1, Some("pci"))
+        .await
+        .unwrap();
+    assert_eq!(claimed, vec![denied_id]);
+}
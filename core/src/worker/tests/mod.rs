@@ -1,6 +1,10 @@
 //! Worker tests
 
+mod authorization_tests;
 mod awaitable_tests;
 mod claim_tests;
+mod replay_tests;
 mod runner_tests;
 mod signals_tests;
+mod sticky_tests;
+mod stream_tests;
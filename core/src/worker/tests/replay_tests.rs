@@ -0,0 +1,156 @@
+//! Tests for replay checks on resume
+//!
+//! A workflow is replayed against its history before it is resumed, and
+//! failed if the replay makes different calls.
+
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use super::super::{
+    complete_work, run_cooperative_worker_loop, AllowAllPolicy, ClaimAuthorizer, DelegatedAction,
+    MiddlewareChain, RunnerOptions, WorkerCounters, WorkerIdentity,
+};
+use crate::db;
+use crate::queue_backend::PostgresQueue;
+use crate::types::{CreateExecutionParams, ExecutionStatus, ExecutionType};
+
+async fn run_once(pool: &PgPool, options: &RunnerOptions) -> DelegatedAction {
+    let identity = WorkerIdentity {
+        id: "w".to_string(),
+        labels: HashMap::new(),
+    };
+    run_cooperative_worker_loop(
+        pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &CancellationToken::new(),
+        &ClaimAuthorizer::new(identity, Arc::new(AllowAllPolicy)),
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        options,
+        &[],
+    )
+    .await
+    .unwrap()
+}
+
+/// Start a workflow that charges with a clock read, and run it to the charge
+///
+/// Returns the ids of the workflow and the charge task.
+async fn start_charge(pool: &PgPool, options: &RunnerOptions) -> (String, String) {
+    db::workflow_definitions::create_workflow_definition(
+        pool,
+        "replayed",
+        "test-replayed",
+        r#"
+            let receipt = await Task.run("charge", { amount: 1, at: Date.now() })
+            let label = await Task.run("ship", { receipt: receipt })
+            return label
+        "#,
+    )
+    .await
+    .unwrap();
+    let mut tx = pool.begin().await.unwrap();
+    let workflow_id = db::executions::create_execution(
+        &mut tx,
+        CreateExecutionParams {
+            id: None,
+            exec_type: ExecutionType::Workflow,
+            target_name: "replayed".to_string(),
+            queue: "default".to_string(),
+            inputs: json!({}),
+            parent_workflow_id: None,
+            namespace: None,
+            ttl_seconds: None,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            traceparent: None,
+            tags: HashMap::new(),
+        },
+    )
+    .await
+    .unwrap();
+    db::work_queue::enqueue_work(&mut *tx, &workflow_id, "default", 0)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    assert!(matches!(
+        run_once(pool, options).await,
+        DelegatedAction::Continue
+    ));
+    let task_id = match run_once(pool, options).await {
+        DelegatedAction::ExecuteTask { execution_id, .. } => execution_id,
+        other => panic!("Expected the task, got {:?}", other),
+    };
+    (workflow_id, task_id)
+}
+
+async fn run_task(pool: &PgPool, options: &RunnerOptions, output: serde_json::Value) {
+    let task_id = match run_once(pool, options).await {
+        DelegatedAction::ExecuteTask { execution_id, .. } => execution_id,
+        other => panic!("Expected a task, got {:?}", other),
+    };
+    complete_work(pool, &task_id, Some(output), None)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn test_workflow_that_replays_the_same_way_resumes(pool: PgPool) {
+    let options = RunnerOptions {
+        replay_check: true,
+        ..Default::default()
+    };
+    let (workflow_id, task_id) = start_charge(&pool, &options).await;
+    complete_work(&pool, &task_id, Some(json!("r1")), None)
+        .await
+        .unwrap();
+
+    // Resumed once after the charge, and again after shipping
+    run_once(&pool, &options).await;
+    run_task(&pool, &options, json!("label")).await;
+    run_once(&pool, &options).await;
+
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+    assert_eq!(execution.output, Some(json!("label")));
+}
+
+#[sqlx::test]
+async fn test_workflow_that_replays_differently_fails(pool: PgPool) {
+    let options = RunnerOptions {
+        replay_check: true,
+        ..Default::default()
+    };
+    let (workflow_id, task_id) = start_charge(&pool, &options).await;
+    // The history no longer matches what the workflow does
+    sqlx::query("UPDATE executions SET inputs = inputs || '{\"amount\": 2}' WHERE id = $1")
+        .bind(&task_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    complete_work(&pool, &task_id, Some(json!("r1")), None)
+        .await
+        .unwrap();
+
+    run_once(&pool, &options).await;
+
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    let error = execution.output.unwrap();
+    assert_eq!(error["type"], "NonDeterministic");
+    assert_eq!(error["divergence"]["kind"], "call");
+    assert_eq!(error["divergence"]["position"], 0);
+    assert_eq!(error["divergence"]["expected"]["inputs"]["amount"], 2);
+}
@@ -9,14 +9,16 @@
 
 use serde_json::json;
 
-use super::super::run_workflow;
+use super::super::{run_workflow, RunnerOptions};
+use crate::config::{StepBudgetAction, StepBudgetConfig};
 use crate::db;
 use crate::test_helpers::{
-    enqueue_and_claim_execution, get_child_executions_with_type, get_child_task_count,
-    get_child_tasks, get_child_workflows, get_task_by_target_name, get_unclaimed_work_count,
-    get_work_queue_count, setup_workflow_test, setup_workflow_test_with_pool,
+    complete_task, enqueue_and_claim_execution, fail_task, get_child_executions_with_type,
+    get_child_task_count, get_child_tasks, get_child_workflows, get_task_by_target_name,
+    get_unclaimed_work_count, get_work_queue_count, setup_workflow_test,
+    setup_workflow_test_with_pool,
 };
-use crate::types::ExecutionStatus;
+use crate::types::{ExecutionEventType, ExecutionStatus};
 
 #[tokio::test(flavor = "multi_thread")]
 async fn test_workflow_completes_without_return_statement() {
@@ -116,9 +118,7 @@ async fn test_workflow_with_task_but_no_return_statement() {
     let (task_id, _) = &child_tasks[0];
 
     // Complete the task out-of-band
-    db::executions::complete_execution(pool.as_ref(), task_id, json!(100))
-        .await
-        .unwrap();
+    complete_task(&pool, task_id, json!(100)).await.unwrap();
 
     // Enqueue work again for the workflow to resume
     enqueue_and_claim_execution(&pool, &workflow_id, "default")
@@ -185,9 +185,7 @@ async fn test_workflow_suspends_on_task_then_completes() {
     assert_eq!(task_name, "process_data");
 
     // Complete the task out-of-band
-    db::executions::complete_execution(pool.as_ref(), task_id, json!(100))
-        .await
-        .unwrap();
+    complete_task(&pool, task_id, json!(100)).await.unwrap();
 
     // Enqueue work again for the workflow to resume
     enqueue_and_claim_execution(&pool, &workflow_id, "default")
@@ -242,9 +240,7 @@ async fn test_workflow_with_multiple_sequential_tasks() {
         .await
         .unwrap();
 
-    db::executions::complete_execution(pool.as_ref(), &task1_id, json!(10))
-        .await
-        .unwrap();
+    complete_task(&pool, &task1_id, json!(10)).await.unwrap();
 
     // Run 2: Suspend on second task
     enqueue_and_claim_execution(&pool, &workflow_id, "default")
@@ -262,9 +258,7 @@ async fn test_workflow_with_multiple_sequential_tasks() {
         .await
         .unwrap();
 
-    db::executions::complete_execution(pool.as_ref(), &task2_id, json!(20))
-        .await
-        .unwrap();
+    complete_task(&pool, &task2_id, json!(20)).await.unwrap();
 
     // Run 3: Suspend on third task
     enqueue_and_claim_execution(&pool, &workflow_id, "default")
@@ -282,9 +276,7 @@ async fn test_workflow_with_multiple_sequential_tasks() {
         .await
         .unwrap();
 
-    db::executions::complete_execution(pool.as_ref(), &task3_id, json!(30))
-        .await
-        .unwrap();
+    complete_task(&pool, &task3_id, json!(30)).await.unwrap();
 
     // Run 4: Complete workflow
     enqueue_and_claim_execution(&pool, &workflow_id, "default")
@@ -333,9 +325,7 @@ async fn test_workflow_with_fire_and_forget_task() {
     assert_eq!(tasks[1].1, "main_task");
 
     // Complete only the main task
-    db::executions::complete_execution(pool.as_ref(), &tasks[1].0, json!(999))
-        .await
-        .unwrap();
+    complete_task(&pool, &tasks[1].0, json!(999)).await.unwrap();
 
     // Resume workflow
     enqueue_and_claim_execution(&pool, &workflow_id, "default")
@@ -429,8 +419,8 @@ async fn test_workflow_resumes_with_failed_task() {
     let task_id = &tasks[0].0;
 
     // Fail the task with error output
-    db::executions::fail_execution(
-        pool.as_ref(),
+    fail_task(
+        &pool,
         task_id,
         json!({"error": "Task failed!", "code": "TASK_ERROR"}),
     )
@@ -460,6 +450,65 @@ async fn test_workflow_resumes_with_failed_task() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_workflow_runs_compensations_before_failing() {
+    let workflow_source = r#"
+        let order = await Task.run("place_order", {})
+        Compensate.register("cancel_order", { order })
+        throw Error("Shipping failed", "SHIPPING")
+    "#;
+
+    let (pool, execution) =
+        setup_workflow_test("workflow_with_compensation", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+    run_workflow(&pool, execution).await.unwrap();
+
+    let order_id = get_task_by_target_name(&pool, &workflow_id, "place_order")
+        .await
+        .unwrap();
+    complete_task(&pool, &order_id, json!("o-1")).await.unwrap();
+    enqueue_and_claim_execution(&pool, &workflow_id, "default")
+        .await
+        .unwrap();
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    run_workflow(&pool, execution).await.unwrap();
+
+    // Suspended on the compensation rather than failed
+    let workflow_execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(workflow_execution.status, ExecutionStatus::Suspended);
+    let cancel_id = get_task_by_target_name(&pool, &workflow_id, "cancel_order")
+        .await
+        .unwrap();
+    let cancel = db::executions::get_execution(&pool, &cancel_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cancel.inputs, json!({ "order": "o-1" }));
+
+    complete_task(&pool, &cancel_id, json!(null)).await.unwrap();
+    enqueue_and_claim_execution(&pool, &workflow_id, "default")
+        .await
+        .unwrap();
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    run_workflow(&pool, execution).await.unwrap();
+
+    let workflow_execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(workflow_execution.status, ExecutionStatus::Failed);
+    assert_eq!(workflow_execution.output.unwrap()["code"], "SHIPPING");
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_resume_without_task_completion_fails() {
     let workflow_source = r#"
@@ -746,6 +795,109 @@ async fn test_awaited_task_is_enqueued_to_work_queue() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_run_options_apply_to_child_task() {
+    let workflow_source = r#"
+        Task.run("charge", {}, { timeout_seconds: 60, max_retries: 7, queue: "payments", priority: 5 })
+        Task.run("refund", {}, { give_up_on: ["VALIDATION"] })
+        Task.run("receipt", {})
+        return "tasks_created"
+    "#;
+
+    let (pool, execution) =
+        setup_workflow_test("workflow_task_options_test", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+    run_workflow(&pool, execution).await.unwrap();
+
+    let charge_id = get_task_by_target_name(&pool, &workflow_id, "charge")
+        .await
+        .unwrap();
+    let charge = db::executions::get_execution(&pool, &charge_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(charge.queue, "payments");
+    let (queue, priority, has_expiry): (String, i32, bool) = sqlx::query_as(
+        r#"
+        SELECT w.queue, w.priority, e.expires_at IS NOT NULL
+        FROM work_queue w JOIN executions e ON e.id = w.execution_id
+        WHERE w.execution_id = $1
+        "#,
+    )
+    .bind(&charge_id)
+    .fetch_one(&*pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        (queue.as_str(), priority, has_expiry),
+        ("payments", 5, true)
+    );
+    let policy = db::executions::get_retry_policy(&*pool, &charge_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(policy.max_retries, Some(7));
+
+    // Codes alone are stored without a limit, which stays the task config's
+    let refund_id = get_task_by_target_name(&pool, &workflow_id, "refund")
+        .await
+        .unwrap();
+    let policy = db::executions::get_retry_policy(&*pool, &refund_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(policy.max_retries, None);
+    assert_eq!(policy.give_up_on, vec!["VALIDATION".to_string()]);
+    assert!(policy.retry_on.is_empty());
+
+    // Without options the task takes the workflow's queue and no policy
+    let receipt_id = get_task_by_target_name(&pool, &workflow_id, "receipt")
+        .await
+        .unwrap();
+    let receipt = db::executions::get_execution(&pool, &receipt_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(receipt.queue, "default");
+    assert!(db::executions::get_retry_policy(&*pool, &receipt_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_child_on_full_queue_fails_the_run_transiently() {
+    use crate::config::QueueConfig;
+    use crate::queue_limits::{QueueFull, QueueLimits};
+
+    let workflow_source = r#"
+        Task.run("send", {}, { queue: "full_child_queue" })
+        return "sent"
+    "#;
+
+    let (pool, execution) =
+        setup_workflow_test("workflow_full_child_queue", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+
+    let options = RunnerOptions {
+        queue_limits: QueueLimits::from_config(&QueueConfig {
+            max_depth: [("full_child_queue".to_string(), 0)].into(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let err = super::super::runner::run_workflow_with_step_budget(&pool, execution, &options)
+        .await
+        .unwrap_err();
+    assert_eq!(err.downcast_ref::<QueueFull>().unwrap().limit, 0);
+    assert!(super::super::runner_retry::is_transient(&err));
+
+    // Nothing the run did was kept
+    assert!(get_task_by_target_name(&pool, &workflow_id, "send")
+        .await
+        .is_err());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_workflow_runtime_error_sets_failed_status() {
     // Workflow that throws a runtime error by accessing undefined variable
@@ -948,9 +1100,7 @@ async fn test_task_then_timer_workflow() {
     // Complete the task
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     assert_eq!(tasks.len(), 1);
-    db::executions::complete_execution(pool.as_ref(), &tasks[0].0, json!(100))
-        .await
-        .unwrap();
+    complete_task(&pool, &tasks[0].0, json!(100)).await.unwrap();
 
     // Second run - resumes from task, 0ms timer fires immediately, workflow completes
     enqueue_and_claim_execution(&pool, &workflow_id, "default")
@@ -986,8 +1136,9 @@ async fn test_multiple_sequential_timers() {
         setup_workflow_test("multi_timer_workflow", workflow_source, json!({})).await;
     let workflow_id = execution.id.clone();
 
-    // Run through all 3 timers
-    for i in 0..3 {
+    // Run until done; 0ms timers may fire within the run that set them,
+    // so this takes at most one run per timer plus one
+    for i in 0..4 {
         if i > 0 {
             enqueue_and_claim_execution(&pool, &workflow_id, "default")
                 .await
@@ -998,20 +1149,12 @@ async fn test_multiple_sequential_timers() {
             .await
             .unwrap()
             .expect("Execution should exist");
+        if execution.status == ExecutionStatus::Completed {
+            break;
+        }
         run_workflow(&pool, execution).await.unwrap();
     }
 
-    // Fourth run - should complete
-    enqueue_and_claim_execution(&pool, &workflow_id, "default")
-        .await
-        .unwrap();
-
-    let execution = db::executions::get_execution(&pool, &workflow_id)
-        .await
-        .unwrap()
-        .expect("Execution should exist");
-    run_workflow(&pool, execution).await.unwrap();
-
     // Verify workflow completed
     let workflow_execution = db::executions::get_execution(&pool, &workflow_id)
         .await
@@ -1103,7 +1246,7 @@ async fn test_timer_captured_then_awaited_after_task() {
     // Complete the task
     let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
     assert_eq!(tasks.len(), 1);
-    db::executions::complete_execution(pool.as_ref(), &tasks[0].0, json!("task_done"))
+    complete_task(&pool, &tasks[0].0, json!("task_done"))
         .await
         .unwrap();
 
@@ -1162,7 +1305,7 @@ async fn test_parallel_timer_and_task() {
     assert_eq!(tasks.len(), 1, "Task should be created");
 
     // Complete the task
-    db::executions::complete_execution(pool.as_ref(), &tasks[0].0, json!("work_done"))
+    complete_task(&pool, &tasks[0].0, json!("work_done"))
         .await
         .unwrap();
 
@@ -1274,6 +1417,169 @@ async fn test_sub_workflow_basic() {
     assert_eq!(parent_execution.output, Some(json!(30.0)));
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_workflow_history_records_lifecycle() {
+    let workflow_source = r#"
+        let result = await Task.run("load", {})
+        return result
+    "#;
+
+    let (pool, execution) =
+        setup_workflow_test("history_workflow", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+    run_workflow(&pool, execution).await.unwrap();
+
+    let child_tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
+    let (task_id, _) = &child_tasks[0];
+    db::executions::start_execution_unless_finished(pool.as_ref(), task_id)
+        .await
+        .unwrap();
+    crate::worker::complete_work(&pool, task_id, Some(json!(7)), None)
+        .await
+        .unwrap();
+
+    enqueue_and_claim_execution(&pool, &workflow_id, "default")
+        .await
+        .unwrap();
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    run_workflow(&pool, execution).await.unwrap();
+
+    let history = db::execution_events::get_history(pool.as_ref(), &workflow_id)
+        .await
+        .unwrap();
+    let event_types: Vec<ExecutionEventType> = history.iter().map(|e| e.event_type).collect();
+    assert_eq!(
+        event_types,
+        vec![
+            ExecutionEventType::Created,
+            ExecutionEventType::Claimed,
+            ExecutionEventType::TaskScheduled,
+            ExecutionEventType::Suspended,
+            ExecutionEventType::TaskCompleted,
+            ExecutionEventType::Claimed,
+            ExecutionEventType::Resumed,
+            ExecutionEventType::Completed,
+        ]
+    );
+    assert_eq!(
+        history[2].details.as_ref().unwrap()["execution_id"],
+        json!(task_id)
+    );
+    assert_eq!(
+        history[4].details.as_ref().unwrap()["status"],
+        json!("completed")
+    );
+
+    let task_history = db::execution_events::get_history(pool.as_ref(), task_id)
+        .await
+        .unwrap();
+    let event_types: Vec<ExecutionEventType> = task_history.iter().map(|e| e.event_type).collect();
+    assert_eq!(
+        event_types,
+        vec![
+            ExecutionEventType::Created,
+            ExecutionEventType::Claimed,
+            ExecutionEventType::Completed,
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_continue_as_new_hands_over_to_fresh_execution() {
+    // Parent awaits a workflow that continues as new until its count reaches 2
+    let parent_source = r#"
+        return await Workflow.run("counting_workflow", {count: 0})
+    "#;
+    let counting_source = r#"
+        if (Inputs.count < 2) {
+            Workflow.continueAsNew({count: Inputs.count + 1})
+        }
+        return Inputs.count
+    "#;
+
+    let (pool, execution) = setup_workflow_test("continue_parent", parent_source, json!({})).await;
+    let parent_id = execution.id.clone();
+    db::workflow_definitions::create_workflow_definition(
+        &pool,
+        "counting_workflow",
+        "test-counting_workflow",
+        counting_source,
+    )
+    .await
+    .unwrap();
+
+    run_workflow(&pool, execution).await.unwrap();
+    let child_workflows = get_child_workflows(&pool, &parent_id).await.unwrap();
+    let mut current_id = child_workflows[0].0.clone();
+
+    // Each run completes its execution and starts the next one
+    for count in 0..2 {
+        enqueue_and_claim_execution(&pool, &current_id, "default")
+            .await
+            .unwrap();
+        let current = db::executions::get_execution(&pool, &current_id)
+            .await
+            .unwrap()
+            .unwrap();
+        run_workflow(&pool, current).await.unwrap();
+
+        let next_id = db::executions::get_continuation(pool.as_ref(), &current_id)
+            .await
+            .unwrap()
+            .expect("workflow should have continued as new");
+        let finished = db::executions::get_execution(&pool, &current_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(finished.status, ExecutionStatus::Completed);
+        assert_eq!(finished.output, Some(json!({"continuedAs": next_id})));
+
+        let next = db::executions::get_execution(&pool, &next_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.target_name, "counting_workflow");
+        assert_eq!(next.inputs, json!({"count": (count + 1) as f64}));
+        assert_eq!(next.parent_workflow_id, Some(parent_id.clone()));
+        assert_eq!(
+            db::executions::get_continued_from(pool.as_ref(), &next_id)
+                .await
+                .unwrap(),
+            Some(current_id.clone())
+        );
+        current_id = next_id;
+    }
+
+    // The last run returns; the parent gets its result through the chain
+    enqueue_and_claim_execution(&pool, &current_id, "default")
+        .await
+        .unwrap();
+    let last = db::executions::get_execution(&pool, &current_id)
+        .await
+        .unwrap()
+        .unwrap();
+    run_workflow(&pool, last).await.unwrap();
+
+    enqueue_and_claim_execution(&pool, &parent_id, "default")
+        .await
+        .unwrap();
+    let parent = db::executions::get_execution(&pool, &parent_id)
+        .await
+        .unwrap()
+        .unwrap();
+    run_workflow(&pool, parent).await.unwrap();
+
+    let parent = db::executions::get_execution(&pool, &parent_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(parent.status, ExecutionStatus::Completed);
+    assert_eq!(parent.output, Some(json!(2.0)));
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_sub_workflow_fire_and_forget() {
     // Parent workflow that spawns a child workflow without awaiting it
@@ -1440,7 +1746,7 @@ async fn test_mixed_tasks_and_workflows() {
     assert_eq!(children[0].2, "task");
 
     // Complete the task
-    db::executions::complete_execution(pool.as_ref(), &children[0].0, json!(5))
+    complete_task(&pool, &children[0].0, json!(5))
         .await
         .unwrap();
 
@@ -1689,3 +1995,537 @@ async fn test_sub_workflow_with_failed_child() {
     let output = parent_execution.output.unwrap();
     assert_eq!(output.get("code").unwrap(), "INTERNAL_ERROR");
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_panic_during_workflow_fails_only_that_execution() {
+    use super::super::runner::isolate_panics;
+    use super::super::WorkerCounters;
+
+    let (pool, execution) = setup_workflow_test("panicking_workflow", "return 1", json!({})).await;
+    let execution_id = execution.id.clone();
+    let counters = WorkerCounters::default();
+
+    let result = isolate_panics(&pool, &counters, &execution_id, async {
+        panic!("boom");
+    })
+    .await;
+    assert!(result.is_ok(), "Panic should not propagate: {:?}", result);
+
+    let execution = db::executions::get_execution(&pool, &execution_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    let output = execution.output.unwrap();
+    assert_eq!(output["type"], json!("WorkerPanic"));
+    assert!(output["message"].as_str().unwrap().contains("boom"));
+
+    // Claim released and metric recorded
+    let work_count = get_work_queue_count(&pool, &execution_id).await.unwrap();
+    assert_eq!(work_count, 0);
+    assert_eq!(counters.snapshot().workflow_panics, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transient_runner_error_schedules_retry() {
+    use super::super::runner::handle_runner_error;
+    use super::super::{RunnerRetryPolicy, WorkerCounters};
+
+    let (pool, execution) = setup_workflow_test("flaky_db_workflow", "return 1", json!({})).await;
+    let execution_id = execution.id.clone();
+    db::executions::start_execution_unless_finished(pool.as_ref(), &execution_id)
+        .await
+        .unwrap();
+    let counters = WorkerCounters::default();
+    let policy = RunnerRetryPolicy {
+        max_attempts: 1,
+        base_delay: std::time::Duration::from_secs(30),
+        max_delay: std::time::Duration::from_secs(60),
+    };
+
+    let error = anyhow::Error::from(sqlx::Error::PoolTimedOut);
+    handle_runner_error(&pool, &counters, &policy, &execution_id, "default", error)
+        .await
+        .unwrap();
+
+    let execution = db::executions::get_execution(&pool, &execution_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Suspended);
+    let runner_errors: i32 =
+        sqlx::query_scalar("SELECT runner_errors FROM executions WHERE id = $1")
+            .bind(&execution_id)
+            .fetch_one(pool.as_ref())
+            .await
+            .unwrap();
+    assert_eq!(runner_errors, 1);
+
+    // Claim released, continuation scheduled ~30s out
+    assert_eq!(get_work_queue_count(&pool, &execution_id).await.unwrap(), 0);
+    let in_future: bool = sqlx::query_scalar(
+        "SELECT run_at > NOW() AT TIME ZONE 'UTC' + INTERVAL '20 seconds' FROM scheduled_queue WHERE params->>'execution_id' = $1",
+    )
+    .bind(&execution_id)
+    .fetch_one(pool.as_ref())
+    .await
+    .unwrap();
+    assert!(in_future);
+    assert_eq!(counters.snapshot().runner_retries, 1);
+
+    // Running again and failing again uses up the single attempt
+    db::executions::start_execution_unless_finished(pool.as_ref(), &execution_id)
+        .await
+        .unwrap();
+    let error = anyhow::Error::from(sqlx::Error::PoolTimedOut);
+    handle_runner_error(&pool, &counters, &policy, &execution_id, "default", error)
+        .await
+        .unwrap();
+
+    let execution = db::executions::get_execution(&pool, &execution_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    assert_eq!(
+        execution.output.unwrap()["code"],
+        json!("RUNNER_RETRIES_EXHAUSTED")
+    );
+    assert_eq!(counters.snapshot().runner_failures, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_non_transient_runner_error_fails_execution() {
+    use super::super::runner::handle_runner_error;
+    use super::super::{RunnerRetryPolicy, WorkerCounters};
+
+    let (pool, execution) =
+        setup_workflow_test("broken_runner_workflow", "return 1", json!({})).await;
+    let execution_id = execution.id.clone();
+    db::executions::start_execution_unless_finished(pool.as_ref(), &execution_id)
+        .await
+        .unwrap();
+    let counters = WorkerCounters::default();
+
+    let error = anyhow::anyhow!("Failed to deserialize VM state");
+    handle_runner_error(
+        &pool,
+        &counters,
+        &RunnerRetryPolicy::default(),
+        &execution_id,
+        "default",
+        error,
+    )
+    .await
+    .unwrap();
+
+    let execution = db::executions::get_execution(&pool, &execution_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    let output = execution.output.unwrap();
+    assert_eq!(output["code"], json!("RUNNER_ERROR"));
+    assert!(output["message"].as_str().unwrap().contains("VM state"));
+    assert_eq!(get_work_queue_count(&pool, &execution_id).await.unwrap(), 0);
+    assert_eq!(counters.snapshot().runner_retries, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_external_task_completed_by_token() {
+    use super::super::complete_external_task;
+    use crate::types::ExecutionOutcome;
+
+    let workflow_source = r#"
+        let cb = ExternalTask.create("await-callback", {order: Inputs.order})
+        let reply = await cb.promise
+        return { token: cb.token, status: reply.status }
+    "#;
+
+    let (pool, execution) = setup_workflow_test(
+        "external_task_workflow",
+        workflow_source,
+        json!({"order": "A-7"}),
+    )
+    .await;
+    let workflow_id = execution.id.clone();
+
+    // First run - suspends on the external task
+    run_workflow(&pool, execution).await.unwrap();
+
+    let children = get_child_executions_with_type(&pool, &workflow_id)
+        .await
+        .unwrap();
+    assert_eq!(children.len(), 1);
+    let (token, target_name, exec_type) = &children[0];
+    assert_eq!(target_name, "await-callback");
+    assert_eq!(exec_type, "external");
+
+    // Nothing is queued for workers to pick up
+    assert_eq!(get_work_queue_count(&pool, token).await.unwrap(), 0);
+    let external = db::executions::get_execution(&pool, token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(external.status, ExecutionStatus::Pending);
+    assert_eq!(external.inputs, json!({"order": "A-7"}));
+
+    // Unknown tokens and non-external executions are rejected
+    let outcome = || ExecutionOutcome::Success(json!({"status": "approved"}));
+    assert!(complete_external_task(&pool, "no-such-token", outcome())
+        .await
+        .is_err());
+    assert!(complete_external_task(&pool, &workflow_id, outcome())
+        .await
+        .is_err());
+
+    // Completing by token re-queues the parent
+    complete_external_task(&pool, token, outcome())
+        .await
+        .unwrap();
+    assert_eq!(
+        get_unclaimed_work_count(&pool, &workflow_id).await.unwrap(),
+        1
+    );
+
+    // A replayed callback can't overwrite the first result
+    let err = complete_external_task(&pool, token, outcome())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("already finished"));
+
+    // Resume - completes with the external result
+    enqueue_and_claim_execution(&pool, &workflow_id, "default")
+        .await
+        .unwrap();
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    run_workflow(&pool, execution).await.unwrap();
+
+    let workflow_execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(workflow_execution.status, ExecutionStatus::Completed);
+    assert_eq!(
+        workflow_execution.output,
+        Some(json!({"token": token, "status": "approved"}))
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_workflow_yields_worker_after_step_budget() {
+    let workflow_source = r#"
+        let total = 0
+        for (let n of [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]) {
+            total = total + n
+        }
+        return total
+    "#;
+
+    let (pool, execution) =
+        setup_workflow_test("hot_loop_workflow", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+    let execution = db::executions::start_execution_unless_finished(&*pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let options = RunnerOptions {
+        step_budget: StepBudgetConfig {
+            max_steps: 20,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(
+        super::super::runner::run_workflow_with_step_budget(&pool, execution, &options)
+            .await
+            .unwrap()
+    );
+
+    // Out of budget: state saved, claim released, continuation queued
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Running);
+    assert!(
+        db::workflow_execution_context::get_context(&pool, &workflow_id)
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(get_work_queue_count(&pool, &workflow_id).await.unwrap(), 1);
+    assert_eq!(
+        get_unclaimed_work_count(&pool, &workflow_id).await.unwrap(),
+        1
+    );
+
+    // Each further claim continues from the saved state until it finishes
+    let mut claims = 1;
+    loop {
+        db::work_queue::claim_specific_execution(&pool, &workflow_id)
+            .await
+            .unwrap();
+        let execution = db::executions::start_execution_unless_finished(&*pool, &workflow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        if execution.status == ExecutionStatus::Completed {
+            assert_eq!(execution.output, Some(json!(55.0)));
+            break;
+        }
+        super::super::runner::run_workflow_with_step_budget(&pool, execution, &options)
+            .await
+            .unwrap();
+        claims += 1;
+        assert!(claims < 100, "Workflow never finished");
+    }
+
+    assert!(claims > 2);
+    assert_eq!(get_work_queue_count(&pool, &workflow_id).await.unwrap(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_workflow_fails_after_step_budget() {
+    use super::super::{run_workflow_isolated, WorkerCounters};
+
+    // Catching the budget error must not let the loop carry on
+    let workflow_source = r#"
+        try {
+            while (true) {}
+        } catch (e) {
+            return "caught"
+        }
+    "#;
+
+    let (pool, execution) = setup_workflow_test("spin_workflow", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+    let execution = db::executions::start_execution_unless_finished(&*pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let counters = WorkerCounters::default();
+    let options = RunnerOptions {
+        step_budget: StepBudgetConfig {
+            max_steps: 50,
+            on_exceeded: StepBudgetAction::Fail,
+        },
+        ..Default::default()
+    };
+
+    run_workflow_isolated(&pool, &counters, &options, execution)
+        .await
+        .unwrap();
+
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    assert_eq!(
+        execution.output.unwrap()["code"],
+        json!("LOOP_BUDGET_EXCEEDED")
+    );
+    assert!(
+        db::workflow_execution_context::get_context(&pool, &workflow_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(get_work_queue_count(&pool, &workflow_id).await.unwrap(), 0);
+    assert_eq!(counters.snapshot().step_budget_failures, 1);
+    assert_eq!(counters.snapshot().step_budget_yields, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_workflow_fails_when_state_is_too_large() {
+    use crate::blob_store::PayloadStore;
+    use crate::config::PayloadsConfig;
+
+    let workflow_source = r#"
+        let rows = ""
+        for (let n of [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]) {
+            rows = rows + "a row of the export that is kept in a local\n"
+        }
+        await Task.run("upload", { count: 10 })
+        return rows
+    "#;
+
+    let (pool, execution) =
+        setup_workflow_test("hoarding_workflow", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+    let execution = db::executions::start_execution_unless_finished(&*pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let options = RunnerOptions {
+        payloads: PayloadStore::from_config(&PayloadsConfig {
+            max_state_bytes: Some(256),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    super::super::runner::run_workflow_with_step_budget(&pool, execution, &options)
+        .await
+        .unwrap();
+
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Failed);
+    let error = execution.output.unwrap();
+    assert_eq!(error["code"], json!("STATE_TOO_LARGE"));
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("over the 256 byte limit"));
+    assert!(
+        db::workflow_execution_context::get_context(&pool, &workflow_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_workflow_resumes_from_binary_state() {
+    use crate::blob_store::PayloadStore;
+    use crate::config::PayloadsConfig;
+    use crate::db::workflow_execution_context::StoredState;
+    use crate::payload::PayloadEncoding;
+
+    let workflow_source = r#"
+        let base = Inputs.base
+        let charged = await Task.run("charge", { amount: base })
+        return base + charged
+    "#;
+
+    let (pool, execution) = setup_workflow_test(
+        "binary_state_workflow",
+        workflow_source,
+        json!({ "base": 5 }),
+    )
+    .await;
+    let workflow_id = execution.id.clone();
+    let options = RunnerOptions {
+        payloads: PayloadStore::from_config(&PayloadsConfig {
+            state_encoding: PayloadEncoding::MsgPack,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let execution = db::executions::start_execution_unless_finished(&*pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    super::super::runner::run_workflow_with_step_budget(&pool, execution, &options)
+        .await
+        .unwrap();
+
+    let context = db::workflow_execution_context::get_context(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(context.vm_state, StoredState::Binary(_)));
+    let (locals,): (Option<serde_json::Value>,) =
+        sqlx::query_as("SELECT locals FROM workflow_execution_context WHERE execution_id = $1")
+            .bind(&workflow_id)
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+    assert_eq!(locals, None);
+
+    let (task_id, _) = &get_child_tasks(&pool, &workflow_id).await.unwrap()[0];
+    complete_task(&pool, task_id, json!(7)).await.unwrap();
+    enqueue_and_claim_execution(&pool, &workflow_id, "default")
+        .await
+        .unwrap();
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    // A worker still writing JSON reads the binary state
+    run_workflow(&pool, execution).await.unwrap();
+
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+    assert_eq!(execution.output, Some(json!(12.0)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_clock_reads_leave_saved_state_for_history() {
+    // Reads the clock on every turn of a loop that suspends
+    let workflow_source = r#"
+        let last = 0
+        let i = 0
+        while (i < 4) {
+            last = Date.now()
+            await Task.run("tick", { i: i })
+            i = i + 1
+        }
+        return last
+    "#;
+
+    let (pool, execution) =
+        setup_workflow_test("polling_workflow", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+    run_workflow(&pool, execution).await.unwrap();
+
+    let mut sizes = Vec::new();
+    for _ in 0..4 {
+        let context = db::workflow_execution_context::get_context(&pool, &workflow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let state = context.vm_state.as_json().unwrap();
+        assert!(state.get("recorded").is_none());
+        sizes.push(state.to_string().len());
+
+        let (task_id, _) = get_child_tasks(&pool, &workflow_id)
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        complete_task(&pool, &task_id, json!(null)).await.unwrap();
+        enqueue_and_claim_execution(&pool, &workflow_id, "default")
+            .await
+            .unwrap();
+        let execution = db::executions::get_execution(&pool, &workflow_id)
+            .await
+            .unwrap()
+            .unwrap();
+        run_workflow(&pool, execution).await.unwrap();
+    }
+    assert!(
+        sizes.windows(2).all(|pair| pair[0] == pair[1]),
+        "{:?}",
+        sizes
+    );
+
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+
+    // Each run's read is in the history instead, in order
+    let reads: Vec<serde_json::Value> =
+        db::execution_events::get_history(pool.as_ref(), &workflow_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|event| event.event_type == ExecutionEventType::Read)
+            .flat_map(|event| event.details.unwrap()["values"].as_array().unwrap().clone())
+            .collect();
+    assert_eq!(reads.len(), 4);
+    assert_eq!(execution.output, Some(reads[3]["v"].clone()));
+}
@@ -10,7 +10,7 @@ use serde_json::json;
 
 use super::super::run_workflow;
 use crate::db;
-use crate::test_helpers::{enqueue_and_claim_execution, setup_workflow_test};
+use crate::test_helpers::{complete_task, enqueue_and_claim_execution, setup_workflow_test};
 use crate::types::ExecutionStatus;
 
 /* ===================== Basic Signal Flow Tests ===================== */
@@ -373,7 +373,7 @@ async fn test_signal_after_task() {
             .unwrap();
     assert_eq!(tasks.len(), 1);
 
-    db::executions::complete_execution(pool.as_ref(), &tasks[0].0, json!("task_done"))
+    complete_task(&pool, &tasks[0].0, json!("task_done"))
         .await
         .unwrap();
 
@@ -0,0 +1,174 @@
+//! Tests for sticky workflow runs
+//!
+//! A workflow saved by a sticky worker is left to that worker when it is
+//! queued again, and resumes from the VM it kept in memory.
+
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use super::super::{
+    complete_work, run_cooperative_worker_loop, AllowAllPolicy, ClaimAuthorizer, DelegatedAction,
+    MiddlewareChain, RunnerOptions, StickyOptions, WorkerCounters, WorkerIdentity,
+};
+use crate::config::StickyConfig;
+use crate::db;
+use crate::queue_backend::PostgresQueue;
+use crate::types::{CreateExecutionParams, ExecutionStatus, ExecutionType};
+
+fn authorizer(worker_id: &str) -> ClaimAuthorizer {
+    let identity = WorkerIdentity {
+        id: worker_id.to_string(),
+        labels: HashMap::new(),
+    };
+    ClaimAuthorizer::new(identity, Arc::new(AllowAllPolicy))
+}
+
+async fn run_once(
+    pool: &PgPool,
+    authorizer: &ClaimAuthorizer,
+    options: &RunnerOptions,
+) -> DelegatedAction {
+    run_cooperative_worker_loop(
+        pool,
+        &PostgresQueue::new(pool.clone()),
+        "default",
+        &CancellationToken::new(),
+        authorizer,
+        &MiddlewareChain::default(),
+        &WorkerCounters::default(),
+        options,
+        &[],
+    )
+    .await
+    .unwrap()
+}
+
+/// Start a workflow on sticky worker "a" and finish the task it awaits
+///
+/// Returns the workflow's id, queued again to resume.
+async fn suspend_on_a(pool: &PgPool, options: &RunnerOptions) -> String {
+    db::workflow_definitions::create_workflow_definition(
+        pool,
+        "sticky",
+        "test-sticky",
+        r#"
+            let receipt = await Task.run("charge", {})
+            return receipt
+        "#,
+    )
+    .await
+    .unwrap();
+    let mut tx = pool.begin().await.unwrap();
+    let workflow_id = db::executions::create_execution(
+        &mut tx,
+        CreateExecutionParams {
+            id: None,
+            exec_type: ExecutionType::Workflow,
+            target_name: "sticky".to_string(),
+            queue: "default".to_string(),
+            inputs: json!({}),
+            parent_workflow_id: None,
+            namespace: None,
+            ttl_seconds: None,
+            retry_policy: None,
+            priority: None,
+            idempotency_key: None,
+            traceparent: None,
+            tags: HashMap::new(),
+        },
+    )
+    .await
+    .unwrap();
+    db::work_queue::enqueue_work(&mut *tx, &workflow_id, "default", 0)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let a = authorizer("a");
+    assert!(matches!(
+        run_once(pool, &a, options).await,
+        DelegatedAction::Continue
+    ));
+    let task_id = match run_once(pool, &a, options).await {
+        DelegatedAction::ExecuteTask { execution_id, .. } => execution_id,
+        other => panic!("Expected the task, got {:?}", other),
+    };
+    complete_work(pool, &task_id, Some(json!("receipt")), None)
+        .await
+        .unwrap();
+    workflow_id
+}
+
+#[sqlx::test]
+async fn test_resumed_workflow_waits_for_its_worker(pool: PgPool) {
+    let options = RunnerOptions {
+        sticky: Some(StickyOptions::new(
+            "a".to_string(),
+            &StickyConfig {
+                enabled: true,
+                timeout_ms: 60_000,
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    let cache = options.sticky.as_ref().unwrap().cache.clone();
+    let workflow_id = suspend_on_a(&pool, &options).await;
+    assert_eq!(cache.len(), 1);
+
+    // Another worker leaves it alone
+    assert!(matches!(
+        run_once(&pool, &authorizer("b"), &RunnerOptions::default()).await,
+        DelegatedAction::Wait { .. }
+    ));
+
+    // Its own worker resumes it from the cached VM
+    assert!(matches!(
+        run_once(&pool, &authorizer("a"), &options).await,
+        DelegatedAction::Continue
+    ));
+    assert!(cache.is_empty());
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+    assert_eq!(execution.output, Some(json!("receipt")));
+}
+
+#[sqlx::test]
+async fn test_other_workers_take_over_after_the_timeout(pool: PgPool) {
+    let options = RunnerOptions {
+        sticky: Some(StickyOptions::new(
+            "a".to_string(),
+            &StickyConfig {
+                enabled: true,
+                timeout_ms: 0,
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    let cache = options.sticky.as_ref().unwrap().cache.clone();
+    let workflow_id = suspend_on_a(&pool, &options).await;
+
+    assert!(matches!(
+        run_once(&pool, &authorizer("b"), &RunnerOptions::default()).await,
+        DelegatedAction::Continue
+    ));
+    let execution = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(execution.status, ExecutionStatus::Completed);
+
+    // The VM "a" kept is stale now; it is never used, only evicted
+    let version = db::workflow_execution_context::get_context_version(&pool, &workflow_id)
+        .await
+        .unwrap();
+    assert_eq!(version, None);
+    assert_eq!(cache.len(), 1);
+}
@@ -0,0 +1,154 @@
+//! Integration tests for streaming task results into workflows
+//!
+//! These tests verify that Task.stream() hands a workflow the chunks a task
+//! emitted, across as many resumes as it takes, and ends when the task does.
+
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::super::run_workflow;
+use crate::db;
+use crate::test_helpers::{
+    complete_task, enqueue_and_claim_execution, fail_task, get_child_tasks, setup_workflow_test,
+};
+use crate::types::ExecutionStatus;
+
+const STREAMING_WORKFLOW: &str = r#"
+    let task = Task.run("export_rows", {})
+    let rows = []
+    let next = 0
+    let done = false
+    while (!done) {
+        let batch = await Task.stream(task, next)
+        rows = rows.concat(batch.chunks)
+        next = batch.next
+        done = batch.done
+    }
+    return rows
+"#;
+
+/// Emit chunks from a task the way a host would mid-run
+async fn emit(pool: &PgPool, task_id: &str, chunks: &[serde_json::Value]) {
+    db::executions::start_execution_unless_finished(pool, task_id)
+        .await
+        .unwrap();
+    let mut tx = pool.begin().await.unwrap();
+    for chunk in chunks {
+        db::task_chunks::append_chunk(&mut tx, task_id, chunk)
+            .await
+            .unwrap();
+    }
+    tx.commit().await.unwrap();
+}
+
+async fn resume(pool: &PgPool, workflow_id: &str) -> ExecutionStatus {
+    enqueue_and_claim_execution(pool, workflow_id, "default")
+        .await
+        .unwrap();
+    let execution = db::executions::get_execution(pool, workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    run_workflow(pool, execution).await.unwrap();
+    db::executions::get_execution(pool, workflow_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stream_consumed_progressively() {
+    let (pool, execution) =
+        setup_workflow_test("streaming_workflow", STREAMING_WORKFLOW, json!({})).await;
+    let workflow_id = execution.id.clone();
+
+    run_workflow(&pool, execution).await.unwrap();
+    let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
+    let task_id = tasks[0].0.clone();
+
+    // Nothing emitted yet: still waiting
+    assert_eq!(
+        resume(&pool, &workflow_id).await,
+        ExecutionStatus::Suspended
+    );
+
+    emit(&pool, &task_id, &[json!(1), json!(2)]).await;
+    assert_eq!(
+        resume(&pool, &workflow_id).await,
+        ExecutionStatus::Suspended
+    );
+
+    emit(&pool, &task_id, &[json!(3)]).await;
+    complete_task(&pool, &task_id, json!(null)).await.unwrap();
+    assert_eq!(
+        resume(&pool, &workflow_id).await,
+        ExecutionStatus::Completed
+    );
+
+    let workflow = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(workflow.output, Some(json!([1.0, 2.0, 3.0])));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stream_read_after_completion() {
+    let (pool, execution) =
+        setup_workflow_test("streaming_workflow", STREAMING_WORKFLOW, json!({})).await;
+    let workflow_id = execution.id.clone();
+
+    run_workflow(&pool, execution).await.unwrap();
+    let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
+    let task_id = tasks[0].0.clone();
+
+    emit(&pool, &task_id, &[json!("a"), json!("b")]).await;
+    complete_task(&pool, &task_id, json!(null)).await.unwrap();
+
+    // One resume reads everything
+    assert_eq!(
+        resume(&pool, &workflow_id).await,
+        ExecutionStatus::Completed
+    );
+    let workflow = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(workflow.output, Some(json!(["a", "b"])));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stream_ends_with_task_failure_after_chunks() {
+    let workflow_source = r#"
+        let task = Task.run("export_rows", {})
+        let first = await Task.stream(task)
+        let rest = await Task.stream(task, first.next)
+        return { chunks: first.chunks, error: rest.message }
+    "#;
+    let (pool, execution) =
+        setup_workflow_test("streaming_workflow", workflow_source, json!({})).await;
+    let workflow_id = execution.id.clone();
+
+    run_workflow(&pool, execution).await.unwrap();
+    let tasks = get_child_tasks(&pool, &workflow_id).await.unwrap();
+    let task_id = tasks[0].0.clone();
+
+    emit(&pool, &task_id, &[json!("partial")]).await;
+    fail_task(&pool, &task_id, json!({"message": "disk full"}))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        resume(&pool, &workflow_id).await,
+        ExecutionStatus::Completed
+    );
+    let workflow = db::executions::get_execution(&pool, &workflow_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        workflow.output,
+        Some(json!({"chunks": ["partial"], "error": "disk full"}))
+    );
+}
@@ -0,0 +1,537 @@
+//! Debug session for one workflow
+//!
+//! Runs the workflow in `launch.program` on the core `Debugger`, in memory
+//! and with a single thread. Awaits are settled from the launch
+//! configuration where it can: tasks from `tasks` (name to result), signals
+//! from `signals` (name to payloads, in order), and timers at once.
+//! Anything else stops the program until its result is given by evaluating
+//! `resume <json>` in the debug console.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::protocol::Request;
+use rhythm_core::executor::{
+    json_to_val, json_to_val_map, Awaitable, Control, Debugger, Stop, Val, WorkflowContext, VM,
+};
+
+/// The only thread a workflow has
+const THREAD_ID: i64 = 1;
+
+/// Variable references of the two scopes; values found in them get the
+/// references after these
+const LOCALS_REF: i64 = 1;
+const GLOBALS_REF: i64 = 2;
+
+/// `launch` request arguments
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchArgs {
+    program: PathBuf,
+    #[serde(default)]
+    inputs: Option<JsonValue>,
+    #[serde(default)]
+    tasks: HashMap<String, JsonValue>,
+    #[serde(default)]
+    signals: HashMap<String, VecDeque<JsonValue>>,
+    #[serde(default)]
+    stop_on_entry: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Entry,
+    Step,
+    Continue,
+}
+
+/// Handles requests, producing the responses and events to send back
+#[derive(Default)]
+pub struct Adapter {
+    seq: i64,
+    launch: Option<LaunchArgs>,
+    debugger: Option<Debugger>,
+    source: Vec<String>,
+    breakpoints: Vec<usize>,
+    configured: bool,
+    started: bool,
+    /// Values whose members can be expanded, by reference minus the scopes
+    handles: Vec<Val>,
+    /// Set once the client disconnects
+    pub done: bool,
+}
+
+impl Adapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle one request
+    pub fn handle(&mut self, request: Request) -> Vec<JsonValue> {
+        let mut events = Vec::new();
+        let body = self.dispatch(&request, &mut events);
+        let mut messages = vec![match body {
+            Ok(body) => self.message(json!({
+                "type": "response",
+                "request_seq": request.seq,
+                "command": request.command,
+                "success": true,
+                "body": body,
+            })),
+            Err(e) => self.message(json!({
+                "type": "response",
+                "request_seq": request.seq,
+                "command": request.command,
+                "success": false,
+                "message": format!("{:#}", e),
+            })),
+        }];
+        for (event, body) in events {
+            messages.push(self.message(json!({ "type": "event", "event": event, "body": body })));
+        }
+        messages
+    }
+
+    fn message(&mut self, mut message: JsonValue) -> JsonValue {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        message
+    }
+
+    fn dispatch(
+        &mut self,
+        request: &Request,
+        events: &mut Vec<(&'static str, JsonValue)>,
+    ) -> Result<JsonValue> {
+        let args = &request.arguments;
+        match request.command.as_str() {
+            "initialize" => {
+                events.push(("initialized", json!({})));
+                Ok(json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                    "supportsTerminateRequest": true,
+                }))
+            }
+            "launch" => {
+                self.launch(args)?;
+                self.start(events);
+                Ok(json!({}))
+            }
+            "setBreakpoints" => self.set_breakpoints(args),
+            "configurationDone" => {
+                self.configured = true;
+                self.start(events);
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "workflow" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Locals", "variablesReference": LOCALS_REF, "expensive": false },
+                    { "name": "Globals", "variablesReference": GLOBALS_REF, "expensive": false },
+                ]
+            })),
+            "variables" => self.variables(args),
+            "continue" => {
+                self.advance(Mode::Continue, events)?;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                self.advance(Mode::Step, events)?;
+                Ok(json!({}))
+            }
+            // Runs only between stops, so there is never anything to pause
+            "pause" => Ok(json!({})),
+            "evaluate" => self.evaluate(args, events),
+            "disconnect" | "terminate" => {
+                self.done = true;
+                Ok(json!({}))
+            }
+            command => bail!("Unsupported request: {}", command),
+        }
+    }
+
+    fn launch(&mut self, args: &JsonValue) -> Result<()> {
+        let launch: LaunchArgs =
+            serde_json::from_value(args.clone()).context("Invalid launch configuration")?;
+        let source = std::fs::read_to_string(&launch.program)
+            .with_context(|| format!("Failed to read {}", launch.program.display()))?;
+        let workflow = rhythm_core::parser::parse_workflow(&source)
+            .map_err(|e| anyhow!("{}: {}", launch.program.display(), e))?;
+        rhythm_core::parser::semantic_validator::validate_workflow(&workflow)
+            .map_err(|e| anyhow!("{}: {}", launch.program.display(), e))?;
+        let inputs = launch.inputs.clone().unwrap_or_else(|| json!({}));
+
+        let mut debugger = Debugger::new(VM::new(
+            workflow.body,
+            json_to_val_map(&inputs)?,
+            WorkflowContext {
+                execution_id: "debug".to_string(),
+            },
+        ));
+        for line in &self.breakpoints {
+            debugger.set_breakpoint(*line);
+        }
+        self.source = source.lines().map(str::to_string).collect();
+        self.debugger = Some(debugger);
+        self.launch = Some(launch);
+        Ok(())
+    }
+
+    /// Run to the first stop once launched and configured, in either order
+    fn start(&mut self, events: &mut Vec<(&'static str, JsonValue)>) {
+        if self.started || !self.configured || self.debugger.is_none() {
+            return;
+        }
+        self.started = true;
+        let stop_on_entry = self.launch.as_ref().is_some_and(|l| l.stop_on_entry);
+        let mode = if stop_on_entry {
+            Mode::Entry
+        } else {
+            Mode::Continue
+        };
+        if let Err(e) = self.advance(mode, events) {
+            events.push(("output", output(&format!("{:#}", e))));
+        }
+    }
+
+    fn set_breakpoints(&mut self, args: &JsonValue) -> Result<JsonValue> {
+        let lines: Vec<usize> = args["breakpoints"]
+            .as_array()
+            .map(|breakpoints| {
+                breakpoints
+                    .iter()
+                    .filter_map(|b| b["line"].as_u64())
+                    .map(|line| line as usize)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if let Some(debugger) = &mut self.debugger {
+            for line in self.breakpoints.drain(..) {
+                debugger.clear_breakpoint(line);
+            }
+            for line in &lines {
+                debugger.set_breakpoint(*line);
+            }
+        }
+        self.breakpoints = lines.clone();
+        let breakpoints: Vec<JsonValue> = lines
+            .iter()
+            .map(|line| json!({ "verified": true, "line": line }))
+            .collect();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Run, settling the awaits the launch configuration answers, until the
+    /// program stops somewhere the client should see
+    fn advance(&mut self, mode: Mode, events: &mut Vec<(&'static str, JsonValue)>) -> Result<()> {
+        self.handles.clear();
+        loop {
+            let debugger = self.debugger.as_mut().context("No program is running")?;
+            let stop = match mode {
+                Mode::Entry | Mode::Step => debugger.step(),
+                Mode::Continue => debugger.continue_running(),
+            };
+            let reason = match stop {
+                Stop::Statement { .. } if matches!(mode, Mode::Entry) => "entry",
+                Stop::Statement { .. } => "step",
+                Stop::Breakpoint { .. } => "breakpoint",
+                Stop::Suspended(awaitable) => {
+                    if let Some(value) = self.settle(&awaitable) {
+                        self.debugger.as_mut().unwrap().resume(value);
+                        continue;
+                    }
+                    let awaiting = self.describe(&awaitable);
+                    events.push((
+                        "output",
+                        output(&format!(
+                            "Awaiting {}; evaluate `resume <json>` to give its result",
+                            awaiting
+                        )),
+                    ));
+                    events.push((
+                        "stopped",
+                        json!({
+                            "reason": "pause",
+                            "description": format!("Awaiting {}", awaiting),
+                            "threadId": THREAD_ID,
+                        }),
+                    ));
+                    return Ok(());
+                }
+                Stop::Finished => {
+                    let message = match self.debugger.as_ref().unwrap().control() {
+                        Control::Return(val) => format!("Returned {}", display(val)),
+                        Control::Throw(val) => format!("Threw {}", display(val)),
+                        _ => "Finished".to_string(),
+                    };
+                    events.push(("output", output(&message)));
+                    events.push(("terminated", json!({})));
+                    return Ok(());
+                }
+            };
+            events.push((
+                "stopped",
+                json!({ "reason": reason, "threadId": THREAD_ID }),
+            ));
+            return Ok(());
+        }
+    }
+
+    /// The result of an await, if the launch configuration gives one
+    fn settle(&mut self, awaitable: &Awaitable) -> Option<Val> {
+        let launch = self.launch.as_mut()?;
+        match awaitable {
+            Awaitable::Execution(id) => {
+                let name = task_name(self.debugger.as_ref()?, id)?;
+                json_to_val(launch.tasks.get(&name)?).ok()
+            }
+            Awaitable::Timer { .. } => Some(Val::Null),
+            Awaitable::Signal { name, .. } => {
+                json_to_val(&launch.signals.get_mut(name)?.pop_front()?).ok()
+            }
+            Awaitable::Stream { execution_id, from } => {
+                self.settle(&Awaitable::Execution(execution_id.clone()))?;
+                Some(Val::Obj(HashMap::from([
+                    ("chunks".to_string(), Val::List(vec![])),
+                    ("next".to_string(), Val::Num(*from as f64)),
+                    ("done".to_string(), Val::Bool(true)),
+                ])))
+            }
+            Awaitable::All { items, is_object } => {
+                let mut values = Vec::new();
+                for (key, item) in items {
+                    values.push((key.clone(), self.settle(item)?));
+                }
+                Some(if *is_object {
+                    Val::Obj(values.into_iter().collect())
+                } else {
+                    Val::List(values.into_iter().map(|(_, val)| val).collect())
+                })
+            }
+            Awaitable::Any {
+                items,
+                is_object,
+                with_kv,
+            }
+            | Awaitable::Race {
+                items,
+                is_object,
+                with_kv,
+            } => items.iter().find_map(|(key, item)| {
+                let value = self.settle(item)?;
+                if !*with_kv {
+                    return Some(value);
+                }
+                let key = match key.parse::<f64>() {
+                    Ok(index) if !*is_object => Val::Num(index),
+                    _ => Val::Str(key.clone()),
+                };
+                Some(Val::Obj(HashMap::from([
+                    ("key".to_string(), key),
+                    ("value".to_string(), value),
+                ])))
+            }),
+        }
+    }
+
+    fn describe(&self, awaitable: &Awaitable) -> String {
+        match awaitable {
+            Awaitable::Execution(id)
+            | Awaitable::Stream {
+                execution_id: id, ..
+            } => self
+                .debugger
+                .as_ref()
+                .and_then(|debugger| task_name(debugger, id))
+                .map_or_else(|| id.clone(), |name| format!("task '{}'", name)),
+            Awaitable::Signal { name, .. } => format!("signal '{}'", name),
+            Awaitable::Timer { .. } => "a timer".to_string(),
+            Awaitable::All { items, .. }
+            | Awaitable::Any { items, .. }
+            | Awaitable::Race { items, .. } => {
+                let items: Vec<String> = items.iter().map(|(_, i)| self.describe(i)).collect();
+                items.join(", ")
+            }
+        }
+    }
+
+    fn stack_trace(&self) -> Result<JsonValue> {
+        let debugger = self.debugger.as_ref().context("No program is running")?;
+        let path = self
+            .launch
+            .as_ref()
+            .map(|l| l.program.display().to_string());
+        let name = self.launch.as_ref().and_then(|l| {
+            l.program
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        });
+        let frames: Vec<JsonValue> = debugger
+            .frames()
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, frame)| frame.kind != "Block")
+            .map(|(id, frame)| {
+                let text = self
+                    .source
+                    .get(frame.line - 1)
+                    .map_or(frame.kind, |text| text.trim());
+                json!({
+                    "id": id,
+                    "name": text,
+                    "line": frame.line,
+                    "column": 1,
+                    "source": { "name": name, "path": path },
+                })
+            })
+            .collect();
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    fn variables(&mut self, args: &JsonValue) -> Result<JsonValue> {
+        let reference = args["variablesReference"].as_i64().unwrap_or_default();
+        let debugger = self.debugger.as_ref().context("No program is running")?;
+        let members: Vec<(String, Val)> = match reference {
+            LOCALS_REF => debugger
+                .locals()
+                .into_iter()
+                .map(|(name, val)| (name.to_string(), val.clone()))
+                .collect(),
+            GLOBALS_REF => ["Inputs", "Context"]
+                .iter()
+                .filter_map(|name| Some((name.to_string(), debugger.global(name)?.clone())))
+                .collect(),
+            _ => {
+                let index = usize::try_from(reference - GLOBALS_REF - 1)
+                    .ok()
+                    .context("Unknown variables reference")?;
+                members(
+                    self.handles
+                        .get(index)
+                        .context("Unknown variables reference")?,
+                )
+            }
+        };
+
+        let variables: Vec<JsonValue> = members
+            .into_iter()
+            .map(|(name, val)| {
+                json!({
+                    "name": name,
+                    "value": display(&val),
+                    "variablesReference": self.reference(val),
+                })
+            })
+            .collect();
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// A reference to expand `val` by, or 0 if it has no members
+    fn reference(&mut self, val: Val) -> i64 {
+        if members(&val).is_empty() {
+            return 0;
+        }
+        self.handles.push(val);
+        self.handles.len() as i64 + GLOBALS_REF
+    }
+
+    /// `resume <json>` settles the current await; anything else is a
+    /// variable path like `order.items.0`
+    fn evaluate(
+        &mut self,
+        args: &JsonValue,
+        events: &mut Vec<(&'static str, JsonValue)>,
+    ) -> Result<JsonValue> {
+        let expression = args["expression"].as_str().unwrap_or_default().trim();
+        if let Some(result) = expression.strip_prefix("resume") {
+            let result = result.trim();
+            let value: JsonValue = if result.is_empty() {
+                JsonValue::Null
+            } else {
+                serde_json::from_str(result).context("The result is not valid JSON")?
+            };
+            let debugger = self.debugger.as_mut().context("No program is running")?;
+            if !debugger.resume(json_to_val(&value)?) {
+                bail!("The program is not stopped on an await");
+            }
+            self.advance(Mode::Step, events)?;
+            return Ok(json!({ "result": "resumed", "variablesReference": 0 }));
+        }
+
+        let debugger = self.debugger.as_ref().context("No program is running")?;
+        let mut path = expression.split('.');
+        let root = path.next().unwrap_or_default();
+        let mut val = debugger
+            .locals()
+            .get(root)
+            .copied()
+            .or_else(|| debugger.global(root))
+            .with_context(|| format!("{} is not defined", root))?
+            .clone();
+        for segment in path {
+            val = members(&val)
+                .into_iter()
+                .find(|(name, _)| name == segment)
+                .map(|(_, member)| member)
+                .with_context(|| format!("{} has no member {}", expression, segment))?;
+        }
+        Ok(json!({ "result": display(&val), "variablesReference": self.reference(val) }))
+    }
+}
+
+fn task_name(debugger: &Debugger, id: &str) -> Option<String> {
+    debugger
+        .vm()
+        .outbox
+        .executions
+        .iter()
+        .find(|creation| creation.id == id)
+        .map(|creation| creation.target_name.clone())
+}
+
+fn output(text: &str) -> JsonValue {
+    json!({ "category": "console", "output": format!("{}\n", text) })
+}
+
+/// Members of an object or list, keys sorted
+fn members(val: &Val) -> Vec<(String, Val)> {
+    match val {
+        Val::Obj(map) => {
+            let mut members: Vec<(String, Val)> =
+                map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+            members
+        }
+        Val::List(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v.clone()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// A value as the Variables view shows it
+fn display(val: &Val) -> String {
+    match val {
+        Val::Null => "null".to_string(),
+        Val::Bool(b) => b.to_string(),
+        Val::Num(n) if n.fract() == 0.0 && n.abs() < 1e15 => format!("{}", *n as i64),
+        Val::Num(n) => n.to_string(),
+        Val::Str(s) => JsonValue::String(s.clone()).to_string(),
+        Val::List(items) => format!("Array({})", items.len()),
+        Val::Obj(map) if map.is_empty() => "{}".to_string(),
+        Val::Obj(_) => "{…}".to_string(),
+        Val::Promise(_) => "Promise".to_string(),
+        Val::Error(error) => format!("{}: {}", error.code, error.message),
+        Val::Func { .. } => "function".to_string(),
+    }
+}
@@ -0,0 +1,45 @@
+//! Rhythm Debug Adapter
+//!
+//! A Debug Adapter Protocol implementation for Rhythm workflows, so editors
+//! can set breakpoints in `.flow` files, step through them and inspect
+//! variables.
+//!
+//! # Usage
+//!
+//! ```bash
+//! rhythm-dap
+//! ```
+//!
+//! The adapter communicates over stdin/stdout using the DAP protocol and
+//! serves one debug session.
+
+use std::io::{self, BufReader};
+
+mod adapter;
+mod protocol;
+
+#[cfg(test)]
+mod tests;
+
+use adapter::Adapter;
+use protocol::{read_message, write_message, Request};
+
+fn main() -> io::Result<()> {
+    let mut reader = BufReader::new(io::stdin().lock());
+    let mut writer = io::stdout().lock();
+    let mut adapter = Adapter::new();
+
+    while let Some(message) = read_message(&mut reader)? {
+        // Responses and events from the client need no answer
+        let Ok(request) = serde_json::from_value::<Request>(message) else {
+            continue;
+        };
+        for message in adapter.handle(request) {
+            write_message(&mut writer, &message)?;
+        }
+        if adapter.done {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use std::path::PathBuf;
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::adapter::Adapter;
+use crate::protocol::Request;
+
+const PROGRAM: &str = r#"let total = 0
+for (let n of [1, 2]) {
+    total = total + n
+}
+let approval = await Signal.next("approval")
+let charge = await Task.run("charge", { total: total, by: approval.by })
+return charge.id"#;
+
+fn program(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("rhythm-dap-{}-{}.flow", name, std::process::id()));
+    std::fs::write(&path, PROGRAM).unwrap();
+    path
+}
+
+fn request(adapter: &mut Adapter, command: &str, arguments: JsonValue) -> Vec<JsonValue> {
+    adapter.handle(Request {
+        seq: 1,
+        command: command.to_string(),
+        arguments,
+    })
+}
+
+/// The events in `messages`, as `(event, body)`
+fn events(messages: &[JsonValue]) -> Vec<(&str, &JsonValue)> {
+    messages
+        .iter()
+        .filter(|m| m["type"] == "event")
+        .map(|m| (m["event"].as_str().unwrap(), &m["body"]))
+        .collect()
+}
+
+fn stopped(messages: &[JsonValue]) -> Option<&str> {
+    events(messages)
+        .into_iter()
+        .find(|(event, _)| *event == "stopped")
+        .and_then(|(_, body)| body["reason"].as_str())
+}
+
+/// Launch with breakpoints, returning the messages from the first stop
+fn launch(adapter: &mut Adapter, config: JsonValue, lines: &[usize]) -> Vec<JsonValue> {
+    let init = request(adapter, "initialize", json!({}));
+    assert_eq!(events(&init)[0].0, "initialized");
+    assert_eq!(init[0]["success"], true);
+
+    assert_eq!(request(adapter, "launch", config)[0]["success"], true);
+    let breakpoints: Vec<JsonValue> = lines.iter().map(|l| json!({ "line": l })).collect();
+    let set = request(
+        adapter,
+        "setBreakpoints",
+        json!({ "breakpoints": breakpoints }),
+    );
+    assert_eq!(
+        set[0]["body"]["breakpoints"].as_array().unwrap().len(),
+        lines.len()
+    );
+    request(adapter, "configurationDone", json!({}))
+}
+
+fn variables(adapter: &mut Adapter, reference: i64) -> Vec<(String, String)> {
+    let response = request(
+        adapter,
+        "variables",
+        json!({ "variablesReference": reference }),
+    );
+    response[0]["body"]["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| {
+            (
+                v["name"].as_str().unwrap().to_string(),
+                v["value"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_breakpoints_stop_and_show_variables() {
+    let path = program("breakpoints");
+    let mut adapter = Adapter::new();
+    let messages = launch(&mut adapter, json!({ "program": path }), &[3]);
+    assert_eq!(stopped(&messages), Some("breakpoint"));
+
+    let trace = request(&mut adapter, "stackTrace", json!({ "threadId": 1 }));
+    let frames = trace[0]["body"]["stackFrames"].as_array().unwrap();
+    assert_eq!(frames[0]["line"], 3);
+    assert_eq!(frames[0]["name"], "total = total + n");
+    assert_eq!(frames[1]["line"], 2);
+
+    assert_eq!(
+        variables(&mut adapter, 1),
+        vec![
+            ("n".to_string(), "1".to_string()),
+            ("total".to_string(), "0".to_string()),
+        ]
+    );
+    let next = request(&mut adapter, "next", json!({ "threadId": 1 }));
+    // The next statement is the same line, for the second item
+    assert_eq!(stopped(&next), Some("breakpoint"));
+    let evaluate = request(&mut adapter, "evaluate", json!({ "expression": "total" }));
+    assert_eq!(evaluate[0]["body"]["result"], "1");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_awaits_settle_from_the_launch_configuration() {
+    let path = program("settle");
+    let mut adapter = Adapter::new();
+    let messages = launch(
+        &mut adapter,
+        json!({
+            "program": path,
+            "signals": { "approval": [{ "by": "ana" }] },
+            "tasks": { "charge": { "id": "ch_1" } },
+        }),
+        &[7],
+    );
+    assert_eq!(stopped(&messages), Some("breakpoint"));
+    assert_eq!(
+        request(
+            &mut adapter,
+            "evaluate",
+            json!({ "expression": "approval.by" })
+        )[0]["body"]["result"],
+        "\"ana\""
+    );
+
+    let locals = request(
+        &mut adapter,
+        "variables",
+        json!({ "variablesReference": 1 }),
+    );
+    let charge = locals[0]["body"]["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|v| v["name"] == "charge")
+        .unwrap()["variablesReference"]
+        .as_i64()
+        .unwrap();
+    assert_eq!(
+        variables(&mut adapter, charge),
+        vec![("id".to_string(), "\"ch_1\"".to_string())]
+    );
+
+    let end = request(&mut adapter, "continue", json!({ "threadId": 1 }));
+    let events = events(&end);
+    assert_eq!(events[0].1["output"], "Returned \"ch_1\"\n");
+    assert_eq!(events[1].0, "terminated");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_unsettled_awaits_resume_from_the_console() {
+    let path = program("resume");
+    let mut adapter = Adapter::new();
+    let messages = launch(
+        &mut adapter,
+        json!({ "program": path, "stopOnEntry": true }),
+        &[],
+    );
+    assert_eq!(stopped(&messages), Some("entry"));
+
+    let paused = request(&mut adapter, "continue", json!({ "threadId": 1 }));
+    assert_eq!(stopped(&paused), Some("pause"));
+    assert!(events(&paused)[0].1["output"]
+        .as_str()
+        .unwrap()
+        .contains("signal 'approval'"));
+
+    let resumed = request(
+        &mut adapter,
+        "evaluate",
+        json!({ "expression": "resume {\"by\": \"ana\"}" }),
+    );
+    assert_eq!(resumed[0]["success"], true);
+    assert_eq!(stopped(&resumed), Some("step"));
+
+    let paused = request(&mut adapter, "continue", json!({ "threadId": 1 }));
+    assert!(events(&paused)[0].1["output"]
+        .as_str()
+        .unwrap()
+        .contains("task 'charge'"));
+
+    let bad = request(
+        &mut adapter,
+        "evaluate",
+        json!({ "expression": "resume {" }),
+    );
+    assert_eq!(bad[0]["success"], false);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_invalid_programs_fail_to_launch() {
+    let path = std::env::temp_dir().join(format!("rhythm-dap-invalid-{}.flow", std::process::id()));
+    std::fs::write(&path, "let x = (").unwrap();
+    let mut adapter = Adapter::new();
+    let response = request(&mut adapter, "launch", json!({ "program": path }));
+    assert_eq!(response[0]["success"], false);
+
+    let missing = request(
+        &mut adapter,
+        "launch",
+        json!({ "program": "/nowhere.flow" }),
+    );
+    assert!(missing[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to read"));
+    assert_eq!(
+        request(&mut adapter, "stepBack", json!({}))[0]["success"],
+        false
+    );
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,4 @@
+//! Tests for the debug adapter
+
+mod adapter_test;
+mod protocol_test;
@@ -0,0 +1,25 @@
+use std::io::Cursor;
+
+use serde_json::json;
+
+use crate::protocol::{read_message, write_message};
+
+#[test]
+fn test_messages_round_trip() {
+    let mut buffer = Vec::new();
+    let first = json!({ "seq": 1, "type": "request", "command": "initialize" });
+    let second = json!({ "seq": 2, "type": "request", "command": "threads" });
+    write_message(&mut buffer, &first).unwrap();
+    write_message(&mut buffer, &second).unwrap();
+
+    let mut reader = Cursor::new(buffer);
+    assert_eq!(read_message(&mut reader).unwrap(), Some(first));
+    assert_eq!(read_message(&mut reader).unwrap(), Some(second));
+    assert_eq!(read_message(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn test_message_without_length_is_rejected() {
+    let mut reader = Cursor::new(b"Content-Type: json\r\n\r\n{}".to_vec());
+    assert!(read_message(&mut reader).is_err());
+}
@@ -4,14 +4,18 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+
+use serde_json::{Map, Value as JsonValue};
 use tokio::sync::RwLock;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
 use crate::completions::{get_completions, get_signature_help, CompletionContext};
+use crate::diagnostics::compute_diagnostics;
 use crate::hover::get_hover_from_ast;
-use crate::parser::{parse_workflow, ParseError, WorkflowDef};
+use crate::parser::{parse_workflow, InputSchema, ParseError, WorkflowDef};
+use crate::tasks::{workspace_root, TaskCatalog};
 
 /// Document state stored for each open file
 #[derive(Debug, Clone)]
@@ -58,6 +62,9 @@ impl DocumentState {
 pub struct RhythmBackend {
     client: Client,
     documents: Arc<RwLock<HashMap<Url, DocumentState>>>,
+    tasks: Arc<RwLock<TaskCatalog>>,
+    /// `[workflow_defaults]` of the workspace's rhythm.toml
+    workflow_defaults: Arc<RwLock<Map<String, JsonValue>>>,
 }
 
 impl RhythmBackend {
@@ -65,6 +72,16 @@ impl RhythmBackend {
         Self {
             client,
             documents: Arc::new(RwLock::new(HashMap::new())),
+            tasks: Arc::new(RwLock::new(TaskCatalog::default())),
+            workflow_defaults: Arc::new(RwLock::new(Map::new())),
+        }
+    }
+
+    /// Pick up changes to the task manifest, logging one that doesn't parse
+    async fn refresh_tasks(&self) {
+        let result = self.tasks.write().await.refresh();
+        if let Err(e) = result {
+            self.client.log_message(MessageType::WARNING, e).await;
         }
     }
 
@@ -75,45 +92,10 @@ impl RhythmBackend {
             return;
         };
 
-        let diagnostics = if let Some(err) = &doc.parse_error {
-            let range = if let Some(span) = &err.span {
-                Range {
-                    start: Position {
-                        line: span.start_line as u32,
-                        character: span.start_col as u32,
-                    },
-                    end: Position {
-                        line: span.end_line as u32,
-                        character: span.end_col as u32,
-                    },
-                }
-            } else {
-                Range {
-                    start: Position {
-                        line: 0,
-                        character: 0,
-                    },
-                    end: Position {
-                        line: 0,
-                        character: 0,
-                    },
-                }
-            };
-
-            vec![Diagnostic {
-                range,
-                severity: Some(DiagnosticSeverity::ERROR),
-                code: None,
-                code_description: None,
-                source: Some("rhythm".to_string()),
-                message: err.message.clone(),
-                related_information: None,
-                tags: None,
-                data: None,
-            }]
-        } else {
-            vec![]
-        };
+        self.refresh_tasks().await;
+        let known_tasks = self.tasks.read().await.names();
+        let defaults = self.workflow_defaults.read().await;
+        let diagnostics = compute_diagnostics(doc, known_tasks.as_ref(), &defaults);
 
         self.client
             .publish_diagnostics(uri, diagnostics, Some(doc.version))
@@ -123,7 +105,22 @@ impl RhythmBackend {
 
 #[tower_lsp::async_trait]
 impl LanguageServer for RhythmBackend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        *self.tasks.write().await = TaskCatalog::new(TaskCatalog::manifest_path(&params));
+        if let Some(root) = workspace_root(&params) {
+            let path = root.join("rhythm.toml");
+            if path.exists() {
+                match rhythm_core::config::Config::from_file(&path) {
+                    Ok(config) => *self.workflow_defaults.write().await = config.workflow_defaults,
+                    Err(e) => {
+                        self.client
+                            .log_message(MessageType::WARNING, format!("{:#}", e))
+                            .await
+                    }
+                }
+            }
+        }
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
@@ -138,7 +135,11 @@ impl LanguageServer for RhythmBackend {
                     },
                 )),
                 completion_provider: Some(CompletionOptions {
-                    trigger_characters: Some(vec![".".to_string(), "(".to_string()]),
+                    trigger_characters: Some(vec![
+                        ".".to_string(),
+                        "(".to_string(),
+                        "\"".to_string(),
+                    ]),
                     resolve_provider: Some(false),
                     work_done_progress_options: Default::default(),
                     all_commit_characters: None,
@@ -226,7 +227,16 @@ impl LanguageServer for RhythmBackend {
             return Ok(None);
         };
 
-        let ctx = CompletionContext::from_position(&doc.content, position.line, position.character);
+        let mut ctx =
+            CompletionContext::from_position(&doc.content, position.line, position.character);
+        // Read from the source, as the body rarely parses while typing `Inputs.`
+        if let Ok(Some(schema)) = InputSchema::of_source(&doc.content) {
+            ctx.inputs = schema.fields;
+        }
+        if ctx.in_task_name {
+            self.refresh_tasks().await;
+            ctx.tasks = self.tasks.read().await.tasks().to_vec();
+        }
         let items = get_completions(&ctx);
 
         Ok(Some(CompletionResponse::Array(items)))
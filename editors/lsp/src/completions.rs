@@ -8,7 +8,8 @@
 
 use tower_lsp::lsp_types::*;
 
-use crate::parser::{DeclareTarget, Span, Stmt};
+use crate::parser::{DeclareTarget, InputField, Span, Stmt};
+use crate::tasks::TaskInfo;
 
 /// All Rhythm keywords
 pub const KEYWORDS: &[(&str, &str)] = &[
@@ -21,6 +22,7 @@ pub const KEYWORDS: &[(&str, &str)] = &[
     ("of", "Iterate over values"),
     ("in", "Iterate over keys"),
     ("return", "Return from workflow"),
+    ("throw", "Throw an error"),
     ("await", "Await a promise"),
     ("try", "Try block for error handling"),
     ("catch", "Catch block for error handling"),
@@ -36,7 +38,11 @@ pub const BUILTIN_MODULES: &[(&str, &str)] = &[
     ("Inputs", "Access workflow input parameters"),
     ("Task", "Execute durable tasks"),
     ("Timer", "Create delays and timers"),
+    ("Date", "Read the current time"),
     ("Signal", "Wait for external signals"),
+    ("ExternalTask", "Wait for results delivered by token"),
+    ("Compensate", "Undo steps when the workflow fails"),
+    ("Log", "Write to the execution's logs"),
     ("Workflow", "Execute nested workflows"),
     ("Promise", "Compose multiple promises"),
     ("Math", "Mathematical utility functions"),
@@ -53,13 +59,33 @@ pub struct MethodInfo {
 
 pub fn get_module_methods(module: &str) -> Vec<MethodInfo> {
     match module {
-        "Task" => vec![MethodInfo {
-            name: "run",
-            signature: "Task.run(taskName: string, inputs?: object): Promise<any>",
-            documentation: "Execute a durable task and return a promise for its result.\n\n\
-                           The task will be executed exactly once, even if the workflow restarts.",
-            insert_text: "run(\"${1:taskName}\", ${2:{}})",
-        }],
+        "Task" => vec![
+            MethodInfo {
+                name: "run",
+                signature: "Task.run(taskName: string, inputs?: object, options?: { timeout_seconds, max_retries, queue, priority }): Promise<any>",
+                documentation: "Execute a durable task and return a promise for its result.\n\n\
+                               The task will be executed exactly once, even if the workflow restarts. \
+                               `options` overrides the task's queue, priority, retry limit and \
+                               claim timeout.",
+                insert_text: "run(\"${1:taskName}\", ${2:{}})",
+            },
+            MethodInfo {
+                name: "stream",
+                signature: "Task.stream(task: Promise | string, from?: number): Promise<{ chunks, next, done }>",
+                documentation: "Wait for partial results the task emitted from chunk `from` on.\n\n\
+                               Resolves once there are new chunks or the task has finished; \
+                               pass `next` as `from` to keep reading until `done`.",
+                insert_text: "stream(${1:task}, ${2:0})",
+            },
+            MethodInfo {
+                name: "all",
+                signature: "Task.all(tasks: Array | Object): Promise<Array | Object>",
+                documentation: "Wait for several tasks to finish, like `Promise.all`.\n\n\
+                               Returns an array or object with each task's result.\n\
+                               Rejects as soon as any task fails.",
+                insert_text: "all([${1}])",
+            },
+        ],
         "Timer" => vec![MethodInfo {
             name: "delay",
             signature: "Timer.delay(seconds: number): Promise<void>",
@@ -68,6 +94,22 @@ pub fn get_module_methods(module: &str) -> Vec<MethodInfo> {
                            from where it left off.",
             insert_text: "delay(${1:seconds})",
         }],
+        "Date" => vec![
+            MethodInfo {
+                name: "now",
+                signature: "Date.now(): number",
+                documentation: "Return the current time in milliseconds since the Unix epoch.\n\n\
+                               The value is recorded with the workflow's state, so a resumed \
+                               workflow keeps the time it read.",
+                insert_text: "now()",
+            },
+            MethodInfo {
+                name: "iso",
+                signature: "Date.iso(ms?: number): string",
+                documentation: "Return an ISO 8601 UTC timestamp for `ms`, or for the current time.",
+                insert_text: "iso()",
+            },
+        ],
         "Signal" => vec![MethodInfo {
             name: "next",
             signature: "Signal.next(name: string): Promise<any>",
@@ -75,13 +117,61 @@ pub fn get_module_methods(module: &str) -> Vec<MethodInfo> {
                            Returns the signal payload when received.",
             insert_text: "next(\"${1:signalName}\")",
         }],
-        "Workflow" => vec![MethodInfo {
-            name: "run",
-            signature: "Workflow.run(workflowName: string, inputs?: object): Promise<any>",
-            documentation: "Execute a nested workflow and return a promise for its result.\n\n\
-                           Child workflows are executed durably as separate workflow instances.",
-            insert_text: "run(\"${1:workflowName}\", ${2:{}})",
+        "ExternalTask" => vec![MethodInfo {
+            name: "create",
+            signature: "ExternalTask.create(name: string, inputs?: object): { token: string, promise: Promise<any> }",
+            documentation: "Create a task that an outside system completes by token.\n\n\
+                           Pass `token` to the system (e.g. in a webhook) and await `promise`; \
+                           it resolves when `complete_external_task(token, result)` is called.",
+            insert_text: "create(\"${1:name}\")",
+        }],
+        "Compensate" => vec![MethodInfo {
+            name: "register",
+            signature: "Compensate.register(taskName: string, inputs: object): null",
+            documentation: "Register a task that undoes a step, run only if the workflow fails.\n\n\
+                           On an uncaught error, registered tasks run one at a time, last \
+                           registered first, before the workflow is marked failed.",
+            insert_text: "register(\"${1:taskName}\", ${2:{}})",
         }],
+        "Log" => vec![
+            MethodInfo {
+                name: "info",
+                signature: "Log.info(message: string, data?: any): null",
+                documentation: "Write an informational message to the execution's logs.\n\n\
+                               `data` is stored alongside it as JSON.",
+                insert_text: "info(\"${1:message}\")",
+            },
+            MethodInfo {
+                name: "warn",
+                signature: "Log.warn(message: string, data?: any): null",
+                documentation: "Write a warning to the execution's logs.\n\n\
+                               `data` is stored alongside it as JSON.",
+                insert_text: "warn(\"${1:message}\")",
+            },
+            MethodInfo {
+                name: "error",
+                signature: "Log.error(message: string, data?: any): null",
+                documentation: "Write an error to the execution's logs.\n\n\
+                               `data` is stored alongside it as JSON.",
+                insert_text: "error(\"${1:message}\")",
+            },
+        ],
+        "Workflow" => vec![
+            MethodInfo {
+                name: "run",
+                signature: "Workflow.run(workflowName: string, inputs?: object): Promise<any>",
+                documentation: "Execute a nested workflow and return a promise for its result.\n\n\
+                               Child workflows are executed durably as separate workflow instances.",
+                insert_text: "run(\"${1:workflowName}\", ${2:{}})",
+            },
+            MethodInfo {
+                name: "continueAsNew",
+                signature: "Workflow.continueAsNew(inputs: object): null",
+                documentation: "Finish this execution and start the workflow afresh with new inputs.\n\n\
+                               Keeps the state of long-running loops bounded.",
+                insert_text: "continueAsNew(${1:{}})",
+            },
+        ],
         "Promise" => vec![
             MethodInfo {
                 name: "all",
@@ -134,16 +224,36 @@ pub fn get_module_methods(module: &str) -> Vec<MethodInfo> {
             },
             MethodInfo {
                 name: "min",
-                signature: "Math.min(a: number, b: number): number",
-                documentation: "Return the smaller of two numbers.",
+                signature: "Math.min(...values: number): number",
+                documentation: "Return the smallest of the numbers.",
                 insert_text: "min(${1:a}, ${2:b})",
             },
             MethodInfo {
                 name: "max",
-                signature: "Math.max(a: number, b: number): number",
-                documentation: "Return the larger of two numbers.",
+                signature: "Math.max(...values: number): number",
+                documentation: "Return the largest of the numbers.",
                 insert_text: "max(${1:a}, ${2:b})",
             },
+            MethodInfo {
+                name: "pow",
+                signature: "Math.pow(base: number, exponent: number): number",
+                documentation: "Raise base to the power of exponent.",
+                insert_text: "pow(${1:base}, ${2:exponent})",
+            },
+            MethodInfo {
+                name: "sqrt",
+                signature: "Math.sqrt(x: number): number",
+                documentation: "Return the square root.",
+                insert_text: "sqrt(${1:x})",
+            },
+            MethodInfo {
+                name: "random",
+                signature: "Math.random(): number",
+                documentation: "Return a random number in [0, 1).\n\n\
+                               The value is recorded with the workflow's state, so a resumed \
+                               workflow keeps the numbers it drew.",
+                insert_text: "random()",
+            },
         ],
         _ => vec![],
     }
@@ -194,6 +304,24 @@ pub fn get_array_methods() -> Vec<MethodInfo> {
             documentation: "Return a new array with elements in reverse order.",
             insert_text: "reverse()",
         },
+        MethodInfo {
+            name: "push",
+            signature: "array.push(...values: any): Array",
+            documentation: "Return a new array with the values appended.",
+            insert_text: "push(${1:value})",
+        },
+        MethodInfo {
+            name: "sort",
+            signature: "array.sort(): Array",
+            documentation: "Return a new array sorted in ascending order.",
+            insert_text: "sort()",
+        },
+        MethodInfo {
+            name: "flat",
+            signature: "array.flat(depth?: number): Array",
+            documentation: "Return a new array with nested arrays flattened.",
+            insert_text: "flat()",
+        },
     ]
 }
 
@@ -278,6 +406,13 @@ pub struct CompletionContext {
     pub after_dot: bool,
     /// The identifier before the dot (if after_dot is true)
     pub dot_target: Option<String>,
+    /// Inputs the workflow declares in its front matter
+    pub inputs: Vec<InputField>,
+    /// Whether we're inside the task name string of `Task.run("` or
+    /// `Compensate.register("`
+    pub in_task_name: bool,
+    /// Tasks listed in the project's task manifest
+    pub tasks: Vec<TaskInfo>,
 }
 
 impl CompletionContext {
@@ -308,6 +443,19 @@ impl CompletionContext {
             None
         };
 
+        // Inside `Task.run("...` (or `Compensate.register("...`) up to the
+        // cursor, with no closing quote yet
+        let in_task_name = ["Task.run(", "Compensate.register("]
+            .iter()
+            .filter_map(|call| prefix.rfind(call).map(|start| start + call.len()))
+            .max()
+            .is_some_and(|start| {
+                prefix[start..]
+                    .trim_start()
+                    .strip_prefix('"')
+                    .is_some_and(|name| !name.contains('"'))
+            });
+
         // Collect variables from the source (simplified - just looks for let/const declarations)
         let mut variables = Vec::new();
         for line in lines.iter().take(line as usize + 1) {
@@ -331,6 +479,9 @@ impl CompletionContext {
             variables,
             after_dot,
             dot_target,
+            inputs: Vec::new(),
+            in_task_name,
+            tasks: Vec::new(),
         }
     }
 }
@@ -339,12 +490,44 @@ impl CompletionContext {
 pub fn get_completions(ctx: &CompletionContext) -> Vec<CompletionItem> {
     let mut items = Vec::new();
 
+    if ctx.in_task_name {
+        // Registered task names, and nothing else inside the string
+        for task in &ctx.tasks {
+            items.push(CompletionItem {
+                label: task.name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some("Registered task".to_string()),
+                documentation: task.description.clone().map(Documentation::String),
+                ..Default::default()
+            });
+        }
+        return items;
+    }
+    if ctx.trigger_char == Some('"') {
+        // Just opened or closed some other string
+        return items;
+    }
+
     if ctx.after_dot {
         // Member access completions
         if let Some(target) = &ctx.dot_target {
             // Check if it's a builtin module
             let methods = get_module_methods(target);
-            if !methods.is_empty() {
+            if target == "Inputs" && !ctx.inputs.is_empty() {
+                for input in &ctx.inputs {
+                    let mut detail = input.ty.to_string();
+                    if !input.required {
+                        detail.push_str(" (optional)");
+                    }
+                    items.push(CompletionItem {
+                        label: input.name.clone(),
+                        kind: Some(CompletionItemKind::FIELD),
+                        detail: Some(detail),
+                        documentation: input.description.clone().map(Documentation::String),
+                        ..Default::default()
+                    });
+                }
+            } else if !methods.is_empty() {
                 for method in methods {
                     items.push(CompletionItem {
                         label: method.name.to_string(),
@@ -0,0 +1,105 @@
+//! Diagnostics for Rhythm documents
+//!
+//! Combines parse errors with the core validation and static analysis rules,
+//! checking task names too when the project's task manifest is known. Rule
+//! levels come from the document's front matter over the project's
+//! `[workflow_defaults]`, and `rhythm-ignore` comments apply as they do on
+//! registration.
+
+use std::collections::BTreeSet;
+
+use serde_json::{Map, Value as JsonValue};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+
+use crate::backend::DocumentState;
+use crate::parser::Span;
+use rhythm_core::parser::analysis::{Severity, INVALID_FRONT_MATTER};
+use rhythm_core::parser::front_matter;
+use rhythm_core::parser::semantic_validator::{Validator, ValidatorConfig};
+
+/// Compute all diagnostics for a document
+///
+/// `defaults` are the project's `[workflow_defaults]` settings.
+pub fn compute_diagnostics(
+    doc: &DocumentState,
+    known_tasks: Option<&BTreeSet<String>>,
+    defaults: &Map<String, JsonValue>,
+) -> Vec<Diagnostic> {
+    if let Some(err) = &doc.parse_error {
+        return vec![Diagnostic {
+            range: err.span.as_ref().map(span_to_range).unwrap_or_default(),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: None,
+            code_description: None,
+            source: Some("rhythm".to_string()),
+            message: err.message.clone(),
+            related_information: None,
+            tags: None,
+            data: None,
+        }];
+    }
+
+    let Some(workflow) = &doc.workflow else {
+        return vec![];
+    };
+
+    // Front matter that isn't YAML is reported by the analysis; settings
+    // that don't read are reported here. Either way the rules keep their
+    // default levels.
+    let own = front_matter::extract(&doc.content)
+        .and_then(|text| front_matter::parse(text).ok())
+        .unwrap_or_default();
+    let mut config_error = None;
+    let config = ValidatorConfig::from_settings(&front_matter::merge(defaults, &own))
+        .unwrap_or_else(|e| {
+            config_error = Some(e.to_string());
+            ValidatorConfig::default()
+        });
+    let mut validator = Validator::new().with_config(config);
+    if let Some(known) = known_tasks {
+        validator = validator.with_known_tasks(known.clone());
+    }
+
+    let mut findings = validator.check(workflow, &doc.content);
+    if let Some(message) = config_error {
+        findings.insert(
+            0,
+            rhythm_core::parser::analysis::Diagnostic {
+                code: INVALID_FRONT_MATTER,
+                severity: Severity::Error,
+                message,
+                span: Span::default(),
+            },
+        );
+    }
+    findings
+        .into_iter()
+        .map(|d| Diagnostic {
+            range: span_to_range(&d.span),
+            severity: Some(match d.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+            }),
+            code: Some(NumberOrString::String(d.code.to_string())),
+            code_description: None,
+            source: Some("rhythm".to_string()),
+            message: d.message,
+            related_information: None,
+            tags: None,
+            data: None,
+        })
+        .collect()
+}
+
+fn span_to_range(span: &Span) -> Range {
+    Range {
+        start: Position {
+            line: span.start_line as u32,
+            character: span.start_col as u32,
+        },
+        end: Position {
+            line: span.end_line as u32,
+            character: span.end_col as u32,
+        },
+    }
+}
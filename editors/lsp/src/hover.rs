@@ -272,7 +272,8 @@ fn find_expr_at_offset(stmt: &Stmt, offset: usize) -> Option<Expr> {
         }
         Stmt::Return {
             value: Some(value), ..
-        } => {
+        }
+        | Stmt::Throw { value, .. } => {
             if let Some(e) = find_expr_at_offset_in_expr(value, offset) {
                 return Some(e);
             }
@@ -347,7 +348,10 @@ fn find_expr_at_offset_in_expr(expr: &Expr, offset: usize) -> Option<Expr> {
                     return Some(e);
                 }
             }
-            Expr::LitList { elements, .. } => {
+            Expr::Template {
+                exprs: elements, ..
+            }
+            | Expr::LitList { elements, .. } => {
                 for elem in elements {
                     if let Some(e) = find_expr_at_offset_in_expr(elem, offset) {
                         return Some(e);
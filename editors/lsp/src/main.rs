@@ -15,8 +15,10 @@ use tracing_subscriber::EnvFilter;
 
 mod backend;
 mod completions;
+mod diagnostics;
 mod hover;
 mod parser;
+mod tasks;
 
 #[cfg(test)]
 mod tests;
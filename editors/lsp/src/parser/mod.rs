@@ -4,6 +4,7 @@
 
 // Re-export core AST types
 pub use rhythm_core::executor::types::ast::{DeclareTarget, Expr, Span, Stmt};
+pub use rhythm_core::parser::input_schema::{InputField, InputSchema};
 pub use rhythm_core::parser::WorkflowDef;
 
 /// Parse error with location information
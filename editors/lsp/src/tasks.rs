@@ -0,0 +1,147 @@
+//! Registered task names
+//!
+//! Workers register their tasks at runtime, so a `.flow` file alone doesn't
+//! say which names `Task.run("...")` may use. A project manifest lists them
+//! instead: by default `.rhythm/tasks.json` in the workspace root, or the
+//! path in the `taskManifest` initialization option. Python projects can
+//! write it with `python -m rhythm tasks -m <module>`:
+//!
+//! ```json
+//! { "tasks": [{ "name": "charge_card", "description": "Charge the order's card" }] }
+//! ```
+//!
+//! Entries may also be bare names. Without a manifest, task names are
+//! neither completed nor checked.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use tower_lsp::lsp_types::InitializeParams;
+
+/// Where the manifest is looked for when no path is configured
+pub const DEFAULT_MANIFEST: &str = ".rhythm/tasks.json";
+
+/// A task listed in the manifest
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TaskInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Name(String),
+    Task(TaskInfo),
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    tasks: Vec<Entry>,
+}
+
+/// Parse a task manifest
+pub fn parse_manifest(text: &str) -> Result<Vec<TaskInfo>, String> {
+    let manifest: Manifest =
+        serde_json::from_str(text).map_err(|e| format!("Invalid task manifest: {}", e))?;
+    Ok(manifest
+        .tasks
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Name(name) => TaskInfo {
+                name,
+                description: None,
+            },
+            Entry::Task(task) => task,
+        })
+        .collect())
+}
+
+/// The tasks in the project's manifest, re-read whenever the file changes
+#[derive(Debug, Default)]
+pub struct TaskCatalog {
+    path: Option<PathBuf>,
+    /// Modification time of the manifest last read
+    modified: Option<SystemTime>,
+    /// `None` until a manifest has been read
+    tasks: Option<Vec<TaskInfo>>,
+}
+
+impl TaskCatalog {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// The manifest path for a workspace, from the `taskManifest`
+    /// initialization option (relative to the root) or the default location
+    pub fn manifest_path(params: &InitializeParams) -> Option<PathBuf> {
+        let configured = params
+            .initialization_options
+            .as_ref()
+            .and_then(|options| options.get("taskManifest"))
+            .and_then(|path| path.as_str())
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+        if let Some(path) = &configured {
+            if path.is_absolute() {
+                return configured;
+            }
+        }
+
+        let root = workspace_root(params)?;
+        Some(root.join(configured.unwrap_or_else(|| PathBuf::from(DEFAULT_MANIFEST))))
+    }
+
+    /// Re-read the manifest if it changed since it was last read
+    ///
+    /// A manifest that disappears turns checking off again. On a read or
+    /// parse error the previous tasks are kept.
+    pub fn refresh(&mut self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let Ok(modified) = std::fs::metadata(path).and_then(|meta| meta.modified()) else {
+            self.modified = None;
+            self.tasks = None;
+            return Ok(());
+        };
+        if self.modified == Some(modified) {
+            return Ok(());
+        }
+
+        self.modified = Some(modified);
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.tasks = Some(parse_manifest(&text)?);
+        Ok(())
+    }
+
+    /// Tasks in the manifest; empty if there is none
+    pub fn tasks(&self) -> &[TaskInfo] {
+        self.tasks.as_deref().unwrap_or_default()
+    }
+
+    /// Names to check `Task.run` against, if a manifest was read
+    pub fn names(&self) -> Option<BTreeSet<String>> {
+        let tasks = self.tasks.as_ref()?;
+        Some(tasks.iter().map(|task| task.name.clone()).collect())
+    }
+}
+
+/// The first workspace folder, or the root of older clients
+#[allow(deprecated)]
+pub(crate) fn workspace_root(params: &InitializeParams) -> Option<PathBuf> {
+    let uri = params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .map(|folder| &folder.uri)
+        .or(params.root_uri.as_ref())?;
+    uri.to_file_path().ok()
+}
@@ -3,7 +3,8 @@ use tower_lsp::lsp_types::CompletionItemKind;
 use crate::completions::{
     collect_variables, get_completions, get_signature_help, CompletionContext,
 };
-use crate::parser::parse_workflow;
+use crate::parser::{parse_workflow, InputSchema};
+use crate::tasks::TaskInfo;
 
 // =============================================================================
 // CompletionContext::from_position tests
@@ -113,7 +114,11 @@ fn test_completions_includes_modules() {
 
     assert!(labels.contains(&"Task"));
     assert!(labels.contains(&"Timer"));
+    assert!(labels.contains(&"Date"));
     assert!(labels.contains(&"Signal"));
+    assert!(labels.contains(&"ExternalTask"));
+    assert!(labels.contains(&"Compensate"));
+    assert!(labels.contains(&"Log"));
     assert!(labels.contains(&"Workflow"));
     assert!(labels.contains(&"Promise"));
     assert!(labels.contains(&"Math"));
@@ -162,7 +167,9 @@ fn test_completions_task_methods() {
 
     let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
     assert!(labels.contains(&"run"));
-    assert_eq!(items.len(), 1); // Task only has one method
+    assert!(labels.contains(&"stream"));
+    assert!(labels.contains(&"all"));
+    assert_eq!(items.len(), 3);
 }
 
 #[test]
@@ -175,6 +182,26 @@ fn test_completions_timer_methods() {
     assert!(labels.contains(&"delay"));
 }
 
+#[test]
+fn test_completions_date_methods() {
+    let source = "Date.";
+    let ctx = CompletionContext::from_position(source, 0, 5);
+    let items = get_completions(&ctx);
+
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(labels, vec!["now", "iso"]);
+}
+
+#[test]
+fn test_completions_workflow_methods() {
+    let source = "Workflow.";
+    let ctx = CompletionContext::from_position(source, 0, 9);
+    let items = get_completions(&ctx);
+
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(labels, vec!["run", "continueAsNew"]);
+}
+
 #[test]
 fn test_completions_signal_methods() {
     let source = "Signal.";
@@ -210,6 +237,9 @@ fn test_completions_math_methods() {
     assert!(labels.contains(&"round"));
     assert!(labels.contains(&"min"));
     assert!(labels.contains(&"max"));
+    assert!(labels.contains(&"pow"));
+    assert!(labels.contains(&"sqrt"));
+    assert!(labels.contains(&"random"));
 }
 
 #[test]
@@ -233,6 +263,60 @@ fn test_completions_method_has_signature() {
     assert!(run_item.detail.as_ref().unwrap().contains("Task.run"));
 }
 
+#[test]
+fn test_completions_declared_inputs() {
+    let source = "```\ninputs:\n  order_id: string\n  tags:\n    type: string[]\n    required: false\n    description: Labels for the order\n```\nreturn Inputs.";
+    let mut ctx = CompletionContext::from_position(source, 8, 14);
+    ctx.inputs = InputSchema::of_source(source).unwrap().unwrap().fields;
+    let items = get_completions(&ctx);
+
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(labels, vec!["order_id", "tags"]);
+    assert_eq!(items[0].kind, Some(CompletionItemKind::FIELD));
+    assert_eq!(items[0].detail.as_deref(), Some("string"));
+    assert_eq!(items[1].detail.as_deref(), Some("string[] (optional)"));
+    assert!(items[1].documentation.is_some());
+}
+
+#[test]
+fn test_completions_task_names() {
+    let source = "let t = Task.run(\"ch";
+    let mut ctx = CompletionContext::from_position(source, 0, 20);
+    assert!(ctx.in_task_name);
+    ctx.tasks = vec![
+        TaskInfo {
+            name: "charge_card".to_string(),
+            description: Some("Charge the order's card".to_string()),
+        },
+        TaskInfo {
+            name: "ship".to_string(),
+            description: None,
+        },
+    ];
+    let items = get_completions(&ctx);
+
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(labels, vec!["charge_card", "ship"]);
+    assert_eq!(items[0].kind, Some(CompletionItemKind::FUNCTION));
+    assert!(items[0].documentation.is_some());
+}
+
+#[test]
+fn test_context_task_name_only_inside_open_string() {
+    let in_task_name = |prefix: &str| {
+        CompletionContext::from_position(prefix, 0, prefix.len() as u32).in_task_name
+    };
+    assert!(in_task_name("await Task.run(\""));
+    assert!(in_task_name("await Task.run( \"char"));
+    assert!(!in_task_name("await Task.run(\"charge\", "));
+    assert!(!in_task_name("await Task.run(name"));
+    assert!(in_task_name("Compensate.register(\"re"));
+    assert!(!in_task_name(
+        "Compensate.register(\"refund\", {}); Log.info(\""
+    ));
+    assert!(!in_task_name("Log.info(\""));
+}
+
 // =============================================================================
 // get_completions - array/string method tests
 // =============================================================================
@@ -253,6 +337,9 @@ fn test_completions_unknown_target_shows_array_string_methods() {
     assert!(labels.contains(&"join"));
     assert!(labels.contains(&"slice"));
     assert!(labels.contains(&"reverse"));
+    assert!(labels.contains(&"push"));
+    assert!(labels.contains(&"sort"));
+    assert!(labels.contains(&"flat"));
 
     // Should include string methods
     assert!(labels.contains(&"toUpperCase"));
@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+
+use serde_json::{json, Map, Value};
+use tower_lsp::lsp_types::*;
+
+use crate::backend::DocumentState;
+use crate::diagnostics::compute_diagnostics;
+
+fn diagnostics(source: &str) -> Vec<Diagnostic> {
+    compute_diagnostics(
+        &DocumentState::new(source.to_string(), 1),
+        None,
+        &Map::new(),
+    )
+}
+
+#[test]
+fn test_clean_document_has_no_diagnostics() {
+    let diags = diagnostics("let t = Task.run(\"a\", {})\nreturn await t");
+    assert!(diags.is_empty());
+}
+
+#[test]
+fn test_parse_error_reported_as_error() {
+    let diags = diagnostics("let = ");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(diags[0].code, None);
+}
+
+#[test]
+fn test_await_non_task_reported_as_warning() {
+    let diags = diagnostics("let x = 1\nlet y = await x");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String("await-non-task".to_string()))
+    );
+    assert_eq!(diags[0].range.start.line, 1);
+}
+
+#[test]
+fn test_unawaited_task_in_operator_reported() {
+    let diags = diagnostics("let t = Task.run(\"a\", {})\nreturn t * 2");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String(
+            "unawaited-task-in-operator".to_string()
+        ))
+    );
+    // Points at the task operand, not the whole expression
+    assert_eq!(diags[0].range.start.line, 1);
+    assert_eq!(diags[0].range.start.character, 7);
+}
+
+#[test]
+fn test_type_mismatch_reported_at_initializer() {
+    let diags = diagnostics("let total: number = 0\ntotal = \"zero\"");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String("type-mismatch".to_string()))
+    );
+    assert_eq!(diags[0].range.start.line, 1);
+    assert_eq!(diags[0].range.start.character, 8);
+}
+
+#[test]
+fn test_typed_inputs_unknown_property_reported() {
+    let diags =
+        diagnostics("async function main(inputs: {orderId: string}) {\n  return inputs.orderID\n}");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String("unknown-property".to_string()))
+    );
+    assert_eq!(diags[0].range.start.line, 1);
+}
+
+#[test]
+fn test_unknown_task_reported_when_manifest_known() {
+    let doc = DocumentState::new("await Task.run(\"chrage\", {})".to_string(), 1);
+    assert!(compute_diagnostics(&doc, None, &Map::new()).is_empty());
+
+    let known = BTreeSet::from(["charge".to_string()]);
+    let diags = compute_diagnostics(&doc, Some(&known), &Map::new());
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String("unknown-task".to_string()))
+    );
+    assert_eq!(diags[0].range.start.character, 15);
+}
+
+#[test]
+fn test_nested_await_reported_as_error() {
+    let diags = diagnostics("let t = Task.run(\"a\", {})\nLog.info(await t)");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String("await-position".to_string()))
+    );
+    assert_eq!(diags[0].range.start.line, 1);
+}
+
+#[test]
+fn test_const_reassignment_reported_as_error() {
+    let diags = diagnostics("const total = 1\ntotal = 2\nreturn total");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String("const-reassignment".to_string()))
+    );
+    assert_eq!(diags[0].range.start.line, 1);
+}
+
+#[test]
+fn test_rule_levels_from_project_and_front_matter() {
+    let Value::Object(defaults) = json!({ "validator": { "unawaited-task": "error" } }) else {
+        unreachable!()
+    };
+    let doc = DocumentState::new("Task.run(\"a\", {})".to_string(), 1);
+    let diags = compute_diagnostics(&doc, None, &defaults);
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::ERROR));
+
+    let doc = DocumentState::new(
+        "```\nvalidator:\n  unawaited-task: off\n```\nTask.run(\"a\", {})".to_string(),
+        1,
+    );
+    assert!(compute_diagnostics(&doc, None, &defaults).is_empty());
+
+    let doc = DocumentState::new("// rhythm-ignore\nTask.run(\"a\", {})".to_string(), 1);
+    assert!(compute_diagnostics(&doc, None, &defaults).is_empty());
+}
+
+#[test]
+fn test_invalid_rule_level_reported() {
+    let diags = diagnostics("```\nvalidator:\n  unawaited-task: loud\n```\nreturn 1");
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(
+        diags[0].code,
+        Some(NumberOrString::String("invalid-front-matter".to_string()))
+    );
+}
@@ -1,4 +1,6 @@
 //! Tests for LSP functionality
 
 mod completions_test;
+mod diagnostics_test;
 mod hover_test;
+mod tasks_test;
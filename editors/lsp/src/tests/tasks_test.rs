@@ -0,0 +1,74 @@
+use tower_lsp::lsp_types::{InitializeParams, Url, WorkspaceFolder};
+
+use crate::tasks::{parse_manifest, TaskCatalog, TaskInfo};
+
+#[test]
+fn test_parse_manifest_accepts_names_and_objects() {
+    let tasks = parse_manifest(
+        r#"{"tasks": ["ship", {"name": "charge_card", "description": "Charge the card"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        tasks,
+        vec![
+            TaskInfo {
+                name: "ship".to_string(),
+                description: None,
+            },
+            TaskInfo {
+                name: "charge_card".to_string(),
+                description: Some("Charge the card".to_string()),
+            },
+        ]
+    );
+    assert!(parse_manifest(r#"{"tasks": [1]}"#).is_err());
+}
+
+#[test]
+fn test_manifest_path_from_options_or_workspace_root() {
+    let mut params = InitializeParams {
+        workspace_folders: Some(vec![WorkspaceFolder {
+            uri: Url::parse("file:///work/shop").unwrap(),
+            name: "shop".to_string(),
+        }]),
+        ..Default::default()
+    };
+    assert_eq!(
+        TaskCatalog::manifest_path(&params).unwrap(),
+        std::path::Path::new("/work/shop/.rhythm/tasks.json")
+    );
+
+    params.initialization_options = Some(serde_json::json!({ "taskManifest": "tasks.json" }));
+    assert_eq!(
+        TaskCatalog::manifest_path(&params).unwrap(),
+        std::path::Path::new("/work/shop/tasks.json")
+    );
+
+    params.workspace_folders = None;
+    assert_eq!(TaskCatalog::manifest_path(&params), None);
+}
+
+#[test]
+fn test_catalog_follows_manifest_file() {
+    let dir = std::env::temp_dir().join(format!("rhythm-lsp-tasks-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tasks.json");
+    let mut catalog = TaskCatalog::new(Some(path.clone()));
+
+    // No manifest: nothing to check against
+    catalog.refresh().unwrap();
+    assert_eq!(catalog.names(), None);
+
+    std::fs::write(&path, r#"{"tasks": ["ship"]}"#).unwrap();
+    catalog.refresh().unwrap();
+    assert_eq!(
+        catalog.names().unwrap().into_iter().collect::<Vec<_>>(),
+        vec!["ship"]
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    catalog.refresh().unwrap();
+    assert!(catalog.tasks().is_empty());
+    assert_eq!(catalog.names(), None);
+    std::fs::remove_dir(&dir).unwrap();
+}
@@ -6,6 +6,9 @@ import {
     ExtensionContext,
     commands,
     window,
+    debug,
+    DebugAdapterDescriptor,
+    DebugAdapterExecutable,
 } from 'vscode';
 
 import {
@@ -18,7 +21,16 @@ import {
 let client: LanguageClient | undefined;
 
 export async function activate(context: ExtensionContext): Promise<void> {
-    const lspPath = findLspExecutable(context);
+    // Debug adapter, started for each debug session
+    context.subscriptions.push(
+        debug.registerDebugAdapterDescriptorFactory('rhythm', {
+            createDebugAdapterDescriptor(): DebugAdapterDescriptor {
+                return new DebugAdapterExecutable(findExecutable(context, 'rhythm-dap', 'dap'));
+            },
+        })
+    );
+
+    const lspPath = findExecutable(context, 'rhythm-lsp', 'lsp');
 
     if (!lspPath) {
         window.showErrorMessage(
@@ -45,6 +57,9 @@ export async function activate(context: ExtensionContext): Promise<void> {
             fileEvents: workspace.createFileSystemWatcher('**/*.flow'),
         },
         outputChannelName: 'Rhythm Language Server',
+        initializationOptions: {
+            taskManifest: workspace.getConfiguration('rhythm').get<string>('tasks.manifest', ''),
+        },
     };
 
     // Create and start the client
@@ -82,11 +97,14 @@ export async function deactivate(): Promise<void> {
 
 
 /**
- * Find the rhythm-lsp executable
+ * Find the rhythm-lsp or rhythm-dap executable
+ *
+ * `component` is both the settings section (`rhythm.<component>.path`) and
+ * the directory under editors/ it is built in.
  */
-function findLspExecutable(context: ExtensionContext): string | undefined {
+function findExecutable(context: ExtensionContext, name: string, component: string): string {
     // First, check user configuration
-    const configPath = workspace.getConfiguration('rhythm.lsp').get<string>('path');
+    const configPath = workspace.getConfiguration(`rhythm.${component}`).get<string>('path');
     if (configPath && fs.existsSync(configPath)) {
         return configPath;
     }
@@ -95,9 +113,9 @@ function findLspExecutable(context: ExtensionContext): string | undefined {
     const platform = os.platform();
     const arch = os.arch();
 
-    let binaryName = 'rhythm-lsp';
+    let binaryName = name;
     if (platform === 'win32') {
-        binaryName = 'rhythm-lsp.exe';
+        binaryName = `${name}.exe`;
     }
 
     // Resolve symlinks to get the real extension path (for local development)
@@ -110,8 +128,8 @@ function findLspExecutable(context: ExtensionContext): string | undefined {
         // Generic bin directory
         path.join(context.extensionPath, 'bin', binaryName),
         // Server directory (for development) - use real path to handle symlinks
-        path.join(realExtensionPath, '..', 'lsp', 'target', 'release', binaryName),
-        path.join(realExtensionPath, '..', 'lsp', 'target', 'debug', binaryName),
+        path.join(realExtensionPath, '..', component, 'target', 'release', binaryName),
+        path.join(realExtensionPath, '..', component, 'target', 'debug', binaryName),
     ];
 
     for (const bundledPath of bundledPaths) {
@@ -0,0 +1,64 @@
+/*
+ * C ABI for Rhythm
+ *
+ * Arguments and results are UTF-8 JSON strings. Every call returns
+ * RHYTHM_OK or one of the RHYTHM_ERR_* codes, and sets *out (when out is
+ * not NULL) to the result, or on error to {"code": "...", "message": "..."}.
+ * Free *out with rhythm_string_free.
+ *
+ * Calls block the calling thread and may be made from any thread.
+ */
+
+#ifndef RHYTHM_H
+#define RHYTHM_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RHYTHM_OK 0
+#define RHYTHM_ERR_NOT_FOUND 1
+#define RHYTHM_ERR_CONFLICT 2
+#define RHYTHM_ERR_VALIDATION 3
+#define RHYTHM_ERR_SERIALIZATION 4
+#define RHYTHM_ERR_DATABASE 5
+#define RHYTHM_ERR_QUOTA_EXCEEDED 6
+#define RHYTHM_ERR_READ_ONLY 7
+#define RHYTHM_ERR_WORKFLOW_TIMEOUT 8
+#define RHYTHM_ERR_WORKFLOW_FAILED 9
+#define RHYTHM_ERR_INTERNAL 10
+#define RHYTHM_ERR_QUEUE_FULL 11
+#define RHYTHM_ERR_UNAUTHENTICATED 12
+#define RHYTHM_ERR_PERMISSION_DENIED 13
+
+/*
+ * Initialize Rhythm once per process. options_json is NULL or an object
+ * with any of database_url, config_path, auto_migrate (default true),
+ * workflows ([{name, source, file_path}]), tasks and read_only.
+ */
+int32_t rhythm_init(const char *options_json, char **out);
+
+/*
+ * Run one operation, e.g. rhythm_call("start_workflow",
+ * "{\"workflow_name\": \"checkout\", \"inputs\": {}}", &out).
+ * args_json is an object, or NULL for none.
+ */
+int32_t rhythm_call(const char *method, const char *args_json, char **out);
+
+/*
+ * Stop claiming, wait up to timeout_ms for tasks handed out to be
+ * reported, put the rest back on their queues and stop the worker.
+ * *out is the drain report.
+ */
+int32_t rhythm_shutdown(uint64_t timeout_ms, char **out);
+
+/* Free a string returned through out. NULL is ignored. */
+void rhythm_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RHYTHM_H */
@@ -0,0 +1,238 @@
+//! C ABI for Rhythm Core
+//!
+//! A small `extern "C"` surface over `rhythm_core::Client` for languages
+//! without a binding of their own (Ruby, PHP, .NET, ...). Everything crosses
+//! the boundary as UTF-8 JSON strings:
+//!
+//! - `rhythm_init(options_json, &out)` initializes core once per process
+//! - `rhythm_call(method, args_json, &out)` runs one `Client` operation
+//! - `rhythm_shutdown(timeout_ms, &out)` drains the worker and stops it
+//! - `rhythm_string_free(out)` frees a string core returned
+//!
+//! Each call returns `RHYTHM_OK` (0) or the `RHYTHM_ERR_*` code of the
+//! error's `ErrorCode`, and sets `*out` to the result or to
+//! `{"code": ..., "message": ...}`. Calls block the calling thread on a
+//! runtime shared by the process, so they may be made from any thread.
+//! See `include/rhythm.h`.
+
+mod methods;
+
+use anyhow::{anyhow, Result};
+use rhythm_core::{Client, ErrorCode, RhythmError};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::OnceLock;
+
+pub const RHYTHM_OK: i32 = 0;
+pub const RHYTHM_ERR_NOT_FOUND: i32 = 1;
+pub const RHYTHM_ERR_CONFLICT: i32 = 2;
+pub const RHYTHM_ERR_VALIDATION: i32 = 3;
+pub const RHYTHM_ERR_SERIALIZATION: i32 = 4;
+pub const RHYTHM_ERR_DATABASE: i32 = 5;
+pub const RHYTHM_ERR_QUOTA_EXCEEDED: i32 = 6;
+pub const RHYTHM_ERR_READ_ONLY: i32 = 7;
+pub const RHYTHM_ERR_WORKFLOW_TIMEOUT: i32 = 8;
+pub const RHYTHM_ERR_WORKFLOW_FAILED: i32 = 9;
+pub const RHYTHM_ERR_INTERNAL: i32 = 10;
+pub const RHYTHM_ERR_QUEUE_FULL: i32 = 11;
+pub const RHYTHM_ERR_UNAUTHENTICATED: i32 = 12;
+pub const RHYTHM_ERR_PERMISSION_DENIED: i32 = 13;
+
+/// Global shared Tokio runtime
+static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+/// Get or initialize the global runtime
+fn get_runtime() -> &'static tokio::runtime::Runtime {
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to create Tokio runtime")
+    })
+}
+
+/// The status code returned for an error of kind `code`
+fn status(code: ErrorCode) -> i32 {
+    match code {
+        ErrorCode::NotFound => RHYTHM_ERR_NOT_FOUND,
+        ErrorCode::Conflict => RHYTHM_ERR_CONFLICT,
+        ErrorCode::Validation => RHYTHM_ERR_VALIDATION,
+        ErrorCode::Serialization => RHYTHM_ERR_SERIALIZATION,
+        ErrorCode::Database => RHYTHM_ERR_DATABASE,
+        ErrorCode::QuotaExceeded => RHYTHM_ERR_QUOTA_EXCEEDED,
+        ErrorCode::QueueFull => RHYTHM_ERR_QUEUE_FULL,
+        ErrorCode::ReadOnly => RHYTHM_ERR_READ_ONLY,
+        ErrorCode::Unauthenticated => RHYTHM_ERR_UNAUTHENTICATED,
+        ErrorCode::PermissionDenied => RHYTHM_ERR_PERMISSION_DENIED,
+        ErrorCode::WorkflowTimeout => RHYTHM_ERR_WORKFLOW_TIMEOUT,
+        ErrorCode::WorkflowFailed => RHYTHM_ERR_WORKFLOW_FAILED,
+        ErrorCode::Internal => RHYTHM_ERR_INTERNAL,
+    }
+}
+
+/// Read a JSON argument, treating NULL as `null`
+///
+/// # Safety
+/// `ptr` must be NULL or a valid NUL-terminated string.
+unsafe fn read_json(ptr: *const c_char, what: &str) -> Result<JsonValue> {
+    if ptr.is_null() {
+        return Ok(JsonValue::Null);
+    }
+    let text = CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| RhythmError::Validation(format!("{} is not valid UTF-8", what)))?;
+    if text.trim().is_empty() {
+        return Ok(JsonValue::Null);
+    }
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Run `f`, write its result or error to `out`, and return the status code
+///
+/// A panic is reported as an internal error instead of unwinding into the
+/// caller's frames.
+fn respond(out: *mut *mut c_char, f: impl FnOnce() -> Result<JsonValue>) -> i32 {
+    let result = catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(anyhow!("Rhythm panicked while handling the call")));
+    let (code, body) = match result {
+        Ok(value) => (RHYTHM_OK, value),
+        Err(e) => {
+            let code = ErrorCode::of(&e);
+            let body = json!({ "code": code.as_str(), "message": format!("{:#}", e) });
+            (status(code), body)
+        }
+    };
+    if !out.is_null() {
+        // serde_json escapes NUL, so the text never contains one
+        let text = CString::new(body.to_string()).expect("JSON has no NUL bytes");
+        unsafe { *out = text.into_raw() };
+    }
+    code
+}
+
+/// Options for `rhythm_init`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct InitArgs {
+    database_url: Option<String>,
+    config_path: Option<String>,
+    auto_migrate: Option<bool>,
+    workflows: Vec<WorkflowArg>,
+    tasks: Vec<rhythm_core::config::TaskConfig>,
+    read_only: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowArg {
+    name: String,
+    source: String,
+    #[serde(default)]
+    file_path: String,
+}
+
+/// Initialize Rhythm (call once at startup)
+///
+/// `options_json` is an object with any of `database_url`, `config_path`,
+/// `auto_migrate` (default true), `workflows` (a list of `{name, source,
+/// file_path}`), `tasks` (task settings) and `read_only`; NULL means none.
+/// Later calls after a successful one do nothing.
+///
+/// # Safety
+/// `options_json` must be NULL or a valid NUL-terminated string, and `out`
+/// NULL or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rhythm_init(options_json: *const c_char, out: *mut *mut c_char) -> i32 {
+    respond(out, || {
+        let options = read_json(options_json, "options")?;
+        let options: InitArgs = if options.is_null() {
+            InitArgs::default()
+        } else {
+            serde_json::from_value(options)?
+        };
+        let workflows = options
+            .workflows
+            .into_iter()
+            .map(|w| rhythm_core::WorkflowFile {
+                name: w.name,
+                source: w.source,
+                file_path: w.file_path,
+            })
+            .collect();
+        get_runtime().block_on(Client::initialize(
+            options.database_url,
+            options.config_path,
+            options.auto_migrate.unwrap_or(true),
+            workflows,
+            options.tasks,
+            options.read_only,
+        ))?;
+        Ok(JsonValue::Null)
+    })
+}
+
+/// Run the `Client` operation `method` with `args_json` (an object)
+///
+/// See `methods.rs` for the methods and their arguments.
+///
+/// # Safety
+/// `method` must be a valid NUL-terminated string, `args_json` NULL or a
+/// valid NUL-terminated string, and `out` NULL or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rhythm_call(
+    method: *const c_char,
+    args_json: *const c_char,
+    out: *mut *mut c_char,
+) -> i32 {
+    respond(out, || {
+        if method.is_null() {
+            return Err(RhythmError::Validation("method is NULL".to_string()).into());
+        }
+        let method = CStr::from_ptr(method)
+            .to_str()
+            .map_err(|_| RhythmError::Validation("method is not valid UTF-8".to_string()))?;
+        let args = read_json(args_json, "args")?;
+        get_runtime().block_on(methods::call(method, args))
+    })
+}
+
+/// Stop the worker: stop claiming, wait up to `timeout_ms` for tasks handed
+/// out to be reported, put the rest back on their queues, and stop the
+/// worker loops
+///
+/// `*out` is set to the drain report, or `null` if Rhythm was never
+/// initialized or is read-only.
+///
+/// # Safety
+/// `out` must be NULL or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn rhythm_shutdown(timeout_ms: u64, out: *mut *mut c_char) -> i32 {
+    respond(out, || {
+        if !Client::is_initialized() {
+            return Ok(JsonValue::Null);
+        }
+        let timeout = std::time::Duration::from_millis(timeout_ms);
+        let report = match get_runtime().block_on(Client::drain_worker(timeout)) {
+            Ok(report) => serde_json::to_value(report)?,
+            Err(e) if ErrorCode::of(&e) == ErrorCode::ReadOnly => JsonValue::Null,
+            Err(e) => return Err(e),
+        };
+        Client::request_shutdown()?;
+        Ok(report)
+    })
+}
+
+/// Free a string returned through an `out` parameter
+///
+/// # Safety
+/// `s` must be NULL or a string returned by this library, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rhythm_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,325 @@
+//! The `Client` operations `rhythm_call` runs, by method name
+//!
+//! Arguments are a JSON object whose fields match the `Client` method's
+//! parameters; optional ones may be left out. Results are the method's
+//! return value as JSON, `null` for none.
+
+use anyhow::Result;
+use rhythm_core::worker::{TaskCompletion, TaskOutcome};
+use rhythm_core::{
+    Client, ConcurrencyKey, ConcurrencyPolicy, CreateExecutionParams, RhythmError,
+    ScheduleExecutionParams,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExecutionId {
+    execution_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClaimArgs {
+    queues: Vec<String>,
+    max_count: usize,
+    #[serde(default)]
+    worker_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompleteArgs {
+    execution_id: String,
+    #[serde(default)]
+    result: JsonValue,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FailArgs {
+    execution_id: String,
+    error: JsonValue,
+    #[serde(default)]
+    retry: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompleteManyArgs {
+    /// Each is `{execution_id, result}` or `{execution_id, error, retry}`
+    completions: Vec<CompletionArg>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompletionArg {
+    execution_id: String,
+    #[serde(default)]
+    result: JsonValue,
+    #[serde(default)]
+    error: Option<JsonValue>,
+    #[serde(default)]
+    retry: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeartbeatArgs {
+    execution_id: String,
+    #[serde(default)]
+    worker_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CancelArgs {
+    execution_id: String,
+    reason: String,
+    #[serde(default)]
+    finish_running_tasks: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TagArgs {
+    execution_id: String,
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StartWorkflowArgs {
+    workflow_name: String,
+    #[serde(default)]
+    inputs: JsonValue,
+    #[serde(default)]
+    queue: Option<String>,
+    #[serde(default)]
+    namespace: Option<String>,
+    /// For `start_workflow` only, with `concurrency_policy`
+    #[serde(default)]
+    concurrency_key: Option<String>,
+    #[serde(default)]
+    concurrency_policy: ConcurrencyPolicy,
+    /// For `run_workflow_sync` only
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkflowResultArgs {
+    execution_id: String,
+    #[serde(default)]
+    wait: Option<bool>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegisterWorkflowArgs {
+    name: String,
+    source: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkflowId {
+    workflow_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignalArgs {
+    workflow_id: String,
+    signal_name: String,
+    #[serde(default)]
+    payload: JsonValue,
+    #[serde(default)]
+    queue: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueueStatsArgs {
+    #[serde(default)]
+    window_minutes: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WaitIdleArgs {
+    timeout_ms: u64,
+}
+
+/// Read `args` as the arguments of a method, `null` as `{}`
+fn parse<T: DeserializeOwned>(args: JsonValue) -> Result<T> {
+    let args = if args.is_null() { json!({}) } else { args };
+    Ok(serde_json::from_value(args)?)
+}
+
+/// Run `method` with `args`
+pub(crate) async fn call(method: &str, args: JsonValue) -> Result<JsonValue> {
+    let value = match method {
+        /* Execution lifecycle */
+        "create_execution" => {
+            let params: CreateExecutionParams = parse(args)?;
+            json!(Client::create_execution(params).await?)
+        }
+        "get_execution" => {
+            let args: ExecutionId = parse(args)?;
+            json!(Client::get_execution(args.execution_id).await?)
+        }
+        "get_execution_history" => {
+            let args: ExecutionId = parse(args)?;
+            json!(Client::get_execution_history(args.execution_id).await?)
+        }
+        "get_execution_logs" => {
+            let args: ExecutionId = parse(args)?;
+            json!(Client::get_execution_logs(args.execution_id).await?)
+        }
+        "claim_executions" => {
+            let args: ClaimArgs = parse(args)?;
+            Client::claim_executions(args.worker_id, args.queues, args.max_count).await?
+        }
+        "complete_execution" => {
+            let args: CompleteArgs = parse(args)?;
+            Client::complete_execution(args.execution_id, args.result).await?;
+            JsonValue::Null
+        }
+        "fail_execution" => {
+            let args: FailArgs = parse(args)?;
+            Client::fail_execution(args.execution_id, args.error, args.retry).await?;
+            JsonValue::Null
+        }
+        "complete_executions" => {
+            let args: CompleteManyArgs = parse(args)?;
+            let completions = args
+                .completions
+                .into_iter()
+                .map(|c| TaskCompletion {
+                    execution_id: c.execution_id,
+                    outcome: match c.error {
+                        Some(error) => TaskOutcome::Fail {
+                            error,
+                            retry: c.retry,
+                        },
+                        None => TaskOutcome::Complete(c.result),
+                    },
+                })
+                .collect();
+            Client::complete_executions(completions).await?;
+            JsonValue::Null
+        }
+        "heartbeat" => {
+            let args: HeartbeatArgs = parse(args)?;
+            json!(Client::heartbeat(args.execution_id, args.worker_id).await?)
+        }
+        "cancel_workflow" => {
+            let args: CancelArgs = parse(args)?;
+            json!(
+                Client::cancel_workflow(args.execution_id, args.reason, args.finish_running_tasks)
+                    .await?
+            )
+        }
+        "pause_execution" => {
+            let args: ExecutionId = parse(args)?;
+            json!(Client::pause_execution(args.execution_id).await?)
+        }
+        "resume_execution" => {
+            let args: ExecutionId = parse(args)?;
+            json!(Client::resume_execution(args.execution_id).await?)
+        }
+        "tag_execution" => {
+            let args: TagArgs = parse(args)?;
+            Client::tag_execution(args.execution_id, args.tags).await?;
+            JsonValue::Null
+        }
+        "get_execution_tags" => {
+            let args: ExecutionId = parse(args)?;
+            json!(Client::get_execution_tags(args.execution_id).await?)
+        }
+        "get_queue_stats" => {
+            let args: QueueStatsArgs = parse(args)?;
+            json!(Client::get_queue_stats(args.window_minutes).await?)
+        }
+
+        /* Workflows */
+        "start_workflow" => {
+            let args: StartWorkflowArgs = parse(args)?;
+            let concurrency = args.concurrency_key.map(|key| ConcurrencyKey {
+                key,
+                policy: args.concurrency_policy,
+            });
+            json!(
+                Client::start_workflow(
+                    args.workflow_name,
+                    args.inputs,
+                    args.queue,
+                    args.namespace,
+                    concurrency
+                )
+                .await?
+            )
+        }
+        "run_workflow_sync" => {
+            let args: StartWorkflowArgs = parse(args)?;
+            let timeout = Duration::from_millis(args.timeout_ms.unwrap_or(30_000));
+            Client::run_workflow_sync(
+                args.workflow_name,
+                args.inputs,
+                args.queue,
+                args.namespace,
+                timeout,
+            )
+            .await?
+        }
+        "get_workflow_result" => {
+            let args: WorkflowResultArgs = parse(args)?;
+            let timeout = Duration::from_millis(args.timeout_ms.unwrap_or(30_000));
+            json!(
+                Client::get_workflow_result(args.execution_id, args.wait.unwrap_or(true), timeout)
+                    .await?
+            )
+        }
+        "schedule_execution" => {
+            let params: ScheduleExecutionParams = parse(args)?;
+            json!(Client::schedule_execution(params).await?)
+        }
+        "register_workflow" => {
+            let args: RegisterWorkflowArgs = parse(args)?;
+            json!(Client::register_workflow(args.name, args.source).await?)
+        }
+        "get_workflow_tasks" => {
+            let args: WorkflowId = parse(args)?;
+            json!(Client::get_workflow_tasks(args.workflow_id).await?)
+        }
+        "send_signal" => {
+            let args: SignalArgs = parse(args)?;
+            Client::send_signal(args.workflow_id, args.signal_name, args.payload, args.queue)
+                .await?;
+            JsonValue::Null
+        }
+
+        /* Worker */
+        "begin_shutdown" => {
+            Client::begin_shutdown()?;
+            JsonValue::Null
+        }
+        "wait_idle" => {
+            let args: WaitIdleArgs = parse(args)?;
+            json!(Client::wait_idle(Duration::from_millis(args.timeout_ms)).await?)
+        }
+
+        _ => {
+            return Err(RhythmError::Validation(format!("Unknown method '{}'", method)).into());
+        }
+    };
+    Ok(value)
+}
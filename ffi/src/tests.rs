@@ -0,0 +1,106 @@
+//! Tests of the C ABI that need no database
+
+use super::*;
+use std::ptr;
+
+/// Call `f` with an `out` pointer and take back the status and JSON it set
+fn take(f: impl FnOnce(*mut *mut c_char) -> i32) -> (i32, JsonValue) {
+    let mut out: *mut c_char = ptr::null_mut();
+    let code = f(&mut out);
+    assert!(!out.is_null(), "out was not set");
+    let text = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+    unsafe { rhythm_string_free(out) };
+    (code, serde_json::from_str(&text).unwrap())
+}
+
+fn call(method: &str, args: &str) -> (i32, JsonValue) {
+    let method = CString::new(method).unwrap();
+    let args = CString::new(args).unwrap();
+    take(|out| unsafe { rhythm_call(method.as_ptr(), args.as_ptr(), out) })
+}
+
+#[test]
+fn test_unknown_method_is_a_validation_error() {
+    let (code, body) = call("launch_rockets", "{}");
+
+    assert_eq!(code, RHYTHM_ERR_VALIDATION);
+    assert_eq!(body["code"], "validation");
+    assert_eq!(body["message"], "Unknown method 'launch_rockets'");
+}
+
+#[test]
+fn test_bad_arguments_are_serialization_errors() {
+    for args in ["{not json", r#"{"execution_id": 42}"#, r#"{"id": "x"}"#] {
+        let (code, body) = call("get_execution", args);
+
+        assert_eq!(code, RHYTHM_ERR_SERIALIZATION, "{}", args);
+        assert_eq!(body["code"], "serialization");
+    }
+}
+
+#[test]
+fn test_calls_before_init_fail_as_internal() {
+    let (code, body) = call("get_execution", r#"{"execution_id": "e-1"}"#);
+
+    assert_eq!(code, RHYTHM_ERR_INTERNAL);
+    assert_eq!(body["code"], "internal");
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .contains("not initialized"));
+}
+
+#[test]
+fn test_null_method_and_out() {
+    let (code, body) = take(|out| unsafe { rhythm_call(ptr::null(), ptr::null(), out) });
+    assert_eq!(code, RHYTHM_ERR_VALIDATION);
+    assert_eq!(body["message"], "method is NULL");
+
+    // A NULL out drops the result, and NULL frees are no-ops
+    let method = CString::new("nope").unwrap();
+    let code = unsafe { rhythm_call(method.as_ptr(), ptr::null(), ptr::null_mut()) };
+    assert_eq!(code, RHYTHM_ERR_VALIDATION);
+    unsafe { rhythm_string_free(ptr::null_mut()) };
+}
+
+#[test]
+fn test_init_rejects_unknown_options() {
+    let options = CString::new(r#"{"database_ulr": "postgres://"}"#).unwrap();
+    let (code, body) = take(|out| unsafe { rhythm_init(options.as_ptr(), out) });
+
+    assert_eq!(code, RHYTHM_ERR_SERIALIZATION);
+    assert!(body["message"].as_str().unwrap().contains("database_ulr"));
+}
+
+#[test]
+fn test_shutdown_before_init_does_nothing() {
+    let (code, body) = take(|out| unsafe { rhythm_shutdown(100, out) });
+
+    assert_eq!(code, RHYTHM_OK);
+    assert_eq!(body, JsonValue::Null);
+}
+
+#[test]
+fn test_every_error_code_has_a_distinct_status() {
+    let codes = [
+        ErrorCode::NotFound,
+        ErrorCode::Conflict,
+        ErrorCode::Validation,
+        ErrorCode::Serialization,
+        ErrorCode::Database,
+        ErrorCode::QuotaExceeded,
+        ErrorCode::QueueFull,
+        ErrorCode::ReadOnly,
+        ErrorCode::Unauthenticated,
+        ErrorCode::PermissionDenied,
+        ErrorCode::WorkflowTimeout,
+        ErrorCode::WorkflowFailed,
+        ErrorCode::Internal,
+    ];
+    let mut statuses: Vec<i32> = codes.iter().map(|c| status(*c)).collect();
+    statuses.sort();
+    statuses.dedup();
+
+    assert_eq!(statuses.len(), codes.len());
+    assert!(!statuses.contains(&RHYTHM_OK));
+}
@@ -3,11 +3,20 @@
 //! This module provides thin PyO3 wrappers around the Client interface.
 //! All functions delegate to `rhythm_core::Client` for a stable, language-agnostic API.
 
+use ::rhythm_core::config::TaskConfig;
+use ::rhythm_core::flow_test::Stub;
+use ::rhythm_core::testing::WorkflowTestHarness;
+use ::rhythm_core::worker::{HostTask, TaskDispatcher, TaskOutcome};
 use ::rhythm_core::{
-    Client, CreateExecutionParams, ExecutionType, ScheduleExecutionParams, WorkflowFile,
+    payload, Client, ConcurrencyKey, CreateExecutionParams, CreateWebhookParams, ErrorCode,
+    Execution, ExecutionFilters, ExecutionType, PayloadEncoding, ScheduleExecutionParams,
+    WorkflowFile,
 };
+use chrono::{DateTime, Utc};
 use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::sync::OnceLock;
 
 /// Global shared Tokio runtime
@@ -31,17 +40,358 @@ fn init_runtime() -> PyResult<()> {
     Ok(())
 }
 
+/* ===================== Errors ===================== */
+
+pyo3::create_exception!(
+    rhythm_core,
+    RhythmError,
+    pyo3::exceptions::PyRuntimeError,
+    "Base class of errors from core; `code` names the kind of error"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    NotFoundError,
+    RhythmError,
+    "The execution or other record does not exist"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    ConflictError,
+    RhythmError,
+    "The record is not in a state that allows the operation"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    ValidationError,
+    RhythmError,
+    "The request itself is invalid"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    SerializationError,
+    RhythmError,
+    "A payload or stored state could not be encoded or decoded"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    DatabaseError,
+    RhythmError,
+    "The database operation failed"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    QuotaExceededError,
+    RhythmError,
+    "Creating the execution would exceed a namespace quota"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    QueueFullError,
+    RhythmError,
+    "The execution's queue is over its max depth"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    UnauthenticatedError,
+    RhythmError,
+    "No API key was presented, or the key is not accepted"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    PermissionDeniedError,
+    RhythmError,
+    "The API key has no scope for the operation"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    ReadOnlyError,
+    RhythmError,
+    "Rhythm was initialized read-only and the operation would change state"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    WorkflowTimeoutError,
+    pyo3::exceptions::PyTimeoutError,
+    "A synchronously run workflow did not finish in time; it keeps running"
+);
+
+pyo3::create_exception!(
+    rhythm_core,
+    WorkflowFailedError,
+    RhythmError,
+    "A synchronously run workflow failed or expired"
+);
+
+/// Map an error from core to the exception class for its `ErrorCode`,
+/// with the code set as the exception's `code` attribute
+fn client_error(e: anyhow::Error) -> PyErr {
+    let code = ErrorCode::of(&e);
+    let message = e.to_string();
+    let err = match code {
+        ErrorCode::NotFound => NotFoundError::new_err(message),
+        ErrorCode::Conflict => ConflictError::new_err(message),
+        ErrorCode::Validation => ValidationError::new_err(message),
+        ErrorCode::Serialization => SerializationError::new_err(message),
+        ErrorCode::Database => DatabaseError::new_err(message),
+        ErrorCode::QuotaExceeded => QuotaExceededError::new_err(message),
+        ErrorCode::QueueFull => QueueFullError::new_err(message),
+        ErrorCode::ReadOnly => ReadOnlyError::new_err(message),
+        ErrorCode::Unauthenticated => UnauthenticatedError::new_err(message),
+        ErrorCode::PermissionDenied => PermissionDeniedError::new_err(message),
+        ErrorCode::WorkflowTimeout => WorkflowTimeoutError::new_err(message),
+        ErrorCode::WorkflowFailed => WorkflowFailedError::new_err(message),
+        ErrorCode::Internal => RhythmError::new_err(message),
+    };
+    Python::with_gil(|py| {
+        // Best effort: the message is what matters if this fails
+        let _ = err.value(py).setattr("code", code.as_str());
+    });
+    err
+}
+
+/* ===================== Types ===================== */
+
+/// Convert a JSON value into native Python objects without a string round-trip
+fn json_to_py(py: Python, value: &JsonValue) -> PyResult<PyObject> {
+    Ok(match value {
+        JsonValue::Null => py.None(),
+        JsonValue::Bool(b) => b.into_pyobject(py)?.to_owned().into_any().unbind(),
+        JsonValue::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                i.into_pyobject(py)?.into_any().unbind()
+            } else if let Some(u) = n.as_u64() {
+                u.into_pyobject(py)?.into_any().unbind()
+            } else {
+                n.as_f64()
+                    .unwrap_or(f64::NAN)
+                    .into_pyobject(py)?
+                    .into_any()
+                    .unbind()
+            }
+        }
+        JsonValue::String(s) => s.into_pyobject(py)?.into_any().unbind(),
+        JsonValue::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_any().unbind()
+        }
+        JsonValue::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+/// Payload argument accepted from Python: JSON text or an encoded byte buffer
+#[derive(FromPyObject)]
+enum PayloadArg<'py> {
+    Bytes(Bound<'py, PyBytes>),
+    Text(String),
+}
+
+fn parse_encoding(encoding: Option<&str>) -> PyResult<PayloadEncoding> {
+    match encoding.map(str::parse::<PayloadEncoding>) {
+        Some(Ok(encoding)) => Ok(encoding),
+        Some(Err(e)) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            e.to_string(),
+        )),
+        None => Ok(PayloadEncoding::default()),
+    }
+}
+
+/// Decode a payload argument, reading `bytes` in place without copying
+fn decode_payload(arg: PayloadArg, encoding: Option<&str>, what: &str) -> PyResult<JsonValue> {
+    let encoding = parse_encoding(encoding)?;
+    let decoded = match &arg {
+        PayloadArg::Bytes(bytes) => payload::decode(bytes.as_bytes(), encoding),
+        PayloadArg::Text(text) => payload::decode(text.as_bytes(), PayloadEncoding::Json),
+    };
+    decoded.map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid {}: {:#}", what, e))
+    })
+}
+
+/// The concurrency key a workflow is started with, if any
+fn concurrency_arg(key: Option<String>, policy: Option<&str>) -> PyResult<Option<ConcurrencyKey>> {
+    let Some(key) = key else {
+        return Ok(None);
+    };
+    let policy = match policy {
+        Some(policy) => serde_json::from_value(JsonValue::from(policy)).map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Invalid concurrency policy '{}': expected reject, queue or replace_existing",
+                policy
+            ))
+        })?,
+        None => Default::default(),
+    };
+    Ok(Some(ConcurrencyKey { key, policy }))
+}
+
+fn encode_payload<'py>(
+    py: Python<'py>,
+    value: &JsonValue,
+    encoding: &str,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let bytes = payload::encode(value, parse_encoding(Some(encoding))?)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+/// Encodings the extension accepts for `bytes` payloads, most preferred first
+#[pyfunction]
+fn supported_payload_encodings() -> Vec<&'static str> {
+    PayloadEncoding::SUPPORTED
+        .iter()
+        .map(PayloadEncoding::as_str)
+        .collect()
+}
+
+/// Choose the payload encoding to use given the encodings the host can produce
+#[pyfunction]
+fn negotiate_payload_encoding(offered: Vec<String>) -> &'static str {
+    PayloadEncoding::negotiate(&offered).as_str()
+}
+
+/// Typed execution record returned across the FFI boundary
+///
+/// `type` and `status` are the labels of the core enums, so they always match
+/// the values of the Python `ExecutionType` / `ExecutionStatus` enums.
+#[pyclass(name = "Execution", module = "rhythm_core", frozen)]
+struct PyExecution {
+    inner: Execution,
+}
+
+#[pymethods]
+impl PyExecution {
+    #[getter]
+    fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    #[getter(r#type)]
+    fn exec_type(&self) -> &'static str {
+        self.inner.exec_type.as_str()
+    }
+
+    #[getter]
+    fn target_name(&self) -> &str {
+        &self.inner.target_name
+    }
+
+    #[getter]
+    fn queue(&self) -> &str {
+        &self.inner.queue
+    }
+
+    #[getter]
+    fn namespace(&self) -> &str {
+        &self.inner.namespace
+    }
+
+    #[getter]
+    fn status(&self) -> &'static str {
+        self.inner.status.as_str()
+    }
+
+    #[getter]
+    fn is_terminal(&self) -> bool {
+        self.inner.status.is_terminal()
+    }
+
+    #[getter]
+    fn inputs(&self, py: Python) -> PyResult<PyObject> {
+        json_to_py(py, &self.inner.inputs)
+    }
+
+    #[getter]
+    fn output(&self, py: Python) -> PyResult<PyObject> {
+        match &self.inner.output {
+            Some(output) => json_to_py(py, output),
+            None => Ok(py.None()),
+        }
+    }
+
+    #[getter]
+    fn attempt(&self) -> i32 {
+        self.inner.attempt
+    }
+
+    #[getter]
+    fn parent_workflow_id(&self) -> Option<&str> {
+        self.inner.parent_workflow_id.as_deref()
+    }
+
+    #[getter]
+    fn created_at(&self) -> DateTime<Utc> {
+        self.inner.created_at
+    }
+
+    #[getter]
+    fn completed_at(&self) -> Option<DateTime<Utc>> {
+        self.inner.completed_at
+    }
+
+    /// Inputs encoded as bytes, for hosts that decode large payloads natively
+    #[pyo3(signature = (encoding="json"))]
+    fn inputs_bytes<'py>(&self, py: Python<'py>, encoding: &str) -> PyResult<Bound<'py, PyBytes>> {
+        encode_payload(py, &self.inner.inputs, encoding)
+    }
+
+    /// Output encoded as bytes, or `None` if the execution has no output yet
+    #[pyo3(signature = (encoding="json"))]
+    fn output_bytes<'py>(
+        &self,
+        py: Python<'py>,
+        encoding: &str,
+    ) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        self.inner
+            .output
+            .as_ref()
+            .map(|output| encode_payload(py, output, encoding))
+            .transpose()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Execution(id={:?}, type={:?}, target_name={:?}, status={:?})",
+            self.inner.id,
+            self.inner.exec_type.as_str(),
+            self.inner.target_name,
+            self.inner.status.as_str()
+        )
+    }
+}
+
 /* ===================== System ===================== */
 
 /// Initialize Rhythm with configuration options
 #[pyfunction]
-#[pyo3(signature = (database_url=None, config_path=None, auto_migrate=true, workflows_json=None))]
+#[pyo3(signature = (database_url=None, config_path=None, auto_migrate=true, workflows_json=None, read_only=false, tasks_json=None))]
 fn initialize_sync(
     py: Python,
     database_url: Option<String>,
     config_path: Option<String>,
     auto_migrate: bool,
     workflows_json: Option<String>,
+    read_only: bool,
+    tasks_json: Option<String>,
 ) -> PyResult<()> {
     let runtime = get_runtime();
 
@@ -90,6 +440,14 @@ fn initialize_sync(
         Vec::new()
     };
 
+    // Task settings from decorators, e.g. @task(retries=5)
+    let tasks: Vec<TaskConfig> = match tasks_json {
+        Some(json) => serde_json::from_str(&json).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid tasks JSON: {}", e))
+        })?,
+        None => Vec::new(),
+    };
+
     // Release GIL while doing DB initialization
     py.allow_threads(|| {
         runtime.block_on(Client::initialize(
@@ -97,6 +455,8 @@ fn initialize_sync(
             config_path,
             auto_migrate,
             workflows,
+            tasks,
+            read_only,
         ))
     })
     .map_err(|e| {
@@ -109,21 +469,31 @@ fn initialize_sync(
 
 /// Create an execution
 #[pyfunction]
-#[pyo3(signature = (exec_type, target_name, queue, inputs, parent_workflow_id=None, id=None))]
+#[pyo3(signature = (exec_type, target_name, queue, inputs, parent_workflow_id=None, id=None, encoding=None, namespace=None, ttl_seconds=None, retry_policy_json=None, priority=None, idempotency_key=None, traceparent=None, tags=None))]
+#[allow(clippy::too_many_arguments)]
 fn create_execution_sync(
     py: Python,
     exec_type: String,
     target_name: String,
     queue: String,
-    inputs: String,
+    inputs: PayloadArg,
     parent_workflow_id: Option<String>,
     id: Option<String>,
+    encoding: Option<&str>,
+    namespace: Option<String>,
+    ttl_seconds: Option<u64>,
+    retry_policy_json: Option<String>,
+    priority: Option<i32>,
+    idempotency_key: Option<String>,
+    traceparent: Option<String>,
+    tags: Option<HashMap<String, String>>,
 ) -> PyResult<String> {
     let runtime = get_runtime();
 
     let exec_type = match exec_type.as_str() {
         "task" => ExecutionType::Task,
         "workflow" => ExecutionType::Workflow,
+        "external" => ExecutionType::External,
         _ => {
             return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
                 "Invalid execution type",
@@ -131,8 +501,17 @@ fn create_execution_sync(
         }
     };
 
-    let inputs: JsonValue = serde_json::from_str(&inputs)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    let inputs = decode_payload(inputs, encoding, "inputs")?;
+
+    let retry_policy = match retry_policy_json {
+        Some(json) => Some(serde_json::from_str(&json).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Invalid retry policy JSON: {}",
+                e
+            ))
+        })?),
+        None => None,
+    };
 
     let params = CreateExecutionParams {
         id,
@@ -141,11 +520,18 @@ fn create_execution_sync(
         queue,
         inputs,
         parent_workflow_id,
+        namespace,
+        ttl_seconds,
+        retry_policy,
+        priority,
+        idempotency_key,
+        traceparent,
+        tags: tags.unwrap_or_default(),
     };
 
     // Release GIL while doing DB write
     py.allow_threads(|| runtime.block_on(Client::create_execution(params)))
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        .map_err(client_error)
 }
 
 /// Run cooperative worker loop - blocks until task needs host execution
@@ -159,16 +545,155 @@ fn run_cooperative_worker_loop(py: Python) -> PyResult<String> {
     // Release GIL while running the worker loop
     let result = py
         .allow_threads(|| runtime.block_on(Client::run_cooperative_worker_loop()))
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+        .map_err(client_error)?;
 
     Ok(result.to_string())
 }
 
+/// Claim up to `max_count` tasks at once, as a JSON list
+#[pyfunction]
+#[pyo3(signature = (queues, max_count, worker_id=None))]
+fn claim_executions_sync(
+    py: Python,
+    queues: Vec<String>,
+    max_count: usize,
+    worker_id: Option<String>,
+) -> PyResult<String> {
+    let runtime = get_runtime();
+
+    // Release GIL while claiming
+    let result = py
+        .allow_threads(|| {
+            runtime.block_on(Client::claim_executions(worker_id, queues, max_count))
+        })
+        .map_err(client_error)?;
+
+    Ok(result.to_string())
+}
+
+/// Runs tasks by calling the Python `dispatch` callable
+///
+/// `dispatch(execution_id, target_name, inputs_json, traceparent)` returns a JSON string, either
+/// `{"result": ...}` or `{"error": {...}, "retry": bool}`.
+struct PyDispatcher {
+    dispatch: PyObject,
+    /// Exception raised by a signal handler, re-raised once the loop stops
+    interrupt: Option<PyErr>,
+}
+
+impl PyDispatcher {
+    fn call(&self, task: &HostTask) -> PyResult<TaskOutcome> {
+        let response: String = Python::with_gil(|py| {
+            self.dispatch
+                .call1(
+                    py,
+                    (
+                        &task.execution_id,
+                        &task.target_name,
+                        task.inputs.to_string(),
+                        &task.traceparent,
+                    ),
+                )?
+                .extract(py)
+        })?;
+        let mut response: JsonValue = serde_json::from_str(&response).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Invalid dispatch response: {}",
+                e
+            ))
+        })?;
+        Ok(match response.get_mut("error") {
+            Some(error) => TaskOutcome::Fail {
+                error: error.take(),
+                retry: response["retry"].as_bool().unwrap_or(false),
+            },
+            None => TaskOutcome::Complete(response["result"].take()),
+        })
+    }
+}
+
+impl TaskDispatcher for PyDispatcher {
+    fn dispatch(&mut self, task: HostTask) -> TaskOutcome {
+        self.call(&task).unwrap_or_else(|e| TaskOutcome::Fail {
+            error: serde_json::json!({
+                "message": format!("Task dispatch failed: {}", e),
+                "type": "DispatchError",
+            }),
+            retry: false,
+        })
+    }
+
+    fn check_interrupt(&mut self) -> anyhow::Result<()> {
+        // Runs Python signal handlers, which can't run while the GIL is released
+        if let Err(e) = Python::with_gil(|py| py.check_signals()) {
+            self.interrupt = Some(e);
+            anyhow::bail!("Interrupted");
+        }
+        Ok(())
+    }
+}
+
+/// Run a worker on `queues` until shutdown, calling `dispatch` for each task
+///
+/// Claiming, workflows, heartbeats and recording task outcomes are all done by
+/// core. Exceptions raised by signal handlers (e.g. KeyboardInterrupt) stop
+/// the loop and propagate.
+#[pyfunction]
+fn run_worker(py: Python, queues: Vec<String>, dispatch: PyObject) -> PyResult<()> {
+    let runtime = get_runtime();
+    let mut dispatcher = PyDispatcher {
+        dispatch,
+        interrupt: None,
+    };
+
+    // The GIL is only held while calling back into Python
+    let result = py.allow_threads(|| runtime.block_on(Client::run_worker(queues, &mut dispatcher)));
+    if let Some(interrupt) = dispatcher.interrupt {
+        return Err(interrupt);
+    }
+    result.map_err(client_error)
+}
+
 /// Request graceful shutdown of worker loops
 #[pyfunction]
 fn request_shutdown() -> PyResult<()> {
-    Client::request_shutdown()
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+    Client::request_shutdown().map_err(client_error)
+}
+
+/// Stop claiming new work, leaving tasks already handed out running
+#[pyfunction]
+fn begin_shutdown() -> PyResult<()> {
+    Client::begin_shutdown().map_err(client_error)
+}
+
+/// Wait up to `timeout_secs` for in-flight tasks to report; returns whether
+/// the worker went idle
+#[pyfunction]
+fn wait_idle_sync(py: Python, timeout_secs: f64) -> PyResult<bool> {
+    let runtime = get_runtime();
+    let timeout = std::time::Duration::try_from_secs_f64(timeout_secs)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+
+    // Release GIL so task threads can report while we wait
+    py.allow_threads(|| runtime.block_on(Client::wait_idle(timeout)))
+        .map_err(client_error)
+}
+
+/// Drain the worker, releasing tasks still running after `timeout_secs`
+#[pyfunction]
+fn drain_worker_sync(py: Python, timeout_secs: f64) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+    let timeout = std::time::Duration::try_from_secs_f64(timeout_secs)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+
+    // Release GIL so task threads can report while we wait
+    let report = py
+        .allow_threads(|| runtime.block_on(Client::drain_worker(timeout)))
+        .map_err(client_error)?;
+
+    let report = serde_json::to_value(report)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &report)
 }
 
 /// Start the internal worker (scheduler queue processor)
@@ -178,68 +703,429 @@ fn request_shutdown() -> PyResult<()> {
 fn start_internal_worker() -> PyResult<()> {
     let runtime = get_runtime();
     let _guard = runtime.enter();
-    Client::start_internal_worker()
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+    Client::start_internal_worker().map_err(client_error)
 }
 
 /// Complete an execution
 #[pyfunction]
-fn complete_execution_sync(py: Python, execution_id: String, result: String) -> PyResult<()> {
+#[pyo3(signature = (execution_id, result, encoding=None))]
+fn complete_execution_sync(
+    py: Python,
+    execution_id: String,
+    result: PayloadArg,
+    encoding: Option<&str>,
+) -> PyResult<()> {
     let runtime = get_runtime();
 
-    let result: JsonValue = serde_json::from_str(&result)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    let result = decode_payload(result, encoding, "result")?;
 
     // Release GIL while doing DB write
     py.allow_threads(|| runtime.block_on(Client::complete_execution(execution_id, result)))
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        .map_err(client_error)
 }
 
 /// Fail an execution
 #[pyfunction]
+#[pyo3(signature = (execution_id, error, retry, encoding=None))]
 fn fail_execution_sync(
     py: Python,
     execution_id: String,
-    error: String,
-    _retry: bool,
+    error: PayloadArg,
+    retry: bool,
+    encoding: Option<&str>,
 ) -> PyResult<()> {
     let runtime = get_runtime();
 
-    let error: JsonValue = serde_json::from_str(&error)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    let error = decode_payload(error, encoding, "error")?;
 
     // Release GIL while doing DB write
-    py.allow_threads(|| runtime.block_on(Client::fail_execution(execution_id, error)))
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+    py.allow_threads(|| runtime.block_on(Client::fail_execution(execution_id, error, retry)))
+        .map_err(client_error)
+}
+
+/// Cancel a workflow and the executions it started
+#[pyfunction]
+#[pyo3(signature = (execution_id, reason, finish_running_tasks=false))]
+fn cancel_workflow_sync(
+    py: Python,
+    execution_id: String,
+    reason: String,
+    finish_running_tasks: bool,
+) -> PyResult<bool> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| {
+        runtime.block_on(Client::cancel_workflow(
+            execution_id,
+            reason,
+            finish_running_tasks,
+        ))
+    })
+    .map_err(client_error)
+}
+
+/// Pause a workflow, holding its queued work until it is resumed
+#[pyfunction]
+fn pause_execution_sync(py: Python, execution_id: String) -> PyResult<bool> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::pause_execution(execution_id)))
+        .map_err(client_error)
+}
+
+/// Resume a paused workflow
+#[pyfunction]
+fn resume_execution_sync(py: Python, execution_id: String) -> PyResult<bool> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::resume_execution(execution_id)))
+        .map_err(client_error)
+}
+
+/// Add tags to an execution, replacing any with the same keys
+#[pyfunction]
+fn tag_execution_sync(
+    py: Python,
+    execution_id: String,
+    tags: HashMap<String, String>,
+) -> PyResult<()> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::tag_execution(execution_id, tags)))
+        .map_err(client_error)
+}
+
+/// Get an execution's tags
+#[pyfunction]
+fn get_execution_tags_sync(py: Python, execution_id: String) -> PyResult<HashMap<String, String>> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    py.allow_threads(|| runtime.block_on(Client::get_execution_tags(execution_id)))
+        .map_err(client_error)
+}
+
+/// Record that a task is still running and extend its claim
+#[pyfunction]
+#[pyo3(signature = (execution_id, worker_id=None))]
+fn heartbeat_sync(py: Python, execution_id: String, worker_id: Option<String>) -> PyResult<bool> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::heartbeat(execution_id, worker_id)))
+        .map_err(client_error)
+}
+
+/// Record a partial result of a running task
+#[pyfunction]
+#[pyo3(signature = (execution_id, chunk, encoding=None))]
+fn emit_partial_result_sync(
+    py: Python,
+    execution_id: String,
+    chunk: PayloadArg,
+    encoding: Option<&str>,
+) -> PyResult<()> {
+    let runtime = get_runtime();
+
+    let chunk = decode_payload(chunk, encoding, "chunk")?;
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::emit_partial_result(execution_id, chunk)))
+        .map_err(client_error)
+}
+
+/// Write a message to an execution's logs
+#[pyfunction]
+#[pyo3(signature = (execution_id, level, message, data=None))]
+fn append_log_sync(
+    py: Python,
+    execution_id: String,
+    level: String,
+    message: String,
+    data: Option<PayloadArg>,
+) -> PyResult<()> {
+    let runtime = get_runtime();
+
+    let level = parse_label(level, "log level")?;
+    let data = data
+        .map(|data| decode_payload(data, None, "data"))
+        .transpose()?;
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::append_log(execution_id, level, message, data)))
+        .map_err(client_error)
 }
 
 /// Get execution by ID
 #[pyfunction]
-fn get_execution_sync(py: Python, execution_id: String) -> PyResult<Option<String>> {
+fn get_execution_sync(py: Python, execution_id: String) -> PyResult<Option<PyExecution>> {
     let runtime = get_runtime();
 
     // Release GIL while doing DB query
     let result = py
         .allow_threads(|| runtime.block_on(Client::get_execution(execution_id)))
+        .map_err(client_error)?;
+
+    Ok(result.map(|inner| PyExecution { inner }))
+}
+
+/// Get an execution's history as a list of dicts, oldest event first
+#[pyfunction]
+fn get_execution_history_sync(py: Python, execution_id: String) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let events = py
+        .allow_threads(|| runtime.block_on(Client::get_execution_history(execution_id)))
+        .map_err(client_error)?;
+
+    let events = serde_json::to_value(events)
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &events)
+}
+
+/// Get an execution's logs as a list of dicts, in the order they were written
+#[pyfunction]
+fn get_execution_logs_sync(py: Python, execution_id: String) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let logs = py
+        .allow_threads(|| runtime.block_on(Client::get_execution_logs(execution_id)))
+        .map_err(client_error)?;
+
+    let logs = serde_json::to_value(logs)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &logs)
+}
+
+/// Get executions whose status changed since a cursor
+///
+/// Returns `(executions, cursor, has_more)`; pass `cursor` to the next call.
+#[pyfunction]
+#[pyo3(signature = (since_cursor=None, limit=None))]
+fn get_changes_sync(
+    py: Python,
+    since_cursor: Option<String>,
+    limit: Option<i64>,
+) -> PyResult<(Vec<PyExecution>, String, bool)> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let changes = py
+        .allow_threads(|| runtime.block_on(Client::get_changes(since_cursor, limit)))
+        .map_err(client_error)?;
+
+    let executions = changes
+        .executions
+        .into_iter()
+        .map(|inner| PyExecution { inner })
+        .collect();
+
+    Ok((executions, changes.cursor, changes.has_more))
+}
+
+/// Parse a lowercase label like "failed" into one of core's enums
+fn parse_label<T: serde::de::DeserializeOwned>(label: String, what: &str) -> PyResult<T> {
+    serde_json::from_value(JsonValue::String(label.clone())).map_err(|_| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Unknown {}: {}", what, label))
+    })
+}
+
+/// List executions matching the filters, one page at a time
+///
+/// Returns `(executions, cursor)`; pass `cursor` to the next call, until it
+/// is None.
+#[pyfunction]
+#[pyo3(signature = (status=None, exec_type=None, queue=None, target_name=None, parent_workflow_id=None, namespace=None, tags=None, created_after=None, created_before=None, order=None, cursor=None, limit=None))]
+#[allow(clippy::too_many_arguments)]
+fn list_executions_sync(
+    py: Python,
+    status: Option<String>,
+    exec_type: Option<String>,
+    queue: Option<String>,
+    target_name: Option<String>,
+    parent_workflow_id: Option<String>,
+    namespace: Option<String>,
+    tags: Option<HashMap<String, String>>,
+    created_after: Option<DateTime<Utc>>,
+    created_before: Option<DateTime<Utc>>,
+    order: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> PyResult<(Vec<PyExecution>, Option<String>)> {
+    let runtime = get_runtime();
+
+    let filters = ExecutionFilters {
+        status: status.map(|s| parse_label(s, "status")).transpose()?,
+        exec_type: exec_type
+            .map(|t| parse_label(t, "execution type"))
+            .transpose()?,
+        queue,
+        target_name,
+        parent_workflow_id,
+        namespace,
+        tags: tags.unwrap_or_default(),
+        created_after,
+        created_before,
+        order: order
+            .map(|o| parse_label(o, "order"))
+            .transpose()?
+            .unwrap_or_default(),
+        after: cursor
+            .map(|c| c.parse())
+            .transpose()
+            .map_err(client_error)?,
+        limit,
+        offset: None,
+    };
+
+    // Release GIL while doing DB query
+    let page = py
+        .allow_threads(|| runtime.block_on(Client::list_executions(filters)))
+        .map_err(client_error)?;
+
+    let executions = page
+        .executions
+        .into_iter()
+        .map(|inner| PyExecution { inner })
+        .collect();
+
+    Ok((executions, page.cursor))
+}
+
+/// Current usage counted against a namespace's quotas, as a dict
+#[pyfunction]
+fn get_namespace_usage_sync(py: Python, namespace: String) -> PyResult<PyObject> {
+    let runtime = get_runtime();
 
-    Ok(result.map(|json| json.to_string()))
+    // Release GIL while doing DB query
+    let usage = py
+        .allow_threads(|| runtime.block_on(Client::get_namespace_usage(namespace)))
+        .map_err(client_error)?;
+
+    let usage = serde_json::to_value(usage)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &usage)
+}
+
+/// Current compliance of the configured claim latency SLOs, as a list of dicts
+#[pyfunction]
+fn get_slo_status_sync(py: Python) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let statuses = py
+        .allow_threads(|| runtime.block_on(Client::get_slo_status()))
+        .map_err(client_error)?;
+
+    let statuses = serde_json::to_value(statuses)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &statuses)
+}
+
+/// Load on each queue over the last `window_minutes`, as a list of dicts
+#[pyfunction]
+#[pyo3(signature = (window_minutes=None))]
+fn get_queue_stats_sync(py: Python, window_minutes: Option<u32>) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let stats = py
+        .allow_threads(|| runtime.block_on(Client::get_queue_stats(window_minutes)))
+        .map_err(client_error)?;
+
+    let stats = serde_json::to_value(stats)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &stats)
 }
 
 /* ===================== Workflow Operations ===================== */
 
 /// Start a workflow execution
 #[pyfunction]
-fn start_workflow_sync(py: Python, workflow_name: String, inputs_json: String) -> PyResult<String> {
+#[pyo3(signature = (workflow_name, inputs_json, encoding=None, namespace=None, concurrency_key=None, concurrency_policy=None))]
+fn start_workflow_sync(
+    py: Python,
+    workflow_name: String,
+    inputs_json: PayloadArg,
+    encoding: Option<&str>,
+    namespace: Option<String>,
+    concurrency_key: Option<String>,
+    concurrency_policy: Option<&str>,
+) -> PyResult<String> {
     let runtime = get_runtime();
 
-    let inputs: serde_json::Value = serde_json::from_str(&inputs_json).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid inputs JSON: {}", e))
-    })?;
+    let inputs = decode_payload(inputs_json, encoding, "inputs")?;
+    let concurrency = concurrency_arg(concurrency_key, concurrency_policy)?;
 
     // Release GIL while doing DB write
-    py.allow_threads(|| runtime.block_on(Client::start_workflow(workflow_name, inputs, None)))
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+    py.allow_threads(|| {
+        runtime.block_on(Client::start_workflow(
+            workflow_name,
+            inputs,
+            None,
+            namespace,
+            concurrency,
+        ))
+    })
+    .map_err(client_error)
+}
+
+/// Run a workflow in the express lane and wait for its output
+#[pyfunction]
+#[pyo3(signature = (workflow_name, inputs_json, timeout_secs, encoding=None, namespace=None))]
+fn run_workflow_sync(
+    py: Python,
+    workflow_name: String,
+    inputs_json: PayloadArg,
+    timeout_secs: f64,
+    encoding: Option<&str>,
+    namespace: Option<String>,
+) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    let inputs = decode_payload(inputs_json, encoding, "inputs")?;
+    let timeout = std::time::Duration::try_from_secs_f64(timeout_secs)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+
+    // Release GIL while waiting for the workflow
+    let output = py
+        .allow_threads(|| {
+            runtime.block_on(Client::run_workflow_sync(
+                workflow_name,
+                inputs,
+                None,
+                namespace,
+                timeout,
+            ))
+        })
+        .map_err(client_error)?;
+    json_to_py(py, &output)
+}
+
+/// Wait up to `timeout_secs` for a workflow to finish and return its output
+#[pyfunction]
+fn get_workflow_result_sync(
+    py: Python,
+    execution_id: String,
+    timeout_secs: f64,
+) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+    let timeout = std::time::Duration::try_from_secs_f64(timeout_secs)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+
+    // Release GIL while waiting for the workflow
+    let output = py
+        .allow_threads(|| {
+            runtime.block_on(Client::get_workflow_result(execution_id, true, timeout))
+        })
+        .map_err(client_error)?;
+    json_to_py(py, &output.unwrap_or(JsonValue::Null))
 }
 
 /// Get workflow child tasks
@@ -250,49 +1136,218 @@ fn get_workflow_tasks_sync(py: Python, workflow_id: String) -> PyResult<String>
     // Release GIL while doing DB query
     let tasks = py
         .allow_threads(|| runtime.block_on(Client::get_workflow_tasks(workflow_id)))
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+        .map_err(client_error)?;
 
     serde_json::to_string(&tasks)
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
 }
 
+/// Get the inputs schema a workflow declares, or None
+#[pyfunction]
+fn get_workflow_schema_sync(py: Python, name: String) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let schema = py
+        .allow_threads(|| runtime.block_on(Client::get_workflow_schema(name)))
+        .map_err(client_error)?;
+
+    let schema = serde_json::to_value(schema)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &schema)
+}
+
+/// Get a workflow's task graph as a dict
+#[pyfunction]
+fn analyze_workflow_sync(py: Python, name: String) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let graph = py
+        .allow_threads(|| runtime.block_on(Client::analyze_workflow(name)))
+        .map_err(client_error)?;
+
+    let graph = serde_json::to_value(graph)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &graph)
+}
+
+/// Run a workflow in memory against stubbed tasks and signals
+///
+/// Needs no database. Returns the run as a dict: `status`, `output` or
+/// `error`, `calls` and `suspensions`.
+#[pyfunction]
+fn run_workflow_test(
+    py: Python,
+    source: String,
+    stubs_json: String,
+    signals_json: String,
+    inputs_json: String,
+) -> PyResult<PyObject> {
+    fn parse<T: serde::de::DeserializeOwned>(json: &str, what: &str) -> PyResult<T> {
+        serde_json::from_str(json).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid {}: {}", what, e))
+        })
+    }
+    let stubs: Vec<Stub> = parse(&stubs_json, "task stubs")?;
+    let signals: Vec<(String, JsonValue)> = parse(&signals_json, "signal payloads")?;
+    let inputs: JsonValue = parse(&inputs_json, "inputs")?;
+
+    let mut harness = WorkflowTestHarness::new(&source).map_err(client_error)?;
+    for stub in stubs {
+        harness.stub(stub);
+    }
+    for (name, payload) in signals {
+        harness.on_signal(&name, payload);
+    }
+    let run = harness.run(inputs).map_err(client_error)?;
+    json_to_py(py, &run.to_json())
+}
+
 /* ===================== Signal Operations ===================== */
 
 /// Send a signal to a workflow
 #[pyfunction]
-#[pyo3(signature = (workflow_id, signal_name, payload_json, queue=None))]
+#[pyo3(signature = (workflow_id, signal_name, payload_json, queue=None, encoding=None))]
 fn send_signal_sync(
     py: Python,
     workflow_id: String,
     signal_name: String,
-    payload_json: String,
+    payload_json: PayloadArg,
     queue: Option<String>,
+    encoding: Option<&str>,
 ) -> PyResult<()> {
     let runtime = get_runtime();
 
-    let payload: serde_json::Value = serde_json::from_str(&payload_json).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid payload JSON: {}", e))
-    })?;
+    let payload = decode_payload(payload_json, encoding, "payload")?;
 
     // Release GIL while doing DB write
     py.allow_threads(|| {
-        runtime.block_on(Client::send_signal(workflow_id, signal_name, payload, queue))
+        runtime.block_on(Client::send_signal(
+            workflow_id,
+            signal_name,
+            payload,
+            queue,
+        ))
     })
-    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+    .map_err(client_error)
+}
+
+/* ===================== Webhook Operations ===================== */
+
+/// Register a webhook, returning it as a dict
+#[pyfunction]
+#[pyo3(signature = (url, secret, events, queue=None, target_name=None))]
+fn register_webhook_sync(
+    py: Python,
+    url: String,
+    secret: String,
+    events: Vec<String>,
+    queue: Option<String>,
+    target_name: Option<String>,
+) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    let events = events
+        .into_iter()
+        .map(|event| parse_label(event, "webhook event"))
+        .collect::<PyResult<Vec<_>>>()?;
+    let params = CreateWebhookParams {
+        url,
+        secret,
+        events,
+        queue,
+        target_name,
+    };
+
+    // Release GIL while doing DB write
+    let webhook = py
+        .allow_threads(|| runtime.block_on(Client::register_webhook(params)))
+        .map_err(client_error)?;
+
+    let webhook = serde_json::to_value(webhook)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &webhook)
+}
+
+/// Registered webhooks, as a list of dicts
+#[pyfunction]
+fn list_webhooks_sync(py: Python) -> PyResult<PyObject> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB query
+    let webhooks = py
+        .allow_threads(|| runtime.block_on(Client::list_webhooks()))
+        .map_err(client_error)?;
+
+    let webhooks = serde_json::to_value(webhooks)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+    json_to_py(py, &webhooks)
+}
+
+/// Remove a webhook
+#[pyfunction]
+fn remove_webhook_sync(py: Python, webhook_id: String) -> PyResult<()> {
+    let runtime = get_runtime();
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::remove_webhook(webhook_id)))
+        .map_err(client_error)
+}
+
+/* ===================== External Task Operations ===================== */
+
+/// Complete an external task by its token
+#[pyfunction]
+#[pyo3(signature = (token, result, encoding=None))]
+fn complete_external_task_sync(
+    py: Python,
+    token: String,
+    result: PayloadArg,
+    encoding: Option<&str>,
+) -> PyResult<()> {
+    let runtime = get_runtime();
+
+    let result = decode_payload(result, encoding, "result")?;
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::complete_external_task(token, result)))
+        .map_err(client_error)
+}
+
+/// Fail an external task by its token
+#[pyfunction]
+#[pyo3(signature = (token, error, encoding=None))]
+fn fail_external_task_sync(
+    py: Python,
+    token: String,
+    error: PayloadArg,
+    encoding: Option<&str>,
+) -> PyResult<()> {
+    let runtime = get_runtime();
+
+    let error = decode_payload(error, encoding, "error")?;
+
+    // Release GIL while doing DB write
+    py.allow_threads(|| runtime.block_on(Client::fail_external_task(token, error)))
+        .map_err(client_error)
 }
 
 /* ===================== Scheduling Operations ===================== */
 
 /// Schedule an execution (workflow or task) to start at a future time
 #[pyfunction]
-#[pyo3(signature = (exec_type, target_name, inputs_json, run_at_iso, queue))]
+#[pyo3(signature = (exec_type, target_name, inputs_json, run_at_iso, queue, encoding=None, namespace=None))]
+#[allow(clippy::too_many_arguments)]
 fn schedule_execution_sync(
     py: Python,
     exec_type: String,
     target_name: String,
-    inputs_json: String,
+    inputs_json: PayloadArg,
     run_at_iso: String,
     queue: String,
+    encoding: Option<&str>,
+    namespace: Option<String>,
 ) -> PyResult<String> {
     let runtime = get_runtime();
 
@@ -306,9 +1361,7 @@ fn schedule_execution_sync(
         }
     };
 
-    let inputs: serde_json::Value = serde_json::from_str(&inputs_json).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid inputs JSON: {}", e))
-    })?;
+    let inputs = decode_payload(inputs_json, encoding, "inputs")?;
 
     let run_at = chrono::NaiveDateTime::parse_from_str(&run_at_iso, "%Y-%m-%dT%H:%M:%S%.f")
         .or_else(|_| chrono::NaiveDateTime::parse_from_str(&run_at_iso, "%Y-%m-%dT%H:%M:%S"))
@@ -325,11 +1378,135 @@ fn schedule_execution_sync(
         queue,
         inputs,
         run_at,
+        namespace,
     };
 
     // Release GIL while doing DB write
     py.allow_threads(|| runtime.block_on(Client::schedule_execution(params)))
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+        .map_err(client_error)
+}
+
+/* ===================== Async Operations ===================== */
+//
+// Awaitable versions of the calls asyncio workers make most. Each returns
+// an asyncio future resolved from the shared Tokio runtime, so the event
+// loop keeps running while the call is in flight.
+
+/// Claim up to `max_count` tasks at once, resolving to a JSON list
+#[pyfunction]
+#[pyo3(signature = (queues, max_count, worker_id=None))]
+fn claim_executions_async(
+    py: Python,
+    queues: Vec<String>,
+    max_count: usize,
+    worker_id: Option<String>,
+) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let result = Client::claim_executions(worker_id, queues, max_count)
+            .await
+            .map_err(client_error)?;
+        Ok(result.to_string())
+    })
+}
+
+/// Complete an execution
+#[pyfunction]
+#[pyo3(signature = (execution_id, result, encoding=None))]
+fn complete_execution_async<'py>(
+    py: Python<'py>,
+    execution_id: String,
+    result: PayloadArg,
+    encoding: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let result = decode_payload(result, encoding, "result")?;
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        Client::complete_execution(execution_id, result)
+            .await
+            .map_err(client_error)
+    })
+}
+
+/// Fail an execution
+#[pyfunction]
+#[pyo3(signature = (execution_id, error, retry, encoding=None))]
+fn fail_execution_async<'py>(
+    py: Python<'py>,
+    execution_id: String,
+    error: PayloadArg,
+    retry: bool,
+    encoding: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let error = decode_payload(error, encoding, "error")?;
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        Client::fail_execution(execution_id, error, retry)
+            .await
+            .map_err(client_error)
+    })
+}
+
+/// Record that a task is still running and extend its claim
+#[pyfunction]
+#[pyo3(signature = (execution_id, worker_id=None))]
+fn heartbeat_async(
+    py: Python,
+    execution_id: String,
+    worker_id: Option<String>,
+) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        Client::heartbeat(execution_id, worker_id)
+            .await
+            .map_err(client_error)
+    })
+}
+
+/// Get execution by ID
+#[pyfunction]
+fn get_execution_async(py: Python, execution_id: String) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let result = Client::get_execution(execution_id)
+            .await
+            .map_err(client_error)?;
+        Ok(result.map(|inner| PyExecution { inner }))
+    })
+}
+
+/// Start a workflow execution, resolving to its ID
+#[pyfunction]
+#[pyo3(signature = (workflow_name, inputs_json, encoding=None, namespace=None, concurrency_key=None, concurrency_policy=None))]
+fn start_workflow_async<'py>(
+    py: Python<'py>,
+    workflow_name: String,
+    inputs_json: PayloadArg,
+    encoding: Option<&str>,
+    namespace: Option<String>,
+    concurrency_key: Option<String>,
+    concurrency_policy: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let inputs = decode_payload(inputs_json, encoding, "inputs")?;
+    let concurrency = concurrency_arg(concurrency_key, concurrency_policy)?;
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        Client::start_workflow(workflow_name, inputs, None, namespace, concurrency)
+            .await
+            .map_err(client_error)
+    })
+}
+
+/// Wait up to `timeout_secs` for a workflow to finish, resolving to its
+/// output as JSON
+#[pyfunction]
+fn get_workflow_result_async(
+    py: Python,
+    execution_id: String,
+    timeout_secs: f64,
+) -> PyResult<Bound<PyAny>> {
+    let timeout = std::time::Duration::try_from_secs_f64(timeout_secs)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let output = Client::get_workflow_result(execution_id, true, timeout)
+            .await
+            .map_err(client_error)?;
+        Ok(output.unwrap_or(JsonValue::Null).to_string())
+    })
 }
 
 /* ===================== Python Module ===================== */
@@ -337,6 +1514,46 @@ fn schedule_execution_sync(
 /// Python module definition
 #[pymodule]
 fn rhythm_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // Run the futures of the async functions on the shared runtime. Fails
+    // only if another module already set one, which it can then keep.
+    let _ = pyo3_async_runtimes::tokio::init_with_runtime(get_runtime());
+
+    // Types
+    m.add_class::<PyExecution>()?;
+    m.add("RhythmError", m.py().get_type::<RhythmError>())?;
+    m.add("NotFoundError", m.py().get_type::<NotFoundError>())?;
+    m.add("ConflictError", m.py().get_type::<ConflictError>())?;
+    m.add("ValidationError", m.py().get_type::<ValidationError>())?;
+    m.add(
+        "SerializationError",
+        m.py().get_type::<SerializationError>(),
+    )?;
+    m.add("DatabaseError", m.py().get_type::<DatabaseError>())?;
+    m.add(
+        "QuotaExceededError",
+        m.py().get_type::<QuotaExceededError>(),
+    )?;
+    m.add("QueueFullError", m.py().get_type::<QueueFullError>())?;
+    m.add("ReadOnlyError", m.py().get_type::<ReadOnlyError>())?;
+    m.add(
+        "UnauthenticatedError",
+        m.py().get_type::<UnauthenticatedError>(),
+    )?;
+    m.add(
+        "PermissionDeniedError",
+        m.py().get_type::<PermissionDeniedError>(),
+    )?;
+    m.add(
+        "WorkflowTimeoutError",
+        m.py().get_type::<WorkflowTimeoutError>(),
+    )?;
+    m.add(
+        "WorkflowFailedError",
+        m.py().get_type::<WorkflowFailedError>(),
+    )?;
+    m.add_function(wrap_pyfunction!(supported_payload_encodings, m)?)?;
+    m.add_function(wrap_pyfunction!(negotiate_payload_encoding, m)?)?;
+
     // System
     m.add_function(wrap_pyfunction!(init_runtime, m)?)?;
     m.add_function(wrap_pyfunction!(initialize_sync, m)?)?;
@@ -344,21 +1561,64 @@ fn rhythm_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Execution lifecycle
     m.add_function(wrap_pyfunction!(create_execution_sync, m)?)?;
     m.add_function(wrap_pyfunction!(run_cooperative_worker_loop, m)?)?;
+    m.add_function(wrap_pyfunction!(claim_executions_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(run_worker, m)?)?;
     m.add_function(wrap_pyfunction!(request_shutdown, m)?)?;
+    m.add_function(wrap_pyfunction!(begin_shutdown, m)?)?;
+    m.add_function(wrap_pyfunction!(wait_idle_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(drain_worker_sync, m)?)?;
     m.add_function(wrap_pyfunction!(start_internal_worker, m)?)?;
     m.add_function(wrap_pyfunction!(complete_execution_sync, m)?)?;
     m.add_function(wrap_pyfunction!(fail_execution_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(emit_partial_result_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(append_log_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(heartbeat_sync, m)?)?;
     m.add_function(wrap_pyfunction!(get_execution_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_execution_history_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_execution_logs_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_changes_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(list_executions_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_namespace_usage_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_slo_status_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_queue_stats_sync, m)?)?;
 
     // Workflow operations
     m.add_function(wrap_pyfunction!(start_workflow_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(run_workflow_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_workflow_result_sync, m)?)?;
     m.add_function(wrap_pyfunction!(get_workflow_tasks_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_workflow_schema_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_workflow_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(run_workflow_test, m)?)?;
+    m.add_function(wrap_pyfunction!(cancel_workflow_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(pause_execution_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(resume_execution_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(tag_execution_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(get_execution_tags_sync, m)?)?;
 
     // Signal operations
     m.add_function(wrap_pyfunction!(send_signal_sync, m)?)?;
 
+    // Webhook operations
+    m.add_function(wrap_pyfunction!(register_webhook_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(list_webhooks_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(remove_webhook_sync, m)?)?;
+
+    // External task operations
+    m.add_function(wrap_pyfunction!(complete_external_task_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(fail_external_task_sync, m)?)?;
+
     // Scheduling operations
     m.add_function(wrap_pyfunction!(schedule_execution_sync, m)?)?;
 
+    // Async operations
+    m.add_function(wrap_pyfunction!(claim_executions_async, m)?)?;
+    m.add_function(wrap_pyfunction!(complete_execution_async, m)?)?;
+    m.add_function(wrap_pyfunction!(fail_execution_async, m)?)?;
+    m.add_function(wrap_pyfunction!(heartbeat_async, m)?)?;
+    m.add_function(wrap_pyfunction!(get_execution_async, m)?)?;
+    m.add_function(wrap_pyfunction!(start_workflow_async, m)?)?;
+    m.add_function(wrap_pyfunction!(get_workflow_result_async, m)?)?;
+
     Ok(())
 }
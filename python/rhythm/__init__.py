@@ -2,15 +2,47 @@
 Rhythm - A lightweight durable execution framework using only Postgres
 """
 
-from rhythm import client, worker
+from rhythm import aio, client, testing, worker
+from rhythm.core import (
+    ConflictError,
+    DatabaseError,
+    NotFoundError,
+    PermissionDeniedError,
+    QueueFullError,
+    QuotaExceededError,
+    ReadOnlyError,
+    RhythmError,
+    SerializationError,
+    UnauthenticatedError,
+    ValidationError,
+    WorkflowFailedError,
+    WorkflowTimeoutError,
+)
 from rhythm.decorators import task
 from rhythm.init import init
+from rhythm.worker import Worker
 
 __all__ = [
     "init",
     "task",
     "worker",
+    "Worker",
     "client",
+    "aio",
+    "testing",
+    "RhythmError",
+    "NotFoundError",
+    "ConflictError",
+    "ValidationError",
+    "SerializationError",
+    "DatabaseError",
+    "QuotaExceededError",
+    "QueueFullError",
+    "ReadOnlyError",
+    "UnauthenticatedError",
+    "PermissionDeniedError",
+    "WorkflowFailedError",
+    "WorkflowTimeoutError",
 ]
 
 __version__ = "0.1.0"
@@ -5,7 +5,7 @@ This module provides a thin wrapper around the Rust CLI implementation.
 All CLI logic is implemented in Rust core for consistency across language adapters.
 """
 
-import asyncio
+import json
 import os
 import sys
 
@@ -29,8 +29,39 @@ def main(database_url, config):
 @click.option("-m", "--import", "import_modules", multiple=True, help="Module to import")
 def worker(queues, worker_id, import_modules):
     """Run a worker to process tasks"""
-    # Import modules to register decorated functions
-    for module_name in import_modules:
+    _import_modules(import_modules)
+
+    click.echo(f"Starting worker for queues: {', '.join(queues)}")
+
+    from rhythm.worker import Worker
+
+    Worker(queues=list(queues)).run()
+
+
+@main.command()
+@click.option("-m", "--import", "import_modules", multiple=True, required=True, help="Module to import")
+@click.option(
+    "-o", "--out", default=".rhythm/tasks.json", show_default=True, help="Manifest to write"
+)
+def tasks(import_modules, out):
+    """Write the task manifest editors complete Task.run names from"""
+    _import_modules(import_modules)
+
+    from rhythm.registry import get_task_manifest
+
+    manifest = get_task_manifest()
+    directory = os.path.dirname(out)
+    if directory:
+        os.makedirs(directory, exist_ok=True)
+    with open(out, "w") as f:
+        json.dump(manifest, f, indent=2)
+        f.write("\n")
+    click.echo(f"Wrote {len(manifest['tasks'])} tasks to {out}")
+
+
+def _import_modules(module_names):
+    """Import modules so their decorated functions register"""
+    for module_name in module_names:
         try:
             __import__(module_name)
             click.echo(f"Imported module: {module_name}")
@@ -38,22 +69,6 @@ def worker(queues, worker_id, import_modules):
             click.echo(f"Failed to import {module_name}: {e}", err=True)
             sys.exit(1)
 
-    click.echo(f"Starting worker for queues: {', '.join(queues)}")
-
-    from rhythm.worker import run_worker
-
-    async def _run():
-        try:
-            await run_worker()
-        except KeyboardInterrupt:
-            click.echo("\nShutting down worker...")
-
-    try:
-        asyncio.run(_run())
-    except KeyboardInterrupt:
-        click.echo("\nInterrupted")
-        sys.exit(130)
-
 
 if __name__ == "__main__":
     main()
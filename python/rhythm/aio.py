@@ -0,0 +1,205 @@
+"""Awaitable client API for asyncio applications and workers
+
+The functions in `rhythm.client` block the calling thread until the database
+answers. These are their async counterparts: each call runs on Rhythm's own
+Tokio runtime and resolves an asyncio future when it finishes, so an event
+loop can claim, run and complete tasks without a thread pool.
+"""
+
+import logging
+from typing import Any, Optional
+
+from rhythm.core import RhythmCore
+from rhythm.models import Execution
+
+logger = logging.getLogger(__name__)
+
+
+async def claim_executions(
+    queues: list[str], max_count: int, worker_id: Optional[str] = None
+) -> list[dict[str, Any]]:
+    """Claim up to `max_count` tasks from `queues` in one round trip.
+
+    Workflows claimed along the way run in Rust. Report each task with
+    `complete_execution` or `fail_execution` once it has run.
+
+    Args:
+        queues: Queues to claim from
+        max_count: Most tasks to claim
+        worker_id: Who is claiming (default: the configured worker id)
+
+    Returns:
+        Tasks as dicts with execution_id, target_name and inputs; empty when idle
+
+    Example:
+        while True:
+            tasks = await rhythm.aio.claim_executions(["default"], 10)
+            await asyncio.gather(*(run(task) for task in tasks))
+            if not tasks:
+                await asyncio.sleep(1)
+
+    Meta:
+        section: Async Client
+    """
+    return await RhythmCore.claim_executions_async(queues, max_count, worker_id)
+
+
+async def complete_execution(task_id: str, result: Any) -> None:
+    """Complete a claimed task with its result.
+
+    Args:
+        task_id: The task's execution ID
+        result: The task's result (must be JSON-serializable)
+
+    Meta:
+        section: Async Client
+    """
+    await RhythmCore.complete_execution_async(task_id, result)
+
+
+async def fail_execution(task_id: str, error: dict[str, Any], retry: bool = False) -> None:
+    """Fail a claimed task.
+
+    Args:
+        task_id: The task's execution ID
+        error: Error details, e.g. {"message": ..., "type": ...}
+        retry: Whether to retry the task under its retry policy
+
+    Meta:
+        section: Async Client
+    """
+    await RhythmCore.fail_execution_async(task_id, error, retry)
+
+
+async def heartbeat(task_id: str, worker_id: Optional[str] = None) -> bool:
+    """Report that a task is still running, keeping its claim.
+
+    Args:
+        task_id: The running task's ID
+        worker_id: Who is running it (default: the configured worker id)
+
+    Returns:
+        False once the task is no longer this worker's to run
+
+    Meta:
+        section: Async Client
+    """
+    return await RhythmCore.heartbeat_async(task_id, worker_id)
+
+
+async def get_execution(execution_id: str) -> Optional[Execution]:
+    """Get an execution by ID.
+
+    Args:
+        execution_id: The execution ID
+
+    Returns:
+        Execution object or None if not found
+
+    Meta:
+        section: Async Client
+    """
+    return await RhythmCore.get_execution_async(execution_id)
+
+
+class AsyncWorkflowHandle(str):
+    """A started workflow: its execution ID, with `result()` to await its output.
+
+    It is the ID string itself, so it can be stored and passed around like one.
+
+    Meta:
+        section: Async Client
+    """
+
+    @property
+    def id(self) -> str:
+        """The workflow's execution ID"""
+        return str(self)
+
+    async def result(self, timeout: float = 30.0) -> Any:
+        """Wait for the workflow to finish and return its output.
+
+        See `get_workflow_result`.
+
+        Args:
+            timeout: Maximum time to wait in seconds (default: 30); 0 checks
+                without waiting
+
+        Returns:
+            The workflow's output
+
+        Raises:
+            WorkflowTimeoutError: If it didn't finish within timeout; it keeps running
+            WorkflowFailedError: If the workflow failed, expired or was cancelled
+        """
+        return await get_workflow_result(self.id, timeout)
+
+    def __repr__(self) -> str:
+        return f"AsyncWorkflowHandle({str.__repr__(self)})"
+
+
+async def start_workflow(
+    workflow_name: str,
+    inputs: dict[str, Any],
+    namespace: Optional[str] = None,
+    concurrency_key: Optional[str] = None,
+    on_conflict: str = "reject",
+) -> AsyncWorkflowHandle:
+    """Start a workflow execution.
+
+    Args:
+        workflow_name: Name of the workflow to execute (matches .flow filename)
+        inputs: Input parameters for the workflow
+        namespace: Namespace for quotas (default: "default")
+        concurrency_key: Key at most one unfinished workflow holds at a time, e.g. a
+            customer ID for a workflow that must not run twice for one customer
+        on_conflict: What to do while another workflow holds `concurrency_key`:
+            "reject" raises ConflictError, "queue" starts this one once the holder
+            and the workflows queued before it have finished, and "replace_existing"
+            cancels them
+
+    Returns:
+        Handle of the workflow: its execution ID, with `result()` to await
+        its output
+
+    Raises:
+        ValidationError: If inputs don't match the workflow's inputs schema
+        ConflictError: If `concurrency_key` is taken and `on_conflict` is "reject"
+
+    Meta:
+        section: Async Client
+    """
+    execution_id = await RhythmCore.start_workflow_async(
+        workflow_name,
+        inputs,
+        namespace=namespace,
+        concurrency_key=concurrency_key,
+        concurrency_policy=on_conflict,
+    )
+    logger.info(f"Started workflow {workflow_name} with ID {execution_id}")
+    return AsyncWorkflowHandle(execution_id)
+
+
+async def get_workflow_result(execution_id: str, timeout: float = 30.0) -> Any:
+    """Wait for a workflow to finish and return its output.
+
+    The wait runs on Rhythm's runtime without blocking the event loop. A
+    workflow that has already finished returns at once.
+
+    Args:
+        execution_id: The workflow's execution ID
+        timeout: Maximum time to wait in seconds (default: 30); 0 checks
+            without waiting
+
+    Returns:
+        The workflow's output
+
+    Raises:
+        WorkflowTimeoutError: If it didn't finish within timeout; it keeps running
+        WorkflowFailedError: If the workflow failed, expired or was cancelled
+        NotFoundError: If there is no execution with this ID
+
+    Meta:
+        section: Async Client
+    """
+    return await RhythmCore.get_workflow_result_async(execution_id, timeout)
@@ -1,11 +1,16 @@
 """Client API for enqueuing work and sending signals"""
 
 import logging
-import time
+from datetime import datetime
 from typing import Any, Optional
 
-from rhythm.core import RhythmCore
-from rhythm.models import Execution, ExecutionStatus
+from rhythm.core import (
+    NotFoundError,
+    QuotaExceededError,
+    RhythmCore,
+    WorkflowFailedError,
+)
+from rhythm.models import Execution, ExecutionChanges, ExecutionPage
 
 logger = logging.getLogger(__name__)
 
@@ -14,6 +19,12 @@ def queue_task(
     name: str,
     inputs: dict,
     queue: str = "default",
+    namespace: Optional[str] = None,
+    ttl_seconds: Optional[int] = None,
+    retry_policy: Optional[dict] = None,
+    priority: Optional[int] = None,
+    idempotency_key: Optional[str] = None,
+    tags: Optional[dict[str, str]] = None,
 ) -> str:
     """Queue a task for execution.
 
@@ -21,10 +32,27 @@ def queue_task(
         name: Task function name
         inputs: Input parameters as a dictionary
         queue: Queue name (default: "default")
+        namespace: Namespace for quotas (default: "default")
+        ttl_seconds: Expire the execution if no worker claims it within this
+            many seconds; it then ends with status "expired" instead of running
+        retry_policy: How failures are retried, e.g. {"max_retries": 5,
+            "backoff": "exponential", "initial_interval_ms": 1000,
+            "max_interval_ms": 60000, "jitter": 0.2}; "backoff" may also be
+            "fixed". Retries are redelivered after the backoff delay
+        priority: Higher priorities are claimed first from the queue
+            (default 0)
+        idempotency_key: If an execution with this key was already queued
+            for the same name and queue within the idempotency window,
+            return its ID instead of queueing another
+        tags: Labels to find the execution by, e.g. {"customer_id": "123"}
 
     Returns:
         Execution ID
 
+    Raises:
+        QuotaExceededError: If the namespace is over one of its quotas
+        QueueFullError: If the queue stays over its max depth
+
     Meta:
         section: Client
     """
@@ -34,6 +62,12 @@ def queue_task(
         queue=queue,
         inputs=inputs,
         parent_workflow_id=None,
+        namespace=namespace,
+        ttl_seconds=ttl_seconds,
+        retry_policy=retry_policy,
+        priority=priority,
+        idempotency_key=idempotency_key,
+        tags=tags,
     )
 
     logger.info(f"Enqueued task {execution_id}: {name} on queue {queue}")
@@ -44,6 +78,11 @@ def queue_workflow(
     name: str,
     inputs: dict,
     queue: str = "default",
+    namespace: Optional[str] = None,
+    ttl_seconds: Optional[int] = None,
+    priority: Optional[int] = None,
+    idempotency_key: Optional[str] = None,
+    tags: Optional[dict[str, str]] = None,
 ) -> str:
     """Queue a workflow for execution.
 
@@ -51,10 +90,23 @@ def queue_workflow(
         name: Workflow name
         inputs: Input parameters as a dictionary
         queue: Queue name (default: "default")
+        namespace: Namespace for quotas (default: "default")
+        ttl_seconds: Expire the execution if no worker claims it within this
+            many seconds; it then ends with status "expired" instead of running
+        priority: Higher priorities are claimed first from the queue
+            (default 0)
+        idempotency_key: If an execution with this key was already queued
+            for the same name and queue within the idempotency window,
+            return its ID instead of queueing another
+        tags: Labels to find the execution by, e.g. {"customer_id": "123"}
 
     Returns:
         Execution ID
 
+    Raises:
+        QuotaExceededError: If the namespace is over one of its quotas
+        QueueFullError: If the queue stays over its max depth
+
     Meta:
         section: Client
     """
@@ -64,6 +116,11 @@ def queue_workflow(
         queue=queue,
         inputs=inputs,
         parent_workflow_id=None,
+        namespace=namespace,
+        ttl_seconds=ttl_seconds,
+        priority=priority,
+        idempotency_key=idempotency_key,
+        tags=tags,
     )
 
     logger.info(f"Enqueued workflow {execution_id}: {name} on queue {queue}")
@@ -76,6 +133,11 @@ def queue_execution(
     inputs: dict,
     queue: str,
     parent_workflow_id: Optional[str] = None,
+    namespace: Optional[str] = None,
+    ttl_seconds: Optional[int] = None,
+    priority: Optional[int] = None,
+    idempotency_key: Optional[str] = None,
+    tags: Optional[dict[str, str]] = None,
 ) -> str:
     """Enqueue an execution (task or workflow).
 
@@ -87,6 +149,15 @@ def queue_execution(
         inputs: Input parameters as a dictionary
         queue: Queue name
         parent_workflow_id: Parent workflow ID (for workflow tasks)
+        namespace: Namespace for quotas (default: the parent's, else "default")
+        ttl_seconds: Expire the execution if no worker claims it within this
+            many seconds; it then ends with status "expired" instead of running
+        priority: Higher priorities are claimed first from the queue
+            (default 0)
+        idempotency_key: If an execution with this key was already queued
+            for the same name and queue within the idempotency window,
+            return its ID instead of queueing another
+        tags: Labels to find the execution by, e.g. {"customer_id": "123"}
 
     Returns:
         Execution ID
@@ -100,6 +171,11 @@ def queue_execution(
         queue=queue,
         inputs=inputs,
         parent_workflow_id=parent_workflow_id,
+        namespace=namespace,
+        ttl_seconds=ttl_seconds,
+        priority=priority,
+        idempotency_key=idempotency_key,
+        tags=tags,
     )
 
     logger.info(f"Enqueued {exec_type} {execution_id}: {target_name} on queue {queue}")
@@ -121,6 +197,113 @@ def get_execution(execution_id: str) -> Optional[Execution]:
     return RhythmCore.get_execution(execution_id)
 
 
+def get_execution_history(execution_id: str) -> list[dict[str, Any]]:
+    """Get the history of an execution, for debugging and audit.
+
+    Each event is a dict with id, execution_id, event_type, details and
+    created_at. Event types are created, claimed, retried, suspended,
+    task_scheduled, task_completed, resumed, read, cancel_requested,
+    cancel_progress, completed, failed, expired and cancelled.
+
+    Args:
+        execution_id: The execution ID
+
+    Returns:
+        List of events, oldest first
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_execution_history(execution_id)
+
+
+def get_execution_logs(execution_id: str) -> list[dict[str, Any]]:
+    """Get everything logged while running an execution.
+
+    Covers `Log.info/warn/error` calls in a workflow and `append_log` calls
+    from task handlers. Each log is a dict with id, execution_id, level
+    (info, warn or error), message, data and created_at.
+
+    Args:
+        execution_id: The execution ID
+
+    Returns:
+        List of logs, in the order they were written
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_execution_logs(execution_id)
+
+
+def get_changes(since_cursor: Optional[str] = None, limit: Optional[int] = None) -> ExecutionChanges:
+    """Get executions whose status changed since a cursor.
+
+    Intended for dashboards that poll: start with no cursor, then pass the
+    returned `cursor` on each subsequent call. A change is returned once its
+    transaction and every older transaction have finished, so no change is
+    skipped.
+
+    Args:
+        since_cursor: Cursor from a previous call, or None to start from the beginning
+        limit: Maximum executions per page (default 100); check `has_more`
+
+    Returns:
+        ExecutionChanges with the changed executions and the next cursor
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_changes(since_cursor=since_cursor, limit=limit)
+
+
+def get_namespace_usage(namespace: str) -> dict[str, Any]:
+    """Get the usage counted against a namespace's quotas.
+
+    Args:
+        namespace: Namespace name
+
+    Returns:
+        Dict with running, enqueued_last_minute and storage_bytes
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_namespace_usage(namespace)
+
+
+def get_slo_status() -> list[dict[str, Any]]:
+    """Get the current compliance of the configured claim latency SLOs.
+
+    SLOs are defined under `[[slos]]` in rhythm.toml.
+
+    Returns:
+        One dict per SLO with its config, counts (total, within), compliance,
+        error_budget_remaining and met
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_slo_status()
+
+
+def get_queue_stats(window_minutes: int = 5) -> list[dict[str, Any]]:
+    """Get the load on each queue, for deciding how many workers to run.
+
+    Args:
+        window_minutes: How far back to count finished executions
+
+    Returns:
+        One dict per queue with pending, claimed, suspended,
+        oldest_pending_secs (None when nothing is pending), and the
+        completed, failed and throughput_per_minute of the window
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_queue_stats(window_minutes=window_minutes)
+
+
 def cancel_execution(execution_id: str) -> bool:
     """Cancel a pending or suspended execution.
 
@@ -146,55 +329,403 @@ def cancel_execution(execution_id: str) -> bool:
         return False
 
 
-def start_workflow(workflow_name: str, inputs: dict[str, Any]) -> str:
+def cancel_workflow(
+    execution_id: str,
+    reason: str = "Workflow cancelled",
+    finish_running_tasks: bool = False,
+) -> bool:
+    """Cancel a workflow and everything it started.
+
+    The workflow and its unfinished child tasks and workflows end with
+    status "cancelled" and a CANCELLED error; a parent awaiting the workflow
+    gets that error. All of it happens in one transaction.
+
+    Args:
+        execution_id: The workflow's execution ID
+        reason: Message stored on the cancelled executions
+        finish_running_tasks: Let tasks a worker is running finish instead
+            of cancelling them; their results are recorded but nothing
+            resumes the workflow
+
+    Returns:
+        True if cancelled, False if the workflow had already finished
+
+    Raises:
+        NotFoundError: If there is no execution with this ID
+        ValidationError: If the execution is not a workflow
+
+    Meta:
+        section: Client
+    """
+    cancelled = RhythmCore.cancel_workflow(execution_id, reason, finish_running_tasks)
+    if cancelled:
+        logger.info(f"Workflow {execution_id} cancelled: {reason}")
+    return cancelled
+
+
+def pause_execution(execution_id: str) -> bool:
+    """Pause a workflow, e.g. while a downstream it calls is broken.
+
+    Its queued work, resumptions from timers and signals included, stays on
+    the queue but isn't claimed until the workflow is resumed. A run in
+    progress finishes its current step, and tasks the workflow already
+    started still run; their results wait for the workflow.
+
+    Args:
+        execution_id: The workflow's execution ID
+
+    Returns:
+        True if paused, False if it was already paused
+
+    Raises:
+        NotFoundError: If there is no execution with this ID
+        ValidationError: If the execution is not a workflow
+        ConflictError: If the workflow has already finished
+
+    Meta:
+        section: Client
+    """
+    paused = RhythmCore.pause_execution(execution_id)
+    if paused:
+        logger.info(f"Workflow {execution_id} paused")
+    return paused
+
+
+def resume_execution(execution_id: str) -> bool:
+    """Resume a paused workflow, letting its queued work be claimed again.
+
+    Args:
+        execution_id: The workflow's execution ID
+
+    Returns:
+        True if resumed, False if it wasn't paused
+
+    Raises:
+        NotFoundError: If there is no execution with this ID
+        ValidationError: If the execution is not a workflow
+        ConflictError: If the workflow has already finished
+
+    Meta:
+        section: Client
+    """
+    resumed = RhythmCore.resume_execution(execution_id)
+    if resumed:
+        logger.info(f"Workflow {execution_id} resumed")
+    return resumed
+
+
+def tag_execution(execution_id: str, tags: dict[str, str]) -> None:
+    """Add tags to an execution, replacing any it has with the same keys.
+
+    Tags find executions in `list_executions`, e.g. every execution for a
+    customer. Executions a workflow starts from then on get its tags too.
+
+    Args:
+        execution_id: The execution's ID
+        tags: Tags to add, e.g. {"customer_id": "123"}
+
+    Raises:
+        NotFoundError: If there is no execution with this ID
+        ValidationError: If a tag key is empty
+
+    Meta:
+        section: Client
+    """
+    RhythmCore.tag_execution(execution_id, tags)
+
+
+def get_execution_tags(execution_id: str) -> dict[str, str]:
+    """Get an execution's tags.
+
+    Args:
+        execution_id: The execution's ID
+
+    Returns:
+        The execution's tags, including those it got from its workflow
+
+    Raises:
+        NotFoundError: If there is no execution with this ID
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_execution_tags(execution_id)
+
+
+class WorkflowHandle(str):
+    """A started workflow: its execution ID, with `result()` to wait for its output.
+
+    It is the ID string itself, so it can be stored and passed around like one.
+
+    Meta:
+        section: Client
+    """
+
+    @property
+    def id(self) -> str:
+        """The workflow's execution ID"""
+        return str(self)
+
+    def result(self, timeout: float = 30.0) -> Any:
+        """Wait for the workflow to finish and return its output.
+
+        See `get_workflow_result`.
+
+        Args:
+            timeout: Maximum time to wait in seconds (default: 30); 0 checks
+                without waiting
+
+        Returns:
+            The workflow's output
+
+        Raises:
+            WorkflowTimeoutError: If it didn't finish within timeout; it keeps running
+            WorkflowFailedError: If the workflow failed, expired or was cancelled
+        """
+        return get_workflow_result(self.id, timeout)
+
+    def __repr__(self) -> str:
+        return f"WorkflowHandle({str.__repr__(self)})"
+
+
+def start_workflow(
+    workflow_name: str,
+    inputs: dict[str, Any],
+    namespace: Optional[str] = None,
+    concurrency_key: Optional[str] = None,
+    on_conflict: str = "reject",
+) -> WorkflowHandle:
     """Start a workflow execution.
 
     Args:
         workflow_name: Name of the workflow to execute (matches .flow filename)
         inputs: Input parameters for the workflow
+        namespace: Namespace for quotas (default: "default")
+        concurrency_key: Key at most one unfinished workflow holds at a time, e.g. a
+            customer ID for a workflow that must not run twice for one customer
+        on_conflict: What to do while another workflow holds `concurrency_key`:
+            "reject" raises ConflictError, "queue" starts this one once the holder
+            and the workflows queued before it have finished, and "replace_existing"
+            cancels them
 
     Returns:
-        Workflow execution ID
+        Handle of the workflow: its execution ID, with `result()` to wait for
+        its output
+
+    Raises:
+        ValidationError: If inputs don't match the workflow's inputs schema
+        ConflictError: If `concurrency_key` is taken and `on_conflict` is "reject"
 
     Example:
-        workflow_id = rhythm.start_workflow(
+        workflow = rhythm.start_workflow(
             "processOrder",
             inputs={"orderId": "order-123", "amount": 99.99}
         )
+        receipt = workflow.result(timeout=60.0)
+
+        # At most one sync per account at a time; later ones wait their turn
+        rhythm.start_workflow(
+            "syncAccount",
+            inputs={"accountId": "acct-1"},
+            concurrency_key="sync:acct-1",
+            on_conflict="queue",
+        )
 
     Meta:
         section: Client
     """
-    execution_id = RhythmCore.start_workflow(workflow_name, inputs)
+    execution_id = RhythmCore.start_workflow(
+        workflow_name,
+        inputs,
+        namespace=namespace,
+        concurrency_key=concurrency_key,
+        concurrency_policy=on_conflict,
+    )
     logger.info(f"Started workflow {workflow_name} with ID {execution_id}")
-    return execution_id
+    return WorkflowHandle(execution_id)
+
+
+def get_workflow_result(execution_id: str, timeout: float = 30.0) -> Any:
+    """Wait for a workflow to finish and return its output.
+
+    The wait happens in Rhythm's core with the GIL released, so there is no
+    need to poll `get_execution`. A workflow that has already finished
+    returns at once.
+
+    Args:
+        execution_id: The workflow's execution ID
+        timeout: Maximum time to wait in seconds (default: 30); 0 checks
+            without waiting
+
+    Returns:
+        The workflow's output
+
+    Raises:
+        WorkflowTimeoutError: If it didn't finish within timeout; it keeps running
+        WorkflowFailedError: If the workflow failed, expired or was cancelled
+        NotFoundError: If there is no execution with this ID
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_workflow_result(execution_id, timeout)
+
+
+def run_workflow_sync(
+    workflow_name: str,
+    inputs: dict[str, Any],
+    timeout: float = 30.0,
+    namespace: Optional[str] = None,
+) -> Any:
+    """Run a workflow and block until it returns, for request/response use.
+
+    The workflow and every task it starts go in an express lane that workers
+    claim ahead of normal work.
+
+    Args:
+        workflow_name: Name of the workflow to execute (matches .flow filename)
+        inputs: Input parameters for the workflow
+        timeout: Maximum time to wait in seconds (default: 30)
+        namespace: Namespace for quotas (default: "default")
+
+    Returns:
+        The workflow's output
+
+    Raises:
+        WorkflowTimeoutError: If it didn't finish within timeout; it keeps running
+        WorkflowFailedError: If the workflow failed, expired or was cancelled
+        QuotaExceededError: If the namespace is over one of its quotas
+        QueueFullError: If the queue stays over its max depth
+
+    Example:
+        receipt = rhythm.client.run_workflow_sync(
+            "quote", inputs={"sku": "abc"}, timeout=5.0
+        )
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.run_workflow_sync(
+        workflow_name, inputs, timeout, namespace=namespace
+    )
+
+
+def get_workflow_schema(name: str) -> Optional[dict[str, Any]]:
+    """Get the inputs schema a registered workflow declares in its front matter.
+
+    `start_workflow` checks inputs against this schema and fills in defaults,
+    so UIs can build forms from it.
+
+    Args:
+        name: Name of the workflow
+
+    Returns:
+        {"fields": [...]} with each input's name, type, whether it is
+        required, and its default and description if it has them; None if
+        the workflow declares no inputs
+
+    Raises:
+        NotFoundError: If no workflow of that name is registered
+
+    Example:
+        schema = rhythm.client.get_workflow_schema("process_order")
+        required = [f["name"] for f in schema["fields"] if f["required"]]
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.get_workflow_schema(name)
+
+
+def analyze_workflow(name: str) -> dict[str, Any]:
+    """Get the task graph of a registered workflow, without running it.
+
+    Lists the tasks, child workflows and signals the workflow may use, and
+    lays out its calls, awaits and loops in the order they may happen. Calls
+    started before the await that waits on them may run in parallel; the
+    graph groups those too. Every branch is included, so this is what the
+    workflow *may* do.
+
+    Args:
+        name: Name of the workflow
+
+    Returns:
+        {"tasks", "workflows", "signals", "dynamic_calls", "nodes", "edges",
+        "parallel"}; each node has an "id", a "kind" ("start", "end",
+        "task", "workflow", "timer", "signal", "await" or "loop") and a
+        "line", and each edge a "from", a "to" and a "kind" ("next",
+        "repeat" or "awaited")
+
+    Raises:
+        NotFoundError: If no workflow of that name is registered
+
+    Example:
+        graph = rhythm.client.analyze_workflow("process_order")
+        print(sorted(graph["tasks"]))
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.analyze_workflow(name)
 
 
 def list_executions(
-    queue: Optional[str] = None,
     status: Optional[str] = None,
+    exec_type: Optional[str] = None,
+    queue: Optional[str] = None,
+    function_name: Optional[str] = None,
+    parent_workflow_id: Optional[str] = None,
+    namespace: Optional[str] = None,
+    tags: Optional[dict[str, str]] = None,
+    created_after: Optional[datetime] = None,
+    created_before: Optional[datetime] = None,
+    order: str = "newest_first",
+    cursor: Optional[str] = None,
     limit: int = 100,
-    offset: int = 0,
-) -> list[dict]:
-    """List executions with optional filters.
+) -> ExecutionPage:
+    """List executions matching filters, one page at a time.
 
-    NOTE: This function is currently not implemented as it requires direct database access.
-    Use the Rust bridge functions instead for execution management.
+    Start with no cursor, then pass the returned `cursor` to get the next
+    page until it is None. Pages are keyed on creation time rather than an
+    offset, so executions created while paging are not returned twice.
 
     Args:
+        status: Filter by status (e.g. "failed")
+        exec_type: Filter by execution type, "task" or "workflow"
         queue: Filter by queue name
-        status: Filter by status
-        limit: Maximum number of results
-        offset: Offset for pagination
+        function_name: Filter by task or workflow name
+        parent_workflow_id: Only executions started by this workflow
+        namespace: Filter by namespace
+        tags: Only executions with all of these tags
+        created_after: Only executions created at or after this time
+        created_before: Only executions created before this time
+        order: "newest_first" (default) or "oldest_first"
+        cursor: Cursor from a previous page, or None for the first page
+        limit: Maximum executions per page (default 100)
 
     Returns:
-        List of execution dicts
+        ExecutionPage with the executions and the cursor of the next page
+
+    Raises:
+        ValueError: If status, exec_type or order is not a known value
+        ValidationError: If cursor is malformed
 
     Meta:
         section: Client
     """
-    raise NotImplementedError(
-        "list_executions is not yet implemented. Use Rust bridge functions for execution management."
+    return RhythmCore.list_executions(
+        status=status,
+        exec_type=exec_type,
+        queue=queue,
+        target_name=function_name,
+        parent_workflow_id=parent_workflow_id,
+        namespace=namespace,
+        tags=tags,
+        created_after=created_after,
+        created_before=created_before,
+        order=order,
+        cursor=cursor,
+        limit=limit,
     )
 
 
@@ -203,6 +734,7 @@ def schedule_task(
     inputs: dict,
     run_at: str,
     queue: str = "default",
+    namespace: Optional[str] = None,
 ) -> str:
     """Schedule a task for execution at a future time.
 
@@ -214,6 +746,9 @@ def schedule_task(
         inputs: Input parameters as a dictionary
         run_at: ISO 8601 datetime string (e.g., "2024-01-15T10:30:00")
         queue: Queue name (default: "default")
+        namespace: Namespace for quotas (default: "default")
+        ttl_seconds: Expire the execution if no worker claims it within this
+            many seconds; it then ends with status "expired" instead of running
 
     Returns:
         Execution ID
@@ -226,6 +761,7 @@ def schedule_task(
         inputs=inputs,
         run_at=run_at,
         queue=queue,
+        namespace=namespace,
     )
 
     logger.info(f"Scheduled task {execution_id}: {name} to run at {run_at}")
@@ -237,6 +773,7 @@ def schedule_workflow(
     inputs: dict,
     run_at: str,
     queue: str = "default",
+    namespace: Optional[str] = None,
 ) -> str:
     """Schedule a workflow for execution at a future time.
 
@@ -248,6 +785,9 @@ def schedule_workflow(
         inputs: Input parameters as a dictionary
         run_at: ISO 8601 datetime string (e.g., "2024-01-15T10:30:00")
         queue: Queue name (default: "default")
+        namespace: Namespace for quotas (default: "default")
+        ttl_seconds: Expire the execution if no worker claims it within this
+            many seconds; it then ends with status "expired" instead of running
 
     Returns:
         Execution ID
@@ -260,6 +800,7 @@ def schedule_workflow(
         inputs=inputs,
         run_at=run_at,
         queue=queue,
+        namespace=namespace,
     )
 
     logger.info(f"Scheduled workflow {execution_id}: {name} to run at {run_at}")
@@ -302,6 +843,176 @@ def send_signal(
     logger.info(f"Sent signal '{signal_name}' to workflow {workflow_id}")
 
 
+def register_webhook(
+    url: str,
+    secret: str,
+    events: list[str],
+    queue: Optional[str] = None,
+    target_name: Optional[str] = None,
+) -> dict[str, Any]:
+    """Register a URL to be notified when matching executions finish.
+
+    Each notification is a JSON POST signed with `secret`: the
+    `X-Rhythm-Signature` header is `sha256=` and the hex HMAC-SHA256 of
+    `<X-Rhythm-Timestamp>.<body>`. Failed deliveries are retried with
+    backoff, so endpoints should dedupe on `X-Rhythm-Delivery`.
+
+    Args:
+        url: Endpoint to POST to
+        secret: Key the payloads are signed with
+        events: Any of "completed", "failed" and "cancelled"
+        queue: Only executions on this queue
+        target_name: Only executions of this task or workflow
+
+    Returns:
+        The webhook: id, url, events, queue, target_name and created_at
+
+    Example:
+        rhythm.client.register_webhook(
+            "https://example.com/hooks/rhythm",
+            secret=os.environ["RHYTHM_WEBHOOK_SECRET"],
+            events=["failed"],
+            target_name="checkout",
+        )
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.register_webhook(
+        url=url,
+        secret=secret,
+        events=events,
+        queue=queue,
+        target_name=target_name,
+    )
+
+
+def list_webhooks() -> list[dict[str, Any]]:
+    """List registered webhooks.
+
+    Returns:
+        One dict per webhook; secrets are not included
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.list_webhooks()
+
+
+def remove_webhook(webhook_id: str) -> None:
+    """Remove a webhook, dropping its undelivered notifications.
+
+    Args:
+        webhook_id: ID returned by `register_webhook`
+
+    Meta:
+        section: Client
+    """
+    RhythmCore.remove_webhook(webhook_id)
+
+
+def complete_external_task(token: str, result: Any) -> None:
+    """Complete an external task, resuming the workflow awaiting it.
+
+    Args:
+        token: Token from `ExternalTask.create` in the workflow
+        result: Value the workflow's `await` returns (any JSON-serializable value)
+
+    Example:
+        # In a webhook handler
+        rhythm.client.complete_external_task(
+            token=request.json["token"],
+            result={"status": "approved"}
+        )
+
+    Meta:
+        section: Client
+    """
+    RhythmCore.complete_external_task(token, result)
+    logger.info(f"Completed external task {token}")
+
+
+def fail_external_task(token: str, error: dict[str, Any]) -> None:
+    """Fail an external task; the workflow's `await` throws the error.
+
+    Args:
+        token: Token from `ExternalTask.create` in the workflow
+        error: Error details, e.g. {"message": "...", "type": "..."}
+
+    Meta:
+        section: Client
+    """
+    RhythmCore.fail_external_task(token, error)
+    logger.info(f"Failed external task {token}")
+
+
+def emit_partial_result(task_id: str, chunk: Any) -> None:
+    """Hand a partial result of a running task to the workflow awaiting it.
+
+    The workflow reads chunks with `Task.stream(task, from)`, as they arrive
+    or after the task finishes.
+
+    Args:
+        task_id: The running task's ID, e.g. from `rhythm.worker.current_task_id()`
+        chunk: Any JSON-serializable value
+
+    Example:
+        @task
+        def export_rows(query: str):
+            for page in fetch_pages(query):
+                rhythm.client.emit_partial_result(current_task_id(), page)
+
+    Meta:
+        section: Client
+    """
+    RhythmCore.emit_partial_result(task_id, chunk)
+
+
+def append_log(task_id: str, level: str, message: str, data: Any = None) -> None:
+    """Write a message to a running task's logs.
+
+    The message is kept with the execution and returned by
+    `get_execution_logs`, alongside the logs of the workflow that ran it.
+
+    Args:
+        task_id: The running task's ID, e.g. from `rhythm.worker.current_task_id()`
+        level: "info", "warn" or "error"
+        message: The message
+        data: Optional JSON-serializable value stored with the message
+
+    Example:
+        @task
+        def charge(order_id: str):
+            rhythm.client.append_log(current_task_id(), "info", "Charging", {"order_id": order_id})
+
+    Meta:
+        section: Client
+    """
+    RhythmCore.append_log(task_id, level, message, data)
+
+
+def heartbeat(task_id: str, worker_id: Optional[str] = None) -> bool:
+    """Report that a task is still running, keeping its claim.
+
+    The worker loop sends heartbeats for the tasks it runs. Hosts that run
+    a task some other way call this at least every 20 seconds; a task whose
+    heartbeat goes silent for longer than `worker.reaper.heartbeat_timeout_secs`
+    is given to another worker, or failed with HEARTBEAT_TIMEOUT once it has
+    been reclaimed `max_reclaims` times.
+
+    Args:
+        task_id: The running task's ID, e.g. from `rhythm.worker.current_task_id()`
+        worker_id: Who is running it (default: the configured worker id)
+
+    Returns:
+        False once the task is no longer this worker's to run
+
+    Meta:
+        section: Client
+    """
+    return RhythmCore.heartbeat(task_id, worker_id)
+
+
 def wait_for_execution(
     execution_id: str,
     timeout: float = 60.0,
@@ -309,12 +1020,13 @@ def wait_for_execution(
 ) -> Execution:
     """Wait for an execution to reach a terminal state and return it.
 
-    Polls the execution status until it reaches "completed" or "failed" status.
+    Waits until it reaches "completed", "failed", "expired" or "cancelled"
+    status, as `get_workflow_result` does.
 
     Args:
         execution_id: The execution ID to wait for
         timeout: Maximum time to wait in seconds (default: 60)
-        poll_interval: How often to poll in seconds (default: 0.5)
+        poll_interval: Unused; the wait no longer polls from Python
 
     Returns:
         Execution object with full execution details
@@ -326,23 +1038,14 @@ def wait_for_execution(
     Meta:
         section: Client
     """
-    start_time = time.time()
-
-    while True:
-        execution = get_execution(execution_id)
-
-        if execution is None:
-            raise RuntimeError(f"Execution {execution_id} not found")
-
-        # Check if reached terminal state
-        if execution.status in (ExecutionStatus.COMPLETED, ExecutionStatus.FAILED):
-            return execution
-
-        # Check timeout
-        if time.time() - start_time > timeout:
-            raise TimeoutError(
-                f"Execution {execution_id} did not complete within {timeout}s "
-                f"(current status: {execution.status})"
-            )
-
-        time.sleep(poll_interval)
+    try:
+        get_workflow_result(execution_id, timeout)
+    except WorkflowFailedError:
+        pass
+    except NotFoundError:
+        raise RuntimeError(f"Execution {execution_id} not found") from None
+
+    execution = get_execution(execution_id)
+    if execution is None:
+        raise RuntimeError(f"Execution {execution_id} not found")
+    return execution
@@ -1,14 +1,45 @@
 """Rhythm core interface"""
 
 import json
-from typing import Any, Dict, List, Optional
+from datetime import datetime
+from typing import Any, Callable, Dict, List, Optional
 
 try:
     from rhythm import rhythm_core as rust
 except ImportError:
     raise ImportError("rhythm_core Rust extension not found.")
 
-from rhythm.models import DelegatedAction, Execution
+from rhythm.models import DelegatedAction, Execution, ExecutionChanges, ExecutionPage
+from rhythm.telemetry import current_traceparent
+
+RhythmError = rust.RhythmError
+NotFoundError = rust.NotFoundError
+ConflictError = rust.ConflictError
+ValidationError = rust.ValidationError
+SerializationError = rust.SerializationError
+DatabaseError = rust.DatabaseError
+QuotaExceededError = rust.QuotaExceededError
+QueueFullError = rust.QueueFullError
+ReadOnlyError = rust.ReadOnlyError
+UnauthenticatedError = rust.UnauthenticatedError
+PermissionDeniedError = rust.PermissionDeniedError
+WorkflowTimeoutError = rust.WorkflowTimeoutError
+WorkflowFailedError = rust.WorkflowFailedError
+
+
+def _payload(value: Any, encoding: Optional[str]) -> Any:
+    """
+    Prepare a payload for the Rust extension.
+
+    With an encoding, the value must already be encoded bytes and is passed
+    through untouched so Rust can decode it in place. Otherwise it is JSON
+    serialized here.
+    """
+    if encoding is not None:
+        if not isinstance(value, (bytes, bytearray)):
+            raise TypeError(f"Payload with encoding '{encoding}' must be bytes")
+        return bytes(value)
+    return json.dumps(value)
 
 
 class RhythmCore:
@@ -20,6 +51,8 @@ class RhythmCore:
         config_path: Optional[str] = None,
         auto_migrate: bool = True,
         workflows: Optional[List[Dict[str, str]]] = None,
+        read_only: bool = False,
+        tasks: Optional[List[Dict[str, Any]]] = None,
     ) -> None:
         """
         Initialize Rhythm with configuration options.
@@ -29,16 +62,23 @@ class RhythmCore:
             config_path: Path to config file (overrides default search)
             auto_migrate: Whether to automatically run migrations if database is not initialized
             workflows: List of workflow files to register (each with name, source, file_path)
+            read_only: Refuse operations that change state with ReadOnlyError;
+                migrations and workflow registration are skipped
+            tasks: Task settings declared in code (each with target_name and
+                optionally max_retries); config file entries take precedence
         """
         workflows_json = None
         if workflows:
             workflows_json = json.dumps(workflows)
+        tasks_json = json.dumps(tasks) if tasks else None
 
         rust.initialize_sync(
             database_url=database_url,
             config_path=config_path,
             auto_migrate=auto_migrate,
             workflows_json=workflows_json,
+            read_only=read_only,
+            tasks_json=tasks_json,
         )
 
     @staticmethod
@@ -48,14 +88,33 @@ class RhythmCore:
         queue: str,
         inputs: Dict[str, Any],
         parent_workflow_id: Optional[str] = None,
+        encoding: Optional[str] = None,
+        namespace: Optional[str] = None,
+        ttl_seconds: Optional[int] = None,
+        retry_policy: Optional[Dict[str, Any]] = None,
+        priority: Optional[int] = None,
+        idempotency_key: Optional[str] = None,
+        traceparent: Optional[str] = None,
+        tags: Optional[Dict[str, str]] = None,
     ) -> str:
-        """Create a new execution"""
+        """Create a new execution; raises QuotaExceededError if over a namespace quota
+
+        `traceparent` defaults to the current OpenTelemetry span's.
+        """
         return rust.create_execution_sync(
             exec_type=exec_type,
             target_name=target_name,
             queue=queue,
-            inputs=json.dumps(inputs),
+            inputs=_payload(inputs, encoding),
             parent_workflow_id=parent_workflow_id,
+            encoding=encoding,
+            namespace=namespace,
+            ttl_seconds=ttl_seconds,
+            retry_policy_json=json.dumps(retry_policy) if retry_policy else None,
+            priority=priority,
+            idempotency_key=idempotency_key,
+            traceparent=traceparent or current_traceparent(),
+            tags=tags,
         )
 
     @staticmethod
@@ -77,6 +136,36 @@ class RhythmCore:
         data = json.loads(result)
         return DelegatedAction.from_dict(data)
 
+    @staticmethod
+    def claim_executions(
+        queues: List[str], max_count: int, worker_id: Optional[str] = None
+    ) -> List[Dict[str, Any]]:
+        """
+        Claim up to max_count tasks from queues in one round trip.
+
+        Workflows claimed along the way run in Rust. Returns the tasks as
+        dicts with execution_id, target_name and inputs; empty when idle.
+        """
+        result = rust.claim_executions_sync(
+            queues=queues, max_count=max_count, worker_id=worker_id
+        )
+        return json.loads(result)
+
+    @staticmethod
+    def run_worker(queues: List[str], dispatch: Callable[[str, str, str], str]) -> None:
+        """
+        Run a worker on `queues` until shutdown, driven entirely by core.
+
+        Core claims work, runs workflows, keeps running tasks' claims alive and
+        records their outcomes. `dispatch(execution_id, target_name,
+        inputs_json)` is called for each task and returns a JSON string:
+        `{"result": ...}` or `{"error": {...}, "retry": bool}`.
+
+        Python signal handlers still run while waiting for work; an exception
+        they raise (e.g. KeyboardInterrupt) stops the worker and propagates.
+        """
+        rust.run_worker(queues, dispatch)
+
     @staticmethod
     def request_shutdown() -> None:
         """
@@ -87,6 +176,25 @@ class RhythmCore:
         """
         rust.request_shutdown()
 
+    @staticmethod
+    def begin_shutdown() -> None:
+        """Stop claiming new work; tasks already handed out keep running."""
+        rust.begin_shutdown()
+
+    @staticmethod
+    def wait_idle(timeout_secs: float) -> bool:
+        """Wait for in-flight tasks to report; returns whether the worker went idle."""
+        return rust.wait_idle_sync(timeout_secs)
+
+    @staticmethod
+    def drain_worker(timeout_secs: float) -> Dict[str, Any]:
+        """
+        Stop claiming, wait for in-flight tasks, then release the rest.
+
+        Returns {"idle": bool, "released": [execution_id, ...]}.
+        """
+        return rust.drain_worker_sync(timeout_secs)
+
     @staticmethod
     def start_internal_worker() -> None:
         """
@@ -98,24 +206,161 @@ class RhythmCore:
         rust.start_internal_worker()
 
     @staticmethod
-    def complete_execution(execution_id: str, result: Any) -> None:
-        """Complete an execution"""
-        rust.complete_execution_sync(execution_id=execution_id, result=json.dumps(result))
+    def supported_payload_encodings() -> List[str]:
+        """Encodings accepted for pre-encoded bytes payloads, most preferred first"""
+        return rust.supported_payload_encodings()
+
+    @staticmethod
+    def negotiate_payload_encoding(offered: List[str]) -> str:
+        """Pick the payload encoding to use from those the caller can produce"""
+        return rust.negotiate_payload_encoding(offered)
+
+    @staticmethod
+    def complete_execution(execution_id: str, result: Any, encoding: Optional[str] = None) -> None:
+        """
+        Complete an execution.
+
+        Pass `encoding` ("json" or "msgpack") with a bytes result to skip
+        JSON serialization in Python for large outputs.
+        """
+        rust.complete_execution_sync(
+            execution_id=execution_id,
+            result=_payload(result, encoding),
+            encoding=encoding,
+        )
 
     @staticmethod
     def fail_execution(execution_id: str, error: Dict[str, Any], retry: bool) -> None:
         """Fail an execution"""
         rust.fail_execution_sync(execution_id=execution_id, error=json.dumps(error), retry=retry)
 
+    @staticmethod
+    def cancel_workflow(execution_id: str, reason: str, finish_running_tasks: bool = False) -> bool:
+        """Cancel a workflow and the executions it started"""
+        return rust.cancel_workflow_sync(
+            execution_id=execution_id,
+            reason=reason,
+            finish_running_tasks=finish_running_tasks,
+        )
+
+    @staticmethod
+    def pause_execution(execution_id: str) -> bool:
+        """Pause a workflow, holding its queued work until it is resumed"""
+        return rust.pause_execution_sync(execution_id=execution_id)
+
+    @staticmethod
+    def resume_execution(execution_id: str) -> bool:
+        """Resume a paused workflow"""
+        return rust.resume_execution_sync(execution_id=execution_id)
+
+    @staticmethod
+    def tag_execution(execution_id: str, tags: Dict[str, str]) -> None:
+        """Add tags to an execution, replacing any with the same keys"""
+        rust.tag_execution_sync(execution_id=execution_id, tags=tags)
+
+    @staticmethod
+    def get_execution_tags(execution_id: str) -> Dict[str, str]:
+        """Get an execution's tags"""
+        return rust.get_execution_tags_sync(execution_id=execution_id)
+
+    @staticmethod
+    def emit_partial_result(execution_id: str, chunk: Any) -> None:
+        """Record a partial result of a running task"""
+        rust.emit_partial_result_sync(execution_id=execution_id, chunk=json.dumps(chunk))
+
+    @staticmethod
+    def append_log(execution_id: str, level: str, message: str, data: Any = None) -> None:
+        """Write a message to an execution's logs"""
+        rust.append_log_sync(
+            execution_id=execution_id,
+            level=level,
+            message=message,
+            data=None if data is None else json.dumps(data),
+        )
+
+    @staticmethod
+    def heartbeat(execution_id: str, worker_id: Optional[str] = None) -> bool:
+        """Record that a task is still running and extend its claim"""
+        return rust.heartbeat_sync(execution_id=execution_id, worker_id=worker_id)
+
     @staticmethod
     def get_execution(execution_id: str) -> Optional[Execution]:
         """Get execution by ID"""
         result = rust.get_execution_sync(execution_id=execution_id)
-        if result:
-            data = json.loads(result)
-            return Execution.from_dict(data)
+        if result is not None:
+            return Execution.from_native(result)
         return None
 
+    @staticmethod
+    def get_execution_history(execution_id: str) -> List[Dict[str, Any]]:
+        """Get an execution's history, oldest event first"""
+        return rust.get_execution_history_sync(execution_id=execution_id)
+
+    @staticmethod
+    def get_execution_logs(execution_id: str) -> List[Dict[str, Any]]:
+        """Get an execution's logs, in the order they were written"""
+        return rust.get_execution_logs_sync(execution_id=execution_id)
+
+    @staticmethod
+    def get_changes(since_cursor: Optional[str] = None, limit: Optional[int] = None) -> ExecutionChanges:
+        """Get executions whose status changed since a cursor"""
+        executions, cursor, has_more = rust.get_changes_sync(since_cursor=since_cursor, limit=limit)
+        return ExecutionChanges(
+            executions=[Execution.from_native(e) for e in executions],
+            cursor=cursor,
+            has_more=has_more,
+        )
+
+    @staticmethod
+    def list_executions(
+        status: Optional[str] = None,
+        exec_type: Optional[str] = None,
+        queue: Optional[str] = None,
+        target_name: Optional[str] = None,
+        parent_workflow_id: Optional[str] = None,
+        namespace: Optional[str] = None,
+        tags: Optional[Dict[str, str]] = None,
+        created_after: Optional[datetime] = None,
+        created_before: Optional[datetime] = None,
+        order: Optional[str] = None,
+        cursor: Optional[str] = None,
+        limit: Optional[int] = None,
+    ) -> ExecutionPage:
+        """List executions matching filters, one page at a time"""
+        executions, next_cursor = rust.list_executions_sync(
+            status=status,
+            exec_type=exec_type,
+            queue=queue,
+            target_name=target_name,
+            parent_workflow_id=parent_workflow_id,
+            namespace=namespace,
+            tags=tags,
+            created_after=created_after,
+            created_before=created_before,
+            order=order,
+            cursor=cursor,
+            limit=limit,
+        )
+        return ExecutionPage(
+            executions=[Execution.from_native(e) for e in executions],
+            cursor=next_cursor,
+        )
+
+    @staticmethod
+    def get_namespace_usage(namespace: str) -> Dict[str, Any]:
+        """Get usage counted against a namespace's quotas"""
+        return rust.get_namespace_usage_sync(namespace=namespace)
+
+    @staticmethod
+    def get_slo_status() -> List[Dict[str, Any]]:
+        """Get compliance of the configured claim latency SLOs"""
+        return rust.get_slo_status_sync()
+
+    @staticmethod
+    def get_queue_stats(window_minutes: int = 5) -> List[Dict[str, Any]]:
+        """Get the load on each queue over the last window_minutes"""
+        return rust.get_queue_stats_sync(window_minutes=window_minutes)
+
     @staticmethod
     def get_workflow_tasks(workflow_id: str) -> List[Dict[str, Any]]:
         """Get workflow child tasks"""
@@ -123,21 +368,121 @@ class RhythmCore:
         return json.loads(result)
 
     @staticmethod
-    def start_workflow(workflow_name: str, inputs: dict) -> str:
+    def get_workflow_schema(name: str) -> Optional[Dict[str, Any]]:
+        """Get the inputs schema a workflow declares, or None"""
+        return rust.get_workflow_schema_sync(name=name)
+
+    @staticmethod
+    def analyze_workflow(name: str) -> Dict[str, Any]:
+        """Get a workflow's task graph"""
+        return rust.analyze_workflow_sync(name=name)
+
+    @staticmethod
+    def run_workflow_test(
+        source: str,
+        stubs: List[Dict[str, Any]],
+        signals: List[Any],
+        inputs: Dict[str, Any],
+    ) -> Dict[str, Any]:
+        """Run a workflow in memory against stubbed tasks and signals"""
+        return rust.run_workflow_test(
+            source=source,
+            stubs_json=json.dumps(stubs),
+            signals_json=json.dumps(signals),
+            inputs_json=json.dumps(inputs),
+        )
+
+    @staticmethod
+    def start_workflow(
+        workflow_name: str,
+        inputs: Any,
+        encoding: Optional[str] = None,
+        namespace: Optional[str] = None,
+        concurrency_key: Optional[str] = None,
+        concurrency_policy: Optional[str] = None,
+    ) -> str:
         """
         Start a workflow execution.
 
         Args:
             workflow_name: Name of the workflow to execute
-            inputs: Input parameters for the workflow
+            inputs: Input parameters for the workflow (pre-encoded bytes if encoding is set)
+            encoding: Encoding of `inputs` when passed as bytes ("json" or "msgpack")
+            namespace: Namespace for quotas (defaults to "default")
+            concurrency_key: Key at most one unfinished workflow holds at a time
+            concurrency_policy: "reject" (default), "queue" or "replace_existing"
 
         Returns:
             Workflow execution ID
+
+        Raises:
+            QuotaExceededError: If the namespace is over one of its quotas
+            QueueFullError: If the queue stays over its max depth
+            ConflictError: If the concurrency key is taken and the policy is "reject"
         """
-        inputs_json = json.dumps(inputs)
         return rust.start_workflow_sync(
             workflow_name=workflow_name,
-            inputs_json=inputs_json,
+            inputs_json=_payload(inputs, encoding),
+            encoding=encoding,
+            namespace=namespace,
+            concurrency_key=concurrency_key,
+            concurrency_policy=concurrency_policy,
+        )
+
+    @staticmethod
+    def run_workflow_sync(
+        workflow_name: str,
+        inputs: Any,
+        timeout: float,
+        encoding: Optional[str] = None,
+        namespace: Optional[str] = None,
+    ) -> Any:
+        """
+        Run a workflow in the express lane and wait for its output.
+
+        Args:
+            workflow_name: Name of the workflow to execute
+            inputs: Input parameters for the workflow (pre-encoded bytes if encoding is set)
+            timeout: Seconds to wait for the workflow to finish
+            encoding: Encoding of `inputs` when passed as bytes ("json" or "msgpack")
+            namespace: Namespace for quotas (defaults to "default")
+
+        Returns:
+            The workflow's output
+
+        Raises:
+            WorkflowTimeoutError: If it didn't finish in time (it keeps running)
+            WorkflowFailedError: If the workflow failed, expired or was cancelled
+            QuotaExceededError: If the namespace is over one of its quotas
+            QueueFullError: If the queue stays over its max depth
+        """
+        return rust.run_workflow_sync(
+            workflow_name=workflow_name,
+            inputs_json=_payload(inputs, encoding),
+            timeout_secs=timeout,
+            encoding=encoding,
+            namespace=namespace,
+        )
+
+    @staticmethod
+    def get_workflow_result(execution_id: str, timeout: float) -> Any:
+        """
+        Wait for a workflow to finish and return its output.
+
+        Args:
+            execution_id: The workflow's execution ID
+            timeout: Seconds to wait for the workflow to finish
+
+        Returns:
+            The workflow's output
+
+        Raises:
+            WorkflowTimeoutError: If it didn't finish in time (it keeps running)
+            WorkflowFailedError: If the workflow failed, expired or was cancelled
+            NotFoundError: If there is no execution with this ID
+        """
+        return rust.get_workflow_result_sync(
+            execution_id=execution_id, timeout_secs=timeout
         )
 
     @staticmethod
@@ -146,6 +491,7 @@ class RhythmCore:
         inputs: dict,
         run_at: str,
         queue: str = "default",
+        namespace: Optional[str] = None,
     ) -> str:
         """
         Schedule a workflow to start at a future time.
@@ -158,6 +504,7 @@ class RhythmCore:
             inputs: Input parameters for the workflow
             run_at: ISO 8601 datetime string (e.g., "2024-01-15T10:30:00")
             queue: Queue name (defaults to "default")
+            namespace: Namespace for quotas (defaults to "default")
 
         Returns:
             Workflow execution ID
@@ -168,6 +515,7 @@ class RhythmCore:
             inputs_json=json.dumps(inputs),
             run_at_iso=run_at,
             queue=queue,
+            namespace=namespace,
         )
 
     @staticmethod
@@ -176,6 +524,7 @@ class RhythmCore:
         inputs: dict,
         run_at: str,
         queue: str = "default",
+        namespace: Optional[str] = None,
     ) -> str:
         """
         Schedule a task to start at a future time.
@@ -188,6 +537,7 @@ class RhythmCore:
             inputs: Input parameters for the task
             run_at: ISO 8601 datetime string (e.g., "2024-01-15T10:30:00")
             queue: Queue name (defaults to "default")
+            namespace: Namespace for quotas (defaults to "default")
 
         Returns:
             Task execution ID
@@ -198,6 +548,7 @@ class RhythmCore:
             inputs_json=json.dumps(inputs),
             run_at_iso=run_at,
             queue=queue,
+            namespace=namespace,
         )
 
     @staticmethod
@@ -225,3 +576,111 @@ class RhythmCore:
             payload_json=json.dumps(payload),
             queue=queue,
         )
+
+    @staticmethod
+    def register_webhook(
+        url: str,
+        secret: str,
+        events: List[str],
+        queue: Optional[str] = None,
+        target_name: Optional[str] = None,
+    ) -> Dict[str, Any]:
+        """Register a webhook for executions finishing"""
+        return rust.register_webhook_sync(
+            url=url,
+            secret=secret,
+            events=events,
+            queue=queue,
+            target_name=target_name,
+        )
+
+    @staticmethod
+    def list_webhooks() -> List[Dict[str, Any]]:
+        """List registered webhooks"""
+        return rust.list_webhooks_sync()
+
+    @staticmethod
+    def remove_webhook(webhook_id: str) -> None:
+        """Remove a webhook"""
+        rust.remove_webhook_sync(webhook_id=webhook_id)
+
+    @staticmethod
+    def complete_external_task(token: str, result: Any) -> None:
+        """Complete an external task by its token"""
+        rust.complete_external_task_sync(token=token, result=json.dumps(result))
+
+    @staticmethod
+    def fail_external_task(token: str, error: Dict[str, Any]) -> None:
+        """Fail an external task by its token"""
+        rust.fail_external_task_sync(token=token, error=json.dumps(error))
+
+    # Awaitable versions of the calls above, resolved from the shared Tokio
+    # runtime without blocking the event loop
+
+    @staticmethod
+    async def claim_executions_async(
+        queues: List[str], max_count: int, worker_id: Optional[str] = None
+    ) -> List[Dict[str, Any]]:
+        """Claim up to max_count tasks from queues in one round trip"""
+        result = await rust.claim_executions_async(
+            queues=queues, max_count=max_count, worker_id=worker_id
+        )
+        return json.loads(result)
+
+    @staticmethod
+    async def complete_execution_async(
+        execution_id: str, result: Any, encoding: Optional[str] = None
+    ) -> None:
+        """Complete an execution"""
+        await rust.complete_execution_async(
+            execution_id=execution_id,
+            result=_payload(result, encoding),
+            encoding=encoding,
+        )
+
+    @staticmethod
+    async def fail_execution_async(execution_id: str, error: Dict[str, Any], retry: bool) -> None:
+        """Fail an execution"""
+        await rust.fail_execution_async(
+            execution_id=execution_id, error=json.dumps(error), retry=retry
+        )
+
+    @staticmethod
+    async def heartbeat_async(execution_id: str, worker_id: Optional[str] = None) -> bool:
+        """Record that a task is still running and extend its claim"""
+        return await rust.heartbeat_async(execution_id=execution_id, worker_id=worker_id)
+
+    @staticmethod
+    async def get_execution_async(execution_id: str) -> Optional[Execution]:
+        """Get execution by ID"""
+        result = await rust.get_execution_async(execution_id=execution_id)
+        if result is not None:
+            return Execution.from_native(result)
+        return None
+
+    @staticmethod
+    async def start_workflow_async(
+        workflow_name: str,
+        inputs: Any,
+        encoding: Optional[str] = None,
+        namespace: Optional[str] = None,
+        concurrency_key: Optional[str] = None,
+        concurrency_policy: Optional[str] = None,
+    ) -> str:
+        """Start a workflow execution, returning its ID"""
+        return await rust.start_workflow_async(
+            workflow_name=workflow_name,
+            inputs_json=_payload(inputs, encoding),
+            encoding=encoding,
+            namespace=namespace,
+            concurrency_key=concurrency_key,
+            concurrency_policy=concurrency_policy,
+        )
+
+    @staticmethod
+    async def get_workflow_result_async(execution_id: str, timeout: float) -> Any:
+        """Wait for a workflow to finish and return its output"""
+        result = await rust.get_workflow_result_async(
+            execution_id=execution_id, timeout_secs=timeout
+        )
+        return json.loads(result)
@@ -1,12 +1,19 @@
 """rhythm.task - Task decorator"""
 
-from typing import Callable, Optional
+from typing import Any, Callable, Optional
 
 from rhythm.client import queue_execution
-from rhythm.registry import register_function
+from rhythm.registry import register_function, register_task_config
 
 
-def task(fn: Optional[Callable] = None, *, name: Optional[str] = None, queue: str = "default"):
+def task(
+    fn: Optional[Callable] = None,
+    *,
+    name: Optional[str] = None,
+    queue: str = "default",
+    retries: Optional[int] = None,
+    output_schema: Optional[dict[str, Any]] = None,
+):
     """Mark a function as a Rhythm task that can be queued for async execution.
 
     Decorated functions can be called directly (synchronous) or queued for
@@ -15,6 +22,12 @@ def task(fn: Optional[Callable] = None, *, name: Optional[str] = None, queue: st
     Args:
         name: Custom task name (defaults to function name). Useful for kebab-case names.
         queue: The queue name to execute in (defaults to "default")
+        retries: Times a failed run is retried before the failure is final.
+            Without it, failures are only retried per `[[task_configs]]`.
+            Declare tasks before `rhythm.init()` so core sees this.
+        output_schema: Fields the task's result must have, as `name: type` entries
+            like a workflow's `inputs:` front matter. A result that doesn't fit fails
+            the task with an INVALID_OUTPUT error.
 
     Returns:
         The decorated function with an added `.queue()` method
@@ -35,6 +48,16 @@ def task(fn: Optional[Callable] = None, *, name: Optional[str] = None, queue: st
         def send_notification(user_id: str, message: str):
             ...
 
+        # On its own queue, retried up to 5 times
+        @task(queue="payments", retries=5)
+        def charge_card(order_id: str):
+            ...
+
+        # Returning a string charge_id and a number amount
+        @task(output_schema={"charge_id": "string", "amount": "number"})
+        def capture(order_id: str):
+            ...
+
     Meta:
         section: Tasks
         kind: decorator
@@ -45,6 +68,7 @@ def task(fn: Optional[Callable] = None, *, name: Optional[str] = None, queue: st
 
         # Register the function in the registry
         register_function(task_name, func)
+        register_task_config(task_name, retries=retries, output_schema=output_schema)
 
         # Add a queue method to the function
         def queue_fn(**inputs) -> str:
@@ -6,22 +6,32 @@ from pathlib import Path
 from typing import List, Optional
 
 from rhythm.core import RhythmCore
+from rhythm.registry import get_task_configs
 
 
 def init(
     database_url: str,
     workflow_paths: Optional[List[str]] = None,
     auto_migrate: bool = True,
+    read_only: bool = False,
 ) -> None:
     """Initialize Rhythm with workflow definitions.
 
     This function initializes the Rust core with a database connection,
     scans for .flow workflow files, and prepares the system for execution.
+    Settings from `@task` decorators imported so far are passed to core.
 
     Args:
         database_url: PostgreSQL connection string
         workflow_paths: List of paths to directories containing .flow files
         auto_migrate: Whether to automatically run migrations if needed
+        read_only: Open read-only, e.g. against a replica during an incident.
+            Queries work; anything that changes state raises ReadOnlyError.
+
+    With `[auth] required = true` in the config, calls are made with the API
+    key in `RHYTHM_API_KEY` (or `[auth] api_key`): they raise
+    UnauthenticatedError without an accepted key, and PermissionDeniedError
+    when its scopes don't cover them.
 
     Meta:
         section: Initialization
@@ -64,4 +74,6 @@ def init(
         database_url=database_url,
         auto_migrate=auto_migrate,
         workflows=workflows if workflows else None,
+        read_only=read_only,
+        tasks=get_task_configs(),
     )
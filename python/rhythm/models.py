@@ -13,6 +13,7 @@ class ExecutionType(str, Enum):
 
     TASK = "task"
     WORKFLOW = "workflow"
+    EXTERNAL = "external"
 
 
 class ExecutionStatus(str, Enum):
@@ -23,6 +24,8 @@ class ExecutionStatus(str, Enum):
     SUSPENDED = "suspended"
     COMPLETED = "completed"
     FAILED = "failed"
+    EXPIRED = "expired"
+    CANCELLED = "cancelled"
 
 
 class Execution(BaseModel):
@@ -32,6 +35,7 @@ class Execution(BaseModel):
     type: ExecutionType
     target_name: str
     queue: str
+    namespace: str = "default"
     status: ExecutionStatus
 
     inputs: dict[str, Any] = Field(default_factory=dict)
@@ -63,12 +67,45 @@ class Execution(BaseModel):
             data["type"] = data.pop("exec_type")
         return cls(**data)
 
+    @classmethod
+    def from_native(cls, native) -> "Execution":
+        """Create from the typed Execution object returned by the Rust extension"""
+        return cls(
+            id=native.id,
+            type=native.type,
+            target_name=native.target_name,
+            queue=native.queue,
+            namespace=native.namespace,
+            status=native.status,
+            inputs=native.inputs,
+            output=native.output,
+            attempt=native.attempt,
+            parent_workflow_id=native.parent_workflow_id,
+            created_at=native.created_at,
+            completed_at=native.completed_at,
+        )
+
+
+class ExecutionChanges(BaseModel):
+    """A page of executions whose status changed since a cursor"""
+
+    executions: list[Execution]
+    cursor: str
+    has_more: bool = False
+
+
+class ExecutionPage(BaseModel):
+    """A page of executions matching a search"""
+
+    executions: list[Execution]
+    cursor: Optional[str] = None
+
 
 class DelegatedAction(BaseModel):
     """Action delegated from Rust cooperative worker loop to host
 
     Action types:
-    - execute_task: Execute a task (has execution_id, target_name, inputs)
+    - execute_task: Execute a task (has execution_id, target_name, inputs, traceparent)
     - continue: Continue immediately, check for more work
     - wait: Wait for duration_ms before checking for more work
     - shutdown: Shutdown requested, worker should exit gracefully
@@ -80,6 +117,7 @@ class DelegatedAction(BaseModel):
     execution_id: Optional[str] = None
     target_name: Optional[str] = None
     inputs: Optional[dict[str, Any]] = None
+    traceparent: Optional[str] = None
 
     # Fields for wait action
     duration_ms: Optional[int] = None
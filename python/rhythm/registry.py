@@ -1,16 +1,55 @@
 """Function registry for looking up decorated functions"""
 
-from typing import Callable, Dict
+from typing import Any, Callable, Dict, List, Optional
 
 # Global registry of target_name -> function
 _FUNCTION_REGISTRY: Dict[str, Callable] = {}
 
+# Settings declared by decorators, target_name -> task config for core
+_TASK_CONFIGS: Dict[str, Dict[str, Any]] = {}
+
 
 def register_function(name: str, fn: Callable):
     """Register a function in the global registry"""
     _FUNCTION_REGISTRY[name] = fn
 
 
+def register_task_config(
+    name: str,
+    retries: Optional[int] = None,
+    output_schema: Optional[Dict[str, Any]] = None,
+):
+    """Record the settings a task was declared with"""
+    config: Dict[str, Any] = {"target_name": name}
+    if retries is not None:
+        config["max_retries"] = retries
+    if output_schema is not None:
+        config["output_schema"] = output_schema
+    _TASK_CONFIGS[name] = config
+
+
+def get_task_configs() -> List[Dict[str, Any]]:
+    """Task settings to hand to core at initialization"""
+    return list(_TASK_CONFIGS.values())
+
+
+def get_task_manifest() -> Dict[str, Any]:
+    """Declared tasks as an editor task manifest, with docstring summaries"""
+    tasks = []
+    for name in sorted(_TASK_CONFIGS):
+        entry: Dict[str, Any] = {"name": name}
+        doc = (_FUNCTION_REGISTRY[name].__doc__ or "").strip()
+        if doc:
+            entry["description"] = doc.splitlines()[0]
+        tasks.append(entry)
+    return {"tasks": tasks}
+
+
+def get_task_retries(name: str) -> int:
+    """Retries a task was declared with, 0 if none"""
+    return _TASK_CONFIGS.get(name, {}).get("max_retries", 0)
+
+
 def get_function(name: str, required: bool = True) -> Callable:
     """Get a function from the registry"""
     if name not in _FUNCTION_REGISTRY:
@@ -23,3 +62,4 @@ def get_function(name: str, required: bool = True) -> Callable:
 def clear_registry():
     """Clear the function registry (useful for testing)"""
     _FUNCTION_REGISTRY.clear()
+    _TASK_CONFIGS.clear()
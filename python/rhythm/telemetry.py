@@ -0,0 +1,36 @@
+"""Trace context propagation through OpenTelemetry, when it is installed"""
+
+import contextlib
+from typing import Iterator, Optional
+
+try:
+    from opentelemetry import trace
+    from opentelemetry.propagate import extract, inject
+except ImportError:  # pragma: no cover - optional dependency
+    trace = None
+
+
+def current_traceparent() -> Optional[str]:
+    """W3C traceparent of the current span, or None without one"""
+    if trace is None:
+        return None
+    carrier: dict[str, str] = {}
+    inject(carrier)
+    return carrier.get("traceparent")
+
+
+@contextlib.contextmanager
+def task_span(target_name: str, execution_id: str, traceparent: Optional[str]) -> Iterator[None]:
+    """Run a task handler in a span continuing the trace core handed over"""
+    if trace is None:
+        yield
+        return
+    context = extract({"traceparent": traceparent}) if traceparent else None
+    tracer = trace.get_tracer("rhythm")
+    with tracer.start_as_current_span(
+        f"rhythm.task {target_name}",
+        context=context,
+        kind=trace.SpanKind.CONSUMER,
+        attributes={"rhythm.execution_id": execution_id, "rhythm.target_name": target_name},
+    ):
+        yield
@@ -0,0 +1,144 @@
+"""Unit-testing workflows without a database
+
+`WorkflowTestHarness` runs a workflow's Flow source in memory: tasks and
+child workflows answer with the stubs registered for their name, timers fire
+at once, and signals take the payloads queued for them.
+
+    harness = WorkflowTestHarness.from_file("workflows/checkout.flow")
+    harness.on_task("charge").returns({"id": "ch_1"})
+    harness.on_task("ship").fails({"code": "OUT_OF_STOCK", "message": "gone"})
+
+    run = harness.run({"order_id": "o1"})
+    run.assert_output({"charged": "ch_1", "shipped": False})
+    run.assert_calls(["charge", "ship"])
+
+Several stubs for one name answer calls in order, the last repeating. A
+stubbed failure is what the workflow's `await` returns, as on a worker.
+"""
+
+from dataclasses import dataclass, field
+from pathlib import Path
+from typing import Any, Optional, Union
+
+from rhythm.core import RhythmCore
+
+
+class TaskStub:
+    """Stub for the next call to a task, finished by `returns` or `fails`"""
+
+    def __init__(self, harness: "WorkflowTestHarness", name: str):
+        self._harness = harness
+        self._name = name
+
+    def returns(self, result: Any) -> "WorkflowTestHarness":
+        """Answer the call with `result`"""
+        self._harness._stubs.append({"name": self._name, "result": result})
+        return self._harness
+
+    def fails(self, error: dict[str, Any]) -> "WorkflowTestHarness":
+        """Fail the call with `error`, e.g. {"code": ..., "message": ...}"""
+        self._harness._stubs.append({"name": self._name, "error": error})
+        return self._harness
+
+
+@dataclass
+class WorkflowTestRun:
+    """A finished run of a `WorkflowTestHarness`
+
+    `status` is "returned", "threw", "stuck" (awaiting something with no
+    stub, see `reason`) or "runaway" (did not finish within the step limit).
+    Lines are 1-based.
+    """
+
+    status: str
+    output: Any = None
+    error: Optional[dict[str, Any]] = None
+    reason: Optional[str] = None
+    line: Optional[int] = None
+    calls: list[dict[str, Any]] = field(default_factory=list)
+    suspensions: list[dict[str, Any]] = field(default_factory=list)
+
+    @property
+    def call_names(self) -> list[str]:
+        """Names of the tasks and child workflows started, in order"""
+        return [call["name"] for call in self.calls]
+
+    @property
+    def suspend_lines(self) -> list[int]:
+        """Lines of the statements the run suspended at, in order"""
+        return [s["line"] for s in self.suspensions if s["line"] is not None]
+
+    def assert_output(self, expected: Any) -> None:
+        """Fail unless the workflow returned `expected`"""
+        if self.status != "returned":
+            raise AssertionError(f"expected output {expected!r}, but {self._describe()}")
+        if self.output != expected:
+            raise AssertionError(f"expected output {expected!r}, got {self.output!r}")
+
+    def assert_error_code(self, code: str) -> None:
+        """Fail unless the workflow threw an error with `code`"""
+        if self.status != "threw" or (self.error or {}).get("code") != code:
+            raise AssertionError(f"expected error {code}, but {self._describe()}")
+
+    def assert_calls(self, expected: list[str]) -> None:
+        """Fail unless exactly these tasks were started, in this order"""
+        if self.call_names != expected:
+            raise AssertionError(f"expected calls {expected}, got {self.call_names}")
+
+    def assert_suspended_at(self, lines: list[int]) -> None:
+        """Fail unless the run suspended at exactly these lines, in order"""
+        if self.suspend_lines != lines:
+            raise AssertionError(
+                f"expected suspend points at lines {lines}, got {self.suspend_lines}"
+            )
+
+    def _describe(self) -> str:
+        at = f" at line {self.line}" if self.line is not None else ""
+        if self.status == "returned":
+            return f"returned {self.output!r}{at}"
+        if self.status == "threw":
+            return f"threw {self.error!r}{at}"
+        if self.status == "stuck":
+            return f"never finished{at}: {self.reason}"
+        return f"did not finish within the step limit{at}"
+
+
+class WorkflowTestHarness:
+    """Runs a workflow in memory against stubbed tasks and signals
+
+    Args:
+        source: The workflow's Flow source
+
+    Meta:
+        section: Testing
+    """
+
+    def __init__(self, source: str):
+        self._source = source
+        self._stubs: list[dict[str, Any]] = []
+        self._signals: list[tuple[str, Any]] = []
+
+    @classmethod
+    def from_file(cls, path: Union[str, Path]) -> "WorkflowTestHarness":
+        """Load a .flow file"""
+        return cls(Path(path).read_text())
+
+    def on_task(self, name: str) -> TaskStub:
+        """Stub calls to the task or child workflow `name`"""
+        return TaskStub(self, name)
+
+    def on_signal(self, name: str, payload: Any) -> "WorkflowTestHarness":
+        """Queue a payload for the signal `name`"""
+        self._signals.append((name, payload))
+        return self
+
+    def run(self, inputs: Optional[dict[str, Any]] = None) -> WorkflowTestRun:
+        """Run the workflow to its end with `inputs`
+
+        Raises:
+            ValidationError: If the source doesn't parse or breaks a validation rule
+        """
+        result = RhythmCore.run_workflow_test(
+            self._source, self._stubs, self._signals, inputs or {}
+        )
+        return WorkflowTestRun(**result)
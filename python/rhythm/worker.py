@@ -1,16 +1,32 @@
 """Worker implementation for executing tasks and workflows"""
 
 import asyncio
+import contextvars
+import json
 import logging
 import signal
-import time
 import traceback
+from typing import List, Optional
 
 from rhythm.core import RhythmCore
-from rhythm.registry import get_function
+from rhythm.registry import get_function, get_task_configs, get_task_retries
+from rhythm.telemetry import task_span
 
 logger = logging.getLogger(__name__)
 
+_current_task_id: contextvars.ContextVar[Optional[str]] = contextvars.ContextVar(
+    "rhythm_current_task_id", default=None
+)
+
+
+def current_task_id() -> Optional[str]:
+    """ID of the task running in this context, or None outside a task.
+
+    Meta:
+        section: Worker
+    """
+    return _current_task_id.get()
+
 
 def _handle_shutdown_signal(signum, frame):
     """Signal handler for graceful shutdown"""
@@ -22,94 +38,133 @@ def _handle_shutdown_signal(signum, frame):
         logger.error(f"Error requesting shutdown: {e}")
 
 
-def run():
-    """Run a worker loop that polls for and executes tasks.
+def _dispatch(
+    execution_id: str, target_name: str, inputs_json: str, traceparent: Optional[str] = None
+) -> str:
+    """Run one task for core and report how it went as JSON"""
+    logger.info(f"Running task: {target_name}")
+    token = _current_task_id.set(execution_id)
+    try:
+        fn = get_function(target_name)
 
-    The worker runs synchronously in a single thread, polling the database
-    for pending tasks and executing them one at a time.
+        # Only support sync functions
+        if asyncio.iscoroutinefunction(fn):
+            raise TypeError(f"Async functions not supported: {target_name}")
+
+        with task_span(target_name, execution_id, traceparent):
+            result = fn(**json.loads(inputs_json))
+        return json.dumps({"result": result})
+    except Exception as e:
+        logger.error(f"Error running {target_name}: {e}\n{traceback.format_exc()}")
+
+        error_data = {
+            "message": str(e),
+            "type": type(e).__name__,
+            "traceback": traceback.format_exc(),
+        }
+        # Lets [[task_configs]] retry_on / give_up_on classify the failure
+        code = getattr(e, "code", None)
+        if isinstance(code, str):
+            error_data["code"] = code
+
+        # Tasks declared with retries are retried until they run out
+        retry = get_task_retries(target_name) > 0
+        return json.dumps({"error": error_data, "retry": retry})
+    finally:
+        _current_task_id.reset(token)
+
+
+class Worker:
+    """A worker whose claim loop, heartbeats and shutdown are run by core.
+
+    Python only runs the tasks core hands it. Long tasks keep their claim
+    while they run, so no other worker picks them up.
+
+    Args:
+        queues: Queues to take work from, polled in order (defaults to "default")
+
+    Example:
+        Worker(queues=["emails", "default"]).run()
+
+        # Shuts down any worker loops still running on exit
+        with Worker(queues=["emails"]) as worker:
+            worker.run()
 
     Meta:
         section: Worker
     """
-    logger.info("Worker starting...")
 
-    # Register signal handlers for graceful shutdown
-    signal.signal(signal.SIGINT, _handle_shutdown_signal)
-    signal.signal(signal.SIGTERM, _handle_shutdown_signal)
-    logger.debug("Signal handlers registered for SIGINT and SIGTERM")
+    def __init__(self, queues: Optional[List[str]] = None):
+        self.queues = list(queues) if queues else ["default"]
 
-    # Ensure core is initialized
-    try:
-        RhythmCore.initialize(auto_migrate=False)
-    except Exception as e:
-        logger.warning(f"Failed to initialize Rust adapter: {e}")
+    def __enter__(self) -> "Worker":
+        return self
+
+    def __exit__(self, exc_type, exc, tb) -> None:
+        self.shutdown()
 
-    # Start the internal worker (scheduler queue processor)
-    RhythmCore.start_internal_worker()
+    def run(self) -> None:
+        """Run until shutdown is requested (SIGINT, SIGTERM or `shutdown()`)"""
+        logger.info(f"Worker starting on queues: {', '.join(self.queues)}")
 
-    # Simple infinite loop
-    while True:
+        # Register signal handlers for graceful shutdown
+        signal.signal(signal.SIGINT, _handle_shutdown_signal)
+        signal.signal(signal.SIGTERM, _handle_shutdown_signal)
+        logger.debug("Signal handlers registered for SIGINT and SIGTERM")
+
+        # Ensure core is initialized
         try:
-            # Call Rust cooperative worker loop - returns quickly with an action
-            logger.debug("Calling cooperative worker loop...")
-            action = RhythmCore.run_cooperative_worker_loop()
-
-            # Handle different action types
-            if action.type == "execute_task":
-                logger.info(
-                    f"Received task: {action.target_name} (execution {action.execution_id})"
-                )
-
-                # Execute the task synchronously
-                fn = get_function(action.target_name)
-
-                # Only support sync functions
-                if asyncio.iscoroutinefunction(fn):
-                    raise TypeError(f"Async functions not supported: {action.target_name}")
-
-                logger.debug(f"Executing sync function {action.target_name}")
-                try:
-                    result = fn(**action.inputs)
-                except Exception as e:
-                    logger.error(
-                        f"Error executing {action.execution_id}: {e}\n{traceback.format_exc()}"
-                    )
-
-                    error_data = {
-                        "message": str(e),
-                        "type": type(e).__name__,
-                        "traceback": traceback.format_exc(),
-                    }
-
-                    # Report the failure
-                    RhythmCore.fail_execution(action.execution_id, error_data, retry=False)
-                    continue
-
-                # Mark as completed
-                RhythmCore.complete_execution(action.execution_id, result)
-
-            elif action.type == "continue":
-                # Workflow was executed internally, check for more work immediately
-                logger.debug("Continue action - checking for more work")
-                continue
-
-            elif action.type == "wait":
-                # No work available, wait before checking again
-                wait_seconds = action.duration_ms / 1000.0
-                logger.debug(f"Wait action - sleeping for {wait_seconds}s")
-                time.sleep(wait_seconds)
-
-            elif action.type == "shutdown":
-                # Shutdown requested
-                logger.info("Shutdown requested, exiting gracefully...")
-                break
-
-            else:
-                logger.warning(f"Unknown action type: {action.type}")
+            RhythmCore.initialize(auto_migrate=False, tasks=get_task_configs())
+        except Exception as e:
+            logger.warning(f"Failed to initialize Rust adapter: {e}")
+
+        # Start the internal worker (scheduler queue processor)
+        RhythmCore.start_internal_worker()
 
+        try:
+            RhythmCore.run_worker(self.queues, _dispatch)
         except KeyboardInterrupt:
             logger.info("Received interrupt signal, stopping...")
-            break
-        except Exception as e:
-            logger.error(f"Error in worker loop: {e}")
-            # Continue on error
+            return
+        logger.info("Shutdown requested, exiting gracefully...")
+
+    def shutdown(self) -> None:
+        """Ask worker loops to stop after their current task"""
+        RhythmCore.request_shutdown()
+
+    def begin_shutdown(self) -> None:
+        """Stop claiming new work; tasks already handed out keep running"""
+        RhythmCore.begin_shutdown()
+
+    def wait_idle(self, timeout: float) -> bool:
+        """Wait up to `timeout` seconds for tasks handed out to report.
+
+        Returns:
+            Whether the worker went idle in time
+        """
+        return RhythmCore.wait_idle(timeout)
+
+    def drain(self, timeout: float) -> dict:
+        """Stop claiming, wait for in-flight tasks, then release the rest.
+
+        Tasks still running after `timeout` seconds go back on their queue
+        for another worker, e.g. before a container's SIGTERM grace period
+        runs out.
+
+        Returns:
+            {"idle": bool, "released": [execution_id, ...]}
+        """
+        return RhythmCore.drain_worker(timeout)
+
+
+def run():
+    """Run a worker loop that polls for and executes tasks.
+
+    The worker runs synchronously in a single thread, polling the database
+    for pending tasks on the "default" queue and executing them one at a
+    time. Same as `Worker().run()`.
+
+    Meta:
+        section: Worker
+    """
+    Worker().run()
@@ -21,6 +21,7 @@ sys.path.insert(0, str(Path(__file__).parent.parent))
 
 import rhythm
 import rhythm.client as client_module
+import rhythm.aio as aio_module
 import rhythm.worker as worker_module
 import rhythm.decorators as decorators_module
 from importlib import import_module
@@ -293,6 +294,7 @@ def main():
         (init_module, "rhythm.init"),
         (decorators_module, "rhythm.decorators"),
         (client_module, "rhythm.client"),
+        (aio_module, "rhythm.aio"),
         (worker_module, "rhythm.worker"),
     ]
 